@@ -186,12 +186,14 @@ class Debug {
       .on('change', (ev) => {
         world.updateRenderRadius(ev.value);
       });
-    worldFolder.addInput(world.options, 'requestRadius', {
-      min: 1,
-      max: 20,
-      step: 1,
-      label: 'request radius',
-    });
+    worldFolder
+      .addInput(world.options, 'requestRadius', {
+        min: 1,
+        max: 20,
+        step: 1,
+        label: 'request radius',
+      })
+      .on('change', () => world.requestRenderRadius());
     worldFolder
       .addInput(worldDebugConfigs, 'time', { min: 0, max: 2400, step: 10, label: 'time value' })
       .on('change', (ev) => world.setTime(ev.value));
@@ -226,6 +228,7 @@ class Debug {
     this.registerDisplay('Block', player, 'lookBlockStr');
     this.registerDisplay('Chunk', world, 'camChunkPosStr');
     this.registerDisplay('Chunks loaded', world, 'chunksLoaded');
+    this.registerDisplay('Chunks pending', world, 'chunksPending');
     this.displayNewline();
     this.displayTitle('/help for a tutorial');
 
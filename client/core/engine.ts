@@ -99,8 +99,12 @@ const defaultConfig: ConfigType = {
   world: {
     renderRadius: 6,
     requestRadius: 8,
+    // chunks out to which far terrain is drawn as heightfields
+    horizonRadius: 24,
     // maximum amount of chunks to process per frame tick
     maxChunkProcessPerFrame: 4,
+    // maximum amount of chunks to request per frame tick
+    maxChunkRequestPerFrame: 4,
     maxBlockPerFrame: 500,
     chunkAnimation: true,
     animationTime: 500,
@@ -145,6 +149,7 @@ const defaultConfig: ConfigType = {
   network: {
     reconnectTimeout: 5000,
     maxServerUpdates: 100,
+    knockbackTolerance: 2,
   },
   particles: {
     count: 10,
@@ -295,6 +300,7 @@ class Engine extends EventEmitter {
       dimension,
       maxHeight,
       subChunks,
+      maxHorizonRadius,
       name,
       packs,
       blocks,
@@ -317,6 +323,7 @@ class Engine extends EventEmitter {
     world.dimension = dimension;
     world.maxHeight = maxHeight;
     world.subChunks = subChunks;
+    world.maxHorizonRadius = maxHorizonRadius;
   };
 
   boot = () => {
@@ -2,7 +2,7 @@ import { Object3D, Vector3, LoadingManager, Mesh, MeshStandardMaterial, MeshBasi
 import { GLTFLoader } from 'three/examples/jsm/loaders/GLTFLoader';
 
 import { AABB, Brain, PhysicalType, BodyOptionsType, Coords3, EntityType, createMaterial_ } from '../libs';
-import { Entity } from '../libs/entity';
+import { Entity, EntitySnapshot } from '../libs/entity';
 import { Helper } from '../utils';
 
 import { Engine } from './engine';
@@ -36,9 +36,12 @@ class Entities {
   public physicals: Map<string, PhysicalType> = new Map();
   public entities: Map<string, Entity> = new Map();
 
-  private updates: [string, string, Coords3, Coords3, Coords3][] = [];
+  private updates: [string, string, Coords3, Coords3, Coords3, EntitySnapshot][] = [];
   private prototypes: Map<string, Entity> = new Map();
 
+  // smallest gap seen between the server's clock and ours, latency included
+  private serverOffset = Infinity;
+
   constructor(public engine: Engine, public options: EntitiesOptionsType) {
     engine.on('ready', () => {
       const { prototypes } = this.options;
@@ -130,14 +133,34 @@ class Entities {
     return this.prototypes.get(type.toLowerCase()).clone();
   };
 
-  handleServerUpdate = (id: string, type: string, position: Coords3, heading?: Coords3, lookAt?: Coords3) => {
-    this.updates.push([id, type, position, heading, lookAt]);
+  handleServerUpdate = (
+    id: string,
+    type: string,
+    position: Coords3,
+    heading?: Coords3,
+    lookAt?: Coords3,
+    snapshot?: EntitySnapshot,
+  ) => {
+    if (snapshot) {
+      // server time turned into ours, so late updates get extrapolated further
+      this.serverOffset = Math.min(this.serverOffset, Date.now() - snapshot.timestamp);
+      snapshot = { ...snapshot, timestamp: snapshot.timestamp + this.serverOffset };
+    }
+
+    this.updates.push([id, type, position, heading, lookAt, snapshot]);
     if (this.updates.length >= this.engine.config.network.maxServerUpdates) {
       this.updates.shift();
     }
   };
 
-  updateEntity = (id: string, type: string, position: Coords3, heading?: Coords3, lookAt?: Coords3) => {
+  updateEntity = (
+    id: string,
+    type: string,
+    position: Coords3,
+    heading?: Coords3,
+    lookAt?: Coords3,
+    snapshot?: EntitySnapshot,
+  ) => {
     if (!this.engine.assetsLoaded) return;
 
     let entity = this.entities.get(id);
@@ -147,7 +170,12 @@ class Entities {
       this.engine.rendering.scene.add(object.mesh);
       entity = object;
     } else {
-      entity.setPosition(position);
+      if (snapshot) {
+        if (!entity.setSnapshot(position, snapshot)) return entity;
+      } else {
+        entity.setPosition(position);
+      }
+
       if (lookAt.length > 0) {
         entity.setTarget(new Vector3(...lookAt));
       } else {
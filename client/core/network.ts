@@ -7,6 +7,10 @@ import { Engine } from './engine';
 
 const { Message, ChatMessage } = protocol;
 
+const PROTOCOL_VERSION = 2;
+const CAPABILITIES = ['zlib', 'rgbLight', 'batch'];
+const TOKEN_STORAGE_KEY = 'mine.js-token';
+
 type CustomWebSocket = WebSocket & {
   sendEvent: (event) => void;
   serverURL: string;
@@ -15,6 +19,7 @@ type CustomWebSocket = WebSocket & {
 type NetworkOptionsType = {
   reconnectTimeout: number;
   maxServerUpdates: number;
+  knockbackTolerance: number;
 };
 
 class Network {
@@ -22,6 +27,7 @@ class Network {
 
   public url = Helper.getServerURL({ path: '/ws/' });
   public connected = false;
+  public capabilities: string[] = [];
 
   private reconnection: NodeJS.Timeout;
 
@@ -61,6 +67,11 @@ class Network {
       this.engine.world.handleReconnection();
       this.connected = true;
 
+      server.sendEvent({
+        type: 'HANDSHAKE',
+        json: { version: PROTOCOL_VERSION, capabilities: CAPABILITIES, token: this.token },
+      });
+
       clearTimeout(this.reconnection);
     };
     server.onerror = () => {};
@@ -87,17 +98,26 @@ class Network {
     this.server.close();
   };
 
+  // login token, taken from the page's `?token=` and remembered for next time
+  get token() {
+    const token = new URL(window.location.href).searchParams.get('token');
+    if (token) localStorage.setItem(TOKEN_STORAGE_KEY, token);
+    return token || localStorage.getItem(TOKEN_STORAGE_KEY) || undefined;
+  }
+
   onInit = (event) => {
     const { world, player } = this.engine;
     const {
-      json: { id, time, tickSpeed, spawn, passables },
+      json: { id, time, tickSpeed, spawn, passables, gameMode },
     } = event;
 
     player.id = id;
     player.teleport(spawn);
+    this.setGameMode(gameMode);
 
     world.setTime(time, false);
     world.setBlockData({ passables });
+    world.requestRenderRadius();
 
     this.engine.setTick(tickSpeed, false);
     this.engine.emit('init');
@@ -105,10 +125,11 @@ class Network {
 
   onConfig = (event) => {
     const {
-      json: { time, tickSpeed },
+      json: { time, tickSpeed, renderRadius },
     } = event;
 
     if (Helper.isNumber(time)) this.engine.world.setTime(time, false);
+    if (Helper.isNumber(renderRadius)) this.engine.world.setGrantedRenderRadius(renderRadius);
     if (Helper.isNumber(tickSpeed)) this.engine.setTick(tickSpeed, false);
   };
 
@@ -141,12 +162,52 @@ class Network {
     }
   };
 
+  onHorizon = (event) => {
+    const { world } = this.engine;
+    const { horizons } = event;
+
+    for (const horizon of horizons) {
+      world.handleServerHorizon(horizon);
+    }
+  };
+
+  onChunkReply = (event) => {
+    const { pending, rejected, rejectedHorizons } = event.json;
+    this.engine.world.handleChunkReply(pending || [], rejected || []);
+    this.engine.world.handleHorizonReply(rejectedHorizons || []);
+  };
+
   onInfo = (event) => {
     const { debug } = this.engine;
-    const { biome } = event.json;
+    const { biome, gameMode, abilities, effects, effectSpeed, equipment, experience, waypoints, mount } = event.json;
     if (debug && biome) {
       debug.biome = biome;
     }
+    if (gameMode) {
+      this.setGameMode(gameMode);
+    }
+    if (abilities) {
+      this.engine.player.setAbilities(abilities);
+    }
+    if (effects) {
+      this.engine.player.setEffects(effects, effectSpeed);
+    }
+    if (equipment) {
+      this.engine.peers.setEquipment(equipment.player, equipment.items);
+    }
+    if (experience) {
+      this.engine.player.experience = experience;
+    }
+    if (waypoints) {
+      this.engine.player.waypoints = waypoints;
+    }
+    if (mount !== undefined) {
+      this.engine.player.setMount(mount);
+    }
+  };
+
+  setGameMode = (gameMode: string) => {
+    this.engine.player.setGameMode(gameMode);
   };
 
   onJoin = (event) => {
@@ -171,22 +232,90 @@ class Network {
     const { peers: peersData } = event;
 
     for (const peer of peersData) {
-      const { id, name, px, py, pz, qx, qy, qz, qw } = peer;
+      const { id, name, team, crouching, vehicle, offset, px, py, pz, qx, qy, qz, qw } = peer;
       if (id === player.id) continue;
-      peers.update(id, { name, position: [px, py, pz], rotation: [qx, qy, qz, qw] });
+      peers.update(id, {
+        name,
+        team: team || '',
+        crouching: !!crouching,
+        vehicle: vehicle || '',
+        offset: offset && offset.length === 3 ? offset : null,
+        position: [px, py, pz],
+        rotation: [qx, qy, qz, qw],
+      });
     }
   };
 
   onEntity = (event) => {
     const { entities } = this.engine;
-    const { entities: entitiesData } = event;
+    const { entities: entitiesData, tick, timestamp } = event;
 
     for (const entity of entitiesData) {
-      const { id, type, px, py, pz, heading, lookAt } = entity;
-      entities.handleServerUpdate(id, type, [px, py, pz], heading, lookAt);
+      const { id, type, px, py, pz, heading, lookAt, velocity, resting } = entity;
+      entities.handleServerUpdate(id, type, [px, py, pz], heading, lookAt, {
+        velocity,
+        resting,
+        tick,
+        timestamp,
+      });
+    }
+  };
+
+  onProfile = (event) => {
+    const { player, peers } = this.engine;
+    const { profiles } = event.json;
+
+    for (const profile of profiles) {
+      // the server might have numbered the name to tell players apart
+      if (profile.id === player.id) player.name = profile.displayName;
+      else peers.setProfile(profile.id, profile);
+    }
+  };
+
+  onHealth = (event) => {
+    const { player, dead } = event.json;
+    if (player === this.engine.player.id) {
+      this.engine.player.setDead(!!dead);
     }
   };
 
+  onLatency = (event) => {
+    const { latencies } = event.json;
+    this.engine.peers.setLatencies(latencies);
+  };
+
+  onKnockback = (event) => {
+    const { player } = this.engine;
+    const { impulse, position } = event.json;
+    const { body } = player.entity;
+
+    body.applyImpulse(impulse);
+
+    // snap back to where the server has the player if they drifted too far off
+    const { x, y, z } = player.object.position;
+    const [sx, sy, sz] = position;
+    if (Math.hypot(sx - x, sy - y, sz - z) > this.options.knockbackTolerance) {
+      const [bx, by, bz] = body.getPosition();
+      body.setPosition([bx + sx - x, by + sy - y, bz + sz - z]);
+    }
+  };
+
+  onHandshake = (event) => {
+    const {
+      json: { capabilities },
+    } = event;
+
+    this.capabilities = capabilities;
+  };
+
+  onError = (event) => {
+    const { text } = event;
+
+    // rejected by the server, reconnecting won't help
+    this.connected = false;
+    this.engine.chat.add({ type: 'ERROR', body: text });
+  };
+
   onChat = (event) => {
     const { message } = event;
     this.engine.chat.add(message);
@@ -196,6 +325,16 @@ class Network {
     const { type } = event;
 
     switch (type) {
+      case 'HANDSHAKE': {
+        this.onHandshake(event);
+        break;
+      }
+
+      case 'ERROR': {
+        this.onError(event);
+        break;
+      }
+
       case 'INIT': {
         this.onInit(event);
         break;
@@ -216,6 +355,16 @@ class Network {
         break;
       }
 
+      case 'REQUEST': {
+        this.onChunkReply(event);
+        break;
+      }
+
+      case 'HORIZON': {
+        this.onHorizon(event);
+        break;
+      }
+
       case 'INFO': {
         this.onInfo(event);
         break;
@@ -245,6 +394,26 @@ class Network {
         this.onChat(event);
         break;
       }
+
+      case 'KNOCKBACK': {
+        this.onKnockback(event);
+        break;
+      }
+
+      case 'PROFILE': {
+        this.onProfile(event);
+        break;
+      }
+
+      case 'LATENCY': {
+        this.onLatency(event);
+        break;
+      }
+
+      case 'HEALTH': {
+        this.onHealth(event);
+        break;
+      }
     }
   };
 
@@ -256,6 +425,13 @@ class Network {
     } catch (e) {
       return;
     }
+
+    // messages written out together by the server come in one frame, in the order they were sent
+    if (event.type === 'BATCH') {
+      event.batch.forEach((message) => this.onEvent(Network.parse(message)));
+      return;
+    }
+
     this.onEvent(event);
   };
 
@@ -276,7 +452,10 @@ class Network {
     if (buffer[0] === 0x78 && buffer[1] === 0x9c) {
       buffer = Pako.inflate(buffer);
     }
-    const message = Message.decode(buffer);
+    return Network.parse(Message.decode(buffer));
+  }
+
+  static parse(message) {
     // @ts-ignore
     message.type = Message.Type[message.type];
     if (message.json) {
@@ -1,6 +1,6 @@
 import { Quaternion, Vector3 } from 'three';
 
-import { Peer } from '../libs';
+import { Peer, PeerProfileType, PeerEquipmentType } from '../libs';
 import { Coords3 } from '../libs/types';
 import { Helper } from '../utils';
 
@@ -8,6 +8,11 @@ import { Engine } from '.';
 
 type PacketType = {
   name: string;
+  team: string;
+  crouching: boolean;
+  // id of the entity ridden, empty if none, with where the rider sits relative to it
+  vehicle: string;
+  offset: Coords3 | null;
   position: Coords3;
   rotation: [...Coords3, number];
 };
@@ -21,6 +26,9 @@ class Peers {
 
   public players: Map<string, Peer> = new Map();
 
+  // round trip times in milliseconds by player id, the player's own included
+  public latencies: Map<string, number> = new Map();
+
   constructor(public engine: Engine, public options: PeersOptionsType) {
     const { updateInterval } = this.options;
 
@@ -54,6 +62,8 @@ class Peers {
               qy,
               qz,
               qw,
+              swimUp: engine.player.entity.brain.state.jumping,
+              crouching: engine.player.crouching,
             },
           ],
         });
@@ -106,8 +116,15 @@ class Peers {
   updateDOM = () => {
     this.wrapper.innerHTML = '';
 
-    const peerNames = Array.from(this.players.values()).map((p) => p.name);
-    peerNames.push(this.engine.player.name);
+    const withLatency = (id: string, name: string) => {
+      const latency = this.latencies.get(id);
+      return latency === undefined ? name : `${name} (${latency}ms)`;
+    };
+
+    const peerNames = Array.from(this.players.values())
+      .filter((p) => p.visible)
+      .map((p) => withLatency(p.id, p.name));
+    peerNames.push(withLatency(this.engine.player.id, this.engine.player.name));
 
     peerNames.forEach((pn) => {
       const newEle = document.createElement('li');
@@ -116,7 +133,7 @@ class Peers {
         padding: '2px 10px',
         borderBottom: '2px solid rgba(0, 0, 0, 0.222)',
       });
-      newEle.innerHTML = pn;
+      newEle.textContent = pn;
       this.wrapper.appendChild(newEle);
     });
   };
@@ -137,10 +154,35 @@ class Peers {
 
     const player = this.players.get(id);
 
-    const { name, position, rotation } = packet;
+    const { name, team, crouching, vehicle, offset, position, rotation } = packet;
 
     if (name !== player.name) this.updateDOM();
-    player.update(name, new Vector3(...position), new Quaternion(...rotation));
+    player.update(name, team, crouching, new Vector3(...position), new Quaternion(...rotation));
+    player.setVehicle(vehicle, offset ? new Vector3(...offset) : null);
+  };
+
+  setProfile = (id: string, profile: PeerProfileType) => {
+    if (!this.players.has(id)) {
+      this.join(id);
+    }
+
+    this.players.get(id).setProfile(profile);
+
+    if (this.wrapper) this.updateDOM();
+  };
+
+  setEquipment = (id: string, equipment: PeerEquipmentType) => {
+    if (!this.players.has(id)) {
+      this.join(id);
+    }
+
+    this.players.get(id).setEquipment(equipment);
+  };
+
+  setLatencies = (latencies: { [id: string]: number }) => {
+    Object.entries(latencies).forEach(([id, latency]) => this.latencies.set(id, latency));
+
+    if (this.wrapper && this.wrapper.style.display !== 'none') this.updateDOM();
   };
 
   leave = (id: string) => {
@@ -151,11 +193,13 @@ class Peers {
     this.engine.rendering.scene.remove(player.mesh);
     this.engine.shadows.remove(player.mesh);
     this.players.delete(id);
+    this.latencies.delete(id);
   };
 
   tick = () => {
     this.players.forEach((peer) => {
-      peer.tick(this.engine.player.object.position);
+      const vehicle = peer.vehicle ? this.engine.entities.entities.get(peer.vehicle) : null;
+      peer.tick(this.engine.player.object.position, vehicle ? vehicle.mesh.position : null);
     });
   };
 }
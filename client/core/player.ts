@@ -24,6 +24,43 @@ type PlayerOptionsType = {
 
 type PerspectiveType = 'first' | 'second' | 'third';
 
+type AbilitiesType = {
+  fly: boolean;
+  speed: number;
+};
+
+type ExperienceType = {
+  points: number;
+  level: number;
+  // how far along the way to the next level, from 0 to 1
+  progress: number;
+};
+
+type WaypointType = {
+  name: string;
+  voxel: Coords3;
+};
+
+type WaypointsType = {
+  // the player's own waypoints
+  own: WaypointType[];
+  // shared by admins with everyone in the world
+  markers: WaypointType[];
+};
+
+type EffectType = {
+  kind: string;
+  level: number;
+  remaining: number;
+};
+
+type MountType = {
+  // id of the mob being ridden
+  entity: string;
+  // height the rider sits at above the mob's feet, in voxels
+  seat: number;
+};
+
 const LOCAL_STORAGE_PLAYER_NAME = 'mine.js-player';
 const DEFAULT_PLAYER_NAME = 'naenaebaby';
 
@@ -36,12 +73,26 @@ const NZ_ROTATION = 5;
 
 const FOOTSTEP_SFX_NAME = 'footsteps';
 
+// share of its height the body keeps while crouching, same as the server's
+const CROUCH_HEIGHT = 0.85;
+
+const BASE_BRIGHTNESS = 0.02;
+const NIGHT_VISION_BRIGHTNESS = 0.6;
+
 type TargetBlock = { voxel: Coords3; rotation?: number; yRotation?: number };
 
 class Player {
   public id: string;
   public name: string;
   public spectatorMode = false;
+  public gameMode = 'survival';
+  public abilities: AbilitiesType = { fly: false, speed: 1 };
+  public effects: EffectType[] = [];
+  public effectSpeed = 1;
+  public dead = false;
+  public experience: ExperienceType = { points: 0, level: 0, progress: 0 };
+  public waypoints: WaypointsType = { own: [], markers: [] };
+  public mount: MountType | null = null;
 
   public controls: PointerLockControls;
 
@@ -56,6 +107,7 @@ class Player {
 
   public own: Peer;
 
+  private baseMaxSpeed: number;
   private acc = new Vector3();
   private vel = new Vector3();
   private vec = new Vector3();
@@ -68,6 +120,7 @@ class Player {
     back: false,
     sprint: false,
   };
+  private steer = { forward: 0, right: 0 };
 
   private lookBlockMesh: Group;
   private shadowMesh: Mesh;
@@ -144,8 +197,16 @@ class Player {
       { occasion: 'keyup' },
     );
 
-    inputs.bind('f', this.toggleSpectatorMode, 'in-game');
+    inputs.bind(
+      'f',
+      () => {
+        if (this.canFly) this.toggleSpectatorMode();
+      },
+      'in-game',
+    );
     inputs.bind('c', this.togglePerspective, 'in-game');
+    inputs.bind('r', this.respawn, 'in-game');
+    inputs.bind('g', this.toggleMount, 'in-game');
 
     this.controls.addEventListener('lock', () => {
       this.engine.emit('lock');
@@ -291,7 +352,9 @@ class Player {
   };
 
   tick = () => {
-    if (this.spectatorMode) {
+    if (this.mount) {
+      this.rideMount();
+    } else if (this.spectatorMode) {
       this.spectatorModeMovements();
     } else {
       this.moveEntity();
@@ -306,7 +369,8 @@ class Player {
     const { delta } = this.engine.clock;
 
     const { right, left, up, down, front, back } = this.movements;
-    const { acceleration, flyingInertia } = this.options;
+    const { flyingInertia } = this.options;
+    const acceleration = this.options.acceleration * this.abilities.speed;
 
     const movementVec = new Vector3();
     movementVec.x = Number(right) - Number(left);
@@ -332,6 +396,61 @@ class Player {
     this.controls.getObject().position.y += this.vel.y;
   };
 
+  // riders sit on their mount wherever the server says it is, sending over how they'd like to steer it
+  rideMount = () => {
+    const { dimension } = this.engine.world.options;
+    const { distToGround } = this.options;
+
+    const mount = this.engine.entities.entities.get(this.mount.entity);
+    if (mount) {
+      const { x, y, z } = mount.mesh.position;
+      this.object.position.set(x, y + (this.mount.seat + distToGround) * dimension, z);
+    }
+
+    const { right, left, front, back, down } = this.movements;
+    const forward = Number(front) - Number(back);
+    const sideways = Number(right) - Number(left);
+
+    if (forward !== this.steer.forward || sideways !== this.steer.right) {
+      this.steer = { forward, right: sideways };
+      this.interact({ action: 'steer', ...this.steer });
+    }
+
+    if (down) {
+      this.movements.down = false;
+      this.interact({ action: 'dismount' });
+    }
+  };
+
+  // get on the closest mob in reach, or off of the one being ridden
+  toggleMount = () => {
+    this.interact({ action: this.mount ? 'dismount' : 'mount' });
+  };
+
+  // riding takes the player's own body out of physics until they get off, like spectating does
+  setMount = (mount: MountType | null) => {
+    if (!!mount === !!this.mount) {
+      this.mount = mount;
+      return;
+    }
+
+    this.mount = mount;
+    this.steer = { forward: 0, right: 0 };
+
+    if (mount) {
+      this.vel.set(0, 0, 0);
+      this.acc.set(0, 0, 0);
+      this.engine.entities.removePhysical('player');
+      this.engine.sounds.pause(FOOTSTEP_SFX_NAME);
+    } else if (!this.spectatorMode) {
+      this.addEntity();
+    }
+  };
+
+  interact = (json: { [key: string]: any }) => {
+    this.engine.network.server.sendEvent({ type: 'INTERACT', json });
+  };
+
   moveEntity = () => {
     const { object } = this.controls;
     const { state, body } = this.entity.brain;
@@ -379,8 +498,9 @@ class Player {
     // set jump as true, and brain will handle the jumping
     state.jumping = up ? (down ? false : true) : down ? false : false;
 
-    // crouch to true, so far used for flying
+    // flying down while flying, otherwise crouching
     state.crouching = down;
+    this.setCrouching(down && body.gravityMultiplier !== 0);
 
     // apply sprint state change
     state.sprinting = sprint;
@@ -436,9 +556,79 @@ class Player {
 
     const { body } = this.entity;
     const isFlying = body.gravityMultiplier === 0;
+
+    // landing is always allowed, taking off only if the server lets the player fly
+    if (!isFlying && !this.canFly) return;
+
     body.gravityMultiplier = isFlying ? 1 : 0;
   };
 
+  setGameMode = (gameMode: string) => {
+    this.gameMode = gameMode;
+    this.updateNoClip();
+  };
+
+  setAbilities = (abilities: AbilitiesType) => {
+    this.abilities = abilities;
+    this.applySpeed();
+    this.updateNoClip();
+  };
+
+  // spectators and players granted flight go through blocks, as they do on the server
+  updateNoClip = () => {
+    const noClip = this.gameMode === 'spectator' || this.abilities.fly;
+
+    if (noClip !== this.spectatorMode) {
+      this.toggleSpectatorMode();
+    }
+
+    if (!this.canFly && this.entity && this.entity.body.gravityMultiplier === 0) {
+      this.toggleFly();
+    }
+  };
+
+  // the server ignores the dead until they ask to respawn
+  setDead = (dead: boolean) => {
+    if (dead && !this.dead) {
+      this.engine.chat.add({ type: 'INFO', body: 'You died! Press R to respawn.' });
+    }
+
+    this.dead = dead;
+  };
+
+  respawn = () => {
+    if (!this.dead) return;
+    this.engine.network.server.sendEvent({ type: 'RESPAWN' });
+  };
+
+  // status effects the server says the player is under, along with how they change their speed
+  setEffects = (effects: EffectType[], effectSpeed: number) => {
+    this.effects = effects;
+    this.effectSpeed = effectSpeed;
+    this.applySpeed();
+
+    const nightVision = effects.some(({ kind }) => kind === 'nightVision');
+    this.engine.world.uMinBrightness.value = nightVision ? NIGHT_VISION_BRIGHTNESS : BASE_BRIGHTNESS;
+  };
+
+  // where each waypoint and marker lies from the player, for beacons and compass hints: the distance in voxels
+  // and the angle around the y axis in radians, 0 facing -z
+  waypointHints = () => {
+    const [vx, , vz] = this.voxel;
+    const { own, markers } = this.waypoints;
+
+    return [...own, ...markers].map(({ name, voxel }) => {
+      const dx = voxel[0] - vx;
+      const dz = voxel[2] - vz;
+      return { name, voxel, distance: Math.sqrt(dx * dx + dz * dz), angle: Math.atan2(-dx, -dz) };
+    });
+  };
+
+  applySpeed = () => {
+    if (!this.entity) return;
+    this.entity.brain.options.maxSpeed = this.baseMaxSpeed * this.abilities.speed * this.effectSpeed;
+  };
+
   toggleSpectatorMode = () => {
     this.spectatorMode = !this.spectatorMode;
     this.shadowMesh.visible = !this.spectatorMode;
@@ -467,6 +657,9 @@ class Player {
       [0, (distToGround - (distToGround + distToTop) / 2) * dimension, 0],
     );
 
+    this.baseMaxSpeed = this.entity.brain.options.maxSpeed;
+    this.applySpeed();
+
     this.entity.body.applyImpulse([0, 4, 0]);
   };
 
@@ -494,6 +687,28 @@ class Player {
     this.own.mesh.visible = this.perspective !== 'first';
   };
 
+  // crouching shrinks the body down towards its feet, taking the camera along as the server
+  // expects it to
+  setCrouching = (crouching: boolean) => {
+    const { body, offsets } = this.entity;
+    if (body.crouching === crouching) return;
+
+    const scale = crouching ? CROUCH_HEIGHT : 1 / CROUCH_HEIGHT;
+
+    body.aabb.vec[1] *= scale;
+    body.aabb.max[1] = body.aabb.base[1] + body.aabb.vec[1];
+    offsets[1] *= scale;
+    body.crouching = crouching;
+  };
+
+  get crouching() {
+    return !!this.entity && this.entity.body.crouching;
+  }
+
+  get canFly() {
+    return this.gameMode !== 'survival' || this.abilities.fly;
+  }
+
   dispose = () => {
     this.controls.dispose();
   };
@@ -682,4 +897,4 @@ class Player {
   }
 }
 
-export { Player, PlayerOptionsType, TargetBlock };
+export { Player, PlayerOptionsType, TargetBlock, MountType };
@@ -400,6 +400,7 @@ uniform vec3 uFogNearColor;
 uniform float uFogNear;
 uniform float uFogFar;
 uniform float uSunlightIntensity;
+uniform float uMinBrightness;
 
 varying float vAO;
 varying vec4 vLight; 
@@ -409,7 +410,7 @@ varying vec4 vLight;
           '#include <envmap_fragment>',
           `
 #include <envmap_fragment>
-float s = max(vLight.a * uSunlightIntensity * 0.8, 0.02);
+float s = max(vLight.a * uSunlightIntensity * 0.8, uMinBrightness);
 float scale = 1.0;
 outgoingLight.rgb *= vec3(s + pow(vLight.r, scale), s + pow(vLight.g, scale), s + pow(vLight.b, scale));
 // outgoingLight.rgb *= vec3(s + scale / sqrt(vLight.r), s + scale / sqrt(vLight.g), s + scale / sqrt(vLight.b));
@@ -463,6 +464,7 @@ vLight = unpackLight(light);
         ...UniformsUtils.clone(ShaderLib.basic.uniforms),
         map: this.atlasUniform,
         uSunlightIntensity: this.engine.world.uSunlightIntensity,
+        uMinBrightness: this.engine.world.uMinBrightness,
         uAOTable: this.aoUniform,
         ...this.engine.rendering.fogUniforms,
       },
@@ -1,7 +1,7 @@
 import { EventEmitter } from 'events';
 
 import BlockChangeSFX from '../assets/sfx/plop.ogg';
-import { AABB, Clouds, ServerChunkType, Sky } from '../libs';
+import { AABB, Clouds, Horizon, ServerChunkType, ServerHorizonType, Sky } from '../libs';
 import { Coords3, Coords2 } from '../libs/types';
 import { Helper } from '../utils';
 
@@ -17,7 +17,11 @@ type WorldOptionsType = {
   dimension?: number;
   renderRadius: number;
   requestRadius: number;
+  // chunks past the meshes drawn as heightfields, capped by the world's max horizon radius in voxels
+  horizonRadius: number;
+  maxHorizonRadius?: number;
   maxChunkProcessPerFrame: number;
+  maxChunkRequestPerFrame: number;
   maxBlockPerFrame: number;
   chunkAnimation: boolean;
   animationTime: number;
@@ -32,9 +36,12 @@ class World extends EventEmitter {
 
   public sky: Sky;
   public clouds: Clouds;
+  public horizon: Horizon;
 
   // uniforms
   public uSunlightIntensity = { value: 0.1 };
+  // lowest a block gets lit, raised by night vision
+  public uMinBrightness = { value: 0.02 };
 
   public blockData: { passables: number[] } = {
     passables: [],
@@ -45,14 +52,24 @@ class World extends EventEmitter {
 
   private pendingChunks: Coords2[] = [];
   private requestedChunks: Set<string> = new Set();
+  // requested chunks the server is still generating or meshing
+  private serverPendingChunks: Set<string> = new Set();
   private receivedChunks: ServerChunkType[] = [];
   private chunks: Map<string, Chunk> = new Map();
+  private pendingHorizons: Coords2[] = [];
+  private requestedHorizons: Set<string> = new Set();
+  // whether horizons need to be hidden or shown again for the chunks drawn with meshes
+  private horizonsDirty = false;
 
   constructor(public engine: Engine, public options: WorldOptionsType) {
     super();
 
     this.sky = new Sky(engine.rendering);
     this.clouds = new Clouds(engine.rendering);
+    this.horizon = new Horizon(engine.rendering, {
+      uSunlightIntensity: this.uSunlightIntensity,
+      uMinBrightness: this.uMinBrightness,
+    });
 
     // kinda ugly
     this.name = options.name;
@@ -87,6 +104,7 @@ class World extends EventEmitter {
 
   tick = () => {
     this.checkCamChunk();
+    this.updateHorizons();
     this.requestChunks();
     this.meshChunks();
     this.animateSky();
@@ -188,12 +206,41 @@ class World extends EventEmitter {
     return chunk?.getSunlight(...vCoords);
   };
 
+  handleChunkReply = (pending: Coords2[], rejected: Coords2[]) => {
+    pending.forEach((coords) => this.serverPendingChunks.add(Helper.getChunkName(coords)));
+
+    // turned away, so asked for again once the camera moves to them
+    rejected.forEach((coords) => {
+      const rep = Helper.getChunkName(coords);
+      this.requestedChunks.delete(rep);
+      this.serverPendingChunks.delete(rep);
+    });
+  };
+
+  handleHorizonReply = (rejected: Coords2[]) => {
+    rejected.forEach((coords) => this.requestedHorizons.delete(Helper.getChunkName(coords)));
+  };
+
+  handleServerHorizon = (horizon: ServerHorizonType) => {
+    horizon.x = horizon.x || 0;
+    horizon.z = horizon.z || 0;
+    const name = Helper.getChunkName([horizon.x, horizon.z]);
+
+    // too far away by now
+    if (!this.requestedHorizons.delete(name)) return;
+
+    const { chunkSize, dimension } = this.options;
+    this.horizon.add(horizon, chunkSize, dimension);
+    this.horizonsDirty = true;
+  };
+
   handleServerChunk = (serverChunk: ServerChunkType, prioritized = false) => {
     serverChunk.x = serverChunk.x || 0;
     serverChunk.z = serverChunk.z || 0;
     const { x: cx, z: cz } = serverChunk;
     const coords = [cx, cz] as Coords2;
     this.requestedChunks.delete(Helper.getChunkName(coords));
+    this.serverPendingChunks.delete(Helper.getChunkName(coords));
     if (prioritized) this.meshChunk(serverChunk);
     else this.receivedChunks.push(serverChunk);
     if (this.receivedChunks.length >= this.engine.config.network.maxServerUpdates) {
@@ -307,6 +354,9 @@ class World extends EventEmitter {
     registry.opaqueChunkMaterial.uniforms.uFogNear.value = renderRadiuus * 0.6 * chunkSize * dimension;
     registry.opaqueChunkMaterial.uniforms.uFogFar.value = renderRadiuus * chunkSize * dimension;
 
+    const horizonRadius = this.getHorizonRadius();
+    this.horizon.setFog(horizonRadius * 0.6 * chunkSize * dimension, horizonRadius * chunkSize * dimension);
+
     this.checkCamChunk();
     this.surroundCamChunks();
   };
@@ -329,6 +379,23 @@ class World extends EventEmitter {
     }
   };
 
+  requestRenderRadius = () => {
+    // the server caps this, and replies with the radius it grants
+    this.engine.network.server.sendEvent({
+      type: 'CONFIG',
+      json: {
+        renderRadius: this.options.requestRadius,
+      },
+    });
+  };
+
+  setGrantedRenderRadius = (granted: number) => {
+    this.options.requestRadius = Math.min(this.options.requestRadius, granted);
+    this.options.renderRadius = Math.min(this.options.renderRadius, granted);
+
+    this.updateRenderRadius(this.options.renderRadius);
+  };
+
   setBlockData = ({ passables }) => {
     if (passables && passables.length) this.blockData.passables = passables;
   };
@@ -346,10 +413,14 @@ class World extends EventEmitter {
     });
 
     this.pendingChunks = [];
+    this.pendingHorizons = [];
     this.receivedChunks = [];
 
     this.chunks.clear();
     this.requestedChunks.clear();
+    this.serverPendingChunks.clear();
+    this.requestedHorizons.clear();
+    this.horizon.clear();
 
     this.surroundCamChunks();
   };
@@ -362,6 +433,10 @@ class World extends EventEmitter {
     return this.chunks.size;
   }
 
+  get chunksPending() {
+    return this.serverPendingChunks.size;
+  }
+
   get camChunkPosStr() {
     const local = Helper.mapVoxelPosToChunkLocalPos(this.engine.player.voxel, this.options.chunkSize);
     return `${local[0]} ${local[1]} ${local[2]} in ${this.camChunkPos[0]} ${this.camChunkPos[1]}`;
@@ -447,6 +522,8 @@ class World extends EventEmitter {
     // make pending chunks radiate from player, might have easier ways of doing so
     this.sortPendingChunks();
 
+    this.surroundCamHorizons();
+
     // if the chunk is too far away, remove from scene.
     const deleteDistance = renderRadius * chunkSize * 1.414;
     const removeDistance = requestRadius * chunkSize * 1.414;
@@ -463,21 +540,78 @@ class World extends EventEmitter {
     }
   };
 
+  // chunks out to which far terrain is drawn, none if the world has no horizons
+  private getHorizonRadius = () => {
+    const { chunkSize, horizonRadius, maxHorizonRadius, renderRadius } = this.options;
+    if (!maxHorizonRadius) return 0;
+    return Math.max(Math.min(horizonRadius, Math.floor(maxHorizonRadius / chunkSize)), renderRadius);
+  };
+
+  // queue the horizons of the chunks past the render radius, dropping the ones too far away
+  private surroundCamHorizons = () => {
+    const { renderRadius } = this.options;
+    const horizonRadius = this.getHorizonRadius();
+    const [cx, cz] = this.camChunkPos;
+
+    this.pendingHorizons = [];
+
+    for (let x = cx - horizonRadius; x <= cx + horizonRadius; x++) {
+      for (let z = cz - horizonRadius; z <= cz + horizonRadius; z++) {
+        const dist = (x - cx) ** 2 + (z - cz) ** 2;
+        if (dist <= renderRadius * renderRadius || dist > horizonRadius * horizonRadius) continue;
+
+        const name = Helper.getChunkName([x, z]);
+        if (!this.horizon.has(name) && !this.requestedHorizons.has(name)) {
+          this.pendingHorizons.push([x, z]);
+        }
+      }
+    }
+
+    this.pendingHorizons.sort((a, b) => (cx - a[0]) ** 2 + (cz - a[1]) ** 2 - (cx - b[0]) ** 2 - (cz - b[1]) ** 2);
+
+    const isFar = (name: string) => {
+      const [x, z] = Helper.parseChunkName(name);
+      return (x - cx) ** 2 + (z - cz) ** 2 > horizonRadius * horizonRadius;
+    };
+
+    this.horizon.names().filter(isFar).forEach(this.horizon.remove);
+    Array.from(this.requestedHorizons).filter(isFar).forEach((name) => this.requestedHorizons.delete(name));
+
+    this.horizonsDirty = true;
+  };
+
+  private updateHorizons = () => {
+    if (!this.horizonsDirty) return;
+
+    this.horizon.update((name) => !!this.chunks.get(name)?.isAdded);
+    this.horizonsDirty = false;
+  };
+
   private requestChunks = () => {
     // separate chunk request into frames to avoid clogging
-    if (this.pendingChunks.length === 0 || !this.engine.connected) return;
+    if ((this.pendingChunks.length === 0 && this.pendingHorizons.length === 0) || !this.engine.connected) return;
 
     // don't clog up the server
-    const framePendingChunks = this.pendingChunks.splice(0, 2);
-    framePendingChunks.forEach(([cx, cz]) => {
-      const rep = Helper.getChunkName([cx, cz]);
-      if (this.requestedChunks.has(rep)) return;
-      this.engine.network.server.sendEvent({
-        type: 'REQUEST',
-        json: { x: cx, z: cz },
-      });
-      this.requestedChunks.add(rep);
+    const { maxChunkRequestPerFrame } = this.options;
+
+    const framePendingChunks = this.pendingChunks
+      .splice(0, maxChunkRequestPerFrame)
+      .filter((coords) => !this.requestedChunks.has(Helper.getChunkName(coords)));
+
+    // heightfields are much lighter than chunks, so more of them are asked for at once
+    const framePendingHorizons = this.pendingHorizons
+      .splice(0, maxChunkRequestPerFrame * 4)
+      .filter((coords) => !this.requestedHorizons.has(Helper.getChunkName(coords)));
+
+    if (framePendingChunks.length === 0 && framePendingHorizons.length === 0) return;
+
+    this.engine.network.server.sendEvent({
+      type: 'REQUEST',
+      json: { chunks: framePendingChunks, horizons: framePendingHorizons },
     });
+
+    framePendingChunks.forEach((coords) => this.requestedChunks.add(Helper.getChunkName(coords)));
+    framePendingHorizons.forEach((coords) => this.requestedHorizons.add(Helper.getChunkName(coords)));
   };
 
   private meshChunks = () => {
@@ -500,6 +634,7 @@ class World extends EventEmitter {
       const { chunkSize, subChunks, dimension, maxHeight } = this.options;
       chunk = new Chunk(this.engine, coords, { size: chunkSize, subChunks, dimension, maxHeight });
       this.setChunk(chunk);
+      this.horizonsDirty = true;
     }
 
     const { meshes, voxels, lights } = serverChunk;
@@ -508,6 +643,9 @@ class World extends EventEmitter {
 
     if (voxels.length) chunk.voxels.data = serverChunk.voxels;
     if (lights.length) chunk.lights.data = serverChunk.lights;
+
+    // empty chunks come without any data, they're all air
+    if (serverChunk.empty) chunk.voxels.data.fill(0);
   };
 
   private animateSky = () => {
@@ -15,6 +15,7 @@ type BrainOptionsType = {
   flyInertia: number;
 
   sprintFactor: number;
+  crouchFactor: number;
   airMoveMult: number;
   jumpImpulse: number;
   jumpForce: number;
@@ -48,6 +49,7 @@ const defaultBrainOptions: BrainOptionsType = {
   flyInertia: 3,
 
   sprintFactor: 1.4,
+  crouchFactor: 0.3,
   airMoveMult: 0.7,
   jumpImpulse: 8,
   jumpForce: 1,
@@ -129,9 +131,8 @@ class Brain {
       const push = this.tempVec2;
       if (this.state.running) {
         let speed = this.options.maxSpeed;
-        // todo: add crouch/sprint modifiers if needed
         if (this.state.sprinting) speed *= this.options.sprintFactor;
-        // if (state.crouch) speed *= state.crouchMoveMult
+        if (this.body.crouching) speed *= this.options.crouchFactor;
         vec3.set(m, 0, 0, speed);
 
         // rotate move vector to entity's heading
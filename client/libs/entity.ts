@@ -6,6 +6,17 @@ import { Coords3 } from './types';
 
 const WALKING_SPEED = 1.4;
 const IDLE_ARM_SPEED = 0.06;
+// seconds an entity keeps moving along its last known velocity without hearing from the server
+const MAX_EXTRAPOLATION = 0.25;
+
+type EntitySnapshot = {
+  velocity: Coords3;
+  // -1, 0 or 1 per axis, the side the entity is resting against
+  resting: Coords3;
+  tick: number;
+  // when the server sent it, in our clock's milliseconds
+  timestamp: number;
+};
 
 class Entity {
   public head: Mesh;
@@ -21,6 +32,11 @@ class Entity {
   public mesh: Mesh;
 
   public newPosition = new Vector3();
+  public velocity = new Vector3();
+  public grounded = false;
+
+  private lastTick = -1;
+  private sentAt = 0;
   private currLooking = new Vector3();
   private bodyLooking = new Vector3();
 
@@ -74,7 +90,15 @@ class Entity {
     // or else network latency will result in a weird
     // animation defect where body glitches out.
     if (this.newPosition.length() !== 0) {
-      this.mesh.position.lerp(this.newPosition, 0.7);
+      const elapsed = Math.min(Math.max(Date.now() - this.sentAt, 0) / 1000, MAX_EXTRAPOLATION);
+      const predicted = this.newPosition.clone().addScaledVector(this.velocity, elapsed);
+
+      // snapped to the ground it rests on, instead of jittering with gravity
+      if (this.grounded) predicted.y = this.newPosition.y;
+
+      this.mesh.position.lerp(predicted, 0.7);
+
+      if (this.grounded) this.mesh.position.y = this.newPosition.y;
     }
 
     if (this.target) {
@@ -138,6 +162,20 @@ class Entity {
     this.newPosition = new Vector3(...position);
   };
 
+  // take in a server update, returning false if it's older than the last one taken
+  setSnapshot = (position: Coords3, { velocity, resting, tick, timestamp }: EntitySnapshot) => {
+    if (tick <= this.lastTick) return false;
+
+    this.lastTick = tick;
+    this.sentAt = timestamp;
+
+    this.setPosition(position);
+    if (velocity.length === 3) this.velocity.set(velocity[0], velocity[1], velocity[2]);
+    this.grounded = resting.length === 3 && resting[1] < 0;
+
+    return true;
+  };
+
   lookAt = (position: Vector3) => {
     this.head.lookAt(position);
   };
@@ -147,4 +185,4 @@ class Entity {
   };
 }
 
-export { Entity };
+export { Entity, EntitySnapshot };
@@ -98,6 +98,31 @@ class Head {
     context.fillRect(4, 1, 1, 1);
   };
 
+  // paints the face from a skin image, or back to the default face without one
+  setSkin = (url?: string) => {
+    if (!url) {
+      this.box.paint('front', this.drawBackground);
+      this.box.paint('front', this.drawFace);
+      return;
+    }
+
+    const image = new Image();
+    image.crossOrigin = 'anonymous';
+    image.onload = () => {
+      this.box.paint('front', (material: MeshBasicMaterial) => {
+        const canvas = <HTMLCanvasElement>material.map.image;
+        if (!canvas) return;
+
+        const context = canvas.getContext('2d');
+
+        context.imageSmoothingEnabled = false;
+        context.clearRect(0, 0, canvas.width, canvas.height);
+        context.drawImage(image, 0, 0, canvas.width, canvas.height);
+      });
+    };
+    image.src = url;
+  };
+
   drawHair = (material: MeshBasicMaterial) => {
     const canvas = <HTMLCanvasElement>material.map.image;
     if (!canvas) return;
@@ -0,0 +1,154 @@
+import { BufferGeometry, DoubleSide, Float32BufferAttribute, Group, Mesh, ShaderMaterial } from 'three';
+
+import { Rendering } from '../core';
+import { Helper } from '../utils';
+
+import HorizonFragmentShader from './shaders/horizon/fragment.glsl';
+import HorizonVertexShader from './shaders/horizon/vertex.glsl';
+import { ServerHorizonType } from './types';
+
+// how much darker the sides of columns are than their tops
+const SIDE_SHADE = 0.7;
+
+// far terrain past the chunk meshes, drawn as heightfields of the blocks on top
+class Horizon {
+  public material: ShaderMaterial;
+
+  private group = new Group();
+  private meshes: Map<string, Mesh> = new Map();
+
+  constructor(public rendering: Rendering, lighting: { [key: string]: { value: number } }) {
+    this.material = new ShaderMaterial({
+      vertexShader: HorizonVertexShader,
+      fragmentShader: HorizonFragmentShader,
+      // walls face whichever way the ground drops
+      side: DoubleSide,
+      uniforms: {
+        ...rendering.fogUniforms,
+        uFogNear: { value: 0 },
+        uFogFar: { value: 0 },
+        ...lighting,
+      },
+    });
+
+    rendering.scene.add(this.group);
+  }
+
+  setFog = (near: number, far: number) => {
+    this.material.uniforms.uFogNear.value = near;
+    this.material.uniforms.uFogFar.value = far;
+  };
+
+  has = (name: string) => {
+    return this.meshes.has(name);
+  };
+
+  add = ({ x: cx, z: cz, heights, colors }: ServerHorizonType, chunkSize: number, dimension: number) => {
+    const name = Helper.getChunkName([cx, cz]);
+    const positions: number[] = [];
+    const vertexColors: number[] = [];
+    const indices: number[] = [];
+
+    const quad = (corners: number[][], color: number, shade: number) => {
+      const start = positions.length / 3;
+      const r = ((color >> 16) & 0xff) / 255;
+      const g = ((color >> 8) & 0xff) / 255;
+      const b = (color & 0xff) / 255;
+
+      corners.forEach(([x, y, z]) => {
+        positions.push(x * dimension, y * dimension, z * dimension);
+        vertexColors.push(r * shade, g * shade, b * shade);
+      });
+
+      indices.push(start, start + 1, start + 2, start + 2, start + 1, start + 3);
+    };
+
+    const heightAt = (lx: number, lz: number) => heights[lz * chunkSize + lx];
+
+    for (let lz = 0; lz < chunkSize; lz++) {
+      for (let lx = 0; lx < chunkSize; lx++) {
+        const color = colors[lz * chunkSize + lx];
+        const top = heightAt(lx, lz) + 1;
+        const vx = cx * chunkSize + lx;
+        const vz = cz * chunkSize + lz;
+
+        quad(
+          [
+            [vx, top, vz],
+            [vx, top, vz + 1],
+            [vx + 1, top, vz],
+            [vx + 1, top, vz + 1],
+          ],
+          color,
+          1,
+        );
+
+        // walls down to the lower neighbors, so cliffs don't leave holes
+        if (lx < chunkSize - 1 && heightAt(lx + 1, lz) + 1 !== top) {
+          const other = heightAt(lx + 1, lz) + 1;
+          quad(
+            [
+              [vx + 1, top, vz],
+              [vx + 1, top, vz + 1],
+              [vx + 1, other, vz],
+              [vx + 1, other, vz + 1],
+            ],
+            top > other ? color : colors[lz * chunkSize + lx + 1],
+            SIDE_SHADE,
+          );
+        }
+
+        if (lz < chunkSize - 1 && heightAt(lx, lz + 1) + 1 !== top) {
+          const other = heightAt(lx, lz + 1) + 1;
+          quad(
+            [
+              [vx, top, vz + 1],
+              [vx + 1, top, vz + 1],
+              [vx, other, vz + 1],
+              [vx + 1, other, vz + 1],
+            ],
+            top > other ? color : colors[(lz + 1) * chunkSize + lx],
+            SIDE_SHADE,
+          );
+        }
+      }
+    }
+
+    const geometry = new BufferGeometry();
+    geometry.setAttribute('position', new Float32BufferAttribute(positions, 3));
+    geometry.setAttribute('color', new Float32BufferAttribute(vertexColors, 3));
+    geometry.setIndex(indices);
+
+    const mesh = new Mesh(geometry, this.material);
+
+    this.remove(name);
+    this.meshes.set(name, mesh);
+    this.group.add(mesh);
+  };
+
+  // hide the horizons of the chunks drawn with their meshes instead
+  update = (isMeshed: (name: string) => boolean) => {
+    this.meshes.forEach((mesh, name) => {
+      mesh.visible = !isMeshed(name);
+    });
+  };
+
+  remove = (name: string) => {
+    const mesh = this.meshes.get(name);
+    if (!mesh) return;
+
+    this.group.remove(mesh);
+    mesh.geometry.dispose();
+    this.meshes.delete(name);
+  };
+
+  names = () => {
+    return Array.from(this.meshes.keys());
+  };
+
+  clear = () => {
+    this.names().forEach(this.remove);
+  };
+}
+
+export { Horizon };
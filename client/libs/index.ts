@@ -4,6 +4,7 @@ export * from './canvas-box';
 export * from './chat-history';
 export * from './clock';
 export * from './clouds';
+export * from './horizon';
 export * from './message';
 export * from './overwrites';
 export * from './peer';
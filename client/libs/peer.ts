@@ -1,4 +1,4 @@
-import { Vector3, Quaternion, NearestFilter } from 'three';
+import { Vector3, Quaternion, NearestFilter, Mesh, BoxGeometry, MeshBasicMaterial } from 'three';
 import SpriteText from 'three-spritetext';
 
 import { Head } from './head';
@@ -10,6 +10,16 @@ type PeerOptionsType = {
   maxNameDistance: number;
 };
 
+// items worn in the head, chest, legs and feet slots, null where nothing's worn
+type PeerEquipmentType = ({ id: number; count: number } | null)[];
+
+type PeerProfileType = {
+  displayName: string;
+  skinUrl?: string;
+  skinHash?: string;
+  visible: boolean;
+};
+
 const defaultPeerOptions: PeerOptionsType = {
   lerpFactor: 0.6,
   headColor: '#94d0cc',
@@ -21,9 +31,17 @@ class Peer {
   public head: Head;
 
   public name = 'testtesttest';
+  public team = '';
+  public crouching = false;
+  public vehicle = '';
+  public offset: Vector3 | null = null;
+  public visible = true;
+  public skin = '';
   public newPosition: Vector3;
   public newQuaternion: Quaternion;
   public nameMesh: SpriteText;
+  public helmet: Mesh;
+  public equipment: PeerEquipmentType = [];
 
   constructor(public id: string, public options: PeerOptionsType = defaultPeerOptions) {
     const { headDimension } = this.options;
@@ -48,22 +66,71 @@ class Peer {
     }
 
     this.head.mesh.add(this.nameMesh);
+
+    // only the head is drawn, so a helmet is all the armor there is to show
+    this.helmet = new Mesh(
+      new BoxGeometry(headDimension * 1.15, headDimension * 0.5, headDimension * 1.15),
+      new MeshBasicMaterial({ color: '#9e9e9e' }),
+    );
+    this.helmet.position.y += headDimension * 0.35;
+    this.helmet.visible = false;
+    this.head.mesh.add(this.helmet);
   }
 
-  update = (name: string, position: Vector3, quaternion: Quaternion) => {
+  update = (name: string, team: string, crouching: boolean, position: Vector3, quaternion: Quaternion) => {
     this.name = name;
-    this.nameMesh.text = name;
+    this.team = team;
+    this.crouching = crouching;
+    // crouching players can't be spotted by their name through walls
+    this.nameMesh.material.depthTest = crouching;
+    this.updateNameTag();
     this.newPosition = position;
     this.newQuaternion = quaternion;
   };
 
-  tick = (camPos: Vector3) => {
+  // riders are drawn where they sit on what they ride, so the two move as one
+  setVehicle = (vehicle: string, offset: Vector3 | null) => {
+    this.vehicle = offset ? vehicle : '';
+    this.offset = this.vehicle ? offset : null;
+  };
+
+  setProfile = ({ displayName, skinUrl, skinHash, visible }: PeerProfileType) => {
+    this.name = displayName;
+    this.updateNameTag();
+    this.visible = visible;
+    this.head.mesh.visible = visible;
+
+    // the hash changes when a skin is replaced behind the same url
+    const skin = skinUrl ? `${skinUrl}#${skinHash || ''}` : '';
+
+    if (skin !== this.skin) {
+      this.skin = skin;
+      this.head.setSkin(skinUrl);
+    }
+  };
+
+  // teammates are told apart by the team before their name
+  private updateNameTag = () => {
+    this.nameMesh.text = this.team ? `[${this.team}] ${this.name}` : this.name;
+  };
+
+  setEquipment = (equipment: PeerEquipmentType) => {
+    this.equipment = equipment;
+    this.helmet.visible = !!equipment[0];
+  };
+
+  tick = (camPos: Vector3, vehiclePos: Vector3 | null = null) => {
     const { lerpFactor, maxNameDistance } = this.options;
 
-    this.head.mesh.position.lerp(this.newPosition, lerpFactor);
+    if (vehiclePos && this.offset) {
+      this.head.mesh.position.copy(vehiclePos).add(this.offset);
+    } else {
+      this.head.mesh.position.lerp(this.newPosition, lerpFactor);
+    }
     this.head.mesh.quaternion.slerp(this.newQuaternion, lerpFactor);
 
-    this.nameMesh.visible = this.head.mesh.position.distanceTo(camPos) < maxNameDistance;
+    const nameDistance = this.crouching ? maxNameDistance / 4 : maxNameDistance;
+    this.nameMesh.visible = this.head.mesh.position.distanceTo(camPos) < nameDistance;
   };
 
   get mesh() {
@@ -71,4 +138,4 @@ class Peer {
   }
 }
 
-export { Peer };
+export { Peer, PeerProfileType, PeerEquipmentType };
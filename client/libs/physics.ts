@@ -12,6 +12,11 @@ import { BodyOptionsType } from './types';
 
 type TestFunctionType = (vx: number, vy: number, vz: number) => boolean;
 
+// deepest a crouching body lets itself drop off of an edge, same as the server's
+const CROUCH_DROP = 0.5;
+// keeps a body flush against a voxel from counting the voxel past it
+const EDGE_EPSILON = 1e-4;
+
 class Physics {
   public bodies: RigidBody[] = [];
 
@@ -124,6 +129,11 @@ class Physics {
     vec3.set(b.forces, 0, 0, 0);
     vec3.set(b.impulses, 0, 0, 0);
 
+    // crouching on the ground, bodies stop at edges rather than walking off of them
+    if (b.crouching && this.oldResting[1] < 0) {
+      this.stopAtEdges(b, this.dx);
+    }
+
     // cache old position for use in autostepping
     if (b.autoStep) {
       Helper.cloneAABB(this.tmpBox, b.aabb);
@@ -234,6 +244,37 @@ class Physics {
     body.velocity[(axis + 2) % 3] *= scaler;
   };
 
+  stopAtEdges = (b: RigidBody, dx: number[]) => {
+    const { base, max } = b.aabb;
+
+    // whether anything solid is under the body's feet once moved along x and z
+    const hasGround = (ox: number, oz: number) => {
+      const y = Math.floor(base[1] - CROUCH_DROP);
+      for (let vx = Math.floor(base[0] + ox); vx <= Math.floor(max[0] + ox - EDGE_EPSILON); vx++) {
+        for (let vz = Math.floor(base[2] + oz); vz <= Math.floor(max[2] + oz - EDGE_EPSILON); vz++) {
+          if (this.testSolid(vx, y, vz)) return true;
+        }
+      }
+      return false;
+    };
+
+    if (dx[0] && !hasGround(dx[0], 0)) {
+      dx[0] = 0;
+      b.velocity[0] = 0;
+    }
+
+    if (dx[2] && !hasGround(0, dx[2])) {
+      dx[2] = 0;
+      b.velocity[2] = 0;
+    }
+
+    // each on its own might still be fine while both together go over a corner
+    if (!hasGround(dx[0], dx[2])) {
+      dx[0] = dx[2] = 0;
+      b.velocity[0] = b.velocity[2] = 0;
+    }
+  };
+
   processCollisions = (box: AABB, velocity: number[], resting: number[]) => {
     vec3.set(resting, 0, 0, 0);
     return sweep(this.testSolid, box, velocity, function (_: never, axis: number, dir: number, vec: number[]) {
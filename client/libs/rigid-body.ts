@@ -16,6 +16,8 @@ class RigidBody {
   public forces = vec3.create();
   public impulses = vec3.create();
   public sleepFrameCount = 10 | 0;
+  // crouching bodies don't walk off of edges
+  public crouching = false;
 
   constructor(
     public aabb: AABB,
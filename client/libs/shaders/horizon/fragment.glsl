@@ -0,0 +1,17 @@
+uniform vec3 uFogColor;
+uniform vec3 uFogNearColor;
+uniform float uFogNear;
+uniform float uFogFar;
+uniform float uSunlightIntensity;
+uniform float uMinBrightness;
+
+varying vec3 vColor;
+
+void main() {
+  gl_FragColor = vec4(vColor * max(uSunlightIntensity * 0.8, uMinBrightness), 1.0);
+
+  // fog
+  float depth = gl_FragCoord.z / gl_FragCoord.w;
+  float fogFactor = smoothstep(uFogNear, uFogFar, depth);
+  gl_FragColor.rgb = mix(gl_FragColor.rgb, mix(uFogNearColor, uFogColor, fogFactor), fogFactor);
+}
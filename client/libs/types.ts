@@ -66,9 +66,17 @@ export type ServerChunkType = {
   meshes: ServerMeshType[];
   voxels: Uint8Array;
   lights: Uint8Array;
+  empty: boolean;
   json: { voxel: Coords3; type: number };
 };
 
+export type ServerHorizonType = {
+  x: number;
+  z: number;
+  heights: number[];
+  colors: number[];
+};
+
 export type Coords2 = [x: number, z: number];
 export type Coords3 = [x: number, y: number, z: number];
 
@@ -0,0 +1,139 @@
+use std::ops::{Index, IndexMut};
+
+/// A 2D array stored flat, rows of the second axis one after another
+///
+/// Indexing is bounds checked per axis in debug builds only. In release builds an index past
+/// the end of an axis is caught only if it lands past the end of the whole array.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Array2<T> {
+    pub data: Vec<T>,
+    pub shape: [usize; 2],
+}
+
+impl<T: Copy> Array2<T> {
+    pub fn new(shape: [usize; 2], default: T) -> Self {
+        Self {
+            data: vec![default; shape[0] * shape[1]],
+            shape,
+        }
+    }
+
+    /// Index of a cell into `data`
+    #[inline]
+    pub fn index(&self, x: usize, z: usize) -> usize {
+        debug_assert!(
+            self.contains(x, z),
+            "{:?} out of an array of {:?}",
+            [x, z],
+            self.shape
+        );
+
+        x * self.shape[1] + z
+    }
+
+    #[inline]
+    pub fn contains(&self, x: usize, z: usize) -> bool {
+        x < self.shape[0] && z < self.shape[1]
+    }
+}
+
+impl<T: Copy> Index<[usize; 2]> for Array2<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, [x, z]: [usize; 2]) -> &T {
+        &self.data[Array2::index(self, x, z)]
+    }
+}
+
+impl<T: Copy> IndexMut<[usize; 2]> for Array2<T> {
+    #[inline]
+    fn index_mut(&mut self, [x, z]: [usize; 2]) -> &mut T {
+        let index = Array2::index(self, x, z);
+        &mut self.data[index]
+    }
+}
+
+/// A 3D array stored flat, the last axis varying fastest, like the voxels of a chunk
+///
+/// Indexing is bounds checked per axis in debug builds only. In release builds an index past
+/// the end of an axis is caught only if it lands past the end of the whole array.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Array3<T> {
+    pub data: Vec<T>,
+    pub shape: [usize; 3],
+}
+
+impl<T: Copy> Array3<T> {
+    pub fn new(shape: [usize; 3], default: T) -> Self {
+        Self {
+            data: vec![default; shape[0] * shape[1] * shape[2]],
+            shape,
+        }
+    }
+
+    /// Index of a cell into `data`
+    #[inline]
+    pub fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        debug_assert!(
+            self.contains(x, y, z),
+            "{:?} out of an array of {:?}",
+            [x, y, z],
+            self.shape
+        );
+
+        (x * self.shape[1] + y) * self.shape[2] + z
+    }
+
+    #[inline]
+    pub fn contains(&self, x: usize, y: usize, z: usize) -> bool {
+        x < self.shape[0] && y < self.shape[1] && z < self.shape[2]
+    }
+}
+
+impl<T: Copy> Index<[usize; 3]> for Array3<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, [x, y, z]: [usize; 3]) -> &T {
+        &self.data[Array3::index(self, x, y, z)]
+    }
+}
+
+impl<T: Copy> IndexMut<[usize; 3]> for Array3<T> {
+    #[inline]
+    fn index_mut(&mut self, [x, y, z]: [usize; 3]) -> &mut T {
+        let index = Array3::index(self, x, y, z);
+        &mut self.data[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn array_works() {
+        let mut data = Array3::new([3, 5, 3], 0);
+
+        data[[1, 2, 2]] = 5;
+        assert_eq!(data[[1, 2, 2]], 5);
+        assert_eq!(data.data[15 + 6 + 2], 5);
+
+        assert!(data.contains(2, 4, 2));
+        assert!(!data.contains(2, 5, 0));
+
+        let mut heights = Array2::new([4, 2], 0);
+        heights[[3, 1]] = 7;
+        assert_eq!(heights.data[7], 7);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn array_checks_axes() {
+        // lands within the data, but past the end of the last axis
+        let data = Array3::new([3, 5, 3], 0);
+        let _ = data[[1, 2, 3]];
+    }
+}
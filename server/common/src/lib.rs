@@ -1,6 +1,6 @@
 pub mod aabb;
+pub mod array;
 pub mod math;
-pub mod ndarray;
 pub mod noise;
 pub mod quaternion;
 pub mod types;
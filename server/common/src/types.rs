@@ -15,6 +15,110 @@ pub struct UV {
     pub end_v: f32,
 }
 
+/// How a block face is drawn beyond its texture
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceEffect {
+    /// Drawn at full brightness, whatever the light around it
+    #[serde(default)]
+    pub emissive: bool,
+
+    /// Frames stacked top to bottom in the texture, played in a loop if more than one
+    #[serde(default)]
+    pub frames: u32,
+
+    /// Seconds each frame is shown for
+    #[serde(default)]
+    pub frame_time: f32,
+}
+
+fn default_speed_multiplier() -> f32 {
+    1.0
+}
+
+fn default_effect_level() -> u32 {
+    1
+}
+
+/// Where on a player a piece of equipment is worn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EquipmentSlot {
+    Head,
+    Chest,
+    Legs,
+    Feet,
+}
+
+impl EquipmentSlot {
+    pub const ALL: [EquipmentSlot; 4] = [
+        EquipmentSlot::Head,
+        EquipmentSlot::Chest,
+        EquipmentSlot::Legs,
+        EquipmentSlot::Feet,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "head" => Some(EquipmentSlot::Head),
+            "chest" => Some(EquipmentSlot::Chest),
+            "legs" => Some(EquipmentSlot::Legs),
+            "feet" => Some(EquipmentSlot::Feet),
+            _ => None,
+        }
+    }
+}
+
+/// Protection an item gives when worn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Armor {
+    pub slot: EquipmentSlot,
+
+    /// Each point takes a share of the damage off, up to a cap
+    pub points: f32,
+}
+
+/// A status effect an item gives to whoever uses it up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusEffect {
+    pub effect: String,
+    pub seconds: f32,
+
+    #[serde(default = "default_effect_level")]
+    pub level: u32,
+}
+
+/// What a block needs next to it to be placed and to stay, popping off once it's gone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Support {
+    /// A solid block under it
+    Solid,
+    /// A block plants grow on under it, like dirt under grass
+    Soil,
+    /// A solid block under it or on any of its sides, like a torch on a wall
+    Attached,
+    /// One of these blocks under it, like farmland under wheat
+    Blocks(Vec<String>),
+}
+
+/// Another voxel a block takes up, filled with a block of its own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockPart {
+    /// Name of the block filling the part
+    pub block: String,
+
+    /// Where the part is from the voxel the block is placed at, turned along with it
+    pub offset: [i32; 3],
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Block {
@@ -34,6 +138,104 @@ pub struct Block {
     pub is_plantable: bool,
     pub transparent_standalone: bool,
 
+    #[serde(default)]
+    pub container_size: usize,
+
+    #[serde(default)]
+    pub power_level: u32,
+
+    #[serde(default)]
+    pub conducts_power: bool,
+
+    #[serde(default)]
+    pub consumes_power: bool,
+
+    #[serde(default)]
+    pub is_tillable: bool,
+
+    #[serde(default)]
+    pub is_farmland: bool,
+
+    #[serde(default)]
+    pub growth_stages: u32,
+
+    #[serde(default)]
+    pub is_portal: bool,
+
+    /// Whether this block holds up the leaves around it
+    #[serde(default)]
+    pub is_log: bool,
+
+    /// Whether this block decays once it's too far from any log
+    #[serde(default)]
+    pub is_leaves: bool,
+
+    /// Extra light levels lost when light passes through this transparent block
+    #[serde(default)]
+    pub light_reduce: u32,
+
+    /// Collision boxes as `[x0, y0, z0, x1, y1, z1]` within the voxel, may reach above it.
+    /// Defaults to a full cube for solid blocks, and nothing for the rest.
+    #[serde(default)]
+    pub collision_boxes: Option<Vec<[f32; 6]>>,
+
+    /// Whether bodies inside this block can climb it like a ladder
+    #[serde(default)]
+    pub is_climbable: bool,
+
+    /// Multiplier on the speed of bodies inside or standing on this block
+    #[serde(default = "default_speed_multiplier")]
+    pub speed_multiplier: f32,
+
+    /// Whether bodies inside or standing on this block are kept from jumping
+    #[serde(default)]
+    pub suppresses_jump: bool,
+
+    /// Friction of bodies standing on this block in place of their own, low for ice
+    #[serde(default)]
+    pub friction: Option<f32>,
+
+    /// How much bodies bounce back off of this block in place of their own, high for slime
+    #[serde(default)]
+    pub restitution: Option<f32>,
+
+    /// Whether this block falls when there's nothing under it to rest on, like sand
+    #[serde(default)]
+    pub has_gravity: bool,
+
+    /// What this block needs next to it, dropping as an item when that's taken away
+    #[serde(default)]
+    pub support: Option<Support>,
+
+    /// Other voxels this block takes up, placed and broken along with it, like the top of a door
+    #[serde(default)]
+    pub parts: Vec<BlockPart>,
+
+    /// Name of the block this is a part of, only ever placed and broken along with it
+    #[serde(default)]
+    pub part_of: Option<String>,
+
+    /// Transparent blocks of the same render group hide the faces between each other, as a
+    /// block does with itself when not set
+    #[serde(default)]
+    pub render_group: Option<String>,
+
+    /// Emissive or animated faces, keyed by the same sides as `textures`
+    #[serde(default)]
+    pub effects: HashMap<String, FaceEffect>,
+
+    /// Status effects given to a player using this item up, like drinking a potion
+    #[serde(default)]
+    pub status_effects: Vec<StatusEffect>,
+
+    /// Whether and where this item can be worn, and how much it protects
+    #[serde(default)]
+    pub armor: Option<Armor>,
+
+    /// Points of experience dropped when mined by a player in survival
+    #[serde(default)]
+    pub experience: u32,
+
     #[serde(default)]
     pub textures: HashMap<String, String>,
 }
@@ -45,6 +247,12 @@ pub struct MeshType {
     pub uvs: Vec<f32>,
     pub aos: Vec<i32>,
     pub lights: Vec<i32>,
+    /// Face normals packed per vertex, see `Mesher::pack_normal`
+    pub normals: Vec<i32>,
+    /// Id of the block each vertex belongs to
+    pub block_ids: Vec<i32>,
+    /// Face effects packed per vertex, see `Mesher::pack_effect`
+    pub effects: Vec<i32>,
 }
 
 pub type GetVoxel<'a> = &'a dyn Fn(i32, i32, i32) -> bool;
@@ -4,7 +4,7 @@ use std::ops::{Index, IndexMut};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Eq, PartialEq, Clone, Default, Hash)]
+#[derive(Debug, Eq, PartialEq, Clone, Default, Hash, Serialize, Deserialize)]
 pub struct Vec2<T>(pub T, pub T);
 
 impl<T: Copy + 'static> Vec2<T> {
@@ -0,0 +1,121 @@
+//! Baselines for meshing, lighting, block updates and voxel lookups on flat, hilly and
+//! cave-heavy chunks.
+//!
+//! Run with `cargo bench -p server_core --features bench`, and pass `--save-baseline <name>`
+//! before a change and `--baseline <name>` after it to compare the two.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+use server_common::vec::Vec2;
+use server_core::{
+    engine::chunks::Chunks,
+    gen::mesher::Mesher,
+    test_utils::{self, AIR, TORCH},
+};
+
+const RADIUS: i32 = 2;
+
+fn terrains() -> Vec<(&'static str, fn() -> Chunks)> {
+    vec![
+        ("flat", || test_utils::chunks(RADIUS, 8)),
+        ("hilly", || test_utils::hilly_chunks(RADIUS)),
+        ("caves", || test_utils::cave_chunks(RADIUS)),
+    ]
+}
+
+fn mesh_chunk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mesh_chunk");
+
+    for (name, create) in terrains() {
+        let chunks = create();
+        let chunk = chunks.get_chunk(&Vec2(0, 0)).unwrap();
+
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                (0..chunks.config.sub_chunks).for_each(|sub_chunk| {
+                    for &transparent in [false, true].iter() {
+                        Mesher::mesh_chunk(
+                            chunk,
+                            transparent,
+                            sub_chunk,
+                            &chunks.config,
+                            &chunks.registry,
+                        );
+                    }
+                });
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn propagate_chunk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("propagate_chunk");
+
+    for (name, create) in terrains() {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter_batched(
+                create,
+                |mut chunks| {
+                    chunks.propagate_chunk(&Vec2(0, 0));
+                    chunks
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update");
+
+    for (name, create) in terrains() {
+        let mut chunks = create();
+        let vy = chunks.get_max_height(3, 3) as i32 + 1;
+
+        // placing and taking away a torch relights and remeshes around it every time
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                chunks.update(3, vy, 3, TORCH, 0, 0);
+                chunks.update(3, vy, 3, AIR, 0, 0);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn get_voxel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_voxel");
+
+    for (name, create) in terrains() {
+        let chunks = create();
+        let size = chunks.config.chunk_size as i32;
+        let max_height = chunks.config.max_height as i32;
+
+        // a sweep across chunk borders, looking chunks up by their coordinates on every read
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                let mut solid = 0;
+                for vx in -size..size {
+                    for vz in -size..size {
+                        for vy in 0..max_height {
+                            if chunks.get_voxel_by_voxel(vx, vy, vz) != AIR {
+                                solid += 1;
+                            }
+                        }
+                    }
+                }
+                solid
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, mesh_chunk, propagate_chunk, update, get_voxel);
+criterion_main!(benches);
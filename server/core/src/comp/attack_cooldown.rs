@@ -0,0 +1,33 @@
+use specs::{Component, VecStorage};
+
+use crate::constants::ATTACK_COOLDOWN;
+
+/// Time left before a player can land another melee hit, so that clients can't swing as fast
+/// as they send messages
+#[derive(Default, Component)]
+#[storage(VecStorage)]
+pub struct AttackCooldown(pub f32);
+
+impl AttackCooldown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.0 <= 0.0
+    }
+
+    /// Start cooling down from an attack, returns false if not ready to attack yet
+    pub fn attack(&mut self) -> bool {
+        if !self.is_ready() {
+            return false;
+        }
+
+        self.0 = ATTACK_COOLDOWN;
+        true
+    }
+
+    pub fn tick(&mut self, delta: f32) {
+        self.0 = (self.0 - delta).max(0.0);
+    }
+}
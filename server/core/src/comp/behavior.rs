@@ -0,0 +1,172 @@
+use specs::{Component, Entity, VecStorage};
+
+use serde::{Deserialize, Serialize};
+
+use server_common::vec::Vec3;
+
+/// Behavior configurations of a passive mob, read from the entity prototype
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BehaviorOptions {
+    /// Names of the blocks this mob follows players for and breeds with
+    pub food: Vec<String>,
+    pub wander_radius: i32,
+    pub idle_time: f32,       // s
+    pub flee_time: f32,       // s
+    pub flee_distance: f32,   // voxels
+    pub follow_distance: f32, // voxels
+    pub love_time: f32,       // s
+    pub breed_cooldown: f32,  // s
+    pub child_scale: f32,
+    pub grow_up_time: f32, // s
+}
+
+impl Default for BehaviorOptions {
+    fn default() -> Self {
+        Self {
+            food: vec![],
+            wander_radius: 6,
+            idle_time: 8.0,
+            flee_time: 4.0,
+            flee_distance: 8.0,
+            follow_distance: 8.0,
+            love_time: 30.0,
+            breed_cooldown: 300.0,
+            child_scale: 0.5,
+            grow_up_time: 600.0,
+        }
+    }
+}
+
+/// What a passive mob is currently up to, in order of priority
+#[derive(Debug, Clone, PartialEq)]
+pub enum BehaviorState {
+    Flee,
+    Breed(Entity),
+    Follow(Entity),
+    Wander,
+    Idle,
+}
+
+/// A simple state machine driving a passive mob, layered on top of path finding.
+///
+/// The behavior system decides on a `goal` every tick, and the path finding system
+/// walks the entity towards it. No goal means the entity stands still.
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct Behavior {
+    pub state: BehaviorState,
+    pub options: BehaviorOptions,
+    pub goal: Option<Vec3<f32>>,
+
+    /// Time left in the current state
+    pub timer: f32,
+    /// Time left willing to breed, set by feeding
+    pub love: f32,
+    /// Time left before being able to breed again
+    pub cooldown: f32,
+    /// Time left before a child grows up, 0 for adults
+    pub growth: f32,
+}
+
+impl Behavior {
+    pub fn new(options: BehaviorOptions) -> Self {
+        Self {
+            state: BehaviorState::Idle,
+            options,
+            goal: None,
+            timer: 0.0,
+            love: 0.0,
+            cooldown: 0.0,
+            growth: 0.0,
+        }
+    }
+
+    /// Create the behavior of a newborn, which has to grow up before breeding
+    pub fn child(options: BehaviorOptions) -> Self {
+        let growth = options.grow_up_time;
+
+        Self {
+            growth,
+            ..Self::new(options)
+        }
+    }
+
+    pub fn is_child(&self) -> bool {
+        self.growth > 0.0
+    }
+
+    /// Whether this mob is looking for a partner
+    pub fn can_breed(&self) -> bool {
+        self.love > 0.0 && self.cooldown <= 0.0 && !self.is_child()
+    }
+
+    /// Whether a block is food to this mob
+    pub fn is_food(&self, name: &str) -> bool {
+        self.options.food.iter().any(|f| f == name)
+    }
+
+    /// Switch to a new state, clearing the goal
+    pub fn set_state(&mut self, state: BehaviorState, timer: f32) {
+        self.state = state;
+        self.timer = timer;
+        self.goal = None;
+    }
+
+    /// Run away from where the damage came from
+    pub fn hurt(&mut self, position: &Vec3<f32>, from: &Vec3<f32>) {
+        let mut away = position.sub(from);
+        away.1 = 0.0;
+
+        let away = if away.len() > 0.0 {
+            away.normalize()
+        } else {
+            Vec3(1.0, 0.0, 0.0)
+        };
+
+        self.set_state(BehaviorState::Flee, self.options.flee_time);
+        self.goal = Some(position.add(&away.scale(self.options.flee_distance)));
+    }
+
+    /// Feed this mob, making adults ready to breed
+    ///
+    /// Returns whether the food was accepted.
+    pub fn feed(&mut self) -> bool {
+        if self.is_child() || self.cooldown > 0.0 || self.love > 0.0 {
+            return false;
+        }
+
+        self.love = self.options.love_time;
+        true
+    }
+
+    /// Mark a successful breeding, starting the cooldown
+    pub fn bred(&mut self) {
+        self.love = 0.0;
+        self.cooldown = self.options.breed_cooldown;
+        self.set_state(BehaviorState::Idle, self.options.idle_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feed_and_breed_works() {
+        let mut behavior = Behavior::new(BehaviorOptions::default());
+        assert!(!behavior.can_breed());
+
+        assert!(behavior.feed());
+        assert!(behavior.can_breed());
+        assert!(!behavior.feed());
+
+        behavior.bred();
+        assert!(!behavior.can_breed());
+        assert!(!behavior.feed());
+
+        let mut child = Behavior::child(BehaviorOptions::default());
+        assert!(child.is_child());
+        assert!(!child.feed());
+    }
+}
@@ -117,9 +117,13 @@ impl Brain {
         let angle = dx.atan2(dz);
         self.state.heading = angle;
 
+        // mobs keep their heads above fluids
+        body.swim_up = body.in_fluid;
+
         // jumping
         let on_ground = body.at_rest_y() < 0.0;
-        let can_jump = on_ground || self.state.jump_count < self.options.air_jumps;
+        let can_jump = !body.modifiers.suppress_jump
+            && (on_ground || self.state.jump_count < self.options.air_jumps);
         if on_ground {
             self.state.is_jumping = false;
             self.state.jump_count = 0;
@@ -0,0 +1,56 @@
+use specs::{Builder, Component, VecStorage};
+
+use server_common::{aabb::Aabb, vec::Vec3};
+
+use crate::{
+    comp::{inventory::ItemStack, rigidbody::RigidBody},
+    constants::{DROPPED_ITEM_SIZE, ITEM_PICKUP_DELAY},
+};
+
+/// A stack of items lying in the world, picked up by players walking over it
+#[derive(Debug, Component)]
+#[storage(VecStorage)]
+pub struct DroppedItem {
+    pub stack: ItemStack,
+
+    /// Time left before it can be picked up
+    pub pickup_delay: f32,
+
+    /// Time it has been lying around for, it's gone after a while
+    pub age: f32,
+}
+
+impl DroppedItem {
+    pub fn new(stack: ItemStack) -> Self {
+        Self {
+            stack,
+            pickup_delay: ITEM_PICKUP_DELAY,
+            age: 0.0,
+        }
+    }
+
+    /// Add the components of a dropped item to an entity being built, centered on `position`
+    ///
+    /// Works with both `World::create_entity` and `LazyUpdate::create_entity`.
+    pub fn build_entity<B: Builder>(
+        builder: B,
+        stack: ItemStack,
+        position: &Vec3<f32>,
+        dimension: f32,
+    ) -> B {
+        let size = DROPPED_ITEM_SIZE * dimension;
+        let base = Vec3(position.0 - size / 2.0, position.1, position.2 - size / 2.0);
+
+        builder
+            .with(RigidBody::new(
+                Aabb::new(&base, &Vec3(size, size, size)),
+                0.0,
+                1.0,
+                1.0,
+                0.0,
+                1.0,
+                false,
+            ))
+            .with(Self::new(stack))
+    }
+}
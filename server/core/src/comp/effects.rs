@@ -0,0 +1,166 @@
+use serde::Serialize;
+use specs::{Component, VecStorage};
+
+use crate::constants::MAX_EFFECT_LEVEL;
+
+/// What a status effect does to the entity under it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EffectKind {
+    /// Moves faster, by a fifth per level
+    Speed,
+    /// Moves slower, by a sixth per level
+    Slowness,
+    /// Sees in the dark
+    NightVision,
+    /// Deals more damage, by a third per level
+    Strength,
+    /// Takes less damage, by a fifth per level
+    Resistance,
+}
+
+impl EffectKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "speed" => Some(EffectKind::Speed),
+            "slowness" => Some(EffectKind::Slowness),
+            "night_vision" => Some(EffectKind::NightVision),
+            "strength" => Some(EffectKind::Strength),
+            "resistance" => Some(EffectKind::Resistance),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EffectKind::Speed => "speed",
+            EffectKind::Slowness => "slowness",
+            EffectKind::NightVision => "night_vision",
+            EffectKind::Strength => "strength",
+            EffectKind::Resistance => "resistance",
+        }
+    }
+}
+
+/// A status effect wearing off over time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Effect {
+    pub kind: EffectKind,
+    /// How strong the effect is, starting at 1
+    pub level: u32,
+    /// Seconds left before the effect wears off
+    pub remaining: f32,
+}
+
+/// Status effects an entity is under, at most one of each kind
+#[derive(Debug, Default, Component)]
+#[storage(VecStorage)]
+pub struct Effects {
+    pub list: Vec<Effect>,
+
+    /// Whether the effects were added to or wore off since last sent to the client
+    pub changed: bool,
+}
+
+impl Effects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an effect. Effects of a kind already in effect stack on it, adding up their levels
+    /// and lasting as long as the longer of the two.
+    pub fn add(&mut self, kind: EffectKind, level: u32, seconds: f32) {
+        if level == 0 || seconds <= 0.0 {
+            return;
+        }
+
+        match self.list.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => {
+                effect.level = (effect.level + level).min(MAX_EFFECT_LEVEL);
+                effect.remaining = effect.remaining.max(seconds);
+            }
+            None => self.list.push(Effect {
+                kind,
+                level: level.min(MAX_EFFECT_LEVEL),
+                remaining: seconds,
+            }),
+        }
+
+        self.changed = true;
+    }
+
+    /// Take off an effect, or every effect without a kind
+    pub fn clear(&mut self, kind: Option<EffectKind>) {
+        let before = self.list.len();
+        self.list
+            .retain(|effect| kind.map_or(false, |kind| effect.kind != kind));
+        self.changed |= self.list.len() != before;
+    }
+
+    /// Wear the effects off over time
+    pub fn tick(&mut self, delta: f32) {
+        let before = self.list.len();
+
+        self.list
+            .iter_mut()
+            .for_each(|effect| effect.remaining -= delta);
+        self.list.retain(|effect| effect.remaining > 0.0);
+
+        self.changed |= self.list.len() != before;
+    }
+
+    /// Level of an effect, 0 if not in effect
+    pub fn level(&self, kind: EffectKind) -> u32 {
+        self.list
+            .iter()
+            .find(|effect| effect.kind == kind)
+            .map_or(0, |effect| effect.level)
+    }
+
+    /// Multiplier on how fast the entity moves
+    pub fn speed_multiplier(&self) -> f32 {
+        let speed = 1.0 + self.level(EffectKind::Speed) as f32 / 5.0;
+        let slowness = 1.0 - self.level(EffectKind::Slowness) as f32 / 6.0;
+        speed * slowness.max(0.0)
+    }
+
+    /// Multiplier on the damage the entity deals
+    pub fn damage_dealt_multiplier(&self) -> f32 {
+        1.0 + self.level(EffectKind::Strength) as f32 / 3.0
+    }
+
+    /// Multiplier on the damage the entity takes
+    pub fn damage_taken_multiplier(&self) -> f32 {
+        (1.0 - self.level(EffectKind::Resistance) as f32 / 5.0).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effects_work() {
+        let mut effects = Effects::new();
+
+        effects.add(EffectKind::Speed, 1, 10.0);
+        effects.add(EffectKind::Speed, 2, 5.0);
+        assert_eq!(effects.level(EffectKind::Speed), 3);
+        assert_eq!(effects.list[0].remaining, 10.0);
+        assert!((effects.speed_multiplier() - 1.6).abs() < 1e-5);
+
+        // levels are capped
+        effects.add(EffectKind::Slowness, 9, 2.0);
+        assert_eq!(effects.level(EffectKind::Slowness), MAX_EFFECT_LEVEL);
+        assert!((effects.speed_multiplier() - 1.6 / 6.0).abs() < 1e-5);
+
+        effects.changed = false;
+        effects.tick(3.0);
+        assert_eq!(effects.level(EffectKind::Slowness), 0);
+        assert!(effects.changed);
+
+        effects.clear(None);
+        assert!(effects.list.is_empty());
+    }
+}
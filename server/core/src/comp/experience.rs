@@ -0,0 +1,115 @@
+use specs::{Builder, Component, VecStorage};
+
+use server_common::{aabb::Aabb, vec::Vec3};
+
+use crate::{
+    comp::rigidbody::RigidBody,
+    constants::{EXPERIENCE_LEVEL_BASE, EXPERIENCE_LEVEL_GROWTH, EXPERIENCE_ORB_SIZE},
+};
+
+/// Experience a player has gathered, counted in points and leveled up along a curve
+#[derive(Debug, Default, Component)]
+#[storage(VecStorage)]
+pub struct Experience {
+    pub points: u32,
+}
+
+impl Experience {
+    pub fn new(points: u32) -> Self {
+        Self { points }
+    }
+
+    pub fn add(&mut self, points: u32) {
+        self.points = self.points.saturating_add(points);
+    }
+
+    /// Level reached with the points gathered so far
+    pub fn level(&self) -> u32 {
+        let mut level = 0;
+
+        while Self::points_for_level(level + 1) <= self.points {
+            level += 1;
+        }
+
+        level
+    }
+
+    /// How far along the way to the next level, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        let level = self.level();
+        let start = Self::points_for_level(level);
+        let end = Self::points_for_level(level + 1);
+
+        (self.points - start) as f32 / (end - start) as f32
+    }
+
+    /// Points needed in total to reach a level, each level taking a bit more than the last
+    pub fn points_for_level(level: u32) -> u32 {
+        EXPERIENCE_LEVEL_BASE * level
+            + EXPERIENCE_LEVEL_GROWTH * level * level.saturating_sub(1) / 2
+    }
+}
+
+/// Experience lying in the world, drifting toward the players nearby to be taken in
+#[derive(Debug, Component)]
+#[storage(VecStorage)]
+pub struct ExperienceOrb {
+    pub points: u32,
+
+    /// Time it has been lying around for, it's gone after a while
+    pub age: f32,
+}
+
+impl ExperienceOrb {
+    pub fn new(points: u32) -> Self {
+        Self { points, age: 0.0 }
+    }
+
+    /// Add the components of an orb to an entity being built, centered on `position`
+    ///
+    /// Works with both `World::create_entity` and `LazyUpdate::create_entity`.
+    pub fn build_entity<B: Builder>(
+        builder: B,
+        points: u32,
+        position: &Vec3<f32>,
+        dimension: f32,
+    ) -> B {
+        let size = EXPERIENCE_ORB_SIZE * dimension;
+        let base = Vec3(position.0 - size / 2.0, position.1, position.2 - size / 2.0);
+
+        builder
+            .with(RigidBody::new(
+                Aabb::new(&base, &Vec3(size, size, size)),
+                0.0,
+                1.0,
+                1.0,
+                0.0,
+                1.0,
+                false,
+            ))
+            .with(Self::new(points))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn experience_levels_work() {
+        assert_eq!(Experience::points_for_level(0), 0);
+        assert_eq!(Experience::points_for_level(1), EXPERIENCE_LEVEL_BASE);
+
+        let mut experience = Experience::new(0);
+        assert_eq!(experience.level(), 0);
+
+        experience.add(Experience::points_for_level(3));
+        assert_eq!(experience.level(), 3);
+        assert_eq!(experience.progress(), 0.0);
+
+        // each level takes more points than the last
+        let step =
+            |level| Experience::points_for_level(level + 1) - Experience::points_for_level(level);
+        assert!(step(4) > step(3));
+    }
+}
@@ -0,0 +1,20 @@
+use specs::{Component, VecStorage};
+
+/// A block with gravity in mid-fall, placed back into the world once its body lands
+#[derive(Debug, Component)]
+#[storage(VecStorage)]
+pub struct FallingBlock {
+    pub id: u32,
+    pub rotation: u32,
+    pub y_rotation: u32,
+}
+
+impl FallingBlock {
+    pub fn new(id: u32, rotation: u32, y_rotation: u32) -> Self {
+        Self {
+            id,
+            rotation,
+            y_rotation,
+        }
+    }
+}
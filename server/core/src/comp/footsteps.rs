@@ -0,0 +1,67 @@
+use specs::{Component, VecStorage};
+
+use server_common::vec::Vec3;
+
+/// Tracks how far an entity has walked on the ground, so footsteps can be played
+/// every few voxels.
+#[derive(Component, Default)]
+#[storage(VecStorage)]
+pub struct Footsteps {
+    pub last: Option<Vec3<f32>>,
+    pub walked: f32,
+}
+
+impl Footsteps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record where the entity is this tick
+    ///
+    /// Returns whether a footstep should be played. Movements longer than a stride, such as
+    /// teleports, don't count as walking.
+    pub fn step(&mut self, position: &Vec3<f32>, on_ground: bool, stride: f32) -> bool {
+        let moved = self
+            .last
+            .replace(position.clone())
+            .map(|last| {
+                let mut diff = position.sub(&last);
+                diff.1 = 0.0;
+                diff.len()
+            })
+            .unwrap_or(0.0);
+
+        if !on_ground || moved > stride {
+            self.walked = 0.0;
+            return false;
+        }
+
+        self.walked += moved;
+
+        if self.walked < stride {
+            return false;
+        }
+
+        self.walked -= stride;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_works() {
+        let mut footsteps = Footsteps::new();
+
+        assert!(!footsteps.step(&Vec3(0.0, 0.0, 0.0), true, 1.0));
+        assert!(!footsteps.step(&Vec3(0.6, 0.0, 0.0), true, 1.0));
+        assert!(footsteps.step(&Vec3(1.2, 0.0, 0.0), true, 1.0));
+
+        // jumping or teleporting doesn't make a sound
+        assert!(!footsteps.step(&Vec3(1.9, 2.0, 0.0), false, 1.0));
+        assert!(!footsteps.step(&Vec3(10.0, 2.0, 0.0), true, 1.0));
+        assert_eq!(footsteps.walked, 0.0);
+    }
+}
@@ -0,0 +1,77 @@
+use specs::{Component, VecStorage};
+
+use crate::constants::INVULNERABILITY_TIME;
+
+/// Hit points of a player or a mob
+#[derive(Debug, Component)]
+#[storage(VecStorage)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+
+    /// Time left before damage can be taken again
+    pub invulnerable: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            invulnerable: 0.0,
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+
+    /// Take damage, returns false if the damage was ignored
+    pub fn damage(&mut self, amount: f32) -> bool {
+        if amount <= 0.0 || self.invulnerable > 0.0 || self.is_dead() {
+            return false;
+        }
+
+        self.current = (self.current - amount).max(0.0);
+        self.invulnerable = INVULNERABILITY_TIME;
+
+        true
+    }
+
+    /// Restore some health, up to the maximum
+    pub fn heal(&mut self, amount: f32) {
+        if self.is_dead() {
+            return;
+        }
+
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    /// Count down the invulnerability
+    pub fn tick(&mut self, delta: f32) {
+        self.invulnerable = (self.invulnerable - delta).max(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn damage_works() {
+        let mut health = Health::new(10.0);
+
+        assert!(health.damage(4.0));
+        assert_eq!(health.current, 6.0);
+
+        // still invulnerable from the last hit
+        assert!(!health.damage(4.0));
+
+        health.tick(INVULNERABILITY_TIME);
+        assert!(health.damage(100.0));
+        assert!(health.is_dead());
+
+        health.heal(5.0);
+        assert!(health.is_dead());
+    }
+}
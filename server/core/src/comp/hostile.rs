@@ -0,0 +1,72 @@
+use specs::{Component, Entity, VecStorage};
+
+use serde::{Deserialize, Serialize};
+
+use server_common::vec::Vec3;
+
+/// Combat configurations of a hostile mob, read from the entity prototype
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostileOptions {
+    pub attack_damage: f32,
+    pub attack_knockback: f32, // voxels/s
+    pub attack_range: f32,     // voxels
+    pub attack_cooldown: f32,  // s
+    pub chase_distance: f32,   // voxels
+    /// How long the last seen position of a target is chased after losing sight of it
+    pub memory_time: f32, // s
+}
+
+impl Default for HostileOptions {
+    fn default() -> Self {
+        Self {
+            attack_damage: 2.0,
+            attack_knockback: 4.0,
+            attack_range: 1.5,
+            attack_cooldown: 1.0,
+            chase_distance: 16.0,
+            memory_time: 5.0,
+        }
+    }
+}
+
+/// A mob that chases down whatever it sees and attacks it up close
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct Hostile {
+    pub options: HostileOptions,
+    pub target: Option<Entity>,
+    /// Where the target was last seen, which is chased through path finding
+    pub goal: Option<Vec3<f32>>,
+
+    /// Time left before the target is forgotten
+    pub memory: f32,
+    /// Time left before the next attack
+    pub cooldown: f32,
+}
+
+impl Hostile {
+    pub fn new(options: HostileOptions) -> Self {
+        Self {
+            options,
+            target: None,
+            goal: None,
+            memory: 0.0,
+            cooldown: 0.0,
+        }
+    }
+
+    /// Lock onto a target seen at a position
+    pub fn spot(&mut self, target: Entity, position: &Vec3<f32>) {
+        self.target = Some(target);
+        self.goal = Some(position.to_owned());
+        self.memory = self.options.memory_time;
+    }
+
+    /// Give up on the current target
+    pub fn forget(&mut self) {
+        self.target = None;
+        self.goal = None;
+        self.memory = 0.0;
+    }
+}
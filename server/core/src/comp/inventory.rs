@@ -0,0 +1,345 @@
+use serde::{Deserialize, Serialize};
+use specs::{Component, VecStorage};
+
+use server_common::types::EquipmentSlot;
+
+use crate::constants::MAX_STACK_SIZE;
+
+/// A stack of items of the same type, referenced by their block id.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub id: u32,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn new(id: u32, count: u32) -> Self {
+        Self { id, count }
+    }
+}
+
+/// A fixed-size list of item slots, used by players and container blocks.
+#[derive(Debug, Clone, Default, Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Inventory {
+    pub slots: Vec<Option<ItemStack>>,
+
+    /// Slot of the item currently held, only meaningful for players
+    #[serde(default)]
+    pub selected: usize,
+
+    /// Items worn, indexed by `EquipmentSlot`, only meaningful for players
+    #[serde(default)]
+    pub equipment: [Option<ItemStack>; 4],
+}
+
+impl Inventory {
+    pub fn new(size: usize) -> Self {
+        Self {
+            slots: vec![None; size],
+            selected: 0,
+            equipment: Default::default(),
+        }
+    }
+
+    /// Number of slots in this inventory
+    pub fn size(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Get the item stack at a slot
+    ///
+    /// Returns None if the slot is empty or out of range.
+    pub fn get(&self, slot: usize) -> Option<&ItemStack> {
+        self.slots.get(slot).and_then(|s| s.as_ref())
+    }
+
+    /// Get the item stack currently held
+    pub fn held(&self) -> Option<&ItemStack> {
+        self.get(self.selected)
+    }
+
+    /// Select the slot of the held item, returns false if the slot is out of range
+    pub fn select(&mut self, slot: usize) -> bool {
+        if slot >= self.size() {
+            return false;
+        }
+
+        self.selected = slot;
+        true
+    }
+
+    /// Whether every slot is empty
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(|s| s.is_none())
+    }
+
+    /// Get the item worn in an equipment slot
+    pub fn equipped(&self, slot: EquipmentSlot) -> Option<&ItemStack> {
+        self.equipment[slot.index()].as_ref()
+    }
+
+    /// Wear one item of a slot in an equipment slot, putting back what was worn there.
+    ///
+    /// Returns false, changing nothing, if there's no room for what was worn before.
+    pub fn equip(&mut self, from: usize, slot: EquipmentSlot) -> bool {
+        let worn = match self.take(from, Some(1)) {
+            Some(worn) => worn,
+            None => return false,
+        };
+
+        if let Some(previous) = self.equipment[slot.index()].take() {
+            if let Some(rest) = self.add(previous) {
+                self.put(from, worn);
+                self.equipment[slot.index()] = Some(rest);
+                return false;
+            }
+        }
+
+        self.equipment[slot.index()] = Some(worn);
+        true
+    }
+
+    /// Take off what's worn in an equipment slot, returns false if there's no room for it
+    pub fn unequip(&mut self, slot: EquipmentSlot) -> bool {
+        let worn = match self.equipment[slot.index()].take() {
+            Some(worn) => worn,
+            None => return false,
+        };
+
+        match self.add(worn) {
+            None => true,
+            Some(rest) => {
+                self.equipment[slot.index()] = Some(rest);
+                false
+            }
+        }
+    }
+
+    /// Take at most `count` items out of a slot. Takes the whole stack if `count` is None.
+    pub fn take(&mut self, slot: usize, count: Option<u32>) -> Option<ItemStack> {
+        let stack = self.slots.get_mut(slot)?.as_mut()?;
+        let count = count.unwrap_or(stack.count).min(stack.count);
+
+        if count == 0 {
+            return None;
+        }
+
+        let taken = ItemStack::new(stack.id, count);
+        stack.count -= count;
+
+        if stack.count == 0 {
+            self.slots[slot] = None;
+        }
+
+        Some(taken)
+    }
+
+    /// Put a stack into a specific slot, merging with same-typed stacks.
+    ///
+    /// Returns whatever could not fit. If the slot holds a different item, nothing is put in.
+    pub fn put(&mut self, slot: usize, stack: ItemStack) -> Option<ItemStack> {
+        let target = match self.slots.get_mut(slot) {
+            Some(target) => target,
+            None => return Some(stack),
+        };
+
+        match target {
+            None => {
+                let count = stack.count.min(MAX_STACK_SIZE);
+                *target = Some(ItemStack::new(stack.id, count));
+                leftover(stack.id, stack.count - count)
+            }
+            Some(existing) if existing.id == stack.id => {
+                let count = stack
+                    .count
+                    .min(MAX_STACK_SIZE - existing.count.min(MAX_STACK_SIZE));
+                existing.count += count;
+                leftover(stack.id, stack.count - count)
+            }
+            _ => Some(stack),
+        }
+    }
+
+    /// Add a stack anywhere in the inventory, filling up matching stacks first.
+    ///
+    /// Returns whatever could not fit.
+    pub fn add(&mut self, stack: ItemStack) -> Option<ItemStack> {
+        let mut remaining = Some(stack);
+
+        for empty_pass in [false, true].iter() {
+            for slot in 0..self.slots.len() {
+                let stack = remaining.take()?;
+
+                let fits = match &self.slots[slot] {
+                    None => *empty_pass,
+                    Some(existing) => existing.id == stack.id,
+                };
+
+                remaining = if fits {
+                    self.put(slot, stack)
+                } else {
+                    Some(stack)
+                };
+            }
+        }
+
+        remaining
+    }
+
+    /// Move items from one slot to another within this inventory.
+    ///
+    /// Same-typed stacks merge, different types swap places.
+    pub fn move_within(&mut self, from: usize, to: usize, count: Option<u32>) -> bool {
+        if from == to || from >= self.size() || to >= self.size() {
+            return false;
+        }
+
+        let taken = match self.take(from, count) {
+            Some(taken) => taken,
+            None => return false,
+        };
+
+        let taken_count = taken.count;
+
+        match self.put(to, taken) {
+            None => {}
+            Some(rest) => {
+                let displaced = self.slots[to].take();
+
+                if displaced.as_ref().map(|d| d.id) == Some(rest.id) {
+                    // target was full of the same type, just give it back
+                    let moved = rest.count < taken_count;
+                    self.slots[to] = displaced;
+                    self.put(from, rest);
+                    return moved;
+                } else if self.slots[from].is_none() {
+                    self.slots[to] = Some(rest);
+                    self.slots[from] = displaced;
+                } else {
+                    // partial move onto a different type, undo
+                    self.slots[to] = displaced;
+                    self.put(from, rest);
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Move items from a slot of one inventory to a slot of another.
+    ///
+    /// Same-typed stacks merge, different types swap places if the whole stack is moved.
+    pub fn transfer(
+        source: &mut Inventory,
+        from: usize,
+        target: &mut Inventory,
+        to: usize,
+        count: Option<u32>,
+    ) -> bool {
+        if to >= target.size() {
+            return false;
+        }
+
+        let taken = match source.take(from, count) {
+            Some(taken) => taken,
+            None => return false,
+        };
+
+        let taken_count = taken.count;
+
+        if let Some(rest) = target.put(to, taken) {
+            let same_type = target.get(to).map(|s| s.id) == Some(rest.id);
+
+            if !same_type && source.slots[from].is_none() {
+                source.slots[from] = target.slots[to].take();
+                target.slots[to] = Some(rest);
+            } else {
+                let moved = rest.count < taken_count;
+                source.put(from, rest);
+                return moved;
+            }
+        }
+
+        true
+    }
+}
+
+fn leftover(id: u32, count: u32) -> Option<ItemStack> {
+    if count == 0 {
+        None
+    } else {
+        Some(ItemStack::new(id, count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_merges_stacks_works() {
+        let mut inventory = Inventory::new(3);
+
+        assert_eq!(inventory.add(ItemStack::new(1, 10)), None);
+        assert_eq!(inventory.add(ItemStack::new(1, 60)), None);
+        assert_eq!(inventory.get(0), Some(&ItemStack::new(1, 64)));
+        assert_eq!(inventory.get(1), Some(&ItemStack::new(1, 6)));
+
+        assert_eq!(
+            inventory.add(ItemStack::new(2, 200)),
+            Some(ItemStack::new(2, 136))
+        );
+    }
+
+    #[test]
+    fn move_within_works() {
+        let mut inventory = Inventory::new(3);
+        inventory.put(0, ItemStack::new(1, 5));
+        inventory.put(1, ItemStack::new(2, 7));
+
+        // swap different types
+        assert!(inventory.move_within(0, 1, None));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new(2, 7)));
+        assert_eq!(inventory.get(1), Some(&ItemStack::new(1, 5)));
+
+        // split into an empty slot
+        assert!(inventory.move_within(0, 2, Some(3)));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new(2, 4)));
+        assert_eq!(inventory.get(2), Some(&ItemStack::new(2, 3)));
+
+        // merge back
+        assert!(inventory.move_within(2, 0, None));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new(2, 7)));
+        assert!(inventory.get(2).is_none());
+
+        assert!(!inventory.move_within(2, 0, None));
+    }
+
+    #[test]
+    fn equip_works() {
+        let mut inventory = Inventory::new(1);
+        inventory.put(0, ItemStack::new(1, 1));
+
+        assert!(inventory.equip(0, EquipmentSlot::Head));
+        assert_eq!(
+            inventory.equipped(EquipmentSlot::Head),
+            Some(&ItemStack::new(1, 1))
+        );
+        assert!(inventory.get(0).is_none());
+
+        // swapping puts back what was worn
+        inventory.put(0, ItemStack::new(2, 1));
+        assert!(inventory.equip(0, EquipmentSlot::Head));
+        assert_eq!(
+            inventory.equipped(EquipmentSlot::Head),
+            Some(&ItemStack::new(2, 1))
+        );
+        assert_eq!(inventory.get(0), Some(&ItemStack::new(1, 1)));
+
+        // no room to take it off
+        assert!(!inventory.unequip(EquipmentSlot::Head));
+        assert!(inventory.equipped(EquipmentSlot::Head).is_some());
+    }
+}
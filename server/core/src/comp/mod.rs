@@ -1,8 +1,21 @@
+pub mod attack_cooldown;
+pub mod behavior;
 pub mod brain;
 pub mod curr_chunk;
+pub mod dropped_item;
+pub mod effects;
 pub mod etype;
+pub mod experience;
+pub mod falling_block;
+pub mod footsteps;
+pub mod health;
+pub mod hostile;
 pub mod id;
+pub mod inventory;
+pub mod mount;
 pub mod name;
+pub mod portal_cooldown;
+pub mod profile;
 pub mod rigidbody;
 pub mod rotation;
 pub mod target;
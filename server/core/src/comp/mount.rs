@@ -0,0 +1,113 @@
+use specs::{Component, Entity, VecStorage};
+
+use serde::{Deserialize, Serialize};
+
+use server_common::{quaternion::Quaternion, vec::Vec3};
+
+/// How a mob carries its rider, read from the entity prototype
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MountableOptions {
+    /// Height the rider sits at above the mount's feet, in voxels
+    pub seat: f32,
+    /// Drag in fluids in place of the world's, low for boats to glide along
+    #[serde(default)]
+    pub fluid_drag: Option<f32>,
+    /// Multiplier on the speed out of fluids, low for boats dragged over land
+    #[serde(default = "default_land_speed")]
+    pub land_speed: f32,
+}
+
+fn default_land_speed() -> f32 {
+    1.0
+}
+
+/// A mob players can ride, steering it around with their movement inputs
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct Mountable {
+    pub options: MountableOptions,
+    /// Id of the player riding it, if anyone is
+    pub rider: Option<usize>,
+    /// Top speed of the mob's brain in fluids, as it gets slowed down on land
+    pub max_speed: f32,
+}
+
+impl Mountable {
+    pub fn new(options: MountableOptions, max_speed: f32) -> Self {
+        Self {
+            options,
+            rider: None,
+            max_speed,
+        }
+    }
+}
+
+/// A player riding a mob, sitting on it rather than moving on their own
+#[derive(Component)]
+#[storage(VecStorage)]
+pub struct Mounted {
+    pub mount: Entity,
+    /// Movement inputs of the rider, as forwards and rightwards from -1 to 1
+    pub steer: (f32, f32),
+}
+
+impl Mounted {
+    pub fn new(mount: Entity) -> Self {
+        Self {
+            mount,
+            steer: (0.0, 0.0),
+        }
+    }
+
+    /// Where the rider wants to go from a position, by their inputs and the way they're
+    /// looking, or None if they're not steering
+    pub fn steer_target(&self, rotation: &Quaternion, from: &Vec3<f32>) -> Option<Vec3<f32>> {
+        let (forward, right) = self.steer;
+
+        if forward == 0.0 && right == 0.0 {
+            return None;
+        }
+
+        // the way the rider faces, flattened onto the ground
+        let &Quaternion(x, y, z, w) = rotation;
+        let fx = -2.0 * (x * z + w * y);
+        let fz = -(1.0 - 2.0 * (x * x + y * y));
+        let length = (fx * fx + fz * fz).sqrt().max(f32::EPSILON);
+        let (fx, fz) = (fx / length, fz / length);
+
+        Some(Vec3(
+            from.0 + fx * forward - fz * right,
+            from.1,
+            from.2 + fz * forward + fx * right,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{Builder, World, WorldExt};
+
+    use super::*;
+
+    #[test]
+    fn steer_target_works() {
+        let mut world = World::new();
+        let mut mounted = Mounted::new(world.create_entity().build());
+        let facing_north = Quaternion(0.0, 0.0, 0.0, 1.0);
+
+        assert_eq!(mounted.steer_target(&facing_north, &Vec3::default()), None);
+
+        mounted.steer = (1.0, 0.0);
+        let ahead = mounted
+            .steer_target(&facing_north, &Vec3::default())
+            .unwrap();
+        assert!(ahead.2 < -0.99 && ahead.0.abs() < 1e-5);
+
+        mounted.steer = (0.0, 1.0);
+        let right = mounted
+            .steer_target(&facing_north, &Vec3::default())
+            .unwrap();
+        assert!(right.0 > 0.99 && right.2.abs() < 1e-5);
+    }
+}
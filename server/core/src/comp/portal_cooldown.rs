@@ -0,0 +1,21 @@
+use specs::{Component, VecStorage};
+
+use crate::constants::PORTAL_COOLDOWN;
+
+/// Time left before an entity can travel through a portal again.
+///
+/// It only ticks down outside of portals, so travelers have to step out of the portal
+/// they arrived in before going back.
+#[derive(Default, Component)]
+#[storage(VecStorage)]
+pub struct PortalCooldown(pub f32);
+
+impl PortalCooldown {
+    pub fn new() -> Self {
+        Self(PORTAL_COOLDOWN)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.0 <= 0.0
+    }
+}
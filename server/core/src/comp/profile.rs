@@ -0,0 +1,119 @@
+use std::fmt;
+
+use serde::Serialize;
+use specs::{Component, VecStorage};
+
+use crate::constants::MAX_NAME_LENGTH;
+
+/// How a player appears to others, sent to every player on spawn and whenever it changes
+#[derive(Debug, Clone, Serialize, Component)]
+#[serde(rename_all = "camelCase")]
+#[storage(VecStorage)]
+pub struct PlayerProfile {
+    /// None until an anonymous player's client names them
+    pub display_name: Option<String>,
+    pub skin_url: Option<String>,
+    /// Lets clients cache skins and tell when one changes behind the same URL
+    pub skin_hash: Option<String>,
+    /// Whether the player's peer is shown to others
+    pub visible: bool,
+
+    /// Whether others have yet to hear about a change
+    #[serde(skip)]
+    pub changed: bool,
+}
+
+impl PlayerProfile {
+    pub fn new(display_name: Option<String>) -> Self {
+        Self {
+            display_name,
+            skin_url: None,
+            skin_hash: None,
+            visible: true,
+            changed: true,
+        }
+    }
+
+    pub fn set_display_name(&mut self, name: String) {
+        self.display_name = Some(name);
+        self.changed = true;
+    }
+
+    pub fn set_skin(&mut self, url: Option<String>, hash: Option<String>) {
+        self.skin_url = url;
+        self.skin_hash = hash;
+        self.changed = true;
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        self.changed = true;
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProfileError {
+    InvalidName,
+    Taken(String),
+    InvalidSkin,
+    NotAdmin,
+    UnknownPlayer,
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::InvalidName => write!(f, "Names need at least one visible character."),
+            ProfileError::Taken(name) => write!(f, "Somebody already goes by {}.", name),
+            ProfileError::InvalidSkin => write!(f, "Skins need to be an http or https URL."),
+            ProfileError::NotAdmin => write!(f, "Only admins can do that."),
+            ProfileError::UnknownPlayer => write!(f, "Nobody online goes by that name."),
+        }
+    }
+}
+
+/// Trim a requested display name, None if nothing usable is left
+pub fn clean_name(name: &str) -> Option<String> {
+    let name = name
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_LENGTH)
+        .collect::<String>();
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// The name itself if it's free, otherwise the name numbered until it is, so that no two
+/// players are seen under the same name
+pub fn unique_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(name) {
+        return name.to_owned();
+    }
+
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|numbered| !taken(numbered))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_name_works() {
+        let taken = ["ian", "ian (2)"];
+        let is_taken = |name: &str| taken.iter().any(|t| t.eq_ignore_ascii_case(name));
+
+        assert_eq!(unique_name("bob", is_taken), "bob");
+        assert_eq!(unique_name("IAN", is_taken), "IAN (3)");
+
+        assert_eq!(clean_name("  \n "), None);
+        assert_eq!(clean_name(" ian\t").as_deref(), Some("ian"));
+    }
+}
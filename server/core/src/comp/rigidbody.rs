@@ -2,6 +2,26 @@ use specs::{Component, VecStorage};
 
 use server_common::{aabb::Aabb, vec::Vec3};
 
+use crate::constants::{AUTO_STEP_HEIGHT, CROUCH_HEIGHT};
+
+/// How the blocks a body is in or standing on affect its movement
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovementModifiers {
+    pub climbable: bool,
+    pub speed_multiplier: f32,
+    pub suppress_jump: bool,
+}
+
+impl Default for MovementModifiers {
+    fn default() -> Self {
+        Self {
+            climbable: false,
+            speed_multiplier: 1.0,
+            suppress_jump: false,
+        }
+    }
+}
+
 #[derive(Default, Component)]
 #[storage(VecStorage)]
 pub struct RigidBody {
@@ -16,6 +36,10 @@ pub struct RigidBody {
     pub restitution: f32,
     pub gravity_multiplier: f32,
     pub auto_step: bool,
+    /// Highest the body automatically steps up, if it does
+    pub step_height: f32,
+    /// Whether the body is crouching, shrunk down and kept from walking off of edges
+    pub crouching: bool,
 
     pub air_drag: f32,
     pub fluid_drag: f32,
@@ -24,9 +48,17 @@ pub struct RigidBody {
     pub velocity: Vec3<f32>,
     pub in_fluid: bool,
     pub ratio_in_fluid: f32,
+    /// Whether the body is trying to swim upwards while in a fluid
+    pub swim_up: bool,
     pub forces: Vec3<f32>,
     pub impulses: Vec3<f32>,
     pub sleep_frame_count: i32,
+
+    pub modifiers: MovementModifiers,
+    /// Whether the body flies through blocks, free of gravity and collisions
+    pub no_clip: bool,
+    /// Where the body was before the last physics step, None if it was moved there since
+    pub last_position: Option<Vec3<f32>>,
 }
 
 impl RigidBody {
@@ -64,6 +96,8 @@ impl RigidBody {
             restitution,
             gravity_multiplier,
             auto_step,
+            step_height: AUTO_STEP_HEIGHT,
+            crouching: false,
 
             air_drag: -1.0,
             fluid_drag: -1.0,
@@ -72,9 +106,14 @@ impl RigidBody {
             velocity: Vec3::default(),
             in_fluid: false,
             ratio_in_fluid: 0.0,
+            swim_up: false,
             forces: Vec3::default(),
             impulses: Vec3::default(),
             sleep_frame_count: 10,
+
+            modifiers: MovementModifiers::default(),
+            no_clip: false,
+            last_position: None,
         }
     }
 
@@ -89,6 +128,28 @@ impl RigidBody {
     pub fn set_position(&mut self, p: &Vec3<f32>) {
         let delta = p.sub(&self.get_position());
         self.aabb.translate(&delta);
+        self.last_position = None;
+        self.mark_active();
+    }
+
+    /// Crouch or stand back up, shrinking or growing the body and its head from its feet
+    pub fn set_crouching(&mut self, crouching: bool) {
+        if self.crouching == crouching {
+            return;
+        }
+
+        let scale = if crouching {
+            CROUCH_HEIGHT
+        } else {
+            1.0 / CROUCH_HEIGHT
+        };
+
+        let mut vec = self.aabb.vec.clone();
+        vec.1 *= scale;
+
+        self.aabb = Aabb::new(&self.aabb.base, &vec);
+        self.head *= scale;
+        self.crouching = crouching;
         self.mark_active();
     }
 
@@ -108,6 +169,17 @@ impl RigidBody {
         self.aabb.base.clone().add(&offset)
     }
 
+    /// Position of the body part of the way from before the last physics step to now, by
+    /// `alpha` from 0 to 1, see `PhysicsStep`
+    pub fn get_interpolated_position(&self, alpha: f32) -> Vec3<f32> {
+        let position = self.get_position();
+
+        match &self.last_position {
+            Some(last) => last.scale_and_add(&position.sub(last), alpha),
+            None => position,
+        }
+    }
+
     /// Getter for rigid body's dimension
     pub fn get_dimension(&self) -> Vec3<f32> {
         self.aabb.vec.clone()
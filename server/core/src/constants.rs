@@ -298,3 +298,331 @@ pub const CHUNK_HORIZONTAL_NEIGHBORS: [[i32; 2]; 4] = [[-1, 0], [1, 0], [0, -1],
 pub const DATA_PADDING: usize = 1;
 
 pub const WORLD_DATA_FILE: &str = "world.json";
+
+/// Version of the format worlds are saved in, see `Migrations` to upgrade older saves
+pub const WORLD_FORMAT_VERSION: u32 = 1;
+
+pub const CONTAINERS_DATA_FILE: &str = "containers.json";
+
+/// Log of every block edit players made, a JSON record per line, if the world is stored in files
+pub const EDITS_DATA_FILE: &str = "edits.jsonl";
+
+/// Folder within a world's save the data of logged in players is kept in
+pub const PLAYER_DATA_FOLDER: &str = "players";
+
+/// Database within a world's save that holds everything, if the world is stored in SQLite
+pub const SQLITE_DATA_FILE: &str = "world.db";
+
+/// File within a world's save recording how many rings of chunks have been pregenerated
+pub const PREGEN_PROGRESS_FILE: &str = "pregen.json";
+
+/// Threads reading and writing a world's storage, apart from the game loop
+pub const STORAGE_IO_THREADS: usize = 4;
+
+/// Milliseconds to wait before trying a failed write to storage again
+pub const STORAGE_RETRY_DELAY_MS: u64 = 1000;
+
+/// Chunks past a player's terrain radius read from storage ahead of them getting there
+pub const CHUNK_READ_AHEAD: i16 = 2;
+
+/// Time of day sunlight is halfway up, out of the 2400 in a day
+pub const SUNRISE_TIME: f32 = 600.0;
+
+/// Time of day sunlight is halfway down, out of the 2400 in a day
+pub const SUNSET_TIME: f32 = 1800.0;
+
+/// Time it takes sunlight to come up or go down, centered on sunrise and sunset
+pub const SUN_TRANSITION_TIME: f32 = 200.0;
+
+/// Share of sunlight left at night
+pub const MIN_SUNLIGHT_INTENSITY: f32 = 0.1;
+
+pub const MAX_STACK_SIZE: u32 = 64;
+
+pub const PLAYER_INVENTORY_SIZE: usize = 36;
+
+pub const MAX_REACH_DISTANCE: f32 = 8.0;
+
+/// Power levels are stored in the 4 stage bits of a voxel
+pub const MAX_POWER_LEVEL: u32 = 15;
+
+/// Ticks a power consumer takes to react to a change
+pub const POWER_CONSUMER_DELAY: i32 = 1;
+
+/// Chunks within this radius around players receive random ticks
+pub const RANDOM_TICK_CHUNK_RADIUS: i32 = 4;
+
+pub const FARMLAND_HYDRATION_RADIUS: i32 = 4;
+
+pub const FARMLAND_DRY_OUT_CHANCE: f64 = 0.25;
+
+pub const CROP_GROWTH_CHANCE_HYDRATED: f64 = 0.33;
+
+pub const CROP_GROWTH_CHANCE_DRY: f64 = 0.15;
+
+/// Furthest a leaf can be from a log, through other leaves, without decaying
+pub const LEAF_DECAY_DISTANCE: u32 = 6;
+
+/// Distance at which two mobs in love breed
+pub const BREED_DISTANCE: f32 = 1.5;
+
+/// Most entities a chunk can hold before mobs in it stop breeding
+pub const MAX_ENTITIES_PER_CHUNK: usize = 16;
+
+/// Mobs following a player stop walking once this close
+pub const FOLLOW_STOP_DISTANCE: f32 = 2.0;
+
+/// Seconds a mob gives up on reaching its wander goal after
+pub const WANDER_TIMEOUT: f32 = 10.0;
+
+pub const PLAYER_MAX_HEALTH: f32 = 20.0;
+
+pub const PLAYER_ATTACK_DAMAGE: f32 = 1.0;
+
+/// Speed an entity hit by a player is knocked away with, in voxels per second
+pub const PLAYER_ATTACK_KNOCKBACK: f32 = 6.0;
+
+/// Seconds a player waits between melee hits
+pub const ATTACK_COOLDOWN: f32 = 0.4;
+
+/// Points checked per voxel along a hit, for blocks standing in the way
+pub const LINE_OF_SIGHT_STEPS: f32 = 4.0;
+
+/// Upwards part of a knockback, relative to its strength
+pub const KNOCKBACK_LIFT: f32 = 0.5;
+
+/// Seconds an entity can't be hurt again after taking damage
+pub const INVULNERABILITY_TIME: f32 = 0.5;
+
+/// Highest level a status effect stacks up to
+pub const MAX_EFFECT_LEVEL: u32 = 5;
+
+/// Seconds a status effect given by `/effect` lasts when not told
+pub const DEFAULT_EFFECT_SECONDS: f32 = 30.0;
+
+/// Share of damage taken off by each point of armor worn
+pub const ARMOR_REDUCTION_PER_POINT: f32 = 0.04;
+
+/// Most of the damage armor can take off, however much is worn
+pub const MAX_ARMOR_REDUCTION: f32 = 0.8;
+
+/// Players further than this from another player won't be told what they're wearing
+pub const EQUIPMENT_VIEW_DISTANCE: f32 = 64.0;
+
+/// Players further than this from a particle effect won't receive it
+pub const PARTICLE_VIEW_DISTANCE: f32 = 32.0;
+
+/// Default distance players hear a sound from, unless the sound registry says otherwise
+pub const SOUND_HEARING_DISTANCE: f32 = 24.0;
+
+/// Distance walked on the ground between two footsteps, in voxels
+pub const FOOTSTEP_STRIDE: f32 = 1.8;
+
+/// Seconds an entity has to spend outside of portals before it can travel again
+pub const PORTAL_COOLDOWN: f32 = 3.0;
+
+/// Voxels around the destination searched for an existing portal to link to
+pub const PORTAL_SEARCH_RADIUS: i32 = 16;
+
+/// Chunks generated around a teleport destination before landing
+pub const TELEPORT_LOAD_RADIUS: i16 = 1;
+
+/// Columns around a teleport destination searched for a safe place to land
+pub const TELEPORT_SEARCH_RADIUS: i32 = 8;
+
+/// Voxels per second bodies climb up, or at most slide down, climbable blocks
+pub const CLIMB_SPEED: f32 = 2.0;
+
+/// Upwards acceleration of a fully submerged body swimming up, in voxels per second squared
+pub const SWIM_ACCELERATION: f32 = 30.0;
+
+/// Most ticks run at once to catch up after falling behind, the rest are skipped
+pub const MAX_CATCH_UP_TICKS: u32 = 10;
+
+/// Most physics steps run in a single tick, the rest are skipped
+pub const MAX_PHYSICS_STEPS_PER_TICK: u32 = 8;
+
+/// Highest bodies automatically step up unless they have a height of their own, in voxels
+pub const AUTO_STEP_HEIGHT: f32 = 1.0;
+
+/// Share of their height bodies keep while crouching, clients shrink players by as much
+pub const CROUCH_HEIGHT: f32 = 0.85;
+
+/// Deepest a crouching body lets itself drop off of an edge, in voxels
+pub const CROUCH_DROP: f32 = 0.5;
+
+/// Ticks averaged over when reporting tick durations
+pub const TICK_TIMING_SAMPLES: usize = 100;
+
+/// Bytes queued for a connection past which particles and sounds are dropped, and peer and entity updates coalesced
+pub const OUTBOX_SOFT_LIMIT: usize = 4 * 1024 * 1024;
+
+/// Bytes queued for a connection past which the client is considered stuck and disconnected
+pub const OUTBOX_HARD_LIMIT: usize = 32 * 1024 * 1024;
+
+/// Bytes a connection writes out per flush before yielding back to the socket
+pub const OUTBOX_FLUSH_BUDGET: usize = 256 * 1024;
+
+/// Bytes past which frames are zlib compressed for clients that can inflate them, unless a
+/// client asks for another threshold in its handshake
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Lowest compression threshold clients can ask for, as compressing small frames only grows them
+pub const MIN_COMPRESSION_THRESHOLD: usize = 128;
+
+/// Version of the client-server protocol spoken by this server
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Oldest client protocol version this server can still serve
+pub const MIN_PROTOCOL_VERSION: u32 = 2;
+
+/// Seconds a new connection has to send its handshake before it's closed
+pub const HANDSHAKE_TIMEOUT: u64 = 10;
+
+/// Seconds between the pings sent to measure a connection's latency and keep it alive
+pub const KEEPALIVE_INTERVAL: u64 = 5;
+
+/// Seconds without hearing from a client before its connection is closed
+pub const KEEPALIVE_TIMEOUT: u64 = 30;
+
+/// Width and height of a dropped item's body, in voxels
+pub const DROPPED_ITEM_SIZE: f32 = 0.25;
+
+/// Seconds before a dropped item can be picked up
+pub const ITEM_PICKUP_DELAY: f32 = 2.0;
+
+/// Seconds a dropped item lies around before it's gone
+pub const ITEM_DESPAWN_TIME: f32 = 300.0;
+
+/// Players pick up the dropped items this close to them, in voxels
+pub const ITEM_PICKUP_DISTANCE: f32 = 1.5;
+
+/// Points of experience needed to reach the first level
+pub const EXPERIENCE_LEVEL_BASE: u32 = 7;
+
+/// Points of experience each level takes more than the one before
+pub const EXPERIENCE_LEVEL_GROWTH: u32 = 2;
+
+/// Width and height of an experience orb's body, in voxels
+pub const EXPERIENCE_ORB_SIZE: f32 = 0.25;
+
+/// Experience orbs drift toward players this close to them, in voxels
+pub const EXPERIENCE_ORB_ATTRACT_DISTANCE: f32 = 8.0;
+
+/// Players take in the experience orbs this close to them, in voxels
+pub const EXPERIENCE_ORB_PICKUP_DISTANCE: f32 = 1.0;
+
+/// Speed experience orbs drift toward players with, in voxels per second
+pub const EXPERIENCE_ORB_SPEED: f32 = 6.0;
+
+/// Seconds an experience orb lies around before it's gone
+pub const EXPERIENCE_ORB_DESPAWN_TIME: f32 = 300.0;
+
+/// Width and height of a falling block's body, in voxels, slightly less than one to slip into gaps
+pub const FALLING_BLOCK_SIZE: f32 = 0.98;
+
+/// Milliseconds a tick spends remeshing changed chunks before leaving the rest for the next tick
+pub const REMESH_BUDGET_MS: u64 = 4;
+
+/// Most chunks a client may list in a single chunk request, the rest are turned away
+pub const MAX_CHUNKS_PER_REQUEST: usize = 32;
+
+/// Most requested chunks sent to a player per tick, the rest wait for the next tick
+pub const CHUNK_REPLIES_PER_TICK: usize = 4;
+
+/// Most horizons of far chunks sent to a player per tick, being much lighter than chunks
+pub const HORIZON_REPLIES_PER_TICK: usize = 16;
+
+/// Most characters a player's display name may have
+pub const MAX_NAME_LENGTH: usize = 24;
+
+/// Player id of commands run from the server console, which counts as an admin
+pub const CONSOLE_ID: usize = usize::MAX;
+
+/// Longest body of an RCON packet, longer responses are split over several
+pub const MAX_RCON_BODY_SIZE: usize = 4096;
+
+/// Seconds a remote console may stay quiet before it's disconnected
+pub const RCON_IDLE_TIMEOUT: u64 = 300;
+
+/// Seconds a remote console waits for a command to run before giving up on it
+pub const RCON_COMMAND_TIMEOUT: u64 = 10;
+
+/// Most remote consoles connected at once, the rest are turned away
+pub const MAX_RCON_CONNECTIONS: usize = 4;
+
+/// Failed logins from an address before it's locked out of the remote console
+pub const MAX_RCON_FAILED_LOGINS: u32 = 3;
+
+/// Seconds an address stays locked out after too many failed logins
+pub const RCON_LOCKOUT: u64 = 300;
+
+/// Seconds a remote console waits to hear that its password is wrong, slowing down guessing
+pub const RCON_FAILED_LOGIN_DELAY: u64 = 2;
+
+/// Folder the scripts hooked into every world's events are loaded from
+pub const SCRIPTS_FOLDER: &str = "scripts";
+
+/// Most operations a script may run per call before it's stopped
+pub const MAX_SCRIPT_OPERATIONS: u64 = 1_000_000;
+
+/// Most edits `/co lookup` lists, the newest ones
+pub const AUDIT_LOOKUP_LIMIT: usize = 10;
+
+/// Most edits one `/co rollback` reverts, the newest ones, so that it reads a bounded part
+/// of the edit log
+pub const AUDIT_ROLLBACK_LIMIT: usize = 10000;
+
+/// How far out a claim reaches from where its owner stands when none is given
+pub const DEFAULT_CLAIM_RADIUS: i32 = 8;
+
+/// Furthest out a single claim may reach from where its owner stands
+pub const MAX_CLAIM_RADIUS: i32 = 32;
+
+/// Most block edits remembered per player for `/undo` and `/redo`
+pub const MAX_JOURNAL_ENTRIES: usize = 1024;
+
+/// Most voxels a single bulk edit command may change
+pub const MAX_EDIT_VOLUME: usize = 64 * 1024;
+
+/// Most overwritten voxels kept per player to undo their bulk edits with, oldest edits go first
+pub const MAX_UNDO_VOXELS: usize = 256 * 1024;
+
+/// Fastest a player can be made to move with `/speed`, as a multiple of the normal speed
+pub const MAX_SPEED_MULTIPLIER: f32 = 10.0;
+
+/// Name given to anonymous players whose client didn't name them
+pub const DEFAULT_PLAYER_NAME: &str = "Player";
+
+/// How far out players hear messages sent in the local chat channel, in voxels
+pub const LOCAL_CHAT_RADIUS: f32 = 48.0;
+
+/// Most characters a chat message is cut to
+pub const MAX_CHAT_LENGTH: usize = 256;
+
+/// How long players stay muted after the chat filter mutes them, in seconds
+pub const CHAT_FILTER_MUTE_SECONDS: u64 = 300;
+
+/// Longest a team name may be
+pub const MAX_TEAM_NAME_LENGTH: usize = 16;
+
+/// Most rings of regions around a player searched by `/locate structure`
+pub const LOCATE_STRUCTURE_RINGS: i32 = 32;
+
+/// Farthest `/locate biome` searches from a player, in voxels
+pub const LOCATE_BIOME_RADIUS: i32 = 4096;
+
+/// Distance between the columns sampled by `/locate biome`, in voxels
+pub const LOCATE_BIOME_STEP: i32 = 32;
+
+/// Ticks between the transforms of players and entities kept in a replay
+pub const REPLAY_TRANSFORM_TICKS: u64 = 2;
+
+/// Most waypoints a player may keep
+pub const MAX_WAYPOINTS: usize = 32;
+
+/// Longest a waypoint or marker name may be
+pub const MAX_WAYPOINT_NAME_LENGTH: usize = 24;
+
+/// Most stale map tiles rendered again per tick
+pub const MAP_TILES_PER_TICK: usize = 4;
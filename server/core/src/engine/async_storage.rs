@@ -0,0 +1,454 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crossbeam_channel::{unbounded, Sender};
+use hashbrown::HashMap;
+use log::{error, warn};
+use serde_json::Value;
+use tokio::{
+    runtime::{Builder, Runtime},
+    sync::{mpsc, oneshot},
+    task::{self, JoinHandle},
+    time,
+};
+use uuid::Uuid;
+
+use server_common::vec::{Vec2, Vec3};
+
+use crate::constants::{STORAGE_IO_THREADS, STORAGE_RETRY_DELAY_MS};
+
+use super::{
+    audit::EditQuery,
+    storage::{Storage, WriteBatch},
+};
+
+/// A chunk read from storage by the request it answers, None if it has never been saved
+pub type LoadedChunk = (u64, Vec2<i32>, Option<Value>);
+
+/// Edits read from the edit log by the request they answer, newest first
+pub type LoadedEdits = (u64, Vec<Value>);
+
+enum Job {
+    Write(u64, WriteBatch),
+    Remove(u64, Vec<Vec2<i32>>),
+    Edits(u64, EditQuery, usize, Arc<Sender<LoadedEdits>>),
+    Compact,
+    Flush(oneshot::Sender<()>),
+}
+
+/// Records queued to be written, along with the write that queued them. A chunk queued to
+/// be removed is kept as None.
+#[derive(Default)]
+struct Unwritten {
+    chunks: HashMap<Vec2<i32>, (u64, Option<Value>)>,
+    players: HashMap<Uuid, (u64, Value)>,
+}
+
+impl Unwritten {
+    /// Forget the records a write put in storage, unless a later write changed them again
+    fn landed(&mut self, write: u64) {
+        self.chunks.retain(|_, (queued_by, _)| *queued_by != write);
+        self.players.retain(|_, (queued_by, _)| *queued_by != write);
+    }
+}
+
+/// A world's storage, run on worker threads so that the game loop never waits on the disk.
+///
+/// Writes are queued and land in storage one batch at a time, in order. Until they do, the
+/// records they write are read back from memory, so loads never see stale data. A write that
+/// fails is tried again until it lands, holding back the ones queued after it.
+pub struct AsyncStorage {
+    storage: Arc<dyn Storage>,
+    runtime: Runtime,
+    writer: mpsc::UnboundedSender<Job>,
+    writes: AtomicU64,
+    requests: AtomicU64,
+    unwritten: Arc<Mutex<Unwritten>>,
+}
+
+impl fmt::Debug for AsyncStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncStorage")
+            .field("storage", &self.storage)
+            .finish()
+    }
+}
+
+impl AsyncStorage {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(1)
+            .max_blocking_threads(STORAGE_IO_THREADS)
+            .thread_name("storage-io")
+            .enable_time()
+            .build()
+            .expect("Unable to start storage threads.");
+
+        let (writer, mut jobs) = mpsc::unbounded_channel();
+        let unwritten = Arc::new(Mutex::new(Unwritten::default()));
+
+        let writing = storage.clone();
+        let landed = unwritten.clone();
+
+        runtime.spawn(async move {
+            while let Some(job) = jobs.recv().await {
+                match job {
+                    Job::Write(write, batch) => {
+                        let storage = writing.clone();
+                        retry(move || storage.write(batch.clone())).await;
+
+                        landed.lock().unwrap().landed(write);
+                    }
+                    Job::Remove(write, coords) => {
+                        let storage = writing.clone();
+                        retry(move || storage.remove_chunks(&coords)).await;
+
+                        landed.lock().unwrap().landed(write);
+                    }
+                    Job::Edits(request, query, limit, sender) => {
+                        let storage = writing.clone();
+                        let edits =
+                            task::spawn_blocking(move || storage.query_edits(&query, limit));
+
+                        match edits.await {
+                            Ok(edits) => {
+                                sender.send((request, edits)).ok();
+                            }
+                            Err(e) => error!("Unable to read the edit log: {}", e),
+                        }
+                    }
+                    Job::Compact => {
+                        let storage = writing.clone();
+
+                        if let Err(e) = task::spawn_blocking(move || storage.compact()).await {
+                            error!("Unable to compact storage: {}", e);
+                        }
+                    }
+                    Job::Flush(done) => {
+                        done.send(()).ok();
+                    }
+                }
+            }
+        });
+
+        Self {
+            storage,
+            runtime,
+            writer,
+            writes: AtomicU64::new(0),
+            requests: AtomicU64::new(0),
+            unwritten,
+        }
+    }
+
+    /// Read a chunk on the worker threads
+    pub fn load_chunk_async(&self, coords: Vec2<i32>) -> JoinHandle<Option<Value>> {
+        if let Some((_, data)) = self.unwritten.lock().unwrap().chunks.get(&coords) {
+            let data = data.to_owned();
+            return self.runtime.spawn(async move { data });
+        }
+
+        let storage = self.storage.clone();
+        self.runtime
+            .spawn_blocking(move || storage.load_chunk(&coords))
+    }
+
+    /// Read a chunk on the worker threads, sending it over once read. Returns the request's
+    /// id, to tell answers to older requests for the same chunk apart.
+    pub fn request_chunk(&self, coords: Vec2<i32>, sender: &Arc<Sender<LoadedChunk>>) -> u64 {
+        let request = self.requests.fetch_add(1, Ordering::Relaxed);
+        let load = self.load_chunk_async(coords.to_owned());
+        let sender = sender.clone();
+
+        self.runtime.spawn(async move {
+            match load.await {
+                Ok(data) => {
+                    sender.send((request, coords, data)).ok();
+                }
+                // never generate over a chunk that couldn't be read
+                Err(e) => error!("Unable to load chunk {:?}: {}", coords, e),
+            }
+        });
+
+        request
+    }
+
+    /// Query the edit log once every write queued before has landed, sending the edits
+    /// over once read. Returns the request's id.
+    pub fn request_edits(
+        &self,
+        query: &EditQuery,
+        limit: usize,
+        sender: &Arc<Sender<LoadedEdits>>,
+    ) -> u64 {
+        let request = self.requests.fetch_add(1, Ordering::Relaxed);
+        let job = Job::Edits(request, query.to_owned(), limit, sender.clone());
+
+        if self.writer.send(job).is_err() {
+            warn!("Storage threads are gone, the edit log can't be read.");
+        }
+
+        request
+    }
+
+    /// Read chunks on the worker threads all at once, waiting for all of them
+    pub fn load_chunks_blocking(&self, coords: &[Vec2<i32>]) -> Vec<Option<Value>> {
+        let loads = coords
+            .iter()
+            .map(|coords| self.load_chunk_async(coords.to_owned()))
+            .collect::<Vec<_>>();
+
+        self.runtime.block_on(async move {
+            let mut loaded = vec![];
+
+            for load in loads {
+                loaded.push(load.await.expect("Unable to load chunk."));
+            }
+
+            loaded
+        })
+    }
+
+    /// Wait for every queued write to land in storage
+    pub fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+
+        if self.writer.send(Job::Flush(done)).is_ok() {
+            self.runtime.block_on(flushed).ok();
+        }
+    }
+}
+
+/// Run a job on the blocking threads until it goes through without panicking
+async fn retry<F>(job: F)
+where
+    F: Fn() + Clone + Send + 'static,
+{
+    while let Err(e) = task::spawn_blocking(job.clone()).await {
+        error!("Unable to write to storage, trying again: {}", e);
+        time::sleep(Duration::from_millis(STORAGE_RETRY_DELAY_MS)).await;
+    }
+}
+
+impl Drop for AsyncStorage {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl Storage for AsyncStorage {
+    fn load_world(&self) -> Option<Value> {
+        self.storage.load_world()
+    }
+
+    fn load_chunk(&self, coords: &Vec2<i32>) -> Option<Value> {
+        if let Some((_, data)) = self.unwritten.lock().unwrap().chunks.get(coords) {
+            return data.to_owned();
+        }
+
+        self.storage.load_chunk(coords)
+    }
+
+    fn load_containers(&self) -> Vec<(Vec3<i32>, Value)> {
+        self.storage.load_containers()
+    }
+
+    fn load_player(&self, uuid: &Uuid) -> Option<Value> {
+        if let Some((_, data)) = self.unwritten.lock().unwrap().players.get(uuid) {
+            return Some(data.to_owned());
+        }
+
+        self.storage.load_player(uuid)
+    }
+
+    /// Waits for the writes queued before to land, use `request_edits` from the game loop
+    fn query_edits(&self, query: &EditQuery, limit: usize) -> Vec<Value> {
+        let (sender, receiver) = unbounded();
+        self.request_edits(query, limit, &Arc::new(sender));
+
+        receiver.recv().map(|(_, edits)| edits).unwrap_or_default()
+    }
+
+    /// The chunks in storage, along with the ones queued to be written
+    fn list_chunks(&self) -> Vec<(Vec2<i32>, u64)> {
+        // held throughout, so that no write lands between listing storage and the queue
+        let unwritten = self.unwritten.lock().unwrap();
+
+        let mut listed = self
+            .storage
+            .list_chunks()
+            .into_iter()
+            .filter(|(coords, _)| !unwritten.chunks.contains_key(coords))
+            .collect::<Vec<_>>();
+
+        listed.extend(unwritten.chunks.iter().filter_map(|(coords, (_, data))| {
+            let bytes = data.as_ref()?.to_string().len() as u64;
+            Some((coords.to_owned(), bytes))
+        }));
+
+        listed
+    }
+
+    /// Queue chunks to be removed after the writes before, reading them as gone meanwhile
+    fn remove_chunks(&self, coords: &[Vec2<i32>]) {
+        let write = self.writes.fetch_add(1, Ordering::Relaxed);
+
+        {
+            let mut unwritten = self.unwritten.lock().unwrap();
+
+            for coords in coords {
+                unwritten.chunks.insert(coords.to_owned(), (write, None));
+            }
+        }
+
+        if self
+            .writer
+            .send(Job::Remove(write, coords.to_vec()))
+            .is_err()
+        {
+            warn!("Storage threads are gone, chunks weren't removed.");
+        }
+    }
+
+    /// Queue a batch to be written after the ones before it
+    fn write(&self, batch: WriteBatch) {
+        let write = self.writes.fetch_add(1, Ordering::Relaxed);
+
+        {
+            let mut unwritten = self.unwritten.lock().unwrap();
+
+            for (coords, data) in batch.chunks.iter() {
+                unwritten
+                    .chunks
+                    .insert(coords.to_owned(), (write, Some(data.to_owned())));
+            }
+
+            for (uuid, data) in batch.players.iter() {
+                unwritten
+                    .players
+                    .insert(uuid.to_owned(), (write, data.to_owned()));
+            }
+        }
+
+        if self.writer.send(Job::Write(write, batch)).is_err() {
+            warn!("Storage threads are gone, a write was dropped.");
+        }
+    }
+
+    fn compact(&self) {
+        if self.writer.send(Job::Compact).is_err() {
+            warn!("Storage threads are gone, storage wasn't compacted.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::atomic::AtomicBool};
+
+    use super::super::storage::{open, MemoryStorage, StorageKind};
+    use super::*;
+
+    /// Storage in memory whose first write fails
+    #[derive(Debug, Default)]
+    struct FlakyStorage {
+        failed: AtomicBool,
+        storage: MemoryStorage,
+    }
+
+    impl Storage for FlakyStorage {
+        fn load_world(&self) -> Option<Value> {
+            self.storage.load_world()
+        }
+
+        fn load_chunk(&self, coords: &Vec2<i32>) -> Option<Value> {
+            self.storage.load_chunk(coords)
+        }
+
+        fn load_containers(&self) -> Vec<(Vec3<i32>, Value)> {
+            self.storage.load_containers()
+        }
+
+        fn load_player(&self, uuid: &Uuid) -> Option<Value> {
+            self.storage.load_player(uuid)
+        }
+
+        fn query_edits(&self, query: &EditQuery, limit: usize) -> Vec<Value> {
+            self.storage.query_edits(query, limit)
+        }
+
+        fn list_chunks(&self) -> Vec<(Vec2<i32>, u64)> {
+            self.storage.list_chunks()
+        }
+
+        fn remove_chunks(&self, coords: &[Vec2<i32>]) {
+            self.storage.remove_chunks(coords)
+        }
+
+        fn write(&self, batch: WriteBatch) {
+            if !self.failed.swap(true, Ordering::Relaxed) {
+                panic!("The disk is full.");
+            }
+
+            self.storage.write(batch)
+        }
+    }
+
+    #[test]
+    fn async_storage_works() {
+        let root =
+            std::env::temp_dir().join(format!("mine.js-async-storage-{}", std::process::id()));
+        let storage = AsyncStorage::new(open(StorageKind::Files, &root));
+
+        storage.write(WriteBatch {
+            chunks: vec![(Vec2(3, -2), serde_json::json!({ "voxels": "abc" }))],
+            ..Default::default()
+        });
+
+        // read back before it lands, then from the files once it has
+        assert_eq!(storage.load_chunk(&Vec2(3, -2)).unwrap()["voxels"], "abc");
+
+        storage.flush();
+        assert!(storage.unwritten.lock().unwrap().chunks.is_empty());
+
+        let loaded = storage.load_chunks_blocking(&[Vec2(3, -2), Vec2(0, 0)]);
+        assert_eq!(loaded[0].as_ref().unwrap()["voxels"], "abc");
+        assert!(loaded[1].is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn async_storage_retries_and_queues() {
+        let flaky = Arc::new(FlakyStorage::default());
+        let storage = AsyncStorage::new(flaky.clone());
+
+        storage.write(WriteBatch {
+            chunks: vec![(Vec2(1, 1), serde_json::json!({ "voxels": "abc" }))],
+            ..Default::default()
+        });
+
+        // the failed write is tried again instead of being dropped
+        storage.flush();
+        assert!(flaky.load_chunk(&Vec2(1, 1)).is_some());
+        assert_eq!(storage.list_chunks().len(), 1);
+
+        // removed chunks read as gone before the removal lands
+        storage.remove_chunks(&[Vec2(1, 1)]);
+        assert!(storage.load_chunk(&Vec2(1, 1)).is_none());
+        assert!(storage.list_chunks().is_empty());
+
+        let (sender, receiver) = unbounded();
+        let request = storage.request_edits(&EditQuery::default(), 10, &Arc::new(sender));
+        assert_eq!(receiver.recv().unwrap(), (request, vec![]));
+
+        storage.flush();
+        assert!(flaky.load_chunk(&Vec2(1, 1)).is_none());
+    }
+}
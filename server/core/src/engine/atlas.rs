@@ -0,0 +1,200 @@
+use std::{collections::HashMap, fs::File};
+
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, ImageOutputFormat, RgbaImage,
+};
+use log::info;
+use serde::Deserialize;
+
+use server_common::types::UV;
+
+use super::registry::Ranges;
+
+/// Settings of how block textures are packed into atlases, loaded from `atlas.json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AtlasConfig {
+    /// Texels of each texture's edges repeated around it, per 16 texels of texture size, so
+    /// that mipmaps blend textures with their own edges instead of their neighbors
+    pub padding: u32,
+}
+
+impl Default for AtlasConfig {
+    fn default() -> Self {
+        Self { padding: 2 }
+    }
+}
+
+impl AtlasConfig {
+    /// Load the atlas config, falling back to the defaults if there's none
+    pub fn load(path: &str) -> Self {
+        match File::open(path) {
+            Ok(file) => serde_json::from_reader(file)
+                .unwrap_or_else(|e| panic!("Unable to read atlas config at {}: {}", path, e)),
+            Err(_) => {
+                info!("No atlas config at {}, using the defaults.", path);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// Block textures of a pack laid out side by side in a single image
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    pub image: RgbaImage,
+    /// Where each texture ended up, by its source
+    pub ranges: Ranges,
+    /// How many textures fit across the atlas, the inverse of a texture's width in UVs
+    pub side_count: u32,
+    pub texture_size: u32,
+}
+
+impl Atlas {
+    /// Pack textures into a square power-of-two atlas, sorted by their sources. Each one is
+    /// resized to `texture_size` and surrounded by a gutter of its own edges.
+    ///
+    /// The UVs only depend on the textures and the padding, so packs of any power-of-two
+    /// texture size share them.
+    pub fn pack(
+        textures: HashMap<String, DynamicImage>,
+        texture_size: u32,
+        config: &AtlasConfig,
+    ) -> Self {
+        assert!(
+            texture_size.is_power_of_two(),
+            "Texture size should be a power of two, got {}.",
+            texture_size
+        );
+
+        let gutter = config.padding * texture_size / 16;
+        let cell_size = texture_size + gutter * 2;
+
+        let count_per_side = ((textures.len() as f32).sqrt().ceil() as u32)
+            .next_power_of_two()
+            .max(2);
+        let atlas_size = (count_per_side * cell_size).next_power_of_two();
+
+        let mut image = RgbaImage::new(atlas_size, atlas_size);
+        let mut ranges = HashMap::new();
+
+        let mut textures = textures.into_iter().collect::<Vec<_>>();
+        textures.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for (index, (source, texture)) in textures.into_iter().enumerate() {
+            let index = index as u32;
+            let cell_x = (index % count_per_side) * cell_size;
+            let cell_y = (index / count_per_side) * cell_size;
+
+            let resized =
+                imageops::resize(&texture, texture_size, texture_size, FilterType::CatmullRom);
+
+            // edge texels stretched out over the gutter
+            for y in 0..cell_size {
+                for x in 0..cell_size {
+                    let texel = resized.get_pixel(
+                        x.saturating_sub(gutter).min(texture_size - 1),
+                        y.saturating_sub(gutter).min(texture_size - 1),
+                    );
+                    image.put_pixel(cell_x + x, cell_y + y, *texel);
+                }
+            }
+
+            let start_x = (cell_x + gutter) as f32;
+            let start_y = (cell_y + gutter) as f32;
+            let f_texture_size = texture_size as f32;
+            let f_atlas_size = atlas_size as f32;
+
+            let (start_u, start_v, end_u, end_v) = fix_texture_bleeding((
+                start_x / f_atlas_size,
+                1.0 - start_y / f_atlas_size,
+                (start_x + f_texture_size) / f_atlas_size,
+                1.0 - (start_y + f_texture_size) / f_atlas_size,
+            ));
+
+            ranges.insert(
+                source,
+                UV {
+                    start_u,
+                    end_u,
+                    start_v,
+                    end_v,
+                },
+            );
+        }
+
+        Self {
+            image,
+            ranges,
+            side_count: atlas_size / texture_size,
+            texture_size,
+        }
+    }
+
+    /// The atlas encoded as a PNG, as served to clients
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut png = vec![];
+
+        DynamicImage::ImageRgba8(self.image.clone())
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .expect("Unable to encode texture atlas.");
+
+        png
+    }
+}
+
+/// Fixing texture bleeding with the
+/// [Half-texel edge correction method](http://drilian.com/2008/11/25/understanding-half-pixel-and-half-texel-offsets/)
+fn fix_texture_bleeding(
+    (start_u, start_v, end_u, end_v): (f32, f32, f32, f32),
+) -> (f32, f32, f32, f32) {
+    let offset = 0.1 / 128.0;
+    (
+        start_u + offset,
+        start_v - offset,
+        end_u - offset,
+        end_v + offset,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    fn textures(size: u32) -> HashMap<String, DynamicImage> {
+        ["a", "b", "c"]
+            .iter()
+            .enumerate()
+            .map(|(i, source)| {
+                let color = Rgba([i as u8 * 100, 0, 0, 255]);
+                let image = RgbaImage::from_pixel(size, size, color);
+                (source.to_string(), DynamicImage::ImageRgba8(image))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pack_works() {
+        let config = AtlasConfig { padding: 2 };
+        let atlas = Atlas::pack(textures(16), 16, &config);
+
+        // 2 by 2 cells of 20 texels, rounded up to 64
+        assert_eq!(atlas.image.dimensions(), (64, 64));
+        assert_eq!(atlas.side_count, 4);
+
+        // gutters take after the texture they surround
+        assert_eq!(atlas.image.get_pixel(20, 0), &Rgba([100, 0, 0, 255]));
+        assert_eq!(atlas.image.get_pixel(39, 19), &Rgba([100, 0, 0, 255]));
+        assert_eq!(atlas.image.get_pixel(40, 0), &Rgba([0, 0, 0, 0]));
+
+        let b = &atlas.ranges["b"];
+        assert!((b.start_u - 22.0 / 64.0).abs() < 0.001);
+        assert!((b.end_u - 38.0 / 64.0).abs() < 0.001);
+
+        let larger = Atlas::pack(textures(128), 128, &config);
+        assert!((larger.ranges["b"].start_u - b.start_u).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,249 @@
+use std::{fmt, sync::Arc};
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use server_common::vec::Vec3;
+
+use crate::constants::{AUDIT_LOOKUP_LIMIT, AUDIT_ROLLBACK_LIMIT};
+
+use super::{
+    async_storage::{AsyncStorage, LoadedEdits},
+    journal::JournalEntry,
+};
+
+/// A block edit as kept in a world's edit log: who changed what, where and when
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditRecord {
+    /// Name the player went by at the time
+    pub player: String,
+    /// Persistent identity of the player, None if they were anonymous
+    pub uuid: Option<Uuid>,
+    #[serde(flatten)]
+    pub edit: JournalEntry,
+}
+
+impl EditRecord {
+    pub fn to_saved(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+/// Which records of the edit log a lookup or rollback is about, parsed from arguments like
+/// `u:<player> t:<time> r:<radius>`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditQuery {
+    pub player: Option<String>,
+    /// Only edits made after this many milliseconds since the unix epoch
+    pub since: Option<u64>,
+    /// Only edits within the columns between these corners
+    pub region: Option<(Vec3<i32>, Vec3<i32>)>,
+}
+
+impl EditQuery {
+    /// Parse query arguments, with times counted back from `now` in milliseconds since the
+    /// unix epoch and radii reaching out from `center`
+    pub fn parse(args: &[&str], now: u64, center: &Vec3<i32>) -> Result<Self, AuditError> {
+        let mut query = Self::default();
+
+        for arg in args {
+            match arg.split_once(':') {
+                Some(("u", name)) if !name.is_empty() => query.player = Some(name.to_owned()),
+                Some(("t", time)) => {
+                    let secs = parse_duration(time).ok_or(AuditError::InvalidArgument)?;
+                    query.since = Some(now.saturating_sub(secs * 1000));
+                }
+                Some(("r", radius)) => {
+                    let radius = radius
+                        .parse::<i32>()
+                        .ok()
+                        .filter(|radius| *radius >= 0)
+                        .ok_or(AuditError::InvalidArgument)?;
+
+                    let reach = Vec3(radius, 0, radius);
+                    query.region = Some((center.sub(&reach), center.add(&reach)));
+                }
+                _ => return Err(AuditError::InvalidArgument),
+            }
+        }
+
+        Ok(query)
+    }
+
+    pub fn matches(&self, record: &EditRecord) -> bool {
+        let edit = &record.edit;
+
+        self.player
+            .as_ref()
+            .map_or(true, |name| record.player.eq_ignore_ascii_case(name))
+            && self.since.map_or(true, |since| edit.timestamp >= since)
+            && self.region.as_ref().map_or(true, |(min, max)| {
+                edit.voxel.0 >= min.0
+                    && edit.voxel.0 <= max.0
+                    && edit.voxel.2 >= min.2
+                    && edit.voxel.2 <= max.2
+            })
+    }
+}
+
+/// What `/co` does with the edits it finds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Lookup,
+    Rollback,
+}
+
+/// A `/co` command waiting on the edit log to be read
+#[derive(Debug)]
+pub struct PendingAudit {
+    pub player_id: usize,
+    pub action: AuditAction,
+    pub query: EditQuery,
+    /// Milliseconds since the unix epoch when the command was run
+    pub now: u64,
+}
+
+/// `/co` commands waiting on the edit log, which is read off the game loop
+pub struct Audits {
+    pending: HashMap<u64, PendingAudit>,
+    sender: Arc<Sender<LoadedEdits>>,
+    receiver: Receiver<LoadedEdits>,
+}
+
+impl Default for Audits {
+    fn default() -> Self {
+        let (sender, receiver) = unbounded();
+
+        Self {
+            pending: HashMap::new(),
+            sender: Arc::new(sender),
+            receiver,
+        }
+    }
+}
+
+impl Audits {
+    /// Have the edits a command is about read from the edit log, to be finished once
+    /// they're in. Lookups read one more than they list, to tell whether there are more.
+    pub fn request(&mut self, storage: &AsyncStorage, audit: PendingAudit) {
+        let limit = match audit.action {
+            AuditAction::Lookup => AUDIT_LOOKUP_LIMIT + 1,
+            AuditAction::Rollback => AUDIT_ROLLBACK_LIMIT,
+        };
+
+        let request = storage.request_edits(&audit.query, limit, &self.sender);
+        self.pending.insert(request, audit);
+    }
+
+    /// The commands whose edits have been read, with the records, newest first
+    pub fn answered(&mut self) -> Vec<(PendingAudit, Vec<EditRecord>)> {
+        let mut answered = vec![];
+
+        for (request, edits) in self.receiver.try_iter() {
+            if let Some(audit) = self.pending.remove(&request) {
+                let records = edits
+                    .into_iter()
+                    .filter_map(|value| serde_json::from_value::<EditRecord>(value).ok())
+                    .collect();
+
+                answered.push((audit, records));
+            }
+        }
+
+        answered
+    }
+}
+
+/// Seconds in a duration like `30s`, `10m`, `2h`, `3d` or `1w`
+pub fn parse_duration(duration: &str) -> Option<u64> {
+    let unit = match duration.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+
+    let count = duration[..duration.len() - 1].parse::<u64>().ok()?;
+
+    Some(count * unit)
+}
+
+/// How long ago something happened, roughly, like `5m ago`
+pub fn format_age(millis: u64) -> String {
+    let secs = millis / 1000;
+
+    match secs {
+        0..=59 => format!("{}s ago", secs),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AuditError {
+    NotAdmin,
+    InvalidArgument,
+    /// Rollbacks need a time range, so that a typo can't undo the whole world
+    MissingTime,
+    NoMatches,
+    Usage(&'static str),
+}
+
+impl fmt::Display for AuditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditError::NotAdmin => write!(f, "Only admins can look into the edit log."),
+            AuditError::InvalidArgument => write!(
+                f,
+                "Arguments are u:<player>, t:<time like 30m or 2d> and r:<radius>."
+            ),
+            AuditError::MissingTime => write!(f, "Rollbacks need a time, like t:1h."),
+            AuditError::NoMatches => write!(f, "No block edits match."),
+            AuditError::Usage(usage) => write!(f, "Usage: {}", usage),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_query_works() {
+        assert_eq!(parse_duration("90s"), Some(90));
+        assert_eq!(parse_duration("2h"), Some(7200));
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("2y"), None);
+
+        let now = 1_000_000;
+        let query = EditQuery::parse(&["u:Ian", "t:10m", "r:5"], now, &Vec3(0, 64, 0)).unwrap();
+
+        let record = |player: &str, voxel: Vec3<i32>, timestamp: u64| EditRecord {
+            player: player.to_owned(),
+            uuid: None,
+            edit: JournalEntry {
+                voxel,
+                old_id: 1,
+                new_id: 0,
+                timestamp,
+            },
+        };
+
+        assert!(query.matches(&record("ian", Vec3(5, 0, -5), now)));
+        assert!(!query.matches(&record("bob", Vec3(5, 0, -5), now)));
+        assert!(!query.matches(&record("ian", Vec3(6, 0, 0), now)));
+        assert!(!query.matches(&record("ian", Vec3(0, 0, 0), now - 601_000)));
+
+        assert_eq!(
+            EditQuery::parse(&["x:1"], now, &Vec3(0, 0, 0)),
+            Err(AuditError::InvalidArgument)
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+use server_common::vec::Vec2;
+
+/// Shape of a world border, in voxels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BorderShape {
+    Circle { center: [f32; 2], radius: f32 },
+    Rectangle { min: [f32; 2], max: [f32; 2] },
+}
+
+/// A horizontal limit of a world.
+///
+/// Chunks fully outside of the border are left empty, and entities walking past it
+/// are pushed back. Entities found further than `buffer` voxels out also take damage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldBorder {
+    #[serde(flatten)]
+    pub shape: BorderShape,
+    #[serde(default)]
+    pub buffer: f32,
+    /// Damage dealt past the buffer, at most once per invulnerability time
+    #[serde(default)]
+    pub damage: f32,
+}
+
+impl WorldBorder {
+    /// Get the closest point within the border
+    pub fn clamp(&self, x: f32, z: f32) -> (f32, f32) {
+        match &self.shape {
+            BorderShape::Circle { center, radius } => {
+                let (dx, dz) = (x - center[0], z - center[1]);
+                let len = (dx * dx + dz * dz).sqrt();
+
+                if len <= *radius {
+                    return (x, z);
+                }
+
+                (center[0] + dx / len * radius, center[1] + dz / len * radius)
+            }
+            BorderShape::Rectangle { min, max } => {
+                (x.max(min[0]).min(max[0]), z.max(min[1]).min(max[1]))
+            }
+        }
+    }
+
+    /// How far outside of the border a point is, 0 if inside
+    pub fn distance_outside(&self, x: f32, z: f32) -> f32 {
+        let (cx, cz) = self.clamp(x, z);
+        ((x - cx).powi(2) + (z - cz).powi(2)).sqrt()
+    }
+
+    pub fn contains(&self, x: f32, z: f32) -> bool {
+        self.distance_outside(x, z) <= 0.0
+    }
+
+    /// Whether any part of a chunk is within the border
+    pub fn contains_chunk(&self, coords: &Vec2<i32>, chunk_size: usize) -> bool {
+        let size = chunk_size as f32;
+        let (min_x, min_z) = (coords.0 as f32 * size, coords.1 as f32 * size);
+        let (max_x, max_z) = (min_x + size, min_z + size);
+
+        match &self.shape {
+            BorderShape::Circle { center, radius } => {
+                // closest point of the chunk to the center
+                let x = center[0].max(min_x).min(max_x);
+                let z = center[1].max(min_z).min(max_z);
+                (x - center[0]).powi(2) + (z - center[1]).powi(2) <= radius * radius
+            }
+            BorderShape::Rectangle { min, max } => {
+                min_x <= max[0] && max_x >= min[0] && min_z <= max[1] && max_z >= min[1]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn border_works() {
+        let circle = WorldBorder {
+            shape: BorderShape::Circle {
+                center: [0.0, 0.0],
+                radius: 10.0,
+            },
+            buffer: 0.0,
+            damage: 0.0,
+        };
+
+        assert!(circle.contains(6.0, 8.0));
+        assert_eq!(circle.clamp(0.0, 20.0), (0.0, 10.0));
+        assert_eq!(circle.distance_outside(0.0, -15.0), 5.0);
+        assert!(circle.contains_chunk(&Vec2(0, 0), 16));
+        assert!(!circle.contains_chunk(&Vec2(1, 1), 16));
+
+        let rectangle = WorldBorder {
+            shape: BorderShape::Rectangle {
+                min: [-8.0, -8.0],
+                max: [8.0, 8.0],
+            },
+            buffer: 0.0,
+            damage: 0.0,
+        };
+
+        assert!(rectangle.contains(8.0, -8.0));
+        assert_eq!(rectangle.clamp(20.0, 0.0), (8.0, 0.0));
+        assert!(rectangle.contains_chunk(&Vec2(-1, 0), 8));
+        assert!(!rectangle.contains_chunk(&Vec2(2, 0), 8));
+    }
+}
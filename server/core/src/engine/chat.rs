@@ -0,0 +1,194 @@
+use std::fmt;
+
+use hashbrown::{HashMap, HashSet};
+
+use crate::constants::MAX_CHAT_LENGTH;
+
+/// Who hears a chat message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChatChannel {
+    /// Everyone in the world
+    Global,
+    /// Players within `LOCAL_CHAT_RADIUS` of the sender
+    Local,
+    /// Players on the sender's team, see `Teams`
+    Team,
+}
+
+impl ChatChannel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "global" | "g" => Some(ChatChannel::Global),
+            "local" | "l" => Some(ChatChannel::Local),
+            "team" | "t" => Some(ChatChannel::Team),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChatChannel::Global => "global",
+            ChatChannel::Local => "local",
+            ChatChannel::Team => "team",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChatError {
+    UnknownChannel,
+    NoTeam,
+    Muted(ChatChannel),
+    Silenced(u64),
+    Filtered,
+    UnknownPlayer,
+    SamePlayer,
+    Empty,
+    Usage(&'static str),
+}
+
+impl fmt::Display for ChatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChatError::UnknownChannel => write!(f, "Channels are global, local and team."),
+            ChatError::NoTeam => write!(f, "Join a team first with /team join <name>."),
+            ChatError::Muted(channel) => write!(
+                f,
+                "The {} channel is muted, /unmute {} to talk in it.",
+                channel.name(),
+                channel.name()
+            ),
+            ChatError::Silenced(seconds) => {
+                write!(f, "You can't chat for another {} seconds.", seconds)
+            }
+            ChatError::Filtered => write!(f, "That message isn't allowed here."),
+            ChatError::UnknownPlayer => write!(f, "Nobody online goes by that name."),
+            ChatError::SamePlayer => write!(f, "Whispers can't be sent to oneself."),
+            ChatError::Empty => write!(f, "There's nothing to send."),
+            ChatError::Usage(usage) => write!(f, "Usage: {}", usage),
+        }
+    }
+}
+
+/// A player's chat settings
+#[derive(Debug, Default)]
+struct Member {
+    /// Channel messages go to when not sent to one in particular, global if none
+    channel: Option<ChatChannel>,
+    muted: HashSet<ChatChannel>,
+}
+
+/// Chat channels and mutes of the players in a world, by player id
+#[derive(Debug, Default)]
+pub struct Chat {
+    members: HashMap<usize, Member>,
+}
+
+impl Chat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget a player's settings as they leave
+    pub fn remove(&mut self, player_id: usize) {
+        self.members.remove(&player_id);
+    }
+
+    /// Channel a player talks in by default
+    pub fn channel(&self, player_id: usize) -> ChatChannel {
+        self.members
+            .get(&player_id)
+            .and_then(|member| member.channel)
+            .unwrap_or(ChatChannel::Global)
+    }
+
+    pub fn set_channel(&mut self, player_id: usize, channel: ChatChannel) {
+        self.members.entry(player_id).or_default().channel = Some(channel);
+    }
+
+    /// Mute or unmute a channel for a player, returning whether anything changed
+    pub fn set_muted(&mut self, player_id: usize, channel: ChatChannel, muted: bool) -> bool {
+        let member = self.members.entry(player_id).or_default();
+
+        if muted {
+            member.muted.insert(channel)
+        } else {
+            member.muted.remove(&channel)
+        }
+    }
+
+    /// Whether a player hears a channel
+    pub fn listens(&self, player_id: usize, channel: ChatChannel) -> bool {
+        self.members
+            .get(&player_id)
+            .map_or(true, |member| !member.muted.contains(&channel))
+    }
+}
+
+/// Make a player's message safe to show, as clients render chat as markup
+///
+/// Control characters are dropped, markup characters are escaped and the message is cut to
+/// `MAX_CHAT_LENGTH` characters. `**bold**` and `*italic*` are then turned into their tags,
+/// the only markup players get to use.
+pub fn sanitize(body: &str) -> String {
+    let escaped = body
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_CHAT_LENGTH)
+        .map(|c| match c {
+            '&' => "&amp;".to_owned(),
+            '<' => "&lt;".to_owned(),
+            '>' => "&gt;".to_owned(),
+            '"' => "&quot;".to_owned(),
+            '\'' => "&#39;".to_owned(),
+            c => c.to_string(),
+        })
+        .collect::<String>();
+
+    let bold = format_pairs(&escaped, "**", "b");
+    format_pairs(&bold, "*", "i")
+}
+
+/// Wrap the text between each pair of markers in a tag, leaving an unpaired marker as is
+fn format_pairs(text: &str, marker: &str, tag: &str) -> String {
+    let parts = text.split(marker).collect::<Vec<_>>();
+    let mut formatted = String::with_capacity(text.len());
+
+    for (i, part) in parts.iter().enumerate() {
+        if i == 0 {
+            formatted.push_str(part);
+        } else if i % 2 == 1 && i + 1 < parts.len() {
+            formatted.push_str(&format!("<{}>{}", tag, part));
+        } else if i % 2 == 0 {
+            formatted.push_str(&format!("</{}>{}", tag, part));
+        } else {
+            formatted.push_str(marker);
+            formatted.push_str(part);
+        }
+    }
+
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_works() {
+        assert_eq!(
+            sanitize("<script>alert(1)</script>"),
+            "&lt;script&gt;alert(1)&lt;/script&gt;"
+        );
+        assert_eq!(sanitize("**hi** there *you*"), "<b>hi</b> there <i>you</i>");
+
+        // unpaired markers are left alone
+        assert_eq!(sanitize("2 * 3 = 6"), "2 * 3 = 6");
+        assert_eq!(sanitize(" a\u{7}b "), "ab");
+        assert_eq!(
+            sanitize(&"a".repeat(MAX_CHAT_LENGTH * 2)).len(),
+            MAX_CHAT_LENGTH
+        );
+    }
+}
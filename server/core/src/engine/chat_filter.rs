@@ -0,0 +1,204 @@
+use hashbrown::HashMap;
+use log::warn;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+/// What happens to a message a filter rule matches, from the mildest on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FilterAction {
+    /// Hash out the matched text, sending the rest
+    Censor,
+    /// Keep the message from being sent
+    Block,
+    /// Keep the message from being sent and mute its sender for a while
+    Mute,
+}
+
+impl FilterAction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "censor" => Some(FilterAction::Censor),
+            "block" => Some(FilterAction::Block),
+            "mute" => Some(FilterAction::Mute),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterAction::Censor => "censor",
+            FilterAction::Block => "block",
+            FilterAction::Mute => "mute",
+        }
+    }
+}
+
+/// A rule of the chat filter, matching either a regular expression or any of a list of
+/// whole words, case insensitively
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilterRule {
+    #[serde(default)]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub words: Vec<String>,
+    pub action: FilterAction,
+}
+
+impl FilterRule {
+    fn compile(&self) -> Result<Regex, regex::Error> {
+        let pattern = match &self.pattern {
+            Some(pattern) => pattern.to_owned(),
+            None => format!(
+                r"\b(?:{})\b",
+                self.words
+                    .iter()
+                    .map(|word| regex::escape(word))
+                    .collect::<Vec<_>>()
+                    .join("|")
+            ),
+        };
+
+        RegexBuilder::new(&pattern).case_insensitive(true).build()
+    }
+}
+
+/// What the chat filter made of a message
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Send the message, censored if anything matched
+    Pass(String),
+    Block,
+    Mute,
+}
+
+/// Rules every player message goes through before it's sent, from the world's config and
+/// from scripts, along with the players muted by them
+///
+/// Every rule is checked against a message, and the harshest action among the rules matching
+/// it is taken.
+#[derive(Debug, Default)]
+pub struct ChatFilter {
+    rules: Vec<(Regex, FilterAction)>,
+    script_rules: Vec<(Regex, FilterAction)>,
+    /// Until when players are muted, in milliseconds since the unix epoch
+    muted_until: HashMap<usize, u64>,
+}
+
+impl ChatFilter {
+    pub fn new(rules: &[FilterRule]) -> Self {
+        Self {
+            rules: compile_rules(rules),
+            ..Default::default()
+        }
+    }
+
+    /// Swap out the rules scripts added, as they're reloaded
+    pub fn set_script_rules(&mut self, rules: &[FilterRule]) {
+        self.script_rules = compile_rules(rules);
+    }
+
+    /// Run a message through the rules
+    pub fn check(&self, body: &str) -> Verdict {
+        let mut action = None;
+        let mut censored = body.to_owned();
+
+        for (regex, rule_action) in self.rules.iter().chain(self.script_rules.iter()) {
+            if !regex.is_match(&censored) {
+                continue;
+            }
+
+            if *rule_action == FilterAction::Censor {
+                censored = regex
+                    .replace_all(&censored, |caps: &regex::Captures| {
+                        "#".repeat(caps[0].chars().count())
+                    })
+                    .into_owned();
+            }
+
+            action = action.max(Some(*rule_action));
+        }
+
+        match action {
+            Some(FilterAction::Mute) => Verdict::Mute,
+            Some(FilterAction::Block) => Verdict::Block,
+            _ => Verdict::Pass(censored),
+        }
+    }
+
+    /// Mute a player until a time in milliseconds since the unix epoch, or unmute them
+    pub fn mute(&mut self, player_id: usize, until: Option<u64>) {
+        match until {
+            Some(until) => self.muted_until.insert(player_id, until),
+            None => self.muted_until.remove(&player_id),
+        };
+    }
+
+    /// Seconds a player is muted for from now, if they are
+    pub fn muted_for(&mut self, player_id: usize, now: u64) -> Option<u64> {
+        let until = *self.muted_until.get(&player_id)?;
+
+        if until <= now {
+            self.muted_until.remove(&player_id);
+            return None;
+        }
+
+        Some((until - now + 999) / 1000)
+    }
+}
+
+fn compile_rules(rules: &[FilterRule]) -> Vec<(Regex, FilterAction)> {
+    rules
+        .iter()
+        .filter(|rule| rule.pattern.is_some() || !rule.words.is_empty())
+        .filter_map(|rule| match rule.compile() {
+            Ok(regex) => Some((regex, rule.action)),
+            Err(e) => {
+                warn!("Skipping chat filter rule {:?}: {}", rule, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_filter_works() {
+        let rules = vec![
+            FilterRule {
+                pattern: None,
+                words: vec!["darn".to_owned(), "heck".to_owned()],
+                action: FilterAction::Censor,
+            },
+            FilterRule {
+                pattern: Some(r"free\s+coins".to_owned()),
+                words: vec![],
+                action: FilterAction::Block,
+            },
+        ];
+        let mut filter = ChatFilter::new(&rules);
+
+        assert_eq!(
+            filter.check("Darn it, what the heck"),
+            Verdict::Pass("#### it, what the ####".to_owned())
+        );
+        // whole words only
+        assert_eq!(filter.check("darned"), Verdict::Pass("darned".to_owned()));
+        assert_eq!(filter.check("get FREE  coins, darn"), Verdict::Block);
+
+        filter.set_script_rules(&[FilterRule {
+            pattern: Some("spam".to_owned()),
+            words: vec![],
+            action: FilterAction::Mute,
+        }]);
+        assert_eq!(filter.check("spam spam"), Verdict::Mute);
+
+        filter.mute(1, Some(10_500));
+        assert_eq!(filter.muted_for(1, 10_000), Some(1));
+        assert_eq!(filter.muted_for(1, 10_500), None);
+    }
+}
@@ -6,26 +6,26 @@ use serde::{Deserialize, Serialize};
 
 use hashbrown::HashSet;
 
-use std::{
-    fs::File,
-    io::{Read, Write},
-    path::Path,
-};
+use std::io::{Read, Write};
 
 use crate::gen::blocks::{BlockRotation, Blocks};
 
 use super::super::{
-    engine::world::WorldConfig,
+    engine::{
+        entities::SuspendedEntity,
+        registry::Registry,
+        storage::{Storage, WriteBatch},
+        world::WorldConfig,
+    },
     gen::lights::{LightColor, Lights},
     network::models::ChunkProtocol,
 };
 
 use server_common::{
-    ndarray::{ndarray, Ndarray},
+    array::{Array2, Array3},
     types::MeshType,
     vec::{Vec2, Vec3},
 };
-use server_utils::convert;
 
 use super::super::constants::DATA_PADDING;
 
@@ -39,7 +39,17 @@ pub struct Meshes {
     pub transparent: Option<MeshType>,
 }
 
-/// Prototype for chunk's internal data used to send to client
+/// What a sub-chunk is made of
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubChunkFill {
+    /// Nothing but air
+    Empty,
+    /// Opaque blocks all the way through and all around
+    Solid,
+    Mixed,
+}
+
+/// Prototype for chunk's internal data kept in a world's storage
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ChunkFileData {
@@ -47,6 +57,8 @@ struct ChunkFileData {
     voxels: String,
     lights: String,
     height_map: String,
+    #[serde(default)]
+    entities: Vec<SuspendedEntity>,
 }
 
 /// Base unit column for voxels
@@ -54,13 +66,11 @@ struct ChunkFileData {
 /// Dimensions are specified as `max_height * chunk_size * max_height`
 #[derive(Clone, Debug)]
 pub struct Chunk {
-    pub name: String,
-
     pub coords: Vec2<i32>,
 
-    voxels: Ndarray<u32>,
-    lights: Ndarray<u32>,
-    height_map: Ndarray<u32>,
+    voxels: Array3<u32>,
+    lights: Array3<u32>,
+    height_map: Array2<u32>,
 
     pub min: Vec3<i32>,
     pub max: Vec3<i32>,
@@ -72,7 +82,10 @@ pub struct Chunk {
     pub needs_terrain: bool,
     pub needs_decoration: bool,
 
+    /// Whether the chunk is only made of air
     pub is_empty: bool,
+    /// What each sub-chunk is made of, refreshed every time the chunk is meshed
+    pub sub_chunk_fills: Vec<SubChunkFill>,
     pub is_dirty: bool,
     pub dirty_levels: HashSet<u32>,
 
@@ -82,44 +95,30 @@ pub struct Chunk {
 
     pub meshes: Vec<Meshes>,
 
-    pub file: String,
+    /// Mobs saved away with the chunk, spawned again and emptied once it's loaded
+    pub entities: Vec<SuspendedEntity>,
 }
 
 impl Chunk {
-    /// Constructor for a chunk. Attempts to load from existing files,
+    /// Constructor for a chunk. Loads from the data read out of the world's storage,
     /// otherwise is marked to be generated.
-    pub fn new(coords: Vec2<i32>, config: &WorldConfig, folder: &Path) -> Self {
+    pub fn new(coords: Vec2<i32>, config: &WorldConfig, saved: Option<serde_json::Value>) -> Self {
         let Vec2(cx, cz) = coords;
 
         let &WorldConfig {
             chunk_size: size,
             dimension,
             max_height,
-            save,
             ..
         } = config;
 
         let max_height = max_height as usize;
 
-        let name = convert::get_chunk_name(cx, cz);
-
-        let voxels = ndarray(
-            vec![
-                size + DATA_PADDING * 2,
-                max_height as usize,
-                size + DATA_PADDING * 2,
-            ],
-            0,
-        );
-        let lights = ndarray(
-            vec![
-                size + DATA_PADDING * 2,
-                max_height as usize,
-                size + DATA_PADDING * 2,
-            ],
-            0,
-        );
-        let height_map = ndarray(vec![size + DATA_PADDING * 2, size + DATA_PADDING * 2], 0);
+        let width = size + DATA_PADDING * 2;
+
+        let voxels = Array3::new([width, max_height, width], 0);
+        let lights = Array3::new([width, max_height, width], 0);
+        let height_map = Array2::new([width, width], 0);
 
         let coords3 = Vec3(cx, 0, cz);
 
@@ -134,14 +133,7 @@ impl Chunk {
                 .add(&Vec3(0, max_height as i32, 0));
         let max = max_inner.add(&paddings);
 
-        let mut path = folder.to_path_buf();
-        let mut file_name = name.clone();
-        file_name.push_str(".json");
-        path.push(file_name.as_str());
-
         let mut new_chunk = Self {
-            name,
-
             coords,
             voxels,
             lights,
@@ -158,6 +150,7 @@ impl Chunk {
             needs_decoration: true,
 
             is_empty: false,
+            sub_chunk_fills: Vec::new(),
             is_dirty: true,
             dirty_levels: HashSet::new(),
 
@@ -167,56 +160,62 @@ impl Chunk {
 
             meshes: Vec::new(),
 
-            file: path.into_os_string().into_string().unwrap(),
+            entities: Vec::new(),
         };
 
-        if save {
-            new_chunk.try_load();
+        if let Some(data) = saved {
+            new_chunk.load(data);
         }
 
         new_chunk
     }
 
-    /// Try to load the chunk from saved data file
-    pub fn try_load(&mut self) {
-        // open a file for reading
-
-        if let Ok(chunk_data) = File::open(&self.file) {
-            let data: ChunkFileData = serde_json::from_reader(chunk_data)
-                .unwrap_or_else(|_| panic!("Couldn't load chunk file: {:?}", self.coords));
-
-            let ChunkFileData {
-                needs_propagation,
-                voxels,
-                lights,
-                height_map,
-            } = data;
-
-            self.needs_saving = false;
-            self.needs_terrain = false;
-            self.needs_decoration = false;
-            self.needs_propagation = needs_propagation;
-
-            let decode_base64 = |base: String| {
-                let decoded = base64::decode(base).unwrap();
-                let mut decoder = Decoder::new(&decoded[..]).unwrap();
-                let mut buf = Vec::new();
-                decoder.read_to_end(&mut buf).unwrap();
-                let mut data = vec![0; buf.len() / 4];
-                LittleEndian::read_u32_into(&buf, &mut data);
-                data
-            };
-
-            self.lights.data = decode_base64(lights);
-            self.voxels.data = decode_base64(voxels);
-            self.height_map.data = decode_base64(height_map);
-        }
+    /// Load the chunk from its saved data
+    pub fn load(&mut self, chunk_data: serde_json::Value) {
+        let data: ChunkFileData = serde_json::from_value(chunk_data)
+            .unwrap_or_else(|_| panic!("Couldn't load chunk data: {:?}", self.coords));
+
+        let ChunkFileData {
+            needs_propagation,
+            voxels,
+            lights,
+            height_map,
+            entities,
+        } = data;
+
+        self.needs_saving = false;
+        self.needs_terrain = false;
+        self.needs_decoration = false;
+        self.needs_propagation = needs_propagation;
+        self.entities = entities;
+
+        let decode_base64 = |base: String| {
+            let decoded = base64::decode(base).unwrap();
+            let mut decoder = Decoder::new(&decoded[..]).unwrap();
+            let mut buf = Vec::new();
+            decoder.read_to_end(&mut buf).unwrap();
+            let mut data = vec![0; buf.len() / 4];
+            LittleEndian::read_u32_into(&buf, &mut data);
+            data
+        };
+
+        self.lights.data = decode_base64(lights);
+        self.voxels.data = decode_base64(voxels);
+        self.height_map.data = decode_base64(height_map);
+
+        self.calc_is_empty();
     }
 
-    /// Save the chunk into a JSON compressed file
-    pub fn save(&self) {
-        let mut file = File::create(&self.file).expect("Could not create chunk file.");
+    /// Save the chunk on its own, see `to_saved` to save many at once
+    pub fn save(&self, storage: &dyn Storage) {
+        storage.write(WriteBatch {
+            chunks: vec![(self.coords.to_owned(), self.to_saved())],
+            ..Default::default()
+        });
+    }
 
+    /// The chunk's voxels, lights and height map compressed into JSON to be saved
+    pub fn to_saved(&self) -> serde_json::Value {
         let to_base_64 = |data: &Vec<u32>| {
             let mut bytes = vec![0; data.len() * 4];
             LittleEndian::write_u32_into(data, &mut bytes);
@@ -232,12 +231,10 @@ impl Chunk {
             lights: to_base_64(&self.lights.data),
             voxels: to_base_64(&self.voxels.data),
             height_map: to_base_64(&self.height_map.data),
+            entities: self.entities.clone(),
         };
 
-        let j = serde_json::to_string(&data).unwrap();
-
-        file.write_all(j.as_bytes())
-            .expect("Unable to write to chunk file.");
+        serde_json::to_value(&data).unwrap()
     }
 
     /// Get the raw value of voxel
@@ -249,7 +246,7 @@ impl Chunk {
         }
 
         let Vec3(lx, ly, lz) = self.to_local(vx, vy, vz);
-        self.voxels[&[lx as usize, ly as usize, lz as usize]]
+        self.voxels[[lx as usize, ly as usize, lz as usize]]
     }
 
     /// Set the raw value of voxel
@@ -259,7 +256,7 @@ impl Chunk {
         assert!(self.contains(vx, vy, vz,));
 
         let Vec3(lx, ly, lz) = self.to_local(vx, vy, vz);
-        self.voxels[&[lx as usize, ly as usize, lz as usize]] = value;
+        self.voxels[[lx as usize, ly as usize, lz as usize]] = value;
     }
 
     /// Get a voxel type within chunk by voxel coordinates
@@ -277,6 +274,10 @@ impl Chunk {
     pub fn set_voxel(&mut self, vx: i32, vy: i32, vz: i32, id: u32) {
         let value = Blocks::insert_id(0, id);
         self.set_raw_voxel(vx, vy, vz, value);
+
+        if id != 0 {
+            self.is_empty = false;
+        }
     }
 
     /// Get a voxel rotation within chunk by voxel coordinates
@@ -436,7 +437,7 @@ impl Chunk {
         }
 
         let Vec3(lx, _, lz) = self.to_local(vx, 0, vz);
-        self.height_map[&[lx as usize, lz as usize]]
+        self.height_map[[lx as usize, lz as usize]]
     }
 
     /// Set the max height of a voxel column
@@ -446,55 +447,140 @@ impl Chunk {
         assert!(self.contains(vx, 0, vz,));
 
         let Vec3(lx, _, lz) = self.to_local(vx, 0, vz);
-        self.height_map[&[lx as usize, lz as usize]] = height;
+        self.height_map[[lx as usize, lz as usize]] = height;
     }
 
-    /// Getter the entire voxel ndarray
+    /// Getter the entire voxel array
     #[inline]
-    pub fn get_voxels(&self) -> &Ndarray<u32> {
+    pub fn get_voxels(&self) -> &Array3<u32> {
         &self.voxels
     }
 
-    /// Setter the entire voxel ndarray
+    /// Setter the entire voxel array
     #[inline]
-    pub fn set_voxels(&mut self, data: Ndarray<u32>) {
+    pub fn set_voxels(&mut self, data: Array3<u32>) {
         self.voxels = data;
+        self.calc_is_empty();
     }
 
-    /// Getter for the entire lights ndarray
+    /// Getter for the entire lights array
     #[inline]
-    pub fn get_lights(&self) -> &Ndarray<u32> {
+    pub fn get_lights(&self) -> &Array3<u32> {
         &self.lights
     }
 
-    /// Setter for the entire lights ndarray
+    /// Setter for the entire lights array
     #[inline]
-    pub fn set_lights(&mut self, data: Ndarray<u32>) {
+    pub fn set_lights(&mut self, data: Array3<u32>) {
         self.lights = data;
     }
 
     /// Getter for the entire height map
     #[inline]
-    pub fn get_height_map(&self) -> &Ndarray<u32> {
+    pub fn get_height_map(&self) -> &Array2<u32> {
         &self.height_map
     }
 
     /// Setter for the entire height map
     #[inline]
-    pub fn set_height_map(&mut self, data: Ndarray<u32>) {
+    pub fn set_height_map(&mut self, data: Array2<u32>) {
         self.height_map = data;
     }
 
-    /// Calculate and mark a sub-chunk as dirty at a certain height
+    /// Check whether there's anything but air in this chunk
+    pub fn calc_is_empty(&mut self) {
+        self.is_empty = self
+            .voxels
+            .data
+            .iter()
+            .all(|&raw| Blocks::extract_id(raw) == 0);
+    }
+
+    /// Check what each sub-chunk is made of, so that meshing can skip sub-chunks that
+    /// are either all air or buried under opaque blocks on every side.
+    pub fn calc_sub_chunk_fills(&mut self, registry: &Registry, sub_chunks: u32) {
+        let max_height = self.max_height as i32;
+        let unit = max_height / sub_chunks as i32;
+
+        let Vec3(min_x, _, min_z) = self.min;
+        let Vec3(max_x, _, max_z) = self.max;
+        let Vec3(inner_min_x, _, inner_min_z) = self.min_inner;
+        let Vec3(inner_max_x, _, inner_max_z) = self.max_inner;
+
+        let is_opaque = |id: u32| {
+            let block = registry.get_block_by_id(id);
+            block.is_solid && !block.is_transparent
+        };
+
+        self.sub_chunk_fills = (0..sub_chunks as i32)
+            .map(|sub_chunk| {
+                if self.is_empty {
+                    return SubChunkFill::Empty;
+                }
+
+                let start = sub_chunk * unit;
+                let end = start + unit;
+
+                let mut is_empty = true;
+
+                'inner: for vx in inner_min_x..inner_max_x {
+                    for vz in inner_min_z..inner_max_z {
+                        for vy in start..end {
+                            if self.get_voxel(vx, vy, vz) != 0 {
+                                is_empty = false;
+                                break 'inner;
+                            }
+                        }
+                    }
+                }
+
+                if is_empty {
+                    return SubChunkFill::Empty;
+                }
+
+                // faces only show up next to non-opaque voxels, so check one voxel around
+                for vx in min_x..max_x {
+                    for vz in min_z..max_z {
+                        for vy in (start - 1).max(0)..(end + 1).min(max_height) {
+                            if !is_opaque(self.get_voxel(vx, vy, vz)) {
+                                return SubChunkFill::Mixed;
+                            }
+                        }
+                    }
+                }
+
+                SubChunkFill::Solid
+            })
+            .collect();
+    }
+
+    /// Whether a sub-chunk has nothing to be meshed
+    pub fn is_sub_chunk_hidden(&self, sub_chunk: u32) -> bool {
+        self.is_empty
+            || matches!(
+                self.sub_chunk_fills.get(sub_chunk as usize),
+                Some(SubChunkFill::Empty) | Some(SubChunkFill::Solid)
+            )
+    }
+
+    /// Light up an empty chunk without propagating, which is either fully lit by the
+    /// sky or left dark.
+    pub fn set_open_lights(&mut self, sky_light: bool, max_light_level: u32) {
+        let level = if sky_light { max_light_level } else { 0 };
+        self.lights = Array3::new(self.lights.shape, Lights::insert_sunlight(0, level));
+    }
+
+    /// Calculate and mark a sub-chunk as dirty at a certain height, along with the one next
+    /// to it if the voxel is on the edge between them
     pub fn calc_dirty_levels(&mut self, vy: i32, max_height: u32, sub_chunks: u32) {
         let vy = vy as u32;
         let unit = max_height / sub_chunks;
         let level = vy / unit;
 
         self.dirty_levels.insert(level);
-        if vy % sub_chunks == 0 && level >= 1 {
+        if vy % unit == 0 && level >= 1 {
             self.dirty_levels.insert(level - 1);
-        } else if vy % sub_chunks == sub_chunks - 1 && level < sub_chunks - 1 {
+        } else if vy % unit == unit - 1 && level < sub_chunks - 1 {
             self.dirty_levels.insert(level + 1);
         }
     }
@@ -513,6 +599,7 @@ impl Chunk {
         ChunkProtocol {
             x: self.coords.0,
             z: self.coords.1,
+            empty: self.is_empty,
             meshes: if needs_meshes {
                 Some(match mesh {
                     MeshLevel::All => self.meshes.to_owned(),
@@ -541,49 +628,49 @@ impl Chunk {
     /// Get the red light value locally
     #[inline]
     fn get_local_red_light(&self, lx: usize, ly: usize, lz: usize) -> u32 {
-        Lights::extract_red_light(self.lights[&[lx, ly, lz]])
+        Lights::extract_red_light(self.lights[[lx, ly, lz]])
     }
 
     /// Set the red light value locally
     #[inline]
     fn set_local_red_light(&mut self, lx: usize, ly: usize, lz: usize, level: u32) {
-        self.lights[&[lx, ly, lz]] = Lights::insert_red_light(self.lights[&[lx, ly, lz]], level);
+        self.lights[[lx, ly, lz]] = Lights::insert_red_light(self.lights[[lx, ly, lz]], level);
     }
 
     /// Get the green light value locally
     #[inline]
     fn get_local_green_light(&self, lx: usize, ly: usize, lz: usize) -> u32 {
-        Lights::extract_green_light(self.lights[&[lx, ly, lz]])
+        Lights::extract_green_light(self.lights[[lx, ly, lz]])
     }
 
     /// Set the green light value locally
     #[inline]
     fn set_local_green_light(&mut self, lx: usize, ly: usize, lz: usize, level: u32) {
-        self.lights[&[lx, ly, lz]] = Lights::insert_green_light(self.lights[&[lx, ly, lz]], level);
+        self.lights[[lx, ly, lz]] = Lights::insert_green_light(self.lights[[lx, ly, lz]], level);
     }
 
     /// Get the blue light value locally
     #[inline]
     fn get_local_blue_light(&self, lx: usize, ly: usize, lz: usize) -> u32 {
-        Lights::extract_blue_light(self.lights[&[lx, ly, lz]])
+        Lights::extract_blue_light(self.lights[[lx, ly, lz]])
     }
 
     /// Set the blue light value locally
     #[inline]
     fn set_local_blue_light(&mut self, lx: usize, ly: usize, lz: usize, level: u32) {
-        self.lights[&[lx, ly, lz]] = Lights::insert_blue_light(self.lights[&[lx, ly, lz]], level);
+        self.lights[[lx, ly, lz]] = Lights::insert_blue_light(self.lights[[lx, ly, lz]], level);
     }
 
     /// Get the sunlight value locally
     #[inline]
     fn get_local_sunlight(&self, lx: usize, ly: usize, lz: usize) -> u32 {
-        Lights::extract_sunlight(self.lights[&[lx, ly, lz]])
+        Lights::extract_sunlight(self.lights[[lx, ly, lz]])
     }
 
     /// Set the sunlight value locally
     #[inline]
     fn set_local_sunlight(&mut self, lx: usize, ly: usize, lz: usize, level: u32) {
-        self.lights[&[lx, ly, lz]] = Lights::insert_sunlight(self.lights[&[lx, ly, lz]], level);
+        self.lights[[lx, ly, lz]] = Lights::insert_sunlight(self.lights[[lx, ly, lz]], level);
     }
 
     /// Convert voxel coordinates to local chunk coordinates
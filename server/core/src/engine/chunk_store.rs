@@ -0,0 +1,1102 @@
+use hashbrown::{HashMap, HashSet};
+use std::{collections::VecDeque, sync::Arc};
+
+use crate::gen::blocks::BlockRotation;
+
+use super::super::{
+    comp::rigidbody::MovementModifiers,
+    constants::VOXEL_NEIGHBORS,
+    engine::{chunk::Chunk, physics::Surface, registry::Registry, world::WorldConfig},
+    gen::{
+        builder::VoxelUpdate,
+        generator::Generator,
+        lights::{LightColor, LightNode, Lights, VoxelLight},
+    },
+    network::models::UpdateProtocol,
+};
+
+use server_common::{
+    aabb::Aabb,
+    types::Block,
+    vec::{Vec2, Vec3},
+};
+use server_utils::convert::{map_voxel_to_chunk, map_voxel_to_chunk_local, map_world_to_voxel};
+
+/// Read access to the voxels of a world, or of a copy of some part of it
+pub trait VoxelAccess {
+    /// The block id at a voxel, 0 where there's nothing loaded
+    fn get_voxel(&self, vx: i32, vy: i32, vz: i32) -> u32;
+
+    /// The height of the highest block of a column, 0 where there's nothing loaded
+    fn get_max_height(&self, vx: i32, vz: i32) -> u32;
+}
+
+/// The loaded chunks of a world and their voxels, light levels and height maps
+///
+/// Writes to a voxel are mirrored into the padding of the chunks around it, and writes to
+/// chunks that aren't loaded yet are queued up for when they are. Generation, lighting whole
+/// chunks and meshing are left to `Chunks`, `Lights` and `Mesher`.
+#[derive(Debug)]
+pub struct ChunkStore {
+    pub config: Arc<WorldConfig>,
+    pub registry: Arc<Registry>,
+
+    /// Chunks changed since caching started
+    pub chunk_cache: HashSet<Vec2<i32>>,
+
+    caching: bool,
+    chunks: HashMap<Vec2<i32>, Chunk>,
+    update_queue: HashMap<Vec2<i32>, Vec<VoxelUpdate>>,
+    /// Lit voxels on the edges of chunks that aren't loaded, to flood light from once they are
+    light_edges: HashMap<Vec2<i32>, HashSet<Vec3<i32>>>,
+    /// Voxels updated since last taken, by the block they became
+    block_changes: HashMap<u32, u64>,
+    /// Voxels updated since last taken, kept only while logging updates for a replay
+    updated_voxels: Option<Vec<Vec3<i32>>>,
+}
+
+impl ChunkStore {
+    pub fn new(config: Arc<WorldConfig>, registry: Arc<Registry>) -> Self {
+        Self {
+            config,
+            registry,
+            chunk_cache: HashSet::new(),
+            caching: false,
+            chunks: HashMap::new(),
+            update_queue: HashMap::new(),
+            light_edges: HashMap::new(),
+            block_changes: HashMap::new(),
+            updated_voxels: None,
+        }
+    }
+
+    /// Getter for the count of internal chunks
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Getter for whether there are no chunks loaded
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Return all chunks as raw
+    pub fn all(&self) -> Vec<&Chunk> {
+        self.chunks.values().collect()
+    }
+
+    /// Return a mutable chunk regardless initialization
+    pub fn raw(&self, coords: &Vec2<i32>) -> Option<&Chunk> {
+        self.get_chunk(coords)
+    }
+
+    /// Coordinates of every loaded chunk
+    pub fn coords(&self) -> Vec<Vec2<i32>> {
+        self.chunks.keys().cloned().collect()
+    }
+
+    pub fn contains(&self, coords: &Vec2<i32>) -> bool {
+        self.chunks.contains_key(coords)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.values()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Chunk> {
+        self.chunks.values_mut()
+    }
+
+    /// Put a chunk in the store, replacing the one at its coordinates if any
+    pub fn insert(&mut self, chunk: Chunk) {
+        self.chunks.insert(chunk.coords.to_owned(), chunk);
+    }
+
+    pub fn remove(&mut self, coords: &Vec2<i32>) -> Option<Chunk> {
+        self.chunks.remove(coords)
+    }
+
+    /// Copy the edges of a chunk and the loaded chunks around it into each other's padding, so
+    /// faces on chunk borders are culled against what's really next to them.
+    ///
+    /// Chunks only mirror writes made while they're loaded, so padding goes stale once a
+    /// neighbor is edited with the chunk unloaded and its queued writes are gone with the server.
+    pub fn sync_padding(&mut self, coords: &Vec2<i32>) {
+        self.pull_padding(coords);
+
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                if dx != 0 || dz != 0 {
+                    self.pull_padding(&Vec2(coords.0 + dx, coords.1 + dz));
+                }
+            }
+        }
+    }
+
+    /// Refresh the padding of a chunk from the loaded chunks its padding belongs to, marking
+    /// the levels that changed as dirty.
+    fn pull_padding(&mut self, coords: &Vec2<i32>) {
+        let chunk = match self.get_chunk(coords) {
+            Some(chunk) => chunk,
+            None => return,
+        };
+
+        let Vec3(min_x, _, min_z) = chunk.min;
+        let Vec3(max_x, _, max_z) = chunk.max;
+        let Vec3(inner_min_x, _, inner_min_z) = chunk.min_inner;
+        let Vec3(inner_max_x, _, inner_max_z) = chunk.max_inner;
+
+        let mut changes = vec![];
+
+        for vx in min_x..max_x {
+            for vz in min_z..max_z {
+                if vx >= inner_min_x && vx < inner_max_x && vz >= inner_min_z && vz < inner_max_z {
+                    continue;
+                }
+
+                let owner = match self.get_chunk_by_voxel(vx, 0, vz) {
+                    Some(owner) if !owner.needs_terrain => owner,
+                    _ => continue,
+                };
+
+                for vy in 0..self.config.max_height as i32 {
+                    let value = owner.get_raw_voxel(vx, vy, vz);
+                    if chunk.get_raw_voxel(vx, vy, vz) != value {
+                        changes.push((vx, vy, vz, value));
+                    }
+                }
+            }
+        }
+
+        if changes.is_empty() {
+            return;
+        }
+
+        let max_height = self.config.max_height;
+        let sub_chunks = self.config.sub_chunks;
+        let chunk = self.get_chunk_mut(coords).unwrap();
+
+        changes.into_iter().for_each(|(vx, vy, vz, value)| {
+            chunk.set_raw_voxel(vx, vy, vz, value);
+            chunk.calc_dirty_levels(vy, max_height, sub_chunks);
+        });
+        chunk.is_dirty = true;
+    }
+
+    /// Take the voxel writes queued up for a chunk that wasn't loaded when they were made
+    pub fn take_queued(&mut self, coords: &Vec2<i32>) -> Option<Vec<VoxelUpdate>> {
+        self.update_queue.remove(coords)
+    }
+
+    /// Remember a lit voxel whose light stopped at the edge of a chunk that isn't loaded
+    pub fn queue_light_edge(&mut self, coords: Vec2<i32>, voxel: Vec3<i32>) {
+        self.light_edges
+            .entry(coords)
+            .or_insert_with(HashSet::new)
+            .insert(voxel);
+    }
+
+    /// Take the lit voxels whose light stopped at the edge of a chunk before it was loaded
+    pub fn take_light_edges(&mut self, coords: &Vec2<i32>) -> Option<HashSet<Vec3<i32>>> {
+        self.light_edges.remove(coords)
+    }
+
+    /// Coordinates of the chunks with voxel writes queued up for them
+    pub fn queued_coords(&self) -> Vec<Vec2<i32>> {
+        self.update_queue.keys().cloned().collect()
+    }
+
+    /// Forget every chunk, every queued voxel write and every light edge
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.update_queue.clear();
+        self.light_edges.clear();
+    }
+
+    /// Start the internal cache, caching any mutated chunks.
+    pub fn start_caching(&mut self) {
+        self.caching = true;
+    }
+
+    /// Stop the internal cache
+    pub fn stop_caching(&mut self) {
+        self.caching = false;
+    }
+
+    /// Clear the internal mutated chunks cache
+    pub fn clear_cache(&mut self) {
+        self.chunk_cache.clear();
+    }
+
+    /// Take the counts of voxels updated since last taken, by the block they became
+    pub fn take_block_changes(&mut self) -> HashMap<u32, u64> {
+        std::mem::take(&mut self.block_changes)
+    }
+
+    /// Start or stop keeping the voxels that get updated
+    pub fn log_updates(&mut self, logging: bool) {
+        self.updated_voxels = if logging { Some(vec![]) } else { None };
+    }
+
+    /// Take the voxels updated since last taken, if updates are being logged
+    pub fn take_updated_voxels(&mut self) -> Vec<Vec3<i32>> {
+        self.updated_voxels
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Get a chunk reference from a coordinate
+    #[inline]
+    pub fn get_chunk(&self, coords: &Vec2<i32>) -> Option<&Chunk> {
+        self.chunks.get(coords)
+    }
+
+    /// Get a mutable chunk reference from a coordinate
+    pub fn get_chunk_mut(&mut self, coords: &Vec2<i32>) -> Option<&mut Chunk> {
+        // self.update_activities(coords);
+
+        let chunk = self.chunks.get_mut(coords);
+        // ? does non-mutable chunks need to be cached?
+        if self.caching && chunk.is_some() {
+            self.chunk_cache.insert(coords.to_owned());
+        }
+
+        chunk
+    }
+
+    /// Get a chunk reference from a voxel coordinate
+    pub fn get_chunk_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> Option<&Chunk> {
+        let coords = map_voxel_to_chunk(vx, vy, vz, self.config.chunk_size);
+        self.get_chunk(&coords)
+    }
+
+    /// Get a mutable chunk reference from a voxel coordinate
+    pub fn get_chunk_by_voxel_mut(&mut self, vx: i32, vy: i32, vz: i32) -> Option<&mut Chunk> {
+        let coords = map_voxel_to_chunk(vx, vy, vz, self.config.chunk_size);
+        self.get_chunk_mut(&coords)
+    }
+
+    /// Get the voxel type at a voxel coordinate
+    pub fn get_voxel_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> u32 {
+        let chunk = self.get_chunk_by_voxel(vx, vy, vz);
+        if let Some(chunk) = chunk {
+            chunk.get_voxel(vx, vy, vz)
+        } else {
+            0
+        }
+    }
+
+    /// Get the voxel type at a world coordinate
+    pub fn get_voxel_by_world(&self, wx: f32, wy: f32, wz: f32) -> u32 {
+        let Vec3(vx, vy, vz) = map_world_to_voxel(wx, wy, wz, self.config.dimension);
+        self.get_voxel_by_voxel(vx, vy, vz)
+    }
+
+    /// Set the voxel type for a voxel coordinate
+    ///
+    /// Note: This clears the voxel rotation and stage.
+    ///
+    /// Side-effects:
+    ///
+    /// 1. Sets the neighboring chunk's padding data if the coordinates are on a chunk edge.
+    /// 2. Calculates the chunk's and the neighbors' dirty sub-chunk levels
+    pub fn set_voxel_by_voxel(&mut self, vx: i32, vy: i32, vz: i32, id: u32) {
+        let max_height = self.config.max_height;
+        if vy as u32 >= max_height {
+            return;
+        }
+
+        let sub_chunks = self.config.sub_chunks;
+        let chunk = self.get_chunk_by_voxel_mut(vx, vy, vz);
+
+        if let Some(chunk) = chunk {
+            chunk.set_voxel(vx, vy, vz, id);
+            chunk.calc_dirty_levels(vy, max_height, sub_chunks);
+            chunk.is_dirty = true;
+        } else {
+            let updates = self
+                .update_queue
+                .entry(map_voxel_to_chunk(vx, vy, vz, self.config.chunk_size))
+                .or_insert_with(Vec::new);
+            updates.push(VoxelUpdate {
+                voxel: Vec3(vx, vy, vz),
+                id,
+            });
+        }
+
+        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
+        neighbors.iter().for_each(|c| {
+            let n_chunk = self.get_chunk_mut(c);
+
+            if let Some(n_chunk) = n_chunk {
+                n_chunk.set_voxel(vx, vy, vz, id);
+                n_chunk.calc_dirty_levels(vy, max_height, sub_chunks);
+                n_chunk.is_dirty = true;
+            } else {
+                let updates = self
+                    .update_queue
+                    .entry(c.to_owned())
+                    .or_insert_with(Vec::new);
+                updates.push(VoxelUpdate {
+                    voxel: Vec3(vx, vy, vz),
+                    id,
+                });
+            }
+        })
+    }
+
+    /// Get the voxel rotation at a voxel coordinate
+    pub fn get_voxel_rotation_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> BlockRotation {
+        let chunk = self.get_chunk_by_voxel(vx, vy, vz);
+        if let Some(chunk) = chunk {
+            chunk.get_voxel_rotation(vx, vy, vz)
+        } else {
+            panic!("Rotation not obtainable.");
+        }
+    }
+
+    /// Set the voxel rotation at a voxel coordinate
+    ///
+    /// Side-effects:
+    ///
+    /// 1. Sets the neighboring chunk's padding data if the coordinates are on a chunk edge.
+    /// 2. Calculates the chunk's and the neighbors' dirty sub-chunk levels
+    pub fn set_voxel_rotation_by_voxel(
+        &mut self,
+        vx: i32,
+        vy: i32,
+        vz: i32,
+        rotation: &BlockRotation,
+    ) {
+        let max_height = self.config.max_height;
+        if vy as u32 >= max_height {
+            return;
+        }
+
+        let sub_chunks = self.config.sub_chunks;
+        let chunk = self.get_chunk_by_voxel_mut(vx, vy, vz);
+
+        // TODO: update chunks data for unloaded chunks.
+
+        if let Some(chunk) = chunk {
+            chunk.set_voxel_rotation(vx, vy, vz, rotation);
+            chunk.calc_dirty_levels(vy, max_height, sub_chunks);
+            chunk.is_dirty = true;
+        }
+
+        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
+        neighbors.iter().for_each(|c| {
+            let n_chunk = self.get_chunk_mut(c);
+
+            if let Some(n_chunk) = n_chunk {
+                n_chunk.set_voxel_rotation(vx, vy, vz, rotation);
+                n_chunk.calc_dirty_levels(vy, max_height, sub_chunks);
+                n_chunk.is_dirty = true;
+            }
+        })
+    }
+
+    /// Get the voxel stage at a voxel coordinate
+    pub fn get_voxel_stage_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> u32 {
+        let chunk = self.get_chunk_by_voxel(vx, vy, vz);
+        if let Some(chunk) = chunk {
+            chunk.get_voxel_stage(vx, vy, vz)
+        } else {
+            panic!("Stage not obtainable.");
+        }
+    }
+
+    /// Get the update protocol describing the current state of a voxel, if it's loaded
+    pub fn get_update_protocol(&self, vx: i32, vy: i32, vz: i32) -> Option<UpdateProtocol> {
+        let chunk = self.get_chunk_by_voxel(vx, vy, vz)?;
+        let (rotation, y_rotation) = BlockRotation::decode(&chunk.get_voxel_rotation(vx, vy, vz));
+
+        Some(UpdateProtocol {
+            vx,
+            vy,
+            vz,
+            r#type: chunk.get_voxel(vx, vy, vz),
+            rotation,
+            y_rotation,
+            stage: chunk.get_voxel_stage(vx, vy, vz),
+        })
+    }
+
+    /// Set the voxel stage at a voxel coordinate
+    ///
+    /// Side-effects:
+    ///
+    /// 1. Sets the neighboring chunk's padding data if the coordinates are on a chunk edge.
+    /// 2. Calculates the chunk's and the neighbors' dirty sub-chunk levels
+    pub fn set_voxel_stage_by_voxel(&mut self, vx: i32, vy: i32, vz: i32, stage: u32) {
+        let max_height = self.config.max_height;
+        if vy as u32 >= max_height {
+            return;
+        }
+
+        let sub_chunks = self.config.sub_chunks;
+        let chunk = self.get_chunk_by_voxel_mut(vx, vy, vz);
+
+        // TODO: update chunks data for unloaded chunks.
+
+        if let Some(chunk) = chunk {
+            chunk.set_voxel_stage(vx, vy, vz, stage);
+            chunk.calc_dirty_levels(vy, max_height, sub_chunks);
+            chunk.is_dirty = true;
+        }
+
+        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
+        neighbors.iter().for_each(|c| {
+            let n_chunk = self.get_chunk_mut(c);
+
+            if let Some(n_chunk) = n_chunk {
+                n_chunk.set_voxel_stage(vx, vy, vz, stage);
+                n_chunk.calc_dirty_levels(vy, max_height, sub_chunks);
+                n_chunk.is_dirty = true;
+            }
+        })
+    }
+
+    /// Get the sunlight level at a voxel coordinate
+    pub fn get_sunlight(&self, vx: i32, vy: i32, vz: i32) -> u32 {
+        let chunk = self.get_chunk_by_voxel(vx, vy, vz);
+        if let Some(chunk) = chunk {
+            chunk.get_sunlight(vx, vy, vz)
+        } else {
+            0
+        }
+    }
+
+    /// Set the sunlight level for a voxel coordinate
+    ///
+    /// Side-effects:
+    ///
+    /// 1. Sets the neighboring chunk's padding data if the coordinates are on a chunk edge.
+    /// 2. Calculates the chunk's and the neighbors' dirty sub-chunk levels
+    pub fn set_sunlight(&mut self, vx: i32, vy: i32, vz: i32, level: u32) {
+        let max_height = self.config.max_height;
+        if vy as u32 >= max_height {
+            return;
+        }
+
+        let sub_chunks = self.config.sub_chunks;
+
+        let chunk = self
+            .get_chunk_by_voxel_mut(vx, vy, vz)
+            .expect("Chunk not found.");
+
+        chunk.set_sunlight(vx, vy, vz, level);
+        chunk.calc_dirty_levels(vy, max_height, sub_chunks);
+        chunk.is_dirty = true;
+
+        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
+        neighbors.iter().for_each(|c| {
+            if let Some(n_chunk) = self.get_chunk_mut(c) {
+                n_chunk.set_sunlight(vx, vy, vz, level);
+                n_chunk.calc_dirty_levels(vy, max_height, sub_chunks);
+                n_chunk.is_dirty = true;
+            }
+        })
+    }
+
+    /// Light levels of a voxel, None if its chunk isn't loaded. Voxels above the world are in
+    /// full sunlight.
+    pub fn get_light(&self, vx: i32, vy: i32, vz: i32) -> Option<VoxelLight> {
+        let chunk = self.get_chunk_by_voxel(vx, vy, vz)?;
+
+        if vy >= self.config.max_height as i32 {
+            return Some(VoxelLight {
+                sunlight: self.config.max_light_level,
+                torch: 0,
+            });
+        }
+
+        Some(VoxelLight {
+            sunlight: chunk.get_sunlight(vx, vy, vz),
+            torch: chunk
+                .get_red_light(vx, vy, vz)
+                .max(chunk.get_green_light(vx, vy, vz))
+                .max(chunk.get_blue_light(vx, vy, vz)),
+        })
+    }
+
+    /// How bright a voxel is at a time of day, None if its chunk isn't loaded
+    pub fn get_brightness(&self, vx: i32, vy: i32, vz: i32, time: f32) -> Option<u32> {
+        self.get_light(vx, vy, vz)
+            .map(|light| light.brightness(time))
+    }
+
+    /// Get the torch light level by voxel coordinates of a specified color
+    pub fn get_torch_light(&self, vx: i32, vy: i32, vz: i32, color: &LightColor) -> u32 {
+        let chunk = self.get_chunk_by_voxel(vx, vy, vz);
+        if let Some(chunk) = chunk {
+            chunk.get_torch_light(vx, vy, vz, color)
+        } else {
+            0
+        }
+    }
+
+    /// Set the torch light level by voxel coordinates of a specified color
+    ///
+    /// Side-effects:
+    ///
+    /// 1. Sets the neighboring chunk's padding data if the coordinates are on a chunk edge.
+    /// 2. Calculates the chunk's and the neighbors' dirty sub-chunk levels
+    pub fn set_torch_light(&mut self, vx: i32, vy: i32, vz: i32, level: u32, color: &LightColor) {
+        let max_height = self.config.max_height;
+        if vy as u32 >= max_height {
+            return;
+        }
+
+        let sub_chunks = self.config.sub_chunks;
+
+        let chunk = self
+            .get_chunk_by_voxel_mut(vx, vy, vz)
+            .expect("Chunk not found.");
+
+        chunk.set_torch_light(vx, vy, vz, level, color);
+        chunk.calc_dirty_levels(vy, max_height, sub_chunks);
+        chunk.is_dirty = true;
+
+        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
+        neighbors.iter().for_each(|c| {
+            if let Some(n_chunk) = self.get_chunk_mut(c) {
+                n_chunk.set_torch_light(vx, vy, vz, level, color);
+                n_chunk.calc_dirty_levels(vy, max_height, sub_chunks);
+                n_chunk.is_dirty = true;
+            }
+        })
+    }
+
+    /// Get a block type from a voxel coordinate
+    pub fn get_block_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> &Block {
+        let voxel = self.get_voxel_by_voxel(vx, vy, vz);
+        self.registry.get_block_by_id(voxel)
+    }
+
+    /// Get a block type from a voxel id
+    pub fn get_block_by_id(&self, id: u32) -> &Block {
+        self.registry.get_block_by_id(id)
+    }
+
+    /// Get the max height at a voxel column coordinate
+    pub fn get_max_height(&self, vx: i32, vz: i32) -> u32 {
+        if let Some(chunk) = self.get_chunk_by_voxel(vx, 0, vz) {
+            chunk.get_max_height(vx, vz)
+        } else {
+            0
+        }
+    }
+
+    /// Set the max height at a voxel column coordinate
+    pub fn set_max_height(&mut self, vx: i32, vz: i32, height: u32) {
+        // this is reasonable because if a chunk DNE, and gets instantiated later on,
+        // max height will be generated on instantiation too.
+        if let Some(chunk) = self.get_chunk_by_voxel_mut(vx, 0, vz) {
+            chunk.set_max_height(vx, vz, height);
+        }
+
+        let neighbors = self.get_neighbor_chunk_coords(vx, 0, vz);
+        neighbors.iter().for_each(|c| {
+            if let Some(n_chunk) = self.get_chunk_mut(c) {
+                n_chunk.set_max_height(vx, vz, height);
+                n_chunk.is_dirty = true;
+            }
+        })
+    }
+
+    /// Get whether a voxel is walkable
+    pub fn get_walkable_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> bool {
+        let block = self
+            .registry
+            .get_block_by_id(self.get_voxel_by_voxel(vx, vy, vz));
+        !block.is_solid || block.is_plant
+    }
+
+    /// Get the movement modifiers of the voxels a body overlaps, or stands on
+    pub fn get_movement_modifiers(&self, aabb: &Aabb) -> MovementModifiers {
+        let mut modifiers = MovementModifiers::default();
+
+        let mut apply = |block: &Block| {
+            modifiers.climbable |= block.is_climbable;
+            modifiers.suppress_jump |= block.suppresses_jump;
+            modifiers.speed_multiplier = modifiers.speed_multiplier.min(block.speed_multiplier);
+        };
+
+        for vx in (aabb.base.0.floor() as i32)..=(aabb.max.0.floor() as i32) {
+            for vy in (aabb.base.1.floor() as i32)..=(aabb.max.1.floor() as i32) {
+                for vz in (aabb.base.2.floor() as i32)..=(aabb.max.2.floor() as i32) {
+                    apply(self.get_block_by_voxel(vx, vy, vz));
+                }
+            }
+        }
+
+        // the block right under the feet, like soul sand
+        let cx = ((aabb.base.0 + aabb.max.0) / 2.0).floor() as i32;
+        let cz = ((aabb.base.2 + aabb.max.2) / 2.0).floor() as i32;
+        let below = (aabb.base.1 - 0.1).floor() as i32;
+        apply(self.get_block_by_voxel(cx, below, cz));
+
+        modifiers
+    }
+
+    /// Get the surface properties of the block right under a region, where it overrides the
+    /// friction and restitution of the bodies on it
+    pub fn get_surface(&self, aabb: &Aabb) -> Surface {
+        let cx = ((aabb.base.0 + aabb.max.0) / 2.0).floor() as i32;
+        let cz = ((aabb.base.2 + aabb.max.2) / 2.0).floor() as i32;
+        let below = (aabb.base.1 - 0.1).floor() as i32;
+        let block = self.get_block_by_voxel(cx, below, cz);
+
+        Surface {
+            friction: block.friction,
+            restitution: block.restitution,
+        }
+    }
+
+    /// Get the collision boxes of every voxel touching a region, in voxel space
+    ///
+    /// Voxels right below the region are checked too, as their boxes can reach above them.
+    /// Fluids never collide, bodies swim through them instead.
+    pub fn get_collision_boxes(&self, region: &Aabb) -> Vec<Aabb> {
+        let x0 = region.base.0.floor() as i32;
+        let y0 = region.base.1.floor() as i32;
+        let z0 = region.base.2.floor() as i32;
+        let x1 = region.max.0.floor() as i32;
+        let y1 = region.max.1.floor() as i32;
+        let z1 = region.max.2.floor() as i32;
+
+        let mut boxes = vec![];
+
+        for vx in x0..=x1 {
+            for vz in z0..=z1 {
+                for vy in (y0 - 1)..=y1 {
+                    let id = self.get_voxel_by_voxel(vx, vy, vz);
+                    if id == 0 {
+                        continue;
+                    }
+
+                    let block = self.registry.get_block_by_id(id);
+                    let offset = Vec3(vx as f32, vy as f32, vz as f32);
+
+                    let add = |b: &[f32; 6], boxes: &mut Vec<Aabb>| {
+                        let aabb = Aabb::new(
+                            &offset.add(&Vec3(b[0], b[1], b[2])),
+                            &Vec3(b[3] - b[0], b[4] - b[1], b[5] - b[2]),
+                        );
+
+                        if aabb.intersects(region) {
+                            boxes.push(aabb);
+                        }
+                    };
+
+                    match &block.collision_boxes {
+                        Some(custom) => custom.iter().for_each(|b| add(b, &mut boxes)),
+                        None if block.is_solid && !block.is_fluid => {
+                            add(&[0.0, 0.0, 0.0, 1.0, 1.0, 1.0], &mut boxes)
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+
+        boxes
+    }
+
+    /// Get whether a voxel is solid
+    pub fn get_solidity_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> bool {
+        self.get_voxel_by_voxel(vx, vy, vz) != 0
+    }
+
+    /// Get whether a voxel is fluid
+    pub fn get_fluidity_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> bool {
+        self.get_block_by_voxel(vx, vy, vz).is_fluid
+    }
+
+    /// Get neighboring chunks according to a voxel coordinate
+    pub fn get_neighbor_chunk_coords(&self, vx: i32, vy: i32, vz: i32) -> HashSet<Vec2<i32>> {
+        let chunk_size = self.config.chunk_size;
+
+        let mut neighbor_chunks = HashSet::new();
+
+        let coords = map_voxel_to_chunk(vx, vy, vz, chunk_size);
+        let Vec3(lx, _, lz) = map_voxel_to_chunk_local(vx, vy, vz, chunk_size);
+
+        let chunk_size = chunk_size as i32;
+        let Vec2(cx, cz) = coords;
+
+        let a = lx <= 0;
+        let b = lz <= 0;
+        let c = lx >= chunk_size - 1;
+        let d = lz >= chunk_size - 1;
+
+        // Direct neighbors
+        if a {
+            neighbor_chunks.insert(Vec2(cx - 1, cz));
+        }
+        if b {
+            neighbor_chunks.insert(Vec2(cx, cz - 1));
+        }
+        if c {
+            neighbor_chunks.insert(Vec2(cx + 1, cz));
+        }
+        if d {
+            neighbor_chunks.insert(Vec2(cx, cz + 1));
+        }
+
+        // Side-to-side diagonals
+        if a && b {
+            neighbor_chunks.insert(Vec2(cx - 1, cz - 1));
+        }
+        if a && d {
+            neighbor_chunks.insert(Vec2(cx - 1, cz + 1));
+        }
+        if b && c {
+            neighbor_chunks.insert(Vec2(cx + 1, cz - 1));
+        }
+        if c && d {
+            neighbor_chunks.insert(Vec2(cx + 1, cz + 1));
+        }
+
+        neighbor_chunks.remove(&coords);
+
+        neighbor_chunks
+    }
+
+    /// Get the voxel above the first standable block below
+    pub fn get_standable_voxel(&self, voxel: &Vec3<i32>) -> Vec3<i32> {
+        let mut voxel = voxel.clone();
+        loop {
+            if voxel.1 == 0 || self.get_walkable_by_voxel(voxel.0, voxel.1, voxel.2) {
+                voxel.1 -= 1;
+            } else {
+                break;
+            }
+        }
+        voxel.1 += 1;
+        voxel
+    }
+
+    /// Update a voxel to a new type
+    pub fn update(&mut self, vx: i32, vy: i32, vz: i32, id: u32, rotation: u32, y_rotation: u32) {
+        // TODO: fix this code (might have better way)
+        self.get_chunk_by_voxel_mut(vx, vy, vz)
+            .unwrap()
+            .needs_saving = true;
+        let needs_propagation = self
+            .get_chunk_by_voxel(vx, vy, vz)
+            .unwrap()
+            .needs_propagation;
+
+        let max_height = self.config.max_height as i32;
+        let max_light_level = self.config.max_light_level;
+
+        let height = self.get_max_height(vx, vz);
+
+        // borrowed from a handle of the registry, so that the blocks stay around while self changes
+        let registry = self.registry.clone();
+        let current_type = registry.get_block_by_id(self.get_voxel_by_voxel(vx, vy, vz));
+        let updated_type = registry.get_block_by_id(id);
+
+        let voxel = Vec3(vx, vy, vz);
+
+        // updating the new block
+        self.set_voxel_by_voxel(vx, vy, vz, id);
+        *self.block_changes.entry(id).or_insert(0) += 1;
+
+        if let Some(updated) = self.updated_voxels.as_mut() {
+            updated.push(voxel.to_owned());
+        }
+
+        if updated_type.rotatable {
+            let y_rotation = if updated_type.y_rotatable {
+                y_rotation
+            } else {
+                0
+            };
+
+            self.set_voxel_rotation_by_voxel(
+                vx,
+                vy,
+                vz,
+                &BlockRotation::encode(rotation, y_rotation),
+            );
+        }
+
+        // updating the height map
+        if self.registry.is_air(id) {
+            if vy == height as i32 {
+                // on max height, should set max height to lower
+                for y in (0..vy).rev() {
+                    if y == 0
+                        || Generator::check_height(
+                            self.get_voxel_by_voxel(vx, y, vz),
+                            &self.registry,
+                        )
+                    {
+                        self.set_max_height(vx, vz, y as u32);
+                        break;
+                    }
+                }
+            }
+        } else if height < vy as u32 {
+            self.set_max_height(vx, vz, vy as u32);
+        }
+
+        const RED: LightColor = LightColor::Red;
+        const GREEN: LightColor = LightColor::Green;
+        const BLUE: LightColor = LightColor::Blue;
+        const NONE: LightColor = LightColor::None;
+
+        // update light levels
+        if !needs_propagation {
+            if current_type.is_light {
+                // remove leftover light
+                Lights::global_remove_light(self, vx, vy, vz, false, &RED);
+                Lights::global_remove_light(self, vx, vy, vz, false, &GREEN);
+                Lights::global_remove_light(self, vx, vy, vz, false, &BLUE);
+            } else if current_type.is_transparent
+                && (!updated_type.is_transparent
+                    || updated_type.light_reduce > current_type.light_reduce)
+            {
+                // remove light if solid block is placed, flooding it back in dimmer if the
+                // new block just dims light
+                [false, true].iter().for_each(|&is_sunlight| {
+                    if is_sunlight {
+                        if self.get_sunlight(vx, vy, vz) != 0 {
+                            Lights::global_remove_light(self, vx, vy, vz, is_sunlight, &NONE);
+                        }
+                    } else {
+                        if self.get_torch_light(vx, vy, vz, &RED) != 0 {
+                            Lights::global_remove_light(self, vx, vy, vz, is_sunlight, &RED);
+                        }
+                        if self.get_torch_light(vx, vy, vz, &GREEN) != 0 {
+                            Lights::global_remove_light(self, vx, vy, vz, is_sunlight, &GREEN);
+                        }
+                        if self.get_torch_light(vx, vy, vz, &BLUE) != 0 {
+                            Lights::global_remove_light(self, vx, vy, vz, is_sunlight, &BLUE);
+                        }
+                    };
+                });
+            }
+
+            if updated_type.is_light {
+                // placing a light
+
+                if updated_type.red_light_level > 0 {
+                    self.set_torch_light(vx, vy, vz, updated_type.red_light_level, &RED);
+
+                    Lights::global_flood_light(
+                        self,
+                        VecDeque::from(vec![LightNode {
+                            voxel: voxel.clone(),
+                            level: updated_type.red_light_level,
+                        }]),
+                        false,
+                        &RED,
+                    );
+                }
+
+                if updated_type.green_light_level > 0 {
+                    self.set_torch_light(vx, vy, vz, updated_type.green_light_level, &GREEN);
+
+                    Lights::global_flood_light(
+                        self,
+                        VecDeque::from(vec![LightNode {
+                            voxel: voxel.clone(),
+                            level: updated_type.green_light_level,
+                        }]),
+                        false,
+                        &GREEN,
+                    );
+                }
+
+                if updated_type.blue_light_level > 0 {
+                    self.set_torch_light(vx, vy, vz, updated_type.blue_light_level, &BLUE);
+
+                    Lights::global_flood_light(
+                        self,
+                        VecDeque::from(vec![LightNode {
+                            voxel,
+                            level: updated_type.blue_light_level,
+                        }]),
+                        false,
+                        &BLUE,
+                    );
+                }
+            } else if updated_type.is_transparent
+                && (!current_type.is_transparent
+                    || current_type.light_reduce > updated_type.light_reduce)
+            {
+                // solid block removed, or light can pass through brighter
+                [false, true].iter().for_each(|&is_sunlight| {
+                    let mut queue = VecDeque::<LightNode>::new();
+                    let mut red_queue = VecDeque::<LightNode>::new();
+                    let mut green_queue = VecDeque::<LightNode>::new();
+                    let mut blue_queue = VecDeque::<LightNode>::new();
+
+                    if is_sunlight && vy == max_height - 1 && self.config.sky_light {
+                        // propagate sunlight down
+                        self.set_sunlight(vx, vy, vz, max_light_level);
+                        queue.push_back(LightNode {
+                            voxel: voxel.clone(),
+                            level: max_light_level,
+                        })
+                    } else {
+                        for [ox, oy, oz] in VOXEL_NEIGHBORS.iter() {
+                            let nvy = vy + oy;
+
+                            if nvy < 0 || nvy >= max_height {
+                                return;
+                            }
+
+                            let nvx = vx + ox;
+                            let nvz = vz + oz;
+                            let n_voxel = Vec3(nvx, nvy, nvz);
+                            let &Block {
+                                is_light,
+                                is_transparent,
+                                ..
+                            } = self.get_block_by_voxel(nvx, nvy, nvz);
+
+                            // need propagation after solid block removed
+                            if is_sunlight {
+                                let level = self.get_sunlight(nvx, nvy, nvz);
+                                if level != 0 && is_transparent {
+                                    queue.push_back(LightNode {
+                                        voxel: n_voxel,
+                                        level,
+                                    })
+                                }
+                            } else {
+                                let red_level = self.get_torch_light(nvx, nvy, nvz, &RED);
+                                if red_level != 0 && (is_transparent || is_light) {
+                                    red_queue.push_back(LightNode {
+                                        voxel: n_voxel.clone(),
+                                        level: red_level,
+                                    })
+                                }
+
+                                let green_level = self.get_torch_light(nvx, nvy, nvz, &GREEN);
+                                if green_level != 0 && (is_transparent || is_light) {
+                                    green_queue.push_back(LightNode {
+                                        voxel: n_voxel.clone(),
+                                        level: green_level,
+                                    })
+                                }
+
+                                let blue_level = self.get_torch_light(nvx, nvy, nvz, &BLUE);
+                                if blue_level != 0 && (is_transparent || is_light) {
+                                    blue_queue.push_back(LightNode {
+                                        voxel: n_voxel,
+                                        level: blue_level,
+                                    })
+                                }
+                            }
+                        }
+                    }
+
+                    if is_sunlight {
+                        Lights::global_flood_light(self, queue, is_sunlight, &NONE);
+                    } else {
+                        Lights::global_flood_light(self, red_queue, is_sunlight, &RED);
+                        Lights::global_flood_light(self, green_queue, is_sunlight, &GREEN);
+                        Lights::global_flood_light(self, blue_queue, is_sunlight, &BLUE);
+                    }
+                })
+            }
+        }
+    }
+
+    /// Mark a chunk for saving from a voxel coordinate
+    pub fn mark_saving_from_voxel(&mut self, vx: i32, vy: i32, vz: i32) {
+        self.get_chunk_by_voxel_mut(vx, vy, vz)
+            .unwrap()
+            .needs_saving = true;
+
+        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
+        neighbors.iter().for_each(|n_coords| {
+            if let Some(chunk) = self.get_chunk_mut(n_coords) {
+                chunk.needs_saving = true;
+            }
+        })
+    }
+}
+
+impl VoxelAccess for ChunkStore {
+    fn get_voxel(&self, vx: i32, vy: i32, vz: i32) -> u32 {
+        self.get_voxel_by_voxel(vx, vy, vz)
+    }
+
+    fn get_max_height(&self, vx: i32, vz: i32) -> u32 {
+        ChunkStore::get_max_height(self, vx, vz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{engine::space::Space, test_utils};
+
+    use super::*;
+
+    #[test]
+    fn chunk_store_works() {
+        let config = Arc::new(test_utils::config());
+        let mut store = ChunkStore::new(config.clone(), Arc::new(test_utils::registry()));
+
+        store.insert(Chunk::new(Vec2(0, 0), &config, None));
+        store.insert(Chunk::new(Vec2(-1, 0), &config, None));
+
+        // a write on the edge of a chunk reaches the padding of the one next to it
+        store.set_voxel_by_voxel(0, 5, 3, test_utils::STONE);
+        assert_eq!(VoxelAccess::get_voxel(&store, 0, 5, 3), test_utils::STONE);
+        assert_eq!(
+            store.get_chunk(&Vec2(-1, 0)).unwrap().get_voxel(0, 5, 3),
+            test_utils::STONE
+        );
+
+        // and a copy of the space around a chunk sees the same voxels, read through the
+        // chunk's padding the way lighting does, one voxel over
+        let space = Space::new(&store, &Vec2(0, 0), 1);
+        assert_eq!(space.get_voxel(1, 5, 4), test_utils::STONE);
+
+        // writes to chunks that aren't loaded wait for them
+        store.set_voxel_by_voxel(20, 5, 20, test_utils::STONE);
+        assert_eq!(store.queued_coords(), vec![Vec2(2, 2)]);
+        assert_eq!(store.take_queued(&Vec2(2, 2)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn sync_padding_works() {
+        let config = Arc::new(test_utils::config());
+        let mut store = ChunkStore::new(config.clone(), Arc::new(test_utils::registry()));
+
+        let loaded = |coords| {
+            let mut chunk = Chunk::new(coords, &config, None);
+            chunk.needs_terrain = false;
+            chunk
+        };
+
+        store.insert(loaded(Vec2(0, 0)));
+
+        // an edit on the edge while the chunk next to it is unloaded, with its queue long gone
+        store.set_voxel_by_voxel(0, 5, 3, test_utils::STONE);
+        store.take_queued(&Vec2(-1, 0));
+
+        let mut neighbor = loaded(Vec2(-1, 0));
+        neighbor.set_raw_voxel(-1, 6, 3, test_utils::STONE);
+        store.insert(neighbor);
+        store.get_chunk_mut(&Vec2(0, 0)).unwrap().is_dirty = false;
+
+        store.sync_padding(&Vec2(-1, 0));
+
+        let neighbor = store.get_chunk(&Vec2(-1, 0)).unwrap();
+        assert_eq!(neighbor.get_voxel(0, 5, 3), test_utils::STONE);
+
+        let chunk = store.get_chunk(&Vec2(0, 0)).unwrap();
+        assert_eq!(chunk.get_voxel(-1, 6, 3), test_utils::STONE);
+        assert!(chunk.is_dirty);
+    }
+}
@@ -1,18 +1,34 @@
 use hashbrown::{HashMap, HashSet};
-use std::{collections::VecDeque, path::PathBuf, sync::Arc};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::Write,
+    ops::{Deref, DerefMut},
+    path::PathBuf,
+    sync::Arc,
+    time::Instant,
+};
 
 use crossbeam_channel::{unbounded, Receiver, Sender};
-use log::{debug, info};
+use log::{debug, info, warn};
 use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+use tracing::info_span;
 
-use crate::gen::{biomes::Biomes, blocks::BlockRotation};
+use crate::gen::biomes::Biomes;
 
 use super::super::{
-    constants::{LEVEL_SEED, VOXEL_NEIGHBORS},
+    constants::{CHUNK_READ_AHEAD, LEVEL_SEED, PREGEN_PROGRESS_FILE},
     engine::{
+        async_storage::{AsyncStorage, LoadedChunk},
         chunk::{Chunk, Meshes},
+        chunk_store::ChunkStore,
+        entities::SuspendedEntity,
+        map::render_horizon,
         registry::Registry,
+        remesh::RemeshQueue,
         space::Space,
+        storage::{self, NoStorage, Storage, WriteBatch},
         world::WorldConfig,
     },
     gen::{
@@ -20,15 +36,35 @@ use super::super::{
         generator::Generator,
         lights::{LightColor, LightNode, Lights},
         mesher::Mesher,
+        structures::Structures,
     },
+    network::models::{messages, ChunkProtocol},
 };
 
 use server_common::{
     noise::Noise,
-    types::Block,
     vec::{Vec2, Vec3},
 };
-use server_utils::convert::{map_voxel_to_chunk, map_voxel_to_chunk_local, map_world_to_voxel};
+use server_utils::convert::map_voxel_to_chunk;
+
+/// Sunlight, then the colors of torch light
+const COLORS: [LightColor; 4] = [
+    LightColor::None,
+    LightColor::Red,
+    LightColor::Green,
+    LightColor::Blue,
+];
+
+/// Chunks lit and meshed by the pool, with the seconds spent lighting and meshing them
+type MeshBatch = (Vec<Chunk>, f32, f32);
+
+/// How far a world's pregeneration got, saved along with its chunks
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PregenProgress {
+    /// Rings of chunks around the origin that are generated, lit and saved
+    rings: i16,
+}
 
 #[derive(Clone, Debug)]
 pub enum MeshLevel {
@@ -37,60 +73,119 @@ pub enum MeshLevel {
     Levels(HashSet<u32>),
 }
 
-/// A wrapper around all the chunks
+/// A wrapper around all the chunks, generating, lighting and meshing the ones in its store
+///
+/// Dereferences to the `ChunkStore` for reading and writing voxels.
 #[derive(Debug)]
 pub struct Chunks {
     pub root_folder: PathBuf,
-    pub chunk_folder: PathBuf,
+    pub storage: Arc<AsyncStorage>,
 
-    pub chunk_cache: HashSet<Vec2<i32>>,
     pub to_generate: Vec<Chunk>,
     pub generating: HashSet<Vec2<i32>>,
     pub to_mesh: VecDeque<Vec2<i32>>,
     pub meshing: HashSet<Vec2<i32>>,
     pub activities: VecDeque<Vec2<i32>>,
 
+    /// Chunk and render radius each player is generating around, by player id
+    pub views: HashMap<usize, (Vec2<i32>, i16)>,
+
     pub config: Arc<WorldConfig>,
     pub registry: Arc<Registry>,
     pub builder: Arc<Builder>,
     pub biomes: Arc<Biomes>,
+    pub structures: Structures,
 
-    caching: bool,
-    chunks: HashMap<Vec2<i32>, Chunk>,
-    update_queue: HashMap<Vec2<i32>, Vec<VoxelUpdate>>,
+    store: ChunkStore,
     noise: Noise,
 
     pool: ThreadPool,
 
+    /// Chunks being read from storage by request, and whether a player's view needs them yet
+    loading: HashMap<Vec2<i32>, (u64, bool)>,
+    /// Chunks read ahead of players' views, None if they have never been saved
+    read_ahead: HashMap<Vec2<i32>, Option<serde_json::Value>>,
+
     gen_sender: Arc<Sender<Vec<Chunk>>>,
     gen_receiver: Arc<Receiver<Vec<Chunk>>>,
 
-    mesh_sender: Arc<Sender<Vec<Chunk>>>,
-    mesh_receiver: Arc<Receiver<Vec<Chunk>>>,
+    load_sender: Arc<Sender<LoadedChunk>>,
+    load_receiver: Arc<Receiver<LoadedChunk>>,
+
+    mesh_sender: Arc<Sender<MeshBatch>>,
+    mesh_receiver: Arc<Receiver<MeshBatch>>,
+
+    /// Horizons of chunks that aren't loaded, generated without keeping the chunks around
+    horizons: HashMap<Vec2<i32>, messages::Horizon>,
+    horizons_generating: HashSet<Vec2<i32>>,
+    horizon_sender: Arc<Sender<messages::Horizon>>,
+    horizon_receiver: Arc<Receiver<messages::Horizon>>,
+
+    /// Seconds spent on lighting and meshing by the pool, since last taken
+    timings: Vec<(&'static str, f32)>,
+
+    /// Mobs of the chunks loaded back in, waiting to be spawned again
+    restored: Vec<SuspendedEntity>,
+    /// Chunks unloaded since last saved, written once the mobs in them are put away with them
+    evicted: HashMap<Vec2<i32>, Chunk>,
+}
+
+impl Deref for Chunks {
+    type Target = ChunkStore;
+
+    fn deref(&self) -> &ChunkStore {
+        &self.store
+    }
+}
+
+impl DerefMut for Chunks {
+    fn deref_mut(&mut self) -> &mut ChunkStore {
+        &mut self.store
+    }
 }
 
-/**
- * THIS CODE IS REALLY REALLY BAD
- * NEED REFACTOR ASAP
- */
 impl Chunks {
-    pub fn new(world_name: &str, config: WorldConfig, registry: Registry) -> Self {
+    pub fn new(world_name: &str, config: WorldConfig, registry: Arc<Registry>) -> Self {
+        let structures = Structures::new(&config.generation, LEVEL_SEED);
+
+        Chunks::from_parts(world_name, config, registry, Biomes::default(), structures)
+    }
+
+    /// Set up the chunks of a world with biomes and structures of its own, instead of the
+    /// ones in the assets
+    pub fn from_parts(
+        world_name: &str,
+        config: WorldConfig,
+        registry: Arc<Registry>,
+        biomes: Biomes,
+        structures: Structures,
+    ) -> Self {
         let (gen_sender, gen_receiver) = unbounded();
         let gen_sender = Arc::new(gen_sender);
         let gen_receiver = Arc::new(gen_receiver);
 
+        let (load_sender, load_receiver) = unbounded();
+        let load_sender = Arc::new(load_sender);
+        let load_receiver = Arc::new(load_receiver);
+
         let (mesh_sender, mesh_receiver) = unbounded();
         let mesh_sender = Arc::new(mesh_sender);
         let mesh_receiver = Arc::new(mesh_receiver);
 
+        let (horizon_sender, horizon_receiver) = unbounded();
+        let horizon_sender = Arc::new(horizon_sender);
+        let horizon_receiver = Arc::new(horizon_receiver);
+
         let mut root_folder = PathBuf::from(&config.chunk_root);
         root_folder.push(world_name);
 
-        let mut chunk_folder = root_folder.clone();
-        chunk_folder.push("chunks");
+        let storage = Arc::new(AsyncStorage::new(if config.save {
+            storage::open(config.storage, &root_folder)
+        } else {
+            Arc::new(NoStorage)
+        }));
 
         if config.save {
-            std::fs::create_dir_all(&chunk_folder).expect("Unable to create chunks directory...");
             info!(
                 "Storage for world \"{}\" is at \"./{}/{}\".",
                 world_name, config.chunk_root, world_name
@@ -99,27 +194,31 @@ impl Chunks {
             info!("World \"{}\" is temporarily saved in memory.", world_name);
         }
 
+        let config = Arc::new(config);
+
         Chunks {
             root_folder,
-            chunk_folder,
-            chunk_cache: HashSet::new(),
+            storage,
+            store: ChunkStore::new(config.clone(), registry.clone()),
 
-            config: Arc::new(config),
-            registry: Arc::new(registry.to_owned()),
-            builder: Arc::new(Builder::new(registry, Noise::new(LEVEL_SEED))),
-            biomes: Arc::new(Biomes::default()),
+            config,
+            builder: Arc::new(Builder::new(registry.clone(), Noise::new(LEVEL_SEED))),
+            registry,
+            biomes: Arc::new(biomes),
+            structures,
 
             to_generate: vec![],
             generating: HashSet::new(),
             to_mesh: VecDeque::new(),
             meshing: HashSet::new(),
             activities: VecDeque::new(),
+            views: HashMap::new(),
 
-            caching: false,
-            chunks: HashMap::new(),
-            update_queue: HashMap::new(),
             noise: Noise::new(LEVEL_SEED),
 
+            loading: HashMap::new(),
+            read_ahead: HashMap::new(),
+
             pool: ThreadPoolBuilder::new()
                 .num_threads(num_cpus::get())
                 .build()
@@ -128,28 +227,52 @@ impl Chunks {
             gen_sender,
             gen_receiver,
 
+            load_sender,
+            load_receiver,
+
             mesh_sender,
             mesh_receiver,
+
+            horizons: HashMap::new(),
+            horizons_generating: HashSet::new(),
+            horizon_sender,
+            horizon_receiver,
+
+            timings: vec![],
+
+            restored: vec![],
+            evicted: HashMap::new(),
         }
     }
 
-    /// Tick does four things:
+    /// Tick does five things:
     ///
-    /// 1. Checks if any chunks needs to be generated. If any is found,
+    /// 1. Takes in the chunks read from storage since the last tick. The
+    /// ones that were never saved are queued to be generated.
+    /// 2. Checks if any chunks needs to be generated. If any is found,
     /// the chunk coordinates are sent to another thread to be generated.
-    /// 2. Checks if any thread is waiting to return a generated chunk. If
+    /// 3. Checks if any thread is waiting to return a generated chunk. If
     /// received any, the new chunk will be added to `chunks` itself.
-    /// 3. Checks if any chunks needs to be meshed. If any is found, the chunks
+    /// 4. Checks if any chunks needs to be meshed. If any is found, the chunks
     /// are then sent to another thread to be meshed (lit and culled).
-    /// 4. Checks if any thread is waiting to return a meshed chunk. If so, add
+    /// 5. Checks if any thread is waiting to return a meshed chunk. If so, add
     /// them back into `chunks` itself.
+    ///
+    /// Worlds in lockstep wait for the chunks handed out to be generated or meshed, instead
+    /// of picking them up on whichever tick they're done.
     pub fn tick(&mut self) {
+        let lockstep = self.config.lockstep.is_some();
+        let mut meshing = false;
+        let mut generating = false;
+
+        self.take_loads();
+
         if !self.to_mesh.is_empty() {
             let to_mesh = self
                 .to_mesh
                 .drain(0..self.config.max_per_thread.min(self.to_mesh.len()))
                 .collect::<Vec<_>>();
-            let to_mesh: Vec<(Chunk, Space)> = to_mesh
+            let to_mesh: Vec<(Chunk, Space, bool)> = to_mesh
                 .iter()
                 .map(|coords| {
                     // mark as meshing
@@ -158,6 +281,7 @@ impl Chunks {
                     (
                         self.get_chunk(coords).unwrap().clone(),
                         Space::new(self, coords, self.config.max_light_level as usize),
+                        self.is_open_air(coords),
                     )
                 })
                 .collect();
@@ -167,18 +291,33 @@ impl Chunks {
             let registry = Arc::clone(&self.registry);
 
             self.pool.spawn(move || {
+                let mut light_secs = 0.0;
+                let mut mesh_secs = 0.0;
+
                 let meshed = to_mesh
                     .into_iter()
-                    .map(|(mut chunk, space)| {
+                    .map(|(mut chunk, space, is_open_air)| {
+                        let start = Instant::now();
+
                         if chunk.needs_propagation {
-                            let lights = Lights::calc_light(&space, &registry, &config);
+                            if is_open_air {
+                                chunk.set_open_lights(config.sky_light, config.max_light_level);
+                            } else {
+                                let lights = Lights::calc_light(&space, &registry, &config);
+                                chunk.set_lights(lights);
+                            }
+
                             chunk.needs_propagation = false;
                             chunk.needs_saving = true;
-                            chunk.set_lights(lights);
                         }
 
                         let sub_chunks = config.sub_chunks;
 
+                        light_secs += start.elapsed().as_secs_f32();
+                        let start = Instant::now();
+
+                        chunk.calc_sub_chunk_fills(&registry, sub_chunks);
+
                         chunk.meshes = Vec::new();
 
                         for sub_chunk in 0..sub_chunks {
@@ -196,12 +335,16 @@ impl Chunks {
                             chunk.is_dirty = false;
                         }
 
+                        mesh_secs += start.elapsed().as_secs_f32();
+
                         chunk
                     })
                     .collect();
 
-                sender.send(meshed).unwrap();
+                sender.send((meshed, light_secs, mesh_secs)).unwrap();
             });
+
+            meshing = true;
         } else if !self.to_generate.is_empty() {
             let chunks = self
                 .to_generate
@@ -228,39 +371,85 @@ impl Chunks {
                     .collect();
                 sender.send(chunks).unwrap();
             });
+
+            generating = true;
         }
 
-        if let Ok(chunks) = self.mesh_receiver.try_recv() {
+        let meshed = if lockstep && meshing {
+            self.mesh_receiver.recv().ok()
+        } else {
+            self.mesh_receiver.try_recv().ok()
+        };
+
+        if let Some((chunks, light_secs, mesh_secs)) = meshed {
             chunks.into_iter().for_each(|c| {
+                let coords = c.coords.to_owned();
+                let propagated = self.get_chunk(&coords).map_or(false, |chunk| {
+                    chunk.needs_propagation && !c.needs_propagation
+                });
+
                 self.add_chunk(c);
+
+                if propagated {
+                    self.stitch_lights(&coords);
+                }
             });
+
+            self.timings.push(("lighting", light_secs));
+            self.timings.push(("meshing", mesh_secs));
         }
 
-        if let Ok(chunks) = self.gen_receiver.try_recv() {
+        let generated = if lockstep && generating {
+            self.gen_receiver.recv().ok()
+        } else {
+            self.gen_receiver.try_recv().ok()
+        };
+
+        if let Some(chunks) = generated {
             chunks.into_iter().for_each(|c| {
                 self.add_chunk(c);
             });
         }
     }
 
-    /// Getter for the count of internal chunks
-    pub fn len(&self) -> usize {
-        self.chunks.len()
+    /// Coordinates of the chunks unloaded since last saved, whose mobs are still around
+    pub fn evicted_coords(&self) -> Vec<Vec2<i32>> {
+        self.evicted.keys().cloned().collect()
     }
 
-    /// Getter for whether there are no chunks loaded
-    pub fn is_empty(&self) -> bool {
-        self.chunks.is_empty()
+    /// Write the chunks unloaded since last saved, along with the mobs that were in them, to be
+    /// spawned again once they're loaded back in
+    pub fn save_evicted(&mut self, suspended: Vec<(Vec2<i32>, Vec<SuspendedEntity>)>) {
+        for (coords, entities) in suspended {
+            if let Some(chunk) = self.evicted.get_mut(&coords) {
+                chunk.entities.extend(entities);
+            }
+        }
+
+        if self.evicted.is_empty() {
+            return;
+        }
+
+        let chunks = self
+            .evicted
+            .drain()
+            .map(|(coords, chunk)| (coords, chunk.to_saved()))
+            .collect();
+
+        self.storage.write(WriteBatch {
+            chunks,
+            ..Default::default()
+        });
     }
 
-    /// Return all chunks as raw
-    pub fn all(&self) -> Vec<&Chunk> {
-        self.chunks.values().collect()
+    /// Take the mobs of the chunks loaded back in since last taken
+    pub fn take_restored(&mut self) -> Vec<SuspendedEntity> {
+        std::mem::take(&mut self.restored)
     }
 
-    /// Return a mutable chunk regardless initialization
-    pub fn raw(&self, coords: &Vec2<i32>) -> Option<&Chunk> {
-        self.get_chunk(coords)
+    /// Take the seconds the pool spent on lighting and meshing since last taken
+    pub fn take_timings(&mut self) -> Vec<(&'static str, f32)> {
+        std::mem::take(&mut self.timings)
     }
 
     /// Return a chunk references only if chunk is fully initialized (generated and decorated)
@@ -306,34 +495,516 @@ impl Chunks {
         self.get_chunk(coords)
     }
 
+    /// The horizon of a chunk, read off of it if its terrain is there.
+    ///
+    /// Otherwise, the terrain is generated on the pool just to read the horizon off of, which
+    /// is None until it's done. Such horizons are of the terrain alone, without decorations or
+    /// edits, and are cached until the chunk loads or too many pile up.
+    pub fn horizon(&mut self, coords: &Vec2<i32>) -> Option<messages::Horizon> {
+        let loaded = self
+            .get_chunk(coords)
+            .filter(|chunk| !chunk.needs_terrain)
+            .map(|chunk| render_horizon(chunk, &self.registry));
+
+        if loaded.is_some() {
+            self.horizons.remove(coords);
+            return loaded;
+        }
+
+        if !self.horizons.contains_key(coords) && self.horizons_generating.insert(coords.clone()) {
+            let coords = coords.to_owned();
+            let sender = Arc::clone(&self.horizon_sender);
+            let registry = Arc::clone(&self.registry);
+            let biomes = Arc::clone(&self.biomes);
+            let config = Arc::clone(&self.config);
+
+            let job = move || {
+                let mut chunk = Chunk::new(coords, &config, None);
+                Generator::generate_chunk(&mut chunk, &registry, &biomes, &config);
+                Generator::generate_chunk_height_map(&mut chunk, &registry, &config);
+
+                sender.send(render_horizon(&chunk, &registry)).unwrap();
+            };
+
+            // worlds in lockstep have it ready right away
+            if self.config.lockstep.is_some() {
+                job();
+            } else {
+                self.pool.spawn(job);
+            }
+        }
+
+        while let Ok(horizon) = self.horizon_receiver.try_recv() {
+            let coords = Vec2(horizon.x, horizon.z);
+            self.horizons_generating.remove(&coords);
+
+            if self.horizons.len() >= self.config.max_loaded_chunks {
+                self.horizons.clear();
+            }
+
+            self.horizons.insert(coords, horizon);
+        }
+
+        self.horizons.get(coords).cloned()
+    }
+
     /// To preload chunks surrounding 0,0
     pub fn preload(&mut self, width: i16) {
         self.generate(&Vec2(0, 0), width, true);
     }
 
-    /// Start the internal cache, caching any mutated chunks.
-    pub fn start_caching(&mut self) {
-        self.caching = true;
+    /// Generate, decorate, light and save every chunk within `radius` chunks of 0,0, one ring
+    /// at a time outwards so that only the rings around the current one are kept in memory.
+    ///
+    /// Progress is saved after every ring, so an interrupted run resumes where it stopped.
+    /// `on_ring` is called with the chunks done so far and the total after every ring.
+    ///
+    /// Chunks that structures reached into after they were lit are left for the server to
+    /// light again once they're loaded.
+    pub fn pregenerate(&mut self, radius: i16, mut on_ring: impl FnMut(usize, usize)) {
+        let mut progress = self.load_pregen_progress();
+
+        let total = (0..=radius).map(|r| Self::ring(r).len()).sum();
+        let mut done = (0..progress.rings.min(radius + 1))
+            .map(|r| Self::ring(r).len())
+            .sum();
+        on_ring(done, total);
+
+        // how many rings out light may flood in from, counting diagonals
+        let reach = (self.config.max_light_level as f32 / self.config.chunk_size as f32).ceil();
+        let margin = (reach * std::f32::consts::SQRT_2).ceil() as i16;
+
+        for r in progress.rings..=radius {
+            let _span = info_span!("pregenerate", ring = r).entered();
+
+            // terrain one ring further than decoration, which spills into neighbors
+            let mut to_generate = vec![];
+
+            let to_load = ((r - margin).max(0)..=r + margin + 1)
+                .flat_map(Self::ring)
+                .filter(|coords| !self.store.contains(coords))
+                .collect::<Vec<_>>();
+            let saved = self.storage.load_chunks_blocking(&to_load);
+
+            for (coords, saved) in to_load.into_iter().zip(saved) {
+                let mut new_chunk = Chunk::new(coords.to_owned(), &self.config, saved);
+
+                if let Some(updates) = self.store.take_queued(&coords) {
+                    for u in updates {
+                        new_chunk.set_voxel(u.voxel.0, u.voxel.1, u.voxel.2, u.id);
+                    }
+                }
+
+                if new_chunk.needs_terrain {
+                    to_generate.push(new_chunk);
+                } else {
+                    let needs_decoration = new_chunk.needs_decoration;
+                    self.store.insert(new_chunk);
+
+                    if !needs_decoration {
+                        let pending = self.structures.take_pending(&coords);
+                        self.decorate_voxels(&pending);
+                    }
+                }
+            }
+
+            to_generate.par_iter_mut().for_each(|new_chunk| {
+                Generator::generate_chunk(new_chunk, &self.registry, &self.biomes, &self.config);
+                Generator::generate_chunk_height_map(new_chunk, &self.registry, &self.config);
+            });
+
+            for chunk in to_generate {
+                self.store.insert(chunk);
+            }
+
+            // decorations and structures landing on lit chunks make their lights stale
+            self.store
+                .iter_mut()
+                .for_each(|chunk| chunk.is_dirty = false);
+
+            let to_decorate = (r..=r + margin)
+                .flat_map(Self::ring)
+                .filter(|coords| self.get_chunk(coords).unwrap().needs_decoration)
+                .collect::<Vec<_>>();
+            self.decorate(&to_decorate);
+
+            // pieces and voxels reaching into chunks saved away
+            let outer = r - margin - 1;
+            let mut leftover = self.structures.pending_coords();
+            leftover.extend(self.store.queued_coords());
+            leftover.retain(|coords| Self::ring_of(coords) <= outer.min(radius));
+            leftover.sort_by_key(|Vec2(x, z)| (*x, *z));
+            leftover.dedup();
+            self.place_leftovers(&leftover);
+
+            self.store.iter_mut().for_each(|chunk| {
+                if chunk.is_dirty && !chunk.needs_propagation && !chunk.needs_decoration {
+                    chunk.needs_propagation = true;
+                    chunk.needs_saving = true;
+                }
+            });
+
+            let to_light = Self::ring(r)
+                .into_iter()
+                .filter(|coords| self.get_chunk(coords).unwrap().needs_propagation)
+                .collect::<Vec<_>>();
+            self.propagate_chunks(&to_light);
+
+            let mut batch = WriteBatch::default();
+
+            for coords in Self::ring(r) {
+                let chunk = self.store.get_chunk_mut(&coords).unwrap();
+
+                if chunk.needs_saving {
+                    batch.chunks.push((coords, chunk.to_saved()));
+                    chunk.needs_saving = false;
+                }
+            }
+
+            if outer >= 0 {
+                for coords in Self::ring(outer) {
+                    if let Some(chunk) = self.store.remove(&coords) {
+                        if chunk.needs_saving {
+                            batch.chunks.push((coords, chunk.to_saved()));
+                        }
+                    }
+                }
+            }
+
+            self.storage.write(batch);
+            self.save_evicted(vec![]);
+
+            // chunks are unloaded here, not by how recently they were used
+            self.activities.clear();
+
+            // only count rings that made it to storage
+            self.storage.flush();
+
+            progress.rings = r + 1;
+            self.save_pregen_progress(&progress);
+
+            done += Self::ring(r).len();
+            on_ring(done, total);
+        }
+
+        // chunks past the radius are left for the server, as they aren't fully decorated
+        let leftover = self
+            .store
+            .iter()
+            .filter(|chunk| chunk.needs_saving && Self::ring_of(&chunk.coords) <= radius)
+            .map(|chunk| (chunk.coords.to_owned(), chunk.to_saved()))
+            .collect();
+
+        self.storage.write(WriteBatch {
+            chunks: leftover,
+            ..Default::default()
+        });
+        self.save_evicted(vec![]);
+        self.storage.flush();
+
+        self.store.clear();
+    }
+
+    /// Chunk coordinates `r` chunks away from 0,0, rounding distances up
+    fn ring(r: i16) -> Vec<Vec2<i32>> {
+        let r = r as i32;
+        let mut coords = vec![];
+
+        for x in -r..=r {
+            for z in -r..=r {
+                let dist = x * x + z * z;
+
+                if dist <= r * r && (r == 0 || dist > (r - 1) * (r - 1)) {
+                    coords.push(Vec2(x, z));
+                }
+            }
+        }
+
+        coords
+    }
+
+    /// Which ring around 0,0 a chunk is in
+    fn ring_of(Vec2(cx, cz): &Vec2<i32>) -> i16 {
+        ((cx * cx + cz * cz) as f32).sqrt().ceil() as i16
     }
 
-    /// Stop the internal cache
-    pub fn stop_caching(&mut self) {
-        self.caching = false;
+    fn load_pregen_progress(&self) -> PregenProgress {
+        let path = self.root_folder.join(PREGEN_PROGRESS_FILE);
+
+        match File::open(&path) {
+            Ok(file) => serde_json::from_reader(file).unwrap_or_else(|e| {
+                warn!("Unable to read pregeneration progress at {:?}: {}", path, e);
+                PregenProgress::default()
+            }),
+            Err(_) => PregenProgress::default(),
+        }
     }
 
-    /// Clear the internal mutated chunks cache
-    pub fn clear_cache(&mut self) {
-        self.chunk_cache.clear();
+    fn save_pregen_progress(&self, progress: &PregenProgress) {
+        let mut file = File::create(self.root_folder.join(PREGEN_PROGRESS_FILE))
+            .expect("Could not create pregeneration progress file.");
+        let j = serde_json::to_string(progress).unwrap();
+
+        file.write_all(j.as_bytes())
+            .expect("Unable to save pregeneration progress");
     }
 
-    /// Save all chunks to their according JSON files
-    pub fn save(&self) {
-        // saving the chunks
-        self.chunks.values().for_each(|chunk| {
-            if chunk.needs_saving {
-                chunk.save();
+    /// Apply the voxels and structure pieces waiting on chunks already saved away, leaving
+    /// them to be lit again
+    fn place_leftovers(&mut self, coords: &[Vec2<i32>]) {
+        for coords in coords {
+            let saved = self.storage.load_chunk(coords);
+            let mut chunk = Chunk::new(coords.to_owned(), &self.config, saved);
+
+            if chunk.needs_terrain {
+                self.store.take_queued(coords);
+                self.structures.take_pending(coords);
+                continue;
             }
-        })
+
+            if let Some(updates) = self.store.take_queued(coords) {
+                for u in updates {
+                    chunk.set_voxel(u.voxel.0, u.voxel.1, u.voxel.2, u.id);
+                }
+            }
+
+            chunk.needs_propagation = true;
+            chunk.needs_saving = true;
+            self.store.insert(chunk);
+
+            let pending = self.structures.take_pending(coords);
+            self.decorate_voxels(&pending);
+
+            self.store
+                .remove(coords)
+                .unwrap()
+                .save(self.storage.as_ref());
+        }
+    }
+
+    /// Light chunks on all threads, with the chunks around them as they are now
+    pub(crate) fn propagate_chunks(&mut self, coords: &[Vec2<i32>]) {
+        let max_light_flood = self.config.max_light_level as usize;
+
+        let spaces = coords
+            .iter()
+            .map(|coords| {
+                let space = if self.is_open_air(coords) {
+                    None
+                } else {
+                    Some(Space::new(self, coords, max_light_flood))
+                };
+
+                (coords.to_owned(), space)
+            })
+            .collect::<Vec<_>>();
+
+        let registry = self.registry.clone();
+        let config = self.config.clone();
+
+        let lights = spaces
+            .into_par_iter()
+            .map(|(coords, space)| {
+                let lights = space.map(|space| Lights::calc_light(&space, &registry, &config));
+                (coords, lights)
+            })
+            .collect::<Vec<_>>();
+
+        let sky_light = self.config.sky_light;
+        let max_light_level = self.config.max_light_level;
+
+        for (coords, lights) in lights {
+            let chunk = self.get_chunk_mut(&coords).expect("Chunk not found");
+
+            match lights {
+                Some(lights) => chunk.set_lights(lights),
+                None => chunk.set_open_lights(sky_light, max_light_level),
+            }
+
+            chunk.needs_propagation = false;
+            chunk.needs_saving = true;
+        }
+
+        coords.iter().for_each(|coords| {
+            self.stitch_lights(coords);
+            self.flood_light_edges(coords);
+        });
+    }
+
+    /// Flood the light that stopped at the edges of a chunk before it was loaded into it, now
+    /// that it's lit. Chunks lit after they're loaded take it in through `stitch_lights` too,
+    /// but ones loaded lit out of storage would otherwise keep the light they were saved with.
+    fn flood_light_edges(&mut self, coords: &Vec2<i32>) {
+        if self
+            .get_chunk(coords)
+            .map_or(true, |chunk| chunk.needs_propagation)
+        {
+            return;
+        }
+
+        let edges = match self.store.take_light_edges(coords) {
+            Some(edges) => edges,
+            None => return,
+        };
+
+        for color in COLORS.iter() {
+            let is_sunlight = matches!(color, LightColor::None);
+
+            // the light at each edge now, as it may have changed since it was queued
+            let queue = edges
+                .iter()
+                .filter_map(|voxel| {
+                    let Vec3(vx, vy, vz) = *voxel;
+                    let level = if is_sunlight {
+                        self.get_sunlight(vx, vy, vz)
+                    } else {
+                        self.get_torch_light(vx, vy, vz, color)
+                    };
+
+                    if level == 0 {
+                        None
+                    } else {
+                        Some(LightNode {
+                            voxel: voxel.to_owned(),
+                            level,
+                        })
+                    }
+                })
+                .collect::<VecDeque<_>>();
+
+            if !queue.is_empty() {
+                Lights::global_flood_light(self, queue, is_sunlight, color);
+            }
+        }
+    }
+
+    /// Flood light both ways across the edges of a chunk that was just lit, into the chunks
+    /// around it that were lit before it or without it and would otherwise end at a seam.
+    /// Chunks yet to be lit are left alone, as they take the light in when they're lit.
+    fn stitch_lights(&mut self, coords: &Vec2<i32>) {
+        if self
+            .get_chunk(coords)
+            .map_or(true, |chunk| chunk.needs_propagation)
+        {
+            return;
+        }
+
+        let size = self.config.chunk_size as i32;
+        let max_height = self.config.max_height as i32;
+        let max_light_level = self.config.max_light_level;
+
+        let Vec2(cx, cz) = coords;
+        let (min_x, min_z) = (cx * size, cz * size);
+
+        let mut queues = [
+            VecDeque::<LightNode>::new(),
+            VecDeque::<LightNode>::new(),
+            VecDeque::<LightNode>::new(),
+            VecDeque::<LightNode>::new(),
+        ];
+
+        for &(ox, oz) in [(-1, 0), (1, 0), (0, -1), (0, 1)].iter() {
+            let n_coords = Vec2(cx + ox, cz + oz);
+
+            if self
+                .get_chunk(&n_coords)
+                .map_or(true, |chunk| chunk.needs_propagation)
+            {
+                continue;
+            }
+
+            for i in 0..size {
+                // the edge voxel within the chunk, and the one across it
+                let (vx, vz) = match (ox, oz) {
+                    (-1, _) => (min_x, min_z + i),
+                    (1, _) => (min_x + size - 1, min_z + i),
+                    (_, -1) => (min_x + i, min_z),
+                    _ => (min_x + i, min_z + size - 1),
+                };
+                let (nvx, nvz) = (vx + ox, vz + oz);
+
+                for vy in 0..max_height {
+                    for &(from, to) in [((vx, vz), (nvx, nvz)), ((nvx, nvz), (vx, vz))].iter() {
+                        let ((fx, fz), (tx, tz)) = (from, to);
+                        let to_block = self.get_block_by_voxel(tx, vy, tz);
+
+                        if !to_block.is_transparent {
+                            continue;
+                        }
+
+                        for (color, queue) in COLORS.iter().zip(queues.iter_mut()) {
+                            let is_sunlight = matches!(color, LightColor::None);
+                            let (level, across) = if is_sunlight {
+                                (self.get_sunlight(fx, vy, fz), self.get_sunlight(tx, vy, tz))
+                            } else {
+                                (
+                                    self.get_torch_light(fx, vy, fz, color),
+                                    self.get_torch_light(tx, vy, tz, color),
+                                )
+                            };
+
+                            let reduced = Lights::reduce_light(
+                                level,
+                                to_block.light_reduce,
+                                is_sunlight,
+                                false,
+                                max_light_level,
+                            );
+
+                            if reduced > across {
+                                queue.push_back(LightNode {
+                                    voxel: Vec3(fx, vy, fz),
+                                    level,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for (color, queue) in COLORS.iter().zip(queues.iter_mut()) {
+            if !queue.is_empty() {
+                let is_sunlight = matches!(color, LightColor::None);
+                Lights::global_flood_light(self, std::mem::take(queue), is_sunlight, color);
+            }
+        }
+    }
+
+    /// Light every loaded chunk again from scratch, throwing away the light they have. Slow, but
+    /// the reference the lighting kept up by `update` has to match.
+    pub fn recompute_all_light(&mut self) {
+        let coords = self.store.coords();
+        self.propagate_chunks(&coords);
+    }
+
+    /// Queue every cached chunk to be remeshed by its dirty levels, then clear the cache.
+    ///
+    /// Returns the light protocols of the cached chunks, ready to be sent along with an update
+    /// message. Their meshes follow once the remesh system gets to them.
+    pub fn take_cache_protocols(&mut self, remeshes: &mut RemeshQueue) -> Vec<ChunkProtocol> {
+        let cache = self.store.chunk_cache.clone();
+        self.clear_cache();
+
+        cache
+            .iter()
+            .filter_map(|coords| {
+                let chunk = self.get_chunk_mut(coords)?;
+                remeshes.push(coords.to_owned(), std::mem::take(&mut chunk.dirty_levels));
+
+                Some(chunk.get_protocol(false, false, true, MeshLevel::None))
+            })
+            .collect()
+    }
+
+    /// The chunks that need saving, ready to be written to the world's storage together
+    pub fn to_saved(&self) -> Vec<(Vec2<i32>, serde_json::Value)> {
+        self.store
+            .iter()
+            .filter(|chunk| chunk.needs_saving)
+            .chain(self.evicted.values())
+            .map(|chunk| (chunk.coords.to_owned(), chunk.to_saved()))
+            .collect()
     }
 
     /// Unload chunks when too many chunks are loaded.
@@ -343,6 +1014,8 @@ impl Chunks {
 
     /// Remesh a chunk, propagating itself and its neighbors then mesh.
     pub fn remesh_chunk(&mut self, coords: &Vec2<i32>, level: &MeshLevel) {
+        let _span = info_span!("remesh_chunk", x = coords.0, z = coords.1).entered();
+
         // let start = Instant::now();
         // propagate light first
         let chunk = self.get_chunk(coords).unwrap();
@@ -357,6 +1030,10 @@ impl Chunks {
         let config = self.config.clone();
         let registry = self.registry.clone();
 
+        self.get_chunk_mut(coords)
+            .unwrap()
+            .calc_sub_chunk_fills(&registry, sub_chunks);
+
         match level {
             MeshLevel::All => {
                 let chunk = self.get_chunk_mut(coords).unwrap();
@@ -411,6 +1088,9 @@ impl Chunks {
     /// 1. Generate the terrain within `terrain_radius`
     /// 2. Populate the terrains within `decorate_radius` with decoration
     ///
+    /// Chunks are read from storage on the IO threads first, along with the ones just past
+    /// `terrain_radius` ahead of the player getting there. Preloads wait for their reads.
+    ///
     /// Note: `decorate_radius` should always be less than `terrain_radius`
     pub fn generate(&mut self, coords: &Vec2<i32>, render_radius: i16, is_preload: bool) {
         let Vec2(cx, cz) = coords;
@@ -420,46 +1100,72 @@ impl Chunks {
 
         let terrain_radius = render_radius + 3;
         let decorate_radius = render_radius;
+        let read_ahead_radius = terrain_radius + CHUNK_READ_AHEAD;
+
+        let mut to_load = vec![];
+        let mut to_read = vec![];
 
-        for x in -terrain_radius..=terrain_radius {
-            for z in -terrain_radius..=terrain_radius {
+        // worlds in lockstep read chunks right away, leaving nothing to land on a later tick
+        let read_now = is_preload || self.config.lockstep.is_some();
+
+        for x in -read_ahead_radius..=read_ahead_radius {
+            for z in -read_ahead_radius..=read_ahead_radius {
                 let dist = x * x + z * z;
 
-                if dist >= terrain_radius * terrain_radius {
+                if dist >= read_ahead_radius * read_ahead_radius {
                     continue;
                 }
 
                 let coords = Vec2(cx + x as i32, cz + z as i32);
-                let chunk = self.get_chunk(&coords);
 
-                if chunk.is_none() {
-                    let index = self.to_generate.iter().position(|c| c.coords.eq(&coords));
+                // unloaded so recently that it isn't saved yet
+                if let Some(chunk) = self.evicted.remove(&coords) {
+                    self.add_chunk(chunk);
+                    continue;
+                }
 
-                    if index.is_none() {
-                        let mut new_chunk =
-                            Chunk::new(coords.to_owned(), &self.config, &self.chunk_folder);
+                if self.store.contains(&coords)
+                    || self.generating.contains(&coords)
+                    || self.to_generate.iter().any(|c| c.coords.eq(&coords))
+                {
+                    continue;
+                }
 
-                        if let Some(updates) = self.update_queue.remove(&coords) {
-                            for u in updates {
-                                new_chunk.set_voxel(u.voxel.0, u.voxel.1, u.voxel.2, u.id);
-                            }
-                        }
+                let needed = dist < terrain_radius * terrain_radius;
 
-                        if new_chunk.needs_terrain {
-                            if !self.generating.contains(&new_chunk.coords) {
-                                to_generate.push(new_chunk);
-                            }
-                        } else {
-                            self.add_chunk(new_chunk);
-                        }
+                if !needed {
+                    if !read_now && !self.read_ahead.contains_key(&coords) {
+                        to_read.push((true, dist, coords));
                     }
+                } else if let Some(saved) = self.read_ahead.remove(&coords) {
+                    to_generate.extend(self.take_loaded(coords, saved));
+                } else if read_now {
+                    to_load.push(coords);
+                } else {
+                    to_read.push((false, dist, coords));
                 }
+            }
+        }
 
-                if let Some(chunk) = self.get_chunk(&coords) {
-                    if chunk.needs_decoration && dist <= decorate_radius * decorate_radius {
-                        to_decorate.push(coords.to_owned());
-                    }
-                }
+        if !to_load.is_empty() {
+            let saved = self.storage.load_chunks_blocking(&to_load);
+
+            for (coords, saved) in to_load.into_iter().zip(saved) {
+                to_generate.extend(self.take_loaded(coords, saved));
+            }
+        }
+
+        // chunks the player needs first, closest ones first
+        to_read.sort_by_key(|(ahead, dist, _)| (*ahead, *dist));
+
+        for (ahead, _, coords) in to_read {
+            if let Some((_, needed)) = self.loading.get_mut(&coords) {
+                *needed |= !ahead;
+            } else {
+                let request = self
+                    .storage
+                    .request_chunk(coords.to_owned(), &self.load_sender);
+                self.loading.insert(coords, (request, !ahead));
             }
         }
 
@@ -488,13 +1194,94 @@ impl Chunks {
             }
         }
 
-        let to_decorate: Vec<Chunk> = to_decorate
-            .iter()
-            .map(|coords| self.chunks.remove(coords))
-            .flatten()
-            .collect();
+        for x in -decorate_radius..=decorate_radius {
+            for z in -decorate_radius..=decorate_radius {
+                if x * x + z * z > decorate_radius * decorate_radius {
+                    continue;
+                }
 
-        let builder = self.builder.clone();
+                let coords = Vec2(cx + x as i32, cz + z as i32);
+
+                if let Some(chunk) = self.get_chunk(&coords) {
+                    if chunk.needs_decoration {
+                        to_decorate.push(coords);
+                    }
+                }
+            }
+        }
+
+        self.decorate(&to_decorate);
+    }
+
+    /// Make a chunk out of what storage had for it, adding it right away if it was saved.
+    ///
+    /// Returns the chunk if its terrain still needs to be generated.
+    fn take_loaded(
+        &mut self,
+        coords: Vec2<i32>,
+        saved: Option<serde_json::Value>,
+    ) -> Option<Chunk> {
+        let mut new_chunk = Chunk::new(coords.to_owned(), &self.config, saved);
+
+        if let Some(updates) = self.store.take_queued(&coords) {
+            for u in updates {
+                new_chunk.set_voxel(u.voxel.0, u.voxel.1, u.voxel.2, u.id);
+            }
+        }
+
+        if new_chunk.needs_terrain {
+            return Some(new_chunk);
+        }
+
+        let needs_decoration = new_chunk.needs_decoration;
+        self.add_chunk(new_chunk);
+
+        // structure pieces that came in while this chunk was saved away
+        if !needs_decoration {
+            let pending = self.structures.take_pending(&coords);
+            self.decorate_voxels(&pending);
+        }
+
+        None
+    }
+
+    /// Take in the chunks read from storage, queueing the ones never saved to be generated.
+    ///
+    /// Answers to requests since superseded, or for chunks loaded some other way, are dropped.
+    fn take_loads(&mut self) {
+        let mut queued = false;
+
+        while let Ok((request, coords, saved)) = self.load_receiver.try_recv() {
+            let needed = match self.loading.get(&coords) {
+                Some((current, needed)) if *current == request => *needed,
+                _ => continue,
+            };
+
+            self.loading.remove(&coords);
+
+            if needed {
+                if let Some(chunk) = self.take_loaded(coords, saved) {
+                    self.to_generate.push(chunk);
+                    queued = true;
+                }
+            } else if self.is_near(&coords, CHUNK_READ_AHEAD) {
+                self.read_ahead.insert(coords, saved);
+            }
+        }
+
+        if queued {
+            self.prune_generation();
+        }
+    }
+
+    /// Decorate chunks on all threads, then build the structures starting in them
+    fn decorate(&mut self, coords: &[Vec2<i32>]) {
+        let to_decorate: Vec<Chunk> = coords
+            .iter()
+            .filter_map(|coords| self.store.remove(coords))
+            .collect();
+
+        let builder = self.builder.clone();
         let biomes = self.biomes.clone();
 
         let to_decorate_updates: Vec<Vec<VoxelUpdate>> = to_decorate
@@ -513,730 +1300,533 @@ impl Chunks {
         for mut chunk in to_decorate {
             let coords = chunk.coords.to_owned();
             chunk.needs_decoration = false;
-            self.add_chunk(chunk);
+            self.update_activities(&coords);
+            self.store.insert(chunk);
             to_decorate_coords.push(coords);
         }
 
         for updates in to_decorate_updates.iter() {
-            for u in updates {
-                let h = self.get_max_height(u.voxel.0, u.voxel.2) as i32;
-                self.set_voxel_by_voxel(u.voxel.0, u.voxel.1, u.voxel.2, u.id);
-                if u.voxel.1 > h && Generator::check_height(u.id, &self.registry) {
-                    self.set_max_height(u.voxel.0, u.voxel.2, u.voxel.1 as u32);
-                }
-            }
+            self.decorate_voxels(updates);
+        }
+
+        for coords in to_decorate_coords.iter() {
+            self.place_structures(coords);
         }
     }
 
-    /// Centered around a coordinate, return 3x3 chunks neighboring the coordinate (not inclusive).
-    fn neighbors(&self, Vec2(cx, cz): &Vec2<i32>) -> Vec<Option<&Chunk>> {
-        let mut neighbors = Vec::new();
-        let r = (self.config.max_light_level as f32 / self.config.chunk_size as f32).ceil() as i32;
+    /// Build the structures starting in a freshly decorated chunk, along with any pieces of
+    /// other structures waiting on it.
+    ///
+    /// Pieces reaching into chunks that aren't loaded yet are kept pending.
+    fn place_structures(&mut self, coords: &Vec2<i32>) {
+        let chunk_size = self.config.chunk_size;
 
-        for x in -r..=r {
-            for z in -r..r {
-                if x == 0 && z == 0 {
-                    continue;
-                }
+        let mut updates = self.structures.take_pending(coords);
 
-                if x * x + z * z >= r * r {
-                    continue;
-                }
+        for (index, Vec2(vx, vz)) in self.structures.origins_in_chunk(coords, chunk_size) {
+            let vy = self.get_max_height(vx, vz) as i32 + 1;
 
-                neighbors.push(self.get_chunk(&Vec2(cx + x, cz + z)));
+            for u in self
+                .structures
+                .place(index, &Vec3(vx, vy, vz), &self.registry)
+            {
+                let target = map_voxel_to_chunk(u.voxel.0, u.voxel.1, u.voxel.2, chunk_size);
+
+                if self.get_chunk(&target).is_some() {
+                    updates.push(u);
+                } else {
+                    self.structures.defer(target, u);
+                }
             }
         }
 
-        neighbors
+        self.decorate_voxels(&updates);
     }
 
-    /// Get a chunk reference from a coordinate
-    #[inline]
-    pub fn get_chunk(&self, coords: &Vec2<i32>) -> Option<&Chunk> {
-        self.chunks.get(&coords)
-    }
-
-    /// Get a mutable chunk reference from a coordinate
-    pub fn get_chunk_mut(&mut self, coords: &Vec2<i32>) -> Option<&mut Chunk> {
-        // self.update_activities(coords);
+    /// Set decoration voxels, raising the height map where they stick out.
+    ///
+    /// Voxels landing in chunks that are already lit, like a tree of a chunk generated later
+    /// reaching over the border, go through `update` so that the sunlight under and around them
+    /// is seeded again. Otherwise the old light stays on until the chunk is lit from scratch.
+    fn decorate_voxels(&mut self, updates: &[VoxelUpdate]) {
+        for u in updates {
+            let Vec3(vx, vy, vz) = u.voxel;
+            let h = self.get_max_height(vx, vz) as i32;
+            let counts = Generator::check_height(u.id, &self.registry);
+
+            let is_lit = self
+                .get_chunk_by_voxel(vx, vy, vz)
+                .map_or(false, |chunk| !chunk.needs_propagation);
+
+            if is_lit {
+                self.update(vx, vy, vz, u.id, 0, 0);
+
+                // `update` raises the height map for plants and fluids too
+                if vy > h && !counts {
+                    self.set_max_height(vx, vz, h as u32);
+                }
+            } else {
+                self.set_voxel_by_voxel(vx, vy, vz, u.id);
 
-        let chunk = self.chunks.get_mut(&coords);
-        // ? does non-mutable chunks need to be cached?
-        if self.caching && chunk.is_some() {
-            self.chunk_cache.insert(coords.to_owned());
+                if vy > h && counts {
+                    self.set_max_height(vx, vz, vy as u32);
+                }
+            }
         }
-
-        chunk
     }
 
-    /// Get a chunk reference from a voxel coordinate
-    pub fn get_chunk_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> Option<&Chunk> {
-        let coords = map_voxel_to_chunk(vx, vy, vz, self.config.chunk_size);
-        self.get_chunk(&coords)
+    /// Generate the chunks around a player, dropping any pending generation no player's
+    /// view covers anymore.
+    pub fn watch(&mut self, owner: usize, coords: &Vec2<i32>, render_radius: i16) {
+        self.views.insert(owner, (coords.to_owned(), render_radius));
+        self.generate(coords, render_radius, false);
+        self.prune_generation();
     }
 
-    /// Get a mutable chunk reference from a voxel coordinate
-    pub fn get_chunk_by_voxel_mut(&mut self, vx: i32, vy: i32, vz: i32) -> Option<&mut Chunk> {
-        let coords = map_voxel_to_chunk(vx, vy, vz, self.config.chunk_size);
-        self.get_chunk_mut(&coords)
+    /// Stop generating chunks for a player, signaled when they leave
+    pub fn unwatch(&mut self, owner: usize) {
+        if self.views.remove(&owner).is_some() {
+            self.prune_generation();
+        }
     }
 
-    /// Get the voxel type at a voxel coordinate
-    pub fn get_voxel_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> u32 {
-        let chunk = self.get_chunk_by_voxel(vx, vy, vz);
-        if let Some(chunk) = chunk {
-            chunk.get_voxel(vx, vy, vz)
-        } else {
-            0
-        }
+    /// Whether any player's view still needs a chunk to be generated
+    pub fn is_watched(&self, coords: &Vec2<i32>) -> bool {
+        self.is_near(coords, 0)
     }
 
-    /// Get the voxel type at a world coordinate
-    pub fn get_voxel_by_world(&self, wx: f32, wy: f32, wz: f32) -> u32 {
-        let Vec3(vx, vy, vz) = map_world_to_voxel(wx, wy, wz, self.config.dimension);
-        self.get_voxel_by_voxel(vx, vy, vz)
+    /// Whether a chunk is within `margin` chunks past any player's terrain radius
+    fn is_near(&self, Vec2(cx, cz): &Vec2<i32>, margin: i16) -> bool {
+        self.views.values().any(|(Vec2(vx, vz), render_radius)| {
+            let radius = (*render_radius + 3 + margin) as i32;
+            let (dx, dz) = (cx - vx, cz - vz);
+            dx * dx + dz * dz < radius * radius
+        })
     }
 
-    /// Set the voxel type for a voxel coordinate
+    /// Cancel the pending generation of chunks no player is around anymore, and generate
+    /// the rest starting from the ones closest to any player. Chunks read ahead of players
+    /// that have since turned away are dropped too.
     ///
-    /// Note: This clears the voxel rotation and stage.
-    ///
-    /// Side-effects:
-    ///
-    /// 1. Sets the neighboring chunk's padding data if the coordinates are on a chunk edge.
-    /// 2. Calculates the chunk's and the neighbors' dirty sub-chunk levels
-    pub fn set_voxel_by_voxel(&mut self, vx: i32, vy: i32, vz: i32, id: u32) {
-        let max_height = self.config.max_height;
-        if vy as u32 >= max_height {
-            return;
+    /// Chunks already sent to the generation threads are kept, as the work is done anyways.
+    fn prune_generation(&mut self) {
+        let far = self
+            .read_ahead
+            .keys()
+            .filter(|coords| !self.is_near(coords, CHUNK_READ_AHEAD))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for coords in far {
+            self.read_ahead.remove(&coords);
         }
 
-        let sub_chunks = self.config.sub_chunks;
-        let chunk = self.get_chunk_by_voxel_mut(vx, vy, vz);
-
-        if let Some(chunk) = chunk {
-            chunk.set_voxel(vx, vy, vz, id);
-            chunk.calc_dirty_levels(vy, max_height, sub_chunks);
-            chunk.is_dirty = true;
-        } else {
-            let updates = self
-                .update_queue
-                .entry(map_voxel_to_chunk(vx, vy, vz, self.config.chunk_size))
-                .or_insert_with(Vec::new);
-            updates.push(VoxelUpdate {
-                voxel: Vec3(vx, vy, vz),
-                id,
-            });
+        let unwatched = self
+            .loading
+            .keys()
+            .filter(|coords| !self.is_watched(coords))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        // still read, but only kept if read ahead
+        for coords in unwatched {
+            if let Some((_, needed)) = self.loading.get_mut(&coords) {
+                *needed = false;
+            }
         }
 
-        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
-        neighbors.iter().for_each(|c| {
-            let n_chunk = self.get_chunk_mut(c);
+        let before = self.to_generate.len();
 
-            if let Some(n_chunk) = n_chunk {
-                n_chunk.set_voxel(vx, vy, vz, id);
-                n_chunk.calc_dirty_levels(vy, max_height, sub_chunks);
-                n_chunk.is_dirty = true;
-            } else {
-                let updates = self
-                    .update_queue
-                    .entry(c.to_owned())
-                    .or_insert_with(Vec::new);
-                updates.push(VoxelUpdate {
-                    voxel: Vec3(vx, vy, vz),
-                    id,
-                });
-            }
-        })
-    }
+        let mut to_generate = std::mem::take(&mut self.to_generate)
+            .into_iter()
+            .filter(|chunk| self.is_watched(&chunk.coords))
+            .map(|chunk| {
+                let Vec2(cx, cz) = &chunk.coords;
+                let dist = self
+                    .views
+                    .values()
+                    .map(|(Vec2(vx, vz), _)| (cx - vx).pow(2) + (cz - vz).pow(2))
+                    .min()
+                    .unwrap_or(0);
+                (dist, chunk)
+            })
+            .collect::<Vec<_>>();
 
-    /// Get the voxel rotation at a voxel coordinate
-    pub fn get_voxel_rotation_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> BlockRotation {
-        let chunk = self.get_chunk_by_voxel(vx, vy, vz);
-        if let Some(chunk) = chunk {
-            chunk.get_voxel_rotation(vx, vy, vz)
-        } else {
-            panic!("Rotation not obtainable.");
-        }
-    }
+        to_generate.sort_by_key(|(dist, _)| *dist);
+        self.to_generate = to_generate.into_iter().map(|(_, chunk)| chunk).collect();
 
-    /// Set the voxel rotation at a voxel coordinate
-    ///
-    /// Side-effects:
-    ///
-    /// 1. Sets the neighboring chunk's padding data if the coordinates are on a chunk edge.
-    /// 2. Calculates the chunk's and the neighbors' dirty sub-chunk levels
-    pub fn set_voxel_rotation_by_voxel(
-        &mut self,
-        vx: i32,
-        vy: i32,
-        vz: i32,
-        rotation: &BlockRotation,
-    ) {
-        let max_height = self.config.max_height;
-        if vy as u32 >= max_height {
-            return;
-        }
+        let cancelled = before - self.to_generate.len();
 
-        let sub_chunks = self.config.sub_chunks;
-        let chunk = self.get_chunk_by_voxel_mut(vx, vy, vz);
+        if cancelled > 0 {
+            debug!("Cancelled the generation of {} chunks.", cancelled);
+        }
+    }
 
-        // TODO: update chunks data for unloaded chunks.
+    /// Centered around a coordinate, return 3x3 chunks neighboring the coordinate (not inclusive).
+    fn neighbors(&self, Vec2(cx, cz): &Vec2<i32>) -> Vec<Option<&Chunk>> {
+        let mut neighbors = Vec::new();
+        let r = (self.config.max_light_level as f32 / self.config.chunk_size as f32).ceil() as i32;
 
-        if let Some(chunk) = chunk {
-            chunk.set_voxel_rotation(vx, vy, vz, rotation);
-            chunk.calc_dirty_levels(vy, max_height, sub_chunks);
-            chunk.is_dirty = true;
-        }
+        for x in -r..=r {
+            for z in -r..r {
+                if x == 0 && z == 0 {
+                    continue;
+                }
 
-        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
-        neighbors.iter().for_each(|c| {
-            let n_chunk = self.get_chunk_mut(c);
+                if x * x + z * z >= r * r {
+                    continue;
+                }
 
-            if let Some(n_chunk) = n_chunk {
-                n_chunk.set_voxel_rotation(vx, vy, vz, rotation);
-                n_chunk.calc_dirty_levels(vy, max_height, sub_chunks);
-                n_chunk.is_dirty = true;
+                neighbors.push(self.get_chunk(&Vec2(cx + x, cz + z)));
             }
-        })
-    }
-
-    /// Get the voxel stage at a voxel coordinate
-    pub fn get_voxel_stage_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> u32 {
-        let chunk = self.get_chunk_by_voxel(vx, vy, vz);
-        if let Some(chunk) = chunk {
-            chunk.get_voxel_stage(vx, vy, vz)
-        } else {
-            panic!("Stage not obtainable.");
         }
+
+        neighbors
     }
 
-    /// Set the voxel stage at a voxel coordinate
-    ///
-    /// Side-effects:
+    /// Add a chunk instance to self
     ///
-    /// 1. Sets the neighboring chunk's padding data if the coordinates are on a chunk edge.
-    /// 2. Calculates the chunk's and the neighbors' dirty sub-chunk levels
-    pub fn set_voxel_stage_by_voxel(&mut self, vx: i32, vy: i32, vz: i32, stage: u32) {
-        let max_height = self.config.max_height;
-        if vy as u32 >= max_height {
-            return;
-        }
+    /// Removes existing chunks first.
+    pub fn add_chunk(&mut self, mut chunk: Chunk) {
+        self.meshing.remove(&chunk.coords);
+        self.generating.remove(&chunk.coords);
 
-        let sub_chunks = self.config.sub_chunks;
-        let chunk = self.get_chunk_by_voxel_mut(vx, vy, vz);
+        // reads still on their way are stale by the time they arrive
+        self.loading.remove(&chunk.coords);
+        self.read_ahead.remove(&chunk.coords);
 
-        // TODO: update chunks data for unloaded chunks.
+        self.update_activities(&chunk.coords);
 
-        if let Some(chunk) = chunk {
-            chunk.set_voxel_stage(vx, vy, vz, stage);
-            chunk.calc_dirty_levels(vy, max_height, sub_chunks);
-            chunk.is_dirty = true;
+        if !chunk.entities.is_empty() {
+            // saved again without them, so they don't come back twice
+            self.restored.append(&mut chunk.entities);
+            chunk.needs_saving = true;
         }
 
-        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
-        neighbors.iter().for_each(|c| {
-            let n_chunk = self.get_chunk_mut(c);
+        let coords = chunk.coords.to_owned();
+        self.store.insert(chunk);
+        self.store.sync_padding(&coords);
+        self.flood_light_edges(&coords);
 
-            if let Some(n_chunk) = n_chunk {
-                n_chunk.set_voxel_stage(vx, vy, vz, stage);
-                n_chunk.calc_dirty_levels(vy, max_height, sub_chunks);
-                n_chunk.is_dirty = true;
-            }
-        })
-    }
-
-    /// Get the sunlight level at a voxel coordinate
-    pub fn get_sunlight(&self, vx: i32, vy: i32, vz: i32) -> u32 {
-        let chunk = self.get_chunk_by_voxel(vx, vy, vz);
-        if let Some(chunk) = chunk {
-            chunk.get_sunlight(vx, vy, vz)
-        } else {
-            0
-        }
+        self.unload_chunks();
     }
 
-    /// Set the sunlight level for a voxel coordinate
-    ///
-    /// Side-effects:
+    /// Propagate light on a chunk. Things this function does:
     ///
-    /// 1. Sets the neighboring chunk's padding data if the coordinates are on a chunk edge.
-    /// 2. Calculates the chunk's and the neighbors' dirty sub-chunk levels
-    pub fn set_sunlight(&mut self, vx: i32, vy: i32, vz: i32, level: u32) {
-        let max_height = self.config.max_height;
-        if vy as u32 >= max_height {
-            return;
-        }
+    /// 1. Spread sunlight from the very top of the chunk
+    /// 2. Recognize the torch lights and flood-fill them as well
+    pub fn propagate_chunk(&mut self, coords: &Vec2<i32>) {
+        let max_light_flood = self.config.max_light_level as usize;
+        let sky_light = self.config.sky_light;
+        let max_light_level = self.config.max_light_level;
 
-        let sub_chunks = self.config.sub_chunks;
+        let lights = if self.is_open_air(coords) {
+            None
+        } else {
+            let space = Space::new(self, coords, max_light_flood);
+            Some(Lights::calc_light(&space, &self.registry, &self.config))
+        };
 
-        let chunk = self
-            .get_chunk_by_voxel_mut(vx, vy, vz)
-            .expect("Chunk not found.");
+        let chunk = self.get_chunk_mut(coords).expect("Chunk not found");
 
-        chunk.set_sunlight(vx, vy, vz, level);
-        chunk.calc_dirty_levels(vy, max_height, sub_chunks);
-        chunk.is_dirty = true;
+        match lights {
+            Some(lights) => chunk.set_lights(lights),
+            None => chunk.set_open_lights(sky_light, max_light_level),
+        }
 
-        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
-        neighbors.iter().for_each(|c| {
-            let n_chunk = self.get_chunk_mut(c).unwrap();
+        chunk.needs_propagation = false;
+        chunk.needs_saving = true;
 
-            n_chunk.set_sunlight(vx, vy, vz, level);
-            n_chunk.calc_dirty_levels(vy, max_height, sub_chunks);
-            n_chunk.is_dirty = true;
-        })
+        self.stitch_lights(coords);
     }
 
-    /// Get the torch light level by voxel coordinates of a specified color
-    pub fn get_torch_light(&self, vx: i32, vy: i32, vz: i32, color: &LightColor) -> u32 {
-        let chunk = self.get_chunk_by_voxel(vx, vy, vz);
-        if let Some(chunk) = chunk {
-            chunk.get_torch_light(vx, vy, vz, color)
-        } else {
-            0
-        }
+    /// Whether a chunk and every chunk light could flood in from are only made of air,
+    /// in which case there's no need to propagate light
+    fn is_open_air(&self, coords: &Vec2<i32>) -> bool {
+        self.get_chunk(coords).map_or(false, |chunk| chunk.is_empty)
+            && self
+                .neighbors(coords)
+                .iter()
+                .all(|neighbor| neighbor.map_or(false, |chunk| chunk.is_empty))
     }
 
-    /// Set the torch light level by voxel coordinates of a specified color
-    ///
-    /// Side-effects:
-    ///
-    /// 1. Sets the neighboring chunk's padding data if the coordinates are on a chunk edge.
-    /// 2. Calculates the chunk's and the neighbors' dirty sub-chunk levels
-    pub fn set_torch_light(&mut self, vx: i32, vy: i32, vz: i32, level: u32, color: &LightColor) {
-        let max_height = self.config.max_height;
-        if vy as u32 >= max_height {
-            return;
+    /// Update the activities of chunks
+    fn update_activities(&mut self, coords: &Vec2<i32>) {
+        if let Some(index) = self.activities.iter().position(|c| *c == *coords) {
+            self.activities.remove(index);
         }
 
-        let sub_chunks = self.config.sub_chunks;
-
-        let chunk = self
-            .get_chunk_by_voxel_mut(vx, vy, vz)
-            .expect("Chunk not found.");
-
-        chunk.set_torch_light(vx, vy, vz, level, color);
-        chunk.calc_dirty_levels(vy, max_height, sub_chunks);
-        chunk.is_dirty = true;
-
-        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
-        neighbors.iter().for_each(|c| {
-            let n_chunk = self.get_chunk_mut(c).unwrap();
-
-            n_chunk.set_torch_light(vx, vy, vz, level, color);
-            n_chunk.calc_dirty_levels(vy, max_height, sub_chunks);
-            n_chunk.is_dirty = true;
-        })
-    }
-
-    /// Get a block type from a voxel coordinate
-    pub fn get_block_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> &Block {
-        let voxel = self.get_voxel_by_voxel(vx, vy, vz);
-        self.registry.get_block_by_id(voxel)
+        self.activities.push_back(coords.to_owned());
     }
 
-    /// Get a block type from a voxel id
-    pub fn get_block_by_id(&self, id: u32) -> &Block {
-        self.registry.get_block_by_id(id)
-    }
+    /// Unload chunks that are too old, kept aside until the mobs in them are saved along
+    fn unload_chunks(&mut self) {
+        let diff = self.store.len() as i32 - self.config.max_loaded_chunks as i32;
 
-    /// Get the max height at a voxel column coordinate
-    pub fn get_max_height(&self, vx: i32, vz: i32) -> u32 {
-        if let Some(chunk) = self.get_chunk_by_voxel(vx, 0, vz) {
-            chunk.get_max_height(vx, vz)
-        } else {
-            0
+        for _ in 0..diff.max(0) {
+            if let Some(coords) = self.activities.pop_front() {
+                if let Some(chunk) = self.store.remove(&coords) {
+                    self.evicted.insert(coords, chunk);
+                }
+            }
         }
     }
+}
 
-    /// Set the max height at a voxel column coordinate
-    pub fn set_max_height(&mut self, vx: i32, vz: i32, height: u32) {
-        // this is reasonable because if a chunk DNE, and gets instantiated later on,
-        // max height will be generated on instantiation too.
-        if let Some(chunk) = self.get_chunk_by_voxel_mut(vx, 0, vz) {
-            chunk.set_max_height(vx, vz, height);
-        }
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
 
-        let neighbors = self.get_neighbor_chunk_coords(vx, 0, vz);
-        neighbors.iter().for_each(|c| {
-            if let Some(n_chunk) = self.get_chunk_mut(c) {
-                n_chunk.set_max_height(vx, vz, height);
-                n_chunk.is_dirty = true;
-            }
-        })
-    }
+    use crate::test_utils::{self, AIR, GLASS, STONE, TORCH};
 
-    /// Get whether a voxel is walkable
-    pub fn get_walkable_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> bool {
-        let block = self
-            .registry
-            .get_block_by_id(self.get_voxel_by_voxel(vx, vy, vz));
-        !block.is_solid || block.is_plant
-    }
+    use super::*;
 
-    /// Get whether a voxel is solid
-    pub fn get_solidity_by_voxel(&self, vx: i32, vy: i32, vz: i32) -> bool {
-        self.get_voxel_by_voxel(vx, vy, vz) != 0
-    }
+    /// Chunk sizes, heights and sub-chunks of the worlds tested at, up to wide and tall ones
+    const WORLD_SIZES: [(usize, u32, u32); 3] = [(8, 32, 2), (12, 128, 8), (32, 256, 8)];
 
-    /// Get whether a voxel is fluid
-    pub fn get_fluidity_by_voxel(&self, _vx: i32, _vy: i32, _vz: i32) -> bool {
-        // TODO: ADD FLUIDS
-        false
+    fn lights_of(chunks: &Chunks, coords: Vec2<i32>) -> Vec<u32> {
+        chunks.get_chunk(&coords).unwrap().get_lights().data.clone()
     }
 
-    /// Get neighboring chunks according to a voxel coordinate
-    pub fn get_neighbor_chunk_coords(&self, vx: i32, vy: i32, vz: i32) -> HashSet<Vec2<i32>> {
-        let chunk_size = self.config.chunk_size;
+    #[test]
+    fn update_height_map_works() {
+        let mut chunks = test_utils::chunks(1, 4);
+        assert_eq!(chunks.get_max_height(3, 3), 3);
 
-        let mut neighbor_chunks = HashSet::new();
+        chunks.update(3, 10, 3, STONE, 0, 0);
+        assert_eq!(chunks.get_max_height(3, 3), 10);
 
-        let coords = map_voxel_to_chunk(vx, vy, vz, chunk_size);
-        let Vec3(lx, _, lz) = map_voxel_to_chunk_local(vx, vy, vz, chunk_size);
+        chunks.update(3, 10, 3, AIR, 0, 0);
+        assert_eq!(chunks.get_max_height(3, 3), 3);
+    }
 
-        let chunk_size = chunk_size as i32;
-        let Vec2(cx, cz) = coords;
+    #[test]
+    fn update_sunlight_works() {
+        let mut chunks = test_utils::chunks(1, 4);
+        let max_light_level = chunks.config.max_light_level;
 
-        let a = lx <= 0;
-        let b = lz <= 0;
-        let c = lx >= chunk_size - 1;
-        let d = lz >= chunk_size - 1;
+        assert_eq!(chunks.get_sunlight(3, 4, 3), max_light_level);
+        assert_eq!(chunks.get_sunlight(3, 2, 3), 0);
 
-        // Direct neighbors
-        if a {
-            neighbor_chunks.insert(Vec2(cx - 1, cz));
-        }
-        if b {
-            neighbor_chunks.insert(Vec2(cx, cz - 1));
-        }
-        if c {
-            neighbor_chunks.insert(Vec2(cx + 1, cz));
-        }
-        if d {
-            neighbor_chunks.insert(Vec2(cx, cz + 1));
-        }
+        // a roof shades what's right under it, light only coming in from the sides
+        chunks.update(3, 10, 3, STONE, 0, 0);
+        assert_eq!(chunks.get_sunlight(3, 9, 3), max_light_level - 1);
 
-        // Side-to-side diagonals
-        if a && b {
-            neighbor_chunks.insert(Vec2(cx - 1, cz - 1));
-        }
-        if a && d {
-            neighbor_chunks.insert(Vec2(cx - 1, cz + 1));
-        }
-        if b && c {
-            neighbor_chunks.insert(Vec2(cx + 1, cz - 1));
-        }
-        if c && d {
-            neighbor_chunks.insert(Vec2(cx + 1, cz + 1));
-        }
+        chunks.update(3, 10, 3, AIR, 0, 0);
+        assert_eq!(chunks.get_sunlight(3, 9, 3), max_light_level);
+    }
+
+    #[test]
+    fn remesh_reaches_blocks_above_terrain() {
+        let mut chunks = test_utils::chunks(1, 4);
 
-        neighbor_chunks.remove(&coords);
+        // the top of a tower, far above anything generated
+        chunks.update(3, 30, 3, STONE, 0, 0);
+        chunks.remesh_chunk(&Vec2(0, 0), &MeshLevel::All);
 
-        neighbor_chunks
+        let chunk = chunks.get_chunk(&Vec2(0, 0)).unwrap();
+        assert!(chunk.meshes[1].opaque.is_some());
     }
 
-    /// Get the voxel above the first standable block below
-    pub fn get_standable_voxel(&self, voxel: &Vec3<i32>) -> Vec3<i32> {
-        let mut voxel = voxel.clone();
-        loop {
-            if voxel.1 == 0 || self.get_walkable_by_voxel(voxel.0, voxel.1, voxel.2) {
-                voxel.1 -= 1;
-            } else {
-                break;
-            }
-        }
-        voxel.1 += 1;
-        voxel
+    #[test]
+    fn restore_entities_works() {
+        let mut chunks = test_utils::chunks(1, 4);
+
+        let mut chunk = Chunk::new(Vec2(5, 5), &chunks.config, None);
+        chunk.entities.push(SuspendedEntity {
+            etype: "cow".to_owned(),
+            position: Vec3(40.0, 5.0, 40.0),
+            child: false,
+            health: Some(3.0),
+        });
+        chunks.add_chunk(chunk);
+
+        assert_eq!(chunks.take_restored().len(), 1);
+        assert!(chunks.take_restored().is_empty());
+
+        // saved again without them
+        let chunk = chunks.get_chunk(&Vec2(5, 5)).unwrap();
+        assert!(chunk.entities.is_empty() && chunk.needs_saving);
     }
 
-    /// Add a chunk instance to self
-    ///
-    /// Removes existing chunks first.
-    pub fn add_chunk(&mut self, chunk: Chunk) {
-        self.meshing.remove(&chunk.coords);
-        self.generating.remove(&chunk.coords);
+    #[test]
+    fn unload_chunks_works() {
+        let mut config = test_utils::config();
+        config.max_loaded_chunks = 9;
+        let mut chunks = test_utils::sized_chunks_with(config, 1, |_, vy, _| vy < 4);
 
-        self.update_activities(&chunk.coords);
+        // the oldest chunk is unloaded, but only written once its mobs are put away with it
+        let chunk = Chunk::new(Vec2(5, 5), &chunks.config, None);
+        chunks.add_chunk(chunk);
 
-        self.chunks.remove(&chunk.coords);
-        self.chunks.insert(chunk.coords.to_owned(), chunk);
+        let evicted = chunks.evicted_coords();
+        assert_eq!(chunks.len(), 9);
+        assert_eq!(evicted.len(), 1);
+        assert!(!chunks.contains(&evicted[0]));
 
-        self.unload_chunks();
+        chunks.save_evicted(vec![(evicted[0].clone(), vec![])]);
+        assert!(chunks.evicted_coords().is_empty());
     }
 
-    /// Update a voxel to a new type
-    pub fn update(&mut self, vx: i32, vy: i32, vz: i32, id: u32, rotation: u32, y_rotation: u32) {
-        // TODO: fix this code (might have better way)
-        self.get_chunk_by_voxel_mut(vx, vy, vz)
-            .unwrap()
-            .needs_saving = true;
-        let needs_propagation = self
-            .get_chunk_by_voxel(vx, vy, vz)
-            .unwrap()
-            .needs_propagation;
+    #[test]
+    fn stitch_lights_works() {
+        let mut chunks = test_utils::chunks(1, 4);
+        let vx = chunks.config.chunk_size as i32 - 1;
 
-        let max_height = self.config.max_height as i32;
-        let max_light_level = self.config.max_light_level;
+        // lit on its own, without touching the chunk next to it
+        chunks.set_voxel_by_voxel(vx, 4, 3, TORCH);
+        chunks.propagate_chunks(&[Vec2(0, 0)]);
 
-        let height = self.get_max_height(vx, vz);
+        assert_eq!(chunks.get_torch_light(vx + 1, 4, 3, &LightColor::Red), 14);
+        assert_eq!(chunks.get_torch_light(vx + 2, 4, 3, &LightColor::Red), 13);
+    }
 
-        // TODO: better way? RefCell?
-        let current_type = self.get_block_by_voxel(vx, vy, vz).clone();
-        let updated_type = self.get_block_by_id(id).clone();
+    #[test]
+    fn flood_light_edges_works() {
+        let mut chunks = test_utils::chunks(1, 4);
+        let vx = chunks.config.chunk_size as i32 - 1;
 
-        let voxel = Vec3(vx, vy, vz);
+        // a torch on the edge while the chunk next to it is saved away, lit as it was
+        let unloaded = chunks.remove(&Vec2(1, 0)).unwrap();
+        chunks.update(vx, 4, 3, TORCH, 0, 0);
+        chunks.add_chunk(unloaded);
 
-        // updating the new block
-        self.set_voxel_by_voxel(vx, vy, vz, id);
+        assert_eq!(chunks.get_torch_light(vx + 1, 4, 3, &LightColor::Red), 14);
+        assert_eq!(chunks.get_torch_light(vx + 2, 4, 3, &LightColor::Red), 13);
+    }
 
-        if updated_type.rotatable {
-            let y_rotation = if updated_type.y_rotatable {
-                y_rotation
-            } else {
-                0
-            };
+    #[test]
+    fn decorate_voxels_relights_lit_chunks() {
+        let mut chunks = test_utils::chunks(2, 4);
+        let max_light_level = chunks.config.max_light_level;
+        assert_eq!(chunks.get_sunlight(3, 9, 3), max_light_level);
 
-            self.set_voxel_rotation_by_voxel(
-                vx,
-                vy,
-                vz,
-                &BlockRotation::encode(rotation, y_rotation),
-            );
-        }
+        // as if a chunk generated next to it grew a tree over the border
+        chunks.decorate_voxels(&[VoxelUpdate {
+            voxel: Vec3(3, 10, 3),
+            id: STONE,
+        }]);
 
-        // updating the height map
-        if self.registry.is_air(id) {
-            if vy == height as i32 {
-                // on max height, should set max height to lower
-                for y in (0..vy).rev() {
-                    if y == 0
-                        || Generator::check_height(
-                            self.get_voxel_by_voxel(vx, y, vz),
-                            &self.registry,
-                        )
-                    {
-                        self.set_max_height(vx, vz, y as u32);
-                        break;
-                    }
-                }
-            }
-        } else if height < vy as u32 {
-            self.set_max_height(vx, vz, vy as u32);
-        }
+        assert_eq!(chunks.get_max_height(3, 3), 10);
+        assert_eq!(chunks.get_sunlight(3, 9, 3), max_light_level - 1);
+    }
 
-        const RED: LightColor = LightColor::Red;
-        const GREEN: LightColor = LightColor::Green;
-        const BLUE: LightColor = LightColor::Blue;
-        const NONE: LightColor = LightColor::None;
-
-        // update light levels
-        if !needs_propagation {
-            if current_type.is_light {
-                // remove leftover light
-                Lights::global_remove_light(self, vx, vy, vz, false, &RED);
-                Lights::global_remove_light(self, vx, vy, vz, false, &GREEN);
-                Lights::global_remove_light(self, vx, vy, vz, false, &BLUE);
-            } else if current_type.is_transparent && !updated_type.is_transparent {
-                // remove light if solid block is placed
-                [false, true].iter().for_each(|&is_sunlight| {
-                    if is_sunlight {
-                        if self.get_sunlight(vx, vy, vz) != 0 {
-                            Lights::global_remove_light(self, vx, vy, vz, is_sunlight, &NONE);
-                        }
-                    } else {
-                        if self.get_torch_light(vx, vy, vz, &RED) != 0 {
-                            Lights::global_remove_light(self, vx, vy, vz, is_sunlight, &RED);
-                        }
-                        if self.get_torch_light(vx, vy, vz, &GREEN) != 0 {
-                            Lights::global_remove_light(self, vx, vy, vz, is_sunlight, &GREEN);
-                        }
-                        if self.get_torch_light(vx, vy, vz, &BLUE) != 0 {
-                            Lights::global_remove_light(self, vx, vy, vz, is_sunlight, &BLUE);
-                        }
-                    };
-                });
-            }
+    #[test]
+    fn update_torch_light_across_chunks_works() {
+        let mut chunks = test_utils::chunks(2, 4);
+        let size = chunks.config.chunk_size as i32;
 
-            if updated_type.is_light {
-                // placing a light
-
-                if updated_type.red_light_level > 0 {
-                    self.set_torch_light(vx, vy, vz, updated_type.red_light_level, &RED);
-
-                    Lights::global_flood_light(
-                        self,
-                        VecDeque::from(vec![LightNode {
-                            voxel: voxel.clone(),
-                            level: updated_type.red_light_level,
-                        }]),
-                        false,
-                        &RED,
-                    );
-                }
+        // on the edge of the chunk at the origin, lighting the chunk next to it
+        let vx = size - 1;
+        chunks.update(vx, 4, 3, TORCH, 0, 0);
 
-                if updated_type.green_light_level > 0 {
-                    self.set_torch_light(vx, vy, vz, updated_type.green_light_level, &GREEN);
-
-                    Lights::global_flood_light(
-                        self,
-                        VecDeque::from(vec![LightNode {
-                            voxel: voxel.clone(),
-                            level: updated_type.green_light_level,
-                        }]),
-                        false,
-                        &GREEN,
-                    );
-                }
+        assert_eq!(chunks.get_torch_light(vx, 4, 3, &LightColor::Red), 15);
+        assert_eq!(chunks.get_torch_light(vx + 1, 4, 3, &LightColor::Red), 14);
+        assert_eq!(chunks.get_torch_light(vx, 4, 3, &LightColor::Green), 0);
 
-                if updated_type.blue_light_level > 0 {
-                    self.set_torch_light(vx, vy, vz, updated_type.blue_light_level, &BLUE);
-
-                    Lights::global_flood_light(
-                        self,
-                        VecDeque::from(vec![LightNode {
-                            voxel,
-                            level: updated_type.blue_light_level,
-                        }]),
-                        false,
-                        &BLUE,
-                    );
-                }
-            } else if updated_type.is_transparent && !current_type.is_transparent {
-                // solid block removed
-                [false, true].iter().for_each(|&is_sunlight| {
-                    let mut queue = VecDeque::<LightNode>::new();
-                    let mut red_queue = VecDeque::<LightNode>::new();
-                    let mut green_queue = VecDeque::<LightNode>::new();
-                    let mut blue_queue = VecDeque::<LightNode>::new();
-
-                    if is_sunlight && vy == max_height - 1 {
-                        // propagate sunlight down
-                        self.set_sunlight(vx, vy, vz, max_light_level);
-                        queue.push_back(LightNode {
-                            voxel: voxel.clone(),
-                            level: max_light_level,
-                        })
-                    } else {
-                        for [ox, oy, oz] in VOXEL_NEIGHBORS.iter() {
-                            let nvy = vy + oy;
+        // both chunks hold the same light on their shared edge
+        let padding = chunks.get_chunk(&Vec2(0, 0)).unwrap();
+        assert_eq!(padding.get_red_light(vx + 1, 4, 3), 14);
 
-                            if nvy < 0 || nvy >= max_height {
-                                return;
-                            }
+        chunks.update(vx, 4, 3, AIR, 0, 0);
 
-                            let nvx = vx + ox;
-                            let nvz = vz + oz;
-                            let n_voxel = Vec3(nvx, nvy, nvz);
-                            let &Block {
-                                is_light,
-                                is_transparent,
-                                ..
-                            } = self.get_block_by_voxel(nvx, nvy, nvz);
-
-                            // need propagation after solid block removed
-                            if is_sunlight {
-                                let level = self.get_sunlight(nvx, nvy, nvz);
-                                if level != 0 && is_transparent {
-                                    queue.push_back(LightNode {
-                                        voxel: n_voxel,
-                                        level,
-                                    })
-                                }
-                            } else {
-                                let red_level = self.get_torch_light(nvx, nvy, nvz, &RED);
-                                if red_level != 0 && (is_transparent || is_light) {
-                                    red_queue.push_back(LightNode {
-                                        voxel: n_voxel.clone(),
-                                        level: red_level,
-                                    })
-                                }
-
-                                let green_level = self.get_torch_light(nvx, nvy, nvz, &GREEN);
-                                if green_level != 0 && (is_transparent || is_light) {
-                                    green_queue.push_back(LightNode {
-                                        voxel: n_voxel.clone(),
-                                        level: green_level,
-                                    })
-                                }
-
-                                let blue_level = self.get_torch_light(nvx, nvy, nvz, &BLUE);
-                                if blue_level != 0 && (is_transparent || is_light) {
-                                    blue_queue.push_back(LightNode {
-                                        voxel: n_voxel,
-                                        level: blue_level,
-                                    })
-                                }
-                            }
-                        }
-                    }
+        assert_eq!(chunks.get_torch_light(vx, 4, 3, &LightColor::Red), 0);
+        assert_eq!(chunks.get_torch_light(vx + 1, 4, 3, &LightColor::Red), 0);
+        assert_eq!(
+            chunks
+                .get_chunk(&Vec2(0, 0))
+                .unwrap()
+                .get_red_light(vx + 1, 4, 3),
+            0
+        );
+    }
 
-                    if is_sunlight {
-                        Lights::global_flood_light(self, queue, is_sunlight, &NONE);
-                    } else {
-                        Lights::global_flood_light(self, red_queue, is_sunlight, &RED);
-                        Lights::global_flood_light(self, green_queue, is_sunlight, &GREEN);
-                        Lights::global_flood_light(self, blue_queue, is_sunlight, &BLUE);
-                    }
-                })
-            }
+    #[test]
+    fn any_world_size_works() {
+        for &(chunk_size, max_height, sub_chunks) in WORLD_SIZES.iter() {
+            let config = test_utils::sized_config(chunk_size, max_height, sub_chunks);
+            let mut chunks = test_utils::sized_chunks_with(config, 1, |_, vy, _| vy < 4);
+            let max_light_level = chunks.config.max_light_level;
+            let top = max_height as i32 - 1;
+            let edge = chunk_size as i32 - 1;
+
+            // a roof at the very top of the world, meshed in the highest sub-chunk
+            chunks.update(3, top, 3, STONE, 0, 0);
+            assert_eq!(chunks.get_max_height(3, 3), top as u32);
+            assert_eq!(chunks.get_sunlight(3, top - 1, 3), max_light_level - 1);
+
+            chunks.remesh_chunk(&Vec2(0, 0), &MeshLevel::All);
+            let chunk = chunks.get_chunk(&Vec2(0, 0)).unwrap();
+            assert_eq!(chunk.meshes.len(), sub_chunks as usize);
+            assert!(chunk.meshes[sub_chunks as usize - 1].opaque.is_some());
+
+            chunks.update(edge, 4, 3, TORCH, 0, 0);
+            assert_eq!(chunks.get_torch_light(edge + 1, 4, 3, &LightColor::Red), 14);
         }
     }
 
-    /// Mark a chunk for saving from a voxel coordinate
-    pub fn mark_saving_from_voxel(&mut self, vx: i32, vy: i32, vz: i32) {
-        self.get_chunk_by_voxel_mut(vx, vy, vz)
-            .unwrap()
-            .needs_saving = true;
+    #[test]
+    fn calc_dirty_levels_works() {
+        let levels = |chunk: &mut Chunk| {
+            let mut levels = std::mem::take(&mut chunk.dirty_levels)
+                .into_iter()
+                .collect::<Vec<_>>();
+            levels.sort_unstable();
+            levels
+        };
 
-        let neighbors = self.get_neighbor_chunk_coords(vx, vy, vz);
-        neighbors.iter().for_each(|n_coords| {
-            if let Some(chunk) = self.get_chunk_mut(n_coords) {
-                chunk.needs_saving = true;
-            }
-        })
-    }
+        for &(chunk_size, max_height, sub_chunks) in WORLD_SIZES.iter() {
+            let config = test_utils::sized_config(chunk_size, max_height, sub_chunks);
+            let mut chunk = Chunk::new(Vec2(0, 0), &config, None);
+            let unit = (max_height / sub_chunks) as i32;
 
-    /// Propagate light on a chunk. Things this function does:
-    ///
-    /// 1. Spread sunlight from the very top of the chunk
-    /// 2. Recognize the torch lights and flood-fill them as well
-    fn propagate_chunk(&mut self, coords: &Vec2<i32>) {
-        let max_light_flood = self.config.max_light_level as usize;
+            // voxels on the edge of a sub-chunk show up in the meshes of the next one too
+            chunk.calc_dirty_levels(unit, max_height, sub_chunks);
+            assert_eq!(levels(&mut chunk), vec![0, 1]);
 
-        let space = Space::new(self, coords, max_light_flood);
-        let lights = Lights::calc_light(&space, &self.registry, &self.config);
+            chunk.calc_dirty_levels(unit - 1, max_height, sub_chunks);
+            assert_eq!(levels(&mut chunk), vec![0, 1]);
 
-        let chunk = self.get_chunk_mut(coords).expect("Chunk not found");
+            chunk.calc_dirty_levels(unit + 1, max_height, sub_chunks);
+            assert_eq!(levels(&mut chunk), vec![1]);
 
-        chunk.needs_propagation = false;
-        chunk.needs_saving = true;
-        chunk.set_lights(lights);
+            chunk.calc_dirty_levels(max_height as i32 - 1, max_height, sub_chunks);
+            assert_eq!(levels(&mut chunk), vec![sub_chunks - 1]);
+        }
     }
 
-    /// Update the activities of chunks
-    fn update_activities(&mut self, coords: &Vec2<i32>) {
-        if let Some(index) = self.activities.iter().position(|c| *c == *coords) {
-            self.activities.remove(index);
-        }
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(16))]
 
-        self.activities.push_back(coords.to_owned());
-    }
+        #[test]
+        fn remove_light_undoes_flood_light(vx in 0..8, vy in 4..32, vz in 0..8) {
+            let mut chunks = test_utils::chunks(2, 4);
+            let before = lights_of(&chunks, Vec2(0, 0));
 
-    /// Unload chunks that are too old.
-    fn unload_chunks(&mut self) {
-        let diff = self.chunks.len() as i32 - self.config.max_loaded_chunks as i32;
-
-        if diff > 0 {
-            for _ in 0..diff {
-                if let Some(coords) = self.activities.pop_front() {
-                    if let Some(chunk) = self.chunks.remove(&coords) {
-                        if self.config.save {
-                            chunk.save();
-                        }
-                    }
-                }
+            chunks.update(vx, vy, vz, TORCH, 0, 0);
+            chunks.update(vx, vy, vz, AIR, 0, 0);
+
+            prop_assert_eq!(lights_of(&chunks, Vec2(0, 0)), before);
+        }
+
+        #[test]
+        fn update_light_matches_recompute(
+            edits in prop::collection::vec(
+                (0..8, 1..32, 0..8, prop::sample::select(vec![AIR, STONE, GLASS])),
+                1..16,
+            )
+        ) {
+            let mut chunks = test_utils::chunks(2, 4);
+
+            // only within the chunk at the origin, which the ones around it fully surround
+            for (vx, vy, vz, id) in edits {
+                chunks.update(vx, vy, vz, id, 0, 0);
             }
+
+            let updated = lights_of(&chunks, Vec2(0, 0));
+            chunks.recompute_all_light();
+
+            prop_assert_eq!(updated, lights_of(&chunks, Vec2(0, 0)));
         }
     }
 }
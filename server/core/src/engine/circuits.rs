@@ -0,0 +1,400 @@
+#![allow(dead_code)]
+
+use hashbrown::HashSet;
+
+use std::collections::{BTreeMap, VecDeque};
+
+use server_common::vec::Vec3;
+
+use super::super::constants::{MAX_POWER_LEVEL, VOXEL_NEIGHBORS};
+
+use super::chunks::Chunks;
+
+/// Node of a power propagation queue
+#[derive(Debug)]
+pub struct PowerNode {
+    pub voxel: Vec3<i32>,
+    pub level: u32,
+}
+
+/// What a change to a circuit touched
+#[derive(Debug, Default)]
+pub struct CircuitChanges {
+    /// Consumers that need to be re-evaluated, which should be scheduled instead of updated
+    /// in place
+    pub consumers: HashSet<Vec3<i32>>,
+    /// Conductors whose power level changed, to send to clients
+    pub powered: HashSet<Vec3<i32>>,
+}
+
+impl CircuitChanges {
+    pub fn extend(&mut self, other: CircuitChanges) {
+        self.consumers.extend(other.consumers);
+        self.powered.extend(other.powered);
+    }
+}
+
+/// Resource of circuit components waiting to be re-evaluated at a later tick
+#[derive(Debug, Default)]
+pub struct CircuitSchedule {
+    queue: BTreeMap<i32, HashSet<Vec3<i32>>>,
+}
+
+impl CircuitSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedule a component to be updated at a tick
+    pub fn schedule(&mut self, voxel: Vec3<i32>, tick: i32) {
+        self.queue
+            .entry(tick)
+            .or_insert_with(HashSet::new)
+            .insert(voxel);
+    }
+
+    /// Take every component that's due by `tick`
+    pub fn due(&mut self, tick: i32) -> HashSet<Vec3<i32>> {
+        let later = self.queue.split_off(&(tick + 1));
+        let due = std::mem::replace(&mut self.queue, later);
+
+        due.into_values().flatten().collect()
+    }
+
+    /// Whether any component is scheduled
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// Power simulation on top of the chunks, similar to how torch lights flood.
+///
+/// Power levels live in the voxel stage bits, so clients can render them right away:
+/// - Sources always emit their registry `power_level`.
+/// - Conductors hold the power level they currently carry, losing 1 per voxel.
+/// - Consumers hold 1 when powered, 0 otherwise.
+pub struct Circuits;
+
+impl Circuits {
+    /// Get the power level a voxel currently provides
+    pub fn get_power(chunks: &Chunks, vx: i32, vy: i32, vz: i32) -> u32 {
+        let block = chunks.get_block_by_voxel(vx, vy, vz);
+
+        if block.power_level > 0 {
+            return block.power_level.min(MAX_POWER_LEVEL);
+        }
+
+        if !block.conducts_power {
+            return 0;
+        }
+
+        chunks
+            .get_chunk_by_voxel(vx, vy, vz)
+            .map(|chunk| chunk.get_voxel_stage(vx, vy, vz))
+            .unwrap_or(0)
+    }
+
+    /// Set the power level carried by a conductor
+    fn set_power(
+        chunks: &mut Chunks,
+        changes: &mut CircuitChanges,
+        vx: i32,
+        vy: i32,
+        vz: i32,
+        level: u32,
+    ) {
+        chunks.set_voxel_stage_by_voxel(vx, vy, vz, level);
+        chunks.mark_saving_from_voxel(vx, vy, vz);
+        changes.powered.insert(Vec3(vx, vy, vz));
+    }
+
+    /// Whether any neighbor of a voxel provides power
+    pub fn is_powered(chunks: &Chunks, vx: i32, vy: i32, vz: i32) -> bool {
+        VOXEL_NEIGHBORS
+            .iter()
+            .any(|[ox, oy, oz]| Circuits::get_power(chunks, vx + ox, vy + oy, vz + oz) > 0)
+    }
+
+    /// Flood fill power from a queue through conductors
+    pub fn global_flood_power(
+        chunks: &mut Chunks,
+        mut queue: VecDeque<PowerNode>,
+    ) -> CircuitChanges {
+        let max_height = chunks.config.max_height as i32;
+        let mut changes = CircuitChanges::default();
+
+        while !queue.is_empty() {
+            let PowerNode { voxel, level } = queue.pop_front().unwrap();
+            let Vec3(vx, vy, vz) = voxel;
+
+            for [ox, oy, oz] in VOXEL_NEIGHBORS.iter() {
+                let nvy = vy + oy;
+
+                if nvy < 0 || nvy >= max_height {
+                    continue;
+                }
+
+                let nvx = vx + ox;
+                let nvz = vz + oz;
+                let block = chunks.get_block_by_voxel(nvx, nvy, nvz);
+
+                if block.consumes_power {
+                    changes.consumers.insert(Vec3(nvx, nvy, nvz));
+                }
+
+                if !block.conducts_power || level <= 1 {
+                    continue;
+                }
+
+                let nl = level - 1;
+
+                if Circuits::get_power(chunks, nvx, nvy, nvz) >= nl {
+                    continue;
+                }
+
+                Circuits::set_power(chunks, &mut changes, nvx, nvy, nvz, nl);
+
+                queue.push_back(PowerNode {
+                    voxel: Vec3(nvx, nvy, nvz),
+                    level: nl,
+                });
+            }
+        }
+
+        changes
+    }
+
+    /// Remove power that used to flow out of a voxel. Steps:
+    ///
+    /// 1. Remove the existing power in a flood-fill fashion
+    /// 2. If other sources still reach the area, flood fill them back
+    pub fn global_remove_power(
+        chunks: &mut Chunks,
+        vx: i32,
+        vy: i32,
+        vz: i32,
+        level: u32,
+    ) -> CircuitChanges {
+        let max_height = chunks.config.max_height as i32;
+
+        let mut changes = CircuitChanges::default();
+        let mut fill = VecDeque::<PowerNode>::new();
+        let mut queue = VecDeque::<PowerNode>::new();
+
+        queue.push_back(PowerNode {
+            voxel: Vec3(vx, vy, vz),
+            level,
+        });
+
+        while !queue.is_empty() {
+            let PowerNode { voxel, level } = queue.pop_front().unwrap();
+            let Vec3(vx, vy, vz) = voxel;
+
+            for [ox, oy, oz] in VOXEL_NEIGHBORS.iter() {
+                let nvy = vy + oy;
+
+                if nvy < 0 || nvy >= max_height {
+                    continue;
+                }
+
+                let nvx = vx + ox;
+                let nvz = vz + oz;
+                let n_voxel = Vec3(nvx, nvy, nvz);
+                let block = chunks.get_block_by_voxel(nvx, nvy, nvz);
+
+                if block.consumes_power {
+                    changes.consumers.insert(n_voxel.clone());
+                }
+
+                if block.power_level > 0 {
+                    fill.push_back(PowerNode {
+                        voxel: n_voxel,
+                        level: Circuits::get_power(chunks, nvx, nvy, nvz),
+                    });
+                    continue;
+                }
+
+                if !block.conducts_power {
+                    continue;
+                }
+
+                let nl = Circuits::get_power(chunks, nvx, nvy, nvz);
+
+                if nl == 0 {
+                    continue;
+                }
+
+                if nl < level {
+                    Circuits::set_power(chunks, &mut changes, nvx, nvy, nvz, 0);
+                    queue.push_back(PowerNode {
+                        voxel: n_voxel,
+                        level: nl,
+                    });
+                } else {
+                    fill.push_back(PowerNode {
+                        voxel: n_voxel,
+                        level: nl,
+                    });
+                }
+            }
+        }
+
+        changes.extend(Circuits::global_flood_power(chunks, fill));
+        changes
+    }
+
+    /// Update the circuit around a voxel that has just been changed.
+    ///
+    /// `old_level` is the power the voxel provided before the change, since the voxel's
+    /// stage has already been cleared by then.
+    pub fn on_update(
+        chunks: &mut Chunks,
+        vx: i32,
+        vy: i32,
+        vz: i32,
+        old_level: u32,
+    ) -> CircuitChanges {
+        let mut changes = CircuitChanges::default();
+
+        if old_level > 0 {
+            changes.extend(Circuits::global_remove_power(chunks, vx, vy, vz, old_level));
+        }
+
+        let registry = chunks.registry.clone();
+        let block = registry.get_block_by_id(chunks.get_voxel_by_voxel(vx, vy, vz));
+
+        if block.power_level > 0 {
+            let level = Circuits::get_power(chunks, vx, vy, vz);
+
+            changes.extend(Circuits::global_flood_power(
+                chunks,
+                VecDeque::from(vec![PowerNode {
+                    voxel: Vec3(vx, vy, vz),
+                    level,
+                }]),
+            ));
+        } else if block.conducts_power {
+            let level = VOXEL_NEIGHBORS
+                .iter()
+                .map(|[ox, oy, oz]| Circuits::get_power(chunks, vx + ox, vy + oy, vz + oz))
+                .max()
+                .unwrap_or(0)
+                .saturating_sub(1);
+
+            if level > 0 {
+                Circuits::set_power(chunks, &mut changes, vx, vy, vz, level);
+                changes.extend(Circuits::global_flood_power(
+                    chunks,
+                    VecDeque::from(vec![PowerNode {
+                        voxel: Vec3(vx, vy, vz),
+                        level,
+                    }]),
+                ));
+            }
+        }
+
+        if block.consumes_power {
+            changes.consumers.insert(Vec3(vx, vy, vz));
+        }
+
+        for [ox, oy, oz] in VOXEL_NEIGHBORS.iter() {
+            let (nvx, nvy, nvz) = (vx + ox, vy + oy, vz + oz);
+
+            if chunks.get_block_by_voxel(nvx, nvy, nvz).consumes_power {
+                changes.consumers.insert(Vec3(nvx, nvy, nvz));
+            }
+        }
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{self, AIR, POWER_LAMP, POWER_SOURCE, POWER_WIRE};
+
+    /// Power carried by the wires from x = 1 to 5
+    fn wire_levels(chunks: &Chunks) -> Vec<u32> {
+        (1..=5)
+            .map(|vx| Circuits::get_power(chunks, vx, 4, 0))
+            .collect()
+    }
+
+    #[test]
+    fn power_floods_through_wires() {
+        let mut chunks = test_utils::chunks(1, 4);
+
+        for vx in 1..=5 {
+            chunks.set_voxel_by_voxel(vx, 4, 0, POWER_WIRE);
+            Circuits::on_update(&mut chunks, vx, 4, 0, 0);
+        }
+        chunks.set_voxel_by_voxel(6, 4, 0, POWER_LAMP);
+        assert_eq!(wire_levels(&chunks), vec![0; 5]);
+
+        chunks.set_voxel_by_voxel(0, 4, 0, POWER_SOURCE);
+        let changes = Circuits::on_update(&mut chunks, 0, 4, 0, 0);
+
+        // losing a level for every wire it goes through
+        assert_eq!(wire_levels(&chunks), vec![14, 13, 12, 11, 10]);
+        assert_eq!(changes.powered.len(), 5);
+        assert!(changes.consumers.contains(&Vec3(6, 4, 0)));
+        assert!(Circuits::is_powered(&chunks, 6, 4, 0));
+    }
+
+    #[test]
+    fn remove_power_undoes_flood() {
+        let mut chunks = test_utils::chunks(1, 4);
+
+        for vx in 1..=5 {
+            chunks.set_voxel_by_voxel(vx, 4, 0, POWER_WIRE);
+        }
+        chunks.set_voxel_by_voxel(0, 4, 0, POWER_SOURCE);
+
+        Circuits::global_flood_power(
+            &mut chunks,
+            VecDeque::from(vec![PowerNode {
+                voxel: Vec3(0, 4, 0),
+                level: 15,
+            }]),
+        );
+        assert_eq!(wire_levels(&chunks), vec![14, 13, 12, 11, 10]);
+
+        chunks.set_voxel_by_voxel(0, 4, 0, AIR);
+        Circuits::global_remove_power(&mut chunks, 0, 4, 0, 15);
+        assert_eq!(wire_levels(&chunks), vec![0; 5]);
+
+        // with another source at the other end, its power flows back in
+        chunks.set_voxel_by_voxel(0, 4, 0, POWER_SOURCE);
+        chunks.set_voxel_by_voxel(6, 4, 0, POWER_SOURCE);
+        Circuits::on_update(&mut chunks, 0, 4, 0, 0);
+        Circuits::on_update(&mut chunks, 6, 4, 0, 0);
+        assert_eq!(wire_levels(&chunks), vec![14, 13, 12, 13, 14]);
+
+        chunks.set_voxel_by_voxel(0, 4, 0, AIR);
+        Circuits::on_update(&mut chunks, 0, 4, 0, 15);
+        assert_eq!(wire_levels(&chunks), vec![10, 11, 12, 13, 14]);
+    }
+
+    #[test]
+    fn circuit_schedule_works() {
+        let mut schedule = CircuitSchedule::new();
+        schedule.schedule(Vec3(0, 0, 0), 5);
+        schedule.schedule(Vec3(1, 0, 0), 3);
+        schedule.schedule(Vec3(2, 0, 0), 3);
+
+        assert!(schedule.due(2).is_empty());
+
+        // everything due by a tick comes out at once, leaving what's due later
+        let due = schedule.due(4);
+        assert_eq!(due.len(), 2);
+        assert!(due.contains(&Vec3(1, 0, 0)) && due.contains(&Vec3(2, 0, 0)));
+        assert!(!schedule.is_empty());
+
+        assert_eq!(
+            schedule.due(10).into_iter().collect::<Vec<_>>(),
+            vec![Vec3(0, 0, 0)]
+        );
+        assert!(schedule.is_empty());
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use server_common::vec::Vec2;
+
+/// What a claim keeps from everyone but its owner and members
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ClaimFlags {
+    /// Only the owner and members may place or break blocks
+    pub no_build: bool,
+    /// Explosions leave the claim's blocks alone
+    pub no_explosion: bool,
+}
+
+impl Default for ClaimFlags {
+    fn default() -> Self {
+        Self {
+            no_build: true,
+            no_explosion: true,
+        }
+    }
+}
+
+impl ClaimFlags {
+    /// Set a flag by the name players know it by, false if there's no such flag
+    pub fn set(&mut self, name: &str, value: bool) -> bool {
+        match name {
+            "no-build" => self.no_build = value,
+            "no-explosion" => self.no_explosion = value,
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+/// Columns of voxels a logged in player protects, from the bottom of the world to the top
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Claim {
+    pub owner: Uuid,
+    /// Name the owner went by when claiming, to tell others whose claim it is
+    pub owner_name: String,
+    pub min: Vec2<i32>,
+    pub max: Vec2<i32>,
+    #[serde(default)]
+    pub members: Vec<Uuid>,
+    #[serde(default)]
+    pub flags: ClaimFlags,
+}
+
+impl Claim {
+    pub fn contains(&self, vx: i32, vz: i32) -> bool {
+        vx >= self.min.0 && vx <= self.max.0 && vz >= self.min.1 && vz <= self.max.1
+    }
+
+    pub fn overlaps(&self, min: &Vec2<i32>, max: &Vec2<i32>) -> bool {
+        min.0 <= self.max.0 && max.0 >= self.min.0 && min.1 <= self.max.1 && max.1 >= self.min.1
+    }
+
+    /// Whether a player, by their identity if they're logged in, belongs to the claim
+    pub fn is_trusted(&self, uuid: Option<&Uuid>) -> bool {
+        uuid.map_or(false, |uuid| {
+            self.owner == *uuid || self.members.contains(uuid)
+        })
+    }
+}
+
+/// Every claim in a world, saved along with its metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Claims {
+    list: Vec<Claim>,
+}
+
+impl Claims {
+    pub fn find(&self, vx: i32, vz: i32) -> Option<&Claim> {
+        self.list.iter().find(|claim| claim.contains(vx, vz))
+    }
+
+    pub fn find_mut(&mut self, vx: i32, vz: i32) -> Option<&mut Claim> {
+        self.list.iter_mut().find(|claim| claim.contains(vx, vz))
+    }
+
+    pub fn add(&mut self, claim: Claim) -> Result<(), ClaimError> {
+        if let Some(other) = self
+            .list
+            .iter()
+            .find(|other| other.overlaps(&claim.min, &claim.max))
+        {
+            return Err(ClaimError::Overlaps(other.owner_name.to_owned()));
+        }
+
+        self.list.push(claim);
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, vx: i32, vz: i32) -> Option<Claim> {
+        let index = self.list.iter().position(|claim| claim.contains(vx, vz))?;
+        Some(self.list.remove(index))
+    }
+
+    /// Whether a player may place or break the blocks of a column
+    pub fn can_build(&self, vx: i32, vz: i32, uuid: Option<&Uuid>) -> bool {
+        self.find(vx, vz).map_or(true, |claim| {
+            !claim.flags.no_build || claim.is_trusted(uuid)
+        })
+    }
+
+    /// Whether an explosion may break the blocks of a column
+    pub fn allows_explosion(&self, vx: i32, vz: i32) -> bool {
+        self.find(vx, vz)
+            .map_or(true, |claim| !claim.flags.no_explosion)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ClaimError {
+    NotLoggedIn,
+    TooLarge(i32),
+    Overlaps(String),
+    NotClaimed,
+    NotOwner,
+    UnknownPlayer,
+    UnknownFlag,
+    NotAdmin,
+    Usage(&'static str),
+}
+
+impl fmt::Display for ClaimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClaimError::NotLoggedIn => write!(f, "Only logged in players can own claims."),
+            ClaimError::TooLarge(radius) => {
+                write!(f, "Claims can reach at most {} blocks out.", radius)
+            }
+            ClaimError::Overlaps(owner) => write!(f, "That overlaps with a claim of {}.", owner),
+            ClaimError::NotClaimed => write!(f, "Nobody claimed this spot."),
+            ClaimError::NotOwner => write!(f, "Only the owner of this claim can do that."),
+            ClaimError::UnknownPlayer => write!(f, "Nobody logged in goes by that name."),
+            ClaimError::UnknownFlag => write!(f, "Flags are no-build and no-explosion."),
+            ClaimError::NotAdmin => write!(f, "Only admins can override claims."),
+            ClaimError::Usage(usage) => write!(f, "Usage: {}", usage),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claims_work() {
+        let owner = Uuid::from_u128(1);
+        let member = Uuid::from_u128(2);
+        let stranger = Uuid::from_u128(3);
+
+        let mut claims = Claims::default();
+        claims
+            .add(Claim {
+                owner,
+                owner_name: "ian".to_owned(),
+                min: Vec2(-2, -2),
+                max: Vec2(2, 2),
+                members: vec![member],
+                flags: ClaimFlags::default(),
+            })
+            .unwrap();
+
+        assert!(claims.can_build(0, 0, Some(&owner)));
+        assert!(claims.can_build(2, -2, Some(&member)));
+        assert!(!claims.can_build(0, 0, Some(&stranger)));
+        assert!(!claims.can_build(0, 0, None));
+        assert!(claims.can_build(3, 0, None));
+        assert!(!claims.allows_explosion(1, 1));
+
+        let overlapping = Claim {
+            owner: stranger,
+            owner_name: "bob".to_owned(),
+            min: Vec2(2, 2),
+            max: Vec2(4, 4),
+            members: vec![],
+            flags: ClaimFlags::default(),
+        };
+        assert_eq!(
+            claims.add(overlapping),
+            Err(ClaimError::Overlaps("ian".to_owned()))
+        );
+
+        claims.find_mut(0, 0).unwrap().flags.set("no-build", false);
+        assert!(claims.can_build(0, 0, None));
+    }
+}
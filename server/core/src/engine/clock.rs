@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-use std::time::SystemTime;
+use crate::constants::{MIN_SUNLIGHT_INTENSITY, SUNRISE_TIME, SUNSET_TIME, SUN_TRANSITION_TIME};
 
 pub struct Clock {
     pub time: f32,
@@ -8,8 +8,6 @@ pub struct Clock {
 
     pub tick: i32,
     pub delta: f32,
-
-    prev_time: SystemTime,
 }
 
 impl Clock {
@@ -19,7 +17,6 @@ impl Clock {
             tick_speed,
             tick: 0,
             delta: 0.0,
-            prev_time: SystemTime::now(),
         }
     }
 
@@ -43,22 +40,46 @@ impl Clock {
         self.tick_speed = tick_speed;
     }
 
+    /// Share of sunlight reaching the world right now
+    pub fn sunlight_intensity(&self) -> f32 {
+        sunlight_intensity(self.time)
+    }
+
     /// Process a tick of clock
     ///
-    /// Adds `tick_speed` to `time`, and calculate the delta time passed.
-    pub fn tick(&mut self) {
-        let now = SystemTime::now();
+    /// Adds `tick_speed` to `time`, over a fixed `delta` of seconds set by the ticker.
+    pub fn tick(&mut self, delta: f32) {
+        self.delta = delta;
+        self.time = (self.time + self.tick_speed * self.delta) % 2400.0;
+        self.tick += 1;
+    }
+}
 
-        self.delta = (now
-            .duration_since(self.prev_time)
-            .expect("Clock may have gone backwards")
-            .as_millis() as f32
-            / 1000.0)
-            .min(0.020);
+/// Share of sunlight reaching the world at a time of day, fading in and out around sunrise
+/// and sunset the way clients shade their skies
+pub fn sunlight_intensity(time: f32) -> f32 {
+    let time = time.rem_euclid(2400.0);
+    let progress = |since: f32| (since / SUN_TRANSITION_TIME + 0.5).clamp(0.0, 1.0);
 
-        self.prev_time = now;
+    let intensity = if time < (SUNRISE_TIME + SUNSET_TIME) / 2.0 {
+        progress(time - SUNRISE_TIME)
+    } else {
+        1.0 - progress(time - SUNSET_TIME)
+    };
 
-        self.time = (self.time + self.tick_speed * self.delta) % 2400.0;
-        self.tick += 1;
+    intensity.max(MIN_SUNLIGHT_INTENSITY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sunlight_intensity_works() {
+        assert_eq!(sunlight_intensity(0.0), MIN_SUNLIGHT_INTENSITY);
+        assert_eq!(sunlight_intensity(SUNRISE_TIME), 0.5);
+        assert_eq!(sunlight_intensity(1200.0), 1.0);
+        assert_eq!(sunlight_intensity(SUNSET_TIME + 50.0), 0.25);
+        assert_eq!(sunlight_intensity(2400.0 + 1200.0), 1.0);
     }
 }
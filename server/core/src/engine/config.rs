@@ -1,10 +1,14 @@
 use hashbrown::HashMap;
 
-use std::fs::{self, File};
+use std::{
+    fs::{self, File},
+    sync::Arc,
+};
 
 use server_utils::json;
 
 use super::{
+    atlas::AtlasConfig,
     registry::Registry,
     world::{WorldConfig, WorldMeta},
 };
@@ -12,7 +16,8 @@ use super::{
 pub struct Configs;
 
 impl Configs {
-    pub fn load_worlds(path: &str) -> (HashMap<String, (WorldMeta, WorldConfig)>, Registry) {
+    /// Load the configs of every world, along with the registry of blocks they all share
+    pub fn load_worlds(path: &str) -> (HashMap<String, (WorldMeta, WorldConfig)>, Arc<Registry>) {
         let worlds_json: serde_json::Value =
             serde_json::from_reader(File::open(path).unwrap()).unwrap();
 
@@ -33,6 +38,14 @@ impl Configs {
             let mut meta: WorldMeta = serde_json::from_value(world_json.clone()).unwrap();
             let config: WorldConfig = serde_json::from_value(world_json).unwrap();
 
+            // sub-chunks are meshed and lit a slice of the height at a time
+            if config.sub_chunks == 0 || config.max_height % config.sub_chunks != 0 {
+                panic!(
+                    "World \"{}\" is {} voxels tall, which doesn't split into {} sub-chunks.",
+                    meta.name, config.max_height, config.sub_chunks
+                );
+            }
+
             meta.packs = packs.clone();
 
             map.insert(meta.name.to_owned(), (meta, config));
@@ -42,8 +55,8 @@ impl Configs {
             panic!("No configs found!");
         }
 
-        let registry = Registry::new(packs, true);
+        let registry = Registry::new(packs, &AtlasConfig::load("assets/metadata/atlas.json"));
 
-        (map, registry)
+        (map, Arc::new(registry))
     }
 }
@@ -0,0 +1,184 @@
+#![allow(dead_code)]
+
+use hashbrown::{HashMap, HashSet};
+use log::warn;
+
+use serde::{Deserialize, Serialize};
+
+use server_common::vec::Vec3;
+
+use crate::comp::inventory::Inventory;
+
+use super::storage::Storage;
+
+/// Which inventory a slot belongs to during a container interaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct SlotRef {
+    pub container: bool,
+    pub slot: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ContainerError {
+    NotFound,
+    NotViewing,
+    Outdated,
+    InvalidMove,
+}
+
+/// A block-bound inventory that any number of players can have open at once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Container {
+    pub inventory: Inventory,
+
+    /// Bumped on every successful change, clients send back the revision they
+    /// acted on so stale moves can be rejected.
+    pub revision: u32,
+
+    #[serde(skip)]
+    pub viewers: HashSet<usize>,
+}
+
+impl Container {
+    pub fn new(size: usize) -> Self {
+        Self {
+            inventory: Inventory::new(size),
+            revision: 0,
+            viewers: HashSet::new(),
+        }
+    }
+}
+
+/// Resource holding every container block's inventory in a world
+#[derive(Debug, Default)]
+pub struct Containers {
+    containers: HashMap<Vec3<i32>, Container>,
+}
+
+impl Containers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the containers saved in a world's storage
+    pub fn load(storage: &dyn Storage) -> Self {
+        let mut containers = Self::new();
+
+        for (voxel, container) in storage.load_containers() {
+            match serde_json::from_value::<Container>(container) {
+                Ok(container) => {
+                    containers.containers.insert(voxel, container);
+                }
+                Err(e) => warn!("Unable to read container at {:?}: {}", voxel, e),
+            }
+        }
+
+        containers
+    }
+
+    /// The containers with anything in them, ready to be saved to a world's storage
+    pub fn to_saved(&self) -> Vec<(Vec3<i32>, serde_json::Value)> {
+        self.containers
+            .iter()
+            .filter(|(_, container)| !container.inventory.is_empty())
+            .map(|(voxel, container)| (voxel.to_owned(), serde_json::to_value(container).unwrap()))
+            .collect()
+    }
+
+    /// Get a container at a voxel coordinate
+    pub fn get(&self, voxel: &Vec3<i32>) -> Option<&Container> {
+        self.containers.get(voxel)
+    }
+
+    /// Open a container for a player, creating it with `size` slots if it doesn't exist yet.
+    ///
+    /// Returns true if the player is the first viewer, which is when the open animation plays.
+    pub fn open(&mut self, voxel: &Vec3<i32>, size: usize, player_id: usize) -> bool {
+        let container = self
+            .containers
+            .entry(voxel.to_owned())
+            .or_insert_with(|| Container::new(size));
+
+        let was_closed = container.viewers.is_empty();
+        container.viewers.insert(player_id) && was_closed
+    }
+
+    /// Close a container for a player
+    ///
+    /// Returns true if the player was the last viewer, which is when the close animation plays.
+    pub fn close(&mut self, voxel: &Vec3<i32>, player_id: usize) -> bool {
+        if let Some(container) = self.containers.get_mut(voxel) {
+            return container.viewers.remove(&player_id) && container.viewers.is_empty();
+        }
+
+        false
+    }
+
+    /// Close every container a player has open, returns the ones that ended up fully closed.
+    pub fn close_all(&mut self, player_id: usize) -> Vec<Vec3<i32>> {
+        let mut closed = vec![];
+
+        for (voxel, container) in self.containers.iter_mut() {
+            if container.viewers.remove(&player_id) && container.viewers.is_empty() {
+                closed.push(voxel.to_owned());
+            }
+        }
+
+        closed
+    }
+
+    /// Remove a container, for instance when its block is broken
+    pub fn remove(&mut self, voxel: &Vec3<i32>) -> Option<Container> {
+        self.containers.remove(voxel)
+    }
+
+    /// Move items between slots of a container and the player's own inventory.
+    ///
+    /// Moves are only applied if the player acted on the latest revision of the container,
+    /// so two players grabbing the same slot at once can't both succeed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn move_slot(
+        &mut self,
+        voxel: &Vec3<i32>,
+        player_id: usize,
+        player_inventory: &mut Inventory,
+        from: SlotRef,
+        to: SlotRef,
+        count: Option<u32>,
+        revision: u32,
+    ) -> Result<(), ContainerError> {
+        let container = self
+            .containers
+            .get_mut(voxel)
+            .ok_or(ContainerError::NotFound)?;
+
+        if !container.viewers.contains(&player_id) {
+            return Err(ContainerError::NotViewing);
+        }
+
+        if container.revision != revision {
+            return Err(ContainerError::Outdated);
+        }
+
+        let inventory = &mut container.inventory;
+
+        let moved = match (from.container, to.container) {
+            (true, true) => inventory.move_within(from.slot, to.slot, count),
+            (false, false) => player_inventory.move_within(from.slot, to.slot, count),
+            (true, false) => {
+                Inventory::transfer(inventory, from.slot, player_inventory, to.slot, count)
+            }
+            (false, true) => {
+                Inventory::transfer(player_inventory, from.slot, inventory, to.slot, count)
+            }
+        };
+
+        if !moved {
+            return Err(ContainerError::InvalidMove);
+        }
+
+        container.revision = container.revision.wrapping_add(1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,19 @@
+use specs::Entity;
+
+use server_common::vec::Vec3;
+
+/// A hit dealt to an entity, applied by the health system
+#[derive(Debug, Clone)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    /// Who dealt the damage, if anyone
+    pub source: Option<Entity>,
+    /// Where the damage came from, mobs flee away from it
+    pub from: Vec3<f32>,
+    /// Speed the target is knocked away from `from` with, if the hit lands
+    pub knockback: f32,
+}
+
+/// Resource of damage to be dispatched this tick
+pub type DamageQueue = Vec<DamageEvent>;
@@ -0,0 +1,34 @@
+use serde::Deserialize;
+
+/// How punishing a world is, configured per world
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Peaceful,
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::Normal
+    }
+}
+
+impl Difficulty {
+    /// Multiplier applied to damage dealt by hostile mobs
+    pub fn damage_multiplier(&self) -> f32 {
+        match self {
+            Difficulty::Peaceful => 0.0,
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.5,
+        }
+    }
+
+    /// Whether hostile mobs are kept out of the world
+    pub fn is_peaceful(&self) -> bool {
+        *self == Difficulty::Peaceful
+    }
+}
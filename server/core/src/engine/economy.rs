@@ -0,0 +1,131 @@
+use std::fmt;
+
+use hashbrown::HashMap;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EconomyError {
+    InvalidAmount,
+    InsufficientFunds(u64),
+    SamePlayer,
+    UnknownPlayer,
+    NotAdmin,
+    Usage(&'static str),
+}
+
+impl fmt::Display for EconomyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EconomyError::InvalidAmount => write!(f, "Amounts have to be whole and above 0."),
+            EconomyError::InsufficientFunds(balance) => {
+                write!(f, "Not enough coins, the balance is {}.", balance)
+            }
+            EconomyError::SamePlayer => write!(f, "Coins can't be sent to oneself."),
+            EconomyError::UnknownPlayer => write!(f, "Nobody online goes by that name."),
+            EconomyError::NotAdmin => write!(f, "Only admins can do that."),
+            EconomyError::Usage(usage) => write!(f, "Usage: {}", usage),
+        }
+    }
+}
+
+/// Coins held by the players in a world, by player id
+///
+/// Every change either goes through whole or not at all, so no coins are made or lost
+/// moving them around.
+#[derive(Debug, Default)]
+pub struct Economy {
+    balances: HashMap<usize, u64>,
+}
+
+impl Economy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn balance(&self, player_id: usize) -> u64 {
+        self.balances.get(&player_id).copied().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, player_id: usize, amount: u64) {
+        self.balances.insert(player_id, amount);
+    }
+
+    /// Forget a player's balance, returning it to be saved
+    pub fn remove(&mut self, player_id: usize) -> u64 {
+        self.balances.remove(&player_id).unwrap_or_default()
+    }
+
+    /// Add coins to a balance, returning the new balance
+    pub fn deposit(&mut self, player_id: usize, amount: u64) -> Result<u64, EconomyError> {
+        if amount == 0 {
+            return Err(EconomyError::InvalidAmount);
+        }
+
+        let balance = self
+            .balance(player_id)
+            .checked_add(amount)
+            .ok_or(EconomyError::InvalidAmount)?;
+
+        self.set(player_id, balance);
+        Ok(balance)
+    }
+
+    /// Take coins out of a balance, returning the new balance
+    pub fn withdraw(&mut self, player_id: usize, amount: u64) -> Result<u64, EconomyError> {
+        if amount == 0 {
+            return Err(EconomyError::InvalidAmount);
+        }
+
+        let balance = self.balance(player_id);
+
+        if balance < amount {
+            return Err(EconomyError::InsufficientFunds(balance));
+        }
+
+        self.set(player_id, balance - amount);
+        Ok(balance - amount)
+    }
+
+    /// Move coins from one balance to another
+    pub fn transfer(&mut self, from: usize, to: usize, amount: u64) -> Result<(), EconomyError> {
+        if from == to {
+            return Err(EconomyError::SamePlayer);
+        }
+
+        // checked ahead, so that the withdrawal never needs undoing
+        self.balance(to)
+            .checked_add(amount)
+            .ok_or(EconomyError::InvalidAmount)?;
+
+        self.withdraw(from, amount)?;
+        self.deposit(to, amount)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_works() {
+        let mut economy = Economy::new();
+        economy.set(1, 10);
+
+        assert_eq!(economy.transfer(1, 2, 4), Ok(()));
+        assert_eq!(economy.balance(1), 6);
+        assert_eq!(economy.balance(2), 4);
+
+        // nothing moves when anything's wrong
+        assert_eq!(
+            economy.transfer(1, 2, 7),
+            Err(EconomyError::InsufficientFunds(6))
+        );
+        assert_eq!(economy.transfer(1, 1, 1), Err(EconomyError::SamePlayer));
+        assert_eq!(economy.transfer(1, 2, 0), Err(EconomyError::InvalidAmount));
+
+        economy.set(2, u64::MAX);
+        assert_eq!(economy.transfer(1, 2, 1), Err(EconomyError::InvalidAmount));
+        assert_eq!(economy.balance(1), 6);
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+};
+
+use server_common::vec::Vec3;
+
+use crate::{
+    constants::{MAX_EDIT_VOLUME, MAX_UNDO_VOXELS},
+    gen::blocks::BlockRotation,
+};
+
+use super::chunks::Chunks;
+
+/// What a voxel holds, enough to put it back the way it was
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxelState {
+    pub id: u32,
+    pub rotation: u32,
+    pub y_rotation: u32,
+    pub stage: u32,
+}
+
+impl VoxelState {
+    /// A block in its default rotation and stage
+    pub fn block(id: u32) -> Self {
+        Self {
+            id,
+            rotation: 0,
+            y_rotation: 0,
+            stage: 0,
+        }
+    }
+
+    pub fn read(chunks: &Chunks, vx: i32, vy: i32, vz: i32) -> Self {
+        let (rotation, y_rotation) =
+            BlockRotation::decode(&chunks.get_voxel_rotation_by_voxel(vx, vy, vz));
+
+        Self {
+            id: chunks.get_voxel_by_voxel(vx, vy, vz),
+            rotation,
+            y_rotation,
+            stage: chunks.get_voxel_stage_by_voxel(vx, vy, vz),
+        }
+    }
+}
+
+/// Voxels to overwrite, or that were overwritten, by their coordinates
+pub type VoxelDiff = Vec<(Vec3<i32>, VoxelState)>;
+
+/// A box of voxels between two corners, both included
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub min: Vec3<i32>,
+    pub max: Vec3<i32>,
+}
+
+impl Region {
+    pub fn new(a: &Vec3<i32>, b: &Vec3<i32>) -> Self {
+        Self {
+            min: Vec3(a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)),
+            max: Vec3(a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)),
+        }
+    }
+
+    pub fn volume(&self) -> usize {
+        let Vec3(sx, sy, sz) = self.max.sub(&self.min);
+        (sx as usize + 1) * (sy as usize + 1) * (sz as usize + 1)
+    }
+
+    pub fn voxels(&self) -> impl Iterator<Item = Vec3<i32>> + '_ {
+        (self.min.0..=self.max.0).flat_map(move |vx| {
+            (self.min.1..=self.max.1)
+                .flat_map(move |vy| (self.min.2..=self.max.2).map(move |vz| Vec3(vx, vy, vz)))
+        })
+    }
+}
+
+/// A player's edits that can still be undone, each as the voxels it overwrote
+///
+/// The oldest edits are forgotten once the history holds too many voxels.
+#[derive(Debug, Default)]
+pub struct EditHistory {
+    diffs: VecDeque<VoxelDiff>,
+    voxels: usize,
+}
+
+impl EditHistory {
+    pub fn push(&mut self, diff: VoxelDiff) {
+        if diff.is_empty() {
+            return;
+        }
+
+        self.voxels += diff.len();
+        self.diffs.push_back(diff);
+
+        while self.voxels > MAX_UNDO_VOXELS {
+            match self.diffs.pop_front() {
+                Some(oldest) => self.voxels -= oldest.len(),
+                None => break,
+            }
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<VoxelDiff> {
+        let diff = self.diffs.pop_back()?;
+        self.voxels -= diff.len();
+        Some(diff)
+    }
+
+    pub fn len(&self) -> usize {
+        self.diffs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diffs.is_empty()
+    }
+}
+
+/// Where a player's selection is, what they copied, and what they can undo
+#[derive(Debug, Default)]
+pub struct EditSession {
+    pub pos1: Option<Vec3<i32>>,
+    pub pos2: Option<Vec3<i32>>,
+    /// Copied voxels, by their offset from where the player stood
+    pub clipboard: Option<VoxelDiff>,
+    pub history: EditHistory,
+}
+
+impl EditSession {
+    /// The region between both positions, as long as it isn't too large to edit at once
+    pub fn selection(&self) -> Result<Region, EditError> {
+        let region = match (&self.pos1, &self.pos2) {
+            (Some(pos1), Some(pos2)) => Region::new(pos1, pos2),
+            _ => return Err(EditError::NoSelection),
+        };
+
+        if region.volume() > MAX_EDIT_VOLUME {
+            return Err(EditError::TooLarge(region.volume()));
+        }
+
+        Ok(region)
+    }
+}
+
+/// Resource of every player's edit session, by player id
+pub type EditSessions = HashMap<usize, EditSession>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EditError {
+    NotAdmin,
+    NoSelection,
+    TooLarge(usize),
+    UnknownBlock(String),
+    EmptyClipboard,
+    NothingToUndo,
+    Usage(&'static str),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::NotAdmin => write!(f, "Only admins can edit the world in bulk."),
+            EditError::NoSelection => write!(f, "Select a region with //pos1 and //pos2 first."),
+            EditError::TooLarge(volume) => write!(
+                f,
+                "Selection of {} voxels is too large, at most {} can be edited at once.",
+                volume, MAX_EDIT_VOLUME
+            ),
+            EditError::UnknownBlock(name) => write!(f, "Unknown block: {}.", name),
+            EditError::EmptyClipboard => write!(f, "Nothing copied yet, use //copy first."),
+            EditError::NothingToUndo => write!(f, "Nothing left to undo."),
+            EditError::Usage(usage) => write!(f, "Usage: {}", usage),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_works() {
+        let region = Region::new(&Vec3(2, 0, -1), &Vec3(0, 1, 0));
+
+        assert_eq!(region.min, Vec3(0, 0, -1));
+        assert_eq!(region.max, Vec3(2, 1, 0));
+        assert_eq!(region.volume(), 12);
+        assert_eq!(region.voxels().count(), 12);
+    }
+
+    #[test]
+    fn edit_history_works() {
+        let diff = |len: usize| vec![(Vec3(0, 0, 0), VoxelState::block(1)); len];
+
+        let mut history = EditHistory::default();
+        history.push(diff(0));
+        assert!(history.is_empty());
+
+        history.push(diff(MAX_UNDO_VOXELS / 2));
+        history.push(diff(MAX_UNDO_VOXELS / 2));
+        history.push(diff(1));
+
+        // the oldest edit is forgotten to stay within bounds
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.pop().unwrap().len(), 1);
+        assert_eq!(history.pop().unwrap().len(), MAX_UNDO_VOXELS / 2);
+        assert!(history.pop().is_none());
+    }
+}
@@ -5,15 +5,23 @@ use server_common::{aabb::Aabb, quaternion::Quaternion, vec::Vec3};
 use specs::{Builder, Entity as ECSEntity, World, WorldExt};
 
 use crate::comp::{
+    behavior::{Behavior, BehaviorOptions},
     brain::{Brain, BrainOptions},
     curr_chunk::CurrChunk,
+    effects::Effects,
     etype::EType,
+    footsteps::Footsteps,
+    health::Health,
+    hostile::{Hostile, HostileOptions},
+    mount::{Mountable, MountableOptions},
+    portal_cooldown::PortalCooldown,
     rigidbody::RigidBody,
     rotation::Rotation,
     target::{Target, TargetInner},
     view_radius::ViewRadius,
     walk_towards::WalkTowards,
 };
+use crate::constants::AUTO_STEP_HEIGHT;
 
 /// JSON format to store a rigid body configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +34,13 @@ pub struct RigidBodyProto {
     pub restitution: f32,
     pub gravity_multiplier: f32,
     pub auto_step: bool,
+    /// Highest the entity automatically steps up, in voxels before it's scaled
+    #[serde(default = "default_step_height")]
+    pub step_height: f32,
+}
+
+fn default_step_height() -> f32 {
+    AUTO_STEP_HEIGHT
 }
 
 /// JSON format to store an entity model
@@ -47,6 +62,37 @@ pub struct EntityPrototype {
     pub brain: String,
     pub brain_options: BrainOptions,
     pub rigidbody: RigidBodyProto,
+
+    /// Passive mob behaviors, entities without it just follow what they observe
+    #[serde(default)]
+    pub behavior: Option<BehaviorOptions>,
+
+    /// Hostile mob combat, chasing and attacking what they observe
+    #[serde(default)]
+    pub hostile: Option<HostileOptions>,
+
+    /// Maximum health, entities without it can't be hurt
+    #[serde(default)]
+    pub health: Option<f32>,
+
+    /// Points of experience dropped when killed by a player
+    #[serde(default)]
+    pub experience: u32,
+
+    /// How players ride the entity, entities without it can't be ridden
+    #[serde(default)]
+    pub mountable: Option<MountableOptions>,
+}
+
+/// A mob taken out of the world along with the chunk it was in, to be spawned again when
+/// the chunk is loaded back in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuspendedEntity {
+    pub etype: String,
+    pub position: Vec3<f32>,
+    pub child: bool,
+    pub health: Option<f32>,
 }
 
 /// Entity type map
@@ -100,6 +146,29 @@ impl Entities {
         position: &Vec3<f32>,
         rotation: &Quaternion,
     ) -> ECSEntity {
+        Entities::build_entity(
+            ecs.create_entity(),
+            prototype,
+            etype,
+            position,
+            rotation,
+            false,
+        )
+        .build()
+    }
+
+    /// Attach the components of a prototype to an entity builder
+    ///
+    /// Works with both `World::create_entity` and `LazyUpdate::create_entity`, so systems
+    /// can spawn entities too. Children are scaled down by the prototype's behavior.
+    pub fn build_entity<B: Builder>(
+        builder: B,
+        prototype: &EntityPrototype,
+        etype: &str,
+        position: &Vec3<f32>,
+        rotation: &Quaternion,
+        child: bool,
+    ) -> B {
         let RigidBodyProto {
             aabb,
             head,
@@ -108,23 +177,36 @@ impl Entities {
             restitution,
             gravity_multiplier,
             auto_step,
+            step_height,
         } = &prototype.rigidbody;
 
         let observe = &prototype.observe;
         let view_distance = &prototype.view_distance;
         let brain_options = prototype.brain_options.to_owned();
 
-        ecs.create_entity()
+        let scale = match &prototype.behavior {
+            Some(options) if child => options.child_scale,
+            _ => 1.0,
+        };
+
+        let mut body = RigidBody::new(
+            Aabb::new(position, &aabb.scale(scale)),
+            *head * scale,
+            *mass,
+            *friction,
+            *restitution,
+            *gravity_multiplier,
+            *auto_step,
+        );
+        body.step_height = *step_height * scale;
+
+        if let Some(fluid_drag) = prototype.mountable.as_ref().and_then(|m| m.fluid_drag) {
+            body.fluid_drag = fluid_drag;
+        }
+
+        let builder = builder
             .with(EType::new(etype))
-            .with(RigidBody::new(
-                Aabb::new(position, &aabb),
-                *head,
-                *mass,
-                *friction,
-                *restitution,
-                *gravity_multiplier,
-                *auto_step,
-            ))
+            .with(body)
             .with(Rotation::from_quaternion(&rotation))
             .with(CurrChunk::new())
             .with(Target(if observe == "all" {
@@ -137,6 +219,31 @@ impl Entities {
             .with(ViewRadius::new(*view_distance))
             .with(Brain::new(brain_options))
             .with(WalkTowards(None, 100))
-            .build()
+            .with(Effects::new())
+            .with(Footsteps::new())
+            .with(PortalCooldown::new());
+
+        let builder = match prototype.behavior.to_owned() {
+            Some(options) if child => builder.with(Behavior::child(options)),
+            Some(options) => builder.with(Behavior::new(options)),
+            None => builder,
+        };
+
+        let builder = match prototype.hostile.to_owned() {
+            Some(options) => builder.with(Hostile::new(options)),
+            None => builder,
+        };
+
+        let builder = match prototype.mountable.to_owned() {
+            Some(options) => {
+                builder.with(Mountable::new(options, prototype.brain_options.max_speed))
+            }
+            None => builder,
+        };
+
+        match prototype.health {
+            Some(health) => builder.with(Health::new(health)),
+            None => builder,
+        }
     }
 }
@@ -0,0 +1,273 @@
+use rand::Rng;
+
+use crate::comp::inventory::ItemStack;
+
+use super::super::constants::{
+    CROP_GROWTH_CHANCE_DRY, CROP_GROWTH_CHANCE_HYDRATED, FARMLAND_DRY_OUT_CHANCE,
+    FARMLAND_HYDRATION_RADIUS,
+};
+
+use super::chunks::Chunks;
+
+/// Farmland stores whether it's hydrated in its stage bits
+const HYDRATED: u32 = 1;
+const DRY: u32 = 0;
+
+/// Tilling, farmland hydration and crop growth.
+///
+/// Crops store their growth stage in the voxel stage bits, going from `0` to
+/// `growth_stages - 1` when fully grown.
+pub struct Farming;
+
+impl Farming {
+    /// Till a voxel into farmland
+    ///
+    /// Returns whether the voxel was tilled. Only tillable blocks with nothing on top can be tilled.
+    pub fn till(chunks: &mut Chunks, vx: i32, vy: i32, vz: i32) -> bool {
+        if chunks.get_chunk_by_voxel(vx, vy, vz).is_none() {
+            return false;
+        }
+
+        let id = chunks.get_voxel_by_voxel(vx, vy, vz);
+        let above = chunks.get_voxel_by_voxel(vx, vy + 1, vz);
+
+        if !chunks.registry.get_block_by_id(id).is_tillable || !chunks.registry.is_air(above) {
+            return false;
+        }
+
+        let &farmland = chunks.registry.get_id_by_name("Farmland");
+
+        chunks.update(vx, vy, vz, farmland, 0, 0);
+
+        if Farming::is_hydrated(chunks, vx, vy, vz) {
+            chunks.set_voxel_stage_by_voxel(vx, vy, vz, HYDRATED);
+        }
+
+        true
+    }
+
+    /// Whether there is water within the hydration radius of a farmland, on the same level
+    /// or one level above.
+    pub fn is_hydrated(chunks: &Chunks, vx: i32, vy: i32, vz: i32) -> bool {
+        let r = FARMLAND_HYDRATION_RADIUS;
+
+        for x in -r..=r {
+            for z in -r..=r {
+                for y in 0..=1 {
+                    if chunks
+                        .registry
+                        .is_fluid(chunks.get_voxel_by_voxel(vx + x, vy + y, vz + z))
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Handle a random tick on a voxel
+    ///
+    /// Returns whether the voxel changed, in which case clients should be notified.
+    pub fn random_tick<R: Rng>(
+        chunks: &mut Chunks,
+        vx: i32,
+        vy: i32,
+        vz: i32,
+        rng: &mut R,
+    ) -> bool {
+        let id = chunks.get_voxel_by_voxel(vx, vy, vz);
+        let block = chunks.registry.get_block_by_id(id);
+        let is_farmland = block.is_farmland;
+        let growth_stages = block.growth_stages;
+
+        if is_farmland {
+            return Farming::tick_farmland(chunks, vx, vy, vz, rng);
+        }
+
+        if growth_stages > 0 {
+            return Farming::tick_crop(chunks, vx, vy, vz, rng);
+        }
+
+        false
+    }
+
+    /// Get the items dropped by harvesting a crop at a certain stage
+    ///
+    /// Fully grown crops drop more of themselves, so they can be replanted.
+    pub fn harvest<R: Rng>(chunks: &Chunks, id: u32, stage: u32, rng: &mut R) -> Option<ItemStack> {
+        let growth_stages = chunks.registry.get_block_by_id(id).growth_stages;
+
+        if growth_stages == 0 {
+            return None;
+        }
+
+        let count = if stage + 1 >= growth_stages {
+            rng.gen_range(2..=4)
+        } else {
+            1
+        };
+
+        Some(ItemStack::new(id, count))
+    }
+
+    /// Get the stage of a voxel, or 0 if the voxel is not loaded
+    fn get_stage(chunks: &Chunks, vx: i32, vy: i32, vz: i32) -> u32 {
+        chunks
+            .get_chunk_by_voxel(vx, vy, vz)
+            .map(|chunk| chunk.get_voxel_stage(vx, vy, vz))
+            .unwrap_or(0)
+    }
+
+    /// Farmland gets hydrated next to water, dries out otherwise and eventually turns back
+    /// into dirt if nothing is planted on it.
+    fn tick_farmland<R: Rng>(chunks: &mut Chunks, vx: i32, vy: i32, vz: i32, rng: &mut R) -> bool {
+        let stage = Farming::get_stage(chunks, vx, vy, vz);
+
+        if Farming::is_hydrated(chunks, vx, vy, vz) {
+            if stage == HYDRATED {
+                return false;
+            }
+
+            chunks.set_voxel_stage_by_voxel(vx, vy, vz, HYDRATED);
+            chunks.mark_saving_from_voxel(vx, vy, vz);
+            return true;
+        }
+
+        if stage == HYDRATED {
+            chunks.set_voxel_stage_by_voxel(vx, vy, vz, DRY);
+            chunks.mark_saving_from_voxel(vx, vy, vz);
+            return true;
+        }
+
+        let above = chunks.get_voxel_by_voxel(vx, vy + 1, vz);
+
+        if chunks.registry.get_block_by_id(above).growth_stages == 0
+            && rng.gen_bool(FARMLAND_DRY_OUT_CHANCE)
+        {
+            let &dirt = chunks.registry.get_id_by_name("Dirt");
+            chunks.update(vx, vy, vz, dirt, 0, 0);
+            return true;
+        }
+
+        false
+    }
+
+    /// Crops grow a stage at a time, faster on hydrated farmland.
+    fn tick_crop<R: Rng>(chunks: &mut Chunks, vx: i32, vy: i32, vz: i32, rng: &mut R) -> bool {
+        let id = chunks.get_voxel_by_voxel(vx, vy, vz);
+        let below = chunks.get_voxel_by_voxel(vx, vy - 1, vz);
+
+        if !chunks.registry.get_block_by_id(below).is_farmland {
+            return false;
+        }
+
+        let growth_stages = chunks.registry.get_block_by_id(id).growth_stages;
+        let stage = Farming::get_stage(chunks, vx, vy, vz);
+
+        if stage + 1 >= growth_stages {
+            return false;
+        }
+
+        let chance = if Farming::get_stage(chunks, vx, vy - 1, vz) == HYDRATED {
+            CROP_GROWTH_CHANCE_HYDRATED
+        } else {
+            CROP_GROWTH_CHANCE_DRY
+        };
+
+        if !rng.gen_bool(chance) {
+            return false;
+        }
+
+        chunks.set_voxel_stage_by_voxel(vx, vy, vz, stage + 1);
+        chunks.mark_saving_from_voxel(vx, vy, vz);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        engine::rng::WorldRng,
+        test_utils::{self, DIRT, FARMLAND, STONE, WATER, WHEAT},
+    };
+
+    use super::*;
+
+    #[test]
+    fn till_works() {
+        let mut chunks = test_utils::chunks(1, 4);
+        chunks.update(3, 3, 3, DIRT, 0, 0);
+        chunks.update(4, 3, 3, DIRT, 0, 0);
+        chunks.update(4, 4, 3, STONE, 0, 0);
+
+        assert!(Farming::till(&mut chunks, 3, 3, 3));
+        assert_eq!(chunks.get_voxel_by_voxel(3, 3, 3), FARMLAND);
+
+        // not with something on top, nor what can't be tilled
+        assert!(!Farming::till(&mut chunks, 4, 3, 3));
+        assert_eq!(chunks.get_voxel_by_voxel(4, 3, 3), DIRT);
+        assert!(!Farming::till(&mut chunks, 5, 3, 3));
+    }
+
+    #[test]
+    fn farmland_hydration_works() {
+        let mut chunks = test_utils::chunks(1, 4);
+        let mut rng = WorldRng::new(Some(42));
+
+        chunks.update(3, 3, 3, FARMLAND, 0, 0);
+        chunks.update(5, 3, 3, WATER, 0, 0);
+
+        assert!(Farming::random_tick(&mut chunks, 3, 3, 3, &mut rng));
+        assert_eq!(chunks.get_voxel_stage_by_voxel(3, 3, 3), HYDRATED);
+        assert!(!Farming::random_tick(&mut chunks, 3, 3, 3, &mut rng));
+
+        // dries out once the water is gone, then turns back into dirt
+        chunks.update(5, 3, 3, STONE, 0, 0);
+        assert!(Farming::random_tick(&mut chunks, 3, 3, 3, &mut rng));
+        assert_eq!(chunks.get_voxel_stage_by_voxel(3, 3, 3), DRY);
+
+        for _ in 0..100 {
+            Farming::random_tick(&mut chunks, 3, 3, 3, &mut rng);
+        }
+        assert_eq!(chunks.get_voxel_by_voxel(3, 3, 3), DIRT);
+    }
+
+    #[test]
+    fn crops_grow_until_fully_grown() {
+        let mut chunks = test_utils::chunks(1, 4);
+        let mut rng = WorldRng::new(Some(42));
+
+        chunks.update(3, 3, 3, FARMLAND, 0, 0);
+        chunks.update(3, 4, 3, WHEAT, 0, 0);
+
+        for _ in 0..1000 {
+            Farming::random_tick(&mut chunks, 3, 4, 3, &mut rng);
+        }
+        assert_eq!(chunks.get_voxel_stage_by_voxel(3, 4, 3), 7);
+        assert!(!Farming::random_tick(&mut chunks, 3, 4, 3, &mut rng));
+
+        // nor without farmland under them
+        chunks.update(4, 4, 3, WHEAT, 0, 0);
+        assert!(!Farming::random_tick(&mut chunks, 4, 4, 3, &mut rng));
+    }
+
+    #[test]
+    fn harvest_works() {
+        let chunks = test_utils::chunks(0, 4);
+        let mut rng = WorldRng::new(Some(42));
+
+        for _ in 0..100 {
+            let grown = Farming::harvest(&chunks, WHEAT, 7, &mut rng).unwrap();
+            assert!((2..=4).contains(&grown.count));
+        }
+
+        assert_eq!(
+            Farming::harvest(&chunks, WHEAT, 6, &mut rng),
+            Some(ItemStack::new(WHEAT, 1))
+        );
+        assert_eq!(Farming::harvest(&chunks, STONE, 0, &mut rng), None);
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// How a player gets around and interacts with the world
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameMode {
+    Survival,
+    Creative,
+    Spectator,
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Survival
+    }
+}
+
+impl GameMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "survival" => Some(GameMode::Survival),
+            "creative" => Some(GameMode::Creative),
+            "spectator" => Some(GameMode::Spectator),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Survival => "survival",
+            GameMode::Creative => "creative",
+            GameMode::Spectator => "spectator",
+        }
+    }
+
+    /// Whether the player flies through blocks, free of gravity and collisions
+    pub fn is_no_clip(&self) -> bool {
+        matches!(self, GameMode::Creative | GameMode::Spectator)
+    }
+
+    /// Whether the player can place and break blocks
+    pub fn can_edit(&self) -> bool {
+        *self != GameMode::Spectator
+    }
+}
+
+/// Resource to store the game mode of every player
+pub type GameModes = HashMap<usize, GameMode>;
+
+/// How a player is allowed to move on top of what their game mode allows
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Abilities {
+    /// Whether the player flies through blocks whatever their game mode
+    pub fly: bool,
+    /// Multiplier of how fast the player moves
+    pub speed: f32,
+}
+
+impl Default for Abilities {
+    fn default() -> Self {
+        Self {
+            fly: false,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Resource to store the abilities of every player
+pub type PlayerAbilities = HashMap<usize, Abilities>;
@@ -0,0 +1,151 @@
+use hashbrown::HashSet;
+
+use server_common::vec::Vec3;
+
+use super::chunks::Chunks;
+
+/// Resource of voxels whose support may have changed, checked by the gravity system.
+///
+/// A block that starts falling queues the voxel above it in turn, so stacks of sand fall
+/// one block after another.
+#[derive(Debug, Default)]
+pub struct GravityQueue {
+    voxels: HashSet<Vec3<i32>>,
+}
+
+impl GravityQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a voxel to be checked for an unsupported block
+    pub fn push(&mut self, voxel: Vec3<i32>) {
+        self.voxels.insert(voxel);
+    }
+
+    /// Take every queued voxel
+    pub fn take(&mut self) -> HashSet<Vec3<i32>> {
+        std::mem::take(&mut self.voxels)
+    }
+
+    /// Whether any voxel is queued
+    pub fn is_empty(&self) -> bool {
+        self.voxels.is_empty()
+    }
+}
+
+/// Blocks with `has_gravity` falling down as entities when there's nothing under them
+pub struct Gravity;
+
+impl Gravity {
+    /// Whether the block at a voxel has gravity and nothing to rest on
+    pub fn is_unsupported(chunks: &Chunks, vx: i32, vy: i32, vz: i32) -> bool {
+        if vy <= 0 {
+            return false;
+        }
+
+        // the block below is only air because its chunk isn't there yet
+        match chunks.get_chunk_by_voxel(vx, vy, vz) {
+            Some(chunk) if !chunk.needs_propagation => {}
+            _ => return false,
+        }
+
+        chunks
+            .registry
+            .has_gravity(chunks.get_voxel_by_voxel(vx, vy, vz))
+            && Self::can_replace(chunks, vx, vy - 1, vz)
+    }
+
+    /// Whether a falling block goes through the block at a voxel, and may land in its place
+    pub fn can_replace(chunks: &Chunks, vx: i32, vy: i32, vz: i32) -> bool {
+        let id = chunks.get_voxel_by_voxel(vx, vy, vz);
+        chunks.registry.is_air(id) || chunks.registry.is_fluid(id)
+    }
+
+    /// Where a block that landed at a voxel settles: there, or the first voxel above with
+    /// room for it when it landed among non-solid blocks such as torches
+    pub fn settle(chunks: &Chunks, vx: i32, vy: i32, vz: i32) -> Option<Vec3<i32>> {
+        let max_height = chunks.config.max_height as i32;
+
+        (vy.max(0)..max_height)
+            .find(|&y| Self::can_replace(chunks, vx, y, vz))
+            .map(|y| Vec3(vx, y, vz))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use server_common::vec::Vec2;
+
+    use crate::test_utils::{self, SAND, STONE, TORCH, WATER};
+
+    use super::*;
+
+    #[test]
+    fn is_unsupported_works() {
+        let mut chunks = test_utils::chunks(1, 4);
+
+        // sand over air falls, sand on the ground stays
+        chunks.update(3, 10, 3, SAND, 0, 0);
+        chunks.update(3, 4, 5, SAND, 0, 0);
+        assert!(Gravity::is_unsupported(&chunks, 3, 10, 3));
+        assert!(!Gravity::is_unsupported(&chunks, 3, 4, 5));
+
+        // only blocks with gravity fall, and nothing falls out of the bottom of the world
+        chunks.update(3, 10, 4, STONE, 0, 0);
+        assert!(!Gravity::is_unsupported(&chunks, 3, 10, 4));
+        chunks.update(3, 0, 3, SAND, 0, 0);
+        assert!(!Gravity::is_unsupported(&chunks, 3, 0, 3));
+
+        // the air under it might just be a chunk that isn't lit yet
+        chunks.get_chunk_mut(&Vec2(0, 0)).unwrap().needs_propagation = true;
+        assert!(!Gravity::is_unsupported(&chunks, 3, 10, 3));
+    }
+
+    #[test]
+    fn can_replace_works() {
+        let mut chunks = test_utils::chunks(1, 4);
+        chunks.update(3, 4, 3, TORCH, 0, 0);
+        chunks.update(3, 4, 4, WATER, 0, 0);
+
+        assert!(Gravity::can_replace(&chunks, 3, 5, 3));
+        assert!(Gravity::can_replace(&chunks, 3, 4, 4));
+        assert!(!Gravity::can_replace(&chunks, 3, 4, 3));
+        assert!(!Gravity::can_replace(&chunks, 3, 3, 3));
+    }
+
+    #[test]
+    fn settle_works() {
+        let mut chunks = test_utils::chunks(1, 4);
+
+        // landing in air, it stays there
+        assert_eq!(Gravity::settle(&chunks, 3, 4, 3), Some(Vec3(3, 4, 3)));
+
+        // landing on a torch, it rests on top of it
+        chunks.update(3, 4, 3, TORCH, 0, 0);
+        assert_eq!(Gravity::settle(&chunks, 3, 4, 3), Some(Vec3(3, 5, 3)));
+
+        // with no room left above, it's gone
+        let max_height = chunks.config.max_height as i32;
+        for vy in 5..max_height {
+            chunks.update(3, vy, 3, STONE, 0, 0);
+        }
+        assert_eq!(Gravity::settle(&chunks, 3, 4, 3), None);
+    }
+
+    #[test]
+    fn gravity_queue_works() {
+        let mut queue = GravityQueue::new();
+        assert!(queue.is_empty());
+
+        queue.push(Vec3(1, 2, 3));
+        queue.push(Vec3(1, 2, 3));
+        queue.push(Vec3(1, 3, 3));
+        assert!(!queue.is_empty());
+
+        // each voxel is only checked once, however many times it's queued
+        assert_eq!(queue.take().len(), 2);
+        assert!(queue.is_empty());
+        assert!(queue.take().is_empty());
+    }
+}
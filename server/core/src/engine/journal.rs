@@ -0,0 +1,135 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use server_common::vec::Vec3;
+
+use crate::constants::MAX_JOURNAL_ENTRIES;
+
+/// A block a player changed from one type to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub voxel: Vec3<i32>,
+    pub old_id: u32,
+    pub new_id: u32,
+    /// Milliseconds since the unix epoch
+    pub timestamp: u64,
+}
+
+impl JournalEntry {
+    pub fn new(voxel: Vec3<i32>, old_id: u32, new_id: u32) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards?")
+            .as_millis() as u64;
+
+        Self {
+            voxel,
+            old_id,
+            new_id,
+            timestamp,
+        }
+    }
+
+    /// The change that takes this one back
+    pub fn inverse(&self) -> Self {
+        Self {
+            voxel: self.voxel.clone(),
+            old_id: self.new_id,
+            new_id: self.old_id,
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+/// A player's recent block edits, to step back and forth through with `/undo` and `/redo`
+///
+/// Only the latest edits are kept, and a new edit forgets whatever was undone before it.
+#[derive(Debug, Default)]
+pub struct Journal {
+    done: VecDeque<JournalEntry>,
+    undone: Vec<JournalEntry>,
+}
+
+impl Journal {
+    pub fn record(&mut self, entry: JournalEntry) {
+        self.undone.clear();
+        self.done.push_back(entry);
+
+        if self.done.len() > MAX_JOURNAL_ENTRIES {
+            self.done.pop_front();
+        }
+    }
+
+    /// Take back up to `count` of the latest edits, returning the changes that undo them in
+    /// the order to apply them
+    pub fn undo(&mut self, count: usize) -> Vec<JournalEntry> {
+        let mut changes = vec![];
+
+        while changes.len() < count {
+            match self.done.pop_back() {
+                Some(entry) => {
+                    changes.push(entry.inverse());
+                    self.undone.push(entry);
+                }
+                None => break,
+            }
+        }
+
+        changes
+    }
+
+    /// Bring back up to `count` of the latest undone edits, in the order to apply them
+    pub fn redo(&mut self, count: usize) -> Vec<JournalEntry> {
+        let mut changes = vec![];
+
+        while changes.len() < count {
+            match self.undone.pop() {
+                Some(entry) => {
+                    changes.push(entry.clone());
+                    self.done.push_back(entry);
+                }
+                None => break,
+            }
+        }
+
+        changes
+    }
+}
+
+/// Resource of every player's journal, by player id
+pub type Journals = HashMap<usize, Journal>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journal_works() {
+        let mut journal = Journal::default();
+
+        journal.record(JournalEntry::new(Vec3(0, 0, 0), 0, 1));
+        journal.record(JournalEntry::new(Vec3(0, 0, 0), 1, 2));
+
+        let undone = journal.undo(5);
+        assert_eq!(undone.len(), 2);
+        assert_eq!((undone[0].old_id, undone[0].new_id), (2, 1));
+        assert_eq!((undone[1].old_id, undone[1].new_id), (1, 0));
+
+        let redone = journal.redo(1);
+        assert_eq!((redone[0].old_id, redone[0].new_id), (0, 1));
+
+        // a new edit can't be followed by a redo
+        journal.record(JournalEntry::new(Vec3(1, 0, 0), 0, 3));
+        assert!(journal.redo(1).is_empty());
+
+        (0..MAX_JOURNAL_ENTRIES + 1).for_each(|_| {
+            journal.record(JournalEntry::new(Vec3(2, 0, 0), 0, 4));
+        });
+        assert_eq!(journal.undo(usize::MAX).len(), MAX_JOURNAL_ENTRIES);
+    }
+}
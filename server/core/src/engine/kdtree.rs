@@ -115,4 +115,18 @@ impl KdTree {
 
         results
     }
+
+    /// Players within `radius` of a point
+    pub fn players_within(&self, point: &Vec3<f32>, radius: f32) -> Vec<&Entity> {
+        self.players
+            .within(
+                &[point.0, point.1, point.2],
+                radius * radius,
+                &squared_euclidean,
+            )
+            .expect("Unable to search KdTree.")
+            .into_iter()
+            .map(|(_, ent)| ent)
+            .collect()
+    }
 }
@@ -0,0 +1,57 @@
+use specs::Entity;
+
+use server_common::vec::Vec3;
+
+use crate::constants::KNOCKBACK_LIFT;
+
+/// A push given to an entity, applied by the knockback system
+#[derive(Debug, Clone)]
+pub struct KnockbackEvent {
+    pub target: Entity,
+    /// Change in velocity, scaled by the target's mass when applied
+    pub velocity: Vec3<f32>,
+}
+
+impl KnockbackEvent {
+    /// Knock an entity away from a point, slightly lifting it off the ground
+    pub fn away_from(
+        target: Entity,
+        position: &Vec3<f32>,
+        from: &Vec3<f32>,
+        strength: f32,
+    ) -> Self {
+        let mut away = position.sub(from);
+        away.1 = 0.0;
+
+        let away = if away.len() > 0.0 {
+            away.normalize()
+        } else {
+            Vec3(0.0, 0.0, 0.0)
+        };
+
+        let mut velocity = away.scale(strength);
+        velocity.1 = strength * KNOCKBACK_LIFT;
+
+        Self { target, velocity }
+    }
+}
+
+/// Resource of knockback to be dispatched this tick
+pub type KnockbackQueue = Vec<KnockbackEvent>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use specs::{Builder, World, WorldExt};
+
+    #[test]
+    fn away_from_works() {
+        let mut world = World::new();
+        let target = world.create_entity().build();
+
+        let event =
+            KnockbackEvent::away_from(target, &Vec3(3.0, 0.0, 0.0), &Vec3(0.0, 5.0, 0.0), 4.0);
+        assert_eq!(event.velocity, Vec3(4.0, 4.0 * KNOCKBACK_LIFT, 0.0));
+    }
+}
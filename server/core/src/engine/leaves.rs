@@ -0,0 +1,186 @@
+use std::collections::VecDeque;
+
+use hashbrown::HashSet;
+use rand::Rng;
+
+use server_common::vec::Vec3;
+
+use super::super::{
+    comp::inventory::ItemStack,
+    constants::{LEAF_DECAY_DISTANCE, VOXEL_NEIGHBORS},
+};
+
+use super::{
+    chunks::Chunks,
+    loot::{LootContext, LootTables},
+};
+
+/// Leaves placed by players store this in their stage bits, and never decay
+pub const PERSISTENT: u32 = 1;
+
+/// Leaves decaying once nothing holds them up, such as after a tree is cut down.
+///
+/// A leaf is held up by a log within `LEAF_DECAY_DISTANCE` voxels, reached through other
+/// leaves. Leaves are only checked when they're randomly ticked, so the leaves of a felled
+/// tree fall apart over time rather than all at once.
+pub struct Leaves;
+
+impl Leaves {
+    /// Handle a random tick on a voxel
+    ///
+    /// Returns what the leaves dropped if they decayed, in which case clients should be notified.
+    /// Decayed leaves drop whatever their loot table rolls, such as saplings.
+    pub fn random_tick<R: Rng>(
+        chunks: &mut Chunks,
+        loot: &LootTables,
+        vx: i32,
+        vy: i32,
+        vz: i32,
+        rng: &mut R,
+    ) -> Option<Vec<ItemStack>> {
+        let id = chunks.get_voxel_by_voxel(vx, vy, vz);
+
+        if !chunks.registry.is_leaves(id) {
+            return None;
+        }
+
+        let stage = chunks
+            .get_chunk_by_voxel(vx, vy, vz)
+            .map_or(PERSISTENT, |chunk| chunk.get_voxel_stage(vx, vy, vz));
+
+        if stage == PERSISTENT || Leaves::is_held_up(chunks, vx, vy, vz) {
+            return None;
+        }
+
+        let drops = loot
+            .roll_block(&chunks.registry, id, &LootContext::default(), rng)
+            .unwrap_or_default();
+
+        let &air = chunks.registry.get_id_by_name("Air");
+        chunks.update(vx, vy, vz, air, 0, 0);
+
+        Some(drops)
+    }
+
+    /// Whether there's a log within reach of a leaf, searching breadth first through leaves.
+    ///
+    /// Unloaded voxels count as logs, since what's in them is unknown.
+    pub fn is_held_up(chunks: &Chunks, vx: i32, vy: i32, vz: i32) -> bool {
+        let max_height = chunks.config.max_height as i32;
+
+        let mut queue = VecDeque::new();
+        let mut visited = HashSet::new();
+
+        queue.push_back((Vec3(vx, vy, vz), 0));
+        visited.insert(Vec3(vx, vy, vz));
+
+        while let Some((Vec3(x, y, z), distance)) = queue.pop_front() {
+            for [ox, oy, oz] in VOXEL_NEIGHBORS.iter() {
+                let neighbor = Vec3(x + ox, y + oy, z + oz);
+                let Vec3(nx, ny, nz) = neighbor;
+
+                if ny < 0 || ny >= max_height || visited.contains(&neighbor) {
+                    continue;
+                }
+
+                if chunks.get_chunk_by_voxel(nx, ny, nz).is_none() {
+                    return true;
+                }
+
+                let block = chunks.get_block_by_voxel(nx, ny, nz);
+
+                if block.is_log {
+                    return true;
+                }
+
+                if block.is_leaves && distance + 1 < LEAF_DECAY_DISTANCE {
+                    queue.push_back((neighbor.clone(), distance + 1));
+                }
+
+                visited.insert(neighbor);
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        engine::rng::WorldRng,
+        test_utils::{self, AIR, LEAVES, LOG},
+    };
+
+    use super::*;
+
+    /// Leaves that drop themselves when they decay
+    fn loot() -> LootTables {
+        let mut loot = LootTables::default();
+        loot.register_block(
+            "Oak Leaves",
+            serde_json::from_value(serde_json::json!({
+                "pools": [{ "entries": [{ "item": "Oak Leaves" }] }]
+            }))
+            .unwrap(),
+        );
+        loot
+    }
+
+    #[test]
+    fn leaves_decay_away_from_logs() {
+        let mut chunks = test_utils::chunks(1, 4);
+        let mut rng = WorldRng::new(Some(42));
+        let reach = LEAF_DECAY_DISTANCE as i32;
+
+        // a branch of leaves reaching one voxel further than a log holds up
+        chunks.update(0, 8, 3, LOG, 0, 0);
+        for vx in 1..=reach + 1 {
+            chunks.update(vx, 8, 3, LEAVES, 0, 0);
+        }
+
+        assert!(Leaves::is_held_up(&chunks, reach, 8, 3));
+        assert!(!Leaves::is_held_up(&chunks, reach + 1, 8, 3));
+
+        assert_eq!(
+            Leaves::random_tick(&mut chunks, &loot(), reach, 8, 3, &mut rng),
+            None
+        );
+        assert_eq!(chunks.get_voxel_by_voxel(reach, 8, 3), LEAVES);
+
+        assert_eq!(
+            Leaves::random_tick(&mut chunks, &loot(), reach + 1, 8, 3, &mut rng),
+            Some(vec![ItemStack::new(LEAVES, 1)])
+        );
+        assert_eq!(chunks.get_voxel_by_voxel(reach + 1, 8, 3), AIR);
+    }
+
+    #[test]
+    fn persistent_leaves_never_decay() {
+        let mut chunks = test_utils::chunks(1, 4);
+        let mut rng = WorldRng::new(Some(42));
+
+        // placed by a player, nowhere near a log
+        chunks.update(3, 8, 3, LEAVES, 0, 0);
+        chunks.set_voxel_stage_by_voxel(3, 8, 3, PERSISTENT);
+
+        assert_eq!(
+            Leaves::random_tick(&mut chunks, &loot(), 3, 8, 3, &mut rng),
+            None
+        );
+        assert_eq!(chunks.get_voxel_by_voxel(3, 8, 3), LEAVES);
+    }
+
+    #[test]
+    fn unloaded_neighbors_hold_leaves_up() {
+        let mut chunks = test_utils::chunks(1, 4);
+        let edge = chunks.config.chunk_size as i32 * 2 - 1;
+
+        // what's past the last loaded chunk might be a log
+        chunks.update(edge, 8, 3, LEAVES, 0, 0);
+        chunks.update(edge - 2, 8, 3, LEAVES, 0, 0);
+
+        assert!(Leaves::is_held_up(&chunks, edge, 8, 3));
+        assert!(!Leaves::is_held_up(&chunks, edge - 2, 8, 3));
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+
+use server_common::vec::Vec2;
+
+use crate::constants::{LOCATE_BIOME_RADIUS, LOCATE_BIOME_STEP, LOCATE_STRUCTURE_RINGS};
+use crate::gen::{biomes::Biomes, structures::Structures};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LocateError {
+    /// Along with the structures there are
+    UnknownStructure(String),
+    /// Along with the biomes there are
+    UnknownBiome(String),
+    NotFound,
+    Usage(&'static str),
+}
+
+impl fmt::Display for LocateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocateError::UnknownStructure(names) => {
+                write!(f, "Structures in this world: {}.", names)
+            }
+            LocateError::UnknownBiome(names) => write!(f, "Biomes in this world: {}.", names),
+            LocateError::NotFound => write!(f, "None found anywhere near."),
+            LocateError::Usage(usage) => write!(f, "Usage: {}", usage),
+        }
+    }
+}
+
+/// Finding the nearest structures and biomes, generated or not.
+///
+/// Both are only decided by the world seed, so chunks nobody has been to yet are searched
+/// the same way as the generated ones.
+pub struct Locate;
+
+impl Locate {
+    /// Find the column of the nearest structure of a kind, searching its regions ring by ring
+    /// until no farther region could hold a closer one
+    pub fn structure(
+        structures: &Structures,
+        index: usize,
+        from: &Vec2<i32>,
+        chunk_size: usize,
+    ) -> Option<Vec2<i32>> {
+        let region_size = structures.spacing(index) * chunk_size as i32;
+        let Vec2(cx, cz) = Vec2(
+            from.0.div_euclid(region_size),
+            from.1.div_euclid(region_size),
+        );
+
+        let mut nearest: Option<(i64, Vec2<i32>)> = None;
+
+        for r in 0..=LOCATE_STRUCTURE_RINGS {
+            for (x, z) in ring(r) {
+                if let Some(origin) = structures.origin(index, &Vec2(cx + x, cz + z), chunk_size) {
+                    let distance = distance_squared(from, &origin);
+
+                    if nearest.as_ref().map_or(true, |(d, _)| distance < *d) {
+                        nearest = Some((distance, origin));
+                    }
+                }
+            }
+
+            // every region of the next ring is at least `r` regions away
+            let reach = r as i64 * region_size as i64;
+            if nearest.as_ref().map_or(false, |(d, _)| *d <= reach * reach) {
+                break;
+            }
+        }
+
+        nearest.map(|(_, origin)| origin)
+    }
+
+    /// Find the nearest column of a biome, sampling every `LOCATE_BIOME_STEP` voxels out to
+    /// `LOCATE_BIOME_RADIUS`
+    pub fn biome(biomes: &Biomes, name: &str, from: &Vec2<i32>) -> Option<Vec2<i32>> {
+        (0..=LOCATE_BIOME_RADIUS / LOCATE_BIOME_STEP).find_map(|r| {
+            ring(r)
+                .into_iter()
+                .map(|(x, z)| {
+                    Vec2(
+                        from.0 + x * LOCATE_BIOME_STEP,
+                        from.1 + z * LOCATE_BIOME_STEP,
+                    )
+                })
+                .filter(|column| biomes.get_biome(column.0, column.1).name == name)
+                .min_by_key(|column| distance_squared(from, column))
+        })
+    }
+}
+
+/// Offsets of the square ring `r` steps out from the center
+fn ring(r: i32) -> Vec<(i32, i32)> {
+    if r == 0 {
+        return vec![(0, 0)];
+    }
+
+    let mut offsets = Vec::with_capacity(8 * r as usize);
+
+    for i in -r..r {
+        offsets.push((i, -r));
+        offsets.push((r, i));
+        offsets.push((-i, r));
+        offsets.push((-r, -i));
+    }
+
+    offsets
+}
+
+fn distance_squared(a: &Vec2<i32>, b: &Vec2<i32>) -> i64 {
+    let dx = (a.0 - b.0) as i64;
+    let dz = (a.1 - b.1) as i64;
+    dx * dx + dz * dz
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::gen::structures::StructureTemplate;
+
+    use super::*;
+
+    #[test]
+    fn locate_works() {
+        assert_eq!(ring(0), vec![(0, 0)]);
+        assert_eq!(ring(2).len(), 16);
+
+        let mut templates = HashMap::new();
+        templates.insert(
+            "ruin".to_owned(),
+            StructureTemplate {
+                spacing: 2,
+                chance: 0.3,
+                generations: vec![],
+                palette: HashMap::new(),
+                pieces: vec![],
+            },
+        );
+        let structures = Structures::from_templates(templates, "hilly", 42);
+
+        let from = Vec2(100, -50);
+        let found = Locate::structure(&structures, 0, &from, 16).unwrap();
+
+        // no region around is any closer
+        let closest = (-20..20)
+            .flat_map(|rx| (-20..20).map(move |rz| Vec2(rx, rz)))
+            .filter_map(|region| structures.origin(0, &region, 16))
+            .map(|origin| distance_squared(&from, &origin))
+            .min()
+            .unwrap();
+        assert_eq!(distance_squared(&from, &found), closest);
+    }
+}
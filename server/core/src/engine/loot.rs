@@ -0,0 +1,269 @@
+use std::{collections::HashMap, fs::File};
+
+use log::warn;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::comp::inventory::ItemStack;
+
+use super::registry::Registry;
+
+fn default_one() -> u32 {
+    1
+}
+
+fn default_count() -> [u32; 2] {
+    [1, 1]
+}
+
+/// What a loot table is rolled with
+#[derive(Debug, Clone, Default)]
+pub struct LootContext {
+    /// Name of the item held by whoever broke the block or killed the mob
+    pub tool: Option<String>,
+    pub fortune: u32,
+    /// Stage of the broken block
+    pub stage: u32,
+}
+
+/// Requirement for a pool or an entry to drop anything
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum LootCondition {
+    /// Broken or killed while holding one of these items
+    Tool { tools: Vec<String> },
+    /// Rolled with at least this fortune level
+    Fortune { min: u32 },
+    /// Passes at random, `chance` being between 0 and 1
+    Chance { chance: f64 },
+    /// Broken while its stage is within a range, such as crops fully grown
+    Stage {
+        #[serde(default)]
+        min: u32,
+        #[serde(default)]
+        max: Option<u32>,
+    },
+}
+
+impl LootCondition {
+    pub fn test<R: Rng>(&self, context: &LootContext, rng: &mut R) -> bool {
+        match self {
+            Self::Tool { tools } => context
+                .tool
+                .as_ref()
+                .map_or(false, |tool| tools.contains(tool)),
+            Self::Fortune { min } => context.fortune >= *min,
+            Self::Chance { chance } => rng.gen_bool(chance.clamp(0.0, 1.0)),
+            Self::Stage { min, max } => {
+                context.stage >= *min && max.map_or(true, |max| context.stage <= max)
+            }
+        }
+    }
+
+    /// Whether every one of a list of conditions passes
+    fn test_all<R: Rng>(conditions: &[Self], context: &LootContext, rng: &mut R) -> bool {
+        conditions
+            .iter()
+            .all(|condition| condition.test(context, rng))
+    }
+}
+
+/// One of the outcomes a pool picks from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LootEntry {
+    /// Name of the block dropped, drops nothing when not set
+    #[serde(default)]
+    pub item: Option<String>,
+    #[serde(default = "default_one")]
+    pub weight: u32,
+    /// Least and most items dropped
+    #[serde(default = "default_count")]
+    pub count: [u32; 2],
+    /// Most extra items dropped per fortune level
+    #[serde(default)]
+    pub fortune_bonus: u32,
+    #[serde(default)]
+    pub conditions: Vec<LootCondition>,
+}
+
+/// Entries picked from by weight, once per roll
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LootPool {
+    #[serde(default = "default_one")]
+    pub rolls: u32,
+    pub entries: Vec<LootEntry>,
+    #[serde(default)]
+    pub conditions: Vec<LootCondition>,
+}
+
+/// What a block or a mob drops, each pool rolled on its own
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LootTable {
+    pub pools: Vec<LootPool>,
+}
+
+impl LootTable {
+    /// Roll every pool, each picking entries by weight among the ones whose conditions pass
+    pub fn roll<R: Rng>(
+        &self,
+        registry: &Registry,
+        context: &LootContext,
+        rng: &mut R,
+    ) -> Vec<ItemStack> {
+        let mut items = vec![];
+
+        for pool in self.pools.iter() {
+            if !LootCondition::test_all(&pool.conditions, context, rng) {
+                continue;
+            }
+
+            for _ in 0..pool.rolls {
+                let entries = pool
+                    .entries
+                    .iter()
+                    .filter(|entry| LootCondition::test_all(&entry.conditions, context, rng))
+                    .collect::<Vec<_>>();
+
+                let total = entries.iter().map(|entry| entry.weight).sum::<u32>();
+
+                if total == 0 {
+                    continue;
+                }
+
+                let mut pick = rng.gen_range(0..total);
+                let entry = entries
+                    .into_iter()
+                    .find(|entry| {
+                        if pick < entry.weight {
+                            return true;
+                        }
+
+                        pick -= entry.weight;
+                        false
+                    })
+                    .unwrap();
+
+                let name = match &entry.item {
+                    Some(name) => name,
+                    None => continue,
+                };
+
+                let id = match registry.find_id_by_name(name) {
+                    Some(id) => id,
+                    None => {
+                        warn!("Loot names an unknown block: {}", name);
+                        continue;
+                    }
+                };
+
+                let [min, max] = entry.count;
+                let mut count = rng.gen_range(min..=max.max(min));
+
+                if entry.fortune_bonus > 0 && context.fortune > 0 {
+                    count += rng.gen_range(0..=entry.fortune_bonus * context.fortune);
+                }
+
+                if count > 0 {
+                    items.push(ItemStack::new(id, count));
+                }
+            }
+        }
+
+        items
+    }
+}
+
+/// Resource of the loot tables of blocks and mobs, by block name and entity type, loaded
+/// from `loot.json`.
+///
+/// Tables can be registered on top of the loaded ones while the world runs, replacing
+/// any table of the same block or mob.
+#[derive(Debug, Default, Deserialize)]
+pub struct LootTables {
+    #[serde(default)]
+    blocks: HashMap<String, LootTable>,
+    #[serde(default)]
+    entities: HashMap<String, LootTable>,
+}
+
+impl LootTables {
+    pub fn new() -> Self {
+        serde_json::from_reader(File::open("assets/metadata/loot.json").unwrap()).unwrap()
+    }
+
+    /// Register the loot table of a block by its name
+    pub fn register_block(&mut self, name: &str, table: LootTable) {
+        self.blocks.insert(name.to_owned(), table);
+    }
+
+    /// Register the loot table of a mob by its entity type
+    pub fn register_entity(&mut self, etype: &str, table: LootTable) {
+        self.entities.insert(etype.to_owned(), table);
+    }
+
+    /// Roll what a broken block drops, None if it has no loot table
+    pub fn roll_block<R: Rng>(
+        &self,
+        registry: &Registry,
+        id: u32,
+        context: &LootContext,
+        rng: &mut R,
+    ) -> Option<Vec<ItemStack>> {
+        let name = &registry.get_block_by_id(id).name;
+
+        self.blocks
+            .get(name)
+            .map(|table| table.roll(registry, context, rng))
+    }
+
+    /// Roll what a killed mob drops, nothing if it has no loot table
+    pub fn roll_entity<R: Rng>(
+        &self,
+        registry: &Registry,
+        etype: &str,
+        context: &LootContext,
+        rng: &mut R,
+    ) -> Vec<ItemStack> {
+        self.entities
+            .get(etype)
+            .map(|table| table.roll(registry, context, rng))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditions_work() {
+        let mut rng = rand::thread_rng();
+        let context = LootContext {
+            tool: Some("Stone".to_owned()),
+            fortune: 2,
+            stage: 7,
+        };
+
+        let conditions: Vec<LootCondition> = serde_json::from_value(serde_json::json!([
+            { "type": "tool", "tools": ["Dirt", "Stone"] },
+            { "type": "fortune", "min": 1 },
+            { "type": "stage", "min": 7 },
+            { "type": "chance", "chance": 1.0 },
+        ]))
+        .unwrap();
+
+        assert!(LootCondition::test_all(&conditions, &context, &mut rng));
+
+        let unripe = LootCondition::Stage {
+            min: 0,
+            max: Some(6),
+        };
+        assert!(!unripe.test(&context, &mut rng));
+
+        let never = LootCondition::Chance { chance: 0.0 };
+        assert!(!never.test(&LootContext::default(), &mut rng));
+    }
+}
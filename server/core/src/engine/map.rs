@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use hashbrown::{HashMap, HashSet};
+use image::{DynamicImage, GenericImageView, ImageOutputFormat, Rgba, RgbaImage};
+
+use server_common::vec::{Vec2, Vec3};
+
+use super::super::network::models::messages;
+use super::{chunk::Chunk, registry::Registry};
+
+/// Top-down tiles of the chunks looked at on the map, PNG encoded and cached by chunk
+///
+/// Tiles of chunks that changed are kept around as stale until they're rendered again, so
+/// that the map never has holes in it.
+#[derive(Debug, Default)]
+pub struct MapTiles {
+    tiles: HashMap<Vec2<i32>, Arc<Vec<u8>>>,
+    stale: HashSet<Vec2<i32>>,
+}
+
+impl MapTiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, coords: &Vec2<i32>) -> Option<Arc<Vec<u8>>> {
+        self.tiles.get(coords).cloned()
+    }
+
+    pub fn insert(&mut self, coords: &Vec2<i32>, tile: Arc<Vec<u8>>) {
+        self.stale.remove(coords);
+        self.tiles.insert(coords.to_owned(), tile);
+    }
+
+    /// Mark a chunk's tile to be rendered again, if it was ever rendered
+    pub fn invalidate(&mut self, coords: &Vec2<i32>) {
+        if self.tiles.contains_key(coords) {
+            self.stale.insert(coords.to_owned());
+        }
+    }
+
+    /// Stop rendering a stale tile again, such as when its chunk was unloaded
+    pub fn unmark_stale(&mut self, coords: &Vec2<i32>) {
+        self.stale.remove(coords);
+    }
+
+    /// The chunks of stale tiles
+    pub fn stale(&self) -> impl Iterator<Item = &Vec2<i32>> {
+        self.stale.iter()
+    }
+}
+
+/// Render a chunk's tile: a pixel per column, colored by the block on top and shaded lighter
+/// or darker by how much higher or lower it is than the column north of it
+pub fn render_tile(chunk: &Chunk, registry: &Registry) -> Vec<u8> {
+    let size = chunk.size as u32;
+    let Vec3(min_x, _, min_z) = chunk.min_inner.to_owned();
+    let mut image = RgbaImage::new(size, size);
+
+    let height = |vx: i32, vz: i32| chunk.get_max_height(vx, vz) as i32;
+
+    for lx in 0..size as i32 {
+        for lz in 0..size as i32 {
+            let (vx, vz) = (min_x + lx, min_z + lz);
+            let vy = height(vx, vz);
+            let id = chunk.get_voxel(vx, vy, vz);
+
+            if registry.is_air(id) {
+                continue;
+            }
+
+            let [r, g, b] = registry.get_map_color(id);
+            let north = if lz > 0 { height(vx, vz - 1) } else { vy };
+            let shade = match vy.cmp(&north) {
+                std::cmp::Ordering::Greater => 1.1,
+                std::cmp::Ordering::Less => 0.8,
+                std::cmp::Ordering::Equal => 1.0,
+            };
+            let shaded = |c: u8| (c as f32 * shade).min(255.0) as u8;
+
+            image.put_pixel(
+                lx as u32,
+                lz as u32,
+                Rgba([shaded(r), shaded(g), shaded(b), 255]),
+            );
+        }
+    }
+
+    let mut png = vec![];
+
+    DynamicImage::ImageRgba8(image)
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .expect("Unable to encode map tile.");
+
+    png
+}
+
+/// Render a chunk's horizon: the height and color of the block on top of every column, for
+/// clients to draw the chunk as a heightfield from afar
+pub fn render_horizon(chunk: &Chunk, registry: &Registry) -> messages::Horizon {
+    let size = chunk.size as i32;
+    let Vec3(min_x, _, min_z) = chunk.min_inner.to_owned();
+    let mut heights = Vec::with_capacity((size * size) as usize);
+    let mut colors = Vec::with_capacity((size * size) as usize);
+
+    for lz in 0..size {
+        for lx in 0..size {
+            let (vx, vz) = (min_x + lx, min_z + lz);
+            let vy = chunk.get_max_height(vx, vz);
+            let id = chunk.get_voxel(vx, vy as i32, vz);
+
+            let [r, g, b] = if registry.is_air(id) {
+                [0, 0, 0]
+            } else {
+                registry.get_map_color(id)
+            };
+
+            heights.push(vy);
+            colors.push(((r as u32) << 16) | ((g as u32) << 8) | b as u32);
+        }
+    }
+
+    messages::Horizon {
+        x: chunk.coords.0,
+        z: chunk.coords.1,
+        heights,
+        colors,
+    }
+}
+
+/// Average color of the opaque texels of a texture, what a block looks like from afar
+pub fn average_color(texture: &DynamicImage) -> [u8; 3] {
+    let (mut sum, mut count) = ([0u64; 3], 0u64);
+
+    for (_, _, Rgba([r, g, b, a])) in texture.pixels() {
+        if a == 0 {
+            continue;
+        }
+
+        sum[0] += r as u64;
+        sum[1] += g as u64;
+        sum[2] += b as u64;
+        count += 1;
+    }
+
+    if count == 0 {
+        return [0, 0, 0];
+    }
+
+    [
+        (sum[0] / count) as u8,
+        (sum[1] / count) as u8,
+        (sum[2] / count) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils;
+
+    use super::*;
+
+    #[test]
+    fn map_tiles_work() {
+        let mut tiles = MapTiles::new();
+
+        // tiles nobody looked at aren't rendered again
+        tiles.invalidate(&Vec2(0, 0));
+        assert_eq!(tiles.stale().count(), 0);
+
+        tiles.insert(&Vec2(0, 0), Arc::new(vec![1]));
+        tiles.invalidate(&Vec2(0, 0));
+        assert_eq!(tiles.stale().collect::<Vec<_>>(), vec![&Vec2(0, 0)]);
+        assert!(tiles.get(&Vec2(0, 0)).is_some());
+
+        tiles.insert(&Vec2(0, 0), Arc::new(vec![2]));
+        assert_eq!(tiles.stale().count(), 0);
+
+        let texture = RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                Rgba([100, 50, 0, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+        assert_eq!(
+            average_color(&DynamicImage::ImageRgba8(texture)),
+            [100, 50, 0]
+        );
+    }
+
+    #[test]
+    fn render_horizon_works() {
+        let chunks = test_utils::hilly_chunks(0);
+        let chunk = chunks.get_chunk(&Vec2(0, 0)).unwrap();
+        let horizon = render_horizon(chunk, &chunks.registry);
+
+        assert_eq!(horizon.heights.len(), 8 * 8);
+        assert_eq!(horizon.heights[8 + 3], chunk.get_max_height(3, 1));
+
+        let [r, g, b] = chunks.registry.get_map_color(test_utils::STONE);
+        assert_eq!(
+            horizon.colors[0],
+            ((r as u32) << 16) | ((g as u32) << 8) | b as u32
+        );
+    }
+}
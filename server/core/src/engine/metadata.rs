@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use server_common::vec::Vec3;
+
+use crate::constants::{LEVEL_SEED, WORLD_FORMAT_VERSION};
+
+use super::{claims::Claims, waypoints::Waypoint, world::WorldConfig};
+
+/// Sizes a world's data is saved with, which it can't be loaded with any others
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldMetrics {
+    pub chunk_size: usize,
+    pub dimension: usize,
+    pub max_height: u32,
+}
+
+impl WorldMetrics {
+    pub fn from_config(config: &WorldConfig) -> Self {
+        Self {
+            chunk_size: config.chunk_size,
+            dimension: config.dimension,
+            max_height: config.max_height,
+        }
+    }
+}
+
+/// Resource of what a world keeps in its `world.json`, along with the version of the
+/// format it's saved in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldMetadata {
+    pub version: u32,
+    pub seed: u32,
+    pub metrics: WorldMetrics,
+    /// Voxel players join at when they've never been in the world, found from the terrain
+    /// when not set
+    #[serde(default)]
+    pub spawn: Option<Vec3<i32>>,
+    pub time: f32,
+    pub tick_speed: f32,
+    #[serde(default)]
+    pub claims: Claims,
+    /// Voxels the members of a team respawn at, by team
+    #[serde(default)]
+    pub team_spawns: BTreeMap<String, Vec3<i32>>,
+    /// Waypoints admins set for every player to see
+    #[serde(default)]
+    pub markers: Vec<Waypoint>,
+}
+
+impl WorldMetadata {
+    /// Metadata of a world that hasn't been saved yet
+    pub fn new(config: &WorldConfig, time: f32, tick_speed: f32) -> Self {
+        Self {
+            version: WORLD_FORMAT_VERSION,
+            seed: LEVEL_SEED,
+            metrics: WorldMetrics::from_config(config),
+            spawn: None,
+            time,
+            tick_speed,
+            claims: Claims::default(),
+            team_spawns: BTreeMap::new(),
+            markers: vec![],
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use log::info;
+use serde_json::{json, Value};
+
+use crate::constants::{LEVEL_SEED, WORLD_FORMAT_VERSION};
+
+use super::{
+    metadata::WorldMetrics,
+    storage::{Storage, WriteBatch},
+};
+
+/// Chunks migrated per write, so that large worlds aren't held in memory all at once
+const CHUNKS_PER_BATCH: usize = 256;
+
+/// An upgrade of saved worlds from the format version before `version` to it
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    /// Upgrade the world's metadata
+    pub world: fn(&mut Value, &WorldMetrics),
+    /// Upgrade a saved chunk, None if chunks are saved the same as before
+    pub chunk: Option<fn(&mut Value)>,
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// Saved by a newer server than this one
+    TooNew { version: u32, latest: u32 },
+    /// No migration upgrades saves to this version
+    Missing(u32),
+}
+
+impl std::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationError::TooNew { version, latest } => write!(
+                f,
+                "Saved in format version {}, but this server only reads up to {}.",
+                version, latest
+            ),
+            MigrationError::Missing(version) => {
+                write!(f, "No migration to format version {}.", version)
+            }
+        }
+    }
+}
+
+/// Registry of the upgrades bringing saves of older format versions up to the current one,
+/// instead of reading them as if nothing changed.
+///
+/// Every change to how worlds are saved bumps `WORLD_FORMAT_VERSION` and registers the
+/// migration to it here.
+pub struct Migrations {
+    migrations: Vec<Migration>,
+}
+
+impl Default for Migrations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Migrations {
+    pub fn new() -> Self {
+        let mut migrations = Self { migrations: vec![] };
+
+        migrations.register(Migration {
+            version: 1,
+            description: "Record the format version, seed and metrics in world.json",
+            world: version_metadata,
+            chunk: None,
+        });
+
+        migrations
+    }
+
+    /// Register a migration, run in order of versions
+    pub fn register(&mut self, migration: Migration) {
+        self.migrations.push(migration);
+        self.migrations.sort_by_key(|migration| migration.version);
+    }
+
+    /// Format version of a world's metadata, 0 for worlds saved before there were versions
+    pub fn version_of(world: &Value) -> u32 {
+        world["version"].as_u64().unwrap_or(0) as u32
+    }
+
+    /// Upgrade a world's metadata to the current format version, returning the migrations
+    /// that were run
+    pub fn migrate_world(
+        &self,
+        world: &mut Value,
+        metrics: &WorldMetrics,
+    ) -> Result<Vec<&Migration>, MigrationError> {
+        let version = Self::version_of(world);
+
+        if version > WORLD_FORMAT_VERSION {
+            return Err(MigrationError::TooNew {
+                version,
+                latest: WORLD_FORMAT_VERSION,
+            });
+        }
+
+        let mut pending = vec![];
+
+        for next in version + 1..=WORLD_FORMAT_VERSION {
+            match self.migrations.iter().find(|m| m.version == next) {
+                Some(migration) => pending.push(migration),
+                None => return Err(MigrationError::Missing(next)),
+            }
+        }
+
+        for migration in pending.iter() {
+            (migration.world)(world, metrics);
+            world["version"] = json!(migration.version);
+        }
+
+        Ok(pending)
+    }
+
+    /// Upgrade a world's saves in its storage, chunks included, returning its metadata in
+    /// the current format.
+    ///
+    /// The metadata is written last, so a world interrupted while migrating chunks is
+    /// migrated again on its next load.
+    pub fn migrate(
+        &self,
+        storage: &dyn Storage,
+        mut world: Value,
+        metrics: &WorldMetrics,
+    ) -> Result<Value, MigrationError> {
+        let from = Self::version_of(&world);
+        let migrated = self.migrate_world(&mut world, metrics)?;
+
+        if migrated.is_empty() {
+            return Ok(world);
+        }
+
+        migrated.iter().for_each(|migration| {
+            info!(
+                "Migrating world to format version {}: {}",
+                migration.version, migration.description
+            );
+        });
+
+        let chunk_migrations = migrated
+            .iter()
+            .filter_map(|migration| migration.chunk)
+            .collect::<Vec<_>>();
+
+        if !chunk_migrations.is_empty() {
+            let saved = storage.list_chunks();
+
+            for batch in saved.chunks(CHUNKS_PER_BATCH) {
+                let chunks = batch
+                    .iter()
+                    .filter_map(|(coords, _)| {
+                        let mut data = storage.load_chunk(coords)?;
+                        chunk_migrations
+                            .iter()
+                            .for_each(|migrate| migrate(&mut data));
+                        Some((coords.to_owned(), data))
+                    })
+                    .collect();
+
+                storage.write(WriteBatch {
+                    chunks,
+                    ..Default::default()
+                });
+            }
+        }
+
+        storage.write(WriteBatch {
+            world: Some(world.clone()),
+            ..Default::default()
+        });
+
+        info!(
+            "Migrated world from format version {} to {}.",
+            from, WORLD_FORMAT_VERSION
+        );
+
+        Ok(world)
+    }
+}
+
+/// Worlds saved before versions only kept their time, and were generated with the seed
+/// and metrics of the time
+fn version_metadata(world: &mut Value, metrics: &WorldMetrics) {
+    world["seed"] = json!(LEVEL_SEED);
+    world["metrics"] = serde_json::to_value(metrics).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_world_works() {
+        let metrics = WorldMetrics {
+            chunk_size: 16,
+            dimension: 1,
+            max_height: 256,
+        };
+
+        let mut world = json!({ "time": 1200.0, "tickSpeed": 2.0 });
+        let migrations = Migrations::new();
+
+        let migrated = migrations.migrate_world(&mut world, &metrics).unwrap();
+        assert_eq!(migrated.len(), WORLD_FORMAT_VERSION as usize);
+        assert_eq!(Migrations::version_of(&world), WORLD_FORMAT_VERSION);
+        assert_eq!(world["metrics"]["maxHeight"], 256);
+        assert_eq!(world["time"], 1200.0);
+
+        // nothing left to do on an up to date world
+        assert!(migrations
+            .migrate_world(&mut world, &metrics)
+            .unwrap()
+            .is_empty());
+
+        let mut newer = json!({ "version": WORLD_FORMAT_VERSION + 1 });
+        assert!(matches!(
+            migrations.migrate_world(&mut newer, &metrics),
+            Err(MigrationError::TooNew { .. })
+        ));
+    }
+}
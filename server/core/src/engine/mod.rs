@@ -1,12 +1,57 @@
 pub mod astar;
+pub mod async_storage;
+pub mod atlas;
+pub mod audit;
+pub mod border;
+pub mod chat;
+pub mod chat_filter;
 pub mod chunk;
+pub mod chunk_store;
 pub mod chunks;
+pub mod circuits;
+pub mod claims;
 pub mod clock;
 pub mod config;
+pub mod containers;
+pub mod damage;
+pub mod difficulty;
+pub mod economy;
+pub mod edit;
 pub mod entities;
+pub mod farming;
+pub mod gamemode;
+pub mod gravity;
+pub mod journal;
 pub mod kdtree;
+pub mod knockback;
+pub mod leaves;
+pub mod locate;
+pub mod loot;
+pub mod map;
+pub mod metadata;
+pub mod migrations;
+pub mod multi_blocks;
+pub mod particles;
 pub mod physics;
+pub mod player_data;
 pub mod players;
+pub mod portals;
+pub mod profiler;
+pub mod prune;
 pub mod registry;
+pub mod remesh;
+pub mod replay;
+pub mod residency;
+pub mod rng;
+pub mod scripts;
+pub mod sounds;
 pub mod space;
+pub mod stats;
+pub mod storage;
+pub mod support;
+pub mod teams;
+pub mod teleport;
+pub mod ticker;
+pub mod transform;
+pub mod waypoints;
 pub mod world;
@@ -0,0 +1,130 @@
+use std::iter;
+
+use server_common::vec::Vec3;
+
+use crate::gen::blocks::BlockRotation;
+
+use super::{chunks::Chunks, registry::Registry};
+
+/// Blocks taking up more than one voxel, like doors and tall plants. Each part is filled with
+/// a block of its own, so it's meshed and collided with like any other block.
+pub struct MultiBlocks;
+
+impl MultiBlocks {
+    /// The voxels and blocks of the parts of a block placed at a voxel, none for most blocks
+    pub fn parts(
+        registry: &Registry,
+        id: u32,
+        origin: &Vec3<i32>,
+        y_rotation: u32,
+    ) -> Vec<(Vec3<i32>, u32)> {
+        registry
+            .get_block_by_id(id)
+            .parts
+            .iter()
+            .map(|part| {
+                let Vec3(ox, oy, oz) = Self::turn(&part.offset, y_rotation);
+                let voxel = Vec3(origin.0 + ox, origin.1 + oy, origin.2 + oz);
+                (voxel, *registry.get_id_by_name(&part.block))
+            })
+            .collect()
+    }
+
+    /// The other voxels still taken up by the block a voxel is a part of, or by the parts of
+    /// the block at it
+    pub fn linked(chunks: &Chunks, vx: i32, vy: i32, vz: i32) -> Vec<Vec3<i32>> {
+        let registry = &chunks.registry;
+        let id = chunks.get_voxel_by_voxel(vx, vy, vz);
+        let block = registry.get_block_by_id(id);
+
+        if block.parts.is_empty() && block.part_of.is_none() {
+            return vec![];
+        }
+
+        let voxel = Vec3(vx, vy, vz);
+        let (_, y_rotation) =
+            BlockRotation::decode(&chunks.get_voxel_rotation_by_voxel(vx, vy, vz));
+
+        let holds =
+            |Vec3(vx, vy, vz): &Vec3<i32>, id: u32| chunks.get_voxel_by_voxel(*vx, *vy, *vz) == id;
+
+        let origin = match &block.part_of {
+            None => Some((voxel.clone(), id)),
+            Some(name) => {
+                let &origin_id = registry.get_id_by_name(name);
+
+                registry
+                    .get_block_by_id(origin_id)
+                    .parts
+                    .iter()
+                    .filter(|part| part.block == block.name)
+                    .map(|part| {
+                        let Vec3(ox, oy, oz) = Self::turn(&part.offset, y_rotation);
+                        Vec3(vx - ox, vy - oy, vz - oz)
+                    })
+                    .find(|origin| holds(origin, origin_id))
+                    .map(|origin| (origin, origin_id))
+            }
+        };
+
+        let (origin, origin_id) = match origin {
+            Some(origin) => origin,
+            // the rest of it is gone already
+            None => return vec![],
+        };
+
+        let parts = Self::parts(registry, origin_id, &origin, y_rotation);
+
+        iter::once((origin, origin_id))
+            .chain(parts)
+            .filter(|(other, other_id)| *other != voxel && holds(other, *other_id))
+            .map(|(other, _)| other)
+            .collect()
+    }
+
+    /// An offset turned by a y rotation, in quarter turns like blocks are
+    fn turn(&[x, y, z]: &[i32; 3], y_rotation: u32) -> Vec3<i32> {
+        // y rotations are stored in eighths of a turn
+        (0..y_rotation / 2 % 4).fold(Vec3(x, y, z), |Vec3(x, y, z), _| Vec3(z, y, -x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{self, BED, BED_FOOT, STONE};
+
+    use super::*;
+
+    #[test]
+    fn parts_works() {
+        let registry = test_utils::registry();
+        let origin = Vec3(1, 4, 1);
+
+        assert_eq!(
+            MultiBlocks::parts(&registry, BED, &origin, 0),
+            vec![(Vec3(1, 4, 2), BED_FOOT)]
+        );
+
+        // a quarter turn around
+        assert_eq!(
+            MultiBlocks::parts(&registry, BED, &origin, 2),
+            vec![(Vec3(2, 4, 1), BED_FOOT)]
+        );
+        assert!(MultiBlocks::parts(&registry, STONE, &origin, 0).is_empty());
+    }
+
+    #[test]
+    fn linked_works() {
+        let mut chunks = test_utils::chunks(1, 4);
+
+        chunks.set_voxel_by_voxel(1, 4, 1, BED);
+        chunks.set_voxel_by_voxel(1, 4, 2, BED_FOOT);
+
+        assert_eq!(MultiBlocks::linked(&chunks, 1, 4, 1), vec![Vec3(1, 4, 2)]);
+        assert_eq!(MultiBlocks::linked(&chunks, 1, 4, 2), vec![Vec3(1, 4, 1)]);
+
+        // a part left behind on its own
+        chunks.set_voxel_by_voxel(1, 4, 1, STONE);
+        assert!(MultiBlocks::linked(&chunks, 1, 4, 2).is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+use server_common::vec::Vec3;
+
+use crate::network::models::ParticleProtocol;
+
+/// Kinds of particle effects clients know how to render
+///
+/// TODO: emit explosions and rain splashes once there are explosives and weather.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleType {
+    BlockBreak,
+    Explosion,
+    RainSplash,
+}
+
+impl ParticleType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParticleType::BlockBreak => "block_break",
+            ParticleType::Explosion => "explosion",
+            ParticleType::RainSplash => "rain_splash",
+        }
+    }
+}
+
+/// A burst of particles at a position, only sent to players nearby
+#[derive(Debug, Clone)]
+pub struct ParticleEvent {
+    pub r#type: ParticleType,
+    pub position: Vec3<f32>,
+    pub count: u32,
+    pub spread: f32,
+}
+
+impl ParticleEvent {
+    pub fn new(r#type: ParticleType, position: Vec3<f32>, count: u32, spread: f32) -> Self {
+        Self {
+            r#type,
+            position,
+            count,
+            spread,
+        }
+    }
+
+    /// Particles of a block being broken, centered in its voxel
+    pub fn block_break(vx: i32, vy: i32, vz: i32, dimension: f32) -> Self {
+        let position = Vec3(
+            (vx as f32 + 0.5) * dimension,
+            (vy as f32 + 0.5) * dimension,
+            (vz as f32 + 0.5) * dimension,
+        );
+
+        Self::new(ParticleType::BlockBreak, position, 12, 0.4 * dimension)
+    }
+
+    /// Convert into its protobuf format
+    pub fn to_protocol(&self) -> ParticleProtocol {
+        let Vec3(px, py, pz) = self.position;
+
+        ParticleProtocol {
+            r#type: self.r#type.as_str().to_owned(),
+            px,
+            py,
+            pz,
+            count: self.count,
+            spread: self.spread,
+        }
+    }
+}
+
+/// Resource of particle effects emitted this tick, sent out by the particles system
+pub type ParticleQueue = Vec<ParticleEvent>;
@@ -1,13 +1,25 @@
 #![allow(dead_code)]
 
-use std::sync::{Arc, Mutex};
-
 use server_common::{aabb::Aabb, math::approx_equals, vec::Vec3};
-use server_utils::sweep::sweep;
 
-use crate::comp::rigidbody::RigidBody;
+use crate::{
+    comp::rigidbody::RigidBody,
+    constants::{CLIMB_SPEED, CROUCH_DROP, MAX_PHYSICS_STEPS_PER_TICK, SWIM_ACCELERATION},
+};
 
 type TestFunction<'a> = &'a dyn Fn(i32, i32, i32) -> bool;
+type BoxesFunction<'a> = &'a dyn Fn(&Aabb) -> Vec<Aabb>;
+type SurfaceFunction<'a> = &'a dyn Fn(&Aabb) -> Surface;
+
+/// Gap under which two boxes count as touching
+const EPSILON: f32 = 1e-4;
+
+/// Friction and restitution of the block a body rests on, where set in place of the body's own
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Surface {
+    pub friction: Option<f32>,
+    pub restitution: Option<f32>,
+}
 
 /// Options to spawn in a body
 pub struct BodyOptions<'a> {
@@ -30,6 +42,53 @@ pub struct PhysicsOptions {
     pub fluid_density: f32,
 }
 
+/// Resource of the fixed steps physics runs in, which can be shorter than a tick
+///
+/// The time of each tick is accumulated and cut into as many steps as fit, carrying the rest
+/// over. Positions sent out are interpolated between the last two steps by how far into the
+/// next one the tick ended, so that stepping at a rate the ticks don't divide doesn't jitter.
+/// Without a rate of its own, physics steps once a tick.
+pub struct PhysicsStep {
+    rate: Option<f32>,
+    accumulator: f32,
+}
+
+impl PhysicsStep {
+    pub fn new(rate: Option<f32>) -> Self {
+        Self {
+            rate: rate.filter(|rate| *rate > 0.0),
+            accumulator: 0.0,
+        }
+    }
+
+    /// Add the seconds a tick took, returning how many steps are due and how long each is
+    pub fn advance(&mut self, delta: f32) -> (u32, f32) {
+        let step = match self.rate {
+            Some(rate) => 1.0 / rate,
+            None => return (1, delta),
+        };
+
+        self.accumulator += delta;
+
+        let due = (self.accumulator / step).floor() as u32;
+        if due > MAX_PHYSICS_STEPS_PER_TICK {
+            self.accumulator = 0.0;
+            return (MAX_PHYSICS_STEPS_PER_TICK, step);
+        }
+
+        self.accumulator -= due as f32 * step;
+        (due, step)
+    }
+
+    /// How far into the next step time is, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        match self.rate {
+            Some(rate) => (self.accumulator * rate).clamp(0.0, 1.0),
+            None => 1.0,
+        }
+    }
+}
+
 /// Resource for operating physics on bodies
 #[derive(Default)]
 pub struct Physics {
@@ -46,8 +105,9 @@ impl Physics {
         &self,
         b: &mut RigidBody,
         dt: f32,
-        test_solid: TestFunction,
+        get_boxes: BoxesFunction,
         test_fluid: TestFunction,
+        get_surface: SurfaceFunction,
     ) {
         let no_gravity = approx_equals(&0.0, &self.options.gravity.len().powi(2));
 
@@ -66,22 +126,28 @@ impl Physics {
         }
 
         // skip bodies if static or no velocity/forces/impulses
-        let local_no_grav = no_gravity || approx_equals(&b.gravity_multiplier, &0.0);
-        if self.body_asleep(b, &dt, &local_no_grav, &test_solid) {
+        let local_no_grav = no_gravity || approx_equals(&b.gravity_multiplier, &0.0) || b.no_clip;
+        if self.body_asleep(b, &dt, &local_no_grav, &get_boxes) {
             return;
         }
         b.sleep_frame_count -= 1;
 
         // check if under water, if so apply buoyancy and drag forces
-        self.apply_fluid_forces(b, &test_fluid);
+        if b.no_clip {
+            b.in_fluid = false;
+            b.ratio_in_fluid = 0.0;
+        } else {
+            self.apply_fluid_forces(b, &test_fluid);
+        }
 
         // semi-implicit Euler integration
 
         // a = f/m + gravity * gravity_multiplier
+        let gravity_multiplier = if b.no_clip { 0.0 } else { b.gravity_multiplier };
         let a = b
             .forces
             .scale(1.0 / b.mass)
-            .scale_and_add(&self.options.gravity, b.gravity_multiplier);
+            .scale_and_add(&self.options.gravity, gravity_multiplier);
 
         // dv = i/m + a*dt
         // v1 = v0 + dv
@@ -89,11 +155,12 @@ impl Physics {
         let dv = dv.scale_and_add(&a, dt);
         b.velocity = b.velocity.add(&dv);
 
-        // apply friction based on change in velocity this frame
-        if !approx_equals(&b.friction, &0.0) {
-            self.apply_friction_by_axis(0, b, &dv);
-            self.apply_friction_by_axis(1, b, &dv);
-            self.apply_friction_by_axis(2, b, &dv);
+        // apply friction based on change in velocity this frame, ice and such slide more
+        let friction = get_surface(&b.aabb).friction.unwrap_or(b.friction);
+        if !approx_equals(&friction, &0.0) {
+            self.apply_friction_by_axis(0, b, &dv, friction);
+            self.apply_friction_by_axis(1, b, &dv, friction);
+            self.apply_friction_by_axis(2, b, &dv, friction);
         }
 
         // linear air or fluid friction - effectively v *= drag;
@@ -114,8 +181,24 @@ impl Physics {
         let mult = (1.0 - (drag * dt) / b.mass).max(0.0);
         b.velocity = b.velocity.scale(mult);
 
-        // x1-x0 = v1*dt
-        let dx = b.velocity.scale(dt);
+        // climbing up when pushing against something, otherwise sliding down slowly
+        if b.modifiers.climbable {
+            let pushing =
+                !approx_equals(&b.resting[0], &0.0) || !approx_equals(&b.resting[2], &0.0);
+            b.velocity[1] = if pushing {
+                b.velocity[1].max(CLIMB_SPEED)
+            } else {
+                b.velocity[1].max(-CLIMB_SPEED)
+            };
+        }
+
+        // x1-x0 = v1*dt, slowed down by blocks like cobwebs
+        let mut dx = b.velocity.scale(dt * b.modifiers.speed_multiplier);
+
+        // crouching on the ground, bodies stop at edges rather than walking off of them
+        if b.crouching && !b.no_clip && old_resting[1] < 0.0 {
+            self.stop_at_edges(b, &mut dx, &get_boxes);
+        }
 
         // clear forces and impulses for next timestep
         b.forces.set(0.0, 0.0, 0.0);
@@ -128,13 +211,18 @@ impl Physics {
             None
         };
 
-        // sweeps aabb along dx and accounts for collisions
-        self.process_collisions(&mut b.aabb, &dx, &mut b.resting, &test_solid);
+        // moves aabb along dx and accounts for collisions, unless flying through everything
+        if b.no_clip {
+            b.resting.set(0.0, 0.0, 0.0);
+            b.aabb.translate(&dx);
+        } else {
+            self.process_collisions(&mut b.aabb, &dx, &mut b.resting, &get_boxes);
+        }
 
         // if autostep, and on ground, run collisions again with stepped up aabb
-        if b.auto_step {
+        if b.auto_step && !b.no_clip {
             let mut tmp_box = tmp_box.unwrap();
-            self.try_auto_stepping(b, &mut tmp_box, &dx, &test_solid);
+            self.try_auto_stepping(b, &mut tmp_box, &dx, &get_boxes);
         }
 
         let mut impacts = Vec3::default();
@@ -160,9 +248,11 @@ impl Physics {
             impacts = impacts.scale(b.mass);
             b.collided = Some(impacts.clone());
 
-            // bounce depending on restitution and min_bounce_impulse
-            if b.restitution > 0.0 && mag > self.options.min_bounce_impulse {
-                impacts = impacts.scale(b.restitution);
+            // bounce depending on restitution, of whatever was landed on if it has its own,
+            // and min_bounce_impulse
+            let restitution = get_surface(&b.aabb).restitution.unwrap_or(b.restitution);
+            if restitution > 0.0 && mag > self.options.min_bounce_impulse {
+                impacts = impacts.scale(restitution);
                 b.apply_impulse(&impacts);
             }
         }
@@ -176,8 +266,8 @@ impl Physics {
 
     fn apply_fluid_forces(&self, body: &mut RigidBody, test_fluid: TestFunction) {
         let aabb = &body.aabb;
-        let cx = aabb.base[0].floor() as i32;
-        let cz = aabb.base[2].floor() as i32;
+        let cx = ((aabb.base[0] + aabb.max[0]) / 2.0).floor() as i32;
+        let cz = ((aabb.base[2] + aabb.max[2]) / 2.0).floor() as i32;
         let y0 = aabb.base[1].floor() as i32;
         let y1 = aabb.max[1].floor() as i32;
 
@@ -209,11 +299,23 @@ impl Physics {
             .scale(-self.options.fluid_density * displaced);
         body.apply_force(&fluid_vec);
 
+        // swimming up, pushing harder the deeper the body is
+        if body.swim_up {
+            let swim = Vec3(0.0, SWIM_ACCELERATION * body.mass * ratio_in_fluid, 0.0);
+            body.apply_force(&swim);
+        }
+
         body.in_fluid = true;
         body.ratio_in_fluid = ratio_in_fluid;
     }
 
-    fn apply_friction_by_axis(&self, axis: usize, body: &mut RigidBody, dvel: &Vec3<f32>) {
+    fn apply_friction_by_axis(
+        &self,
+        axis: usize,
+        body: &mut RigidBody,
+        dvel: &Vec3<f32>,
+        friction: f32,
+    ) {
         // friction applies only if moving into a touched surface
         let rest_dir = body.resting[axis];
         let v_normal = dvel[axis];
@@ -238,7 +340,7 @@ impl Physics {
         //        dvF = dt * Ff / m
         //            = dt * (u * m * dvnormal / dt) / m
         //            = u * dvnormal
-        let dv_max = (body.friction * v_normal).abs();
+        let dv_max = (friction * v_normal).abs();
 
         // decrease lateral vel by dv_max (or clamp to zero)
         let scaler = if v_curr > dv_max {
@@ -256,32 +358,38 @@ impl Physics {
         aabb: &mut Aabb,
         velocity: &Vec3<f32>,
         resting: &mut Vec3<f32>,
-        test_solid: TestFunction,
-    ) -> f32 {
+        get_boxes: BoxesFunction,
+    ) {
         resting.set(0.0, 0.0, 0.0);
 
-        // temporarily set axis to something impossible, just for safety
-        let d = Arc::new(Mutex::new((10, 0)));
-        let temp = d.clone();
+        let boxes = get_boxes(&Self::swept_region(aabb, velocity));
+        let mut velocity = velocity.clone();
 
-        let dist = sweep(
-            test_solid,
-            aabb,
-            velocity,
-            &mut move |_, axis: usize, dir: i32, vec: &mut Vec3<f32>| {
-                *temp.lock().unwrap() = (axis, dir);
-                vec[axis] = 0.0;
-                false
-            },
-            false,
-        );
+        // moving farther than its own size, a body could hop past thin boxes one axis at a
+        // time, so it's first moved up to whatever it would hit along the way
+        if (0..3).any(|i| velocity[i].abs() > aabb.vec[i]) {
+            if let Some((time, axis)) = Self::first_impact(aabb, &boxes, &velocity) {
+                aabb.translate(&velocity.scale(time));
 
-        let (axis, dir) = *d.lock().unwrap();
-        if axis != 10 {
-            resting[axis] = dir as f32;
+                resting[axis] = velocity[axis].signum();
+                velocity = velocity.scale(1.0 - time);
+                velocity[axis] = 0.0;
+            }
         }
 
-        dist
+        // resolve vertical movement first so bodies land before sliding
+        for &axis in &[1, 0, 2] {
+            let wanted = velocity[axis];
+            let moved = Self::clip_axis(aabb, &boxes, axis, wanted);
+
+            if !approx_equals(&moved, &wanted) {
+                resting[axis] = wanted.signum();
+            }
+
+            let mut delta = Vec3::default();
+            delta[axis] = moved;
+            aabb.translate(&delta);
+        }
     }
 
     fn try_auto_stepping(
@@ -289,7 +397,7 @@ impl Physics {
         b: &mut RigidBody,
         old_aabb: &mut Aabb,
         dx: &Vec3<f32>,
-        test_solid: TestFunction,
+        get_boxes: BoxesFunction,
     ) {
         // in the air
         if b.resting[1] >= 0.0 && !b.in_fluid {
@@ -313,45 +421,32 @@ impl Physics {
         // original target position before being obstructed
         let target_pos = old_aabb.base.add(&dx);
 
-        // move towards the target until the first x/z collision
-        sweep(
-            test_solid,
-            old_aabb,
-            dx,
-            &mut move |_, axis, _, vec| {
-                if axis == 1 {
-                    vec[axis] = 0.0;
-                    return false;
-                }
-                true
-            },
-            false,
-        );
-
-        let y = b.aabb.base[1];
-        let y_dist = (y + 1.001).floor() - y;
-        let upvec = Vec3(0.0, y_dist, 0.0);
-        let collided = Arc::new(Mutex::new(false));
-        let temp = collided.clone();
-        sweep(
-            test_solid,
+        let mut region = Self::swept_region(old_aabb, dx);
+        region = region.expand(&Self::swept_region(
             old_aabb,
-            &upvec,
-            &mut move |_, _, _, _| {
-                *temp.lock().unwrap() = true;
-                true
-            },
-            false,
-        );
-        if *collided.lock().unwrap() {
+            &Vec3(0.0, b.step_height, 0.0),
+        ));
+        let boxes = get_boxes(&region);
+
+        // lift the body as far as the step height, bailing if something is right above
+        let up = Self::clip_axis(old_aabb, &boxes, 1, b.step_height);
+        if up <= EPSILON {
             return;
         }
+        old_aabb.translate(&Vec3(0.0, up, 0.0));
 
         // now move in x/z however far was left over before hitting the obstruction
-        let mut leftover = target_pos.sub(&old_aabb.base);
-        leftover[1] = 0.0;
         let mut tmp_resting = Vec3::default();
-        self.process_collisions(old_aabb, &leftover, &mut tmp_resting, test_solid);
+        for &axis in &[0, 2] {
+            let moved = Self::clip_axis(old_aabb, &boxes, axis, dx[axis]);
+            if !approx_equals(&moved, &dx[axis]) {
+                tmp_resting[axis] = dx[axis].signum();
+            }
+
+            let mut delta = Vec3::default();
+            delta[axis] = moved;
+            old_aabb.translate(&delta);
+        }
 
         // bail if no movement happened in the originally blocked direction
         if x_blocked && !approx_equals(&old_aabb.base[0], &target_pos[0]) {
@@ -361,6 +456,10 @@ impl Physics {
             return;
         }
 
+        // settle back down onto whatever was stepped onto
+        let down = Self::clip_axis(old_aabb, &boxes, 1, -up);
+        old_aabb.translate(&Vec3(0.0, down, 0.0));
+
         // done - oldBox is now at the target auto-stepped position
         b.aabb.copy(old_aabb);
         b.resting[0] = tmp_resting[0];
@@ -368,12 +467,49 @@ impl Physics {
         b.stepped = true;
     }
 
+    /// Keep a body from moving along x or z where there'd be nothing under it to stand on
+    fn stop_at_edges(&self, b: &mut RigidBody, dx: &mut Vec3<f32>, get_boxes: BoxesFunction) {
+        let aabb = b.aabb.clone();
+        let has_ground = |offset: &Vec3<f32>| {
+            let mut feet = aabb.clone();
+            feet.translate(offset);
+
+            let below = Aabb::new(
+                &Vec3(feet.base.0, feet.base.1 - CROUCH_DROP, feet.base.2),
+                &Vec3(feet.vec.0, CROUCH_DROP, feet.vec.2),
+            );
+
+            get_boxes(&below).iter().any(|other| {
+                (0..3).all(|i| other.max[i] > below.base[i] + EPSILON)
+                    && (0..3).all(|i| other.base[i] < below.max[i] - EPSILON)
+            })
+        };
+
+        for &axis in &[0, 2] {
+            let mut offset = Vec3::default();
+            offset[axis] = dx[axis];
+
+            if !approx_equals(&dx[axis], &0.0) && !has_ground(&offset) {
+                dx[axis] = 0.0;
+                b.velocity[axis] = 0.0;
+            }
+        }
+
+        // each on its own might still be fine while both together go over a corner
+        if !has_ground(&Vec3(dx[0], 0.0, dx[2])) {
+            dx[0] = 0.0;
+            dx[2] = 0.0;
+            b.velocity[0] = 0.0;
+            b.velocity[2] = 0.0;
+        }
+    }
+
     fn body_asleep(
         &self,
         body: &mut RigidBody,
         dt: &f32,
         no_gravity: &bool,
-        test_solid: TestFunction,
+        get_boxes: BoxesFunction,
     ) -> bool {
         if body.sleep_frame_count > 0 {
             return false;
@@ -385,26 +521,318 @@ impl Physics {
         }
 
         // otherwise check body is resting against something
-        // i.e. sweep along by distance d = 1/2 g*t^2
+        // i.e. move along by distance d = 1/2 g*t^2
         // and check there's still collision
         let g_mult = 0.5 * dt * dt * body.gravity_multiplier;
         let sleep_vec = self.options.gravity.scale(g_mult);
 
-        let is_resting = Arc::new(Mutex::new(false));
-        let temp = is_resting.clone();
-
-        sweep(
-            test_solid,
-            &mut body.aabb,
-            &sleep_vec,
-            &mut move |_, _, _, _| {
-                *temp.lock().unwrap() = true;
-                true
-            },
-            true,
-        );
-
-        let result = *is_resting.lock().unwrap();
-        result
+        let boxes = get_boxes(&Self::swept_region(&body.aabb, &sleep_vec));
+
+        (0..3).any(|axis| {
+            let moved = Self::clip_axis(&body.aabb, &boxes, axis, sleep_vec[axis]);
+            !approx_equals(&moved, &sleep_vec[axis])
+        })
+    }
+
+    /// The region an aabb covers while moving along `delta`
+    fn swept_region(aabb: &Aabb, delta: &Vec3<f32>) -> Aabb {
+        let mut moved = aabb.clone();
+        moved.translate(delta);
+
+        let mut region = aabb.clone();
+        region.expand(&moved)
+    }
+
+    /// When an aabb moving along `delta` first hits any of the boxes, as the fraction of
+    /// `delta` it gets through along with the axis it hits on
+    fn first_impact(aabb: &Aabb, boxes: &[Aabb], delta: &Vec3<f32>) -> Option<(f32, usize)> {
+        let mut first: Option<(f32, usize)> = None;
+
+        'boxes: for other in boxes {
+            let mut enter = (f32::NEG_INFINITY, 0);
+            let mut exit = f32::INFINITY;
+
+            for axis in 0..3 {
+                let d = delta[axis];
+
+                if approx_equals(&d, &0.0) {
+                    // not moving on this axis, so it has to overlap already
+                    if other.max[axis] <= aabb.base[axis] + EPSILON
+                        || other.base[axis] >= aabb.max[axis] - EPSILON
+                    {
+                        continue 'boxes;
+                    }
+                    continue;
+                }
+
+                let (near, far) = if d > 0.0 {
+                    (
+                        other.base[axis] - aabb.max[axis],
+                        other.max[axis] - aabb.base[axis],
+                    )
+                } else {
+                    (
+                        other.max[axis] - aabb.base[axis],
+                        other.base[axis] - aabb.max[axis],
+                    )
+                };
+
+                if near / d > enter.0 {
+                    enter = (near / d, axis);
+                }
+                exit = exit.min(far / d);
+            }
+
+            // boxes already overlapped are left to be clipped axis by axis
+            let (time, axis) = enter;
+            if !(0.0..1.0).contains(&time) || time >= exit {
+                continue;
+            }
+
+            if first.map_or(true, |(t, _)| time < t) {
+                first = Some((time, axis));
+            }
+        }
+
+        first
+    }
+
+    /// How far an aabb can move along an axis before hitting any of the boxes
+    fn clip_axis(aabb: &Aabb, boxes: &[Aabb], axis: usize, delta: f32) -> f32 {
+        let mut delta = delta;
+
+        for other in boxes {
+            // only boxes overlapping on the other two axes can block this one
+            let overlaps = (0..3).filter(|&i| i != axis).all(|i| {
+                other.max[i] > aabb.base[i] + EPSILON && other.base[i] < aabb.max[i] - EPSILON
+            });
+
+            if !overlaps {
+                continue;
+            }
+
+            if delta > 0.0 && other.base[axis] >= aabb.max[axis] - EPSILON {
+                delta = delta.min(other.base[axis] - aabb.max[axis]).max(0.0);
+            } else if delta < 0.0 && other.max[axis] <= aabb.base[axis] + EPSILON {
+                delta = delta.max(other.max[axis] - aabb.base[axis]).min(0.0);
+            }
+        }
+
+        delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constants::CROUCH_HEIGHT;
+
+    use super::*;
+
+    #[test]
+    fn clip_axis_works() {
+        let body = Aabb::new(&Vec3(0.2, 1.0, 0.2), &Vec3(0.6, 1.8, 0.6));
+        let slab = Aabb::new(&Vec3(0.0, 0.0, 0.0), &Vec3(1.0, 0.5, 1.0));
+        let fence = Aabb::new(&Vec3(1.375, 0.0, 0.375), &Vec3(0.25, 1.5, 0.25));
+        let boxes = vec![slab, fence];
+
+        // lands on the slab's top half way down the voxel
+        assert!(approx_equals(
+            &Physics::clip_axis(&body, &boxes, 1, -1.0),
+            &-0.5
+        ));
+
+        // the fence post reaches above its voxel, blocking the body sideways
+        let moved = Physics::clip_axis(&body, &boxes, 0, 1.0);
+        assert!(approx_equals(&moved, &0.575));
+
+        // nothing blocks moving up
+        assert!(approx_equals(
+            &Physics::clip_axis(&body, &boxes, 1, 2.0),
+            &2.0
+        ));
+    }
+
+    fn make_physics() -> Physics {
+        Physics::new(PhysicsOptions {
+            gravity: Vec3(0.0, -24.0, 0.0),
+            ..Default::default()
+        })
+    }
+
+    fn make_body() -> RigidBody {
+        let aabb = Aabb::new(&Vec3(0.2, 10.0, 0.2), &Vec3(0.6, 1.8, 0.6));
+        RigidBody::new(aabb, 1.6, 1.0, 1.0, 0.0, 1.0, false)
+    }
+
+    fn no_surface(_: &Aabb) -> Surface {
+        Surface::default()
+    }
+
+    #[test]
+    fn ladder_ascent_works() {
+        let physics = make_physics();
+
+        // a wall right in front of the ladder
+        let wall = |region: &Aabb| {
+            let wall = Aabb::new(&Vec3(0.8, 0.0, 0.0), &Vec3(1.0, 100.0, 1.0));
+            if wall.intersects(region) {
+                vec![wall]
+            } else {
+                vec![]
+            }
+        };
+        let no_fluid = |_, _, _| false;
+
+        let mut body = make_body();
+        body.modifiers.climbable = true;
+
+        for _ in 0..30 {
+            body.apply_force(&Vec3(20.0, 0.0, 0.0));
+            physics.iterate_body(&mut body, 1.0 / 60.0, &wall, &no_fluid, &no_surface);
+        }
+
+        assert!(body.aabb.base.1 > 10.5);
+    }
+
+    #[test]
+    fn web_slowdown_works() {
+        let physics = make_physics();
+
+        let nothing = |_: &Aabb| vec![];
+        let no_fluid = |_, _, _| false;
+
+        let mut free = make_body();
+        let mut webbed = make_body();
+        webbed.modifiers.speed_multiplier = 0.25;
+
+        for _ in 0..30 {
+            physics.iterate_body(&mut free, 1.0 / 60.0, &nothing, &no_fluid, &no_surface);
+            physics.iterate_body(&mut webbed, 1.0 / 60.0, &nothing, &no_fluid, &no_surface);
+        }
+
+        let free_fall = 10.0 - free.aabb.base.1;
+        let webbed_fall = 10.0 - webbed.aabb.base.1;
+        assert!(approx_equals(&(free_fall * 0.25), &webbed_fall));
+    }
+
+    #[test]
+    fn swimming_works() {
+        let physics = Physics::new(PhysicsOptions {
+            gravity: Vec3(0.0, -24.0, 0.0),
+            fluid_drag: 0.4,
+            fluid_density: 2.0,
+            ..Default::default()
+        });
+
+        let nothing = |_: &Aabb| vec![];
+        let deep_water = |_, y, _| y < 20;
+
+        let mut floating = make_body();
+        let mut swimming = make_body();
+        swimming.swim_up = true;
+
+        for _ in 0..30 {
+            physics.iterate_body(
+                &mut floating,
+                1.0 / 60.0,
+                &nothing,
+                &deep_water,
+                &no_surface,
+            );
+            physics.iterate_body(
+                &mut swimming,
+                1.0 / 60.0,
+                &nothing,
+                &deep_water,
+                &no_surface,
+            );
+        }
+
+        assert!(floating.in_fluid && swimming.in_fluid);
+        assert!(swimming.aabb.base.1 > floating.aabb.base.1);
+    }
+
+    #[test]
+    fn physics_step_works() {
+        let mut once = PhysicsStep::new(None);
+        assert_eq!(once.advance(0.05), (1, 0.05));
+        assert!(approx_equals(&once.alpha(), &1.0));
+
+        // three steps of 60Hz fit into a 20Hz tick, with a little carried over
+        let mut step = PhysicsStep::new(Some(60.0));
+        let (steps, secs) = step.advance(0.055);
+        assert_eq!(steps, 3);
+        assert!(approx_equals(&secs, &(1.0 / 60.0)));
+        assert!((step.alpha() - 0.3).abs() < 1e-3);
+
+        // a stalled tick doesn't spiral into more steps than allowed
+        assert_eq!(step.advance(10.0).0, MAX_PHYSICS_STEPS_PER_TICK);
+        assert!(approx_equals(&step.alpha(), &0.0));
+    }
+
+    #[test]
+    fn fast_bodies_dont_tunnel() {
+        let physics = make_physics();
+        let wall = Aabb::new(&Vec3(2.0, 0.0, -1.0), &Vec3(0.1, 3.0, 2.0));
+        let boxes = |_: &Aabb| vec![wall.clone()];
+
+        // moving up first would have carried the arrow over the wall
+        let mut arrow = Aabb::new(&Vec3(0.0, 0.0, 0.0), &Vec3(0.25, 0.25, 0.25));
+        let mut resting = Vec3::default();
+        physics.process_collisions(&mut arrow, &Vec3(4.0, 4.0, 0.0), &mut resting, &boxes);
+
+        assert!(arrow.max.0 <= 2.0 + EPSILON);
+        assert!(approx_equals(&resting.0, &1.0));
+    }
+
+    #[test]
+    fn bouncy_surfaces_work() {
+        let physics = make_physics();
+
+        let floor = |_: &Aabb| vec![Aabb::new(&Vec3(-10.0, 8.0, -10.0), &Vec3(20.0, 1.0, 20.0))];
+        let no_fluid = |_, _, _| false;
+        let slime = |_: &Aabb| Surface {
+            restitution: Some(0.8),
+            ..Default::default()
+        };
+
+        let bounces = |get_surface: SurfaceFunction| {
+            let mut body = make_body();
+            (0..60).any(|_| {
+                physics.iterate_body(&mut body, 1.0 / 60.0, &floor, &no_fluid, get_surface);
+                body.velocity.1 > 1.0
+            })
+        };
+
+        assert!(bounces(&slime));
+        assert!(!bounces(&no_surface));
+    }
+
+    #[test]
+    fn crouching_stops_at_edges() {
+        let physics = make_physics();
+
+        let ledge = |_: &Aabb| vec![Aabb::new(&Vec3(-10.0, 9.0, -10.0), &Vec3(11.0, 1.0, 20.0))];
+        let no_fluid = |_, _, _| false;
+
+        let walk_off = |crouching: bool| {
+            let mut body = make_body();
+            body.friction = 0.0;
+            body.set_crouching(crouching);
+
+            for _ in 0..120 {
+                body.apply_force(&Vec3(10.0, 0.0, 0.0));
+                physics.iterate_body(&mut body, 1.0 / 60.0, &ledge, &no_fluid, &no_surface);
+            }
+
+            body
+        };
+
+        let crouched = walk_off(true);
+        assert!(crouched.aabb.base.0 < 1.0);
+        assert!(approx_equals(&crouched.aabb.base.1, &10.0));
+        assert!(approx_equals(&crouched.aabb.vec.1, &(1.8 * CROUCH_HEIGHT)));
+
+        assert!(walk_off(false).aabb.base.1 < 10.0);
     }
 }
@@ -0,0 +1,52 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use server_common::vec::Vec3;
+
+use crate::comp::inventory::Inventory;
+
+use super::{
+    gamemode::{Abilities, GameMode},
+    storage::Storage,
+    waypoints::Waypoint,
+};
+
+/// What a world remembers about a logged in player between sessions, keyed by their UUID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerData {
+    pub position: Vec3<f32>,
+    pub inventory: Inventory,
+    pub game_mode: GameMode,
+    /// Missing from data saved before abilities could be granted
+    #[serde(default)]
+    pub abilities: Abilities,
+    #[serde(default)]
+    pub experience: u32,
+    /// Coins held, see `Economy`
+    #[serde(default)]
+    pub balance: u64,
+    #[serde(default)]
+    pub team: Option<String>,
+    #[serde(default)]
+    pub waypoints: Vec<Waypoint>,
+}
+
+impl PlayerData {
+    /// Load a player's data, None if they've never been in this world
+    pub fn load(storage: &dyn Storage, uuid: &Uuid) -> Option<Self> {
+        match serde_json::from_value(storage.load_player(uuid)?) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                warn!("Unable to read player data of {}: {}", uuid, e);
+                None
+            }
+        }
+    }
+
+    /// The player's data ready to be saved to a world's storage
+    pub fn to_saved(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
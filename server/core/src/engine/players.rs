@@ -1,12 +1,13 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     usize,
 };
 
 use actix::Recipient;
 use specs::Entity;
+use uuid::Uuid;
 
-use super::super::network::{message, models::messages};
+use super::super::network::{message, models::messages, outbox::SharedOutbox};
 
 use server_common::vec::Vec2;
 
@@ -19,13 +20,53 @@ pub type PlayerUpdates = HashMap<usize, messages::Peer>;
 pub struct Player {
     pub entity: Entity,
     pub name: Option<String>,
-    pub addr: Recipient<message::Message>,
+    pub addr: Recipient<message::Flush>,
+    pub outbox: SharedOutbox,
+    /// Persistent identity of a logged in player, None if anonymous
+    pub uuid: Option<Uuid>,
+    /// Whether the player may run admin commands
+    pub admin: bool,
+    /// Whether an admin builds in claims as if they were trusted, toggled with `/claimoverride`
+    pub bypass_claims: bool,
+    /// Round trip time of the player's connection in milliseconds, None until measured
+    pub latency: Option<u32>,
     pub requested_chunks: VecDeque<Vec2<i32>>,
+    /// Requested chunks the player was told are pending, so they're only told once
+    pub pending_chunks: HashSet<Vec2<i32>>,
+    /// Chunks past the player's meshes requested as far terrain
+    pub requested_horizons: VecDeque<Vec2<i32>>,
+}
+
+impl Player {
+    /// Queue a message on the player's connection, waking it up to write it out
+    ///
+    /// Returns false if the connection is gone or has fallen too far behind.
+    pub fn send(&self, msg: &messages::Message) -> bool {
+        let (queued, wake) = {
+            let mut outbox = self.outbox.lock().unwrap();
+            (outbox.push(msg.to_owned()), outbox.wake())
+        };
+
+        let awake = !wake || self.addr.do_send(message::Flush).is_ok();
+
+        queued && awake
+    }
 }
 
 /// Resource to store all server-side players in a HashMap
 pub type Players = HashMap<usize, Player>;
 
+/// Whether a player other than `except` goes by a name, ignoring case
+pub fn is_name_taken(players: &Players, name: &str, except: usize) -> bool {
+    players.iter().any(|(&id, player)| {
+        id != except
+            && player
+                .name
+                .as_ref()
+                .map_or(false, |taken| taken.eq_ignore_ascii_case(name))
+    })
+}
+
 pub trait BroadcastExt {
     fn broadcast(
         &mut self,
@@ -58,11 +99,7 @@ impl BroadcastExt for Players {
         if !include.is_empty() {
             for id in include.iter() {
                 if let Some(player) = self.get(id) {
-                    if player
-                        .addr
-                        .do_send(message::Message(msg.to_owned()))
-                        .is_err()
-                    {
+                    if !player.send(msg) {
                         resting_players.push(*id);
                     }
                 }
@@ -73,11 +110,7 @@ impl BroadcastExt for Players {
                     continue;
                 }
 
-                if player
-                    .addr
-                    .do_send(message::Message(msg.to_owned()))
-                    .is_err()
-                {
+                if !player.send(msg) {
                     resting_players.push(*id);
                 }
             }
@@ -0,0 +1,168 @@
+use server_common::vec::Vec3;
+use server_utils::convert::{map_voxel_to_chunk, map_world_to_voxel};
+
+use super::super::constants::{PORTAL_SEARCH_RADIUS, TELEPORT_LOAD_RADIUS};
+
+use super::{chunks::Chunks, teleport::Teleport};
+
+/// What walked into a portal
+#[derive(Debug, Clone)]
+pub enum Traveler {
+    /// A player, by id
+    Player(usize),
+    /// An entity, by type. Entities are re-spawned in the other world.
+    Entity(String),
+}
+
+/// A traveler waiting to be moved to another world by the server
+#[derive(Debug, Clone)]
+pub struct PortalTransfer {
+    pub traveler: Traveler,
+    pub world: String,
+    /// Position in the destination world, before linking to a portal there
+    pub position: Vec3<f32>,
+}
+
+/// Resource of travelers that went through a portal this tick
+pub type PortalQueue = Vec<PortalTransfer>;
+
+/// Linking portals across worlds.
+///
+/// A portal leads to the world set by `portal_to` in the world config, scaling the
+/// horizontal coordinates by `portal_scale`. On arrival, the closest portal around the
+/// scaled position is used, or a new one is built if there's none.
+pub struct Portals;
+
+impl Portals {
+    /// Map a position to the coordinates of the linked world
+    pub fn scale(position: &Vec3<f32>, scale: f32) -> Vec3<f32> {
+        Vec3(position.0 * scale, position.1, position.2 * scale)
+    }
+
+    /// Find the bottom voxel of the closest portal around a voxel, in loaded chunks only
+    pub fn find(chunks: &Chunks, voxel: &Vec3<i32>, radius: i32) -> Option<Vec3<i32>> {
+        let max_height = chunks.config.max_height as i32;
+        let Vec3(vx, vy, vz) = voxel;
+
+        let mut closest: Option<(Vec3<i32>, i32)> = None;
+
+        for x in -radius..=radius {
+            for z in -radius..=radius {
+                let (nvx, nvz) = (vx + x, vz + z);
+
+                if chunks.get_chunk_by_voxel(nvx, 0, nvz).is_none() {
+                    continue;
+                }
+
+                for nvy in 1..max_height {
+                    let id = chunks.get_voxel_by_voxel(nvx, nvy, nvz);
+                    let below = chunks.get_voxel_by_voxel(nvx, nvy - 1, nvz);
+
+                    if !chunks.registry.is_portal(id) || chunks.registry.is_portal(below) {
+                        continue;
+                    }
+
+                    let dy = nvy - vy;
+                    let dist = x * x + dy * dy + z * z;
+
+                    if closest.as_ref().map(|(_, d)| dist < *d).unwrap_or(true) {
+                        closest = Some((Vec3(nvx, nvy, nvz), dist));
+                    }
+                }
+            }
+        }
+
+        closest.map(|(voxel, _)| voxel)
+    }
+
+    /// Build a portal standing on a voxel: two portal blocks framed by obsidian, facing the z-axis.
+    ///
+    /// Returns the voxels that changed.
+    pub fn build(chunks: &mut Chunks, vx: i32, vy: i32, vz: i32) -> Vec<Vec3<i32>> {
+        let &portal = chunks.registry.get_id_by_name("Portal");
+        let &obsidian = chunks.registry.get_id_by_name("Obsidian");
+
+        let mut voxels = vec![
+            (Vec3(vx, vy - 1, vz), obsidian),
+            (Vec3(vx, vy + 2, vz), obsidian),
+            (Vec3(vx, vy, vz), portal),
+            (Vec3(vx, vy + 1, vz), portal),
+        ];
+
+        for y in vy - 1..=vy + 2 {
+            voxels.push((Vec3(vx - 1, y, vz), obsidian));
+            voxels.push((Vec3(vx + 1, y, vz), obsidian));
+        }
+
+        let max_height = chunks.config.max_height as i32;
+
+        voxels
+            .into_iter()
+            .filter(|(Vec3(_, vy, _), _)| *vy >= 0 && *vy < max_height)
+            .map(|(voxel, id)| {
+                let Vec3(vx, vy, vz) = voxel;
+
+                // freshly generated chunks get their lights calculated once meshed
+                let needs_propagation = chunks
+                    .get_chunk_by_voxel(vx, vy, vz)
+                    .map(|chunk| chunk.needs_propagation)
+                    .unwrap_or(true);
+
+                if needs_propagation {
+                    chunks.set_voxel_by_voxel(vx, vy, vz, id);
+                } else {
+                    chunks.update(vx, vy, vz, id, 0, 0);
+                }
+
+                voxel
+            })
+            .collect()
+    }
+
+    /// Link a position in this world to a portal, building one if none is around.
+    ///
+    /// Returns where the traveler should arrive, along with the voxels that changed.
+    pub fn link(chunks: &mut Chunks, position: &Vec3<f32>) -> (Vec3<f32>, Vec<Vec3<i32>>) {
+        let dimension = chunks.config.dimension;
+        let max_height = chunks.config.max_height as i32;
+
+        let Vec3(vx, vy, vz) = map_world_to_voxel(position.0, position.1, position.2, dimension);
+        let vy = vy.max(1).min(max_height - 1);
+
+        let coords = map_voxel_to_chunk(vx, vy, vz, chunks.config.chunk_size);
+        chunks.generate(&coords, TELEPORT_LOAD_RADIUS, true);
+
+        let (Vec3(vx, vy, vz), changed) =
+            match Portals::find(chunks, &Vec3(vx, vy, vz), PORTAL_SEARCH_RADIUS) {
+                Some(portal) => (portal, vec![]),
+                None => {
+                    let vy = Teleport::find_height(chunks, vx, max_height - 2, vz)
+                        .unwrap_or_else(|| chunks.get_max_height(vx, vz) as i32 + 1);
+                    (Vec3(vx, vy, vz), Portals::build(chunks, vx, vy, vz))
+                }
+            };
+
+        let dimension = dimension as f32;
+        let arrival = Vec3(
+            (vx as f32 + 0.5) * dimension,
+            vy as f32 * dimension,
+            (vz as f32 + 0.5) * dimension,
+        );
+
+        (arrival, changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_works() {
+        let nether = Portals::scale(&Vec3(80.0, 40.0, -16.0), 0.125);
+        assert_eq!(nether, Vec3(10.0, 40.0, -2.0));
+
+        let overworld = Portals::scale(&nether, 8.0);
+        assert_eq!(overworld, Vec3(80.0, 40.0, -16.0));
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+
+use tracing_chrome::{ChromeLayer, ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::{layer::SubscriberExt, reload, Registry};
+
+type ChromeHandle = reload::Handle<Option<ChromeLayer<Registry>>, Registry>;
+
+#[derive(Debug)]
+pub enum ProfilerError {
+    Unavailable,
+    AlreadyRunning(PathBuf),
+    NotRunning,
+    Io(String),
+}
+
+impl std::fmt::Display for ProfilerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProfilerError::Unavailable => write!(f, "Profiling is unavailable on this server."),
+            ProfilerError::AlreadyRunning(path) => {
+                write!(f, "Already profiling to {}.", path.display())
+            }
+            ProfilerError::NotRunning => write!(f, "No profile is running."),
+            ProfilerError::Io(err) => write!(f, "Could not start profiling: {}", err),
+        }
+    }
+}
+
+/// Exports the server's tracing spans as a chrome trace while a profile is running.
+///
+/// The tracing subscriber is process-wide, so every world shares the same profiler and
+/// a running profile records all of them. Open the trace in `chrome://tracing` or
+/// Perfetto, or fold it into a flamegraph.
+#[derive(Clone)]
+pub struct Profiler {
+    handle: Option<ChromeHandle>,
+    session: Arc<Mutex<Option<(PathBuf, FlushGuard)>>>,
+}
+
+impl Profiler {
+    /// Install the tracing subscriber with an idle chrome layer to be swapped in later
+    pub fn install() -> Self {
+        let (layer, handle) = reload::Layer::new(None);
+        let subscriber = Registry::default().with(layer);
+
+        let handle = match tracing::subscriber::set_global_default(subscriber) {
+            Ok(_) => Some(handle),
+            Err(_) => {
+                warn!("A tracing subscriber is already installed, profiling is disabled.");
+                None
+            }
+        };
+
+        Self {
+            handle,
+            session: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Start recording spans into a new trace file under `folder`, returning its path
+    pub fn start(&self, folder: &Path, name: &str) -> Result<PathBuf, ProfilerError> {
+        let handle = self.handle.as_ref().ok_or(ProfilerError::Unavailable)?;
+        let mut session = self.session.lock().unwrap();
+
+        if let Some((path, _)) = session.as_ref() {
+            return Err(ProfilerError::AlreadyRunning(path.to_owned()));
+        }
+
+        fs::create_dir_all(folder).map_err(|e| ProfilerError::Io(e.to_string()))?;
+
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let path = folder.join(format!("{}-{}.json", name, secs));
+
+        let (layer, guard) = ChromeLayerBuilder::new()
+            .file(path.to_string_lossy().into_owned())
+            .include_args(true)
+            .build();
+
+        handle
+            .reload(Some(layer))
+            .map_err(|e| ProfilerError::Io(e.to_string()))?;
+
+        info!("Profiling to {}.", path.display());
+        *session = Some((path.to_owned(), guard));
+
+        Ok(path)
+    }
+
+    /// Stop recording and flush the trace file, returning its path
+    pub fn stop(&self) -> Result<PathBuf, ProfilerError> {
+        let handle = self.handle.as_ref().ok_or(ProfilerError::Unavailable)?;
+        let (path, guard) = self
+            .session
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or(ProfilerError::NotRunning)?;
+
+        if let Err(e) = handle.reload(None) {
+            warn!("Failed to detach the chrome trace layer: {}", e);
+        }
+
+        // dropping the guard writes out the rest of the trace
+        drop(guard);
+
+        info!("Profile saved to {}.", path.display());
+
+        Ok(path)
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use server_common::vec::Vec2;
+
+use super::{chunk::Chunk, chunks::Chunks, registry::Registry, storage, world::WorldConfig};
+
+/// What pruning a world's saved chunks removed, or would have removed on a dry run
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    pub scanned: usize,
+    /// Chunks with no part within the world border
+    pub outside_border: usize,
+    /// Chunks exactly as the generator makes them
+    pub unmodified: usize,
+    /// Space the removed chunks took up in storage
+    pub bytes: u64,
+}
+
+/// Delete the saved chunks a world can do without: the ones outside of its border, and the
+/// ones no player has changed, which the generator makes again the same way when needed.
+///
+/// Meant to be run while the world isn't being served.
+pub fn prune_chunks(
+    world_name: &str,
+    config: &WorldConfig,
+    registry: &Arc<Registry>,
+    dry_run: bool,
+) -> PruneReport {
+    // generates chunks from scratch to compare the saved ones against
+    let mut reference = Chunks::new(
+        world_name,
+        WorldConfig {
+            save: false,
+            ..config.clone()
+        },
+        registry.clone(),
+    );
+
+    let storage = storage::open(config.storage, &reference.root_folder);
+
+    // neighboring chunks one after another, so the reference generates as little as possible
+    let mut saved = storage.list_chunks();
+    saved.sort_by_key(|(Vec2(cx, cz), _)| (*cx, *cz));
+
+    let mut report = PruneReport::default();
+    let mut prunable = vec![];
+
+    for (coords, bytes) in saved {
+        report.scanned += 1;
+
+        let outside_border = config.border.as_ref().map_or(false, |border| {
+            !border.contains_chunk(&coords, config.chunk_size)
+        });
+
+        let unmodified = !outside_border && {
+            let chunk = Chunk::new(coords.to_owned(), config, storage.load_chunk(&coords));
+
+            // neighbors' trees and structures reach into the chunk too
+            reference.generate(&coords, 2, true);
+
+            reference.raw(&coords).map_or(false, |generated| {
+                !generated.needs_decoration
+                    && generated.get_voxels().data == chunk.get_voxels().data
+            })
+        };
+
+        if outside_border {
+            report.outside_border += 1;
+        } else if unmodified {
+            report.unmodified += 1;
+        } else {
+            continue;
+        }
+
+        report.bytes += bytes;
+        prunable.push(coords);
+    }
+
+    if !dry_run && !prunable.is_empty() {
+        storage.remove_chunks(&prunable);
+        storage.compact();
+    }
+
+    report
+}
@@ -2,12 +2,16 @@
 
 use std::collections::HashMap;
 use std::fs::File;
+use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 
 use server_common::types::{Block, TypeMap, UV};
 use server_utils::json;
 
+use super::atlas::{Atlas, AtlasConfig};
+use super::map::average_color;
+
 pub type Ranges = HashMap<String, UV>;
 pub type Blocks = HashMap<u32, Block>;
 
@@ -21,32 +25,70 @@ pub struct PackDetails {
 /// Resource to control block data and textures
 #[derive(Debug, Clone)]
 pub struct Registry {
-    pub atlas: image::RgbaImage,
+    /// PNG encoded texture atlases of every pack, by pack name
+    pub atlases: HashMap<String, Arc<Vec<u8>>>,
     pub ranges: Ranges,
     pub blocks: Blocks,
     pub uv_side_count: u32,
     pub uv_texture_size: u32,
+    /// Color of each block seen from above, by block id
+    pub map_colors: HashMap<u32, [u8; 3]>,
 
     name_map: HashMap<String, u32>,
 }
 
 impl Registry {
-    pub fn new(mut packs: Vec<String>, write: bool) -> Self {
+    pub fn new(mut packs: Vec<String>, atlas_config: &AtlasConfig) -> Self {
         if packs.is_empty() {
             panic!("No texture packs found.");
         }
 
-        let registry: Self = Registry::load_pack(&packs.remove(0), write);
+        let mut registry: Self = Registry::load_pack(&packs.remove(0), atlas_config);
 
         packs.into_iter().for_each(|name| {
-            Registry::load_pack(&name, write);
+            let pack = Registry::load_pack(&name, atlas_config);
+            registry.atlases.extend(pack.atlases);
         });
 
         registry
     }
 
-    /// Load a texture pack
-    pub fn load_pack(pack_name: &str, write: bool) -> Self {
+    /// A registry of blocks by id, without any textures packed. Every texture the blocks
+    /// use spans the whole atlas.
+    pub fn from_blocks(blocks: Blocks) -> Self {
+        let name_map = blocks
+            .iter()
+            .map(|(&id, block)| (block.name.to_owned(), id))
+            .collect();
+
+        let ranges = blocks
+            .values()
+            .flat_map(|block| block.textures.values())
+            .map(|source| {
+                let uv = UV {
+                    start_u: 0.0,
+                    end_u: 1.0,
+                    start_v: 1.0,
+                    end_v: 0.0,
+                };
+
+                (source.to_owned(), uv)
+            })
+            .collect();
+
+        Self {
+            atlases: HashMap::new(),
+            ranges,
+            blocks,
+            uv_side_count: 1,
+            uv_texture_size: 16,
+            map_colors: HashMap::new(),
+            name_map,
+        }
+    }
+
+    /// Load a texture pack, packing its textures into an atlas
+    pub fn load_pack(pack_name: &str, atlas_config: &AtlasConfig) -> Self {
         let blocks_json: HashMap<String, String> =
             serde_json::from_reader(File::open("assets/metadata/blocks.json").unwrap()).unwrap();
 
@@ -131,87 +173,31 @@ impl Registry {
             blocks.insert(id, new_block);
         }
 
-        // OBTAINED TEXTURE MAP
-        let map_size = texture_map.len() as f32;
-        let mut shifts = 1;
-        let count_per_side = map_size.sqrt().ceil() as u32;
-        while 1 << shifts < count_per_side {
-            shifts += 1;
-        }
-        let count_per_side = 1 << shifts;
-        let texture_dim = pack.dimension;
-        let atlas_width = count_per_side * texture_dim;
-        let atlas_height = count_per_side * texture_dim;
-
-        let mut atlas: image::RgbaImage = image::ImageBuffer::new(atlas_width, atlas_height);
-
-        let mut ranges = HashMap::new();
-
-        let mut row = 0;
-        let mut col = 0;
-
-        let mut texture_map_vec: Vec<_> = texture_map.into_iter().collect();
-        texture_map_vec.sort_by(|x, y| x.0.cmp(&y.0));
-
-        for (key, image) in texture_map_vec {
-            if col >= count_per_side {
-                col = 0;
-                row += 1;
-            }
-
-            let start_x = col * texture_dim;
-            let start_y = row * texture_dim;
-
-            let resized = image::imageops::resize(
-                &image,
-                texture_dim,
-                texture_dim,
-                image::imageops::FilterType::CatmullRom,
-            );
-
-            image::imageops::overlay(&mut atlas, &resized, start_x, start_y);
-
-            let f_start_x = start_x as f32;
-            let f_start_y = start_y as f32;
-
-            let f_atlas_width = atlas_width as f32;
-            let f_atlas_height = atlas_height as f32;
-
-            let start_u = f_start_x / f_atlas_width;
-            let end_u = (f_start_x + texture_dim as f32) / f_atlas_width;
-            let start_v = 1.0 - f_start_y / f_atlas_height;
-            let end_v = 1.0 - (f_start_y + texture_dim as f32) / f_atlas_height;
-
-            let (start_u, start_v, end_u, end_v) =
-                fix_texture_bleeding((start_u, start_v, end_u, end_v));
-
-            let uv = UV {
-                start_u,
-                end_u,
-                start_v,
-                end_v,
-            };
+        let map_colors = blocks
+            .iter()
+            .filter_map(|(&id, block)| {
+                let source = block
+                    .textures
+                    .get("top")
+                    .or_else(|| block.textures.get("all"))
+                    .or_else(|| block.textures.values().next())?;
 
-            ranges.insert(key, uv);
+                Some((id, average_color(texture_map.get(source)?)))
+            })
+            .collect();
 
-            col += 1;
-        }
+        let atlas = Atlas::pack(texture_map, pack.dimension, atlas_config);
 
-        if write {
-            atlas
-                .save(&format!(
-                    "assets/textures/generated/{}-atlas.png",
-                    pack_name
-                ))
-                .unwrap();
-        }
+        let mut atlases = HashMap::new();
+        atlases.insert(pack_name.to_owned(), Arc::new(atlas.to_png()));
 
         Self {
-            atlas,
-            ranges,
+            atlases,
+            ranges: atlas.ranges,
             blocks,
-            uv_texture_size: texture_dim,
-            uv_side_count: count_per_side,
+            uv_texture_size: atlas.texture_size,
+            uv_side_count: atlas.side_count,
+            map_colors,
             name_map,
         }
     }
@@ -257,6 +243,11 @@ impl Registry {
     }
 
     /// Get block texture by id
+    /// Color of a block seen from above on the map, gray for blocks without textures
+    pub fn get_map_color(&self, id: u32) -> [u8; 3] {
+        self.map_colors.get(&id).cloned().unwrap_or([128, 128, 128])
+    }
+
     pub fn get_texture_by_id(&self, id: u32) -> &HashMap<String, String> {
         &self.get_block_by_id(id).textures
     }
@@ -296,6 +287,41 @@ impl Registry {
         self.get_block_by_id(id).is_plantable && self.get_block_by_id(above).is_empty
     }
 
+    /// Check if block is leaves by id
+    pub fn is_leaves(&self, id: u32) -> bool {
+        self.get_block_by_id(id).is_leaves
+    }
+
+    /// Check if block is a container by id
+    pub fn is_container(&self, id: u32) -> bool {
+        self.get_block_by_id(id).container_size > 0
+    }
+
+    /// Check if block is a portal by id
+    pub fn is_portal(&self, id: u32) -> bool {
+        self.get_block_by_id(id).is_portal
+    }
+
+    /// Check if block falls when unsupported by id
+    pub fn has_gravity(&self, id: u32) -> bool {
+        self.get_block_by_id(id).has_gravity
+    }
+
+    /// Whether two blocks are of the same render group, hiding the faces between them
+    pub fn is_same_render_group(&self, id: u32, other: u32) -> bool {
+        if id == other {
+            return true;
+        }
+
+        match (
+            &self.get_block_by_id(id).render_group,
+            &self.get_block_by_id(other).render_group,
+        ) {
+            (Some(group), Some(other_group)) => group == other_group,
+            _ => false,
+        }
+    }
+
     /// Get block data by id
     #[inline]
     pub fn get_block_by_id(&self, id: u32) -> &Block {
@@ -320,6 +346,11 @@ impl Registry {
             .unwrap_or_else(|| panic!("Type name not found: {}", name))
     }
 
+    /// Get block id by name, None if there's no such block
+    pub fn find_id_by_name(&self, name: &str) -> Option<u32> {
+        self.name_map.get(name).copied()
+    }
+
     /// Get UV map by block
     pub fn get_uv_map(&self, block: &Block) -> HashMap<String, &UV> {
         let mut uv_map = HashMap::new();
@@ -381,17 +412,3 @@ pub fn get_texture_type(texture: &HashMap<String, String>) -> &str {
         "x"
     }
 }
-
-/// Fixing texture bleeding with the
-/// [Half-texel edge correction method](http://drilian.com/2008/11/25/understanding-half-pixel-and-half-texel-offsets/)
-fn fix_texture_bleeding(
-    (start_u, start_v, end_u, end_v): (f32, f32, f32, f32),
-) -> (f32, f32, f32, f32) {
-    let offset = 0.1 / 128 as f32;
-    (
-        start_u + offset,
-        start_v - offset,
-        end_u - offset,
-        end_v + offset,
-    )
-}
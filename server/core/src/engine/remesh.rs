@@ -0,0 +1,83 @@
+use hashbrown::{HashMap, HashSet};
+
+use server_common::vec::Vec2;
+
+/// Resource of chunks waiting to be remeshed after their voxels or lights changed, along with
+/// the sub-chunk levels that changed.
+///
+/// The remesh system works through it under a time budget every tick, nearest to players
+/// first, so that a burst of changes doesn't take up a whole tick.
+#[derive(Debug, Default)]
+pub struct RemeshQueue {
+    levels: HashMap<Vec2<i32>, HashSet<u32>>,
+}
+
+impl RemeshQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue the levels of a chunk to be remeshed, along with any already queued
+    pub fn push(&mut self, coords: Vec2<i32>, levels: HashSet<u32>) {
+        self.levels.entry(coords).or_default().extend(levels);
+    }
+
+    /// Take a chunk's queued levels out
+    pub fn take(&mut self, coords: &Vec2<i32>) -> Option<HashSet<u32>> {
+        self.levels.remove(coords)
+    }
+
+    /// The queued chunks, nearest to any of `centers` first
+    pub fn by_distance(&self, centers: &[Vec2<i32>]) -> Vec<Vec2<i32>> {
+        let mut queued = self.levels.keys().cloned().collect::<Vec<_>>();
+
+        queued.sort_by_cached_key(|Vec2(cx, cz)| {
+            centers
+                .iter()
+                .map(|Vec2(x, z)| (cx - x).pow(2) + (cz - z).pow(2))
+                .min()
+                .unwrap_or(i32::MAX)
+        });
+
+        queued
+    }
+
+    /// The queued chunks, in no particular order
+    pub fn coords(&self) -> impl Iterator<Item = &Vec2<i32>> {
+        self.levels.keys()
+    }
+
+    /// How many chunks are queued
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Whether any chunk is queued
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remesh_queue_works() {
+        let mut queue = RemeshQueue::new();
+
+        queue.push(Vec2(5, 5), [0].iter().cloned().collect());
+        queue.push(Vec2(1, 0), [1].iter().cloned().collect());
+        queue.push(Vec2(5, 5), [2].iter().cloned().collect());
+
+        assert_eq!(queue.len(), 2);
+        assert_eq!(
+            queue.by_distance(&[Vec2(4, 4), Vec2(-3, 0)]),
+            vec![Vec2(5, 5), Vec2(1, 0)]
+        );
+
+        let levels = queue.take(&Vec2(5, 5)).unwrap();
+        assert_eq!(levels, [0, 2].iter().cloned().collect());
+        assert!(queue.take(&Vec2(5, 5)).is_none());
+    }
+}
@@ -0,0 +1,348 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+
+use hashbrown::{HashMap, HashSet};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use server_common::vec::Vec3;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    InvalidName,
+    AlreadyRecording,
+    NotRecording,
+    NotFound,
+    NotPlaying,
+    NotAdmin,
+    Io(String),
+    Usage(&'static str),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::InvalidName => {
+                write!(f, "Replay names are letters, digits, - and _ only.")
+            }
+            ReplayError::AlreadyRecording => write!(f, "A replay is already being recorded."),
+            ReplayError::NotRecording => write!(f, "No replay is being recorded."),
+            ReplayError::NotFound => write!(f, "There's no replay of that name."),
+            ReplayError::NotPlaying => write!(f, "No replay is playing."),
+            ReplayError::NotAdmin => write!(f, "Only admins can do that."),
+            ReplayError::Io(err) => write!(f, "Could not open the replay: {}", err),
+            ReplayError::Usage(usage) => write!(f, "Usage: {}", usage),
+        }
+    }
+}
+
+/// Something that happened in a world, as kept in a replay
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ReplayEvent {
+    #[serde(rename_all = "camelCase")]
+    Player {
+        id: usize,
+        name: String,
+        position: [f32; 3],
+        rotation: [f32; 4],
+    },
+    #[serde(rename_all = "camelCase")]
+    Entity {
+        id: u32,
+        entity_type: String,
+        position: [f32; 3],
+    },
+    #[serde(rename_all = "camelCase")]
+    Block {
+        voxel: Vec3<i32>,
+        id: u32,
+        rotation: u32,
+        y_rotation: u32,
+        stage: u32,
+    },
+}
+
+/// A line of a replay file, the events of a tick
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    /// Ticks since the recording started
+    pub tick: u64,
+    pub events: Vec<ReplayEvent>,
+}
+
+#[derive(Debug)]
+struct Recording {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    start: u64,
+}
+
+/// A replay being played back to a player
+#[derive(Debug)]
+struct Playback {
+    frames: VecDeque<ReplayFrame>,
+    start: u64,
+    /// Voxels the replay changed for the player, to be set back once it's done
+    voxels: HashSet<Vec3<i32>>,
+    /// Players the replay showed the player, as the ids they were sent with
+    peers: HashSet<String>,
+}
+
+/// What's left to clean up for a player after a replay played back to them
+#[derive(Debug)]
+pub struct PlaybackLeftovers {
+    pub voxels: Vec<Vec3<i32>>,
+    pub peers: Vec<String>,
+}
+
+/// Resource of the replays of a world, kept as JSON lines under its `replays` folder
+///
+/// A single replay gets recorded at a time, while any number of admins can play them back.
+/// Playback is only sent to the player watching it, best done in spectator mode.
+#[derive(Debug)]
+pub struct Replays {
+    folder: PathBuf,
+    recording: Option<Recording>,
+    playbacks: HashMap<usize, Playback>,
+}
+
+impl Replays {
+    pub fn new(folder: PathBuf) -> Self {
+        Self {
+            folder,
+            recording: None,
+            playbacks: HashMap::new(),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Start recording a new replay at a tick, returning where it's saved
+    pub fn start(&mut self, name: &str, tick: u64) -> Result<PathBuf, ReplayError> {
+        if self.recording.is_some() {
+            return Err(ReplayError::AlreadyRecording);
+        }
+
+        let path = self.path(name)?;
+
+        fs::create_dir_all(&self.folder).map_err(|e| ReplayError::Io(e.to_string()))?;
+        let file = File::create(&path).map_err(|e| ReplayError::Io(e.to_string()))?;
+
+        self.recording = Some(Recording {
+            path: path.to_owned(),
+            writer: BufWriter::new(file),
+            start: tick,
+        });
+
+        Ok(path)
+    }
+
+    /// Stop recording, returning where the replay got saved
+    pub fn stop(&mut self) -> Result<PathBuf, ReplayError> {
+        let mut recording = self.recording.take().ok_or(ReplayError::NotRecording)?;
+
+        if let Err(e) = recording.writer.flush() {
+            warn!("Unable to finish replay {:?}: {}", recording.path, e);
+        }
+
+        Ok(recording.path)
+    }
+
+    /// Append the events of a tick to the replay being recorded, stopping the recording
+    /// if it can't be written to
+    pub fn record(&mut self, tick: u64, events: Vec<ReplayEvent>) {
+        let recording = match self.recording.as_mut() {
+            Some(recording) if !events.is_empty() => recording,
+            _ => return,
+        };
+
+        let frame = ReplayFrame {
+            tick: tick.saturating_sub(recording.start),
+            events,
+        };
+
+        let line = serde_json::to_string(&frame).unwrap();
+
+        if let Err(e) = writeln!(recording.writer, "{}", line) {
+            warn!("Unable to record replay {:?}: {}", recording.path, e);
+            self.recording = None;
+        }
+    }
+
+    /// Start playing a replay back to a player from a tick on, returning how many ticks
+    /// it lasts
+    pub fn play(&mut self, player_id: usize, name: &str, tick: u64) -> Result<u64, ReplayError> {
+        let path = self.path(name)?;
+        let file = File::open(&path).map_err(|_| ReplayError::NotFound)?;
+
+        let frames = BufReader::new(file)
+            .lines()
+            .filter_map(|line| line.ok())
+            .filter_map(|line| serde_json::from_str::<ReplayFrame>(&line).ok())
+            .collect::<VecDeque<_>>();
+
+        let length = frames.back().map_or(0, |frame| frame.tick);
+
+        self.playbacks.insert(
+            player_id,
+            Playback {
+                frames,
+                start: tick,
+                voxels: HashSet::new(),
+                peers: HashSet::new(),
+            },
+        );
+
+        Ok(length)
+    }
+
+    /// Stop playing a replay back to a player, returning what's left to clean up
+    pub fn stop_playback(&mut self, player_id: usize) -> Option<PlaybackLeftovers> {
+        self.playbacks
+            .remove(&player_id)
+            .map(|playback| PlaybackLeftovers {
+                voxels: playback.voxels.into_iter().collect(),
+                peers: playback.peers.into_iter().collect(),
+            })
+    }
+
+    /// Take the events due by a tick for each player watching a replay, along with the
+    /// players whose replays ran out
+    pub fn advance(&mut self, tick: u64) -> (Vec<(usize, Vec<ReplayEvent>)>, Vec<usize>) {
+        let mut due = vec![];
+        let mut finished = vec![];
+
+        for (&player_id, playback) in self.playbacks.iter_mut() {
+            let elapsed = tick.saturating_sub(playback.start);
+            let mut events = vec![];
+
+            while playback
+                .frames
+                .front()
+                .map_or(false, |frame| frame.tick <= elapsed)
+            {
+                events.extend(playback.frames.pop_front().unwrap().events);
+            }
+
+            for event in events.iter() {
+                match event {
+                    ReplayEvent::Player { id, .. } => {
+                        playback.peers.insert(replay_id(*id as u64));
+                    }
+                    ReplayEvent::Entity { .. } => {}
+                    ReplayEvent::Block { voxel, .. } => {
+                        playback.voxels.insert(voxel.to_owned());
+                    }
+                }
+            }
+
+            if !events.is_empty() {
+                due.push((player_id, events));
+            }
+
+            if playback.frames.is_empty() {
+                finished.push(player_id);
+            }
+        }
+
+        (due, finished)
+    }
+
+    /// Names of the saved replays, sorted
+    pub fn list(&self) -> Vec<String> {
+        let mut names = fs::read_dir(&self.folder)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter_map(|entry| {
+                        let path = entry.path();
+                        match path.extension() {
+                            Some(ext) if ext == "jsonl" => {
+                                Some(path.file_stem()?.to_string_lossy().into_owned())
+                            }
+                            _ => None,
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        names.sort();
+        names
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, ReplayError> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        if !valid {
+            return Err(ReplayError::InvalidName);
+        }
+
+        Ok(self.folder.join(format!("{}.jsonl", name)))
+    }
+}
+
+/// The id players and entities of a replay are sent with, so that they're never mistaken
+/// for the ones actually around
+pub fn replay_id(id: u64) -> String {
+    format!("replay-{}", id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_work() {
+        let folder = std::env::temp_dir().join(format!("mine.js-replays-{}", std::process::id()));
+        let mut replays = Replays::new(folder.to_owned());
+
+        assert_eq!(replays.start("../up", 0), Err(ReplayError::InvalidName));
+
+        replays.start("test", 100).unwrap();
+        assert_eq!(
+            replays.start("other", 100),
+            Err(ReplayError::AlreadyRecording)
+        );
+
+        let block = ReplayEvent::Block {
+            voxel: Vec3(1, 2, 3),
+            id: 4,
+            rotation: 0,
+            y_rotation: 0,
+            stage: 0,
+        };
+        replays.record(100, vec![block.clone()]);
+        replays.record(101, vec![]);
+        replays.record(105, vec![block.clone()]);
+        replays.stop().unwrap();
+
+        assert_eq!(replays.list(), vec!["test".to_owned()]);
+        assert_eq!(replays.play(1, "test", 200), Ok(5));
+
+        // frames come in as their ticks come around
+        let (due, finished) = replays.advance(200);
+        assert_eq!(due, vec![(1, vec![block.clone()])]);
+        assert!(finished.is_empty());
+
+        let (due, finished) = replays.advance(210);
+        assert_eq!(due, vec![(1, vec![block])]);
+        assert_eq!(finished, vec![1]);
+
+        let leftovers = replays.stop_playback(1).unwrap();
+        assert_eq!(leftovers.voxels, vec![Vec3(1, 2, 3)]);
+
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use hashbrown::{HashMap, HashSet};
+
+use server_common::vec::Vec2;
+use specs::Entity;
+
+/// Which entities are in which chunk, kept up by the chunking system as entities move.
+///
+/// Answers questions about an area by the chunks it covers instead of by walking every
+/// entity, such as how crowded a chunk is or what goes away with a chunk being unloaded.
+#[derive(Debug, Default)]
+pub struct ChunkResidency {
+    chunks: HashMap<Vec2<i32>, HashSet<Entity>>,
+    entities: HashMap<Entity, Vec2<i32>>,
+}
+
+impl ChunkResidency {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an entity being in a chunk, moving it out of the chunk it was in before
+    pub fn insert(&mut self, entity: Entity, coords: &Vec2<i32>) {
+        if let Some(old) = self.entities.insert(entity, coords.to_owned()) {
+            if old == *coords {
+                return;
+            }
+
+            self.leave(entity, &old);
+        }
+
+        self.chunks
+            .entry(coords.to_owned())
+            .or_insert_with(HashSet::new)
+            .insert(entity);
+    }
+
+    /// Forget an entity, returning the chunk it was in
+    pub fn remove(&mut self, entity: Entity) -> Option<Vec2<i32>> {
+        let coords = self.entities.remove(&entity)?;
+        self.leave(entity, &coords);
+        Some(coords)
+    }
+
+    /// Forget every entity that doesn't pass the check, such as the ones deleted since
+    pub fn retain(&mut self, mut keep: impl FnMut(Entity) -> bool) {
+        let gone = self
+            .entities
+            .keys()
+            .filter(|&&entity| !keep(entity))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        gone.into_iter().for_each(|entity| {
+            self.remove(entity);
+        });
+    }
+
+    /// The chunk an entity is in
+    pub fn chunk_of(&self, entity: Entity) -> Option<&Vec2<i32>> {
+        self.entities.get(&entity)
+    }
+
+    /// The entities in a chunk
+    pub fn in_chunk(&self, coords: &Vec2<i32>) -> impl Iterator<Item = &Entity> {
+        self.chunks.get(coords).into_iter().flatten()
+    }
+
+    /// How many entities are in a chunk
+    pub fn count(&self, coords: &Vec2<i32>) -> usize {
+        self.chunks.get(coords).map_or(0, |entities| entities.len())
+    }
+
+    /// The entities in the chunks within `radius` chunks of a chunk
+    pub fn within(&self, Vec2(cx, cz): &Vec2<i32>, radius: i32) -> Vec<Entity> {
+        let mut entities = vec![];
+
+        for x in -radius..=radius {
+            for z in -radius..=radius {
+                if x * x + z * z > radius * radius {
+                    continue;
+                }
+
+                entities.extend(self.in_chunk(&Vec2(cx + x, cz + z)));
+            }
+        }
+
+        entities
+    }
+
+    /// Every chunk with an entity in it
+    pub fn coords(&self) -> impl Iterator<Item = &Vec2<i32>> {
+        self.chunks.keys()
+    }
+
+    fn leave(&mut self, entity: Entity, coords: &Vec2<i32>) {
+        if let Some(entities) = self.chunks.get_mut(coords) {
+            entities.remove(&entity);
+
+            if entities.is_empty() {
+                self.chunks.remove(coords);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{Builder, World, WorldExt};
+
+    use super::*;
+
+    #[test]
+    fn residency_works() {
+        let mut world = World::new();
+        let a = world.create_entity().build();
+        let b = world.create_entity().build();
+
+        let mut residency = ChunkResidency::new();
+        residency.insert(a, &Vec2(0, 0));
+        residency.insert(b, &Vec2(0, 0));
+        assert_eq!(residency.count(&Vec2(0, 0)), 2);
+
+        // moving over leaves the chunk it was in
+        residency.insert(a, &Vec2(1, 0));
+        assert_eq!(residency.count(&Vec2(0, 0)), 1);
+        assert_eq!(residency.chunk_of(a), Some(&Vec2(1, 0)));
+        assert_eq!(residency.within(&Vec2(0, 0), 1).len(), 2);
+        assert!(residency.within(&Vec2(3, 0), 1).is_empty());
+
+        residency.retain(|entity| entity != b);
+        assert_eq!(residency.count(&Vec2(0, 0)), 0);
+        assert_eq!(residency.coords().count(), 1);
+    }
+}
@@ -0,0 +1,51 @@
+use rand::{rngs::StdRng, Error, RngCore, SeedableRng};
+
+/// Randomness of a world's simulation, from mobs wandering to crops growing and loot dropping.
+///
+/// Worlds run in lockstep seed it, so that they play out the same way every run.
+#[derive(Debug)]
+pub struct WorldRng(StdRng);
+
+impl WorldRng {
+    pub fn new(seed: Option<u64>) -> Self {
+        Self(match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        })
+    }
+}
+
+impl RngCore for WorldRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn world_rng_works() {
+        let rolls = |rng: &mut WorldRng| (0..8).map(|_| rng.gen::<u32>()).collect::<Vec<_>>();
+
+        let seeded = rolls(&mut WorldRng::new(Some(42)));
+
+        assert_eq!(seeded, rolls(&mut WorldRng::new(Some(42))));
+        assert_ne!(seeded, rolls(&mut WorldRng::new(Some(43))));
+    }
+}
@@ -0,0 +1,378 @@
+use std::{
+    collections::HashSet,
+    fs, mem,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use log::{info, warn};
+use rhai::{Dynamic, Engine, FuncArgs, Scope, AST, FLOAT, INT};
+
+use server_common::vec::Vec3;
+
+use crate::constants::MAX_SCRIPT_OPERATIONS;
+
+use super::chat_filter::{FilterAction, FilterRule};
+use super::chunks::Chunks;
+
+/// Something a script asked the world to do, carried out once the hook that asked returns
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptAction {
+    SetVoxel(Vec3<i32>, u32),
+    /// Spawn an entity of a type at a position, in voxels
+    Spawn(String, Vec3<f32>),
+    Chat(String),
+    /// Give coins to the player of a name, or take them away with a negative amount
+    Coins(String, i64),
+}
+
+/// What the bindings work with while a hook runs
+#[derive(Default)]
+struct ScriptContext {
+    /// The world's chunks, lent to scripts for as long as a hook runs
+    chunks: Option<Chunks>,
+    /// Name of the script whose hook runs, for `schedule` to call back into
+    script: String,
+    actions: Vec<ScriptAction>,
+    /// Calls scheduled by the hook: ticks to wait, the script, and its function
+    scheduled: Vec<(u64, String, String)>,
+    /// Chat filter rules added by the scripts' top levels
+    chat_filters: Vec<FilterRule>,
+}
+
+struct Script {
+    name: String,
+    /// Only the functions of the script, so that calling hooks doesn't run its top level again
+    functions: AST,
+    hooks: HashSet<String>,
+}
+
+/// Rhai scripts hooked into a world's events, loaded from the `.rhai` files of a folder
+///
+/// Scripts handle events by defining functions named after them: `on_join(player)`,
+/// `on_leave(player)`, `on_chat(player, message)`, `on_filter(player, message, action)`,
+/// `on_block(player, x, y, z, id)`, `on_pay(from, to, amount)` and `on_tick()`. A script that
+/// errors only fails the call it was in, and every call is limited in how much it may compute
+/// so that no script can hang the world.
+pub struct Scripts {
+    folder: PathBuf,
+    engine: Engine,
+    scripts: Vec<Script>,
+    context: Arc<Mutex<ScriptContext>>,
+    /// Calls scheduled by scripts: the tick they're due, the script, and its function
+    scheduled: Vec<(u64, String, String)>,
+    chat_filters: Vec<FilterRule>,
+}
+
+impl Scripts {
+    pub fn new(folder: &Path) -> Self {
+        let context = Arc::new(Mutex::new(ScriptContext::default()));
+
+        let mut scripts = Self {
+            folder: folder.to_path_buf(),
+            engine: create_engine(&context),
+            scripts: vec![],
+            context,
+            scheduled: vec![],
+            chat_filters: vec![],
+        };
+
+        scripts.reload();
+        scripts
+    }
+
+    /// Load every script in the folder again, forgetting the calls they scheduled
+    ///
+    /// Returns how many scripts loaded, and why the others didn't.
+    pub fn reload(&mut self) -> (usize, Vec<String>) {
+        self.scripts.clear();
+        self.scheduled.clear();
+        self.context.lock().unwrap().chat_filters.clear();
+
+        let mut errors = vec![];
+
+        let mut paths = match fs::read_dir(&self.folder) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().map_or(false, |ext| ext == "rhai"))
+                .collect::<Vec<_>>(),
+            Err(_) => vec![],
+        };
+        paths.sort();
+
+        for path in paths {
+            let name = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+
+            match self.load(&name, &path) {
+                Ok(script) => self.scripts.push(script),
+                Err(e) => {
+                    warn!("Unable to load script {:?}: {}", path, e);
+                    errors.push(format!("{}: {}", name, e));
+                }
+            }
+        }
+
+        // whatever the top levels asked for has no world to happen in yet
+        let mut context = self.context.lock().unwrap();
+        context.actions.clear();
+        context.scheduled.clear();
+        self.chat_filters = mem::take(&mut context.chat_filters);
+
+        if !self.scripts.is_empty() {
+            info!(
+                "Loaded {} scripts from {:?}.",
+                self.scripts.len(),
+                self.folder
+            );
+        }
+
+        (self.scripts.len(), errors)
+    }
+
+    /// Compile a script and run its top level once
+    fn load(&self, name: &str, path: &Path) -> Result<Script, String> {
+        let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let ast = self.engine.compile(&source).map_err(|e| e.to_string())?;
+
+        self.context.lock().unwrap().script = name.to_owned();
+
+        self.engine
+            .eval_ast_with_scope::<Dynamic>(&mut Scope::new(), &ast)
+            .map_err(|e| e.to_string())?;
+
+        let hooks = ast
+            .iter_functions()
+            .map(|function| function.name.to_string())
+            .collect();
+
+        Ok(Script {
+            name: name.to_owned(),
+            functions: ast.clone_functions_only(),
+            hooks,
+        })
+    }
+
+    /// Chat filter rules the scripts added as they loaded
+    pub fn chat_filters(&self) -> &[FilterRule] {
+        &self.chat_filters
+    }
+
+    /// Whether any script defines a function, so that the world only lends its chunks out
+    /// when there's something to call
+    pub fn has_hook(&self, function: &str) -> bool {
+        self.scripts
+            .iter()
+            .any(|script| script.hooks.contains(function))
+    }
+
+    /// Call a function of every script defining it, or only of the script named `only`. The
+    /// scripts can read the chunks while they run, and what they ask for is returned along
+    /// with the chunks.
+    pub fn call(
+        &mut self,
+        chunks: Chunks,
+        only: Option<&str>,
+        function: &str,
+        args: impl FuncArgs + Clone,
+    ) -> (Chunks, Vec<ScriptAction>) {
+        self.context.lock().unwrap().chunks = Some(chunks);
+
+        for script in self.scripts.iter() {
+            if only.map_or(false, |only| only != script.name) || !script.hooks.contains(function) {
+                continue;
+            }
+
+            self.context.lock().unwrap().script = script.name.to_owned();
+
+            let result = self.engine.call_fn::<Dynamic>(
+                &mut Scope::new(),
+                &script.functions,
+                function,
+                args.clone(),
+            );
+
+            if let Err(e) = result {
+                warn!("Script {} failed in {}: {}", script.name, function, e);
+            }
+        }
+
+        let mut context = self.context.lock().unwrap();
+        let chunks = context.chunks.take().unwrap();
+        let actions = mem::take(&mut context.actions);
+
+        (chunks, actions)
+    }
+
+    /// Take the calls due by `tick`, as the scripts and the functions to call
+    pub fn take_due(&mut self, tick: u64) -> Vec<(String, String)> {
+        let scheduled = mem::take(&mut self.context.lock().unwrap().scheduled);
+        self.scheduled.extend(
+            scheduled
+                .into_iter()
+                .map(|(ticks, script, function)| (tick + ticks, script, function)),
+        );
+
+        let (due, later) = mem::take(&mut self.scheduled)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(at, _, _)| *at <= tick);
+        self.scheduled = later;
+
+        due.into_iter()
+            .map(|(_, script, function)| (script, function))
+            .collect()
+    }
+}
+
+/// Create the engine scripts run on, with the functions they can call
+///
+/// - `get_voxel(x, y, z)`: the block id at a voxel, -1 if it isn't loaded
+/// - `set_voxel(x, y, z, id)`
+/// - `block_id(name)`: the id of a block, -1 if there's no such block
+/// - `spawn(type, x, y, z)`: spawn an entity at a position in voxels
+/// - `chat(message)`: tell every player something
+/// - `give_coins(player, amount)` and `take_coins(player, amount)`
+/// - `add_chat_filter(pattern, action)`: run chat through a regular expression, to `censor`,
+///   `block` or `mute` on a match. Only works at the top level of a script.
+/// - `schedule(ticks, function)`: call a function of the same script some ticks later
+fn create_engine(context: &Arc<Mutex<ScriptContext>>) -> Engine {
+    let mut engine = Engine::new();
+
+    engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+    engine.on_print(|text| info!("[script] {}", text));
+
+    let shared = context.clone();
+    engine.register_fn("get_voxel", move |x: INT, y: INT, z: INT| -> INT {
+        let context = shared.lock().unwrap();
+        let (vx, vy, vz) = (x as i32, y as i32, z as i32);
+
+        match &context.chunks {
+            Some(chunks)
+                if vy >= 0
+                    && vy < chunks.config.max_height as i32
+                    && chunks.get_chunk_by_voxel(vx, vy, vz).is_some() =>
+            {
+                chunks.get_voxel_by_voxel(vx, vy, vz) as INT
+            }
+            _ => -1,
+        }
+    });
+
+    let shared = context.clone();
+    engine.register_fn("set_voxel", move |x: INT, y: INT, z: INT, id: INT| {
+        if id >= 0 {
+            let voxel = Vec3(x as i32, y as i32, z as i32);
+            let mut context = shared.lock().unwrap();
+            context
+                .actions
+                .push(ScriptAction::SetVoxel(voxel, id as u32));
+        }
+    });
+
+    let shared = context.clone();
+    engine.register_fn("block_id", move |name: &str| -> INT {
+        let context = shared.lock().unwrap();
+
+        context
+            .chunks
+            .as_ref()
+            .and_then(|chunks| chunks.registry.find_id_by_name(name))
+            .map_or(-1, |id| id as INT)
+    });
+
+    let shared = context.clone();
+    engine.register_fn("spawn", move |etype: &str, x: FLOAT, y: FLOAT, z: FLOAT| {
+        let position = Vec3(x as f32, y as f32, z as f32);
+        let mut context = shared.lock().unwrap();
+        context
+            .actions
+            .push(ScriptAction::Spawn(etype.to_owned(), position));
+    });
+
+    let shared = context.clone();
+    engine.register_fn("chat", move |message: &str| {
+        let mut context = shared.lock().unwrap();
+        context.actions.push(ScriptAction::Chat(message.to_owned()));
+    });
+
+    let shared = context.clone();
+    engine.register_fn("give_coins", move |player: &str, amount: INT| {
+        let mut context = shared.lock().unwrap();
+        context
+            .actions
+            .push(ScriptAction::Coins(player.to_owned(), amount));
+    });
+
+    let shared = context.clone();
+    engine.register_fn("take_coins", move |player: &str, amount: INT| {
+        let mut context = shared.lock().unwrap();
+        context
+            .actions
+            .push(ScriptAction::Coins(player.to_owned(), -amount));
+    });
+
+    let shared = context.clone();
+    engine.register_fn("add_chat_filter", move |pattern: &str, action: &str| {
+        match FilterAction::from_name(action) {
+            Some(action) => shared.lock().unwrap().chat_filters.push(FilterRule {
+                pattern: Some(pattern.to_owned()),
+                words: vec![],
+                action,
+            }),
+            None => warn!("Unknown chat filter action {}.", action),
+        }
+    });
+
+    let shared = context.clone();
+    engine.register_fn("schedule", move |ticks: INT, function: &str| {
+        let mut context = shared.lock().unwrap();
+        let script = context.script.to_owned();
+        context
+            .scheduled
+            .push((ticks.max(0) as u64, script, function.to_owned()));
+    });
+
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_work() {
+        let folder = std::env::temp_dir().join(format!("mine.js-scripts-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+
+        fs::write(
+            folder.join("greet.rhai"),
+            "fn on_join(player) { schedule(2, \"later\"); }",
+        )
+        .unwrap();
+        fs::write(folder.join("broken.rhai"), "fn on_join(player) {").unwrap();
+        fs::write(folder.join("notes.txt"), "not a script").unwrap();
+
+        let mut scripts = Scripts::new(&folder);
+        let (loaded, errors) = scripts.reload();
+
+        assert_eq!(loaded, 1);
+        assert_eq!(errors.len(), 1);
+        assert!(scripts.has_hook("on_join"));
+        assert!(!scripts.has_hook("on_tick"));
+
+        scripts.context.lock().unwrap().scheduled =
+            vec![(2, "greet".to_owned(), "later".to_owned())];
+
+        assert!(scripts.take_due(10).is_empty());
+        assert_eq!(
+            scripts.take_due(12),
+            vec![("greet".to_owned(), "later".to_owned())]
+        );
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
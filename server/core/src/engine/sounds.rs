@@ -0,0 +1,119 @@
+use std::{collections::HashMap, fs::File};
+
+use serde::{Deserialize, Serialize};
+
+use server_common::vec::Vec3;
+
+use crate::{constants::SOUND_HEARING_DISTANCE, network::models::SoundProtocol};
+
+fn default_one() -> f32 {
+    1.0
+}
+
+fn default_distance() -> f32 {
+    SOUND_HEARING_DISTANCE
+}
+
+/// JSON format of a sound in the sound registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SoundInfo {
+    /// Assigned on load, so clients can map the ids of sound events back to files
+    #[serde(default)]
+    pub id: u32,
+    pub file: String,
+    #[serde(default = "default_one")]
+    pub volume: f32,
+    #[serde(default = "default_one")]
+    pub pitch: f32,
+    /// How far away players can hear this sound, in voxels
+    #[serde(default = "default_distance")]
+    pub distance: f32,
+}
+
+pub type SoundInfos = HashMap<String, SoundInfo>;
+
+/// Registry of every sound clients can be told to play, loaded from `sounds.json`
+pub struct Sounds {
+    infos: SoundInfos,
+    ids: Vec<String>,
+}
+
+impl Default for Sounds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sounds {
+    pub fn new() -> Self {
+        let mut infos: SoundInfos =
+            serde_json::from_reader(File::open("assets/metadata/sounds.json").unwrap()).unwrap();
+
+        // sort the names so that ids stay the same across restarts
+        let mut ids = infos.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+
+        ids.iter().enumerate().for_each(|(id, name)| {
+            infos.get_mut(name).unwrap().id = id as u32;
+        });
+
+        Self { infos, ids }
+    }
+
+    /// Clone all sound infos
+    pub fn get_all(&self) -> SoundInfos {
+        self.infos.clone()
+    }
+
+    pub fn get_info(&self, name: &str) -> Option<&SoundInfo> {
+        self.infos.get(name)
+    }
+
+    pub fn get_name(&self, id: u32) -> Option<&String> {
+        self.ids.get(id as usize)
+    }
+
+    /// Create an event playing a registered sound with its default volume and pitch
+    pub fn event(&self, name: &str, position: Vec3<f32>) -> Option<SoundEvent> {
+        let info = self.get_info(name)?;
+
+        Some(SoundEvent {
+            sound: info.id,
+            position,
+            volume: info.volume,
+            pitch: info.pitch,
+            distance: info.distance,
+        })
+    }
+}
+
+/// A sound played at a position, only sent to players within hearing distance
+#[derive(Debug, Clone)]
+pub struct SoundEvent {
+    pub sound: u32,
+    pub position: Vec3<f32>,
+    pub volume: f32,
+    pub pitch: f32,
+    /// Hearing distance in voxels
+    pub distance: f32,
+}
+
+impl SoundEvent {
+    /// Convert into its protobuf format
+    pub fn to_protocol(&self) -> SoundProtocol {
+        let Vec3(px, py, pz) = self.position;
+
+        SoundProtocol {
+            sound: self.sound,
+            px,
+            py,
+            pz,
+            volume: self.volume,
+            pitch: self.pitch,
+        }
+    }
+}
+
+/// Resource of sounds played this tick, sent out by the sounds system
+pub type SoundQueue = Vec<SoundEvent>;
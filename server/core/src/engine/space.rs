@@ -1,9 +1,12 @@
 use hashbrown::HashMap;
 
-use super::{chunks::Chunks, world::WorldConfig};
+use super::{
+    chunk_store::{ChunkStore, VoxelAccess},
+    world::WorldConfig,
+};
 
 use server_common::{
-    ndarray::Ndarray,
+    array::{Array2, Array3},
     vec::{Vec2, Vec3},
 };
 use server_utils::convert::{map_voxel_to_chunk, map_voxel_to_chunk_local};
@@ -12,16 +15,16 @@ use server_utils::convert::{map_voxel_to_chunk, map_voxel_to_chunk_local};
 /// Used for reference in other threads.
 pub struct Space {
     pub width: usize,
-    pub shape: Vec<usize>,
+    pub shape: [usize; 3],
     pub min: Vec3<i32>,
 
-    voxels: HashMap<Vec2<i32>, Ndarray<u32>>,
-    height_maps: HashMap<Vec2<i32>, Ndarray<u32>>,
+    voxels: HashMap<Vec2<i32>, Array3<u32>>,
+    height_maps: HashMap<Vec2<i32>, Array2<u32>>,
     chunk_size: usize,
 }
 
 impl Space {
-    pub fn new(chunks: &Chunks, Vec2(cx, cz): &Vec2<i32>, margin: usize) -> Self {
+    pub fn new(chunks: &ChunkStore, Vec2(cx, cz): &Vec2<i32>, margin: usize) -> Self {
         assert!(margin > 0, "Margin of 0 on Space is wasteful");
 
         let WorldConfig {
@@ -54,7 +57,7 @@ impl Space {
         // i'm not sure why it needs a +1 here, but it does.
         let min = Vec3(cx * cs - m + 1, 0, cz * cs - m + 1);
 
-        let shape = vec![width, max_height as usize, width];
+        let shape = [width, max_height as usize, width];
 
         Self {
             width,
@@ -65,15 +68,17 @@ impl Space {
             chunk_size,
         }
     }
+}
 
+impl VoxelAccess for Space {
     /// Access a voxel by voxel coordinates within the space
     ///
     /// `x,y,z` in terms of voxels
-    pub fn get_voxel(&self, vx: i32, vy: i32, vz: i32) -> u32 {
+    fn get_voxel(&self, vx: i32, vy: i32, vz: i32) -> u32 {
         let coords = map_voxel_to_chunk(vx, vy, vz, self.chunk_size);
         let Vec3(lx, ly, lz) = map_voxel_to_chunk_local(vx, vy, vz, self.chunk_size);
         if let Some(voxels) = self.voxels.get(&coords) {
-            voxels[&[lx as usize, ly as usize, lz as usize]]
+            voxels[[lx as usize, ly as usize, lz as usize]]
         } else {
             0
         }
@@ -82,11 +87,11 @@ impl Space {
     /// Access the max height by voxel column within the space
     ///
     /// `x,y,z` in terms of voxels
-    pub fn get_max_height(&self, vx: i32, vz: i32) -> u32 {
+    fn get_max_height(&self, vx: i32, vz: i32) -> u32 {
         let coords = map_voxel_to_chunk(vx, 0, vz, self.chunk_size);
         let Vec3(lx, _, lz) = map_voxel_to_chunk_local(vx, 0, vz, self.chunk_size);
         if let Some(height_map) = self.height_maps.get(&coords) {
-            height_map[&[lx as usize, lz as usize]]
+            height_map[[lx as usize, lz as usize]]
         } else {
             0
         }
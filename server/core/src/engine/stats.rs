@@ -0,0 +1,137 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+use hashbrown::HashMap;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use server_common::vec::Vec3;
+
+/// How a world's stats are recorded, not at all if left out of the config
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsConfig {
+    /// Seconds between snapshots
+    pub interval: u64,
+    /// Width of the squares players are counted in on the heatmap, in voxels
+    #[serde(default = "default_heatmap_bucket")]
+    pub heatmap_bucket: i32,
+}
+
+fn default_heatmap_bucket() -> i32 {
+    64
+}
+
+/// A line of the stats log, covering the time since the snapshot before it
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsSnapshot {
+    /// Seconds since the unix epoch
+    pub time: u64,
+    pub chunks: usize,
+    pub players: usize,
+    /// Voxels changed, by the name of the block they became
+    pub blocks: BTreeMap<String, u64>,
+    /// Ticks players spent in each square of the heatmap, as `[x, z, ticks]` with the
+    /// squares counted from the origin
+    pub heatmap: Vec<[i64; 3]>,
+}
+
+/// Resource of the stats recorder, keeping a time-lapse of a world in `stats.jsonl` to be
+/// looked at offline
+///
+/// Players are counted into the heatmap every tick, so that it shows where time is spent
+/// rather than where players happened to be at the snapshot.
+#[derive(Debug)]
+pub struct StatsRecorder {
+    config: StatsConfig,
+    path: PathBuf,
+    /// When the last snapshot was taken, in seconds since the unix epoch
+    last: u64,
+    heatmap: HashMap<(i32, i32), u64>,
+}
+
+impl StatsRecorder {
+    pub fn new(config: StatsConfig, path: PathBuf, now: u64) -> Self {
+        Self {
+            config,
+            path,
+            last: now,
+            heatmap: HashMap::new(),
+        }
+    }
+
+    /// Count a player standing at a voxel into the heatmap
+    pub fn sample(&mut self, voxel: &Vec3<i32>) {
+        let bucket = self.config.heatmap_bucket.max(1);
+        let square = (voxel.0.div_euclid(bucket), voxel.2.div_euclid(bucket));
+
+        *self.heatmap.entry(square).or_insert(0) += 1;
+    }
+
+    /// Whether it's time for another snapshot
+    pub fn is_due(&self, now: u64) -> bool {
+        now >= self.last + self.config.interval.max(1)
+    }
+
+    /// Take the heatmap gathered since the last snapshot, sorted by square
+    pub fn take_heatmap(&mut self) -> Vec<[i64; 3]> {
+        let mut heatmap = self
+            .heatmap
+            .drain()
+            .map(|((x, z), ticks)| [x as i64, z as i64, ticks as i64])
+            .collect::<Vec<_>>();
+
+        heatmap.sort_unstable();
+        heatmap
+    }
+
+    /// Append a snapshot to the log
+    pub fn write(&mut self, snapshot: &StatsSnapshot) {
+        self.last = snapshot.time;
+
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| {
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)
+            })
+            .and_then(|mut file| writeln!(file, "{}", serde_json::to_string(snapshot).unwrap()));
+
+        if let Err(e) = result {
+            warn!("Unable to record stats to {:?}: {}", self.path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_work() {
+        let config = StatsConfig {
+            interval: 60,
+            heatmap_bucket: 16,
+        };
+        let mut recorder = StatsRecorder::new(config, PathBuf::from("stats.jsonl"), 1000);
+
+        recorder.sample(&Vec3(0, 10, 15));
+        recorder.sample(&Vec3(15, 80, 0));
+        recorder.sample(&Vec3(-1, 10, 16));
+
+        assert_eq!(recorder.take_heatmap(), vec![[-1, 1, 1], [0, 0, 2]]);
+        assert!(recorder.take_heatmap().is_empty());
+
+        assert!(!recorder.is_due(1059));
+        assert!(recorder.is_due(1060));
+    }
+}
@@ -0,0 +1,794 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use log::warn;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, ToSql};
+use serde::Deserialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use server_common::vec::{Vec2, Vec3};
+use server_utils::convert::get_chunk_name;
+
+use crate::constants::{
+    CONTAINERS_DATA_FILE, EDITS_DATA_FILE, PLAYER_DATA_FOLDER, SQLITE_DATA_FILE, WORLD_DATA_FILE,
+};
+
+use super::audit::{EditQuery, EditRecord};
+
+/// Where a world keeps its data, set by `storage` in the world's config
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageKind {
+    /// A JSON file per chunk and per player, next to the world's and the containers' files
+    Files,
+    /// A single SQLite database, written to in transactions
+    Sqlite,
+    /// Kept in memory and lost once the server stops, for tests that save and load worlds
+    Memory,
+}
+
+impl Default for StorageKind {
+    fn default() -> Self {
+        StorageKind::Files
+    }
+}
+
+/// Writes that are saved together, all or nothing if the storage supports it.
+///
+/// Records are the same JSON no matter the storage, so worlds can be moved between them.
+#[derive(Debug, Default, Clone)]
+pub struct WriteBatch {
+    pub world: Option<Value>,
+    pub chunks: Vec<(Vec2<i32>, Value)>,
+    /// Every container of the world, replacing the saved ones
+    pub containers: Option<Vec<(Vec3<i32>, Value)>>,
+    pub players: Vec<(Uuid, Value)>,
+    /// Block edits to append to the edit log, which is never rewritten
+    pub edits: Vec<Value>,
+}
+
+/// Persistence of a world's data: its clock, chunks, block entities, logged in players and
+/// the log of their edits
+pub trait Storage: fmt::Debug + Send + Sync {
+    fn load_world(&self) -> Option<Value>;
+
+    fn load_chunk(&self, coords: &Vec2<i32>) -> Option<Value>;
+
+    fn load_containers(&self) -> Vec<(Vec3<i32>, Value)>;
+
+    fn load_player(&self, uuid: &Uuid) -> Option<Value>;
+
+    /// The edits in the log matching a query, newest first and at most `limit` of them
+    fn query_edits(&self, query: &EditQuery, limit: usize) -> Vec<Value>;
+
+    /// Every saved chunk, along with the bytes it takes up
+    fn list_chunks(&self) -> Vec<(Vec2<i32>, u64)>;
+
+    fn remove_chunks(&self, coords: &[Vec2<i32>]);
+
+    fn write(&self, batch: WriteBatch);
+
+    /// Give the space freed by removed data back to the disk
+    fn compact(&self) {}
+}
+
+/// Open the storage of a world saved at `root`
+pub fn open(kind: StorageKind, root: &Path) -> Arc<dyn Storage> {
+    match kind {
+        StorageKind::Files => Arc::new(FileStorage::new(root)),
+        StorageKind::Sqlite => Arc::new(SqliteStorage::new(root)),
+        StorageKind::Memory => Arc::new(MemoryStorage::default()),
+    }
+}
+
+/// Storage of worlds that aren't saved, which has nothing to load and drops every write
+#[derive(Debug)]
+pub struct NoStorage;
+
+impl Storage for NoStorage {
+    fn load_world(&self) -> Option<Value> {
+        None
+    }
+
+    fn load_chunk(&self, _: &Vec2<i32>) -> Option<Value> {
+        None
+    }
+
+    fn load_containers(&self) -> Vec<(Vec3<i32>, Value)> {
+        vec![]
+    }
+
+    fn load_player(&self, _: &Uuid) -> Option<Value> {
+        None
+    }
+
+    fn query_edits(&self, _: &EditQuery, _: usize) -> Vec<Value> {
+        vec![]
+    }
+
+    fn list_chunks(&self) -> Vec<(Vec2<i32>, u64)> {
+        vec![]
+    }
+
+    fn remove_chunks(&self, _: &[Vec2<i32>]) {}
+
+    fn write(&self, _: WriteBatch) {}
+}
+
+/// Storage that keeps everything written in memory, to be loaded back for as long as the
+/// server runs
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    data: Mutex<MemoryData>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryData {
+    world: Option<Value>,
+    chunks: HashMap<Vec2<i32>, Value>,
+    containers: Vec<(Vec3<i32>, Value)>,
+    players: HashMap<Uuid, Value>,
+    edits: Vec<Value>,
+}
+
+impl Storage for MemoryStorage {
+    fn load_world(&self) -> Option<Value> {
+        self.data.lock().unwrap().world.clone()
+    }
+
+    fn load_chunk(&self, coords: &Vec2<i32>) -> Option<Value> {
+        self.data.lock().unwrap().chunks.get(coords).cloned()
+    }
+
+    fn load_containers(&self) -> Vec<(Vec3<i32>, Value)> {
+        self.data.lock().unwrap().containers.clone()
+    }
+
+    fn load_player(&self, uuid: &Uuid) -> Option<Value> {
+        self.data.lock().unwrap().players.get(uuid).cloned()
+    }
+
+    fn query_edits(&self, query: &EditQuery, limit: usize) -> Vec<Value> {
+        self.data
+            .lock()
+            .unwrap()
+            .edits
+            .iter()
+            .rev()
+            .filter(|edit| edit_matches(query, edit))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn list_chunks(&self) -> Vec<(Vec2<i32>, u64)> {
+        self.data
+            .lock()
+            .unwrap()
+            .chunks
+            .iter()
+            .map(|(coords, chunk)| (coords.clone(), chunk.to_string().len() as u64))
+            .collect()
+    }
+
+    fn remove_chunks(&self, coords: &[Vec2<i32>]) {
+        let mut data = self.data.lock().unwrap();
+        coords.iter().for_each(|coords| {
+            data.chunks.remove(coords);
+        });
+    }
+
+    fn write(&self, batch: WriteBatch) {
+        let mut data = self.data.lock().unwrap();
+
+        if let Some(world) = batch.world {
+            data.world = Some(world);
+        }
+
+        data.chunks.extend(batch.chunks);
+
+        if let Some(containers) = batch.containers {
+            data.containers = containers;
+        }
+
+        data.players.extend(batch.players);
+        data.edits.extend(batch.edits);
+    }
+}
+
+#[derive(Debug)]
+pub struct FileStorage {
+    root: PathBuf,
+    chunk_folder: PathBuf,
+    player_folder: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(root: &Path) -> Self {
+        let chunk_folder = root.join("chunks");
+        let player_folder = root.join(PLAYER_DATA_FOLDER);
+
+        fs::create_dir_all(&chunk_folder).expect("Unable to create chunks directory...");
+
+        Self {
+            root: root.to_path_buf(),
+            chunk_folder,
+            player_folder,
+        }
+    }
+
+    fn chunk_path(&self, Vec2(cx, cz): &Vec2<i32>) -> PathBuf {
+        self.chunk_folder
+            .join(format!("{}.json", get_chunk_name(*cx, *cz)))
+    }
+
+    fn player_path(&self, uuid: &Uuid) -> PathBuf {
+        self.player_folder.join(format!("{}.json", uuid))
+    }
+
+    fn read(path: &Path) -> Option<Value> {
+        let file = File::open(path).ok()?;
+
+        match serde_json::from_reader(file) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("Unable to read {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    fn save(path: &Path, value: &impl serde::Serialize) {
+        let mut file =
+            File::create(path).unwrap_or_else(|e| panic!("Could not create {:?}: {}", path, e));
+        let j = serde_json::to_string(value).unwrap();
+
+        file.write_all(j.as_bytes())
+            .unwrap_or_else(|e| panic!("Unable to save {:?}: {}", path, e));
+    }
+}
+
+impl Storage for FileStorage {
+    fn load_world(&self) -> Option<Value> {
+        Self::read(&self.root.join(WORLD_DATA_FILE))
+    }
+
+    fn load_chunk(&self, coords: &Vec2<i32>) -> Option<Value> {
+        Self::read(&self.chunk_path(coords))
+    }
+
+    fn load_containers(&self) -> Vec<(Vec3<i32>, Value)> {
+        let path = self.root.join(CONTAINERS_DATA_FILE);
+
+        Self::read(&path)
+            .map(|value| {
+                serde_json::from_value(value).unwrap_or_else(|e| {
+                    warn!("Unable to read containers at {:?}: {}", path, e);
+                    vec![]
+                })
+            })
+            .unwrap_or_default()
+    }
+
+    fn load_player(&self, uuid: &Uuid) -> Option<Value> {
+        Self::read(&self.player_path(uuid))
+    }
+
+    /// Reads through the whole log, keeping no more than `limit` edits in memory
+    fn query_edits(&self, query: &EditQuery, limit: usize) -> Vec<Value> {
+        let path = self.root.join(EDITS_DATA_FILE);
+
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(_) => return vec![],
+        };
+
+        let mut found = VecDeque::new();
+
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            match serde_json::from_str(&line) {
+                Ok(edit) if edit_matches(query, &edit) => {
+                    found.push_back(edit);
+
+                    if found.len() > limit {
+                        found.pop_front();
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Unable to read edit from {:?}: {}", path, e),
+            }
+        }
+
+        found.into_iter().rev().collect()
+    }
+
+    fn list_chunks(&self) -> Vec<(Vec2<i32>, u64)> {
+        let entries = match fs::read_dir(&self.chunk_folder) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Unable to read chunks at {:?}: {}", self.chunk_folder, e);
+                return vec![];
+            }
+        };
+
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let coords = parse_chunk_file(&entry.path())?;
+                let bytes = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                Some((coords, bytes))
+            })
+            .collect()
+    }
+
+    fn remove_chunks(&self, coords: &[Vec2<i32>]) {
+        for coords in coords {
+            let path = self.chunk_path(coords);
+
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("Unable to remove chunk file {:?}: {}", path, e);
+            }
+        }
+    }
+
+    fn write(&self, batch: WriteBatch) {
+        if let Some(world) = batch.world {
+            Self::save(&self.root.join(WORLD_DATA_FILE), &world);
+        }
+
+        for (coords, chunk) in batch.chunks {
+            Self::save(&self.chunk_path(&coords), &chunk);
+        }
+
+        if let Some(containers) = batch.containers {
+            Self::save(&self.root.join(CONTAINERS_DATA_FILE), &containers);
+        }
+
+        if !batch.players.is_empty() {
+            fs::create_dir_all(&self.player_folder).expect("Could not create player data folder.");
+        }
+
+        for (uuid, player) in batch.players {
+            Self::save(&self.player_path(&uuid), &player);
+        }
+
+        if !batch.edits.is_empty() {
+            let path = self.root.join(EDITS_DATA_FILE);
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .unwrap_or_else(|e| panic!("Could not open {:?}: {}", path, e));
+
+            let lines = batch
+                .edits
+                .iter()
+                .map(|edit| format!("{}\n", edit))
+                .collect::<String>();
+
+            file.write_all(lines.as_bytes())
+                .unwrap_or_else(|e| panic!("Unable to save {:?}: {}", path, e));
+        }
+    }
+}
+
+/// Chunk coordinates of a chunk file, named like `3_-2.json`
+/// Whether a saved edit is one a query asks for
+fn edit_matches(query: &EditQuery, edit: &Value) -> bool {
+    EditRecord::deserialize(edit).map_or(false, |record| query.matches(&record))
+}
+
+fn parse_chunk_file(path: &Path) -> Option<Vec2<i32>> {
+    if path.extension()? != "json" {
+        return None;
+    }
+
+    let (cx, cz) = path.file_stem()?.to_str()?.split_once('_')?;
+
+    Some(Vec2(cx.parse().ok()?, cz.parse().ok()?))
+}
+
+/// Storage in a single SQLite database with a table per kind of data, each record a JSON
+/// column so they can be queried from outside with `json_extract`.
+pub struct SqliteStorage {
+    path: PathBuf,
+    connection: Mutex<Connection>,
+}
+
+impl fmt::Debug for SqliteStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqliteStorage")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl SqliteStorage {
+    pub fn new(root: &Path) -> Self {
+        fs::create_dir_all(root).expect("Unable to create world directory...");
+
+        let path = root.join(SQLITE_DATA_FILE);
+        let connection = Self::connect(&path)
+            .unwrap_or_else(|e| panic!("Unable to open world database at {:?}: {}", path, e));
+
+        Self {
+            path,
+            connection: Mutex::new(connection),
+        }
+    }
+
+    fn connect(path: &Path) -> rusqlite::Result<Connection> {
+        let connection = Connection::open(path)?;
+
+        // lets outside tools read while the server writes
+        connection.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS world (id INTEGER PRIMARY KEY CHECK (id = 0), data TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS chunks (x INTEGER NOT NULL, z INTEGER NOT NULL, data TEXT NOT NULL, PRIMARY KEY (x, z));
+             CREATE TABLE IF NOT EXISTS containers (x INTEGER NOT NULL, y INTEGER NOT NULL, z INTEGER NOT NULL, data TEXT NOT NULL, PRIMARY KEY (x, y, z));
+             CREATE TABLE IF NOT EXISTS players (uuid TEXT PRIMARY KEY, data TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS edits (id INTEGER PRIMARY KEY AUTOINCREMENT, data TEXT NOT NULL);
+             CREATE INDEX IF NOT EXISTS edits_by_time ON edits (json_extract(data, '$.timestamp'));
+             CREATE INDEX IF NOT EXISTS edits_by_column ON edits (json_extract(data, '$.voxel[0]'), json_extract(data, '$.voxel[2]'));
+             CREATE INDEX IF NOT EXISTS edits_by_player ON edits (json_extract(data, '$.player') COLLATE NOCASE);",
+        )?;
+
+        Ok(connection)
+    }
+
+    /// Run a query for a single JSON record, if there's any
+    fn query(&self, sql: &str, params: impl rusqlite::Params) -> Option<Value> {
+        let connection = self.connection.lock().unwrap();
+
+        let data = connection
+            .query_row(sql, params, |row| row.get::<_, String>(0))
+            .optional()
+            .unwrap_or_else(|e| panic!("Unable to query world database: {}", e))?;
+
+        match serde_json::from_str(&data) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("Unable to read record from {:?}: {}", self.path, e);
+                None
+            }
+        }
+    }
+
+    /// Run a query for every row it matches
+    fn select<T>(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+        map: impl FnMut(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
+    ) -> Vec<T> {
+        let connection = self.connection.lock().unwrap();
+
+        let rows = connection.prepare(sql).and_then(|mut select| {
+            let rows = select.query_map(params, map)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+        });
+
+        rows.unwrap_or_else(|e| panic!("Unable to query world database: {}", e))
+    }
+
+    fn try_write(&self, batch: WriteBatch) -> rusqlite::Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        if let Some(world) = batch.world {
+            transaction.execute(
+                "INSERT OR REPLACE INTO world (id, data) VALUES (0, ?1)",
+                params![world.to_string()],
+            )?;
+        }
+
+        {
+            let mut insert = transaction
+                .prepare_cached("INSERT OR REPLACE INTO chunks (x, z, data) VALUES (?1, ?2, ?3)")?;
+
+            for (Vec2(cx, cz), chunk) in batch.chunks {
+                insert.execute(params![cx, cz, chunk.to_string()])?;
+            }
+        }
+
+        if let Some(containers) = batch.containers {
+            transaction.execute("DELETE FROM containers", [])?;
+
+            let mut insert = transaction
+                .prepare_cached("INSERT INTO containers (x, y, z, data) VALUES (?1, ?2, ?3, ?4)")?;
+
+            for (Vec3(vx, vy, vz), container) in containers {
+                insert.execute(params![vx, vy, vz, container.to_string()])?;
+            }
+        }
+
+        {
+            let mut insert = transaction
+                .prepare_cached("INSERT OR REPLACE INTO players (uuid, data) VALUES (?1, ?2)")?;
+
+            for (uuid, player) in batch.players {
+                insert.execute(params![uuid.to_string(), player.to_string()])?;
+            }
+        }
+
+        {
+            let mut insert = transaction.prepare_cached("INSERT INTO edits (data) VALUES (?1)")?;
+
+            for edit in batch.edits {
+                insert.execute(params![edit.to_string()])?;
+            }
+        }
+
+        transaction.commit()
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load_world(&self) -> Option<Value> {
+        self.query("SELECT data FROM world WHERE id = 0", [])
+    }
+
+    fn load_chunk(&self, Vec2(cx, cz): &Vec2<i32>) -> Option<Value> {
+        self.query(
+            "SELECT data FROM chunks WHERE x = ?1 AND z = ?2",
+            params![cx, cz],
+        )
+    }
+
+    fn load_containers(&self) -> Vec<(Vec3<i32>, Value)> {
+        let rows = self.select("SELECT x, y, z, data FROM containers", [], |row| {
+            Ok((
+                Vec3::<i32>(row.get(0)?, row.get(1)?, row.get(2)?),
+                row.get::<_, String>(3)?,
+            ))
+        });
+
+        rows.into_iter()
+            .filter_map(|(voxel, data)| match serde_json::from_str(&data) {
+                Ok(value) => Some((voxel, value)),
+                Err(e) => {
+                    warn!("Unable to read container at {:?}: {}", voxel, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn load_player(&self, uuid: &Uuid) -> Option<Value> {
+        self.query(
+            "SELECT data FROM players WHERE uuid = ?1",
+            params![uuid.to_string()],
+        )
+    }
+
+    /// Filters on the indexed fields of the edits, in the database
+    fn query_edits(&self, query: &EditQuery, limit: usize) -> Vec<Value> {
+        let mut conditions = vec![];
+        let mut params: Vec<Box<dyn ToSql>> = vec![];
+
+        if let Some(since) = query.since {
+            conditions.push("json_extract(data, '$.timestamp') >= ?");
+            params.push(Box::new(since as i64));
+        }
+
+        if let Some((min, max)) = &query.region {
+            conditions.push("json_extract(data, '$.voxel[0]') BETWEEN ? AND ?");
+            conditions.push("json_extract(data, '$.voxel[2]') BETWEEN ? AND ?");
+            for bound in &[min.0, max.0, min.2, max.2] {
+                params.push(Box::new(*bound));
+            }
+        }
+
+        if let Some(player) = &query.player {
+            conditions.push("json_extract(data, '$.player') = ? COLLATE NOCASE");
+            params.push(Box::new(player.to_owned()));
+        }
+
+        let filter = if conditions.is_empty() {
+            "1".to_owned()
+        } else {
+            conditions.join(" AND ")
+        };
+        params.push(Box::new(limit.min(i64::MAX as usize) as i64));
+
+        let rows = self.select(
+            &format!(
+                "SELECT data FROM edits WHERE {} ORDER BY id DESC LIMIT ?",
+                filter
+            ),
+            params_from_iter(params),
+            |row| row.get::<_, String>(0),
+        );
+
+        rows.into_iter()
+            .filter_map(|data| match serde_json::from_str(&data) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    warn!("Unable to read edit from {:?}: {}", self.path, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn list_chunks(&self) -> Vec<(Vec2<i32>, u64)> {
+        self.select("SELECT x, z, length(data) FROM chunks", [], |row| {
+            Ok((Vec2(row.get(0)?, row.get(1)?), row.get::<_, i64>(2)? as u64))
+        })
+    }
+
+    fn remove_chunks(&self, coords: &[Vec2<i32>]) {
+        let mut connection = self.connection.lock().unwrap();
+
+        let removed = connection.transaction().and_then(|transaction| {
+            {
+                let mut delete =
+                    transaction.prepare_cached("DELETE FROM chunks WHERE x = ?1 AND z = ?2")?;
+
+                for Vec2(cx, cz) in coords {
+                    delete.execute(params![cx, cz])?;
+                }
+            }
+
+            transaction.commit()
+        });
+
+        if let Err(e) = removed {
+            warn!("Unable to remove chunks from {:?}: {}", self.path, e);
+        }
+    }
+
+    fn write(&self, batch: WriteBatch) {
+        // nothing is written unless everything is
+        self.try_write(batch)
+            .unwrap_or_else(|e| panic!("Unable to save to world database {:?}: {}", self.path, e));
+    }
+
+    fn compact(&self) {
+        if let Err(e) = self.connection.lock().unwrap().execute_batch("VACUUM") {
+            warn!("Unable to compact world database {:?}: {}", self.path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::journal::JournalEntry;
+    use super::*;
+
+    fn edit(player: &str, vx: i32, timestamp: u64) -> Value {
+        EditRecord {
+            player: player.to_owned(),
+            uuid: None,
+            edit: JournalEntry {
+                voxel: Vec3(vx, 5, 0),
+                old_id: 0,
+                new_id: 1,
+                timestamp,
+            },
+        }
+        .to_saved()
+    }
+
+    fn edits() -> Vec<Value> {
+        vec![
+            edit("ian", 0, 1000),
+            edit("naenae", 20, 2000),
+            edit("ian", 1, 3000),
+        ]
+    }
+
+    /// Check the edits of `edits` are queried the same from any storage
+    fn check_edit_queries(storage: &dyn Storage) {
+        let timestamps = |query: &EditQuery, limit: usize| {
+            storage
+                .query_edits(query, limit)
+                .iter()
+                .map(|edit| edit["timestamp"].as_u64().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let everything = EditQuery::default();
+        assert_eq!(timestamps(&everything, 10), vec![3000, 2000, 1000]);
+        assert_eq!(timestamps(&everything, 1), vec![3000]);
+
+        let since = EditQuery {
+            since: Some(2000),
+            ..Default::default()
+        };
+        assert_eq!(timestamps(&since, 10), vec![3000, 2000]);
+
+        let region = EditQuery {
+            region: Some((Vec3(-2, 0, -2), Vec3(2, 0, 2))),
+            ..Default::default()
+        };
+        assert_eq!(timestamps(&region, 10), vec![3000, 1000]);
+
+        let player = EditQuery {
+            player: Some("NAENAE".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(timestamps(&player, 10), vec![2000]);
+    }
+
+    #[test]
+    fn parse_chunk_file_works() {
+        assert_eq!(
+            parse_chunk_file(Path::new("data/main/chunks/3_-2.json")),
+            Some(Vec2(3, -2))
+        );
+        assert_eq!(parse_chunk_file(Path::new("chunks/3_-2.tmp")), None);
+        assert_eq!(parse_chunk_file(Path::new("chunks/notes.json")), None);
+    }
+
+    #[test]
+    fn sqlite_storage_works() {
+        let root = std::env::temp_dir().join(format!("mine.js-storage-{}", std::process::id()));
+        let storage = SqliteStorage::new(&root);
+        let uuid = Uuid::from_u128(1);
+
+        storage.write(WriteBatch {
+            world: Some(serde_json::json!({ "time": 1.0 })),
+            chunks: vec![(Vec2(3, -2), serde_json::json!({ "voxels": "abc" }))],
+            containers: Some(vec![(Vec3(1, 2, 3), serde_json::json!({ "size": 27 }))]),
+            players: vec![(uuid, serde_json::json!({ "gameMode": "survival" }))],
+            edits: edits(),
+        });
+
+        assert_eq!(storage.load_world().unwrap()["time"], 1.0);
+        assert_eq!(storage.load_chunk(&Vec2(3, -2)).unwrap()["voxels"], "abc");
+        assert_eq!(storage.load_containers()[0].0, Vec3(1, 2, 3));
+        assert_eq!(storage.load_player(&uuid).unwrap()["gameMode"], "survival");
+        check_edit_queries(&storage);
+
+        storage.remove_chunks(&[Vec2(3, -2)]);
+        assert!(storage.list_chunks().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn memory_storage_works() {
+        let storage = MemoryStorage::default();
+
+        storage.write(WriteBatch {
+            chunks: vec![(Vec2(3, -2), serde_json::json!({ "voxels": "abc" }))],
+            edits: edits()[..1].to_vec(),
+            ..Default::default()
+        });
+        storage.write(WriteBatch {
+            world: Some(serde_json::json!({ "time": 1.0 })),
+            edits: edits()[1..].to_vec(),
+            ..Default::default()
+        });
+
+        assert_eq!(storage.load_world().unwrap()["time"], 1.0);
+        assert_eq!(storage.load_chunk(&Vec2(3, -2)).unwrap()["voxels"], "abc");
+        check_edit_queries(&storage);
+
+        storage.remove_chunks(&[Vec2(3, -2)]);
+        assert!(storage.list_chunks().is_empty());
+    }
+
+    #[test]
+    fn file_storage_queries_edits() {
+        let root = std::env::temp_dir().join(format!("mine.js-file-edits-{}", std::process::id()));
+        let storage = FileStorage::new(&root);
+
+        storage.write(WriteBatch {
+            edits: edits(),
+            ..Default::default()
+        });
+
+        check_edit_queries(&storage);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+use rand::Rng;
+
+use server_common::{aabb::Aabb, types::Support, vec::Vec3};
+
+use crate::comp::inventory::ItemStack;
+
+use super::{
+    chunks::Chunks,
+    farming::Farming,
+    loot::{LootContext, LootTables},
+};
+
+/// Blocks that need others next to them, like plants on soil and torches on walls
+pub struct Supports;
+
+impl Supports {
+    /// Whether a block would be held up at a voxel by the blocks around it. Blocks without a
+    /// `support` stay anywhere.
+    pub fn is_supported(chunks: &Chunks, id: u32, vx: i32, vy: i32, vz: i32) -> bool {
+        let support = match &chunks.registry.get_block_by_id(id).support {
+            Some(support) => support,
+            None => return true,
+        };
+
+        let holds = |vx: i32, vy: i32, vz: i32| {
+            // what's there is only air because its chunk isn't there yet
+            if chunks
+                .get_chunk_by_voxel(vx, vy, vz)
+                .map_or(true, |chunk| chunk.needs_propagation)
+            {
+                return true;
+            }
+
+            let block = chunks.get_block_by_voxel(vx, vy, vz);
+
+            match support {
+                Support::Soil => block.is_plantable,
+                Support::Blocks(names) => names.contains(&block.name),
+                Support::Solid | Support::Attached => block.is_solid && !block.is_fluid,
+            }
+        };
+
+        if vy > 0 && holds(vx, vy - 1, vz) {
+            return true;
+        }
+
+        *support == Support::Attached
+            && [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                .iter()
+                .any(|&(ox, oz)| holds(vx + ox, vy, vz + oz))
+    }
+
+    /// Whether the block at a voxel lost what held it up, and has to pop off
+    pub fn is_unsupported(chunks: &Chunks, vx: i32, vy: i32, vz: i32) -> bool {
+        match chunks.get_chunk_by_voxel(vx, vy, vz) {
+            Some(chunk) if !chunk.needs_propagation => {}
+            _ => return false,
+        }
+
+        let id = chunks.get_voxel_by_voxel(vx, vy, vz);
+        !Self::is_supported(chunks, id, vx, vy, vz)
+    }
+
+    /// Whether a block placed at a voxel would end up inside any of the bodies, in world space
+    pub fn is_obstructed(
+        chunks: &Chunks,
+        id: u32,
+        Vec3(vx, vy, vz): &Vec3<i32>,
+        bodies: &[Aabb],
+    ) -> bool {
+        let block = chunks.registry.get_block_by_id(id);
+        let dimension = chunks.config.dimension as f32;
+
+        let full = [[0.0, 0.0, 0.0, 1.0, 1.0, 1.0]];
+        let boxes: &[[f32; 6]] = match &block.collision_boxes {
+            Some(custom) => custom.as_slice(),
+            None if block.is_solid && !block.is_fluid => &full,
+            None => &[],
+        };
+
+        boxes.iter().any(|b| {
+            let aabb = Aabb::new(
+                &Vec3(
+                    (*vx as f32 + b[0]) * dimension,
+                    (*vy as f32 + b[1]) * dimension,
+                    (*vz as f32 + b[2]) * dimension,
+                ),
+                &Vec3(
+                    (b[3] - b[0]) * dimension,
+                    (b[4] - b[1]) * dimension,
+                    (b[5] - b[2]) * dimension,
+                ),
+            );
+
+            bodies.iter().any(|body| body.intersects(&aabb))
+        })
+    }
+
+    /// What a block drops when it pops off: what breaking it would, or else the block itself
+    pub fn drops<R: Rng>(
+        chunks: &Chunks,
+        loot: &LootTables,
+        id: u32,
+        stage: u32,
+        rng: &mut R,
+    ) -> Vec<ItemStack> {
+        let context = LootContext {
+            stage,
+            ..Default::default()
+        };
+
+        loot.roll_block(&chunks.registry, id, &context, rng)
+            .or_else(|| Farming::harvest(chunks, id, stage, rng).map(|stack| vec![stack]))
+            .unwrap_or_else(|| vec![ItemStack::new(id, 1)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{self, GLASS, STONE, TORCH};
+
+    use super::*;
+
+    #[test]
+    fn is_supported_works() {
+        // flat ground with a wall along x = 4
+        let mut chunks = test_utils::chunks_with(1, |vx, vy, _| vy < 4 || (vx == 4 && vy < 8));
+
+        // on the ground, against the side of the wall, and in the air
+        assert!(Supports::is_supported(&chunks, TORCH, 2, 4, 3));
+        assert!(Supports::is_supported(&chunks, TORCH, 3, 6, 3));
+        assert!(!Supports::is_supported(&chunks, TORCH, 2, 6, 3));
+
+        // blocks without a support stay anywhere
+        assert!(Supports::is_supported(&chunks, STONE, 2, 6, 3));
+
+        chunks.set_voxel_by_voxel(2, 6, 3, TORCH);
+        assert!(Supports::is_unsupported(&chunks, 2, 6, 3));
+        assert!(!Supports::is_unsupported(&chunks, 2, 7, 3));
+    }
+
+    #[test]
+    fn is_obstructed_works() {
+        let chunks = test_utils::chunks(1, 4);
+        let bodies = [Aabb::new(&Vec3(3.2, 4.0, 3.2), &Vec3(0.6, 1.8, 0.6))];
+        let obstructed = |id, voxel| Supports::is_obstructed(&chunks, id, &voxel, &bodies);
+
+        assert!(obstructed(GLASS, Vec3(3, 5, 3)));
+        assert!(!obstructed(GLASS, Vec3(4, 5, 3)));
+
+        // torches don't get in the way
+        assert!(!obstructed(TORCH, Vec3(3, 5, 3)));
+    }
+}
@@ -0,0 +1,220 @@
+use std::fmt;
+
+use hashbrown::{HashMap, HashSet};
+
+use crate::constants::MAX_TEAM_NAME_LENGTH;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TeamError {
+    InvalidName,
+    NotOnTeam,
+    UnknownPlayer,
+    NotAdmin,
+    NotOwner,
+    NotInvited,
+    Usage(&'static str),
+}
+
+impl fmt::Display for TeamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TeamError::InvalidName => write!(
+                f,
+                "Team names are up to {} letters, digits, - and _.",
+                MAX_TEAM_NAME_LENGTH
+            ),
+            TeamError::NotOnTeam => write!(f, "Join a team first with /team join <name>."),
+            TeamError::UnknownPlayer => write!(f, "Nobody online goes by that name."),
+            TeamError::NotAdmin => write!(f, "Only admins can do that."),
+            TeamError::NotOwner => write!(f, "Only the team's owner can do that."),
+            TeamError::NotInvited => write!(f, "That team needs an invite from its owner to join."),
+            TeamError::Usage(usage) => write!(f, "Usage: {}", usage),
+        }
+    }
+}
+
+/// The team each player in a world is on, by player id
+///
+/// Teams come to be as players join them and are gone once the last member leaves, only
+/// their spawn points are kept along with the world. Whoever starts a team owns it, and
+/// others need an invite from them to join.
+#[derive(Debug, Default)]
+pub struct Teams {
+    members: HashMap<usize, String>,
+    owners: HashMap<String, usize>,
+    invites: HashMap<String, HashSet<usize>>,
+}
+
+impl Teams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn team(&self, player_id: usize) -> Option<&str> {
+        self.members.get(&player_id).map(|team| team.as_str())
+    }
+
+    /// Who runs a team, if anyone is on it
+    pub fn owner(&self, team: &str) -> Option<usize> {
+        self.owners.get(team).cloned()
+    }
+
+    /// Whether a player may join a team by themselves: nobody is on it yet, or its owner
+    /// invited them
+    pub fn can_join(&self, player_id: usize, team: &str) -> bool {
+        !self.owners.contains_key(team)
+            || self
+                .invites
+                .get(team)
+                .map_or(false, |invited| invited.contains(&player_id))
+    }
+
+    /// Let a player join a team
+    pub fn invite(&mut self, team: &str, player_id: usize) {
+        self.invites
+            .entry(team.to_owned())
+            .or_default()
+            .insert(player_id);
+    }
+
+    /// Put a player on a team, taking them off of the one they were on. Starting a team
+    /// makes them its owner.
+    pub fn join(&mut self, player_id: usize, team: &str) {
+        self.leave(player_id);
+        self.members.insert(player_id, team.to_owned());
+        self.owners.entry(team.to_owned()).or_insert(player_id);
+
+        if let Some(invited) = self.invites.get_mut(team) {
+            invited.remove(&player_id);
+        }
+    }
+
+    /// Take a player off of their team, returning the team. An owner leaving hands the team
+    /// over to another member.
+    pub fn leave(&mut self, player_id: usize) -> Option<String> {
+        let team = self.members.remove(&player_id)?;
+
+        if self.owners.get(&team) == Some(&player_id) {
+            match self.members(&team).into_iter().min() {
+                Some(next) => {
+                    self.owners.insert(team.to_owned(), next);
+                }
+                None => {
+                    self.owners.remove(&team);
+                    self.invites.remove(&team);
+                }
+            }
+        }
+
+        Some(team)
+    }
+
+    /// Forget a player that left the world, along with the invites they had
+    pub fn remove(&mut self, player_id: usize) {
+        self.leave(player_id);
+        self.invites.values_mut().for_each(|invited| {
+            invited.remove(&player_id);
+        });
+    }
+
+    /// Players on a team
+    pub fn members(&self, team: &str) -> Vec<usize> {
+        self.members
+            .iter()
+            .filter(|(_, member)| *member == team)
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    /// Whether two players are on the same team
+    pub fn same_team(&self, a: usize, b: usize) -> bool {
+        match (self.team(a), self.team(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Teams with anyone on them and how many are, by name
+    pub fn list(&self) -> Vec<(String, usize)> {
+        let mut counts = HashMap::new();
+
+        self.members.values().for_each(|team| {
+            *counts.entry(team.to_owned()).or_insert(0) += 1;
+        });
+
+        let mut list = counts.into_iter().collect::<Vec<_>>();
+        list.sort();
+        list
+    }
+}
+
+/// Check a team name, lowercasing it so that teams can't be told apart by case alone
+pub fn clean_team_name(name: &str) -> Option<String> {
+    let valid = !name.is_empty()
+        && name.chars().count() <= MAX_TEAM_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Some(name.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn teams_work() {
+        let mut teams = Teams::new();
+        teams.join(1, "red");
+        teams.join(2, "red");
+        teams.join(3, "blue");
+
+        assert!(teams.same_team(1, 2));
+        assert!(!teams.same_team(1, 3));
+        assert!(!teams.same_team(1, 4));
+        assert_eq!(
+            teams.list(),
+            vec![("blue".to_owned(), 1), ("red".to_owned(), 2)]
+        );
+
+        teams.join(2, "blue");
+        assert_eq!(teams.members("red"), vec![1]);
+        assert_eq!(teams.leave(1), Some("red".to_owned()));
+        assert!(teams.members("red").is_empty());
+        assert_eq!(teams.owner("red"), None);
+
+        assert_eq!(clean_team_name("Red_Team"), Some("red_team".to_owned()));
+        assert_eq!(clean_team_name("<b>"), None);
+    }
+
+    #[test]
+    fn team_owners_work() {
+        let mut teams = Teams::new();
+        teams.join(1, "red");
+
+        // only the owner's invitees can join a team that's been started
+        assert_eq!(teams.owner("red"), Some(1));
+        assert!(!teams.can_join(2, "red"));
+        assert!(teams.can_join(2, "blue"));
+
+        teams.invite("red", 2);
+        assert!(teams.can_join(2, "red"));
+        teams.join(2, "red");
+        teams.join(3, "red");
+        assert_eq!(teams.owner("red"), Some(1));
+
+        // the invite is used up once joined
+        teams.leave(2);
+        assert!(!teams.can_join(2, "red"));
+
+        teams.remove(1);
+        assert_eq!(teams.owner("red"), Some(3));
+        teams.leave(3);
+        assert!(teams.can_join(2, "red"));
+    }
+}
@@ -0,0 +1,144 @@
+use specs::Entity;
+
+use server_common::vec::Vec3;
+use server_utils::convert::map_world_to_voxel;
+
+use crate::network::models::{create_of_type, messages, MessageType};
+
+use super::chunks::Chunks;
+
+/// Finding safe places to land, for teleports, portals and spawning.
+pub struct Teleport;
+
+impl Teleport {
+    /// Whether an entity can stand at a voxel: solid ground below that isn't the surface of
+    /// a fluid, and two free voxels that aren't fluids to stand in.
+    pub fn is_safe(chunks: &Chunks, vx: i32, vy: i32, vz: i32) -> bool {
+        let is_fluid = |vy: i32| chunks.get_block_by_voxel(vx, vy, vz).is_fluid;
+        let is_free = |vy: i32| chunks.get_walkable_by_voxel(vx, vy, vz) && !is_fluid(vy);
+
+        vy > 0
+            && !chunks.get_walkable_by_voxel(vx, vy - 1, vz)
+            && !is_fluid(vy - 1)
+            && is_free(vy)
+            && is_free(vy + 1)
+    }
+
+    /// Find the highest safe height in a column, at or below `from`. Works under ceilings too.
+    pub fn find_height(chunks: &Chunks, vx: i32, from: i32, vz: i32) -> Option<i32> {
+        let max_height = chunks.config.max_height as i32;
+
+        (1..=from.min(max_height - 2))
+            .rev()
+            .find(|&vy| Teleport::is_safe(chunks, vx, vy, vz))
+    }
+
+    /// Search the columns around a voxel, closest first, for a safe place to land.
+    ///
+    /// Each column is searched downwards from the voxel's height.
+    pub fn find_landing(chunks: &Chunks, voxel: &Vec3<i32>, radius: i32) -> Option<Vec3<i32>> {
+        let Vec3(vx, vy, vz) = voxel;
+
+        let mut offsets = vec![];
+        for x in -radius..=radius {
+            for z in -radius..=radius {
+                if x * x + z * z <= radius * radius {
+                    offsets.push((x, z));
+                }
+            }
+        }
+        offsets.sort_by_key(|(x, z)| x * x + z * z);
+
+        offsets.into_iter().find_map(|(x, z)| {
+            let (nvx, nvz) = (vx + x, vz + z);
+
+            chunks.get_chunk_by_voxel(nvx, 0, nvz)?;
+
+            Teleport::find_height(chunks, nvx, *vy, nvz).map(|nvy| Vec3(nvx, nvy, nvz))
+        })
+    }
+
+    /// Create the message telling clients something got teleported.
+    ///
+    /// Players are told which voxel to stand on, same as when they spawn.
+    pub fn create_message(
+        player: Option<usize>,
+        entity: Entity,
+        position: &Vec3<f32>,
+        dimension: usize,
+    ) -> messages::Message {
+        let Vec3(px, py, pz) = position;
+        let Vec3(vx, vy, vz) = map_world_to_voxel(*px, *py, *pz, dimension);
+
+        let mut json = serde_json::json!({
+            "position": [px, py, pz],
+            "voxel": [vx, vy - 1, vz],
+        });
+
+        match player {
+            Some(id) => json["player"] = serde_json::json!(id.to_string()),
+            None => json["entity"] = serde_json::json!(entity.id().to_string()),
+        }
+
+        let mut message = create_of_type(MessageType::Teleport);
+        message.json = json.to_string();
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        constants::TELEPORT_SEARCH_RADIUS,
+        test_utils::{self, AIR, LAVA, STONE},
+    };
+
+    use super::*;
+
+    #[test]
+    fn is_safe_works() {
+        let mut chunks = test_utils::chunks(1, 4);
+
+        assert!(Teleport::is_safe(&chunks, 0, 4, 0));
+        assert!(!Teleport::is_safe(&chunks, 0, 3, 0));
+        assert!(!Teleport::is_safe(&chunks, 0, 5, 0));
+
+        // two blocks of headroom are needed to stand
+        chunks.update(0, 5, 0, STONE, 0, 0);
+        assert!(!Teleport::is_safe(&chunks, 0, 4, 0));
+
+        chunks.update(0, 5, 0, AIR, 0, 0);
+        chunks.update(0, 6, 0, STONE, 0, 0);
+        assert!(Teleport::is_safe(&chunks, 0, 4, 0));
+    }
+
+    #[test]
+    fn find_landing_skips_lava_and_void() {
+        // no ground at all in the middle column, and a pool of lava next to it
+        let mut chunks = test_utils::chunks_with(1, |vx, vy, vz| vy < 4 && (vx, vz) != (0, 0));
+        for vy in 1..4 {
+            chunks.update(-1, vy, 0, LAVA, 0, 0);
+        }
+
+        assert_eq!(Teleport::find_height(&chunks, 0, 20, 0), None);
+        assert_eq!(Teleport::find_height(&chunks, -1, 20, 0), None);
+        assert_eq!(Teleport::find_height(&chunks, 1, 20, 0), Some(4));
+
+        assert_eq!(
+            Teleport::find_landing(&chunks, &Vec3(0, 20, 0), TELEPORT_SEARCH_RADIUS),
+            Some(Vec3(0, 4, -1))
+        );
+    }
+
+    #[test]
+    fn find_landing_stays_in_radius() {
+        // ground only further than three columns out
+        let chunks = test_utils::chunks_with(1, |vx, vy, vz| vy < 4 && vx * vx + vz * vz > 9);
+
+        assert_eq!(Teleport::find_landing(&chunks, &Vec3(0, 20, 0), 3), None);
+
+        let Vec3(vx, vy, vz) = Teleport::find_landing(&chunks, &Vec3(0, 20, 0), 4).unwrap();
+        assert_eq!(vy, 4);
+        assert!(vx * vx + vz * vz > 9 && vx * vx + vz * vz <= 16);
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use log::warn;
+
+use crate::constants::{MAX_CATCH_UP_TICKS, TICK_TIMING_SAMPLES};
+
+/// Stages a tick's time is split into when reported
+pub const TICK_STAGES: [&str; 4] = ["physics", "lighting", "meshing", "network"];
+
+/// Rolling durations of the last ticks, split by stage
+#[derive(Default)]
+pub struct Timings {
+    current: HashMap<&'static str, f32>,
+    samples: HashMap<&'static str, VecDeque<f32>>,
+    ticks: VecDeque<Instant>,
+}
+
+impl Timings {
+    /// Add time spent on a stage during the current tick, in seconds
+    pub fn record(&mut self, stage: &'static str, secs: f32) {
+        *self.current.entry(stage).or_insert(0.0) += secs;
+    }
+
+    /// Close off the current tick, which took `secs` seconds overall
+    pub fn end_tick(&mut self, secs: f32) {
+        self.record("total", secs);
+
+        for stage in TICK_STAGES.iter().chain(["total"].iter()) {
+            let secs = self.current.remove(stage).unwrap_or(0.0);
+            let samples = self.samples.entry(stage).or_insert_with(VecDeque::new);
+
+            samples.push_back(secs);
+            if samples.len() > TICK_TIMING_SAMPLES {
+                samples.pop_front();
+            }
+        }
+
+        self.current.clear();
+
+        self.ticks.push_back(Instant::now());
+        if self.ticks.len() > TICK_TIMING_SAMPLES {
+            self.ticks.pop_front();
+        }
+    }
+
+    /// Average seconds a stage took per tick over the last ticks
+    pub fn average(&self, stage: &str) -> f32 {
+        match self.samples.get(stage) {
+            Some(samples) if !samples.is_empty() => {
+                samples.iter().sum::<f32>() / samples.len() as f32
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Ticks actually run per second over the last ticks
+    pub fn tps(&self) -> f32 {
+        match (self.ticks.front(), self.ticks.back()) {
+            (Some(first), Some(last)) if self.ticks.len() > 1 => {
+                let secs = last.duration_since(*first).as_secs_f32();
+                if secs > 0.0 {
+                    (self.ticks.len() - 1) as f32 / secs
+                } else {
+                    0.0
+                }
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Resource running the world at a fixed number of ticks per second
+///
+/// The world is polled more often than it ticks. Real time passed is accumulated, and as many
+/// fixed-length ticks as fit in it are run, so slow ticks are caught up on afterwards.
+pub struct Ticker {
+    pub tps: f32,
+    pub timings: Arc<Mutex<Timings>>,
+    /// Ticks run so far, numbering updates so that clients can put them in order
+    pub tick: u32,
+
+    accumulator: f32,
+    prev_time: Instant,
+}
+
+impl Ticker {
+    pub fn new(tps: f32) -> Self {
+        Self {
+            tps,
+            timings: Arc::new(Mutex::new(Timings::default())),
+            tick: 0,
+            accumulator: 0.0,
+            prev_time: Instant::now(),
+        }
+    }
+
+    /// Length of a single tick, in seconds
+    pub fn step(&self) -> f32 {
+        1.0 / self.tps
+    }
+
+    /// Accumulate the time passed since last called, returning how many ticks are due
+    pub fn advance(&mut self) -> u32 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.prev_time).as_secs_f32();
+        self.prev_time = now;
+
+        self.consume(elapsed)
+    }
+
+    fn consume(&mut self, elapsed: f32) -> u32 {
+        let step = self.step();
+        self.accumulator += elapsed;
+
+        let due = (self.accumulator / step).floor() as u32;
+
+        if due > MAX_CATCH_UP_TICKS {
+            warn!(
+                "Can't keep up, skipping {} ticks.",
+                due - MAX_CATCH_UP_TICKS
+            );
+            self.accumulator = 0.0;
+            return MAX_CATCH_UP_TICKS;
+        }
+
+        self.accumulator -= due as f32 * step;
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catch_up_works() {
+        let mut ticker = Ticker::new(20.0);
+
+        assert_eq!(ticker.consume(0.02), 0);
+        assert_eq!(ticker.consume(0.04), 1);
+        assert_eq!(ticker.consume(0.16), 3);
+
+        // too far behind, the rest is dropped
+        assert_eq!(ticker.consume(100.0), MAX_CATCH_UP_TICKS);
+        assert_eq!(ticker.consume(0.0), 0);
+    }
+}
@@ -0,0 +1,126 @@
+use server_common::vec::Vec3;
+
+use crate::gen::blocks::BlockRotation;
+
+use super::{edit::VoxelState, registry::Registry};
+
+/// A turn around the y axis in quarter turns, after an optional mirror along x, so that pasted
+/// and generated structures can face any way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Transform {
+    /// Quarter turns, the same way y rotations turn blocks
+    pub turns: u32,
+    /// Whether x is flipped before turning
+    pub mirror: bool,
+}
+
+impl Transform {
+    pub fn new(turns: u32, mirror: bool) -> Self {
+        Self {
+            turns: turns % 4,
+            mirror,
+        }
+    }
+
+    /// Where an offset from the origin of a structure ends up
+    pub fn voxel(&self, Vec3(x, y, z): &Vec3<i32>) -> Vec3<i32> {
+        let x = if self.mirror { -x } else { *x };
+        (0..self.turns).fold(Vec3(x, *y, *z), |Vec3(x, y, z), _| Vec3(z, y, -x))
+    }
+
+    /// How a block's rotation changes, so that it faces the same way relative to the rest of
+    /// the structure
+    ///
+    /// Blocks facing up or down spin along with the structure, while blocks on their side are
+    /// turned to face another side.
+    pub fn rotation(&self, rotation: u32, y_rotation: u32) -> (u32, u32) {
+        use BlockRotation::*;
+
+        let spin = |degrees: u32, by: i32| (degrees as i32 + by).rem_euclid(360) as u32;
+
+        let mut block = BlockRotation::encode(rotation, y_rotation);
+
+        if self.mirror {
+            block = match block {
+                PX(rot) => NX(rot),
+                NX(rot) => PX(rot),
+                PY(rot) => PY(spin(0, -(rot as i32))),
+                NY(rot) => NY(spin(0, -(rot as i32))),
+                side => side,
+            };
+        }
+
+        for _ in 0..self.turns {
+            block = match block {
+                PX(rot) => NZ(rot),
+                NZ(rot) => NX(rot),
+                NX(rot) => PZ(rot),
+                PZ(rot) => PX(rot),
+                PY(rot) => PY(spin(rot, 90)),
+                // upside down, the spin goes the other way
+                NY(rot) => NY(spin(rot, -90)),
+            };
+        }
+
+        BlockRotation::decode(&block)
+    }
+
+    /// A voxel's state once it's been moved along with the rest, left as is for blocks that
+    /// can't be turned
+    pub fn state(&self, registry: &Registry, state: &VoxelState) -> VoxelState {
+        let block = registry.get_block_by_id(state.id);
+        let (rotation, y_rotation) = self.rotation(state.rotation, state.y_rotation);
+
+        VoxelState {
+            rotation: if block.rotatable {
+                rotation
+            } else {
+                state.rotation
+            },
+            y_rotation: if block.y_rotatable {
+                y_rotation
+            } else {
+                state.y_rotation
+            },
+            ..*state
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{self, BED, STONE};
+
+    use super::*;
+
+    #[test]
+    fn transform_works() {
+        let offset = Vec3(2, 1, 0);
+
+        assert_eq!(Transform::default().voxel(&offset), offset);
+        assert_eq!(Transform::new(1, false).voxel(&offset), Vec3(0, 1, -2));
+        assert_eq!(Transform::new(2, true).voxel(&offset), Vec3(2, 1, 0));
+        assert_eq!(Transform::new(4, false), Transform::default());
+
+        // a quarter turn spins blocks standing up by two eighths, and turns those on their side
+        let turn = Transform::new(1, false);
+        assert_eq!(turn.rotation(0, 0), (0, 2));
+        assert_eq!(turn.rotation(0, 7), (0, 1));
+        assert_eq!(turn.rotation(2, 0), (5, 0));
+
+        let mirror = Transform::new(0, true);
+        assert_eq!(mirror.rotation(0, 2), (0, 6));
+        assert_eq!(mirror.rotation(2, 0), (3, 0));
+        assert_eq!(mirror.rotation(4, 0), (4, 0));
+
+        let registry = test_utils::registry();
+        let turn = Transform::new(2, false);
+        let state = |id| VoxelState {
+            y_rotation: 2,
+            ..VoxelState::block(id)
+        };
+
+        assert_eq!(turn.state(&registry, &state(BED)).y_rotation, 6);
+        assert_eq!(turn.state(&registry, &state(STONE)), state(STONE));
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use server_common::vec::Vec3;
+
+use crate::constants::{MAX_WAYPOINTS, MAX_WAYPOINT_NAME_LENGTH};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum WaypointError {
+    InvalidName,
+    Taken,
+    Unknown,
+    TooMany,
+    NotAdmin,
+    Usage(&'static str),
+}
+
+impl fmt::Display for WaypointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaypointError::InvalidName => write!(
+                f,
+                "Waypoint names are up to {} letters, digits, - and _.",
+                MAX_WAYPOINT_NAME_LENGTH
+            ),
+            WaypointError::Taken => write!(f, "There's a waypoint of that name already."),
+            WaypointError::Unknown => write!(f, "There's no waypoint of that name."),
+            WaypointError::TooMany => write!(f, "Waypoints are limited to {}.", MAX_WAYPOINTS),
+            WaypointError::NotAdmin => write!(f, "Only admins can do that."),
+            WaypointError::Usage(usage) => write!(f, "Usage: {}", usage),
+        }
+    }
+}
+
+/// A named voxel for clients to point players towards
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Waypoint {
+    pub name: String,
+    pub voxel: Vec3<i32>,
+}
+
+/// Add a waypoint to a list, keeping names unique and the list within `MAX_WAYPOINTS`
+pub fn add_waypoint(
+    list: &mut Vec<Waypoint>,
+    name: &str,
+    voxel: Vec3<i32>,
+) -> Result<(), WaypointError> {
+    let valid = !name.is_empty()
+        && name.chars().count() <= MAX_WAYPOINT_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !valid {
+        return Err(WaypointError::InvalidName);
+    }
+
+    if list
+        .iter()
+        .any(|waypoint| waypoint.name.eq_ignore_ascii_case(name))
+    {
+        return Err(WaypointError::Taken);
+    }
+
+    if list.len() >= MAX_WAYPOINTS {
+        return Err(WaypointError::TooMany);
+    }
+
+    list.push(Waypoint {
+        name: name.to_owned(),
+        voxel,
+    });
+
+    Ok(())
+}
+
+/// Take a waypoint of a name off of a list
+pub fn remove_waypoint(list: &mut Vec<Waypoint>, name: &str) -> Result<Waypoint, WaypointError> {
+    let index = list
+        .iter()
+        .position(|waypoint| waypoint.name.eq_ignore_ascii_case(name))
+        .ok_or(WaypointError::Unknown)?;
+
+    Ok(list.remove(index))
+}
+
+/// Waypoints of the players in a world, by player id
+///
+/// Every player only sees their own, along with the markers admins set for everyone which
+/// are kept in the world's metadata.
+#[derive(Debug, Default)]
+pub struct Waypoints {
+    players: HashMap<usize, Vec<Waypoint>>,
+}
+
+impl Waypoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, player_id: usize) -> &[Waypoint] {
+        self.players
+            .get(&player_id)
+            .map_or(&[], |list| list.as_slice())
+    }
+
+    pub fn get_mut(&mut self, player_id: usize) -> &mut Vec<Waypoint> {
+        self.players.entry(player_id).or_default()
+    }
+
+    pub fn set(&mut self, player_id: usize, list: Vec<Waypoint>) {
+        self.players.insert(player_id, list);
+    }
+
+    /// Forget a player's waypoints as they leave
+    pub fn remove(&mut self, player_id: usize) {
+        self.players.remove(&player_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waypoints_work() {
+        let mut waypoints = Waypoints::new();
+        let list = waypoints.get_mut(1);
+
+        assert_eq!(add_waypoint(list, "home", Vec3(1, 2, 3)), Ok(()));
+        assert_eq!(
+            add_waypoint(list, "Home", Vec3(0, 0, 0)),
+            Err(WaypointError::Taken)
+        );
+        assert_eq!(
+            add_waypoint(list, "<b>", Vec3(0, 0, 0)),
+            Err(WaypointError::InvalidName)
+        );
+
+        for i in 1..MAX_WAYPOINTS {
+            add_waypoint(list, &i.to_string(), Vec3(0, 0, 0)).unwrap();
+        }
+        assert_eq!(
+            add_waypoint(list, "more", Vec3(0, 0, 0)),
+            Err(WaypointError::TooMany)
+        );
+
+        assert_eq!(remove_waypoint(list, "HOME").unwrap().voxel, Vec3(1, 2, 3));
+        assert_eq!(remove_waypoint(list, "home"), Err(WaypointError::Unknown));
+        assert_eq!(waypoints.get(1).len(), MAX_WAYPOINTS - 1);
+        assert!(waypoints.get(2).is_empty());
+    }
+}
@@ -1,74 +1,150 @@
 #![allow(dead_code)]
 
 use actix::Recipient;
-use log::info;
+use log::{info, warn};
+use tracing::info_span;
 
 use ansi_term::Colour::Yellow;
 
 use specs::shred::{Fetch, FetchMut, Resource};
 
-use std::io::Write;
-use std::time::Instant;
-use std::{collections::VecDeque, fs::File};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rand::Rng;
+use rhai::{FuncArgs, INT};
+use uuid::Uuid;
 
-use specs::{Builder, DispatcherBuilder, World as ECSWorld, WorldExt};
+use specs::{Builder, DispatcherBuilder, Entity, Join, World as ECSWorld, WorldExt};
 
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 
 use server_common::quaternion::Quaternion;
 
+use crate::comp::attack_cooldown::AttackCooldown;
+use crate::comp::behavior::Behavior;
 use crate::comp::brain::Brain;
 use crate::comp::curr_chunk::CurrChunk;
+use crate::comp::dropped_item::DroppedItem;
+use crate::comp::effects::{EffectKind, Effects};
 use crate::comp::etype::EType;
+use crate::comp::experience::{Experience, ExperienceOrb};
+use crate::comp::falling_block::FallingBlock;
+use crate::comp::footsteps::Footsteps;
+use crate::comp::health::Health;
+use crate::comp::hostile::Hostile;
 use crate::comp::id::Id;
+use crate::comp::inventory::{Inventory, ItemStack};
+use crate::comp::mount::{Mountable, Mounted};
 use crate::comp::name::Name;
+use crate::comp::portal_cooldown::PortalCooldown;
+use crate::comp::profile::{clean_name, unique_name, PlayerProfile, ProfileError};
 use crate::comp::rotation::Rotation;
 use crate::comp::target::Target;
 use crate::comp::view_radius::ViewRadius;
 use crate::comp::walk_towards::WalkTowards;
+use crate::gen::presets::FlatLayer;
+use crate::network::auth::Identity;
 use crate::network::models::{create_of_type, ChatType};
+use crate::network::outbox::SharedOutbox;
 use crate::sys::{
-    BroadcastSystem, ChunkingSystem, EntitiesSystem, GenerationSystem, MeshingSystem,
-    ObserveSystem, PathFindSystem, PeersSystem, SearchSystem, WalkTowardsSystem,
+    BehaviorSystem, BorderSystem, BroadcastSystem, ChunkingSystem, CircuitsSystem, EffectsSystem,
+    EntitiesSystem, EquipmentSystem, ExperienceSystem, GenerationSystem, GravitySystem,
+    HealthSystem, HorizonSystem, HostileSystem, ItemsSystem, KnockbackSystem, MapSystem,
+    MeshingSystem, MountsSystem, ObserveSystem, ParticlesSystem, PathFindSystem, PeersSystem,
+    PortalsSystem, ProfilesSystem, RandomTickSystem, RemeshSystem, SearchSystem, SoundsSystem,
+    Timed, WalkTowardsSystem,
 };
 use crate::{
     comp::rigidbody::RigidBody,
-    network::message::{JoinResult, Message},
+    network::message::{Flush, JoinResult},
 };
 
+use super::audit::{
+    format_age, AuditAction, AuditError, Audits, EditQuery, EditRecord, PendingAudit,
+};
+use super::border::WorldBorder;
+use super::chat::{sanitize, Chat, ChatChannel, ChatError};
+use super::chat_filter::{ChatFilter, FilterRule, Verdict};
+use super::circuits::{CircuitChanges, CircuitSchedule, Circuits};
+use super::claims::{Claim, ClaimError, ClaimFlags};
+use super::containers::{ContainerError, Containers, SlotRef};
+use super::damage::{DamageEvent, DamageQueue};
+use super::difficulty::Difficulty;
+use super::economy::{Economy, EconomyError};
+use super::edit::{EditError, EditSession, EditSessions, Region, VoxelDiff, VoxelState};
 use super::entities::Entities;
+use super::farming::Farming;
+use super::gamemode::{Abilities, GameMode, GameModes, PlayerAbilities};
+use super::gravity::{Gravity, GravityQueue};
+use super::journal::{JournalEntry, Journals};
 use super::kdtree::KdTree;
+use super::knockback::KnockbackQueue;
+use super::leaves;
+use super::locate::{Locate, LocateError};
+use super::loot::{LootContext, LootTables};
+use super::map::{render_tile, MapTiles};
+use super::metadata::{WorldMetadata, WorldMetrics};
+use super::migrations::Migrations;
+use super::multi_blocks::MultiBlocks;
+use super::particles::{ParticleEvent, ParticleQueue};
+use super::player_data::PlayerData;
+use super::portals::{PortalQueue, Portals};
+use super::profiler::{Profiler, ProfilerError};
+use super::remesh::RemeshQueue;
+use super::replay::{replay_id, ReplayError, ReplayEvent, Replays};
+use super::residency::ChunkResidency;
+use super::rng::WorldRng;
+use super::scripts::{ScriptAction, Scripts};
+use super::sounds::{SoundQueue, Sounds};
+use super::stats::{StatsConfig, StatsRecorder, StatsSnapshot};
+use super::storage::{Storage, StorageKind, WriteBatch};
+use super::support::Supports;
+use super::teams::{clean_team_name, TeamError, Teams};
+use super::teleport::Teleport;
+use super::ticker::{Ticker, TICK_STAGES};
+use super::transform::Transform;
+use super::waypoints::{add_waypoint, remove_waypoint, Waypoint, WaypointError, Waypoints};
 use super::{
     super::{
-        constants::WORLD_DATA_FILE,
-        engine::chunks::MeshLevel,
+        constants::{
+            AUDIT_LOOKUP_LIMIT, AUDIT_ROLLBACK_LIMIT, CHAT_FILTER_MUTE_SECONDS, CONSOLE_ID,
+            DEFAULT_CLAIM_RADIUS, DEFAULT_EFFECT_SECONDS, LEVEL_SEED, LINE_OF_SIGHT_STEPS,
+            LOCAL_CHAT_RADIUS, MAX_CHUNKS_PER_REQUEST, MAX_CLAIM_RADIUS, MAX_REACH_DISTANCE,
+            MAX_SPEED_MULTIPLIER, PLAYER_ATTACK_DAMAGE, PLAYER_ATTACK_KNOCKBACK,
+            PLAYER_INVENTORY_SIZE, PLAYER_MAX_HEALTH, POWER_CONSUMER_DELAY, REPLAY_TRANSFORM_TICKS,
+            SCRIPTS_FOLDER, TELEPORT_LOAD_RADIUS, TELEPORT_SEARCH_RADIUS,
+        },
         network::models::{
-            create_chat_message, create_message, messages, ChunkProtocol, MessageComponents,
-            MessageType,
+            create_chat_message, create_chunk_reply, create_experience_message,
+            create_health_message, create_horizon_reply, create_inventory_message,
+            create_latency_message, create_message, create_mount_message, create_profiles_message,
+            create_waypoints_message, messages, EntityProtocol, MessageComponents, MessageType,
+            PeerProtocol, UpdateProtocol,
         },
         sys::PhysicsSystem,
     },
-    physics::{Physics, PhysicsOptions},
+    physics::{Physics, PhysicsOptions, PhysicsStep},
     players::Player,
 };
 
 use server_common::{
     aabb::Aabb,
+    types::EquipmentSlot,
     vec::{Vec2, Vec3},
 };
 
+use server_utils::convert::{map_voxel_to_chunk, map_world_to_voxel};
+
 use super::chunks::Chunks;
 use super::clock::Clock;
-use super::players::{BroadcastExt, PlayerUpdates, Players};
+use super::players::{is_name_taken, BroadcastExt, PlayerUpdates, Players};
 use super::registry::Registry;
 
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct WorldData {
-    time: f32,
-    tick_speed: f32,
-}
-
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WorldConfig {
@@ -77,15 +153,83 @@ pub struct WorldConfig {
     pub max_height: u32,
     pub max_light_level: u32,
     pub save: bool,
+    /// Whether a saved world is kept in JSON files or in a SQLite database
+    #[serde(default)]
+    pub storage: StorageKind,
     pub chunk_root: String,
     pub render_radius: usize,
+    /// Furthest a player can ask to see, in voxels
+    pub max_render_radius: usize,
+    /// Furthest a player can ask to see far terrain as a heightfield, in voxels, none if 0
+    #[serde(default)]
+    pub max_horizon_radius: usize,
     pub max_loaded_chunks: usize,
     pub sub_chunks: u32,
     pub generation: String,
     pub player_dimensions: Vec3<f32>,
     pub player_head: f32,
     pub max_per_thread: usize,
+    /// Milliseconds between polls of the world, which then runs every tick due
     pub server_tick_rate: u64,
+    /// Ticks the world runs per second
+    pub tps: f32,
+    pub random_tick_speed: u32,
+    /// Whether sunlight floods down from the top of the world
+    pub sky_light: bool,
+    /// Scale applied to horizontal coordinates when walking through a portal
+    pub portal_scale: f32,
+
+    #[serde(default)]
+    pub difficulty: Difficulty,
+
+    /// Whether players can hurt each other
+    #[serde(default = "default_pvp")]
+    pub pvp: bool,
+
+    /// Whether players keep their items when they die, instead of dropping them
+    #[serde(default)]
+    pub keep_inventory: bool,
+
+    /// Whether players on the same team can hurt each other
+    #[serde(default)]
+    pub friendly_fire: bool,
+
+    /// Game mode players join the world in
+    #[serde(default)]
+    pub game_mode: GameMode,
+
+    /// Name of the world portals lead to
+    #[serde(default)]
+    pub portal_to: Option<String>,
+
+    #[serde(default)]
+    pub border: Option<WorldBorder>,
+
+    /// Layers of the "superflat" generation, from the bottom up
+    #[serde(default)]
+    pub flat_layers: Vec<FlatLayer>,
+
+    /// Rules player messages are run through before they're sent
+    #[serde(default)]
+    pub chat_filter: Vec<FilterRule>,
+
+    /// How the time-lapse of the world's stats is recorded, if it is
+    #[serde(default)]
+    pub stats: Option<StatsConfig>,
+
+    /// Physics steps run per second, once a tick if not set
+    #[serde(default)]
+    pub physics_rate: Option<f32>,
+
+    /// Seed to run the world in lockstep with, for simulations that play out the same every
+    /// run: a tick each time the world is polled, with chunks generated and meshed in the tick
+    /// they're queued in
+    #[serde(default)]
+    pub lockstep: Option<u64>,
+}
+
+fn default_pvp() -> bool {
+    true
 }
 
 #[derive(Deserialize, Clone)]
@@ -107,6 +251,8 @@ pub struct World {
     pub name: String,
     pub preload: i16,
     pub description: String,
+
+    scripts: Scripts,
 }
 
 /// Resource of messages to be broadcasted per tick
@@ -121,7 +267,7 @@ impl World {
     /// Instantiate a new voxel world, registers the necessary components and resources
     ///
     /// Attempts to save the world data to its corresponding JSON file.
-    pub fn new(meta: WorldMeta, config: WorldConfig, registry: Registry) -> Self {
+    pub fn new(meta: WorldMeta, config: WorldConfig, registry: Arc<Registry>) -> Self {
         let WorldMeta {
             name,
             description,
@@ -134,26 +280,81 @@ impl World {
         let mut ecs = ECSWorld::new();
 
         // ECS Components
+        ecs.register::<Behavior>();
         ecs.register::<Brain>();
         ecs.register::<CurrChunk>();
+        ecs.register::<Effects>();
         ecs.register::<EType>();
+        ecs.register::<FallingBlock>();
+        ecs.register::<DroppedItem>();
+        ecs.register::<Experience>();
+        ecs.register::<ExperienceOrb>();
+        ecs.register::<Footsteps>();
+        ecs.register::<Health>();
+        ecs.register::<Hostile>();
         ecs.register::<Id>();
+        ecs.register::<Inventory>();
+        ecs.register::<Mountable>();
+        ecs.register::<Mounted>();
         ecs.register::<Target>();
         ecs.register::<Name>();
+        ecs.register::<PortalCooldown>();
+        ecs.register::<AttackCooldown>();
+        ecs.register::<PlayerProfile>();
         ecs.register::<RigidBody>();
         ecs.register::<Rotation>();
         ecs.register::<ViewRadius>();
         ecs.register::<WalkTowards>();
 
         // ECS Resources
+        let chunks = Chunks::new(&name, config.clone(), registry);
+        let containers = Containers::load(chunks.storage.as_ref());
+
+        if let Some(stats) = config.stats.to_owned() {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Time went backwards?")
+                .as_secs();
+            let path = chunks.root_folder.join("stats.jsonl");
+            ecs.insert(StatsRecorder::new(stats, path, now));
+        }
+
+        ecs.insert(Replays::new(chunks.root_folder.join("replays")));
+        ecs.insert(Audits::default());
+
         ecs.insert(name.to_owned());
-        ecs.insert(Chunks::new(&name, config.clone(), registry));
+        ecs.insert(chunks);
+        ecs.insert(containers);
+        ecs.insert(CircuitSchedule::new());
+        ecs.insert(DamageQueue::new());
+        ecs.insert(GravityQueue::new());
+        ecs.insert(KnockbackQueue::new());
+        ecs.insert(LootTables::new());
+        ecs.insert(ParticleQueue::new());
+        ecs.insert(SoundQueue::new());
+        ecs.insert(PortalQueue::new());
+        ecs.insert(RemeshQueue::new());
+        ecs.insert(MapTiles::new());
         ecs.insert(Clock::new(time, tick_speed));
+        ecs.insert(WorldMetadata::new(&config, time, tick_speed));
+        ecs.insert(Ticker::new(config.tps));
+        ecs.insert(WorldRng::new(config.lockstep));
         ecs.insert(KdTree::new());
+        ecs.insert(ChunkResidency::new());
         ecs.insert(Players::new());
+        ecs.insert(GameModes::new());
+        ecs.insert(PlayerAbilities::new());
+        ecs.insert(Economy::new());
+        ecs.insert(Chat::new());
+        ecs.insert(Teams::new());
+        ecs.insert(Waypoints::new());
+        ecs.insert(ChatFilter::new(&config.chat_filter));
+        ecs.insert(EditSessions::new());
+        ecs.insert(Journals::new());
         ecs.insert(PlayerUpdates::new());
         ecs.insert(MessagesQueue::new());
         ecs.insert(Entities::new());
+        ecs.insert(Sounds::new());
         ecs.insert(Physics::new(PhysicsOptions {
             gravity: Vec3(0.0, -24.0, 0.0),
             min_bounce_impulse: 0.1,
@@ -161,6 +362,7 @@ impl World {
             fluid_drag: 0.4,
             fluid_density: 2.0,
         }));
+        ecs.insert(PhysicsStep::new(config.physics_rate));
         ecs.insert(config.clone());
         ecs.insert(meta);
 
@@ -170,8 +372,12 @@ impl World {
             name,
             preload,
             description,
+
+            scripts: Scripts::new(Path::new(SCRIPTS_FOLDER)),
         };
 
+        new_world.update_chat_filters();
+
         if config.save {
             new_world.sync_config();
             new_world.save();
@@ -224,17 +430,32 @@ impl World {
     pub fn add_player(
         &mut self,
         id: Option<usize>,
-        player_name: Option<String>,
-        player_addr: Recipient<Message>,
+        identity: Identity,
+        player_addr: Recipient<Flush>,
+        player_outbox: SharedOutbox,
+        spawn: Option<Vec3<f32>>,
     ) -> JoinResult {
-        let mut id = id.unwrap_or_else(rand::random::<usize>);
+        let mut id = id.unwrap_or_else(|| self.ecs.write_resource::<WorldRng>().gen());
+
+        // logged in players pick up where they left off, unless they're sent somewhere
+        let saved = identity.uuid.and_then(|uuid| self.load_player_data(&uuid));
+        let spawn = spawn.or_else(|| saved.as_ref().map(|data| data.position.clone()));
 
         let clock = self.read_resource::<Clock>();
         let chunks = self.read_resource::<Chunks>();
 
         let time = clock.time;
         let tick_speed = clock.tick_speed;
-        let spawn = [0, chunks.get_max_height(0, 0) as i32, 0];
+        let spawn = match spawn {
+            Some(Vec3(x, y, z)) => {
+                let Vec3(vx, vy, vz) = map_world_to_voxel(x, y, z, chunks.config.dimension);
+                [vx, vy, vz]
+            }
+            None => {
+                let Vec3(vx, vy, vz) = self.spawn_voxel();
+                [vx, vy, vz]
+            }
+        };
         let passables = chunks.registry.get_passable_solids();
 
         drop(clock);
@@ -244,7 +465,7 @@ impl World {
 
         loop {
             if players.contains_key(&id) {
-                id = rand::random::<usize>();
+                id = self.ecs.write_resource::<WorldRng>().gen();
             } else {
                 break;
             }
@@ -252,18 +473,42 @@ impl World {
 
         drop(players);
 
+        // anonymous players are named by their client once they're in
+        let player_name = identity
+            .name
+            .as_deref()
+            .and_then(clean_name)
+            .map(|name| self.claim_name(id, &name, identity.uuid.is_some()));
+
         let config = self.read_resource::<WorldConfig>();
         let dimension = config.player_dimensions.clone();
         let render_radius = config.render_radius as i16;
         let head = config.player_head;
+        let game_mode = saved
+            .as_ref()
+            .map_or(config.game_mode, |data| data.game_mode);
+        let abilities = saved
+            .as_ref()
+            .map_or_else(Abilities::default, |data| data.abilities);
 
         drop(config);
 
+        let experience = saved.as_ref().map_or(0, |data| data.experience);
+        let balance = saved.as_ref().map_or(0, |data| data.balance);
+        let team = saved.as_ref().and_then(|data| data.team.to_owned());
+        let waypoints = saved
+            .as_ref()
+            .map_or_else(Vec::new, |data| data.waypoints.to_owned());
+        let inventory = saved
+            .map(|data| data.inventory)
+            .unwrap_or_else(|| Inventory::new(PLAYER_INVENTORY_SIZE));
+
         let entity = self
             .ecs_mut()
             .create_entity()
             .with(Id::new(id.to_owned()))
-            .with(Name::new(&player_name))
+            .with(Name::default())
+            .with(PlayerProfile::new(player_name.to_owned()))
             .with(RigidBody::new(
                 Aabb::new(
                     &Vec3(spawn[0] as f32, spawn[1] as f32, spawn[2] as f32),
@@ -279,18 +524,54 @@ impl World {
             .with(Rotation::new(0.0, 0.0, 0.0, 0.0))
             .with(CurrChunk::new())
             .with(ViewRadius::new(render_radius))
+            .with(inventory)
+            .with(Health::new(PLAYER_MAX_HEALTH))
+            .with(Experience::new(experience))
+            .with(Effects::new())
+            .with(Footsteps::new())
+            .with(PortalCooldown::new())
+            .with(AttackCooldown::new())
             .build();
 
         let mut players = self.write_resource::<Players>();
 
+        let script_name = player_name.clone().unwrap_or_else(|| "Somebody".to_owned());
+
         let new_player = Player {
             entity,
             name: player_name,
             addr: player_addr,
+            outbox: player_outbox,
+            uuid: identity.uuid,
+            admin: identity.admin,
+            bypass_claims: false,
+            latency: None,
             requested_chunks: VecDeque::default(),
+            pending_chunks: Default::default(),
+            requested_horizons: VecDeque::default(),
         };
 
         players.insert(id, new_player);
+        drop(players);
+
+        self.write_resource::<GameModes>().insert(id, game_mode);
+        self.write_resource::<PlayerAbilities>()
+            .insert(id, abilities);
+        self.write_resource::<Economy>().set(id, balance);
+
+        if let Some(team) = team {
+            self.write_resource::<Teams>().join(id, &team);
+        }
+
+        self.write_resource::<Waypoints>().set(id, waypoints);
+
+        self.send_abilities(id);
+        self.send_inventory(id);
+        self.send_experience(id);
+        self.send_profiles(id);
+        self.send_waypoints(id);
+
+        self.run_scripts(None, "on_join", (script_name,));
 
         JoinResult {
             id,
@@ -298,6 +579,7 @@ impl World {
             tick_speed,
             spawn,
             passables,
+            game_mode: game_mode.name().to_owned(),
         }
     }
 
@@ -316,6 +598,41 @@ impl World {
         let player = player.unwrap();
         drop(players);
 
+        if let Some(uuid) = player.uuid {
+            if let Some(data) = self.player_data(*player_id, player.entity) {
+                self.read_resource::<Chunks>().storage.write(WriteBatch {
+                    players: vec![(uuid, data.to_saved())],
+                    ..Default::default()
+                });
+            }
+        }
+
+        self.write_resource::<Chunks>().unwatch(*player_id);
+        self.write_resource::<GameModes>().remove(player_id);
+        self.write_resource::<PlayerAbilities>().remove(player_id);
+        self.write_resource::<Economy>().remove(*player_id);
+        self.write_resource::<Chat>().remove(*player_id);
+        self.write_resource::<Teams>().remove(*player_id);
+        self.write_resource::<Waypoints>().remove(*player_id);
+        self.write_resource::<Replays>().stop_playback(*player_id);
+        self.write_resource::<EditSessions>().remove(player_id);
+        self.write_resource::<Journals>().remove(player_id);
+
+        let closed = self.write_resource::<Containers>().close_all(*player_id);
+        closed.iter().for_each(|voxel| {
+            message_queue.push(container_animation(voxel, "close"));
+        });
+
+        if let Some(mounted) = self.ecs.read_component::<Mounted>().get(player.entity) {
+            if let Some(mountable) = self
+                .ecs
+                .write_component::<Mountable>()
+                .get_mut(mounted.mount)
+            {
+                mountable.rider = None;
+            }
+        }
+
         let player_name = player.name.unwrap_or_else(|| "Somebody".to_owned());
 
         self.ecs_mut()
@@ -338,9 +655,226 @@ impl World {
 
         message_queue.into_iter().for_each(|message| {
             self.broadcast(&message, vec![], vec![]);
+        });
+
+        self.run_scripts(None, "on_leave", (player_name,));
+    }
+
+    /// Settle who goes by a name: logged in players take it from anonymous ones, who get
+    /// numbered instead, and everyone else numbers theirs until it's free
+    fn claim_name(&mut self, player_id: usize, name: &str, logged_in: bool) -> String {
+        if logged_in {
+            let impostors = self
+                .read_resource::<Players>()
+                .iter()
+                .filter(|(&id, player)| {
+                    id != player_id
+                        && player.uuid.is_none()
+                        && player
+                            .name
+                            .as_ref()
+                            .map_or(false, |taken| taken.eq_ignore_ascii_case(name))
+                })
+                .map(|(&id, _)| id)
+                .collect::<Vec<_>>();
+
+            impostors
+                .into_iter()
+                .for_each(|impostor| self.give_up_name(impostor, name));
+        }
+
+        let players = self.read_resource::<Players>();
+        unique_name(name, |candidate| {
+            is_name_taken(&players, candidate, player_id)
         })
     }
 
+    /// Number a player's name so that somebody else can go by it, letting them know
+    fn give_up_name(&mut self, player_id: usize, name: &str) {
+        let renamed = {
+            let players = self.read_resource::<Players>();
+            unique_name(name, |candidate| {
+                candidate.eq_ignore_ascii_case(name)
+                    || is_name_taken(&players, candidate, player_id)
+            })
+        };
+
+        let message = create_chat_message(
+            MessageType::Message,
+            ChatType::Info,
+            "",
+            &format!("{} is taken, you now go by {}.", name, renamed),
+        );
+
+        self.rename_player(player_id, renamed);
+        self.broadcast(&message, vec![player_id], vec![]);
+    }
+
+    /// Change the name a player goes by, everyone hears of it through their profile
+    fn rename_player(&mut self, player_id: usize, name: String) {
+        let entity = match self.write_resource::<Players>().get_mut(&player_id) {
+            Some(player) => {
+                player.name = Some(name.to_owned());
+                player.entity
+            }
+            None => return,
+        };
+
+        // players who haven't been announced yet keep no name tag, so that they still are
+        if let Some(Name(Some(tag))) = self.ecs.write_component::<Name>().get_mut(entity) {
+            *tag = name.to_owned();
+        }
+
+        if let Some(profile) = self.ecs.write_component::<PlayerProfile>().get_mut(entity) {
+            profile.set_display_name(name);
+        }
+    }
+
+    /// Find an online player by the name they go by, ignoring case
+    fn find_player_by_name(&self, name: &str) -> Option<usize> {
+        self.read_resource::<Players>()
+            .iter()
+            .find(|(_, player)| {
+                player
+                    .name
+                    .as_ref()
+                    .map_or(false, |taken| taken.eq_ignore_ascii_case(name))
+            })
+            .map(|(&id, _)| id)
+    }
+
+    /// Tell a new player how everyone else in the world appears
+    fn send_profiles(&mut self, player_id: usize) {
+        use specs::Join;
+
+        let message = {
+            let ids = self.ecs.read_component::<Id>();
+            let profiles = self.ecs.read_component::<PlayerProfile>();
+
+            let others = (&ids, &profiles)
+                .join()
+                .filter(|(id, profile)| id.0 != player_id && profile.display_name.is_some())
+                .map(|(id, profile)| (id.0, profile))
+                .collect::<Vec<_>>();
+
+            if others.is_empty() {
+                return;
+            }
+
+            create_profiles_message(&others)
+        };
+
+        self.broadcast(&message, vec![player_id], vec![]);
+    }
+
+    fn is_admin(&self, player_id: usize) -> bool {
+        player_id == CONSOLE_ID
+            || self
+                .read_resource::<Players>()
+                .get(&player_id)
+                .map_or(false, |player| player.admin)
+    }
+
+    /// The player an admin command acts on: the one named, or whoever ran it
+    fn admin_target(&self, by: usize, name: Option<&str>) -> Result<usize, ProfileError> {
+        if !self.is_admin(by) {
+            return Err(ProfileError::NotAdmin);
+        }
+
+        match name {
+            Some(name) => self
+                .find_player_by_name(name)
+                .ok_or(ProfileError::UnknownPlayer),
+            None => Ok(by),
+        }
+    }
+
+    /// Rename a player at the request of `by`. Only admins may rename others, or take a name
+    /// from whoever goes by it, who then gets numbered.
+    fn nickname(
+        &mut self,
+        by: usize,
+        player_id: usize,
+        name: &str,
+    ) -> Result<String, ProfileError> {
+        let admin = self.is_admin(by);
+
+        if by != player_id && !admin {
+            return Err(ProfileError::NotAdmin);
+        }
+
+        let name = clean_name(name).ok_or(ProfileError::InvalidName)?;
+
+        let holders = self
+            .read_resource::<Players>()
+            .iter()
+            .filter(|(&id, player)| {
+                id != player_id
+                    && player
+                        .name
+                        .as_ref()
+                        .map_or(false, |taken| taken.eq_ignore_ascii_case(&name))
+            })
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+
+        if !holders.is_empty() && !admin {
+            return Err(ProfileError::Taken(name));
+        }
+
+        holders
+            .into_iter()
+            .for_each(|holder| self.give_up_name(holder, &name));
+
+        self.rename_player(player_id, name.to_owned());
+
+        Ok(name)
+    }
+
+    fn update_profile<T>(
+        &mut self,
+        player_id: usize,
+        update: impl FnOnce(&mut PlayerProfile) -> T,
+    ) -> Option<T> {
+        let entity = self.get_player_entity(player_id)?;
+
+        self.ecs
+            .write_component::<PlayerProfile>()
+            .get_mut(entity)
+            .map(update)
+    }
+
+    /// Store a player's latency, and let everyone know for their lists of players
+    pub fn set_latency(&mut self, player_id: usize, latency: u32) {
+        match self.write_resource::<Players>().get_mut(&player_id) {
+            Some(player) => player.latency = Some(latency),
+            None => return,
+        }
+
+        let message = create_latency_message(&[(player_id, latency)]);
+        self.broadcast_lazy(&message, vec![], vec![], 0);
+    }
+
+    /// List who's online along with their latency, for `/list`
+    fn list_players(&self) -> String {
+        let players = self.read_resource::<Players>();
+
+        let mut entries = players
+            .values()
+            .filter_map(|player| {
+                let name = player.name.as_ref()?;
+
+                Some(match player.latency {
+                    Some(latency) => format!("{} ({}ms)", name, latency),
+                    None => name.to_owned(),
+                })
+            })
+            .collect::<Vec<_>>();
+        entries.sort();
+
+        format!("{} online: {}", entries.len(), entries.join(", "))
+    }
+
     /// Broadcast a message instantly
     ///
     /// Suggested against, use message_queue instead.
@@ -367,17 +901,101 @@ impl World {
         ))
     }
 
-    /// Handles server-side chunk request
+    /// Hands a message from a player over to whatever handles its type
+    pub fn on_message(&mut self, player_id: usize, raw: messages::Message) {
+        match raw.r#type() {
+            // the dead can't touch anything until they respawn
+            MessageType::Update
+            | MessageType::Container
+            | MessageType::Interact
+            | MessageType::Inventory
+                if self.is_dead(player_id) => {}
+            MessageType::Request => self.on_chunk_request(player_id, raw),
+            MessageType::Config => self.on_config(player_id, raw),
+            MessageType::Update => self.on_update(player_id, raw),
+            MessageType::Peer => self.on_peer(player_id, raw),
+            MessageType::Message => self.on_chat_message(player_id, raw),
+            MessageType::Container => self.on_container(player_id, raw),
+            MessageType::Interact => self.on_interact(player_id, raw),
+            MessageType::Inventory => self.on_inventory(player_id, raw),
+            MessageType::Respawn => self.on_respawn(player_id),
+            _ => {}
+        }
+    }
+
+    /// Handles a client's request for the chunks it lists as `[x, z]` pairs. They're sent
+    /// once they're ready, with a notice for the ones that aren't yet.
+    ///
+    /// Chunks past the player's view never finish generating, so they're turned away along
+    /// with the ones past the request's limit.
     pub fn on_chunk_request(&mut self, player_id: usize, msg: messages::Message) {
-        let mut players = self.write_resource::<Players>();
+        let json = match msg.parse_json() {
+            Ok(json) => json,
+            Err(_) => return,
+        };
 
-        let json = msg.parse_json().unwrap();
+        let requested = json["chunks"]
+            .as_array()
+            .map(|pairs| {
+                pairs
+                    .iter()
+                    .filter_map(|pair| {
+                        Some(Vec2(pair[0].as_i64()? as i32, pair[1].as_i64()? as i32))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        // chunks past the meshes, drawn as heightfields
+        let horizons = json["horizons"]
+            .as_array()
+            .map(|pairs| {
+                pairs
+                    .iter()
+                    .filter_map(|pair| {
+                        Some(Vec2(pair[0].as_i64()? as i32, pair[1].as_i64()? as i32))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let (accepted, rejected): (Vec<_>, Vec<_>) =
+            requested.into_iter().enumerate().partition(|(i, coords)| {
+                *i < MAX_CHUNKS_PER_REQUEST && self.is_in_view(player_id, coords)
+            });
+
+        let (accepted_horizons, rejected_horizons): (Vec<_>, Vec<_>) =
+            horizons.into_iter().enumerate().partition(|(i, coords)| {
+                *i < MAX_CHUNKS_PER_REQUEST && self.is_in_horizon(player_id, coords)
+            });
 
-        let cx = json["x"].as_i64().unwrap() as i32;
-        let cz = json["z"].as_i64().unwrap() as i32;
+        let mut players = self.write_resource::<Players>();
 
         if let Some(player) = players.get_mut(&player_id) {
-            player.requested_chunks.push_back(Vec2(cx, cz));
+            for (_, coords) in accepted {
+                if !player.requested_chunks.contains(&coords) {
+                    player.requested_chunks.push_back(coords);
+                }
+            }
+
+            for (_, coords) in accepted_horizons {
+                if !player.requested_horizons.contains(&coords) {
+                    player.requested_horizons.push_back(coords);
+                }
+            }
+
+            if !rejected.is_empty() {
+                let rejected = rejected.into_iter().map(|(_, c)| c).collect::<Vec<_>>();
+                player.send(&create_chunk_reply(&[], &rejected));
+            }
+
+            if !rejected_horizons.is_empty() {
+                let rejected = rejected_horizons
+                    .into_iter()
+                    .map(|(_, c)| c)
+                    .collect::<Vec<_>>();
+                player.send(&create_horizon_reply(&rejected));
+            }
         }
     }
 
@@ -401,6 +1019,19 @@ impl World {
         // damn?
         drop(clock);
 
+        let mut json = json;
+
+        // the view distance is the player's own, not something to share with everyone
+        if let Some(radius) = json.as_object_mut().and_then(|o| o.remove("renderRadius")) {
+            if let Some(radius) = radius.as_u64() {
+                self.set_render_radius(player_id, radius as usize);
+            }
+        }
+
+        if time.is_none() && tick_speed.is_none() {
+            return;
+        }
+
         let mut new_message = create_of_type(MessageType::Config);
         new_message.json = json.to_string();
 
@@ -411,17 +1042,59 @@ impl World {
     ///
     /// Remesh chunks based on which sub-chunks are changed according to internal
     /// chunk caching system.
-    pub fn on_update(&mut self, _player_id: usize, msg: messages::Message) {
-        let mut chunks = self.write_resource::<Chunks>();
+    pub fn on_update(&mut self, player_id: usize, msg: messages::Message) {
+        if !self.get_game_mode(player_id).can_edit() {
+            self.reject_updates(player_id, &msg.updates);
+            return;
+        }
 
-        let &air = chunks.registry.get_id_by_name("Air");
+        let (mut updates, denied): (Vec<_>, Vec<_>) = msg
+            .updates
+            .into_iter()
+            .partition(|update| self.can_build_at(player_id, update.vx, update.vz));
 
-        let mut updates = msg.updates;
-        let mut results = vec![];
+        if !denied.is_empty() {
+            self.reject_updates(player_id, &denied);
+        }
+
+        let held = self.get_player_entity(player_id).and_then(|entity| {
+            self.ecs
+                .read_component::<Inventory>()
+                .get(entity)
+                .and_then(|inventory| inventory.held().map(|held| held.id))
+        });
+
+        // only what's mined the hard way gives experience
+        let survival = self.get_game_mode(player_id) == GameMode::Survival;
 
-        while !updates.is_empty() {
-            let update = updates.pop().unwrap();
+        // blocks can't be placed inside of players or mobs
+        let bodies = (
+            &self.ecs.read_storage::<RigidBody>(),
+            &self.ecs.read_storage::<Health>(),
+        )
+            .join()
+            .map(|(body, _)| body.aabb.clone())
+            .collect::<Vec<_>>();
 
+        let mut chunks = self.ecs.write_resource::<Chunks>();
+        let loot = self.ecs.read_resource::<LootTables>();
+
+        let &air = chunks.registry.get_id_by_name("Air");
+        let tool = held.map(|id| chunks.registry.get_block_by_id(id).name.to_owned());
+
+        let mut results = vec![];
+        let mut rejected = vec![];
+        let mut broken_containers = vec![];
+        let mut harvested = vec![];
+        let mut orbs = vec![];
+        let mut particles = vec![];
+        let mut sounds = vec![];
+        let mut circuits = CircuitChanges::default();
+        let mut supports = vec![];
+        let mut journaled = vec![];
+        let mut rng = self.ecs.write_resource::<WorldRng>();
+
+        while let Some(mut update) = updates.pop() {
             let vx = update.vx;
             let vy = update.vy;
             let vz = update.vz;
@@ -443,15 +1116,158 @@ impl World {
                 continue;
             }
 
+            if current_id != id
+                && !chunks.registry.is_air(id)
+                && (!Supports::is_supported(&chunks, id, vx, vy, vz)
+                    || Supports::is_obstructed(&chunks, id, &Vec3(vx, vy, vz), &bodies))
+            {
+                rejected.push(update);
+                continue;
+            }
+
+            // the rest of a block taking up more voxels has to fit, and goes down along with it
+            let (parts, linked) = if current_id != id {
+                (
+                    MultiBlocks::parts(&chunks.registry, id, &Vec3(vx, vy, vz), y_rotation),
+                    MultiBlocks::linked(&chunks, vx, vy, vz),
+                )
+            } else {
+                (vec![], vec![])
+            };
+
+            if current_id != id
+                && (chunks.registry.get_block_by_id(id).part_of.is_some()
+                    || !parts.iter().all(|(Vec3(px, py, pz), part)| {
+                        *py >= 0
+                            && *py < chunks.config.max_height as i32
+                            && chunks
+                                .get_chunk_by_voxel(*px, *py, *pz)
+                                .map_or(false, |chunk| !chunk.needs_propagation)
+                            && Gravity::can_replace(&chunks, *px, *py, *pz)
+                            && !Supports::is_obstructed(
+                                &chunks,
+                                *part,
+                                &Vec3(*px, *py, *pz),
+                                &bodies,
+                            )
+                    }))
+            {
+                rejected.push(update);
+                continue;
+            }
+
+            if current_id != id && chunks.registry.is_container(current_id) {
+                broken_containers.push(Vec3(vx, vy, vz));
+            }
+
+            if current_id != id {
+                journaled.push(JournalEntry::new(Vec3(vx, vy, vz), current_id, id));
+            }
+
+            // parts drop nothing, the block they're a part of does
+            if current_id != id
+                && chunks
+                    .registry
+                    .get_block_by_id(current_id)
+                    .part_of
+                    .is_none()
+            {
+                let context = LootContext {
+                    tool: tool.clone(),
+                    stage: chunk.get_voxel_stage(vx, vy, vz),
+                    ..Default::default()
+                };
+
+                let items = match loot.roll_block(&chunks.registry, current_id, &context, &mut *rng)
+                {
+                    Some(items) => items,
+                    None => Farming::harvest(&chunks, current_id, context.stage, &mut *rng)
+                        .into_iter()
+                        .collect(),
+                };
+
+                let position = voxel_center(&Vec3(vx, vy, vz), chunks.config.dimension as f32);
+                harvested.extend(items.into_iter().map(|stack| (position.clone(), stack)));
+            }
+
+            let dimension = chunks.config.dimension as f32;
+
+            if current_id != id && chunks.registry.is_air(id) && survival {
+                let points = chunks.registry.get_block_by_id(current_id).experience;
+
+                if points > 0 {
+                    orbs.push((voxel_center(&Vec3(vx, vy, vz), dimension), points));
+                }
+            }
+
+            if chunks.registry.is_air(id) {
+                particles.push(ParticleEvent::block_break(vx, vy, vz, dimension));
+                sounds.push(("block.break", voxel_center(&Vec3(vx, vy, vz), dimension)));
+            } else {
+                sounds.push(("block.place", voxel_center(&Vec3(vx, vy, vz), dimension)));
+            }
+
+            let old_power = Circuits::get_power(&chunks, vx, vy, vz);
+
             chunks.start_caching();
             chunks.update(vx, vy, vz, id, rotation, y_rotation);
+            circuits.extend(Circuits::on_update(&mut chunks, vx, vy, vz, old_power));
+
+            // leaves placed by players shouldn't decay away from logs
+            if chunks.registry.is_leaves(id) {
+                chunks.set_voxel_stage_by_voxel(vx, vy, vz, leaves::PERSISTENT);
+                update.stage = leaves::PERSISTENT;
+            }
+
+            for (Vec3(px, py, pz), part) in parts {
+                let part_id = chunks.get_voxel_by_voxel(px, py, pz);
+                journaled.push(JournalEntry::new(Vec3(px, py, pz), part_id, part));
+
+                chunks.update(px, py, pz, part, rotation, y_rotation);
+                chunks
+                    .get_neighbor_chunk_coords(px, py, pz)
+                    .into_iter()
+                    .for_each(|c| {
+                        chunks.chunk_cache.insert(c);
+                    });
+
+                supports.push(Vec3(px, py + 1, pz));
+                results.push(messages::Update {
+                    vx: px,
+                    vy: py,
+                    vz: pz,
+                    r#type: part,
+                    rotation,
+                    y_rotation,
+                    stage: 0,
+                });
+            }
+
             chunks.stop_caching();
 
+            supports.push(Vec3(vx, vy, vz));
+            supports.push(Vec3(vx, vy + 1, vz));
+            for &(ox, oz) in [(1, 0), (-1, 0), (0, 1), (0, -1)].iter() {
+                supports.push(Vec3(vx + ox, vy, vz + oz));
+            }
+
             let neighbor_chunks = chunks.get_neighbor_chunk_coords(vx, vy, vz);
             neighbor_chunks.into_iter().for_each(|c| {
                 chunks.chunk_cache.insert(c);
             });
 
+            linked.into_iter().for_each(|Vec3(lx, ly, lz)| {
+                updates.push(messages::Update {
+                    vx: lx,
+                    vy: ly,
+                    vz: lz,
+                    r#type: air,
+                    rotation: 0,
+                    y_rotation: 0,
+                    stage: 0,
+                });
+            });
+
             if chunks
                 .registry
                 .is_plant(chunks.get_voxel_by_voxel(vx, vy + 1, vz))
@@ -463,115 +1279,3085 @@ impl World {
                     r#type: air,
                     rotation: 0,
                     y_rotation: 0,
+                    stage: 0,
                 });
             }
 
             results.push(update);
         }
 
-        let cache = chunks.chunk_cache.clone();
-        chunks.clear_cache();
-
-        drop(chunks);
+        // conductors carry their power level in their stage, for clients to render
+        let powered = circuits
+            .powered
+            .iter()
+            .filter_map(|Vec3(vx, vy, vz)| chunks.get_update_protocol(*vx, *vy, *vz))
+            .collect::<Vec<_>>();
 
-        let mut chunk_mesh_protocols = vec![];
+        let chunk_protocols =
+            chunks.take_cache_protocols(&mut self.ecs.write_resource::<RemeshQueue>());
 
-        cache.iter().for_each(|coords| {
-            let mut chunks = self.write_resource::<Chunks>();
+        drop(chunks);
+        drop(loot);
+        drop(rng);
 
-            let levels = chunks.raw(&coords).unwrap().dirty_levels.clone();
-            let mesh_level = MeshLevel::Levels(levels);
+        if !rejected.is_empty() {
+            self.reject_updates(player_id, &rejected);
+        }
 
-            let chunk = chunks.get(&coords, &mesh_level, true).unwrap();
-            chunk_mesh_protocols.push(chunk.get_protocol(true, false, false, mesh_level));
+        self.log_edits(player_id, &journaled);
 
-            drop(chunks);
+        let edited = journaled
+            .iter()
+            .map(|entry| {
+                let Vec3(vx, vy, vz) = entry.voxel;
+                (vx as INT, vy as INT, vz as INT, entry.new_id as INT)
+            })
+            .collect::<Vec<_>>();
+
+        let mut journals = self.write_resource::<Journals>();
+        let journal = journals.entry(player_id).or_default();
+        journaled
+            .into_iter()
+            .for_each(|entry| journal.record(entry));
+        drop(journals);
+
+        self.write_resource::<ParticleQueue>()
+            .append(&mut particles);
+
+        let mut sounds = sounds
+            .into_iter()
+            .filter_map(|(name, position)| self.read_resource::<Sounds>().event(name, position))
+            .collect::<Vec<_>>();
+        self.write_resource::<SoundQueue>().append(&mut sounds);
+
+        let tick = self.read_resource::<Clock>().tick;
+        let mut schedule = self.write_resource::<CircuitSchedule>();
+        circuits.consumers.into_iter().for_each(|voxel| {
+            schedule.schedule(voxel, tick + POWER_CONSUMER_DELAY);
         });
+        drop(schedule);
 
-        let chunks = self.read_resource::<Chunks>();
+        let mut gravity = self.write_resource::<GravityQueue>();
+        supports.into_iter().for_each(|voxel| gravity.push(voxel));
+        drop(gravity);
 
         // First send the message, so borrow checker doesn't freak out
         let mut components = MessageComponents::default_for(MessageType::Update);
-        let mut chunk_protocols: Vec<ChunkProtocol> = cache
-            .iter()
-            .map(|coords| {
-                chunks
-                    .get_chunk(coords)
-                    .unwrap()
-                    .get_protocol(false, false, true, MeshLevel::None)
-            })
-            .collect();
-        chunk_protocols.append(&mut chunk_mesh_protocols);
         components.chunks = Some(chunk_protocols);
+        components.updates = Some(powered);
         let mut new_message = create_message(components);
+        results.append(&mut new_message.updates);
         new_message.updates = results;
 
-        drop(chunks);
-
         self.broadcast(&new_message, vec![], vec![]);
-    }
 
-    /// Adds the player update to the resource `PlayerUpdate`, handled later in an ECS system.
-    pub fn on_peer(&mut self, player_id: usize, msg: messages::Message) {
-        let mut player_updates = self.write_resource::<PlayerUpdates>();
-        player_updates.insert(player_id, msg.peers[0].clone());
-    }
+        let dimension = self.dimension();
+        harvested.into_iter().for_each(|(position, stack)| {
+            DroppedItem::build_entity(self.ecs.create_entity(), stack, &position, dimension)
+                .build();
+        });
 
-    /// Handles an incoming chat message, broadcasts response lazily
-    pub fn on_chat_message(&mut self, player_id: usize, msg: messages::Message) {
-        if let Some(message) = msg.message.clone() {
-            let sender: String = message.sender;
-            let body: String = message.body;
+        orbs.into_iter().for_each(|(position, points)| {
+            ExperienceOrb::build_entity(self.ecs.create_entity(), points, &position, dimension)
+                .build();
+        });
+
+        self.remove_containers(broken_containers);
+
+        if !edited.is_empty() && self.scripts.has_hook("on_block") {
+            let name = self.player_name(player_id);
+
+            edited.into_iter().for_each(|(vx, vy, vz, id)| {
+                self.run_scripts(None, "on_block", (name.to_owned(), vx, vy, vz, id));
+            });
+        }
+    }
+
+    /// Forget the containers at voxels whose blocks are gone, closing them on whoever had them open
+    fn remove_containers(&mut self, voxels: Vec<Vec3<i32>>) {
+        voxels.into_iter().for_each(|voxel| {
+            let container = self.write_resource::<Containers>().remove(&voxel);
+
+            if let Some(mut container) = container {
+                let dimension = self.dimension();
+                let position = voxel_center(&voxel, dimension);
+
+                let dropped = container
+                    .inventory
+                    .slots
+                    .iter_mut()
+                    .filter_map(Option::take)
+                    .collect::<Vec<_>>();
+
+                for stack in dropped {
+                    DroppedItem::build_entity(
+                        self.ecs.create_entity(),
+                        stack,
+                        &position,
+                        dimension,
+                    )
+                    .build();
+                }
+
+                let viewers = container.viewers.into_iter().collect::<Vec<_>>();
+
+                if !viewers.is_empty() {
+                    let message = container_animation(&voxel, "close");
+                    self.broadcast(&message, viewers, vec![]);
+                }
+            }
+        });
+    }
+
+    /// Overwrite voxels in bulk, the way player edits do but without drops, sounds or particles.
+    /// Voxels out of bounds or in chunks that aren't ready are left alone.
+    ///
+    /// Returns what the changed voxels held before, to undo the edit with.
+    pub fn edit_voxels(&mut self, edits: &[(Vec3<i32>, VoxelState)]) -> VoxelDiff {
+        let mut chunks = self.ecs.write_resource::<Chunks>();
+        let max_height = chunks.config.max_height as i32;
+
+        let mut previous = vec![];
+        let mut results = vec![];
+        let mut broken_containers = vec![];
+        let mut circuits = CircuitChanges::default();
+
+        chunks.start_caching();
+
+        for (voxel, state) in edits {
+            let &Vec3(vx, vy, vz) = voxel;
+
+            if vy < 0 || vy >= max_height || !chunks.registry.has_type(state.id) {
+                continue;
+            }
+
+            match chunks.get_chunk_by_voxel(vx, vy, vz) {
+                Some(chunk) if !chunk.needs_propagation => {}
+                _ => continue,
+            }
+
+            let current = VoxelState::read(&chunks, vx, vy, vz);
+            if current == *state {
+                continue;
+            }
+
+            if current.id != state.id && chunks.registry.is_container(current.id) {
+                broken_containers.push(voxel.clone());
+            }
+
+            let old_power = Circuits::get_power(&chunks, vx, vy, vz);
+
+            chunks.update(vx, vy, vz, state.id, state.rotation, state.y_rotation);
+            chunks.set_voxel_stage_by_voxel(vx, vy, vz, state.stage);
+            circuits.extend(Circuits::on_update(&mut chunks, vx, vy, vz, old_power));
+
+            let neighbor_chunks = chunks.get_neighbor_chunk_coords(vx, vy, vz);
+            neighbor_chunks.into_iter().for_each(|c| {
+                chunks.chunk_cache.insert(c);
+            });
+
+            results.push(messages::Update {
+                vx,
+                vy,
+                vz,
+                r#type: state.id,
+                rotation: state.rotation,
+                y_rotation: state.y_rotation,
+                stage: state.stage,
+            });
+            previous.push((voxel.clone(), current));
+        }
+
+        chunks.stop_caching();
+
+        // conductors carry their power level in their stage, for clients to render
+        let powered = circuits
+            .powered
+            .iter()
+            .filter_map(|Vec3(vx, vy, vz)| chunks.get_update_protocol(*vx, *vy, *vz))
+            .collect::<Vec<_>>();
+
+        let chunk_protocols =
+            chunks.take_cache_protocols(&mut self.ecs.write_resource::<RemeshQueue>());
+
+        drop(chunks);
+
+        let tick = self.read_resource::<Clock>().tick;
+        let mut schedule = self.write_resource::<CircuitSchedule>();
+        circuits.consumers.into_iter().for_each(|voxel| {
+            schedule.schedule(voxel, tick + POWER_CONSUMER_DELAY);
+        });
+        drop(schedule);
+
+        if !results.is_empty() {
+            let mut components = MessageComponents::default_for(MessageType::Update);
+            components.chunks = Some(chunk_protocols);
+            components.updates = Some(powered);
+            let mut new_message = create_message(components);
+            results.append(&mut new_message.updates);
+            new_message.updates = results;
+
+            self.broadcast(&new_message, vec![], vec![]);
+        }
+
+        self.remove_containers(broken_containers);
+
+        previous
+    }
+
+    /// Apply the changes undoing or redoing a player's journaled edits, leaving out voxels
+    /// claimed by others since
+    ///
+    /// Returns how many voxels changed.
+    fn replay_journal(&mut self, player_id: usize, changes: &[JournalEntry]) -> usize {
+        let changes = changes
+            .iter()
+            .filter(|change| self.can_build_at(player_id, change.voxel.0, change.voxel.2))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        self.apply_changes(&changes)
+    }
+
+    /// Apply block changes through the same path as bulk edits, so that light and neighbors
+    /// update as usual. Changes to voxels that no longer hold what they expect, as they were
+    /// edited again since, are skipped.
+    ///
+    /// Returns how many voxels changed.
+    fn apply_changes(&mut self, changes: &[JournalEntry]) -> usize {
+        let chunks = self.read_resource::<Chunks>();
+
+        // what each voxel will hold once the changes before it are applied
+        let mut expected = HashMap::new();
+        let mut edits = vec![];
+
+        for change in changes {
+            let &Vec3(vx, vy, vz) = &change.voxel;
+
+            let current = match expected.get(&change.voxel) {
+                Some(&id) => id,
+                None if chunks.get_chunk_by_voxel(vx, vy, vz).is_some() => {
+                    chunks.get_voxel_by_voxel(vx, vy, vz)
+                }
+                None => continue,
+            };
+
+            if current != change.old_id {
+                continue;
+            }
+
+            expected.insert(change.voxel.clone(), change.new_id);
+            edits.push((change.voxel.clone(), VoxelState::block(change.new_id)));
+        }
+
+        drop(chunks);
+
+        self.edit_voxels(&edits).len()
+    }
+
+    /// Call a function of the world's scripts, lending them the chunks while they run, then
+    /// carry out what they asked for
+    fn run_scripts(&mut self, only: Option<&str>, function: &str, args: impl FuncArgs + Clone) {
+        if !self.scripts.has_hook(function) {
+            return;
+        }
+
+        let chunks = self
+            .ecs
+            .remove::<Chunks>()
+            .expect("Chunks resource is missing.");
+        let (chunks, actions) = self.scripts.call(chunks, only, function, args);
+        self.ecs.insert(chunks);
+
+        let dimension = self.dimension();
+        let mut edits = vec![];
+
+        for action in actions {
+            match action {
+                ScriptAction::SetVoxel(voxel, id) => edits.push((voxel, VoxelState::block(id))),
+                ScriptAction::Spawn(etype, Vec3(x, y, z)) => {
+                    let position = Vec3(x * dimension, y * dimension, z * dimension);
+
+                    if self.spawn_entity(&etype, &position).is_none() {
+                        warn!("Scripts can't spawn an unknown entity type {}.", etype);
+                    }
+                }
+                ScriptAction::Chat(text) => {
+                    let message =
+                        create_chat_message(MessageType::Message, ChatType::Info, "", &text);
+                    self.broadcast(&message, vec![], vec![]);
+                }
+                ScriptAction::Coins(name, amount) => {
+                    let result = match self.find_player_by_name(&name) {
+                        Some(id) if amount >= 0 => self.deposit(id, amount as u64),
+                        Some(id) => self.withdraw(id, amount.unsigned_abs()),
+                        None => Err(EconomyError::UnknownPlayer),
+                    };
+
+                    if let Err(err) = result {
+                        warn!("Scripts can't move {} coins of {}: {}", amount, name, err);
+                    }
+                }
+            }
+        }
+
+        if !edits.is_empty() {
+            self.edit_voxels(&edits);
+        }
+    }
+
+    /// The name a player goes by, for where anonymous players need one
+    fn player_name(&self, player_id: usize) -> String {
+        self.read_resource::<Players>()
+            .get(&player_id)
+            .and_then(|player| player.name.to_owned())
+            .unwrap_or_else(|| "Somebody".to_owned())
+    }
+
+    /// Append a player's edits to the world's edit log
+    fn log_edits(&self, player_id: usize, edits: &[JournalEntry]) {
+        if edits.is_empty() {
+            return;
+        }
+
+        let (player, uuid) = match self.read_resource::<Players>().get(&player_id) {
+            Some(player) => (
+                player
+                    .name
+                    .to_owned()
+                    .unwrap_or_else(|| "Somebody".to_owned()),
+                player.uuid,
+            ),
+            None => return,
+        };
+
+        let edits = edits
+            .iter()
+            .map(|edit| {
+                EditRecord {
+                    player: player.to_owned(),
+                    uuid,
+                    edit: edit.clone(),
+                }
+                .to_saved()
+            })
+            .collect();
+
+        self.read_resource::<Chunks>().storage.write(WriteBatch {
+            edits,
+            ..Default::default()
+        });
+    }
+
+    /// Adds the player update to the resource `PlayerUpdate`, handled later in an ECS system.
+    pub fn on_peer(&mut self, player_id: usize, msg: messages::Message) {
+        let mut player_updates = self.write_resource::<PlayerUpdates>();
+        player_updates.insert(player_id, msg.peers[0].clone());
+    }
+
+    /// Handles container interactions: opening, closing, and moving items between slots.
+    ///
+    /// Every slot move is arbitrated here. A move made against an outdated revision of the
+    /// container is rejected and the player gets the latest contents back instead, so
+    /// concurrent viewers always converge to the server's state.
+    pub fn on_container(&mut self, player_id: usize, msg: messages::Message) {
+        let json = match msg.parse_json() {
+            Ok(json) => json,
+            Err(_) => return,
+        };
+
+        let voxel = match parse_voxel(&json["voxel"]) {
+            Some(voxel) => voxel,
+            None => return,
+        };
+
+        let players = self.read_resource::<Players>();
+        let entity = match players.get(&player_id) {
+            Some(player) => player.entity,
+            None => return,
+        };
+        drop(players);
+
+        let size = self
+            .read_resource::<Chunks>()
+            .get_block_by_voxel(voxel.0, voxel.1, voxel.2)
+            .container_size;
+
+        let in_reach = self.is_in_reach(player_id, &voxel_center(&voxel, self.dimension()));
+
+        let action = json["action"].as_str().unwrap_or_default();
+
+        match action {
+            "open" => {
+                if size == 0 || !in_reach {
+                    return;
+                }
+
+                let first_viewer = self
+                    .write_resource::<Containers>()
+                    .open(&voxel, size, player_id);
+
+                if first_viewer {
+                    let message = container_animation(&voxel, "open");
+                    self.broadcast_lazy(&message, vec![], vec![], player_id);
+                }
+
+                self.send_container_contents(&voxel, vec![player_id], Some(player_id), false);
+            }
+            "close" => {
+                let last_viewer = self.write_resource::<Containers>().close(&voxel, player_id);
+
+                if last_viewer {
+                    let message = container_animation(&voxel, "close");
+                    self.broadcast_lazy(&message, vec![], vec![], player_id);
+                }
+            }
+            "move" => {
+                let parse_slot = |value: &serde_json::Value| -> Option<SlotRef> {
+                    serde_json::from_value(value.to_owned()).ok()
+                };
+
+                let (from, to) = match (parse_slot(&json["from"]), parse_slot(&json["to"])) {
+                    (Some(from), Some(to)) => (from, to),
+                    _ => return,
+                };
+
+                let count = json["count"].as_u64().map(|c| c as u32);
+                let revision = json["revision"].as_u64().unwrap_or_default() as u32;
+
+                let result = if in_reach {
+                    let mut inventories = self.ecs.write_component::<Inventory>();
+                    let mut containers = self.ecs.write_resource::<Containers>();
+
+                    match inventories.get_mut(entity) {
+                        Some(inventory) => containers
+                            .move_slot(&voxel, player_id, inventory, from, to, count, revision),
+                        None => Err(ContainerError::InvalidMove),
+                    }
+                } else {
+                    Err(ContainerError::NotViewing)
+                };
+
+                match result {
+                    Ok(()) => {
+                        let viewers = self
+                            .read_resource::<Containers>()
+                            .get(&voxel)
+                            .map(|c| c.viewers.iter().cloned().collect::<Vec<_>>())
+                            .unwrap_or_default();
+
+                        self.send_container_contents(&voxel, viewers, Some(player_id), false);
+                    }
+                    Err(ContainerError::NotFound) => {}
+                    Err(_) => {
+                        self.send_container_contents(
+                            &voxel,
+                            vec![player_id],
+                            Some(player_id),
+                            true,
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles a player interacting with the world:
+    ///
+    /// - `till`: till the dirt at `voxel` into farmland
+    /// - `hit`: hit the entity `entity` or the player `player`, hurting them
+    /// - `feed`: feed the held item to the mob `entity`
+    /// - `use`: use up the held item, like drinking a potion
+    /// - `mount`: ride the mob `entity`, or the closest one in reach to ride
+    /// - `dismount`: get off of the mob being ridden
+    /// - `steer`: steer the mob being ridden by `forward` and `right`, from -1 to 1
+    pub fn on_interact(&mut self, player_id: usize, msg: messages::Message) {
+        let json = match msg.parse_json() {
+            Ok(json) => json,
+            Err(_) => return,
+        };
+
+        match json["action"].as_str().unwrap_or_default() {
+            "till" => {
+                if let Some(voxel) = parse_voxel(&json["voxel"]) {
+                    self.till(player_id, &voxel);
+                }
+            }
+            "hit" => {
+                let entity = match json["player"].as_str() {
+                    Some(id) => id
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|id| self.get_player_entity(id)),
+                    None => self.parse_entity(&json["entity"]),
+                };
+
+                if let Some(entity) = entity {
+                    self.hit_entity(player_id, entity);
+                }
+            }
+            "feed" => {
+                if let Some(entity) = self.parse_entity(&json["entity"]) {
+                    self.feed_entity(player_id, entity);
+                }
+            }
+            "use" => self.use_item(player_id),
+            "mount" => {
+                let entity = match json["entity"].is_null() {
+                    true => self.closest_mount(player_id),
+                    false => self.parse_entity(&json["entity"]),
+                };
+
+                if let Some(entity) = entity {
+                    self.mount(player_id, entity);
+                }
+            }
+            "dismount" => self.dismount(player_id),
+            "steer" => {
+                let forward = json["forward"].as_f64().unwrap_or_default() as f32;
+                let right = json["right"].as_f64().unwrap_or_default() as f32;
+
+                if let Some(entity) = self.get_player_entity(player_id) {
+                    if let Some(mounted) = self.ecs.write_component::<Mounted>().get_mut(entity) {
+                        mounted.steer = (forward.clamp(-1.0, 1.0), right.clamp(-1.0, 1.0));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles changes to a player's own inventory, such as selecting the held slot
+    ///
+    /// Actions:
+    /// - `select`: hold the item in `slot`
+    /// - `equip`: wear the armor in `slot` where it's meant to be worn
+    /// - `unequip`: take off what's worn in the equipment slot `equipment`
+    pub fn on_inventory(&mut self, player_id: usize, msg: messages::Message) {
+        let json = match msg.parse_json() {
+            Ok(json) => json,
+            Err(_) => return,
+        };
+
+        let entity = match self.get_player_entity(player_id) {
+            Some(entity) => entity,
+            None => return,
+        };
+
+        let slot = json["slot"].as_u64().unwrap_or_default() as usize;
+
+        match json["action"].as_str().unwrap_or_default() {
+            "select" => {
+                if let Some(inventory) = self.ecs.write_component::<Inventory>().get_mut(entity) {
+                    inventory.select(slot);
+                }
+            }
+            "equip" => {
+                let chunks = self.read_resource::<Chunks>();
+                let mut inventories = self.ecs.write_component::<Inventory>();
+
+                if let Some(inventory) = inventories.get_mut(entity) {
+                    let armor = inventory
+                        .get(slot)
+                        .and_then(|stack| chunks.registry.get_block_by_id(stack.id).armor.clone());
+
+                    // only armor goes on, and only where it's meant to be worn
+                    if let Some(armor) = armor {
+                        inventory.equip(slot, armor.slot);
+                    }
+                }
+
+                drop(inventories);
+                drop(chunks);
+
+                self.send_inventory(player_id);
+            }
+            "unequip" => {
+                let equipment = json["equipment"]
+                    .as_str()
+                    .and_then(EquipmentSlot::from_name);
+
+                if let Some(equipment) = equipment {
+                    if let Some(inventory) = self.ecs.write_component::<Inventory>().get_mut(entity)
+                    {
+                        inventory.unequip(equipment);
+                    }
+
+                    self.send_inventory(player_id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Till a voxel into farmland, then send the changes to everyone
+    fn till(&mut self, player_id: usize, voxel: &Vec3<i32>) {
+        if !self.get_game_mode(player_id).can_edit()
+            || !self.is_in_reach(player_id, &voxel_center(voxel, self.dimension()))
+        {
+            return;
+        }
+
+        let &Vec3(vx, vy, vz) = voxel;
+        let mut chunks = self.ecs.write_resource::<Chunks>();
+
+        if vy < 0 || vy >= chunks.config.max_height as i32 {
+            return;
+        }
+
+        chunks.start_caching();
+        let tilled = Farming::till(&mut chunks, vx, vy, vz);
+        chunks.stop_caching();
+
+        if !tilled {
+            chunks.clear_cache();
+            return;
+        }
+
+        let mut components = MessageComponents::default_for(MessageType::Update);
+        components.updates = chunks.get_update_protocol(vx, vy, vz).map(|u| vec![u]);
+        components.chunks =
+            Some(chunks.take_cache_protocols(&mut self.ecs.write_resource::<RemeshQueue>()));
+
+        drop(chunks);
+
+        let message = create_message(components);
+        self.broadcast_lazy(&message, vec![], vec![], player_id);
+    }
+
+    /// A player hits an entity, dealing damage through the health system
+    ///
+    /// Hits are checked against the player's reach, what stands in between and how long ago
+    /// they last hit anything. Players are only hurt by each other where PvP is on, and only
+    /// when in survival.
+    fn hit_entity(&mut self, player_id: usize, entity: Entity) {
+        let source = match self.get_player_entity(player_id) {
+            Some(source) if source != entity => source,
+            _ => return,
+        };
+
+        if self.get_game_mode(player_id) == GameMode::Spectator {
+            return;
+        }
+
+        let target_player = self.ecs.read_component::<Id>().get(entity).map(|id| id.0);
+
+        if let Some(target_player) = target_player {
+            if !self.read_resource::<WorldConfig>().pvp
+                || self.get_game_mode(target_player) != GameMode::Survival
+            {
+                return;
+            }
+        }
+
+        let (from, eye) = match self.ecs.read_component::<RigidBody>().get(source) {
+            Some(body) => (body.get_position(), body.get_head_position()),
+            None => return,
+        };
+
+        // aim for the head and the middle of the body, so that a step in front of the target's
+        // feet doesn't stop a hit that could clearly land
+        let aims = match self.ecs.read_component::<RigidBody>().get(entity) {
+            Some(body) => {
+                let center = body
+                    .get_position()
+                    .add(&Vec3(0.0, body.get_dimension().1 / 2.0, 0.0));
+                [body.get_head_position(), center]
+            }
+            None => return,
+        };
+
+        if !aims
+            .iter()
+            .any(|aim| self.is_in_reach(player_id, aim) && self.is_line_clear(&eye, aim))
+        {
+            return;
+        }
+
+        let ready = self
+            .ecs
+            .write_component::<AttackCooldown>()
+            .get_mut(source)
+            .map_or(true, |cooldown| cooldown.attack());
+
+        if !ready {
+            return;
+        }
+
+        self.write_resource::<DamageQueue>().push(DamageEvent {
+            target: entity,
+            amount: PLAYER_ATTACK_DAMAGE,
+            source: Some(source),
+            from,
+            knockback: PLAYER_ATTACK_KNOCKBACK,
+        });
+    }
+
+    /// Whether no solid block stands between two points, checked in steps along the line
+    fn is_line_clear(&self, from: &Vec3<f32>, to: &Vec3<f32>) -> bool {
+        let dimension = self.read_resource::<WorldConfig>().dimension;
+        let chunks = self.read_resource::<Chunks>();
+
+        let delta = to.sub(from);
+        let steps = (delta.len() / dimension as f32 * LINE_OF_SIGHT_STEPS).ceil() as i32;
+
+        (1..steps).all(|step| {
+            let Vec3(px, py, pz) = from.scale_and_add(&delta, step as f32 / steps as f32);
+            let Vec3(vx, vy, vz) = map_world_to_voxel(px, py, pz, dimension);
+            !chunks.get_block_by_voxel(vx, vy, vz).is_solid
+        })
+    }
+
+    /// A player feeds their held item to a mob, consuming one if the mob accepts it
+    fn feed_entity(&mut self, player_id: usize, entity: Entity) {
+        let player_entity = match self.get_player_entity(player_id) {
+            Some(player_entity) => player_entity,
+            None => return,
+        };
+
+        let position = match self.ecs.read_component::<RigidBody>().get(entity) {
+            Some(body) => body.get_position(),
+            None => return,
+        };
+
+        if !self.is_in_reach(player_id, &position) {
+            return;
+        }
+
+        let chunks = self.read_resource::<Chunks>();
+        let mut inventories = self.ecs.write_component::<Inventory>();
+        let mut behaviors = self.ecs.write_component::<Behavior>();
+
+        let (inventory, behavior) = match (
+            inventories.get_mut(player_entity),
+            behaviors.get_mut(entity),
+        ) {
+            (Some(inventory), Some(behavior)) => (inventory, behavior),
+            _ => return,
+        };
+
+        let is_food = inventory
+            .held()
+            .map(|held| behavior.is_food(&chunks.registry.get_block_by_id(held.id).name))
+            .unwrap_or(false);
+
+        if !is_food || !behavior.feed() {
+            return;
+        }
+
+        inventory.take(inventory.selected, Some(1));
+
+        drop(behaviors);
+        drop(inventories);
+        drop(chunks);
+
+        self.send_inventory(player_id);
+    }
+
+    /// A player uses up their held item, taking on the status effects it gives if any
+    fn use_item(&mut self, player_id: usize) {
+        let entity = match self.get_player_entity(player_id) {
+            Some(entity) => entity,
+            None => return,
+        };
+
+        let effects = {
+            let chunks = self.read_resource::<Chunks>();
+            let mut inventories = self.ecs.write_component::<Inventory>();
+
+            let inventory = match inventories.get_mut(entity) {
+                Some(inventory) => inventory,
+                None => return,
+            };
+
+            let effects = match inventory.held() {
+                Some(held) => chunks
+                    .registry
+                    .get_block_by_id(held.id)
+                    .status_effects
+                    .clone(),
+                None => return,
+            };
+
+            if effects.is_empty() {
+                return;
+            }
+
+            inventory.take(inventory.selected, Some(1));
+            effects
+        };
+
+        for effect in effects {
+            match EffectKind::from_name(&effect.effect) {
+                Some(kind) => self.add_effect(entity, kind, effect.level, effect.seconds),
+                None => warn!(
+                    "Items can't give an unknown status effect {}.",
+                    effect.effect
+                ),
+            }
+        }
+
+        self.send_inventory(player_id);
+    }
+
+    /// The closest mob within a player's reach that nobody is riding
+    fn closest_mount(&self, player_id: usize) -> Option<Entity> {
+        let entity = self.get_player_entity(player_id)?;
+        let bodies = self.ecs.read_component::<RigidBody>();
+        let head = bodies.get(entity)?.get_head_position();
+
+        (
+            &self.ecs.entities(),
+            &self.ecs.read_component::<Mountable>(),
+            &bodies,
+        )
+            .join()
+            .filter(|(_, mountable, _)| mountable.rider.is_none())
+            .map(|(mount, _, body)| (mount, body.get_position().sub(&head).len()))
+            .filter(|&(_, distance)| distance <= MAX_REACH_DISTANCE * self.dimension())
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(mount, _)| mount)
+    }
+
+    /// A player climbs onto a mob nobody is riding, steering it until they get off
+    fn mount(&mut self, player_id: usize, entity: Entity) {
+        let player_entity = match self.get_player_entity(player_id) {
+            Some(player_entity) if player_entity != entity => player_entity,
+            _ => return,
+        };
+
+        if self.get_game_mode(player_id) == GameMode::Spectator
+            || self.ecs.read_component::<Mounted>().contains(player_entity)
+        {
+            return;
+        }
+
+        let position = match self.ecs.read_component::<RigidBody>().get(entity) {
+            Some(body) => body.get_position(),
+            None => return,
+        };
+
+        if !self.is_in_reach(player_id, &position) {
+            return;
+        }
+
+        let seat = match self.ecs.write_component::<Mountable>().get_mut(entity) {
+            Some(mountable) if mountable.rider.is_none() => {
+                mountable.rider = Some(player_id);
+                mountable.options.seat
+            }
+            _ => return,
+        };
+
+        self.ecs
+            .write_component::<Mounted>()
+            .insert(player_entity, Mounted::new(entity))
+            .expect("Unable to insert mounted component.");
+
+        let message = create_mount_message(Some((entity, seat)));
+        self.broadcast_lazy(&message, vec![player_id], vec![], 0);
+    }
+
+    /// A player gets off of the mob they're riding, landing somewhere safe around it
+    fn dismount(&mut self, player_id: usize) {
+        let player_entity = match self.get_player_entity(player_id) {
+            Some(player_entity) => player_entity,
+            None => return,
+        };
+
+        let mount = match self.ecs.write_component::<Mounted>().remove(player_entity) {
+            Some(mounted) => mounted.mount,
+            None => return,
+        };
+
+        let seat = self
+            .ecs
+            .write_component::<Mountable>()
+            .get_mut(mount)
+            .map(|mountable| {
+                mountable.rider = None;
+                mountable.options.seat
+            })
+            .unwrap_or_default();
+
+        let message = create_mount_message(None);
+        self.broadcast_lazy(&message, vec![player_id], vec![], 0);
+
+        let target = match self.ecs.read_component::<RigidBody>().get(mount) {
+            Some(body) => body
+                .get_position()
+                .add(&Vec3(0.0, seat * self.dimension(), 0.0)),
+            None => return,
+        };
+
+        self.teleport(player_entity, &target);
+    }
+
+    /// Put an entity under a status effect, stacking on the one of the same kind it's under
+    pub fn add_effect(&mut self, entity: Entity, kind: EffectKind, level: u32, seconds: f32) {
+        if let Some(effects) = self.ecs.write_component::<Effects>().get_mut(entity) {
+            effects.add(kind, level, seconds);
+        }
+    }
+
+    /// Take every status effect off an entity
+    pub fn clear_effects(&mut self, entity: Entity) {
+        if let Some(effects) = self.ecs.write_component::<Effects>().get_mut(entity) {
+            effects.clear(None);
+        }
+    }
+
+    /// Get the ECS entity of a player
+    /// Get the game mode of a player
+    pub fn get_game_mode(&self, player_id: usize) -> GameMode {
+        self.read_resource::<GameModes>()
+            .get(&player_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Switch a player to another game mode, letting their client know
+    pub fn set_game_mode(&mut self, player_id: usize, game_mode: GameMode) {
+        self.write_resource::<GameModes>()
+            .insert(player_id, game_mode);
+
+        let mut message = create_of_type(MessageType::Info);
+        message.json = format!("{{\"gameMode\": \"{}\"}}", game_mode.name());
+        self.broadcast(&message, vec![player_id], vec![]);
+    }
+
+    /// Get the abilities granted to a player
+    pub fn get_abilities(&self, player_id: usize) -> Abilities {
+        self.read_resource::<PlayerAbilities>()
+            .get(&player_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Grant a player abilities, letting their client know
+    pub fn set_abilities(&mut self, player_id: usize, abilities: Abilities) {
+        self.write_resource::<PlayerAbilities>()
+            .insert(player_id, abilities);
+        self.send_abilities(player_id);
+    }
+
+    fn send_abilities(&mut self, player_id: usize) {
+        let abilities = self.get_abilities(player_id);
+
+        let mut message = create_of_type(MessageType::Info);
+        message.json = serde_json::json!({ "abilities": abilities }).to_string();
+        self.broadcast_lazy(&message, vec![player_id], vec![], 0);
+    }
+
+    /// Set how many chunks around a player are loaded, capped by the world's max render radius
+    ///
+    /// The player's chunk ring is watched again on the next tick, which generates the newly
+    /// covered chunks and reprioritizes pending generation. The granted radius is sent back.
+    pub fn set_render_radius(&mut self, player_id: usize, chunks: usize) {
+        let entity = match self.get_player_entity(player_id) {
+            Some(entity) => entity,
+            None => return,
+        };
+
+        let (chunk_size, max_render_radius) = {
+            let config = self.read_resource::<WorldConfig>();
+            (config.chunk_size, config.max_render_radius)
+        };
+
+        let radius = (chunks * chunk_size).min(max_render_radius).max(chunk_size);
+
+        if let Some(view_radius) = self.ecs.write_component::<ViewRadius>().get_mut(entity) {
+            view_radius.0 = radius as i16;
+        }
+
+        if let Some(curr_chunk) = self.ecs.write_component::<CurrChunk>().get_mut(entity) {
+            curr_chunk.changed = true;
+        }
+
+        let mut message = create_of_type(MessageType::Config);
+        message.json = format!("{{\"renderRadius\": {}}}", radius / chunk_size);
+        self.broadcast(&message, vec![player_id], vec![]);
+    }
+
+    /// Whether a chunk is within the ring of chunks loaded around a player
+    fn is_in_view(&self, player_id: usize, Vec2(cx, cz): &Vec2<i32>) -> bool {
+        let entity = match self.get_player_entity(player_id) {
+            Some(entity) => entity,
+            None => return false,
+        };
+
+        let chunk_size = self.read_resource::<WorldConfig>().chunk_size as f32;
+        let radius = match self.ecs.read_component::<ViewRadius>().get(entity) {
+            // a chunk of leeway for the server's position lagging behind the client
+            Some(view_radius) => (view_radius.0 as f32 / chunk_size).ceil() as i32 + 1,
+            None => return false,
+        };
+
+        match &self
+            .ecs
+            .read_component::<CurrChunk>()
+            .get(entity)
+            .and_then(|c| c.val.clone())
+        {
+            Some(Vec2(vx, vz)) => (cx - vx).pow(2) + (cz - vz).pow(2) <= radius * radius,
+            // not placed in a chunk yet, don't turn away the first requests
+            None => true,
+        }
+    }
+
+    /// Whether a chunk is close enough to a player to be sent as far terrain
+    fn is_in_horizon(&self, player_id: usize, Vec2(cx, cz): &Vec2<i32>) -> bool {
+        let entity = match self.get_player_entity(player_id) {
+            Some(entity) => entity,
+            None => return false,
+        };
+
+        let (chunk_size, max_horizon_radius) = {
+            let config = self.read_resource::<WorldConfig>();
+            (config.chunk_size as f32, config.max_horizon_radius as f32)
+        };
+
+        if max_horizon_radius == 0.0 {
+            return false;
+        }
+
+        // a chunk of leeway, like for the chunks in view
+        let radius = (max_horizon_radius / chunk_size).ceil() as i32 + 1;
+
+        match &self
+            .ecs
+            .read_component::<CurrChunk>()
+            .get(entity)
+            .and_then(|c| c.val.clone())
+        {
+            Some(Vec2(vx, vz)) => (cx - vx).pow(2) + (cz - vz).pow(2) <= radius * radius,
+            None => true,
+        }
+    }
+
+    /// Send a player back the actual voxels they tried to change, undoing their local edits
+    fn reject_updates(&mut self, player_id: usize, updates: &[messages::Update]) {
+        let chunks = self.read_resource::<Chunks>();
+
+        let updates = updates
+            .iter()
+            .filter_map(|u| chunks.get_update_protocol(u.vx, u.vy, u.vz))
+            .collect::<Vec<_>>();
+
+        drop(chunks);
+
+        let mut components = MessageComponents::default_for(MessageType::Update);
+        components.updates = Some(updates);
+
+        let message = create_message(components);
+        self.broadcast(&message, vec![player_id], vec![]);
+    }
+
+    /// Coins a player holds
+    pub fn balance(&self, player_id: usize) -> u64 {
+        self.read_resource::<Economy>().balance(player_id)
+    }
+
+    /// Give a player coins, returning their new balance
+    pub fn deposit(&mut self, player_id: usize, amount: u64) -> Result<u64, EconomyError> {
+        self.write_resource::<Economy>().deposit(player_id, amount)
+    }
+
+    /// Take coins from a player, returning their new balance
+    pub fn withdraw(&mut self, player_id: usize, amount: u64) -> Result<u64, EconomyError> {
+        self.write_resource::<Economy>().withdraw(player_id, amount)
+    }
+
+    /// Move coins from one player to another, letting scripts know once they've moved
+    pub fn pay(&mut self, from: usize, to: usize, amount: u64) -> Result<(), EconomyError> {
+        self.write_resource::<Economy>()
+            .transfer(from, to, amount)?;
+
+        let receipt = create_chat_message(
+            MessageType::Message,
+            ChatType::Info,
+            "",
+            &format!("{} sent you {} coins.", self.player_name(from), amount),
+        );
+        self.broadcast_lazy(&receipt, vec![to], vec![], 0);
+
+        let (from, to) = (self.player_name(from), self.player_name(to));
+        self.run_scripts(None, "on_pay", (from, to, amount as INT));
+
+        Ok(())
+    }
+
+    /// The voxel players spawn at when they've got nowhere else to be
+    fn spawn_voxel(&self) -> Vec3<i32> {
+        if let Some(spawn) = self.read_resource::<WorldMetadata>().spawn.clone() {
+            return spawn;
+        }
+
+        let chunks = self.read_resource::<Chunks>();
+
+        // worlds with a ceiling would spawn players on top of it
+        if !chunks.config.sky_light {
+            let top = chunks.config.max_height as i32;
+            return Vec3(
+                0,
+                Teleport::find_height(&chunks, 0, top, 0).unwrap_or(top),
+                0,
+            );
+        }
+
+        Vec3(0, chunks.get_max_height(0, 0) as i32, 0)
+    }
+
+    /// Whether a player is dead, waiting to respawn
+    pub fn is_dead(&self, player_id: usize) -> bool {
+        self.get_player_entity(player_id).map_or(false, |entity| {
+            self.ecs
+                .read_component::<Health>()
+                .get(entity)
+                .map_or(false, |health| health.is_dead())
+        })
+    }
+
+    /// Bring a dead player back to life at the world's spawn
+    pub fn on_respawn(&mut self, player_id: usize) {
+        if !self.is_dead(player_id) {
+            return;
+        }
+
+        let entity = match self.get_player_entity(player_id) {
+            Some(entity) => entity,
+            None => return,
+        };
+
+        let message = match self.ecs.write_component::<Health>().get_mut(entity) {
+            Some(health) => {
+                *health = Health::new(health.max);
+                create_health_message(Some(player_id), entity, health)
+            }
+            None => return,
+        };
+
+        self.clear_effects(entity);
+
+        // spawns are either the ground or the voxel above it, and landings are searched
+        // downwards, so start looking one voxel higher to find both
+        let Vec3(vx, vy, vz) = self.respawn_voxel(player_id);
+        let d = self.dimension();
+        self.teleport(
+            entity,
+            &Vec3(
+                (vx as f32 + 0.5) * d,
+                (vy + 1) as f32 * d,
+                (vz as f32 + 0.5) * d,
+            ),
+        );
+
+        self.broadcast_lazy(&message, vec![], vec![], 0);
+    }
+
+    /// The voxel a player respawns at, their team's spawn if it has one
+    fn respawn_voxel(&self, player_id: usize) -> Vec3<i32> {
+        let team_spawn = self
+            .read_resource::<Teams>()
+            .team(player_id)
+            .and_then(|team| {
+                self.read_resource::<WorldMetadata>()
+                    .team_spawns
+                    .get(team)
+                    .cloned()
+            });
+
+        team_spawn.unwrap_or_else(|| self.spawn_voxel())
+    }
+
+    fn get_player_entity(&self, player_id: usize) -> Option<Entity> {
+        self.read_resource::<Players>()
+            .get(&player_id)
+            .map(|player| player.entity)
+    }
+
+    /// Get the position of a player, at the center bottom of their body
+    fn get_player_position(&self, player_id: usize) -> Option<Vec3<f32>> {
+        let entity = self.get_player_entity(player_id)?;
+
+        self.ecs
+            .read_component::<RigidBody>()
+            .get(entity)
+            .map(|body| body.get_position())
+    }
+
+    /// Get the voxel a player stands in
+    fn get_player_voxel(&self, player_id: usize) -> Option<Vec3<i32>> {
+        let position = self.get_player_position(player_id)?;
+        let dimension = self.read_resource::<WorldConfig>().dimension;
+
+        Some(map_world_to_voxel(
+            position.0, position.1, position.2, dimension,
+        ))
+    }
+
+    /// Parse the arguments of `/tp` into a world position: `x y z` or `x z` in voxels, or
+    /// the name of another player
+    fn parse_teleport_target(&self, args: &[&str]) -> Option<Vec3<f32>> {
+        let config = self.read_resource::<WorldConfig>();
+        let dimension = config.dimension as f32;
+        let top = config.max_height as f32;
+
+        drop(config);
+
+        let numbers = args
+            .iter()
+            .map(|arg| arg.parse::<f32>())
+            .collect::<Result<Vec<_>, _>>();
+
+        match (args, numbers.as_deref()) {
+            (_, Ok(&[x, y, z])) => Some(Vec3(x * dimension, y * dimension, z * dimension)),
+            (_, Ok(&[x, z])) => Some(Vec3(x * dimension, top * dimension, z * dimension)),
+            ([name], _) => {
+                let id = self
+                    .read_resource::<Players>()
+                    .iter()
+                    .find(|(_, player)| player.name.as_deref() == Some(*name))
+                    .map(|(id, _)| *id)?;
+
+                self.get_player_position(id)
+            }
+            _ => None,
+        }
+    }
+
+    /// Find a living entity from the id clients know it by
+    fn parse_entity(&self, value: &serde_json::Value) -> Option<Entity> {
+        let id = value.as_str()?.parse::<u32>().ok()?;
+        let entity = self.ecs.entities().entity(id);
+
+        if self.ecs.is_alive(entity) {
+            Some(entity)
+        } else {
+            None
+        }
+    }
+
+    /// Getter for the world's voxel dimension
+    fn dimension(&self) -> f32 {
+        self.read_resource::<WorldConfig>().dimension as f32
+    }
+
+    /// Whether a point is within a player's reach, measured from the player's head
+    fn is_in_reach(&self, player_id: usize, point: &Vec3<f32>) -> bool {
+        let entity = match self.get_player_entity(player_id) {
+            Some(entity) => entity,
+            None => return false,
+        };
+
+        let reach = MAX_REACH_DISTANCE * self.dimension();
+
+        self.ecs()
+            .read_component::<RigidBody>()
+            .get(entity)
+            .map(|body| body.get_head_position().sub(point).len() <= reach)
+            .unwrap_or(false)
+    }
+
+    /// Add items to a player's inventory, then send them the new inventory
+    ///
+    /// Items that don't fit are lost for now.
+    pub fn give_items(&mut self, player_id: usize, stacks: Vec<ItemStack>) {
+        let entity = match self.get_player_entity(player_id) {
+            Some(entity) => entity,
+            None => return,
+        };
+
+        if let Some(inventory) = self.ecs.write_component::<Inventory>().get_mut(entity) {
+            stacks.into_iter().for_each(|stack| {
+                inventory.add(stack);
+            });
+        }
+
+        self.send_inventory(player_id);
+    }
+
+    fn load_player_data(&self, uuid: &Uuid) -> Option<PlayerData> {
+        PlayerData::load(self.read_resource::<Chunks>().storage.as_ref(), uuid)
+    }
+
+    /// What to remember about a logged in player until they come back
+    fn player_data(&self, player_id: usize, entity: Entity) -> Option<PlayerData> {
+        let position = self
+            .ecs
+            .read_component::<RigidBody>()
+            .get(entity)?
+            .get_position();
+        let inventory = self
+            .ecs
+            .read_component::<Inventory>()
+            .get(entity)?
+            .to_owned();
+
+        let experience = self
+            .ecs
+            .read_component::<Experience>()
+            .get(entity)
+            .map_or(0, |experience| experience.points);
+
+        Some(PlayerData {
+            position,
+            inventory,
+            game_mode: self.get_game_mode(player_id),
+            abilities: self.get_abilities(player_id),
+            experience,
+            balance: self.balance(player_id),
+            team: self
+                .read_resource::<Teams>()
+                .team(player_id)
+                .map(|team| team.to_owned()),
+            waypoints: self.read_resource::<Waypoints>().get(player_id).to_vec(),
+        })
+    }
+
+    /// Send a player how much experience they've gathered lazily
+    fn send_experience(&mut self, player_id: usize) {
+        let entity = match self.get_player_entity(player_id) {
+            Some(entity) => entity,
+            None => return,
+        };
+
+        let message = match self.ecs.read_component::<Experience>().get(entity) {
+            Some(experience) => create_experience_message(experience),
+            None => return,
+        };
+
+        self.broadcast_lazy(&message, vec![player_id], vec![], 0);
+    }
+
+    /// Send a player their waypoints and the world's markers lazily
+    fn send_waypoints(&mut self, player_id: usize) {
+        let message = create_waypoints_message(
+            self.read_resource::<Waypoints>().get(player_id),
+            &self.read_resource::<WorldMetadata>().markers,
+        );
+
+        self.broadcast_lazy(&message, vec![player_id], vec![], 0);
+    }
+
+    /// Send a player their own inventory lazily
+    fn send_inventory(&mut self, player_id: usize) {
+        let entity = match self.get_player_entity(player_id) {
+            Some(entity) => entity,
+            None => return,
+        };
+
+        let message = match self.ecs.read_component::<Inventory>().get(entity) {
+            Some(inventory) => create_inventory_message(inventory),
+            None => return,
+        };
+
+        self.broadcast_lazy(&message, vec![player_id], vec![], 0);
+    }
+
+    /// Send the contents of a container to a list of players lazily
+    ///
+    /// The player inventory of `owner` is attached to their copy of the message.
+    fn send_container_contents(
+        &mut self,
+        voxel: &Vec3<i32>,
+        include: Vec<usize>,
+        owner: Option<usize>,
+        rejected: bool,
+    ) {
+        if include.is_empty() {
+            return;
+        }
+
+        let containers = self.read_resource::<Containers>();
+        let container = match containers.get(voxel) {
+            Some(container) => container,
+            None => return,
+        };
+
+        let mut json = serde_json::json!({
+            "action": "contents",
+            "voxel": voxel,
+            "revision": container.revision,
+            "slots": container.inventory.slots,
+            "rejected": rejected,
+        });
+
+        drop(containers);
+
+        let owner_inventory = owner.and_then(|owner| {
+            let players = self.read_resource::<Players>();
+            let entity = players.get(&owner)?.entity;
+            let inventories = self.ecs().read_component::<Inventory>();
+            inventories.get(entity).map(|i| i.slots.clone())
+        });
+
+        let others = include
+            .iter()
+            .filter(|&&id| Some(id) != owner)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !others.is_empty() {
+            let mut message = create_of_type(MessageType::Container);
+            message.json = json.to_string();
+            self.broadcast_lazy(&message, others, vec![], 0);
+        }
+
+        if let (Some(owner), Some(slots)) = (owner, owner_inventory) {
+            if include.contains(&owner) {
+                json["inventory"] = serde_json::json!(slots);
+
+                let mut message = create_of_type(MessageType::Container);
+                message.json = json.to_string();
+                self.broadcast_lazy(&message, vec![owner], vec![], 0);
+            }
+        }
+    }
+
+    /// Handles an incoming chat message, broadcasts response lazily
+    pub fn on_chat_message(&mut self, player_id: usize, msg: messages::Message) {
+        if let Some(message) = msg.message.clone() {
+            // chat goes out under the name the server knows the player by, whatever the
+            // client claims
+            let sender: String = self
+                .read_resource::<Players>()
+                .get(&player_id)
+                .and_then(|player| player.name.to_owned())
+                .unwrap_or(message.sender);
+            let body: String = message.body;
 
             info!("{}: {}", sender, body);
 
-            if body.starts_with('/') {
-                let body = body
-                    .strip_prefix('/')
-                    .unwrap()
-                    .split_whitespace()
-                    .collect::<Vec<_>>();
+            if body.starts_with('/') {
+                let body = body
+                    .strip_prefix('/')
+                    .unwrap()
+                    .split_whitespace()
+                    .collect::<Vec<_>>();
+
+                let msgs = self.run_command(player_id, &body);
+                msgs.into_iter().for_each(|msg| {
+                    self.broadcast_lazy(&msg, vec![], vec![], player_id);
+                });
+            } else {
+                let channel = self.read_resource::<Chat>().channel(player_id);
+
+                match self.send_chat(player_id, channel, &body) {
+                    Ok(()) => self.run_scripts(None, "on_chat", (sender, body)),
+                    Err(err) => {
+                        let message = create_chat_message(
+                            MessageType::Message,
+                            ChatType::Error,
+                            "",
+                            &err.to_string(),
+                        );
+                        self.broadcast_lazy(&message, vec![player_id], vec![], 0);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send a player's message to whoever listens to a channel, the sender always among them
+    pub fn send_chat(
+        &mut self,
+        player_id: usize,
+        channel: ChatChannel,
+        body: &str,
+    ) -> Result<(), ChatError> {
+        let body = sanitize(&self.filter_chat(player_id, body)?);
+
+        if body.is_empty() {
+            return Err(ChatError::Empty);
+        }
+
+        let chat = self.read_resource::<Chat>();
+
+        if !chat.listens(player_id, channel) {
+            return Err(ChatError::Muted(channel));
+        }
+
+        let mut recipients = match channel {
+            ChatChannel::Global => self.read_resource::<Players>().keys().cloned().collect(),
+            ChatChannel::Local => self.players_near(player_id, LOCAL_CHAT_RADIUS),
+            ChatChannel::Team => {
+                let teams = self.read_resource::<Teams>();
+                teams
+                    .team(player_id)
+                    .map(|team| teams.members(team))
+                    .ok_or(ChatError::NoTeam)?
+            }
+        };
+
+        recipients.retain(|&id| id != player_id && chat.listens(id, channel));
+        recipients.push(player_id);
+        drop(chat);
+
+        let name = sanitize(&self.player_name(player_id));
+        let sender = match channel {
+            ChatChannel::Global => name,
+            _ => format!("[{}] {}", channel.name(), name),
+        };
+
+        let message = create_chat_message(MessageType::Message, ChatType::Player, &sender, &body);
+        self.broadcast_lazy(&message, recipients, vec![], player_id);
+
+        Ok(())
+    }
+
+    /// Send a message only the sender and the receiver see, mutes notwithstanding
+    pub fn whisper(&mut self, from: usize, to: &str, body: &str) -> Result<(), ChatError> {
+        let to = self
+            .find_player_by_name(to)
+            .ok_or(ChatError::UnknownPlayer)?;
+
+        if to == from {
+            return Err(ChatError::SamePlayer);
+        }
+
+        let body = sanitize(&self.filter_chat(from, body)?);
+
+        if body.is_empty() {
+            return Err(ChatError::Empty);
+        }
+
+        let sender = format!(
+            "{} → {}",
+            sanitize(&self.player_name(from)),
+            sanitize(&self.player_name(to))
+        );
+
+        let message = create_chat_message(MessageType::Message, ChatType::Player, &sender, &body);
+        self.broadcast_lazy(&message, vec![from, to], vec![], from);
+
+        Ok(())
+    }
+
+    /// Run a player's message through the chat filter, returning it as it may be sent
+    ///
+    /// Admins hear of the messages the filter stops, and scripts get to act on them through
+    /// `on_filter`.
+    fn filter_chat(&mut self, player_id: usize, body: &str) -> Result<String, ChatError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards?")
+            .as_millis() as u64;
+
+        let mut filter = self.write_resource::<ChatFilter>();
+
+        if let Some(seconds) = filter.muted_for(player_id, now) {
+            return Err(ChatError::Silenced(seconds));
+        }
+
+        let (action, err) = match filter.check(body) {
+            Verdict::Pass(body) => return Ok(body),
+            Verdict::Block => ("blocked", ChatError::Filtered),
+            Verdict::Mute => {
+                filter.mute(player_id, Some(now + CHAT_FILTER_MUTE_SECONDS * 1000));
+                ("muted", ChatError::Silenced(CHAT_FILTER_MUTE_SECONDS))
+            }
+        };
+
+        drop(filter);
+
+        let name = self.player_name(player_id);
+        let notice = create_chat_message(
+            MessageType::Message,
+            ChatType::Info,
+            "",
+            &format!(
+                "Chat filter {} {}: {}",
+                action,
+                sanitize(&name),
+                sanitize(body)
+            ),
+        );
+        let admins = self
+            .read_resource::<Players>()
+            .keys()
+            .filter(|&&id| self.is_admin(id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !admins.is_empty() {
+            self.broadcast_lazy(&notice, admins, vec![], 0);
+        }
+
+        info!("Chat filter {} {}: {}", action, name, body);
+        self.run_scripts(
+            None,
+            "on_filter",
+            (name, body.to_owned(), action.to_owned()),
+        );
+
+        Err(err)
+    }
+
+    /// Hand the chat filter the rules scripts added as they loaded
+    fn update_chat_filters(&mut self) {
+        let rules = self.scripts.chat_filters().to_vec();
+        self.write_resource::<ChatFilter>().set_script_rules(&rules);
+    }
+
+    /// Players within a distance in voxels of a player, by the spatial index
+    fn players_near(&self, player_id: usize, radius: f32) -> Vec<usize> {
+        let position = match self.get_player_position(player_id) {
+            Some(position) => position,
+            None => return vec![],
+        };
+
+        let radius = radius * self.read_resource::<WorldConfig>().dimension as f32;
+        let ids = self.ecs.read_component::<Id>();
+
+        self.read_resource::<KdTree>()
+            .players_within(&position, radius)
+            .into_iter()
+            .filter_map(|&ent| ids.get(ent))
+            .map(|id| id.0)
+            .collect()
+    }
+
+    /// Run `/team`: show, join, leave or list teams, invite others or set where the team
+    /// respawns, which only its owner can do. Admins can put others on teams or take them
+    /// off by naming them last.
+    fn team_command(&mut self, player_id: usize, body: &[&str]) -> Result<String, TeamError> {
+        const USAGE: &str =
+            "/team [join <name> [player]|leave [player]|invite <player>|list|spawn [clear]]";
+
+        let target = |world: &Self, name: Option<&str>| {
+            world
+                .admin_target(player_id, name)
+                .map_err(|err| match err {
+                    ProfileError::NotAdmin => TeamError::NotAdmin,
+                    _ => TeamError::UnknownPlayer,
+                })
+        };
+
+        match (
+            body.get(1).cloned(),
+            body.get(2).cloned(),
+            body.get(3).cloned(),
+        ) {
+            (None, None, None) => self
+                .read_resource::<Teams>()
+                .team(player_id)
+                .map(|team| format!("On team {}.", team))
+                .ok_or(TeamError::NotOnTeam),
+            (Some("join"), Some(team), player) => {
+                let team = clean_team_name(team).ok_or(TeamError::InvalidName)?;
+                let target = match player {
+                    Some(_) => target(self, player)?,
+                    None => player_id,
+                };
+
+                if !self.read_resource::<Teams>().can_join(target, &team)
+                    && !self.is_admin(player_id)
+                {
+                    return Err(TeamError::NotInvited);
+                }
+
+                self.write_resource::<Teams>().join(target, &team);
+                Ok(format!(
+                    "{} joined team {}.",
+                    self.player_name(target),
+                    team
+                ))
+            }
+            (Some("leave"), player, None) => {
+                let target = match player {
+                    Some(_) => target(self, player)?,
+                    None => player_id,
+                };
+
+                let team = self
+                    .write_resource::<Teams>()
+                    .leave(target)
+                    .ok_or(TeamError::NotOnTeam)?;
+
+                let mut chat = self.write_resource::<Chat>();
+                if chat.channel(target) == ChatChannel::Team {
+                    chat.set_channel(target, ChatChannel::Global);
+                }
+                drop(chat);
+
+                Ok(format!("{} left team {}.", self.player_name(target), team))
+            }
+            (Some("invite"), Some(name), None) => {
+                let team = self.owned_team(player_id)?;
+                let invited = self
+                    .find_player_by_name(name)
+                    .ok_or(TeamError::UnknownPlayer)?;
+
+                self.write_resource::<Teams>().invite(&team, invited);
+                Ok(format!(
+                    "{} can now join team {}.",
+                    self.player_name(invited),
+                    team
+                ))
+            }
+            (Some("list"), None, None) => {
+                let list = self.read_resource::<Teams>().list();
+
+                if list.is_empty() {
+                    return Ok("Nobody is on a team.".to_owned());
+                }
+
+                let entries = list
+                    .iter()
+                    .map(|(team, count)| format!("{} ({})", team, count))
+                    .collect::<Vec<_>>();
+
+                Ok(format!("Teams: {}", entries.join(", ")))
+            }
+            (Some("spawn"), clear, None) if clear.map_or(true, |clear| clear == "clear") => {
+                let team = self.owned_team(player_id)?;
+
+                if clear.is_some() {
+                    self.write_resource::<WorldMetadata>()
+                        .team_spawns
+                        .remove(&team);
+                    return Ok(format!("Team {} respawns at the world spawn.", team));
+                }
+
+                let voxel = self
+                    .get_player_voxel(player_id)
+                    .ok_or(TeamError::UnknownPlayer)?;
+                self.write_resource::<WorldMetadata>()
+                    .team_spawns
+                    .insert(team.to_owned(), voxel.clone());
+
+                Ok(format!(
+                    "Team {} respawns at {} {} {}.",
+                    team, voxel.0, voxel.1, voxel.2
+                ))
+            }
+            _ => Err(TeamError::Usage(USAGE)),
+        }
+    }
+
+    /// The team a player is on, as long as they own it or are an admin
+    fn owned_team(&self, player_id: usize) -> Result<String, TeamError> {
+        let teams = self.read_resource::<Teams>();
+        let team = teams.team(player_id).ok_or(TeamError::NotOnTeam)?;
+
+        if teams.owner(team) != Some(player_id) && !self.is_admin(player_id) {
+            return Err(TeamError::NotOwner);
+        }
+
+        Ok(team.to_owned())
+    }
+
+    /// Run `/waypoint` on a player's own waypoints, or `/marker` on the markers everyone sees
+    /// when `markers` is set, which only admins can add or remove
+    fn waypoint_command(
+        &mut self,
+        player_id: usize,
+        body: &[&str],
+        markers: bool,
+    ) -> Result<String, WaypointError> {
+        let (kind, usage) = if markers {
+            ("Marker", "/marker [add <name>|remove <name>|list]")
+        } else {
+            ("Waypoint", "/waypoint [add <name>|remove <name>|list]")
+        };
+
+        let action = body.get(1).cloned().unwrap_or("list");
+
+        if markers && action != "list" && !self.is_admin(player_id) {
+            return Err(WaypointError::NotAdmin);
+        }
+
+        let reply = match (action, body.get(2).cloned(), body.len()) {
+            ("add", Some(name), 3) => {
+                let voxel = self
+                    .get_player_voxel(player_id)
+                    .ok_or(WaypointError::Usage(usage))?;
+
+                if markers {
+                    let mut metadata = self.write_resource::<WorldMetadata>();
+                    add_waypoint(&mut metadata.markers, name, voxel.clone())?;
+                } else {
+                    let mut waypoints = self.write_resource::<Waypoints>();
+                    add_waypoint(waypoints.get_mut(player_id), name, voxel.clone())?;
+                }
+
+                format!(
+                    "{} {} set at {} {} {}.",
+                    kind, name, voxel.0, voxel.1, voxel.2
+                )
+            }
+            ("remove", Some(name), 3) => {
+                let removed = if markers {
+                    remove_waypoint(&mut self.write_resource::<WorldMetadata>().markers, name)?
+                } else {
+                    remove_waypoint(self.write_resource::<Waypoints>().get_mut(player_id), name)?
+                };
+
+                format!("{} {} removed.", kind, removed.name)
+            }
+            ("list", None, _) => {
+                let list: Vec<Waypoint> = if markers {
+                    self.read_resource::<WorldMetadata>().markers.to_owned()
+                } else {
+                    self.read_resource::<Waypoints>().get(player_id).to_vec()
+                };
+
+                if list.is_empty() {
+                    return Ok(format!("There are no {}s.", kind.to_lowercase()));
+                }
+
+                let entries = list
+                    .iter()
+                    .map(|waypoint| {
+                        let Vec3(vx, vy, vz) = waypoint.voxel;
+                        format!("{} ({} {} {})", waypoint.name, vx, vy, vz)
+                    })
+                    .collect::<Vec<_>>();
+
+                return Ok(format!("{}s: {}", kind, entries.join(", ")));
+            }
+            _ => return Err(WaypointError::Usage(usage)),
+        };
+
+        if markers {
+            let ids = self
+                .read_resource::<Players>()
+                .iter()
+                .map(|(&id, _)| id)
+                .collect::<Vec<_>>();
+            ids.into_iter().for_each(|id| self.send_waypoints(id));
+        } else {
+            self.send_waypoints(player_id);
+        }
+
+        Ok(reply)
+    }
+
+    /// Run `/locate`, finding the nearest structure or biome of a kind to a player
+    fn locate_command(&self, player_id: usize, body: &[&str]) -> Result<String, LocateError> {
+        const USAGE: &str = "/locate <structure|biome> <name>";
+
+        let voxel = self
+            .get_player_voxel(player_id)
+            .ok_or(LocateError::Usage(USAGE))?;
+        let from = Vec2(voxel.0, voxel.2);
+        let chunks = self.read_resource::<Chunks>();
+
+        let (kind, name) = match (body.get(1).cloned(), body.get(2).cloned(), body.len()) {
+            (Some(kind), Some(name), 3) => (kind, name),
+            _ => return Err(LocateError::Usage(USAGE)),
+        };
+
+        let found = match kind {
+            "structure" => {
+                let index = chunks.structures.index_of(name).ok_or_else(|| {
+                    LocateError::UnknownStructure(chunks.structures.names().join(", "))
+                })?;
+
+                Locate::structure(&chunks.structures, index, &from, chunks.config.chunk_size)
+            }
+            "biome" => {
+                if !chunks.biomes.has_biome(name) {
+                    let names = chunks
+                        .biomes
+                        .configs
+                        .biomes
+                        .iter()
+                        .map(|biome| biome.name.as_str())
+                        .collect::<Vec<_>>();
+                    return Err(LocateError::UnknownBiome(names.join(", ")));
+                }
+
+                Locate::biome(&chunks.biomes, name, &from)
+            }
+            _ => return Err(LocateError::Usage(USAGE)),
+        };
+
+        let Vec2(vx, vz) = found.ok_or(LocateError::NotFound)?;
+        let distance = (((vx - from.0) as f64).powi(2) + ((vz - from.1) as f64).powi(2)).sqrt();
+
+        // the height is only known once the chunk is generated
+        let vy = match chunks.get_chunk_by_voxel(vx, 0, vz) {
+            Some(_) => (chunks.get_max_height(vx, vz) + 1).to_string(),
+            None => "~".to_owned(),
+        };
+
+        Ok(format!(
+            "Nearest {} is at {} {} {}, {} blocks away.",
+            name, vx, vy, vz, distance as i64
+        ))
+    }
+
+    /// Run `/co lookup` or `/co rollback` on the edits in the world's edit log matching
+    /// `u:<player> t:<time> r:<radius>`, admins only. The edit log is read off the game
+    /// loop, and the command finished once it's in.
+    fn audit_command(&mut self, player_id: usize, body: &[&str]) -> Result<(), AuditError> {
+        const USAGE: &str = "/co <lookup|rollback> [u:<player>] [t:<time>] [r:<radius>]";
+
+        if !self.is_admin(player_id) {
+            return Err(AuditError::NotAdmin);
+        }
+
+        let action = match body.get(1).cloned() {
+            Some("lookup") | Some("l") => AuditAction::Lookup,
+            Some("rollback") | Some("rb") => AuditAction::Rollback,
+            _ => return Err(AuditError::Usage(USAGE)),
+        };
+        let args = &body[2..];
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards?")
+            .as_millis() as u64;
+        let center = self.get_player_voxel(player_id).unwrap_or_default();
+        let query = EditQuery::parse(args, now, &center)?;
+
+        if action == AuditAction::Rollback && query.since.is_none() {
+            return Err(AuditError::MissingTime);
+        }
+
+        let storage = self.read_resource::<Chunks>().storage.clone();
+        self.write_resource::<Audits>().request(
+            &storage,
+            PendingAudit {
+                player_id,
+                action,
+                query,
+                now,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Finish the `/co` commands whose edit log has been read, telling their players how
+    /// it went
+    fn finish_audits(&mut self) {
+        let answered = self.write_resource::<Audits>().answered();
+
+        for (audit, records) in answered {
+            let player_id = audit.player_id;
+
+            let replies = match self.finish_audit(audit, records) {
+                Ok(replies) => replies
+                    .iter()
+                    .map(|reply| {
+                        create_chat_message(MessageType::Message, ChatType::Info, "", reply)
+                    })
+                    .collect::<Vec<_>>(),
+                Err(err) => vec![create_chat_message(
+                    MessageType::Message,
+                    ChatType::Error,
+                    "",
+                    &err.to_string(),
+                )],
+            };
+
+            for reply in replies {
+                self.broadcast_lazy(&reply, vec![player_id], vec![], 0);
+            }
+        }
+    }
+
+    /// Look up or roll back the records matching a `/co` command, newest first
+    ///
+    /// Rollbacks revert the matching edits from the newest on, skipping blocks that were
+    /// edited again by others since.
+    fn finish_audit(
+        &mut self,
+        audit: PendingAudit,
+        records: Vec<EditRecord>,
+    ) -> Result<Vec<String>, AuditError> {
+        let now = audit.now;
+
+        if records.is_empty() {
+            return Err(AuditError::NoMatches);
+        }
+
+        match audit.action {
+            AuditAction::Lookup => {
+                let chunks = self.read_resource::<Chunks>();
+                let name = |id: u32| {
+                    if chunks.registry.has_type(id) {
+                        chunks.registry.get_block_by_id(id).name.to_owned()
+                    } else {
+                        format!("#{}", id)
+                    }
+                };
+
+                let mut replies = vec![if records.len() > AUDIT_LOOKUP_LIMIT {
+                    format!(
+                        "Over {} block edits match, newest first:",
+                        AUDIT_LOOKUP_LIMIT
+                    )
+                } else {
+                    format!("{} block edits match, newest first:", records.len())
+                }];
+
+                replies.extend(records.iter().take(AUDIT_LOOKUP_LIMIT).map(|record| {
+                    let edit = &record.edit;
+
+                    let action = if chunks.registry.has_type(edit.old_id)
+                        && chunks.registry.is_air(edit.old_id)
+                    {
+                        format!("placed {}", name(edit.new_id))
+                    } else if chunks.registry.has_type(edit.new_id)
+                        && chunks.registry.is_air(edit.new_id)
+                    {
+                        format!("broke {}", name(edit.old_id))
+                    } else {
+                        format!("replaced {} with {}", name(edit.old_id), name(edit.new_id))
+                    };
+
+                    format!(
+                        "{} {} at {}, {}, {}, {}",
+                        record.player,
+                        action,
+                        edit.voxel.0,
+                        edit.voxel.1,
+                        edit.voxel.2,
+                        format_age(now.saturating_sub(edit.timestamp))
+                    )
+                }));
+
+                Ok(replies)
+            }
+            AuditAction::Rollback => {
+                let changes = records
+                    .iter()
+                    .map(|record| record.edit.inverse())
+                    .collect::<Vec<_>>();
+
+                let changed = self.apply_changes(&changes);
+
+                let mut replies = vec![format!(
+                    "Rolled back {} of {} block edits, the rest were edited again since or aren't loaded.",
+                    changed,
+                    changes.len()
+                )];
+
+                if changes.len() >= AUDIT_ROLLBACK_LIMIT {
+                    replies.push(format!(
+                        "Only the newest {} matching block edits are rolled back at once.",
+                        AUDIT_ROLLBACK_LIMIT
+                    ));
+                }
+
+                Ok(replies)
+            }
+        }
+    }
+
+    /// Whether a player may place or break blocks in a column, as far as claims go
+    fn can_build_at(&self, player_id: usize, vx: i32, vz: i32) -> bool {
+        let players = self.read_resource::<Players>();
+
+        match players.get(&player_id) {
+            Some(player) => {
+                player.bypass_claims
+                    || self.read_resource::<WorldMetadata>().claims.can_build(
+                        vx,
+                        vz,
+                        player.uuid.as_ref(),
+                    )
+            }
+            None => false,
+        }
+    }
+
+    /// Run one of the commands that claim land, share it and set what it's protected from
+    ///
+    /// Claims belong to logged in players and reach from the bottom of the world to the top.
+    /// Admins can manage anyone's claim, and build in them after `/claimoverride`.
+    fn claim_command(&mut self, player_id: usize, body: &[&str]) -> Result<String, ClaimError> {
+        let (uuid, name, admin) = match self.read_resource::<Players>().get(&player_id) {
+            Some(player) => (
+                player.uuid,
+                player
+                    .name
+                    .to_owned()
+                    .unwrap_or_else(|| "Somebody".to_owned()),
+                player.admin,
+            ),
+            None => return Err(ClaimError::NotLoggedIn),
+        };
+
+        if body[0] == "claimoverride" {
+            if !admin {
+                return Err(ClaimError::NotAdmin);
+            }
+
+            let mut players = self.write_resource::<Players>();
+            let player = players.get_mut(&player_id).unwrap();
+            player.bypass_claims = !player.bypass_claims;
+
+            return Ok(if player.bypass_claims {
+                "You can now build in every claim.".to_owned()
+            } else {
+                "Claims apply to you again.".to_owned()
+            });
+        }
+
+        let Vec3(vx, _, vz) = self
+            .get_player_voxel(player_id)
+            .ok_or(ClaimError::NotClaimed)?;
+
+        if body[0] == "claim" {
+            let owner = uuid.ok_or(ClaimError::NotLoggedIn)?;
+
+            let radius = match body.get(1) {
+                Some(arg) => arg
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|radius| *radius >= 0)
+                    .ok_or(ClaimError::Usage("/claim [radius]"))?,
+                None => DEFAULT_CLAIM_RADIUS,
+            };
+
+            if radius > MAX_CLAIM_RADIUS {
+                return Err(ClaimError::TooLarge(MAX_CLAIM_RADIUS));
+            }
+
+            self.write_resource::<WorldMetadata>().claims.add(Claim {
+                owner,
+                owner_name: name,
+                min: Vec2(vx - radius, vz - radius),
+                max: Vec2(vx + radius, vz + radius),
+                members: vec![],
+                flags: ClaimFlags::default(),
+            })?;
+
+            let side = radius * 2 + 1;
+            return Ok(format!("Claimed {} by {} blocks around you.", side, side));
+        }
+
+        // the rest manage the claim the player stands in, which only its owner and admins can
+        let is_owner = match self.read_resource::<WorldMetadata>().claims.find(vx, vz) {
+            Some(claim) => admin || uuid.as_ref() == Some(&claim.owner),
+            None => return Err(ClaimError::NotClaimed),
+        };
+
+        if !is_owner {
+            return Err(ClaimError::NotOwner);
+        }
+
+        match body[0] {
+            "unclaim" => {
+                let claim = self
+                    .write_resource::<WorldMetadata>()
+                    .claims
+                    .remove(vx, vz)
+                    .ok_or(ClaimError::NotClaimed)?;
+
+                Ok(format!("Removed the claim of {}.", claim.owner_name))
+            }
+            "trust" | "untrust" => {
+                let target = body
+                    .get(1)
+                    .ok_or(ClaimError::Usage("/trust <player> or /untrust <player>"))?;
+
+                let member = self
+                    .find_player_by_name(target)
+                    .and_then(|id| self.read_resource::<Players>().get(&id)?.uuid)
+                    .ok_or(ClaimError::UnknownPlayer)?;
+
+                let mut metadata = self.write_resource::<WorldMetadata>();
+                let claim = metadata
+                    .claims
+                    .find_mut(vx, vz)
+                    .ok_or(ClaimError::NotClaimed)?;
+
+                claim.members.retain(|uuid| *uuid != member);
+
+                if body[0] == "trust" {
+                    claim.members.push(member);
+                    Ok(format!("{} can now build here.", target))
+                } else {
+                    Ok(format!("{} can no longer build here.", target))
+                }
+            }
+            "claimflag" => {
+                let value = match body.get(2).cloned() {
+                    Some("on") => true,
+                    Some("off") => false,
+                    _ => {
+                        return Err(ClaimError::Usage(
+                            "/claimflag <no-build|no-explosion> <on|off>",
+                        ))
+                    }
+                };
+
+                let mut metadata = self.write_resource::<WorldMetadata>();
+                let claim = metadata
+                    .claims
+                    .find_mut(vx, vz)
+                    .ok_or(ClaimError::NotClaimed)?;
+
+                if !claim.flags.set(body[1], value) {
+                    return Err(ClaimError::UnknownFlag);
+                }
+
+                Ok(format!(
+                    "Turned {} {}.",
+                    body[1],
+                    if value { "on" } else { "off" }
+                ))
+            }
+            _ => Err(ClaimError::Usage(
+                "/claim, /unclaim, /trust, /untrust, /claimflag or /claimoverride",
+            )),
+        }
+    }
+
+    /// Run one of the `//` commands that select, copy and edit whole regions, admins only
+    ///
+    /// Selections and clipboards are kept per player, and so is the history `//undo` walks
+    /// back through.
+    fn world_edit(&mut self, player_id: usize, body: &[&str]) -> Result<String, EditError> {
+        if !self.is_admin(player_id) {
+            return Err(EditError::NotAdmin);
+        }
+
+        // where the player stands, which is where clipboards are copied from and pasted to
+        let standing = self.get_player_voxel(player_id);
+
+        match body[0] {
+            "/pos1" | "/pos2" => {
+                let numbers = body[1..]
+                    .iter()
+                    .map(|arg| arg.parse::<i32>())
+                    .collect::<Result<Vec<_>, _>>();
+
+                let voxel = match numbers.as_deref() {
+                    Ok(&[vx, vy, vz]) => Vec3(vx, vy, vz),
+                    Ok(&[]) => standing.ok_or(EditError::NoSelection)?,
+                    _ => return Err(EditError::Usage("//pos1 [x y z] or //pos2 [x y z]")),
+                };
+
+                let first = body[0] == "/pos1";
+                let reply = format!(
+                    "{} position set to {}, {}, {}.",
+                    if first { "First" } else { "Second" },
+                    voxel.0,
+                    voxel.1,
+                    voxel.2
+                );
+
+                Ok(self.update_edit_session(player_id, |session| {
+                    if first {
+                        session.pos1 = Some(voxel);
+                    } else {
+                        session.pos2 = Some(voxel);
+                    }
+
+                    match (&session.pos1, &session.pos2) {
+                        (Some(pos1), Some(pos2)) => format!(
+                            "{} Selected {} voxels.",
+                            reply,
+                            Region::new(pos1, pos2).volume()
+                        ),
+                        _ => reply,
+                    }
+                }))
+            }
+            "/set" => {
+                let name = match body.get(1..) {
+                    Some(words) if !words.is_empty() => words.join(" "),
+                    _ => return Err(EditError::Usage("//set <block>")),
+                };
+
+                let id = self
+                    .read_resource::<Chunks>()
+                    .registry
+                    .find_id_by_name(&name)
+                    .ok_or(EditError::UnknownBlock(name))?;
+
+                let region = self.update_edit_session(player_id, |session| session.selection())?;
+                let edits = region
+                    .voxels()
+                    .map(|voxel| (voxel, VoxelState::block(id)))
+                    .collect::<Vec<_>>();
+
+                let previous = self.edit_voxels(&edits);
+                let count = previous.len();
+                self.update_edit_session(player_id, |session| session.history.push(previous));
+
+                Ok(format!("Set {} voxels.", count))
+            }
+            "/copy" => {
+                let region = self.update_edit_session(player_id, |session| session.selection())?;
+                let origin = standing.unwrap_or_else(|| region.min.clone());
+
+                let chunks = self.read_resource::<Chunks>();
+                let clipboard = region
+                    .voxels()
+                    .filter(|&Vec3(vx, vy, vz)| chunks.get_chunk_by_voxel(vx, vy, vz).is_some())
+                    .map(|voxel| {
+                        let state = VoxelState::read(&chunks, voxel.0, voxel.1, voxel.2);
+                        (voxel.sub(&origin), state)
+                    })
+                    .collect::<Vec<_>>();
+                drop(chunks);
+
+                let count = clipboard.len();
+                self.update_edit_session(player_id, |session| session.clipboard = Some(clipboard));
+
+                Ok(format!("Copied {} voxels.", count))
+            }
+            "/paste" => {
+                let turns = |degrees: &str| match degrees {
+                    "0" => Some(0),
+                    "90" => Some(1),
+                    "180" => Some(2),
+                    "270" => Some(3),
+                    _ => None,
+                };
+
+                let transform = match &body[1..] {
+                    [] => Some(Transform::default()),
+                    ["flip"] => Some(Transform::new(0, true)),
+                    [degrees] => turns(degrees).map(|turns| Transform::new(turns, false)),
+                    [degrees, "flip"] => turns(degrees).map(|turns| Transform::new(turns, true)),
+                    _ => None,
+                }
+                .ok_or(EditError::Usage("//paste [90|180|270] [flip]"))?;
+
+                let origin = standing.ok_or(EditError::EmptyClipboard)?;
+                let registry = self.read_resource::<Chunks>().registry.clone();
+
+                let edits = self
+                    .update_edit_session(player_id, |session| {
+                        session.clipboard.as_ref().map(|clipboard| {
+                            clipboard
+                                .iter()
+                                .map(|(offset, state)| {
+                                    (
+                                        origin.add(&transform.voxel(offset)),
+                                        transform.state(&registry, state),
+                                    )
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .ok_or(EditError::EmptyClipboard)?;
+
+                let previous = self.edit_voxels(&edits);
+                let count = previous.len();
+                self.update_edit_session(player_id, |session| session.history.push(previous));
+
+                Ok(format!("Pasted {} voxels.", count))
+            }
+            "/undo" => {
+                let previous = self
+                    .update_edit_session(player_id, |session| session.history.pop())
+                    .ok_or(EditError::NothingToUndo)?;
+
+                let count = self.edit_voxels(&previous).len();
+
+                Ok(format!("Undid {} voxels.", count))
+            }
+            _ => Err(EditError::Usage(
+                "//pos1, //pos2, //set <block>, //copy, //paste [90|180|270] [flip] or //undo",
+            )),
+        }
+    }
+
+    /// Work on a player's edit session, started on first use
+    fn update_edit_session<T>(
+        &mut self,
+        player_id: usize,
+        update: impl FnOnce(&mut EditSession) -> T,
+    ) -> T {
+        update(
+            self.write_resource::<EditSessions>()
+                .entry(player_id)
+                .or_default(),
+        )
+    }
+
+    /// Run a command from the server console, with or without its leading `/`, as an admin
+    /// who isn't in the world. Returns the lines it replied with.
+    pub fn run_console_command(&mut self, command: &str) -> Vec<String> {
+        let command = command.trim();
+        let body = command
+            .strip_prefix('/')
+            .unwrap_or(command)
+            .split_whitespace()
+            .collect::<Vec<_>>();
+
+        info!("Console: {}", command);
+
+        self.run_command(CONSOLE_ID, &body)
+            .into_iter()
+            .filter_map(|msg| msg.message.map(|message| message.body))
+            .collect()
+    }
+
+    /// Run a chat command, given as its words without the leading `/`, returning the replies
+    /// for whoever ran it
+    fn run_command(&mut self, player_id: usize, body: &[&str]) -> Vec<messages::Message> {
+        let mut msgs = vec![];
 
-                let mut msgs = vec![];
+        let create_msg = |chat_type: ChatType, body: &str| {
+            create_chat_message(MessageType::Message, chat_type, "", body)
+        };
 
-                let create_msg = |chat_type: ChatType, body: &str| {
-                    create_chat_message(MessageType::Message, chat_type, "", body)
-                };
+        if body.is_empty() {
+            msgs.push(create_msg(ChatType::Error, "Unknown command."));
+        } else {
+            match body[0] {
+                "save" => {
+                    self.save();
+                    msgs.push(create_msg(ChatType::Info, "World has been saved."));
+                }
+                "summon" => {
+                    let etype = body.get(1).cloned().unwrap_or("Test");
+
+                    if self.test_entity(player_id, etype) {
+                        msgs.push(create_msg(
+                            ChatType::Info,
+                            &format!("Summoned a {} entity.", etype),
+                        ));
+                    } else {
+                        msgs.push(create_msg(ChatType::Error, "Unknown entity type."));
+                    }
+                }
+                "gamemode" => {
+                    let game_mode = body.get(1).and_then(|name| GameMode::from_name(name));
+
+                    msgs.push(match game_mode {
+                        Some(game_mode) => {
+                            match self.admin_target(player_id, body.get(2).cloned()) {
+                                Ok(target) => {
+                                    self.set_game_mode(target, game_mode);
+                                    create_msg(
+                                        ChatType::Info,
+                                        &format!("Game mode set to {}.", game_mode.name()),
+                                    )
+                                }
+                                Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                            }
+                        }
+                        None => create_msg(
+                            ChatType::Error,
+                            "Usage: /gamemode <survival|creative|spectator> [player]",
+                        ),
+                    });
+                }
+                "fly" => {
+                    let (switch, name) = match body.get(1).cloned() {
+                        Some("on") => (Some(true), body.get(2).cloned()),
+                        Some("off") => (Some(false), body.get(2).cloned()),
+                        name => (None, name),
+                    };
+
+                    msgs.push(match self.admin_target(player_id, name) {
+                        Ok(target) => {
+                            let mut abilities = self.get_abilities(target);
+                            abilities.fly = switch.unwrap_or(!abilities.fly);
+                            self.set_abilities(target, abilities);
+
+                            create_msg(
+                                ChatType::Info,
+                                if abilities.fly {
+                                    "Flying enabled."
+                                } else {
+                                    "Flying disabled."
+                                },
+                            )
+                        }
+                        Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                    });
+                }
+                "speed" => {
+                    // no multiplier resets the speed
+                    let speed = match body.get(1) {
+                        Some(arg) => arg
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|speed| *speed > 0.0 && *speed <= MAX_SPEED_MULTIPLIER),
+                        None => Some(1.0),
+                    };
+
+                    msgs.push(match speed {
+                        Some(speed) => match self.admin_target(player_id, body.get(2).cloned()) {
+                            Ok(target) => {
+                                let mut abilities = self.get_abilities(target);
+                                abilities.speed = speed;
+                                self.set_abilities(target, abilities);
+
+                                create_msg(ChatType::Info, &format!("Speed set to {}x.", speed))
+                            }
+                            Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                        },
+                        None => create_msg(
+                            ChatType::Error,
+                            &format!(
+                                "Usage: /speed [multiplier up to {}] [player]",
+                                MAX_SPEED_MULTIPLIER
+                            ),
+                        ),
+                    });
+                }
+                "effect" => {
+                    // `/effect clear [player]`, or `/effect <effect> [seconds] [level] [player]`
+                    let effect = match body.get(1).cloned() {
+                        Some("clear") => Some((None, body.get(2).cloned())),
+                        Some(name) => {
+                            let seconds = body.get(2).map_or(Some(DEFAULT_EFFECT_SECONDS), |arg| {
+                                arg.parse::<f32>().ok().filter(|seconds| *seconds > 0.0)
+                            });
+                            let level = body.get(3).map_or(Some(1), |arg| {
+                                arg.parse::<u32>().ok().filter(|level| *level > 0)
+                            });
+
+                            match (EffectKind::from_name(name), seconds, level) {
+                                (Some(kind), Some(seconds), Some(level)) => {
+                                    Some((Some((kind, seconds, level)), body.get(4).cloned()))
+                                }
+                                _ => None,
+                            }
+                        }
+                        None => None,
+                    };
+
+                    msgs.push(match effect {
+                        Some((effect, name)) => match self.admin_target(player_id, name) {
+                            Ok(target) => match self.get_player_entity(target) {
+                                Some(entity) => {
+                                    let reply = match effect {
+                                        Some((kind, seconds, level)) => {
+                                            self.add_effect(entity, kind, level, seconds);
+                                            format!(
+                                                "Gave {} {} for {} seconds.",
+                                                kind.name(),
+                                                level,
+                                                seconds
+                                            )
+                                        }
+                                        None => {
+                                            self.clear_effects(entity);
+                                            "Status effects cleared.".to_owned()
+                                        }
+                                    };
+
+                                    create_msg(ChatType::Info, &reply)
+                                }
+                                None => create_msg(
+                                    ChatType::Error,
+                                    &ProfileError::UnknownPlayer.to_string(),
+                                ),
+                            },
+                            Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                        },
+                        None => create_msg(
+                            ChatType::Error,
+                            "Usage: /effect <speed|slowness|night_vision|strength|resistance|clear> [seconds] [level] [player]",
+                        ),
+                    });
+                }
+                "gamerule" => {
+                    let switch = match body.get(2).cloned() {
+                        Some("on") => Some(true),
+                        Some("off") => Some(false),
+                        _ => None,
+                    };
+
+                    msgs.push(match (body.get(1).cloned(), switch) {
+                        (_, Some(_)) if !self.is_admin(player_id) => {
+                            create_msg(ChatType::Error, &ProfileError::NotAdmin.to_string())
+                        }
+                        (Some(rule @ "pvp"), Some(on))
+                        | (Some(rule @ "keep_inventory"), Some(on))
+                        | (Some(rule @ "friendly_fire"), Some(on)) => {
+                            let mut config = self.write_resource::<WorldConfig>();
+
+                            match rule {
+                                "pvp" => config.pvp = on,
+                                "keep_inventory" => config.keep_inventory = on,
+                                _ => config.friendly_fire = on,
+                            }
+
+                            create_msg(
+                                ChatType::Info,
+                                &format!("Turned {} {}.", rule, if on { "on" } else { "off" }),
+                            )
+                        }
+                        _ => create_msg(
+                            ChatType::Error,
+                            "Usage: /gamerule <pvp|keep_inventory|friendly_fire> <on|off>",
+                        ),
+                    });
+                }
+                "balance" => {
+                    let target = match body.get(1).cloned() {
+                        Some(_) if !self.is_admin(player_id) => Err(EconomyError::NotAdmin),
+                        Some(name) => self
+                            .find_player_by_name(name)
+                            .ok_or(EconomyError::UnknownPlayer),
+                        None => Ok(player_id),
+                    };
+
+                    msgs.push(match target {
+                        Ok(target) => create_msg(
+                            ChatType::Info,
+                            &format!("Balance: {} coins.", self.balance(target)),
+                        ),
+                        Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                    });
+                }
+                "pay" => {
+                    let result = match (body.get(1), body.get(2)) {
+                        (Some(name), Some(amount)) => {
+                            match (self.find_player_by_name(name), amount.parse::<u64>().ok()) {
+                                (Some(to), Some(amount)) => self
+                                    .pay(player_id, to, amount)
+                                    .map(|_| format!("Sent {} coins to {}.", amount, name)),
+                                (None, _) => Err(EconomyError::UnknownPlayer),
+                                (_, None) => Err(EconomyError::InvalidAmount),
+                            }
+                        }
+                        _ => Err(EconomyError::Usage("/pay <player> <amount>")),
+                    };
 
-                if body.is_empty() {
-                    msgs.push(create_msg(ChatType::Error, "Unknown command."));
-                } else {
-                    match body[0] {
-                        "save" => {
-                            self.save();
-                            msgs.push(create_msg(ChatType::Info, "World has been saved."));
+                    msgs.push(match result {
+                        Ok(reply) => create_msg(ChatType::Info, &reply),
+                        Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                    });
+                }
+                "eco" => {
+                    // `/eco <give|take|set> <player> <amount>`, for admins to mint and burn coins
+                    let usage = EconomyError::Usage("/eco <give|take|set> <player> <amount>");
+
+                    let result = match (body.get(1).cloned(), body.get(2), body.get(3)) {
+                        _ if !self.is_admin(player_id) => Err(EconomyError::NotAdmin),
+                        (Some(action), Some(name), Some(amount)) => {
+                            match (self.find_player_by_name(name), amount.parse::<u64>().ok()) {
+                                (Some(target), Some(amount)) => match action {
+                                    "give" => self.deposit(target, amount),
+                                    "take" => self.withdraw(target, amount),
+                                    "set" => {
+                                        self.write_resource::<Economy>().set(target, amount);
+                                        Ok(amount)
+                                    }
+                                    _ => Err(usage),
+                                },
+                                (None, _) => Err(EconomyError::UnknownPlayer),
+                                (_, None) => Err(EconomyError::InvalidAmount),
+                            }
+                        }
+                        _ => Err(usage),
+                    };
+
+                    msgs.push(match result {
+                        Ok(balance) => create_msg(
+                            ChatType::Info,
+                            &format!("Their balance is now {} coins.", balance),
+                        ),
+                        Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                    });
+                }
+                "msg" | "w" | "tell" => {
+                    let result = match (body.get(1), body.len() > 2) {
+                        (Some(name), true) => self.whisper(player_id, name, &body[2..].join(" ")),
+                        _ => Err(ChatError::Usage("/msg <player> <message>")),
+                    };
+
+                    if let Err(err) = result {
+                        msgs.push(create_msg(ChatType::Error, &err.to_string()));
+                    }
+                }
+                "channel" | "ch" => {
+                    // `/channel <name>` switches channels, `/channel <name> <message>` sends one
+                    // message to a channel without switching
+                    let channel = body.get(1).map(|name| ChatChannel::from_name(name));
+
+                    let result = match channel {
+                        Some(Some(channel)) if body.len() > 2 => self
+                            .send_chat(player_id, channel, &body[2..].join(" "))
+                            .map(|_| None),
+                        Some(Some(ChatChannel::Team))
+                            if self.read_resource::<Teams>().team(player_id).is_none() =>
+                        {
+                            Err(ChatError::NoTeam)
                         }
-                        "summon" => {
-                            self.test_entity(player_id);
-                            msgs.push(create_msg(ChatType::Info, "Summoned a test entity."));
+                        Some(Some(channel)) => {
+                            self.write_resource::<Chat>()
+                                .set_channel(player_id, channel);
+                            Ok(Some(format!("Now talking in {} chat.", channel.name())))
                         }
-                        _ => {}
+                        Some(None) => Err(ChatError::UnknownChannel),
+                        None => Err(ChatError::Usage("/channel <global|local|team> [message]")),
+                    };
+
+                    match result {
+                        Ok(Some(reply)) => msgs.push(create_msg(ChatType::Info, &reply)),
+                        Ok(None) => {}
+                        Err(err) => msgs.push(create_msg(ChatType::Error, &err.to_string())),
                     }
                 }
-
-                msgs.into_iter().for_each(|msg| {
-                    self.broadcast_lazy(&msg, vec![], vec![], player_id);
-                });
-            } else {
-                self.broadcast_lazy(&msg, vec![], vec![], player_id);
+                "mute" | "unmute" => {
+                    let muted = body[0] == "mute";
+
+                    let result = match body.get(1).map(|name| ChatChannel::from_name(name)) {
+                        Some(Some(channel)) => {
+                            self.write_resource::<Chat>()
+                                .set_muted(player_id, channel, muted);
+                            Ok(format!(
+                                "{} chat {}.",
+                                channel.name(),
+                                if muted { "muted" } else { "unmuted" }
+                            ))
+                        }
+                        Some(None) => Err(ChatError::UnknownChannel),
+                        None => Err(ChatError::Usage("/mute <global|local|team>")),
+                    };
+
+                    msgs.push(match result {
+                        Ok(reply) => create_msg(ChatType::Info, &reply),
+                        Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                    });
+                }
+                "team" => msgs.push(match self.team_command(player_id, body) {
+                    Ok(reply) => create_msg(ChatType::Info, &reply),
+                    Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                }),
+                "replay" => msgs.push(match self.replay_command(player_id, body) {
+                    Ok(reply) => create_msg(ChatType::Info, &reply),
+                    Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                }),
+                "locate" => msgs.push(match self.locate_command(player_id, body) {
+                    Ok(reply) => create_msg(ChatType::Info, &reply),
+                    Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                }),
+                "waypoint" | "wp" | "marker" => {
+                    let markers = body[0] == "marker";
+                    msgs.push(match self.waypoint_command(player_id, body, markers) {
+                        Ok(reply) => create_msg(ChatType::Info, &reply),
+                        Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                    })
+                }
+                "silence" => {
+                    // `/silence <player> [seconds]`, for admins to mute a player from chat, or
+                    // let them talk again with 0 seconds
+                    let target = self.admin_target(player_id, body.get(1).cloned());
+                    let seconds = body
+                        .get(2)
+                        .map_or(Some(CHAT_FILTER_MUTE_SECONDS), |s| s.parse::<u64>().ok());
+
+                    msgs.push(match (target, seconds, body.len()) {
+                        (_, _, 1) => {
+                            create_msg(ChatType::Error, "Usage: /silence <player> [seconds]")
+                        }
+                        (Err(err), _, _) => create_msg(ChatType::Error, &err.to_string()),
+                        (Ok(_), None, _) => {
+                            create_msg(ChatType::Error, "Seconds have to be whole.")
+                        }
+                        (Ok(target), Some(seconds), _) => {
+                            let now = SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .expect("Time went backwards?")
+                                .as_millis() as u64;
+                            let until = if seconds == 0 {
+                                None
+                            } else {
+                                Some(now + seconds * 1000)
+                            };
+
+                            self.write_resource::<ChatFilter>().mute(target, until);
+
+                            create_msg(
+                                ChatType::Info,
+                                &match until {
+                                    Some(_) => format!(
+                                        "{} can't chat for {} seconds.",
+                                        self.player_name(target),
+                                        seconds
+                                    ),
+                                    None => format!("{} can chat again.", self.player_name(target)),
+                                },
+                            )
+                        }
+                    });
+                }
+                "tps" => {
+                    msgs.push(create_msg(ChatType::Info, &self.tps_report()));
+                }
+                "list" => {
+                    msgs.push(create_msg(ChatType::Info, &self.list_players()));
+                }
+                "profile" if !self.is_admin(player_id) => {
+                    msgs.push(create_msg(
+                        ChatType::Error,
+                        &ProfileError::NotAdmin.to_string(),
+                    ));
+                }
+                "profile" => {
+                    let result = match body.get(1).cloned() {
+                        Some("start") => Some(
+                            self.start_profile()
+                                .map(|path| format!("Profiling to {}.", path.display())),
+                        ),
+                        Some("stop") => Some(
+                            self.stop_profile()
+                                .map(|path| format!("Profile saved to {}.", path.display())),
+                        ),
+                        _ => None,
+                    };
+
+                    msgs.push(match result {
+                        Some(Ok(text)) => create_msg(ChatType::Info, &text),
+                        Some(Err(err)) => create_msg(ChatType::Error, &err.to_string()),
+                        None => create_msg(ChatType::Error, "Usage: /profile <start|stop>"),
+                    });
+                }
+                // teleporting generates the chunks around where it lands, right on the tick
+                "tp" if !self.is_admin(player_id) => {
+                    msgs.push(create_msg(
+                        ChatType::Error,
+                        &ProfileError::NotAdmin.to_string(),
+                    ));
+                }
+                "tp" => {
+                    let target = self.parse_teleport_target(&body[1..]);
+                    let entity = self.get_player_entity(player_id);
+
+                    match (target, entity) {
+                        (Some(target), Some(entity)) => match self.teleport(entity, &target) {
+                            Some(position) => {
+                                let dimension = self.read_resource::<WorldConfig>().dimension;
+                                let Vec3(vx, vy, vz) = map_world_to_voxel(
+                                    position.0, position.1, position.2, dimension,
+                                );
+
+                                msgs.push(create_msg(
+                                    ChatType::Info,
+                                    &format!("Teleported to {}, {}, {}.", vx, vy, vz),
+                                ));
+                            }
+                            None => msgs.push(create_msg(
+                                ChatType::Error,
+                                "Nowhere safe to land around there.",
+                            )),
+                        },
+                        _ => msgs.push(create_msg(
+                            ChatType::Error,
+                            "Usage: /tp <x> [y] <z> or /tp <player>",
+                        )),
+                    }
+                }
+                "nick" => {
+                    let result = match (body.get(1), body.get(2)) {
+                        (Some(name), None) => Some(self.nickname(player_id, player_id, name)),
+                        (Some(name), Some(target)) => Some(
+                            self.find_player_by_name(target)
+                                .ok_or(ProfileError::UnknownPlayer)
+                                .and_then(|target| self.nickname(player_id, target, name)),
+                        ),
+                        _ => None,
+                    };
+
+                    msgs.push(match result {
+                        Some(Ok(name)) => {
+                            create_msg(ChatType::Info, &format!("Renamed to {}.", name))
+                        }
+                        Some(Err(err)) => create_msg(ChatType::Error, &err.to_string()),
+                        None => create_msg(ChatType::Error, "Usage: /nick <name> [player]"),
+                    });
+                }
+                "skin" => {
+                    let url = body.get(1).cloned();
+
+                    if url.map_or(true, |url| {
+                        url.starts_with("https://") || url.starts_with("http://")
+                    }) {
+                        let url = url.map(|url| url.to_owned());
+                        let hash = body.get(2).map(|hash| hash.to_string());
+
+                        self.update_profile(player_id, |profile| profile.set_skin(url, hash));
+
+                        msgs.push(create_msg(ChatType::Info, "Skin updated."));
+                    } else {
+                        msgs.push(create_msg(
+                            ChatType::Error,
+                            &ProfileError::InvalidSkin.to_string(),
+                        ));
+                    }
+                }
+                "vanish" => {
+                    if self.is_admin(player_id) {
+                        let visible = self.update_profile(player_id, |profile| {
+                            profile.set_visible(!profile.visible);
+                            profile.visible
+                        });
+
+                        msgs.push(create_msg(
+                            ChatType::Info,
+                            if visible == Some(false) {
+                                "Others can no longer see you."
+                            } else {
+                                "Others can see you again."
+                            },
+                        ));
+                    } else {
+                        msgs.push(create_msg(
+                            ChatType::Error,
+                            &ProfileError::NotAdmin.to_string(),
+                        ));
+                    }
+                }
+                "undo" | "redo" => {
+                    let count = match body.get(1) {
+                        Some(arg) => arg.parse::<usize>().ok().filter(|count| *count > 0),
+                        None => Some(1),
+                    };
+
+                    msgs.push(match count {
+                        Some(_) if !self.get_game_mode(player_id).can_edit() => create_msg(
+                            ChatType::Error,
+                            "You can't edit blocks in this game mode.",
+                        ),
+                        Some(count) => {
+                            let undo = body[0] == "undo";
+
+                            let changes = {
+                                let mut journals = self.write_resource::<Journals>();
+                                let journal = journals.entry(player_id).or_default();
+
+                                if undo {
+                                    journal.undo(count)
+                                } else {
+                                    journal.redo(count)
+                                }
+                            };
+
+                            if changes.is_empty() {
+                                create_msg(
+                                    ChatType::Error,
+                                    if undo {
+                                        "Nothing left to undo."
+                                    } else {
+                                        "Nothing left to redo."
+                                    },
+                                )
+                            } else {
+                                let skipped =
+                                    changes.len() - self.replay_journal(player_id, &changes);
+                                let verb = if undo { "Undid" } else { "Redid" };
+
+                                create_msg(
+                                    ChatType::Info,
+                                    &if skipped == 0 {
+                                        format!("{} {} block edits.", verb, changes.len())
+                                    } else {
+                                        format!(
+                                            "{} {} block edits, {} of them were left alone as the blocks changed since.",
+                                            verb,
+                                            changes.len(),
+                                            skipped
+                                        )
+                                    },
+                                )
+                            }
+                        }
+                        None => create_msg(
+                            ChatType::Error,
+                            &format!("Usage: /{} [count]", body[0]),
+                        ),
+                    });
+                }
+                "reloadscripts" => {
+                    if self.is_admin(player_id) {
+                        let (loaded, errors) = self.scripts.reload();
+                        self.update_chat_filters();
+
+                        msgs.push(create_msg(
+                            ChatType::Info,
+                            &format!("Loaded {} scripts.", loaded),
+                        ));
+                        errors.iter().for_each(|error| {
+                            msgs.push(create_msg(ChatType::Error, error));
+                        });
+                    } else {
+                        msgs.push(create_msg(
+                            ChatType::Error,
+                            &ProfileError::NotAdmin.to_string(),
+                        ));
+                    }
+                }
+                "co" => {
+                    if let Err(err) = self.audit_command(player_id, body) {
+                        msgs.push(create_msg(ChatType::Error, &err.to_string()));
+                    }
+                }
+                "claim" | "unclaim" | "trust" | "untrust" | "claimflag" | "claimoverride" => {
+                    msgs.push(match self.claim_command(player_id, body) {
+                        Ok(reply) => create_msg(ChatType::Info, &reply),
+                        Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                    });
+                }
+                "/pos1" | "/pos2" | "/set" | "/copy" | "/paste" | "/undo" => {
+                    msgs.push(match self.world_edit(player_id, body) {
+                        Ok(reply) => create_msg(ChatType::Info, &reply),
+                        Err(err) => create_msg(ChatType::Error, &err.to_string()),
+                    });
+                }
+                _ => {}
             }
         }
+
+        msgs
+    }
+
+    /// Describe how fast the world is ticking, and where the time goes
+    fn tps_report(&self) -> String {
+        let ticker = self.read_resource::<Ticker>();
+        let timings = ticker.timings.lock().unwrap();
+
+        let stages = TICK_STAGES
+            .iter()
+            .map(|stage| format!("{} {:.2}ms", stage, timings.average(stage) * 1000.0))
+            .collect::<Vec<_>>();
+
+        format!(
+            "TPS: {:.1}/{}, average tick {:.2}ms ({}).",
+            timings.tps(),
+            ticker.tps,
+            timings.average("total") * 1000.0,
+            stages.join(", ")
+        )
+    }
+
+    /// Start exporting tracing spans into a chrome trace under the world's chunk root
+    fn start_profile(&self) -> Result<PathBuf, ProfilerError> {
+        let profiler = self
+            .ecs
+            .try_fetch::<Profiler>()
+            .ok_or(ProfilerError::Unavailable)?;
+        let folder = PathBuf::from(&self.read_resource::<WorldConfig>().chunk_root).join("traces");
+
+        profiler.start(&folder, &self.name)
+    }
+
+    /// Stop the running profile, flushing its trace file
+    fn stop_profile(&self) -> Result<PathBuf, ProfilerError> {
+        self.ecs
+            .try_fetch::<Profiler>()
+            .ok_or(ProfilerError::Unavailable)?
+            .stop()
     }
 
     /// TEST:
     ///
-    /// Used to test entity spawning
-    pub fn test_entity(&mut self, player_id: usize) {
+    /// Used to test entity spawning, returns false if the entity type doesn't exist
+    pub fn test_entity(&mut self, player_id: usize, etype: &str) -> bool {
         let players = self.read_resource::<Players>();
         let player = players.get(&player_id);
 
         if player.is_none() {
-            return;
+            return false;
         }
 
         let player = player.unwrap();
@@ -584,69 +4370,559 @@ impl World {
         drop(bodies);
         drop(players);
 
+        self.spawn_entity(etype, &pos).is_some()
+    }
+
+    /// Spawn an entity from its prototype, returns `None` if the entity type doesn't exist
+    pub fn spawn_entity(&mut self, etype: &str, position: &Vec3<f32>) -> Option<Entity> {
         let entities = self.read_resource::<Entities>();
-        let prototype = entities
-            .get_prototype("Test")
-            .unwrap_or_else(|| panic!("Prototype not found: Test"))
-            .clone();
+        let prototype = entities.get_prototype(etype)?.clone();
 
         drop(entities);
 
-        Entities::spawn_entity(
+        Some(Entities::spawn_entity(
             self.ecs_mut(),
             &prototype,
-            "Test",
-            &Vec3(pos.0, pos.1, pos.2),
+            etype,
+            position,
             &Quaternion(0.0, 0.0, 0.0, 0.0),
-        );
+        ))
+    }
+
+    /// Teleport an entity or a player's entity to a position.
+    ///
+    /// The destination chunks are loaded first, then the closest safe place to land is
+    /// searched for downwards from the target. Returns where the entity landed, or `None`
+    /// if there's nowhere safe to land.
+    pub fn teleport(&mut self, entity: Entity, target: &Vec3<f32>) -> Option<Vec3<f32>> {
+        let mut chunks = self.write_resource::<Chunks>();
+
+        let dimension = chunks.config.dimension;
+        let max_height = chunks.config.max_height as i32;
+
+        let mut target = target.clone();
+
+        if let Some(border) = &chunks.config.border {
+            let d = dimension as f32;
+            let (x, z) = border.clamp(target.0 / d, target.2 / d);
+            target = Vec3(x * d, target.1, z * d);
+        }
+
+        let Vec3(vx, vy, vz) = map_world_to_voxel(target.0, target.1, target.2, dimension);
+        let voxel = Vec3(vx, vy.max(1).min(max_height - 2), vz);
+
+        let coords = map_voxel_to_chunk(voxel.0, voxel.1, voxel.2, chunks.config.chunk_size);
+        chunks.generate(&coords, TELEPORT_LOAD_RADIUS, true);
+
+        let Vec3(vx, vy, vz) = Teleport::find_landing(&chunks, &voxel, TELEPORT_SEARCH_RADIUS)?;
+        let coords = map_voxel_to_chunk(vx, vy, vz, chunks.config.chunk_size);
+
+        drop(chunks);
+
+        let d = dimension as f32;
+        let position = Vec3((vx as f32 + 0.5) * d, vy as f32 * d, (vz as f32 + 0.5) * d);
+
+        let mut bodies = self.ecs().write_component::<RigidBody>();
+        let body = bodies.get_mut(entity)?;
+
+        body.set_position(&position);
+        body.velocity = Vec3::default();
+        body.forces = Vec3::default();
+        body.impulses = Vec3::default();
+        body.resting = Vec3::default();
+
+        drop(bodies);
+
+        // subscribe to the chunks around the destination right away
+        if let Some(curr_chunk) = self.ecs().write_component::<CurrChunk>().get_mut(entity) {
+            curr_chunk.val = Some(coords);
+            curr_chunk.changed = true;
+        }
+
+        let player = self.ecs().read_component::<Id>().get(entity).map(|id| id.0);
+
+        if let Some(id) = player {
+            // chunks requested around the old position aren't needed anymore
+            if let Some(player) = self.write_resource::<Players>().get_mut(&id) {
+                let dropped = player.requested_chunks.drain(..).collect::<Vec<_>>();
+                let dropped_horizons = player.requested_horizons.drain(..).collect::<Vec<_>>();
+                player.pending_chunks.clear();
+
+                if !dropped.is_empty() {
+                    player.send(&create_chunk_reply(&[], &dropped));
+                }
+
+                if !dropped_horizons.is_empty() {
+                    player.send(&create_horizon_reply(&dropped_horizons));
+                }
+            }
+        }
+
+        let message = Teleport::create_message(player, entity, &position, dimension);
+        self.broadcast_lazy(&message, vec![], vec![], 0);
+
+        Some(position)
+    }
+
+    /// Link a position to a portal in this world, building one if there's none around
+    ///
+    /// Returns where a traveler should arrive.
+    pub fn link_portal(&mut self, position: &Vec3<f32>) -> Vec3<f32> {
+        let mut chunks = self.ecs.write_resource::<Chunks>();
+
+        let mut position = position.clone();
+
+        // never link to a portal outside of the border
+        if let Some(border) = &chunks.config.border {
+            let dimension = chunks.config.dimension as f32;
+            let (x, z) = border.clamp(position.0 / dimension, position.2 / dimension);
+            position = Vec3(x * dimension, position.1, z * dimension);
+        }
+
+        chunks.start_caching();
+        let (arrival, changed) = Portals::link(&mut chunks, &position);
+        chunks.stop_caching();
+
+        if changed.is_empty() {
+            chunks.clear_cache();
+            return arrival;
+        }
+
+        let updates = changed
+            .into_iter()
+            .filter_map(|Vec3(vx, vy, vz)| chunks.get_update_protocol(vx, vy, vz))
+            .collect();
+
+        let mut components = MessageComponents::default_for(MessageType::Update);
+        components.updates = Some(updates);
+        components.chunks =
+            Some(chunks.take_cache_protocols(&mut self.ecs.write_resource::<RemeshQueue>()));
+
+        drop(chunks);
+
+        self.broadcast(&create_message(components), vec![], vec![]);
+
+        arrival
     }
 
-    /// Sync configurations to the world's JSON file
+    /// Sync configurations from the world's storage, upgrading saves of older formats first.
+    ///
+    /// Panics on saves that can't be read as they are, rather than corrupting them: ones of a
+    /// newer format, or of other chunk metrics than configured.
     pub fn sync_config(&mut self) {
         let chunks = self.read_resource::<Chunks>();
 
-        let mut path = chunks.root_folder.clone();
-        path.push(WORLD_DATA_FILE);
+        let metadata = chunks.storage.load_world().map(|data| {
+            let metrics = WorldMetrics::from_config(&chunks.config);
+
+            let data = Migrations::new()
+                .migrate(chunks.storage.as_ref(), data, &metrics)
+                .unwrap_or_else(|e| panic!("Unable to load world \"{}\": {}", self.name, e));
+
+            let metadata: WorldMetadata = serde_json::from_value(data)
+                .unwrap_or_else(|e| panic!("Unable to read world \"{}\": {}", self.name, e));
+
+            if metadata.metrics != metrics {
+                panic!(
+                    "World \"{}\" was saved with {:?}, but is configured with {:?}.",
+                    self.name, metadata.metrics, metrics
+                );
+            }
+
+            if metadata.seed != LEVEL_SEED {
+                warn!(
+                    "World \"{}\" was generated with seed {}, new chunks won't line up with it.",
+                    self.name, metadata.seed
+                );
+            }
+
+            metadata
+        });
 
         drop(chunks);
 
-        if let Ok(file) = File::open(path) {
-            let WorldData { time, tick_speed } = serde_json::from_reader(file).unwrap();
+        if let Some(metadata) = metadata {
             let mut clock = self.write_resource::<Clock>();
 
-            clock.set_time(time);
-            clock.set_tick_speed(tick_speed);
+            clock.set_time(metadata.time);
+            clock.set_tick_speed(metadata.tick_speed);
+            drop(clock);
+
+            *self.write_resource::<WorldMetadata>() = metadata;
+        }
+    }
+
+    /// The PNG map tile of a chunk, rendered if it's loaded and hasn't been yet
+    pub fn map_tile(&mut self, coords: &Vec2<i32>) -> Option<Arc<Vec<u8>>> {
+        if let Some(tile) = self.read_resource::<MapTiles>().get(coords) {
+            return Some(tile);
+        }
+
+        let chunks = self.read_resource::<Chunks>();
+        let tile = match chunks.get_chunk(coords) {
+            Some(chunk) if !chunk.needs_terrain && !chunk.needs_decoration => {
+                Arc::new(render_tile(chunk, &chunks.registry))
+            }
+            _ => return None,
+        };
+        drop(chunks);
+
+        self.write_resource::<MapTiles>()
+            .insert(coords, tile.clone());
+
+        Some(tile)
+    }
+
+    /// Set where players join the world when they've never been in it, saved with the world
+    pub fn set_spawn(&mut self, spawn: Option<Vec3<i32>>) {
+        self.write_resource::<WorldMetadata>().spawn = spawn;
+    }
+
+    /// Count players into the stats heatmap, and append a snapshot of the world to the stats
+    /// log when one is due, if the world records its stats
+    fn record_stats(&self) {
+        let mut recorder = match self.ecs.try_fetch_mut::<StatsRecorder>() {
+            Some(recorder) => recorder,
+            None => return,
+        };
+
+        let ids = self
+            .read_resource::<Players>()
+            .iter()
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+
+        ids.iter()
+            .filter_map(|&id| self.get_player_voxel(id))
+            .for_each(|voxel| recorder.sample(&voxel));
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards?")
+            .as_secs();
+
+        if !recorder.is_due(now) {
+            return;
+        }
+
+        let mut chunks = self.ecs.write_resource::<Chunks>();
+        let changes = chunks.take_block_changes();
+
+        let mut blocks = BTreeMap::new();
+        changes.into_iter().for_each(|(id, count)| {
+            let name = chunks.registry.get_block_by_id(id).name.to_owned();
+            *blocks.entry(name).or_insert(0) += count;
+        });
+
+        let snapshot = StatsSnapshot {
+            time: now,
+            chunks: chunks.len(),
+            players: ids.len(),
+            blocks,
+            heatmap: recorder.take_heatmap(),
+        };
+        drop(chunks);
+
+        recorder.write(&snapshot);
+    }
+
+    /// Keep what happened this tick in the replay being recorded, and send the players
+    /// watching replays what happens next in them
+    fn update_replays(&mut self) {
+        let tick = self.read_resource::<Ticker>().tick as u64;
+
+        if self.read_resource::<Replays>().is_recording() {
+            let events = self.replay_events(tick);
+            self.write_resource::<Replays>().record(tick, events);
+        }
+
+        let (due, finished) = self.write_resource::<Replays>().advance(tick);
+
+        for (player_id, events) in due {
+            self.send_replay_events(player_id, events);
+        }
+
+        for player_id in finished {
+            self.end_playback(player_id);
+
+            let message = create_chat_message(
+                MessageType::Message,
+                ChatType::Info,
+                "",
+                "The replay is over.",
+            );
+            self.broadcast_lazy(&message, vec![player_id], vec![], 0);
+        }
+    }
+
+    /// What happened this tick worth keeping in a replay: every voxel updated, and where the
+    /// players and entities are every `REPLAY_TRANSFORM_TICKS` ticks
+    fn replay_events(&self, tick: u64) -> Vec<ReplayEvent> {
+        let mut events = vec![];
+
+        if tick % REPLAY_TRANSFORM_TICKS == 0 {
+            let bodies = self.ecs.read_component::<RigidBody>();
+            let rotations = self.ecs.read_component::<Rotation>();
+            let types = self.ecs.read_component::<EType>();
+
+            let players = self
+                .read_resource::<Players>()
+                .iter()
+                .map(|(&id, player)| (id, player.entity))
+                .collect::<Vec<_>>();
+
+            for (id, entity) in players {
+                if let (Some(body), Some(rotation)) = (bodies.get(entity), rotations.get(entity)) {
+                    let Vec3(px, py, pz) = body.get_position();
+                    let Quaternion(qx, qy, qz, qw) = rotation.0;
+
+                    events.push(ReplayEvent::Player {
+                        id,
+                        name: self.player_name(id),
+                        position: [px, py, pz],
+                        rotation: [qx, qy, qz, qw],
+                    });
+                }
+            }
+
+            for (entity, etype, body) in (&self.ecs.entities(), &types, &bodies).join() {
+                let Vec3(px, py, pz) = body.get_position();
+
+                events.push(ReplayEvent::Entity {
+                    id: entity.id(),
+                    entity_type: etype.0.to_owned(),
+                    position: [px, py, pz],
+                });
+            }
+        }
+
+        let mut chunks = self.ecs.write_resource::<Chunks>();
+        let voxels = chunks.take_updated_voxels();
+
+        events.extend(voxels.into_iter().filter_map(|Vec3(vx, vy, vz)| {
+            let update = chunks.get_update_protocol(vx, vy, vz)?;
+
+            Some(ReplayEvent::Block {
+                voxel: Vec3(vx, vy, vz),
+                id: update.r#type,
+                rotation: update.rotation,
+                y_rotation: update.y_rotation,
+                stage: update.stage,
+            })
+        }));
+
+        events
+    }
+
+    /// Send a player watching a replay what happened in it, with the players and entities in
+    /// it going by ids of their own
+    fn send_replay_events(&mut self, player_id: usize, events: Vec<ReplayEvent>) {
+        let mut peers = vec![];
+        let mut entities = vec![];
+        let mut updates = vec![];
+
+        for event in events {
+            match event {
+                ReplayEvent::Player {
+                    id,
+                    name,
+                    position: [px, py, pz],
+                    rotation: [qx, qy, qz, qw],
+                } => peers.push(PeerProtocol {
+                    id: replay_id(id as u64),
+                    name,
+                    px,
+                    py,
+                    pz,
+                    qx,
+                    qy,
+                    qz,
+                    qw,
+                    swim_up: false,
+                    team: String::new(),
+                    crouching: false,
+                    vehicle: String::new(),
+                    offset: vec![],
+                }),
+                ReplayEvent::Entity {
+                    id,
+                    entity_type,
+                    position: [px, py, pz],
+                } => entities.push(EntityProtocol {
+                    id: replay_id(id as u64),
+                    r#type: entity_type,
+                    px,
+                    py,
+                    pz,
+                    heading: None,
+                    look_at: None,
+                    velocity: Vec3(0.0, 0.0, 0.0),
+                    resting: Vec3(0, 0, 0),
+                }),
+                ReplayEvent::Block {
+                    voxel: Vec3(vx, vy, vz),
+                    id,
+                    rotation,
+                    y_rotation,
+                    stage,
+                } => updates.push(UpdateProtocol {
+                    vx,
+                    vy,
+                    vz,
+                    r#type: id,
+                    rotation,
+                    y_rotation,
+                    stage,
+                }),
+            }
+        }
+
+        let mut messages = vec![];
+
+        if !peers.is_empty() {
+            let mut components = MessageComponents::default_for(MessageType::Peer);
+            components.peers = Some(peers);
+            messages.push(create_message(components));
+        }
+
+        if !entities.is_empty() {
+            let mut components = MessageComponents::default_for(MessageType::Entity);
+            components.entities = Some(entities);
+            messages.push(create_message(components));
+        }
+
+        if !updates.is_empty() {
+            let mut components = MessageComponents::default_for(MessageType::Update);
+            components.updates = Some(updates);
+            messages.push(create_message(components));
+        }
+
+        messages
+            .iter()
+            .for_each(|message| self.broadcast_lazy(message, vec![player_id], vec![], 0));
+    }
+
+    /// Stop playing a replay back to a player, setting the voxels it changed back to what
+    /// they actually are and taking away the players it showed
+    fn end_playback(&mut self, player_id: usize) -> Option<()> {
+        let leftovers = self.write_resource::<Replays>().stop_playback(player_id)?;
+
+        let updates = {
+            let chunks = self.read_resource::<Chunks>();
+            leftovers
+                .voxels
+                .iter()
+                .filter_map(|Vec3(vx, vy, vz)| chunks.get_update_protocol(*vx, *vy, *vz))
+                .collect::<Vec<_>>()
+        };
+
+        if !updates.is_empty() {
+            let mut components = MessageComponents::default_for(MessageType::Update);
+            components.updates = Some(updates);
+            self.broadcast_lazy(&create_message(components), vec![player_id], vec![], 0);
+        }
+
+        for peer in leftovers.peers {
+            let mut message = create_of_type(MessageType::Leave);
+            message.text = peer;
+            self.broadcast_lazy(&message, vec![player_id], vec![], 0);
+        }
+
+        Some(())
+    }
+
+    /// Run `/replay`, for admins to record what happens in the world and to watch it again
+    fn replay_command(&mut self, player_id: usize, body: &[&str]) -> Result<String, ReplayError> {
+        const USAGE: &str = "/replay <record <name>|stop|play <name>|end|list>";
+
+        if !self.is_admin(player_id) {
+            return Err(ReplayError::NotAdmin);
+        }
+
+        let tick = self.read_resource::<Ticker>().tick as u64;
+
+        match (body.get(1).cloned(), body.get(2).cloned(), body.len()) {
+            (Some("record"), Some(name), 3) => {
+                let path = self.write_resource::<Replays>().start(name, tick)?;
+                self.write_resource::<Chunks>().log_updates(true);
+
+                Ok(format!("Recording replay to {}.", path.display()))
+            }
+            (Some("stop"), None, _) => {
+                let path = self.write_resource::<Replays>().stop()?;
+                self.write_resource::<Chunks>().log_updates(false);
+
+                Ok(format!("Replay saved to {}.", path.display()))
+            }
+            (Some("play"), Some(name), 3) => {
+                self.end_playback(player_id);
+
+                let ticks = self
+                    .write_resource::<Replays>()
+                    .play(player_id, name, tick)?;
+                let tps = self.read_resource::<WorldConfig>().tps;
+
+                Ok(format!(
+                    "Playing replay {}, {:.0} seconds long. /replay end to stop watching.",
+                    name,
+                    ticks as f32 / tps
+                ))
+            }
+            (Some("end"), None, _) => self
+                .end_playback(player_id)
+                .map(|_| "Stopped watching the replay.".to_owned())
+                .ok_or(ReplayError::NotPlaying),
+            (Some("list"), None, _) => {
+                let names = self.read_resource::<Replays>().list();
+
+                if names.is_empty() {
+                    return Ok("There are no replays.".to_owned());
+                }
+
+                Ok(format!("Replays: {}", names.join(", ")))
+            }
+            _ => Err(ReplayError::Usage(USAGE)),
         }
     }
 
-    /// Saves the world. Things done:
+    /// Saves the world to its storage, all at once. Things saved:
     ///
-    /// 1. Saves the world configs (`time`, `tick_speed`, ...etc)
-    /// 2. Save all chunks within `chunks` to their corresponding JSON files
+    /// 1. The world's metadata (`time`, `tick_speed`, `spawn`, ...etc)
+    /// 2. All chunks within `chunks` that need saving
+    /// 3. Container contents
+    /// 4. Logged in players
     pub fn save(&self) {
         let chunks = self.read_resource::<Chunks>();
         let clock = self.read_resource::<Clock>();
 
         if chunks.config.save {
-            // saving world data
-            let mut root = chunks.root_folder.clone();
-            root.push(WORLD_DATA_FILE);
-
-            let mut file = File::create(root.into_os_string().into_string().unwrap())
-                .expect("Could not create world config.");
-
-            let data = WorldData {
+            let data = WorldMetadata {
                 time: clock.time,
                 tick_speed: clock.tick_speed,
+                ..(*self.read_resource::<WorldMetadata>()).clone()
             };
 
-            let j = serde_json::to_string(&data).unwrap();
-
-            file.write_all(j.as_bytes())
-                .expect("Unable to save world data");
-
-            // saving chunks
-            chunks.save();
+            let players = self
+                .read_resource::<Players>()
+                .iter()
+                .filter_map(|(&id, player)| player.uuid.map(|uuid| (id, uuid, player.entity)))
+                .collect::<Vec<_>>();
+
+            let players = players
+                .into_iter()
+                .filter_map(|(id, uuid, entity)| {
+                    let data = self.player_data(id, entity)?;
+                    Some((uuid, data.to_saved()))
+                })
+                .collect();
+
+            chunks.storage.write(WriteBatch {
+                world: Some(serde_json::to_value(&data).unwrap()),
+                chunks: chunks.to_saved(),
+                containers: Some(self.read_resource::<Containers>().to_saved()),
+                players,
+                edits: vec![],
+            });
 
             // info!(
             //     "Saving data for world \"{}\" took {:?}.",
@@ -656,43 +4932,175 @@ impl World {
         }
     }
 
-    /// A world tick
+    /// Poll the world, running as many fixed-length ticks as are due since the last poll
+    pub fn tick(&mut self) {
+        let due = if self.read_resource::<WorldConfig>().lockstep.is_some() {
+            1
+        } else {
+            self.write_resource::<Ticker>().advance()
+        };
+
+        for _ in 0..due {
+            self.step();
+        }
+    }
+
+    /// A single world tick, run right away however long it's been since the last, which is
+    /// how tests drive worlds by hand
     ///
     /// 1. Tick resources
     ///     - `Clock`
     ///     - `Chunks`
     /// 2. Dispatch all ECS systems
     /// 3. Periodically save the world
-    pub fn tick(&mut self) {
+    pub fn step(&mut self) {
         // TODO: make dispatchers
+        let _span = info_span!("tick", world = %self.name).entered();
+        let start = Instant::now();
+
+        let (delta, timings) = {
+            let mut ticker = self.write_resource::<Ticker>();
+            ticker.tick = ticker.tick.wrapping_add(1);
+            (ticker.step(), Arc::clone(&ticker.timings))
+        };
 
         // handle game tick
-        self.write_resource::<Clock>().tick();
+        self.write_resource::<Clock>().tick(delta);
 
         // handle chunk generation
-        self.write_resource::<Chunks>().tick();
+        let pool_timings = {
+            let mut chunks = self.write_resource::<Chunks>();
+            chunks.tick();
+            chunks.take_timings()
+        };
 
         let mut dispatcher = DispatcherBuilder::new()
-            .with(PhysicsSystem, "physics", &[])
-            .with(PeersSystem, "peers", &["physics"])
+            .with(
+                Timed::new(PhysicsSystem, "physics", &timings),
+                "physics",
+                &[],
+            )
+            .with(
+                Timed::new(PeersSystem, "network", &timings),
+                "peers",
+                &["physics"],
+            )
             .with(ChunkingSystem, "chunking", &["peers"])
             .with(GenerationSystem, "generation", &["chunking"])
-            .with(MeshingSystem, "meshing", &["generation"])
+            .with(
+                Timed::new(MeshingSystem, "meshing", &timings),
+                "meshing",
+                &["generation"],
+            )
+            .with(HorizonSystem, "horizon", &["meshing"])
             .with(SearchSystem, "search", &["peers"])
             .with(ObserveSystem, "observe", &["search"])
             .with(EntitiesSystem, "entities", &["chunking"])
-            .with(PathFindSystem, "pathfind", &["observe"])
-            .with(BroadcastSystem, "broadcast", &["peers"])
+            .with(BehaviorSystem, "behavior", &["observe"])
+            .with(HostileSystem, "hostile", &["observe"])
+            .with(BorderSystem, "border", &["peers"])
+            .with(EffectsSystem, "effects", &["physics"])
+            .with(
+                HealthSystem,
+                "health",
+                &["behavior", "hostile", "border", "effects"],
+            )
+            .with(PathFindSystem, "pathfind", &["health"])
+            .with(KnockbackSystem, "knockback", &["health"])
+            .with(CircuitsSystem, "circuits", &["generation"])
+            .with(GravitySystem, "gravity", &["physics", "generation"])
+            .with(ItemsSystem, "items", &["physics", "health"])
+            .with(ExperienceSystem, "experience", &["physics", "health"])
+            .with(RandomTickSystem, "random_tick", &["circuits"])
+            .with(MapSystem, "map", &["meshing", "gravity", "random_tick"])
+            .with(
+                RemeshSystem,
+                "remesh",
+                &["meshing", "gravity", "random_tick", "map"],
+            )
+            .with(ParticlesSystem, "particles", &["peers"])
+            .with(SoundsSystem, "sounds", &["peers", "health"])
+            .with(PortalsSystem, "portals", &["peers"])
+            .with(ProfilesSystem, "profiles", &["peers"])
+            .with(EquipmentSystem::default(), "equipment", &["peers"])
+            .with(
+                Timed::new(BroadcastSystem, "network", &timings),
+                "broadcast",
+                &[
+                    "peers",
+                    "profiles",
+                    "random_tick",
+                    "gravity",
+                    "remesh",
+                    "health",
+                    "knockback",
+                    "particles",
+                    "sounds",
+                    "equipment",
+                    "items",
+                    "experience",
+                ],
+            )
             .with(WalkTowardsSystem, "walk_towards", &["pathfind"])
+            .with(MountsSystem, "mounts", &["walk_towards"])
             .build();
 
-        dispatcher.dispatch(&self.ecs);
+        {
+            let _span = info_span!("dispatch").entered();
+            dispatcher.dispatch(&self.ecs);
+        }
 
         self.ecs.maintain();
 
+        {
+            let mut timings = timings.lock().unwrap();
+            pool_timings
+                .into_iter()
+                .for_each(|(stage, secs)| timings.record(stage, secs));
+            timings.end_tick(start.elapsed().as_secs_f32());
+        }
+
+        let tick = self.read_resource::<Ticker>().tick as u64;
+        for (script, function) in self.scripts.take_due(tick) {
+            self.run_scripts(Some(&script), &function, ());
+        }
+        self.run_scripts(None, "on_tick", ());
+
+        self.record_stats();
+        self.update_replays();
+        self.finish_audits();
+
         // saving the chunks
         if self.read_resource::<Clock>().tick % 8000 == 0 {
             self.save()
         }
     }
 }
+
+/// Parse a `[vx, vy, vz]` JSON array into voxel coordinates
+fn parse_voxel(value: &serde_json::Value) -> Option<Vec3<i32>> {
+    match value.as_array() {
+        Some(arr) if arr.len() == 3 => Some(Vec3(
+            arr[0].as_i64()? as i32,
+            arr[1].as_i64()? as i32,
+            arr[2].as_i64()? as i32,
+        )),
+        _ => None,
+    }
+}
+
+/// Get the world position of a voxel's center
+fn voxel_center(voxel: &Vec3<i32>, dimension: f32) -> Vec3<f32> {
+    Vec3(
+        (voxel.0 as f32 + 0.5) * dimension,
+        (voxel.1 as f32 + 0.5) * dimension,
+        (voxel.2 as f32 + 0.5) * dimension,
+    )
+}
+
+/// Create a message telling clients to play a container's open/close animation
+fn container_animation(voxel: &Vec3<i32>, action: &str) -> messages::Message {
+    let mut message = create_of_type(MessageType::Container);
+    message.json = serde_json::json!({ "action": action, "voxel": voxel }).to_string();
+    message
+}
@@ -9,11 +9,7 @@ use kdtree::KdTree;
 
 use serde::Deserialize;
 
-use server_common::{
-    math::smooth_interpolation,
-    ndarray::{ndarray, Ndarray},
-    noise::Noise,
-};
+use server_common::{array::Array2, math::smooth_interpolation, noise::Noise};
 
 pub const TEMPERATURE_SCALE: f64 = 0.005;
 pub const HUMIDITY_SCALE: f64 = 0.002;
@@ -98,6 +94,11 @@ impl Biomes {
         let biome_configs: BiomeConfigs =
             serde_json::from_reader(File::open("assets/metadata/biomes.json").unwrap()).unwrap();
 
+        Biomes::from_configs(biome_configs)
+    }
+
+    /// Set up the biomes of a configuration, without reading `biomes.json`
+    pub fn from_configs(biome_configs: BiomeConfigs) -> Self {
         let BiomeConfigs {
             temperature_scale,
             temperature_seed,
@@ -173,6 +174,11 @@ impl Biomes {
             .collect()
     }
 
+    /// Whether a biome of a name can show up, presets being the only way biomes are placed
+    pub fn has_biome(&self, name: &str) -> bool {
+        self.configs.biomes.iter().any(|biome| biome.name == name)
+    }
+
     /// Get the interpolated height of X nearest biomes
     pub fn get_biome(&self, vx: i32, vz: i32) -> Biome {
         let vx = vx as f64;
@@ -289,8 +295,8 @@ pub fn get_height_within(
     x_max: i32,
     z_max: i32,
     noise: &Noise,
-) -> Ndarray<i32> {
-    let mut height_map = ndarray(vec![(x_max - x_min) as usize, (z_max - z_min) as usize], 0);
+) -> Array2<i32> {
+    let mut height_map = Array2::new([(x_max - x_min) as usize, (z_max - z_min) as usize], 0);
 
     let bottom_left = get_biome_config(x_min, z_min, noise).1.height_offset as f64;
     let bottom_right = get_biome_config(x_max, z_min, noise).1.height_offset as f64;
@@ -312,7 +318,7 @@ pub fn get_height_within(
                 z as f64,
             );
 
-            height_map[&[(x - x_min) as usize, (z - z_min) as usize]] = h as i32;
+            height_map[[(x - x_min) as usize, (z - z_min) as usize]] = h as i32;
         }
     }
 
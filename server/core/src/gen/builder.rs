@@ -1,4 +1,4 @@
-use std::f32::consts::PI;
+use std::{f32::consts::PI, sync::Arc};
 
 use super::super::engine::{chunk::Chunk, registry::Registry};
 
@@ -17,11 +17,11 @@ pub struct VoxelUpdate {
 #[derive(Debug)]
 pub struct Builder {
     noise: Noise,
-    registry: Registry,
+    registry: Arc<Registry>,
 }
 
 impl Builder {
-    pub fn new(registry: Registry, noise: Noise) -> Self {
+    pub fn new(registry: Arc<Registry>, noise: Noise) -> Self {
         Self { noise, registry }
     }
 
@@ -5,12 +5,14 @@ use super::super::{
 };
 
 use super::biomes::{get_biome_config, BiomeConfig, Biomes, CAVE_SCALE};
+use super::presets::{Amplified, Islands, Superflat, TerrainGenerator};
 
 use log::debug;
 use server_common::{
     noise::{Noise, NoiseConfig},
     vec::Vec3,
 };
+use tracing::info_span;
 
 pub struct Generator;
 
@@ -22,6 +24,17 @@ impl Generator {
         biomes: &Biomes,
         config: &WorldConfig,
     ) {
+        let _span = info_span!("generate_chunk", x = chunk.coords.0, z = chunk.coords.1).entered();
+
+        // chunks beyond the world border are left as void
+        if let Some(border) = &config.border {
+            if !border.contains_chunk(&chunk.coords, config.chunk_size) {
+                chunk.needs_terrain = false;
+                chunk.calc_is_empty();
+                return;
+            }
+        }
+
         let Vec3(start_x, start_y, start_z) = chunk.min;
         let Vec3(end_x, end_y, end_z) = chunk.max;
 
@@ -29,9 +42,8 @@ impl Generator {
             "flat" => {
                 let types = registry.get_type_map(vec!["Stone", "Stone Bricks"]);
 
-                let is_empty = true;
-
-                let flat_height = 30;
+                // kept under the top of worlds shorter than the ground
+                let flat_height = 30.min(end_y - 1);
 
                 for vx in start_x..end_x {
                     for vz in start_z..end_z {
@@ -44,8 +56,6 @@ impl Generator {
                         }
                     }
                 }
-
-                chunk.is_empty = is_empty;
             }
             "hilly" => {
                 let types = registry.get_type_map(vec!["Air", "Grass Block", "Stone", "Dirt"]);
@@ -55,8 +65,6 @@ impl Generator {
                 let stone = types["Stone"];
                 let dirt = types["Dirt"];
 
-                let is_empty = true;
-
                 let noise = Noise::new(LEVEL_SEED);
 
                 let is_solid_at = |vx: i32, vy: i32, vz: i32, biome: &BiomeConfig| {
@@ -173,15 +181,11 @@ impl Generator {
                         chunk.set_voxel(u.voxel.0, u.voxel.1, u.voxel.2, u.id);
                     })
                 });
-
-                chunk.is_empty = is_empty;
             }
             "biome_test" => {
                 let types =
                     registry.get_type_map(vec!["Water", "Grass Block", "Sand", "Stone", "Dirt"]);
 
-                let is_empty = true;
-
                 let noise = Noise::new(LEVEL_SEED);
 
                 let mut sum = 0.0;
@@ -243,14 +247,56 @@ impl Generator {
                     }
                 }
 
-                chunk.is_empty = is_empty;
-
                 // debug!("average {:?}", sum / count);
             }
+            "nether" => {
+                let types = registry.get_type_map(vec!["Bedrock", "Netherrack"]);
+
+                let noise = Noise::new(LEVEL_SEED);
+
+                let ceiling = config.max_height as i32 - 1;
+
+                for vx in start_x..end_x {
+                    for vz in start_z..end_z {
+                        for vy in start_y..end_y {
+                            if vy == 0 || vy == ceiling {
+                                chunk.set_voxel(vx, vy, vz, types["Bedrock"]);
+                                continue;
+                            }
+
+                            // caverns open up in the middle, closing off towards the floor and ceiling
+                            let y_prop = vy as f64 / ceiling as f64;
+                            let closing = ((y_prop - 0.5).abs() * 2.0).powi(4);
+
+                            let density = noise.fractal_octave_perlin3(
+                                vx as f64,
+                                vy as f64 * 1.5,
+                                vz as f64,
+                                CAVE_SCALE,
+                                3,
+                            );
+
+                            if density + closing > 0.1 {
+                                chunk.set_voxel(vx, vy, vz, types["Netherrack"]);
+                            }
+                        }
+                    }
+                }
+            }
+            "superflat" => {
+                Superflat::new(&config.flat_layers).generate(chunk, registry);
+            }
+            "amplified" => {
+                Amplified::new(LEVEL_SEED, config.max_height).generate(chunk, registry);
+            }
+            "islands" => {
+                Islands::new(LEVEL_SEED, config.max_height).generate(chunk, registry);
+            }
             _ => panic!("Generation type not found."),
         }
 
         chunk.needs_terrain = false;
+        chunk.calc_is_empty();
     }
 
     /// Generate chunk's height map
@@ -1,15 +1,20 @@
 use std::collections::VecDeque;
 
+use tracing::info_span;
+
 use super::super::{
     constants::{CHUNK_HORIZONTAL_NEIGHBORS, DATA_PADDING, VOXEL_NEIGHBORS},
-    engine::{chunks::Chunks, registry::Registry, space::Space, world::WorldConfig},
+    engine::{
+        chunk_store::{ChunkStore, VoxelAccess},
+        clock::sunlight_intensity,
+        registry::Registry,
+        space::Space,
+        world::WorldConfig,
+    },
 };
 
-use server_common::{
-    ndarray::{ndarray, Ndarray},
-    types::Block,
-    vec::Vec3,
-};
+use server_common::{array::Array3, types::Block, vec::Vec3};
+use server_utils::convert::map_voxel_to_chunk;
 
 /// Node of a light propagation queue
 #[derive(Debug)]
@@ -28,6 +33,23 @@ pub enum LightColor {
     Blue,
 }
 
+/// Light levels of a voxel, as gameplay sees them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VoxelLight {
+    pub sunlight: u32,
+    /// The brightest of the voxel's red, green and blue light
+    pub torch: u32,
+}
+
+impl VoxelLight {
+    /// How bright the voxel is at a time of day, its sunlight dimmed at night unless its torch
+    /// light is brighter
+    pub fn brightness(&self, time: f32) -> u32 {
+        let sunlight = (self.sunlight as f32 * sunlight_intensity(time)).round() as u32;
+        sunlight.max(self.torch)
+    }
+}
+
 pub struct Lights;
 
 impl Lights {
@@ -79,119 +101,129 @@ impl Lights {
         (light & 0xFFF0) | (level)
     }
 
+    /// Light level reaching a transparent block next to a voxel lit at `level`
+    ///
+    /// Light loses a level for every voxel it travels, except for full sunlight going
+    /// straight down, and loses the block's `light_reduce` on top of that.
+    #[inline]
+    pub fn reduce_light(
+        level: u32,
+        light_reduce: u32,
+        is_sunlight: bool,
+        is_down: bool,
+        max_light_level: u32,
+    ) -> u32 {
+        let sd = is_sunlight && is_down && level == max_light_level;
+        level.saturating_sub(if sd { 0 } else { 1 } + light_reduce)
+    }
+
     /// Getter for sunlight by arbitrary coordinates
-    fn get_sunlight(lights: &Ndarray<u32>, x: i32, y: i32, z: i32) -> u32 {
+    fn get_sunlight(lights: &Array3<u32>, x: i32, y: i32, z: i32) -> u32 {
         let x = x as usize;
         let y = y as usize;
         let z = z as usize;
 
-        if !lights.contains(&[x, y, z]) {
+        if !lights.contains(x, y, z) {
             return 0;
         }
 
-        Lights::extract_sunlight(lights[&[x, y, z]])
+        Lights::extract_sunlight(lights[[x, y, z]])
     }
 
     /// Setter for sunlight by arbitrary coordinates
-    fn set_sunlight(lights: &mut Ndarray<u32>, x: i32, y: i32, z: i32, level: u32) {
+    fn set_sunlight(lights: &mut Array3<u32>, x: i32, y: i32, z: i32, level: u32) {
         let x = x as usize;
         let y = y as usize;
         let z = z as usize;
 
-        if !lights.contains(&[x, y, z]) {
+        if !lights.contains(x, y, z) {
             return;
         }
 
-        lights[&[x, y, z]] = Lights::insert_sunlight(lights[&[x, y, z]], level);
+        lights[[x, y, z]] = Lights::insert_sunlight(lights[[x, y, z]], level);
     }
 
     /// Getter for red light by arbitrary coordinates
-    fn get_red_light(lights: &Ndarray<u32>, x: i32, y: i32, z: i32) -> u32 {
+    fn get_red_light(lights: &Array3<u32>, x: i32, y: i32, z: i32) -> u32 {
         let x = x as usize;
         let y = y as usize;
         let z = z as usize;
 
-        if !lights.contains(&[x, y, z]) {
+        if !lights.contains(x, y, z) {
             return 0;
         }
 
-        Lights::extract_red_light(lights[&[x, y, z]])
+        Lights::extract_red_light(lights[[x, y, z]])
     }
 
     /// Setter for red light by arbitrary coordinates
-    fn set_red_light(lights: &mut Ndarray<u32>, x: i32, y: i32, z: i32, level: u32) {
+    fn set_red_light(lights: &mut Array3<u32>, x: i32, y: i32, z: i32, level: u32) {
         let x = x as usize;
         let y = y as usize;
         let z = z as usize;
 
-        if !lights.contains(&[x, y, z]) {
+        if !lights.contains(x, y, z) {
             return;
         }
 
-        lights[&[x, y, z]] = Lights::insert_red_light(lights[&[x, y, z]], level);
+        lights[[x, y, z]] = Lights::insert_red_light(lights[[x, y, z]], level);
     }
 
     /// Getter for green light by arbitrary coordinates
-    fn get_green_light(lights: &Ndarray<u32>, x: i32, y: i32, z: i32) -> u32 {
+    fn get_green_light(lights: &Array3<u32>, x: i32, y: i32, z: i32) -> u32 {
         let x = x as usize;
         let y = y as usize;
         let z = z as usize;
 
-        if !lights.contains(&[x, y, z]) {
+        if !lights.contains(x, y, z) {
             return 0;
         }
 
-        Lights::extract_green_light(lights[&[x, y, z]])
+        Lights::extract_green_light(lights[[x, y, z]])
     }
 
     /// Setter for green light by arbitrary coordinates
-    fn set_green_light(lights: &mut Ndarray<u32>, x: i32, y: i32, z: i32, level: u32) {
+    fn set_green_light(lights: &mut Array3<u32>, x: i32, y: i32, z: i32, level: u32) {
         let x = x as usize;
         let y = y as usize;
         let z = z as usize;
 
-        if !lights.contains(&[x, y, z]) {
+        if !lights.contains(x, y, z) {
             return;
         }
 
-        lights[&[x, y, z]] = Lights::insert_green_light(lights[&[x, y, z]], level);
+        lights[[x, y, z]] = Lights::insert_green_light(lights[[x, y, z]], level);
     }
 
     /// Getter for blue light by arbitrary coordinates
-    fn get_blue_light(lights: &Ndarray<u32>, x: i32, y: i32, z: i32) -> u32 {
+    fn get_blue_light(lights: &Array3<u32>, x: i32, y: i32, z: i32) -> u32 {
         let x = x as usize;
         let y = y as usize;
         let z = z as usize;
 
-        if !lights.contains(&[x, y, z]) {
+        if !lights.contains(x, y, z) {
             return 0;
         }
 
-        Lights::extract_blue_light(lights[&[x, y, z]])
+        Lights::extract_blue_light(lights[[x, y, z]])
     }
 
     /// Setter for blue light by arbitrary coordinates
-    fn set_blue_light(lights: &mut Ndarray<u32>, x: i32, y: i32, z: i32, level: u32) {
+    fn set_blue_light(lights: &mut Array3<u32>, x: i32, y: i32, z: i32, level: u32) {
         let x = x as usize;
         let y = y as usize;
         let z = z as usize;
 
-        if !lights.contains(&[x, y, z]) {
+        if !lights.contains(x, y, z) {
             return;
         }
 
-        lights[&[x, y, z]] = Lights::insert_blue_light(lights[&[x, y, z]], level);
+        lights[[x, y, z]] = Lights::insert_blue_light(lights[[x, y, z]], level);
     }
 
     /// Getter for torch light with arbitrary coordinates by color
     #[inline]
-    fn get_torch_light(
-        lights: &Ndarray<u32>,
-        vx: i32,
-        vy: i32,
-        vz: i32,
-        color: &LightColor,
-    ) -> u32 {
+    fn get_torch_light(lights: &Array3<u32>, vx: i32, vy: i32, vz: i32, color: &LightColor) -> u32 {
         match color {
             LightColor::Red => Lights::get_red_light(lights, vx, vy, vz),
             LightColor::Green => Lights::get_green_light(lights, vx, vy, vz),
@@ -203,7 +235,7 @@ impl Lights {
     /// Setter for torch light with arbitrary coordinates by color
     #[inline]
     fn set_torch_light(
-        lights: &mut Ndarray<u32>,
+        lights: &mut Array3<u32>,
         vx: i32,
         vy: i32,
         vz: i32,
@@ -223,13 +255,15 @@ impl Lights {
     /// 1. Remove the existing lights in a flood-fill fashion
     /// 2. If external light source exists, flood fill them back
     pub fn global_remove_light(
-        chunks: &mut Chunks,
+        chunks: &mut ChunkStore,
         vx: i32,
         vy: i32,
         vz: i32,
         is_sunlight: bool,
         color: &LightColor,
     ) {
+        let _span = info_span!("global_remove_light", is_sunlight).entered();
+
         let max_height = chunks.config.max_height as i32;
         let max_light_level = chunks.config.max_light_level;
 
@@ -311,11 +345,13 @@ impl Lights {
 
     /// Flood fill light from a queue
     pub fn global_flood_light(
-        chunks: &mut Chunks,
+        chunks: &mut ChunkStore,
         mut queue: VecDeque<LightNode>,
         is_sunlight: bool,
         color: &LightColor,
     ) {
+        let _span = info_span!("global_flood_light", is_sunlight, nodes = queue.len()).entered();
+
         let max_height = chunks.config.max_height as i32;
         let max_light_level = chunks.config.max_light_level;
 
@@ -332,12 +368,29 @@ impl Lights {
 
                 let nvx = vx + ox;
                 let nvz = vz + oz;
-                let sd = is_sunlight && *oy == -1 && level == max_light_level;
-                let nl = level - if sd { 0 } else { 1 };
+                // chunks that aren't loaded or lit yet take the light in once they're lit
+                match chunks.get_chunk_by_voxel(nvx, nvy, nvz) {
+                    Some(chunk) if !chunk.needs_propagation => {}
+                    Some(_) => continue,
+                    None => {
+                        let coords = map_voxel_to_chunk(nvx, nvy, nvz, chunks.config.chunk_size);
+                        chunks.queue_light_edge(coords, Vec3(vx, vy, vz));
+                        continue;
+                    }
+                }
+
                 let n_voxel = Vec3(nvx, nvy, nvz);
                 let block_type = chunks.get_block_by_voxel(nvx, nvy, nvz);
+                let nl = Lights::reduce_light(
+                    level,
+                    block_type.light_reduce,
+                    is_sunlight,
+                    *oy == -1,
+                    max_light_level,
+                );
 
                 if !block_type.is_transparent
+                    || nl == 0
                     || (if is_sunlight {
                         chunks.get_sunlight(nvx, nvy, nvz)
                     } else {
@@ -369,16 +422,18 @@ impl Lights {
         is_sunlight: bool,
         color: &LightColor,
         space: &Space,
-        lights: &mut Ndarray<u32>,
+        lights: &mut Array3<u32>,
         registry: &Registry,
         config: &WorldConfig,
     ) {
+        let _span = info_span!("flood_light", is_sunlight, nodes = queue.len()).entered();
+
         let max_height = config.max_height as i32;
         let max_light_level = config.max_light_level;
 
         // i heard .get() is faster than []
-        let shape0 = *space.shape.get(0).unwrap() as i32;
-        let shape2 = *space.shape.get(2).unwrap() as i32;
+        let shape0 = space.shape[0] as i32;
+        let shape2 = space.shape[2] as i32;
 
         let Vec3(start_x, _, start_z) = space.min;
 
@@ -400,13 +455,19 @@ impl Lights {
                     continue;
                 }
 
-                let sd = is_sunlight && *oy == -1 && level == max_light_level;
-                let nl = level - if sd { 0 } else { 1 };
                 let n_voxel = Vec3(nvx, nvy, nvz);
                 let block_type =
                     registry.get_block_by_id(space.get_voxel(nvx + start_x, nvy, nvz + start_z));
+                let nl = Lights::reduce_light(
+                    level,
+                    block_type.light_reduce,
+                    is_sunlight,
+                    *oy == -1,
+                    max_light_level,
+                );
 
                 if !block_type.is_transparent
+                    || nl == 0
                     || (if is_sunlight {
                         Lights::get_sunlight(&lights, nvx, nvy, nvz)
                     } else {
@@ -431,7 +492,7 @@ impl Lights {
     }
 
     /// Propagate both sunlight and torch light within a confined space.
-    pub fn propagate(space: &Space, registry: &Registry, config: &WorldConfig) -> Ndarray<u32> {
+    pub fn propagate(space: &Space, registry: &Registry, config: &WorldConfig) -> Array3<u32> {
         let Space {
             width, min, shape, ..
         } = space;
@@ -440,10 +501,11 @@ impl Lights {
             chunk_size,
             max_height,
             max_light_level,
+            sky_light,
             ..
         } = config;
 
-        let mut lights = ndarray(shape.to_owned(), 0);
+        let mut lights = Array3::new(*shape, 0);
 
         let mut red_light_queue = VecDeque::<LightNode>::new();
         let mut green_light_queue = VecDeque::<LightNode>::new();
@@ -459,7 +521,9 @@ impl Lights {
 
         for z in 1..(width - 1) as i32 {
             for x in 1..(width - 1) as i32 {
-                let h = space.get_max_height(x + start_x, z + start_z) as i32;
+                // sunlight coming down this column, dimmed by blocks such as water and stopped
+                // by the first one it can't pass through
+                let mut sun = max_light_level;
 
                 for y in (0..max_height as i32).rev() {
                     let id = space.get_voxel(x + start_x, y, z + start_z);
@@ -469,10 +533,31 @@ impl Lights {
                         red_light_level,
                         green_light_level,
                         blue_light_level,
+                        light_reduce,
                         ..
                     } = registry.get_block_by_id(id);
 
-                    if y > h && is_transparent {
+                    if !is_transparent {
+                        sun = 0;
+                    }
+
+                    if sky_light && sun > 0 {
+                        sun = Lights::reduce_light(sun, light_reduce, true, true, max_light_level);
+
+                        if sun < max_light_level {
+                            // dimmed sunlight spreads out like any other light
+                            Lights::set_sunlight(&mut lights, x, y, z, sun);
+
+                            if sun > 0 {
+                                sunlight_queue.push_back(LightNode {
+                                    level: sun,
+                                    voxel: Vec3(x, y, z),
+                                });
+                            }
+                        }
+                    }
+
+                    if sky_light && sun == max_light_level {
                         Lights::set_sunlight(&mut lights, x, y, z, max_light_level);
 
                         for [ox, oz] in CHUNK_HORIZONTAL_NEIGHBORS.iter() {
@@ -567,8 +652,8 @@ impl Lights {
             config,
         );
 
-        let mut chunk_lights = ndarray(
-            vec![
+        let mut chunk_lights = Array3::new(
+            [
                 chunk_size + DATA_PADDING * 2,
                 max_height as usize,
                 chunk_size + DATA_PADDING * 2,
@@ -580,10 +665,10 @@ impl Lights {
         for x in (margin - DATA_PADDING)..(margin + chunk_size + DATA_PADDING) {
             for z in (margin - DATA_PADDING)..(margin + chunk_size + DATA_PADDING) {
                 for cy in 0..max_height as usize {
-                    let cx = x - margin + DATA_PADDING;
-                    let cz = z - margin + DATA_PADDING;
+                    let cx = x + DATA_PADDING - margin;
+                    let cz = z + DATA_PADDING - margin;
 
-                    chunk_lights[&[cx, cy, cz]] = lights[&[x, cy, z]];
+                    chunk_lights[[cx, cy, cz]] = lights[[x, cy, z]];
                 }
             }
         }
@@ -592,7 +677,101 @@ impl Lights {
     }
 
     /// Calculate the light levels within a confined space
-    pub fn calc_light(space: &Space, registry: &Registry, config: &WorldConfig) -> Ndarray<u32> {
+    pub fn calc_light(space: &Space, registry: &Registry, config: &WorldConfig) -> Array3<u32> {
         Lights::propagate(&space, registry, config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        engine::chunks::Chunks,
+        test_utils::{self, AIR, LEAVES, TORCH, WATER},
+    };
+
+    use super::*;
+
+    /// Chunks of stone with a tunnel along x at y = 4 and z = 3, for light that can only
+    /// go one way
+    fn tunnel() -> Chunks {
+        test_utils::chunks_with(1, |_, vy, vz| vy < 8 && !(vy == 4 && vz == 3))
+    }
+
+    /// Red light along the tunnel from x = -2 to 7
+    fn tunnel_lights(chunks: &Chunks) -> Vec<u32> {
+        (-2..=7)
+            .map(|vx| chunks.get_torch_light(vx, 4, 3, &LightColor::Red))
+            .collect()
+    }
+
+    #[test]
+    fn underwater_falloff_works() {
+        let max = 15;
+
+        // full sunlight keeps going down through air
+        assert_eq!(Lights::reduce_light(max, 0, true, true, max), max);
+        assert_eq!(Lights::reduce_light(max, 0, true, false, max), max - 1);
+
+        // and gets dimmer the deeper it goes into water
+        let mut level = max;
+        let depths = (0..6)
+            .map(|_| {
+                level = Lights::reduce_light(level, 2, true, true, max);
+                level
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(depths, vec![13, 10, 7, 4, 1, 0]);
+
+        // torch light through leaves
+        assert_eq!(Lights::reduce_light(10, 2, false, false, max), 7);
+        assert_eq!(Lights::reduce_light(2, 2, false, true, max), 0);
+    }
+
+    #[test]
+    fn light_through_water_and_leaves_works() {
+        let mut chunks = tunnel();
+
+        // two voxels of water, then two of leaves, east of the torch
+        chunks.update(1, 4, 3, WATER, 0, 0);
+        chunks.update(2, 4, 3, WATER, 0, 0);
+        chunks.update(3, 4, 3, LEAVES, 0, 0);
+        chunks.update(4, 4, 3, LEAVES, 0, 0);
+
+        chunks.update(0, 4, 3, TORCH, 0, 0);
+        assert_eq!(
+            tunnel_lights(&chunks),
+            vec![13, 14, 15, 12, 9, 6, 3, 2, 1, 0]
+        );
+
+        // taking the torch away leaves the tunnel dark
+        chunks.update(0, 4, 3, AIR, 0, 0);
+        assert_eq!(tunnel_lights(&chunks), vec![0; 10]);
+    }
+
+    #[test]
+    fn remove_light_restores_levels() {
+        let mut chunks = tunnel();
+
+        chunks.update(1, 4, 3, WATER, 0, 0);
+        chunks.update(3, 4, 3, LEAVES, 0, 0);
+        chunks.update(0, 4, 3, TORCH, 0, 0);
+        assert_eq!(
+            tunnel_lights(&chunks),
+            vec![13, 14, 15, 12, 11, 8, 7, 6, 5, 4]
+        );
+
+        // draining the water lets the light through as if it was never there
+        chunks.update(1, 4, 3, AIR, 0, 0);
+        assert_eq!(
+            tunnel_lights(&chunks),
+            vec![13, 14, 15, 14, 13, 10, 9, 8, 7, 6]
+        );
+
+        chunks.update(3, 4, 3, AIR, 0, 0);
+        assert_eq!(
+            tunnel_lights(&chunks),
+            vec![13, 14, 15, 14, 13, 12, 11, 10, 9, 8]
+        );
+    }
+}
@@ -13,7 +13,7 @@ use super::super::{
 };
 
 use server_common::{
-    types::{Block, MeshType, UV},
+    types::{Block, FaceEffect, MeshType, UV},
     vec::Vec3,
 };
 
@@ -30,6 +30,76 @@ fn get_block_by_voxel<'a>(
 }
 
 impl Mesher {
+    /// Pack an axis-aligned normal into a single integer, 2 bits per axis
+    pub fn pack_normal(&[nx, ny, nz]: &[i32; 3]) -> i32 {
+        ((nx + 1) << 4) | ((ny + 1) << 2) | (nz + 1)
+    }
+
+    /// Pack a face's effect into a single integer: whether it's emissive in the lowest bit,
+    /// its frame count in the next 8 and its frame time in milliseconds above. Plain faces are 0.
+    pub fn pack_effect(effect: Option<&FaceEffect>) -> i32 {
+        effect.map_or(0, |effect| {
+            let frames = effect.frames.min(0xff) as i32;
+            let frame_ms = ((effect.frame_time * 1000.0).round() as i32).clamp(0, 0x3f_ffff);
+
+            (frame_ms << 9) | (frames << 1) | effect.emissive as i32
+        })
+    }
+
+    /// Whether a block's face shows against the neighbor it faces. Opaque neighbors hide it,
+    /// and so do transparent ones of the same render group, like water next to water, unless
+    /// they're drawn standalone. Faces between different groups, like water and glass, show.
+    fn is_face_visible(id: u32, neighbor_id: u32, dir: &[i32; 3], registry: &Registry) -> bool {
+        let block = registry.get_block_by_id(id);
+        let neighbor = registry.get_block_by_id(neighbor_id);
+
+        if !neighbor.is_transparent && !neighbor.is_fluid {
+            return false;
+        }
+
+        !block.is_transparent
+            || neighbor.is_empty
+            || !registry.is_same_render_group(id, neighbor_id)
+            || (neighbor.transparent_standalone && dir[0] + dir[1] + dir[2] >= 1)
+    }
+
+    /// Smooth light of a vertex, averaged from the transparent voxels among the 4 touching it
+    /// in front of its face. The first sample is the face neighbor, which always counts.
+    ///
+    /// Returns the sunlight, red, green and blue light levels.
+    fn vertex_light(
+        vx: i32,
+        vy: i32,
+        vz: i32,
+        samples: &[[i32; 3]; 4],
+        chunk: &Chunk,
+        registry: &Registry,
+    ) -> [i32; 4] {
+        let mut sum = [0; 4];
+        let mut count = 0;
+
+        for (i, [ox, oy, oz]) in samples.iter().enumerate() {
+            let (nvx, nvy, nvz) = (vx + ox, vy + oy, vz + oz);
+
+            if i != 0 && !get_block_by_voxel(nvx, nvy, nvz, chunk, registry).is_transparent {
+                continue;
+            }
+
+            sum[0] += chunk.get_sunlight(nvx, nvy, nvz);
+            sum[1] += chunk.get_red_light(nvx, nvy, nvz);
+            sum[2] += chunk.get_green_light(nvx, nvy, nvz);
+            sum[3] += chunk.get_blue_light(nvx, nvy, nvz);
+            count += 1;
+        }
+
+        [
+            (sum[0] / count) as i32,
+            (sum[1] / count) as i32,
+            (sum[2] / count) as i32,
+            (sum[3] / count) as i32,
+        ]
+    }
+
     /// Meshing a chunk. Poorly written. Needs refactor.
     pub fn mesh_chunk(
         chunk: &Chunk,
@@ -51,6 +121,10 @@ impl Mesher {
             ..
         } = config;
 
+        if chunk.is_sub_chunk_hidden(sub_chunk) {
+            return None;
+        }
+
         let mut positions = Vec::<f32>::new();
         let mut indices = Vec::<i32>::new();
         let mut uvs = Vec::<f32>::new();
@@ -59,6 +133,9 @@ impl Mesher {
         let mut green_lights = Vec::<i32>::new();
         let mut blue_lights = Vec::<i32>::new();
         let mut sunlights = Vec::<i32>::new();
+        let mut normals = Vec::<i32>::new();
+        let mut block_ids = Vec::<i32>::new();
+        let mut effects = Vec::<i32>::new();
 
         let &Vec3(start_x, _, start_z) = min_inner;
         let &Vec3(end_x, _, end_z) = max_inner;
@@ -92,9 +169,9 @@ impl Mesher {
                         is_transparent,
                         is_block,
                         is_plant,
-                        is_fluid,
                         ..
                     } = registry.get_block_by_id(voxel_id);
+                    let face_effects = &registry.get_block_by_id(voxel_id).effects;
 
                     // TODO: simplify this logic
                     if (is_solid || is_plant)
@@ -118,6 +195,7 @@ impl Mesher {
                                     start_v,
                                     end_v,
                                 } = uv_map.get(texture.get(*mat).unwrap()).unwrap();
+                                let effect = Mesher::pack_effect(face_effects.get(*mat));
                                 let ndx = (positions.len() / 3) as i32;
 
                                 for &CornerSimplified { pos, uv } in corners.iter() {
@@ -150,6 +228,11 @@ impl Mesher {
                                     blue_lights.push(chunk.get_blue_light(vx, vy, vz) as i32);
 
                                     aos.push(3);
+
+                                    // plants are shaded as if they were facing up
+                                    normals.push(Mesher::pack_normal(&[0, 1, 0]));
+                                    block_ids.push(voxel_id as i32);
+                                    effects.push(effect);
                                 }
 
                                 indices.push(ndx);
@@ -188,38 +271,29 @@ impl Mesher {
                                 let nvz = vz + dir[2];
 
                                 let neighbor_id = chunk.get_voxel(nvx, nvy, nvz);
-                                let n_block_type = registry.get_block_by_id(neighbor_id);
-
-                                if ((n_block_type.is_transparent && !n_block_type.is_fluid)
-                                    || (n_block_type.is_fluid && !is_fluid))
-                                    && (!transparent
-                                        || n_block_type.is_empty
-                                        || neighbor_id != voxel_id
-                                        || (n_block_type.transparent_standalone
-                                            && dir[0] + dir[1] + dir[2] >= 1))
-                                {
+
+                                if Mesher::is_face_visible(voxel_id, neighbor_id, &dir, registry) {
+                                    let side = if is_mat_1 {
+                                        "all"
+                                    } else if is_mat_3 {
+                                        *mat3
+                                    } else {
+                                        *mat6
+                                    };
+
                                     let UV {
                                         start_u,
                                         end_u,
                                         start_v,
                                         end_v,
-                                    } = if is_mat_1 {
-                                        uv_map.get(texture.get("all").unwrap()).unwrap()
-                                    } else if is_mat_3 {
-                                        uv_map.get(texture.get(*mat3).unwrap()).unwrap()
-                                    } else {
-                                        uv_map.get(texture.get(*mat6).unwrap()).unwrap()
-                                    };
+                                    } = uv_map.get(texture.get(side).unwrap()).unwrap();
 
                                     let ndx = (positions.len() / 3) as i32;
-                                    let mut face_aos = vec![];
 
-                                    let mut four_sunlights = vec![];
-                                    let mut four_red_lights = vec![];
-                                    let mut four_green_lights = vec![];
-                                    let mut four_blue_lights = vec![];
+                                    let mut face_aos = [0; 4];
+                                    let mut face_lights = [[0; 4]; 4];
 
-                                    for CornerData { pos, uv } in corners.iter() {
+                                    for (i, CornerData { pos, uv }) in corners.iter().enumerate() {
                                         let mut position =
                                             [pos[0] as f32, pos[1] as f32, pos[2] as f32];
 
@@ -238,270 +312,62 @@ impl Mesher {
                                         uvs.push(uv[0] as f32 * (end_u - start_u) + start_u);
                                         uvs.push(uv[1] as f32 * (start_v - end_v) + end_v);
 
-                                        // calculating the 8 voxels around this vertex
-                                        let dx = position[0].round() as i32;
-                                        let dy = position[1].round() as i32;
-                                        let dz = position[2].round() as i32;
-
-                                        let dx = if dx == 0 { -1 } else { 1 };
-                                        let dy = if dy == 0 { -1 } else { 1 };
-                                        let dz = if dz == 0 { -1 } else { 1 };
-
-                                        let mut sum_sunlight = vec![];
-                                        let mut sum_red_lights = vec![];
-                                        let mut sum_green_lights = vec![];
-                                        let mut sum_blue_lights = vec![];
-
-                                        let b000 = get_block_by_voxel(vx, vy, vz, chunk, registry)
-                                            .is_transparent;
-                                        let b001 =
-                                            get_block_by_voxel(vx, vy, vz + dz, chunk, registry)
-                                                .is_transparent;
-                                        let b010 =
-                                            get_block_by_voxel(vx, vy + dy, vz, chunk, registry)
-                                                .is_transparent;
-                                        let b011 = get_block_by_voxel(
-                                            vx,
-                                            vy + dy,
-                                            vz + dz,
-                                            chunk,
-                                            registry,
-                                        )
-                                        .is_transparent;
-                                        let b100 =
-                                            get_block_by_voxel(vx + dx, vy, vz, chunk, registry)
-                                                .is_transparent;
-                                        let b101 = get_block_by_voxel(
-                                            vx + dx,
-                                            vy,
-                                            vz + dz,
-                                            chunk,
-                                            registry,
-                                        )
-                                        .is_transparent;
-                                        let b110 = get_block_by_voxel(
-                                            vx + dx,
-                                            vy + dy,
-                                            vz,
-                                            chunk,
-                                            registry,
-                                        )
-                                        .is_transparent;
-                                        let b111 = get_block_by_voxel(
-                                            vx + dx,
-                                            vy + dy,
-                                            vz + dz,
-                                            chunk,
-                                            registry,
-                                        )
-                                        .is_transparent;
-
-                                        if dir[0].abs() == 1 {
-                                            face_aos.push(vertex_ao(b110, b101, b111));
-                                        } else if dir[1].abs() == 1 {
-                                            face_aos.push(vertex_ao(b110, b011, b111));
+                                        // which side of the voxel this vertex is on
+                                        let dx = if position[0].round() as i32 == 0 {
+                                            -1
                                         } else {
-                                            face_aos.push(vertex_ao(b011, b101, b111));
-                                        }
-
-                                        // TODO: light be leaking
-
-                                        if b000 {
-                                            sum_sunlight.push(chunk.get_sunlight(vx, vy, vz));
-                                            sum_red_lights.push(chunk.get_red_light(vx, vy, vz));
-                                            sum_green_lights
-                                                .push(chunk.get_green_light(vx, vy, vz));
-                                            sum_blue_lights.push(chunk.get_blue_light(vx, vy, vz));
-                                        }
-
-                                        if b001 {
-                                            sum_sunlight.push(chunk.get_sunlight(vx, vy, vz + dz));
-                                            sum_red_lights.push(chunk.get_red_light(
-                                                vx,
-                                                vy,
-                                                vz + dz,
-                                            ));
-                                            sum_green_lights.push(chunk.get_green_light(
-                                                vx,
-                                                vy,
-                                                vz + dz,
-                                            ));
-                                            sum_blue_lights.push(chunk.get_blue_light(
-                                                vx,
-                                                vy,
-                                                vz + dz,
-                                            ));
-                                        }
-
-                                        if b010 {
-                                            sum_sunlight.push(chunk.get_sunlight(vx, vy + dy, vz));
-                                            sum_red_lights.push(chunk.get_red_light(
-                                                vx,
-                                                vy + dy,
-                                                vz,
-                                            ));
-                                            sum_green_lights.push(chunk.get_green_light(
-                                                vx,
-                                                vy + dy,
-                                                vz,
-                                            ));
-                                            sum_blue_lights.push(chunk.get_blue_light(
-                                                vx,
-                                                vy + dy,
-                                                vz,
-                                            ));
-                                        }
-
-                                        if b011 {
-                                            sum_sunlight.push(chunk.get_sunlight(
-                                                vx,
-                                                vy + dy,
-                                                vz + dz,
-                                            ));
-                                            sum_red_lights.push(chunk.get_red_light(
-                                                vx,
-                                                vy + dy,
-                                                vz + dz,
-                                            ));
-                                            sum_green_lights.push(chunk.get_green_light(
-                                                vx,
-                                                vy + dy,
-                                                vz + dz,
-                                            ));
-                                            sum_blue_lights.push(chunk.get_blue_light(
-                                                vx,
-                                                vy + dy,
-                                                vz + dz,
-                                            ));
-                                        }
-
-                                        if b100 {
-                                            sum_sunlight.push(chunk.get_sunlight(vx + dx, vy, vz));
-                                            sum_red_lights.push(chunk.get_red_light(
-                                                vx + dx,
-                                                vy,
-                                                vz,
-                                            ));
-                                            sum_green_lights.push(chunk.get_green_light(
-                                                vx + dx,
-                                                vy,
-                                                vz,
-                                            ));
-                                            sum_blue_lights.push(chunk.get_blue_light(
-                                                vx + dx,
-                                                vy,
-                                                vz,
-                                            ));
-                                        }
-
-                                        if b101 {
-                                            sum_sunlight.push(chunk.get_sunlight(
-                                                vx + dx,
-                                                vy,
-                                                vz + dz,
-                                            ));
-                                            sum_red_lights.push(chunk.get_red_light(
-                                                vx + dx,
-                                                vy,
-                                                vz + dz,
-                                            ));
-                                            sum_green_lights.push(chunk.get_green_light(
-                                                vx + dx,
-                                                vy,
-                                                vz + dz,
-                                            ));
-                                            sum_blue_lights.push(chunk.get_blue_light(
-                                                vx + dx,
-                                                vy,
-                                                vz + dz,
-                                            ));
-                                        }
-
-                                        if b110 {
-                                            sum_sunlight.push(chunk.get_sunlight(
-                                                vx + dx,
-                                                vy + dy,
-                                                vz,
-                                            ));
-                                            sum_red_lights.push(chunk.get_red_light(
-                                                vx + dx,
-                                                vy + dy,
-                                                vz,
-                                            ));
-                                            sum_green_lights.push(chunk.get_green_light(
-                                                vx + dx,
-                                                vy + dy,
-                                                vz,
-                                            ));
-                                            sum_blue_lights.push(chunk.get_blue_light(
-                                                vx + dx,
-                                                vy + dy,
-                                                vz,
-                                            ));
-                                        }
-
-                                        if b111 {
-                                            sum_sunlight.push(chunk.get_sunlight(
-                                                vx + dx,
-                                                vy + dy,
-                                                vz + dz,
-                                            ));
-                                            sum_red_lights.push(chunk.get_red_light(
-                                                vx + dx,
-                                                vy + dy,
-                                                vz + dz,
-                                            ));
-                                            sum_green_lights.push(chunk.get_green_light(
-                                                vx + dx,
-                                                vy + dy,
-                                                vz + dz,
-                                            ));
-                                            sum_blue_lights.push(chunk.get_blue_light(
-                                                vx + dx,
-                                                vy + dy,
-                                                vz + dz,
-                                            ));
-                                        }
-
-                                        four_sunlights.push(
-                                            (sum_sunlight.iter().sum::<u32>() as f32
-                                                / sum_sunlight.len() as f32)
-                                                as i32,
-                                        );
-
-                                        four_red_lights.push(
-                                            (sum_red_lights.iter().sum::<u32>() as f32
-                                                / sum_red_lights.len() as f32)
-                                                as i32,
-                                        );
+                                            1
+                                        };
+                                        let dy = if position[1].round() as i32 == 0 {
+                                            -1
+                                        } else {
+                                            1
+                                        };
+                                        let dz = if position[2].round() as i32 == 0 {
+                                            -1
+                                        } else {
+                                            1
+                                        };
 
-                                        four_green_lights.push(
-                                            (sum_green_lights.iter().sum::<u32>() as f32
-                                                / sum_green_lights.len() as f32)
-                                                as i32,
+                                        // the 4 voxels touching this vertex in front of the face:
+                                        // the face neighbor, its two sides and the corner
+                                        let [side1, side2] = if dir[0].abs() == 1 {
+                                            [[dx, dy, 0], [dx, 0, dz]]
+                                        } else if dir[1].abs() == 1 {
+                                            [[dx, dy, 0], [0, dy, dz]]
+                                        } else {
+                                            [[0, dy, dz], [dx, 0, dz]]
+                                        };
+                                        let samples = [dir, side1, side2, [dx, dy, dz]];
+
+                                        let is_transparent = |[ox, oy, oz]: [i32; 3]| {
+                                            get_block_by_voxel(
+                                                vx + ox,
+                                                vy + oy,
+                                                vz + oz,
+                                                chunk,
+                                                registry,
+                                            )
+                                            .is_transparent
+                                        };
+
+                                        face_aos[i] = vertex_ao(
+                                            is_transparent(side1),
+                                            is_transparent(side2),
+                                            is_transparent(samples[3]),
                                         );
-
-                                        four_blue_lights.push(
-                                            (sum_blue_lights.iter().sum::<u32>() as f32
-                                                / sum_blue_lights.len() as f32)
-                                                as i32,
+                                        face_lights[i] = Mesher::vertex_light(
+                                            vx, vy, vz, &samples, chunk, registry,
                                         );
                                     }
 
-                                    let a_rt = four_red_lights[0];
-                                    let b_rt = four_red_lights[1];
-                                    let c_rt = four_red_lights[2];
-                                    let d_rt = four_red_lights[3];
-
-                                    let a_gt = four_green_lights[0];
-                                    let b_gt = four_green_lights[1];
-                                    let c_gt = four_green_lights[2];
-                                    let d_gt = four_green_lights[3];
-
-                                    let a_bt = four_blue_lights[0];
-                                    let b_bt = four_blue_lights[1];
-                                    let c_bt = four_blue_lights[2];
-                                    let d_bt = four_blue_lights[3];
+                                    let [corner_a, corner_b, corner_c, corner_d] = face_lights;
+                                    let [a_rt, b_rt, c_rt, d_rt] =
+                                        [corner_a[1], corner_b[1], corner_c[1], corner_d[1]];
+                                    let [a_gt, b_gt, c_gt, d_gt] =
+                                        [corner_a[2], corner_b[2], corner_c[2], corner_d[2]];
+                                    let [a_bt, b_bt, c_bt, d_bt] =
+                                        [corner_a[3], corner_b[3], corner_c[3], corner_d[3]];
 
                                     let threshold = 0;
 
@@ -565,11 +431,19 @@ impl Mesher {
                                         indices.push(ndx + 3);
                                     }
 
-                                    aos.append(&mut face_aos);
-                                    sunlights.append(&mut four_sunlights);
-                                    red_lights.append(&mut four_red_lights);
-                                    green_lights.append(&mut four_green_lights);
-                                    blue_lights.append(&mut four_blue_lights);
+                                    aos.extend_from_slice(&face_aos);
+                                    normals.extend_from_slice(&[Mesher::pack_normal(&dir); 4]);
+                                    block_ids.extend_from_slice(&[voxel_id as i32; 4]);
+                                    effects.extend_from_slice(
+                                        &[Mesher::pack_effect(face_effects.get(side)); 4],
+                                    );
+
+                                    for [sunlight, red, green, blue] in face_lights.iter() {
+                                        sunlights.push(*sunlight);
+                                        red_lights.push(*red);
+                                        green_lights.push(*green);
+                                        blue_lights.push(*blue);
+                                    }
                                 }
                             }
                         }
@@ -595,6 +469,39 @@ impl Mesher {
             uvs,
             aos,
             lights,
+            normals,
+            block_ids,
+            effects,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_normal_works() {
+        assert_eq!(Mesher::pack_normal(&[0, 1, 0]), 0b01_10_01);
+        assert_eq!(Mesher::pack_normal(&[-1, 0, 0]), 0b00_01_01);
+        assert_eq!(Mesher::pack_normal(&[0, 0, 1]), 0b01_01_10);
+    }
+
+    #[test]
+    fn pack_effect_works() {
+        assert_eq!(Mesher::pack_effect(None), 0);
+
+        let glowing = FaceEffect {
+            emissive: true,
+            ..Default::default()
+        };
+        assert_eq!(Mesher::pack_effect(Some(&glowing)), 1);
+
+        let flowing = FaceEffect {
+            emissive: false,
+            frames: 4,
+            frame_time: 0.25,
+        };
+        assert_eq!(Mesher::pack_effect(Some(&flowing)), (250 << 9) | (4 << 1));
+    }
+}
@@ -0,0 +1,259 @@
+use hashbrown::HashMap;
+
+use serde::Deserialize;
+
+use server_common::{noise::Noise, vec::Vec3};
+
+use super::super::engine::{chunk::Chunk, registry::Registry};
+
+/// A single layer of a superflat world, stacked from the bottom of the world up
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlatLayer {
+    pub block: String,
+    pub height: i32,
+}
+
+/// A terrain generator deciding on a block for every voxel of the world.
+///
+/// Generators only depend on their seed and configurations, so the same voxel always
+/// ends up with the same block no matter which chunk or thread generates it.
+pub trait TerrainGenerator {
+    /// Name of the block at a voxel, `None` for air
+    fn block_at(&self, vx: i32, vy: i32, vz: i32) -> Option<&str>;
+
+    /// Fill in the terrain of a chunk, keeping any voxel that's already been set
+    fn generate(&self, chunk: &mut Chunk, registry: &Registry) {
+        let Vec3(start_x, start_y, start_z) = chunk.min;
+        let Vec3(end_x, end_y, end_z) = chunk.max;
+
+        let mut ids = HashMap::new();
+
+        for vx in start_x..end_x {
+            for vz in start_z..end_z {
+                for vy in start_y..end_y {
+                    // chunks might come in with preset voxels
+                    if chunk.get_voxel(vx, vy, vz) != 0 {
+                        continue;
+                    }
+
+                    if let Some(name) = self.block_at(vx, vy, vz) {
+                        let id = *ids
+                            .entry(name)
+                            .or_insert_with(|| *registry.get_id_by_name(name));
+                        chunk.set_voxel(vx, vy, vz, id);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Flat layers of blocks, configured per world through `flatLayers`
+pub struct Superflat {
+    layers: Vec<FlatLayer>,
+}
+
+impl Superflat {
+    pub fn new(layers: &[FlatLayer]) -> Self {
+        let layers = if layers.is_empty() {
+            vec![
+                FlatLayer {
+                    block: "Bedrock".to_owned(),
+                    height: 1,
+                },
+                FlatLayer {
+                    block: "Dirt".to_owned(),
+                    height: 2,
+                },
+                FlatLayer {
+                    block: "Grass Block".to_owned(),
+                    height: 1,
+                },
+            ]
+        } else {
+            layers.to_vec()
+        };
+
+        Self { layers }
+    }
+}
+
+impl TerrainGenerator for Superflat {
+    fn block_at(&self, _: i32, vy: i32, _: i32) -> Option<&str> {
+        let mut top = 0;
+
+        for layer in self.layers.iter() {
+            top += layer.height;
+
+            if vy < top {
+                return Some(layer.block.as_str());
+            }
+        }
+
+        None
+    }
+}
+
+/// Tall mountains with sharp ridges, going up to most of the world height
+pub struct Amplified {
+    noise: Noise,
+    max_height: i32,
+}
+
+impl Amplified {
+    const SCALE: f64 = 0.004;
+
+    pub fn new(seed: u32, max_height: u32) -> Self {
+        Self {
+            noise: Noise::new(seed),
+            max_height: max_height as i32,
+        }
+    }
+
+    /// Height of the terrain surface at a column
+    pub fn height_at(&self, vx: i32, vz: i32) -> i32 {
+        let (x, z) = (vx as f64, vz as f64);
+
+        let hills = (self.noise.fractal_octave_perlin2(x, z, Amplified::SCALE, 4) + 1.0) / 2.0;
+        let ridges = (self.noise.ridged2(x, z, Amplified::SCALE * 2.0) + 1.0) / 2.0;
+
+        let base = self.max_height as f64 * 0.2;
+        let amplitude = self.max_height as f64 * 0.75;
+        let height = base + (hills * hills * 0.7 + ridges.max(0.0) * 0.3) * amplitude;
+
+        (height as i32).max(1).min(self.max_height - 1)
+    }
+}
+
+impl TerrainGenerator for Amplified {
+    fn block_at(&self, vx: i32, vy: i32, vz: i32) -> Option<&str> {
+        let height = self.height_at(vx, vz);
+
+        if vy > height {
+            return None;
+        }
+
+        // peaks are left bare
+        if height > self.max_height * 3 / 4 || vy < height - 3 {
+            return Some("Stone");
+        }
+
+        Some(if vy == height { "Grass Block" } else { "Dirt" })
+    }
+}
+
+/// Floating islands scattered in the middle of an otherwise empty world
+pub struct Islands {
+    noise: Noise,
+    max_height: i32,
+}
+
+impl Islands {
+    const SCALE: f64 = 0.02;
+
+    pub fn new(seed: u32, max_height: u32) -> Self {
+        Self {
+            noise: Noise::new(seed),
+            max_height: max_height as i32,
+        }
+    }
+
+    /// Whether a voxel is inside of an island
+    pub fn is_solid_at(&self, vx: i32, vy: i32, vz: i32) -> bool {
+        let center = self.max_height as f64 / 2.0;
+        let band = self.max_height as f64 / 4.0;
+
+        // islands thin out away from the middle of the world
+        let falloff = ((vy as f64 - center) / band).powi(2);
+
+        if falloff >= 1.0 {
+            return false;
+        }
+
+        let density = self.noise.fractal_octave_perlin3(
+            vx as f64,
+            vy as f64 * 2.0,
+            vz as f64,
+            Islands::SCALE,
+            3,
+        );
+
+        density - falloff * 0.5 > 0.15
+    }
+}
+
+impl TerrainGenerator for Islands {
+    fn block_at(&self, vx: i32, vy: i32, vz: i32) -> Option<&str> {
+        if !self.is_solid_at(vx, vy, vz) {
+            return None;
+        }
+
+        if !self.is_solid_at(vx, vy + 1, vz) {
+            return Some("Grass Block");
+        }
+
+        if !self.is_solid_at(vx, vy + 3, vz) {
+            return Some("Dirt");
+        }
+
+        Some("Stone")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: u32 = 1234;
+
+    fn sample(generator: &dyn TerrainGenerator) -> Vec<Option<String>> {
+        let mut blocks = vec![];
+
+        for vx in (-64..64).step_by(7) {
+            for vz in (-64..64).step_by(7) {
+                for vy in (0..128).step_by(5) {
+                    blocks.push(generator.block_at(vx, vy, vz).map(|b| b.to_owned()));
+                }
+            }
+        }
+
+        blocks
+    }
+
+    #[test]
+    fn superflat_works() {
+        let flat = Superflat::new(&[]);
+        assert_eq!(flat.block_at(0, 0, 0), Some("Bedrock"));
+        assert_eq!(flat.block_at(5, 2, -5), Some("Dirt"));
+        assert_eq!(flat.block_at(0, 3, 0), Some("Grass Block"));
+        assert_eq!(flat.block_at(0, 4, 0), None);
+
+        let flat = Superflat::new(&[FlatLayer {
+            block: "Stone".to_owned(),
+            height: 10,
+        }]);
+        assert_eq!(flat.block_at(0, 9, 0), Some("Stone"));
+        assert_eq!(flat.block_at(0, 10, 0), None);
+    }
+
+    #[test]
+    fn amplified_is_deterministic() {
+        let a = Amplified::new(SEED, 128);
+        let b = Amplified::new(SEED, 128);
+
+        assert_eq!(sample(&a), sample(&b));
+        assert_eq!(a.block_at(0, 0, 0), Some("Stone"));
+        assert!((1..128).contains(&a.height_at(100, -100)));
+    }
+
+    #[test]
+    fn islands_is_deterministic() {
+        let a = Islands::new(SEED, 128);
+        let b = Islands::new(SEED, 128);
+
+        assert_eq!(sample(&a), sample(&b));
+        assert_eq!(a.block_at(0, 0, 0), None);
+        assert_eq!(a.block_at(0, 127, 0), None);
+    }
+}
@@ -0,0 +1,253 @@
+use std::{collections::HashMap, fs::File};
+
+use serde::Deserialize;
+
+use server_common::vec::{Vec2, Vec3};
+use server_utils::convert::map_voxel_to_chunk;
+
+use super::super::engine::{registry::Registry, transform::Transform};
+
+use super::builder::VoxelUpdate;
+
+/// JSON format of a part of a structure, placed relative to the structure's origin
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructurePiece {
+    pub offset: [i32; 3],
+    /// Horizontal slices from the bottom up, each row going along z and each
+    /// character along x. Spaces leave the voxel untouched.
+    pub layers: Vec<Vec<String>>,
+}
+
+/// JSON format of a structure template in `structures.json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructureTemplate {
+    /// Size of the regions, in chunks, each holding at most one of this structure
+    pub spacing: i32,
+    /// Chance of a region holding this structure
+    pub chance: f64,
+    /// World generations this structure appears in, all of them if empty
+    #[serde(default)]
+    pub generations: Vec<String>,
+    pub palette: HashMap<char, String>,
+    pub pieces: Vec<StructurePiece>,
+}
+
+/// Places structures across the world.
+///
+/// Where a structure starts is only decided by the world seed, so every structure gets built
+/// in full once the chunk of its origin is decorated. Pieces that fall into chunks that
+/// aren't loaded yet are kept pending until those chunks come in, so structures end up
+/// complete no matter the order chunks are loaded in.
+#[derive(Debug)]
+pub struct Structures {
+    seed: u32,
+    templates: Vec<(String, StructureTemplate)>,
+    pending: HashMap<Vec2<i32>, Vec<VoxelUpdate>>,
+}
+
+impl Structures {
+    /// Load the structure templates appearing in a world generation
+    pub fn new(generation: &str, seed: u32) -> Self {
+        let templates: HashMap<String, StructureTemplate> =
+            serde_json::from_reader(File::open("assets/metadata/structures.json").unwrap())
+                .unwrap();
+
+        Structures::from_templates(templates, generation, seed)
+    }
+
+    pub fn from_templates(
+        templates: HashMap<String, StructureTemplate>,
+        generation: &str,
+        seed: u32,
+    ) -> Self {
+        let mut templates = templates
+            .into_iter()
+            .filter(|(_, t)| {
+                t.generations.is_empty() || t.generations.iter().any(|g| g == generation)
+            })
+            .collect::<Vec<_>>();
+
+        // sort the names so that every template rolls the same way across restarts
+        templates.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Self {
+            seed,
+            templates,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Find a structure appearing in this world by name
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.templates.iter().position(|(n, _)| n == name)
+    }
+
+    /// Names of the structures appearing in this world, sorted
+    pub fn names(&self) -> Vec<&str> {
+        self.templates.iter().map(|(n, _)| n.as_str()).collect()
+    }
+
+    /// Size of the regions of a structure, in chunks
+    pub fn spacing(&self, index: usize) -> i32 {
+        self.templates[index].1.spacing.max(1)
+    }
+
+    /// Get the voxel column a structure starts at within a region, if the region has one
+    pub fn origin(
+        &self,
+        index: usize,
+        Vec2(rx, rz): &Vec2<i32>,
+        chunk_size: usize,
+    ) -> Option<Vec2<i32>> {
+        let (_, template) = self.templates.get(index)?;
+
+        let roll = |salt: u32| hash(self.seed, *rx, *rz, index as u32 * 4 + salt);
+
+        if (roll(0) % 10_000) as f64 >= template.chance * 10_000.0 {
+            return None;
+        }
+
+        let region_size = template.spacing.max(1) as u64 * chunk_size as u64;
+
+        Some(Vec2(
+            rx * region_size as i32 + (roll(1) % region_size) as i32,
+            rz * region_size as i32 + (roll(2) % region_size) as i32,
+        ))
+    }
+
+    /// Get the structures starting within a chunk, along with the columns they start at
+    pub fn origins_in_chunk(
+        &self,
+        coords: &Vec2<i32>,
+        chunk_size: usize,
+    ) -> Vec<(usize, Vec2<i32>)> {
+        self.templates
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (_, template))| {
+                let spacing = template.spacing.max(1);
+                let region = Vec2(coords.0.div_euclid(spacing), coords.1.div_euclid(spacing));
+                let origin = self.origin(index, &region, chunk_size)?;
+
+                if map_voxel_to_chunk(origin.0, 0, origin.1, chunk_size) == *coords {
+                    Some((index, origin))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Which way a structure starting at a voxel column faces
+    pub fn transform(&self, index: usize, Vec2(vx, vz): &Vec2<i32>) -> Transform {
+        let roll = hash(self.seed, *vx, *vz, index as u32 * 4 + 3);
+        Transform::new((roll % 4) as u32, (roll >> 2) % 2 == 1)
+    }
+
+    /// Build every piece of a structure starting at a voxel, turned the way it faces
+    pub fn place(&self, index: usize, origin: &Vec3<i32>, registry: &Registry) -> Vec<VoxelUpdate> {
+        let (_, template) = &self.templates[index];
+        let transform = self.transform(index, &Vec2(origin.0, origin.2));
+
+        let ids = template
+            .palette
+            .iter()
+            .map(|(c, name)| (*c, *registry.get_id_by_name(name)))
+            .collect::<HashMap<_, _>>();
+
+        let mut updates = vec![];
+
+        for piece in template.pieces.iter() {
+            let [ox, oy, oz] = piece.offset;
+
+            for (y, layer) in piece.layers.iter().enumerate() {
+                for (z, row) in layer.iter().enumerate() {
+                    for (x, c) in row.chars().enumerate() {
+                        if let Some(&id) = ids.get(&c) {
+                            let offset =
+                                transform.voxel(&Vec3(ox + x as i32, oy + y as i32, oz + z as i32));
+
+                            updates.push(VoxelUpdate {
+                                voxel: origin.add(&offset),
+                                id,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        updates
+    }
+
+    /// Keep a piece around until its chunk is loaded
+    pub fn defer(&mut self, coords: Vec2<i32>, update: VoxelUpdate) {
+        self.pending
+            .entry(coords)
+            .or_insert_with(Vec::new)
+            .push(update);
+    }
+
+    /// Chunks that have pieces waiting on them
+    pub fn pending_coords(&self) -> Vec<Vec2<i32>> {
+        self.pending.keys().cloned().collect()
+    }
+
+    /// Take the pieces waiting on a chunk
+    pub fn take_pending(&mut self, coords: &Vec2<i32>) -> Vec<VoxelUpdate> {
+        self.pending.remove(coords).unwrap_or_default()
+    }
+}
+
+/// Hash a region and a salt with the world seed, the same way on every platform
+fn hash(seed: u32, x: i32, z: i32, salt: u32) -> u64 {
+    let mut h = (seed as u64) ^ 0x9e37_79b9_7f4a_7c15;
+
+    for v in [x as u32, z as u32, salt].iter() {
+        h ^= *v as u64;
+        h = h.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        h ^= h >> 31;
+    }
+
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> StructureTemplate {
+        StructureTemplate {
+            spacing: 2,
+            chance: 1.0,
+            generations: vec![],
+            palette: HashMap::new(),
+            pieces: vec![],
+        }
+    }
+
+    #[test]
+    fn placement_works() {
+        let mut templates = HashMap::new();
+        templates.insert("ruin".to_owned(), template());
+
+        let a = Structures::from_templates(templates.clone(), "hilly", 42);
+        let b = Structures::from_templates(templates, "hilly", 42);
+
+        for rx in -4..4 {
+            for rz in -4..4 {
+                let region = Vec2(rx, rz);
+                let origin = a.origin(0, &region, 16).unwrap();
+                assert_eq!(Some(origin.clone()), b.origin(0, &region, 16));
+
+                // the origin stays within its region, and only its chunk knows about it
+                let coords = map_voxel_to_chunk(origin.0, 0, origin.1, 16);
+                assert_eq!(coords.0.div_euclid(2), rx);
+                assert_eq!(coords.1.div_euclid(2), rz);
+                assert_eq!(a.origins_in_chunk(&coords, 16), vec![(0, origin)]);
+            }
+        }
+    }
+}
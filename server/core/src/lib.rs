@@ -2,5 +2,9 @@ pub mod comp;
 pub mod constants;
 pub mod engine;
 pub mod gen;
+pub mod logger;
 pub mod network;
 pub mod sys;
+
+#[cfg(any(test, feature = "bench"))]
+pub mod test_utils;
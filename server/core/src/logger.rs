@@ -0,0 +1,258 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use log::LevelFilter;
+use serde::Deserialize;
+use serde_json::json;
+
+/// How the server logs, set by `log` in `server.json`
+///
+/// ```json
+/// "log": {
+///   "level": "info",
+///   "format": "json",
+///   "modules": { "server_core::engine::chunks": "warn" },
+///   "file": { "path": "logs/server.log", "maxSize": 10485760, "maxFiles": 5 }
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogConfig {
+    pub level: String,
+    pub format: LogFormat,
+    /// Levels of modules and everything under them, overriding `level`
+    pub modules: HashMap<String, String>,
+    /// Also log to a file, rotated once it grows too large
+    pub file: Option<LogFileConfig>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "debug".to_owned(),
+            format: LogFormat::Pretty,
+            modules: HashMap::new(),
+            file: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogFormat {
+    /// A line per record for people to read
+    Pretty,
+    /// A JSON object per line for log collectors to parse
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFileConfig {
+    pub path: String,
+    /// Bytes the file may grow to before it's rotated
+    #[serde(default = "default_max_size")]
+    pub max_size: u64,
+    /// Rotated files kept around as `<path>.1` to `<path>.<maxFiles>`, newest first
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+}
+
+fn default_max_size() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+impl LogConfig {
+    /// Load the `log` section of the server config, logging everything from debug up to
+    /// stdout if there's none
+    pub fn load(path: &str) -> Self {
+        #[derive(Deserialize, Default)]
+        #[serde(default)]
+        struct ServerConfig {
+            log: LogConfig,
+        }
+
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                serde_json::from_str::<ServerConfig>(&text)
+                    .unwrap_or_else(|e| panic!("Unable to read server config at {}: {}", path, e))
+                    .log
+            }
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Install the logger this config describes
+    pub fn apply(&self) -> Result<(), fern::InitError> {
+        let mut dispatch = fern::Dispatch::new().level(parse_level(&self.level)?);
+
+        for (module, level) in self.modules.iter() {
+            dispatch = dispatch.level_for(module.to_owned(), parse_level(level)?);
+        }
+
+        dispatch = match self.format {
+            LogFormat::Pretty => dispatch.format(|out, message, record| {
+                out.finish(format_args!(
+                    "{} [{}] [{}]: {}",
+                    chrono::Local::now().format("[%H:%M:%S]"),
+                    record.level(),
+                    record.target(),
+                    message
+                ))
+            }),
+            LogFormat::Json => dispatch.format(|out, message, record| {
+                out.finish(format_args!(
+                    "{}",
+                    json!({
+                        "time": chrono::Local::now().to_rfc3339(),
+                        "level": record.level().to_string(),
+                        "target": record.target(),
+                        "message": message.to_string(),
+                    })
+                ))
+            }),
+        };
+
+        dispatch = dispatch.chain(io::stdout());
+
+        if let Some(file) = &self.file {
+            let writer: Box<dyn Write + Send> = Box::new(RotatingFile::open(file)?);
+            dispatch = dispatch.chain(writer);
+        }
+
+        dispatch.apply()?;
+
+        Ok(())
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, fern::InitError> {
+    LevelFilter::from_str(level).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown log level \"{}\"", level),
+        )
+        .into()
+    })
+}
+
+/// A log file that moves itself aside to `<path>.1` once it grows past a size, shifting the
+/// older ones along and dropping the oldest
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(config: &LogFileConfig) -> io::Result<Self> {
+        let path = PathBuf::from(&config.path);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = Self::append(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            max_size: config.max_size,
+            max_files: config.max_files,
+            file,
+            size,
+        })
+    }
+
+    fn append(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated(index);
+                if from.exists() {
+                    fs::rename(from, self.rotated(index + 1))?;
+                }
+            }
+
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+
+        self.file = Self::append(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotating_file_works() {
+        let folder = std::env::temp_dir().join(format!("mine.js-logs-{}", std::process::id()));
+        let config = LogFileConfig {
+            path: folder.join("server.log").to_string_lossy().into_owned(),
+            max_size: 8,
+            max_files: 2,
+        };
+
+        let mut file = RotatingFile::open(&config).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"].iter() {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |name: &str| fs::read_to_string(folder.join(name)).unwrap();
+        assert_eq!(read("server.log"), "fourth\n");
+        assert_eq!(read("server.log.1"), "third\n");
+        assert_eq!(read("server.log.2"), "second\n");
+        assert!(!folder.join("server.log.3").exists());
+
+        assert!(parse_level("warn").is_ok());
+        assert!(parse_level("loud").is_err());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,271 @@
+use std::{collections::HashMap, fmt, fs::File};
+
+use jsonwebtoken::{decode, errors::ErrorKind, Algorithm, DecodingKey, Validation};
+use log::{info, warn};
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Where players' identities come from, loaded from `auth.json`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthConfig {
+    /// Whether players without a valid token are turned away instead of joining anonymously
+    #[serde(default)]
+    pub required: bool,
+
+    /// Static tokens, each mapped to the name of the identity it logs in as
+    #[serde(default)]
+    pub tokens: HashMap<String, String>,
+
+    /// Verification of JWTs issued by an external identity provider
+    #[serde(default)]
+    pub jwt: Option<JwtConfig>,
+
+    /// Logins allowed to run admin commands, as `token:<name>` for static tokens,
+    /// `jwt:<issuer>:<subject>` for JWTs or the UUID of the player. Nobody but the console is
+    /// an admin if none are listed.
+    #[serde(default)]
+    pub admins: Vec<String>,
+}
+
+/// How to verify JWTs, signed either with a shared secret (HS256) or an RSA key (RS256)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JwtConfig {
+    pub secret: Option<String>,
+    /// PEM encoded public key of the identity provider
+    pub public_key: Option<String>,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+}
+
+/// Claims read off of a verified JWT
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String,
+    #[serde(default)]
+    iss: Option<String>,
+    #[serde(default)]
+    preferred_username: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+/// Static tokens are hashed apart from JWTs, so that no JWT can share a UUID with one
+const TOKEN_NAMESPACE: Uuid = Uuid::NAMESPACE_X500;
+
+/// Who a connection logged in as
+#[derive(Debug, Clone)]
+pub struct Identity {
+    /// Stable across sessions for the same login, None for anonymous players
+    pub uuid: Option<Uuid>,
+    pub name: Option<String>,
+    /// Whether the player may run admin commands
+    pub admin: bool,
+}
+
+impl Identity {
+    pub fn anonymous() -> Self {
+        Self {
+            uuid: None,
+            name: None,
+            admin: false,
+        }
+    }
+
+    /// The same key always maps to the same UUID, so nothing has to be stored
+    fn persistent(namespace: &Uuid, key: &str, name: Option<String>) -> Self {
+        Self {
+            uuid: Some(Uuid::new_v5(namespace, key.as_bytes())),
+            name,
+            admin: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthError {
+    Missing,
+    Invalid,
+    Expired,
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Missing => write!(f, "This server requires you to log in."),
+            AuthError::Invalid => write!(f, "Invalid login token."),
+            AuthError::Expired => write!(f, "Login token has expired, please log in again."),
+        }
+    }
+}
+
+/// Checks the tokens clients send along with their handshake
+#[derive(Default)]
+pub struct Auth {
+    config: AuthConfig,
+    jwt: Option<(DecodingKey<'static>, Validation)>,
+}
+
+impl Auth {
+    pub fn new(config: AuthConfig) -> Self {
+        let jwt = config.jwt.as_ref().and_then(|jwt| {
+            let (key, algorithm) = match (&jwt.public_key, &jwt.secret) {
+                (Some(pem), _) => match DecodingKey::from_rsa_pem(pem.as_bytes()) {
+                    Ok(key) => (key.into_static(), Algorithm::RS256),
+                    Err(e) => {
+                        warn!("Invalid JWT public key, JWT logins are disabled: {}", e);
+                        return None;
+                    }
+                },
+                (None, Some(secret)) => (
+                    DecodingKey::from_secret(secret.as_bytes()).into_static(),
+                    Algorithm::HS256,
+                ),
+                (None, None) => {
+                    warn!("JWT auth needs a secret or a public key, JWT logins are disabled.");
+                    return None;
+                }
+            };
+
+            let mut validation = Validation::new(algorithm);
+            validation.iss = jwt.issuer.to_owned();
+
+            if let Some(audience) = &jwt.audience {
+                validation.set_audience(&[audience]);
+            }
+
+            Some((key, validation))
+        });
+
+        Self { config, jwt }
+    }
+
+    /// Load the auth config, letting everyone in anonymously if there's none
+    pub fn load(path: &str) -> Self {
+        let config = match File::open(path) {
+            Ok(file) => match serde_json::from_reader::<_, AuthConfig>(file) {
+                Ok(config) => config,
+                Err(e) => panic!("Unable to read auth config at {}: {}", path, e),
+            },
+            Err(_) => {
+                info!("No auth config at {}, players join anonymously.", path);
+                AuthConfig::default()
+            }
+        };
+
+        Self::new(config)
+    }
+
+    /// Find out who a token belongs to, static tokens first then JWTs
+    ///
+    /// Admins are only ever matched on what can't be picked by players, never on their names.
+    pub fn authenticate(&self, token: Option<&str>) -> Result<Identity, AuthError> {
+        let (mut identity, login) = self.identify(token)?;
+
+        identity.admin = match (&login, &identity.uuid) {
+            (Some(login), Some(uuid)) => {
+                let uuid = uuid.to_string();
+                self.config
+                    .admins
+                    .iter()
+                    .any(|admin| *admin == *login || *admin == uuid)
+            }
+            _ => false,
+        };
+
+        Ok(identity)
+    }
+
+    /// Who a token belongs to, along with the login they're known by to the admin list
+    fn identify(&self, token: Option<&str>) -> Result<(Identity, Option<String>), AuthError> {
+        let token = match token.filter(|t| !t.is_empty()) {
+            Some(token) => token,
+            None if self.config.required => return Err(AuthError::Missing),
+            None => return Ok((Identity::anonymous(), None)),
+        };
+
+        if let Some(name) = self.config.tokens.get(token) {
+            let identity = Identity::persistent(&TOKEN_NAMESPACE, name, Some(name.to_owned()));
+            return Ok((identity, Some(format!("token:{}", name))));
+        }
+
+        let (key, validation) = self.jwt.as_ref().ok_or(AuthError::Invalid)?;
+
+        match decode::<Claims>(token, key, validation) {
+            Ok(data) => {
+                let Claims {
+                    sub,
+                    iss,
+                    preferred_username,
+                    name,
+                } = data.claims;
+                let key = format!("{}:{}", iss.unwrap_or_else(|| "jwt".to_owned()), sub);
+                let identity =
+                    Identity::persistent(&Uuid::NAMESPACE_OID, &key, preferred_username.or(name));
+
+                Ok((identity, Some(format!("jwt:{}", key))))
+            }
+            Err(e) => match e.kind() {
+                ErrorKind::ExpiredSignature => Err(AuthError::Expired),
+                _ => Err(AuthError::Invalid),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authenticate_works() {
+        let mut config = AuthConfig::default();
+        config.tokens.insert("secret".to_owned(), "ian".to_owned());
+        config.tokens.insert("other".to_owned(), "bob".to_owned());
+        config.admins.push("token:ian".to_owned());
+
+        let auth = Auth::new(config.clone());
+
+        let first = auth.authenticate(Some("secret")).unwrap();
+        let again = auth.authenticate(Some("secret")).unwrap();
+        assert!(first.uuid.is_some());
+        assert_eq!(first.uuid, again.uuid);
+        assert_eq!(first.name.as_deref(), Some("ian"));
+        assert!(first.admin);
+        assert!(!auth.authenticate(Some("other")).unwrap().admin);
+        assert!(!auth.authenticate(None).unwrap().admin);
+
+        // admins are listed by login or UUID, not by the names players go by
+        let admin_with = |admins: Vec<String>| {
+            let config = AuthConfig {
+                admins,
+                ..config.clone()
+            };
+            Auth::new(config)
+                .authenticate(Some("secret"))
+                .unwrap()
+                .admin
+        };
+        assert!(!admin_with(vec!["ian".to_owned()]));
+        assert!(admin_with(vec![first.uuid.unwrap().to_string()]));
+
+        // servers without admins have none
+        let open = Auth::new(AuthConfig::default());
+        assert!(!open.authenticate(None).unwrap().admin);
+
+        // a JWT issued as "token" doesn't log in as the static token of the same name
+        let jwt = Identity::persistent(&Uuid::NAMESPACE_OID, "token:ian", None);
+        assert_ne!(jwt.uuid, first.uuid);
+
+        assert_eq!(auth.authenticate(None).unwrap().uuid, None);
+        assert_eq!(
+            auth.authenticate(Some("wrong")).unwrap_err(),
+            AuthError::Invalid
+        );
+
+        config.required = true;
+        let auth = Auth::new(config);
+        assert_eq!(auth.authenticate(None).unwrap_err(), AuthError::Missing);
+    }
+}
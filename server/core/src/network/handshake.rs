@@ -0,0 +1,215 @@
+use std::fmt;
+
+use crate::constants::{
+    COMPRESSION_THRESHOLD, MIN_COMPRESSION_THRESHOLD, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+
+use super::auth::AuthError;
+use super::models::{create_of_type, messages, MessageType};
+
+/// Optional protocol features a client and the server agree on during the handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// Large messages may be zlib compressed
+    Zlib,
+    /// Chunk meshes with quantized positions, not served by this server yet
+    QuantizedMeshes,
+    /// Torch light packed as separate red, green and blue channels
+    RgbLight,
+    /// Messages written out together may come in one batch message
+    Batch,
+}
+
+/// Capabilities this server can serve
+const SUPPORTED: [Capability; 3] = [Capability::Zlib, Capability::RgbLight, Capability::Batch];
+
+/// Capabilities a client must have, since the server can't serve data without them
+const REQUIRED: [Capability; 1] = [Capability::RgbLight];
+
+impl Capability {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "zlib" => Some(Capability::Zlib),
+            "quantizedMeshes" => Some(Capability::QuantizedMeshes),
+            "rgbLight" => Some(Capability::RgbLight),
+            "batch" => Some(Capability::Batch),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Capability::Zlib => "zlib",
+            Capability::QuantizedMeshes => "quantizedMeshes",
+            Capability::RgbLight => "rgbLight",
+            Capability::Batch => "batch",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum HandshakeError {
+    Missing,
+    TimedOut,
+    Malformed,
+    Outdated(u32),
+    Unsupported(u32),
+    Incapable(Capability),
+    Unauthorized(AuthError),
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeError::Missing => write!(f, "Expected a handshake before anything else."),
+            HandshakeError::TimedOut => write!(f, "Timed out waiting for a handshake."),
+            HandshakeError::Malformed => write!(f, "Malformed handshake."),
+            HandshakeError::Outdated(version) => write!(
+                f,
+                "Client protocol version {} is too old, this server needs at least {}. Please update your client.",
+                version, MIN_PROTOCOL_VERSION
+            ),
+            HandshakeError::Unsupported(version) => write!(
+                f,
+                "Client protocol version {} is newer than this server's {}.",
+                version, PROTOCOL_VERSION
+            ),
+            HandshakeError::Incapable(capability) => write!(
+                f,
+                "Client is missing the \"{}\" capability this server requires.",
+                capability.name()
+            ),
+            HandshakeError::Unauthorized(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Check a client's handshake, returning the capabilities both sides agreed on
+pub fn negotiate(message: &messages::Message) -> Result<Vec<Capability>, HandshakeError> {
+    if message.r#type != MessageType::Handshake as i32 {
+        return Err(HandshakeError::Missing);
+    }
+
+    let json = message
+        .parse_json()
+        .map_err(|_| HandshakeError::Malformed)?;
+
+    let version = json["version"].as_u64().ok_or(HandshakeError::Malformed)? as u32;
+
+    if version < MIN_PROTOCOL_VERSION {
+        return Err(HandshakeError::Outdated(version));
+    }
+
+    if version > PROTOCOL_VERSION {
+        return Err(HandshakeError::Unsupported(version));
+    }
+
+    // unknown capabilities come from newer clients, and are simply left out
+    let offered = json["capabilities"]
+        .as_array()
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| name.as_str().and_then(Capability::from_name))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    if let Some(&missing) = REQUIRED.iter().find(|c| !offered.contains(c)) {
+        return Err(HandshakeError::Incapable(missing));
+    }
+
+    Ok(SUPPORTED
+        .iter()
+        .filter(|c| offered.contains(c))
+        .copied()
+        .collect())
+}
+
+/// Bytes past which frames to a client get compressed, the server's default unless the client
+/// asked for a threshold of its own in the handshake
+pub fn compression_threshold(message: &messages::Message) -> usize {
+    message
+        .parse_json()
+        .ok()
+        .and_then(|json| json["compressionThreshold"].as_u64())
+        .map(|threshold| (threshold as usize).max(MIN_COMPRESSION_THRESHOLD))
+        .unwrap_or(COMPRESSION_THRESHOLD)
+}
+
+/// The server's reply to a successful handshake
+pub fn create_handshake_message(
+    capabilities: &[Capability],
+    compression_threshold: usize,
+) -> messages::Message {
+    let names = capabilities.iter().map(|c| c.name()).collect::<Vec<_>>();
+
+    let mut message = create_of_type(MessageType::Handshake);
+    message.json = serde_json::json!({
+        "version": PROTOCOL_VERSION,
+        "capabilities": names,
+        "compressionThreshold": compression_threshold,
+    })
+    .to_string();
+
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake(json: &str) -> messages::Message {
+        let mut message = create_of_type(MessageType::Handshake);
+        message.json = json.to_owned();
+        message
+    }
+
+    #[test]
+    fn negotiate_works() {
+        let agreed = negotiate(&handshake(&format!(
+            r#"{{"version": {}, "capabilities": ["rgbLight", "quantizedMeshes", "zlib", "future"]}}"#,
+            PROTOCOL_VERSION
+        )));
+        assert_eq!(agreed, Ok(vec![Capability::Zlib, Capability::RgbLight]));
+
+        let incapable = negotiate(&handshake(&format!(
+            r#"{{"version": {}, "capabilities": ["zlib"]}}"#,
+            PROTOCOL_VERSION
+        )));
+        assert_eq!(
+            incapable,
+            Err(HandshakeError::Incapable(Capability::RgbLight))
+        );
+
+        let newer = negotiate(&handshake(&format!(
+            r#"{{"version": {}, "capabilities": []}}"#,
+            PROTOCOL_VERSION + 1
+        )));
+        assert_eq!(
+            newer,
+            Err(HandshakeError::Unsupported(PROTOCOL_VERSION + 1))
+        );
+
+        assert_eq!(
+            negotiate(&create_of_type(MessageType::Peer)),
+            Err(HandshakeError::Missing)
+        );
+    }
+
+    #[test]
+    fn compression_threshold_works() {
+        assert_eq!(
+            compression_threshold(&handshake(r#"{"version": 2}"#)),
+            COMPRESSION_THRESHOLD
+        );
+        assert_eq!(
+            compression_threshold(&handshake(r#"{"compressionThreshold": 4096}"#)),
+            4096
+        );
+        assert_eq!(
+            compression_threshold(&handshake(r#"{"compressionThreshold": 1}"#)),
+            MIN_COMPRESSION_THRESHOLD
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    convert::TryInto,
+    time::{Duration, Instant},
+};
+
+use crate::constants::KEEPALIVE_TIMEOUT;
+
+/// Keeps track of whether a connection is still alive, and of how long a round trip takes
+///
+/// Pings carry when they were sent, so that the pongs echoing them back tell the round trip
+/// without anything to remember in between.
+#[derive(Debug)]
+pub struct Keepalive {
+    started: Instant,
+    last_heard: Instant,
+    /// Smoothed round trip time, None until the first pong
+    pub rtt: Option<Duration>,
+}
+
+impl Default for Keepalive {
+    fn default() -> Self {
+        let now = Instant::now();
+
+        Self {
+            started: now,
+            last_heard: now,
+            rtt: None,
+        }
+    }
+}
+
+impl Keepalive {
+    /// Note that the other end sent something, so it's still there
+    pub fn heard(&mut self) {
+        self.last_heard = Instant::now();
+    }
+
+    pub fn is_timed_out(&self) -> bool {
+        self.last_heard.elapsed() > Duration::from_secs(KEEPALIVE_TIMEOUT)
+    }
+
+    /// Payload of a ping sent now
+    pub fn ping(&self) -> Vec<u8> {
+        (self.started.elapsed().as_micros() as u64)
+            .to_be_bytes()
+            .to_vec()
+    }
+
+    /// Measure a round trip off of the pong to one of our pings, None if it isn't one
+    pub fn pong(&mut self, payload: &[u8]) -> Option<Duration> {
+        let sent = u64::from_be_bytes(payload.try_into().ok()?);
+        let rtt = self
+            .started
+            .elapsed()
+            .checked_sub(Duration::from_micros(sent))?;
+
+        self.heard();
+
+        // smoothed so that a single slow pong doesn't throw it off
+        let rtt = match self.rtt {
+            Some(previous) => (previous * 3 + rtt) / 4,
+            None => rtt,
+        };
+
+        self.rtt = Some(rtt);
+
+        Some(rtt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keepalive_works() {
+        let mut keepalive = Keepalive::default();
+
+        let ping = keepalive.ping();
+        assert!(keepalive.pong(&ping).is_some());
+        assert!(keepalive.rtt.is_some());
+
+        assert_eq!(keepalive.pong(b"hello"), None);
+        assert_eq!(keepalive.pong(&u64::MAX.to_be_bytes()), None);
+        assert!(!keepalive.is_timed_out());
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+};
+
+use log::info;
+use rustls::{
+    internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys},
+    NoClientAuth, ServerConfig,
+};
+use serde::Deserialize;
+
+use super::rcon::RconConfig;
+
+/// Settings of the game's HTTP and WebSocket listener, loaded from `server.json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListenerConfig {
+    pub address: String,
+
+    /// Serve over HTTPS and secure WebSockets, plaintext if not set
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+
+    /// Accept remote consoles over RCON, not at all if not set
+    #[serde(default)]
+    pub rcon: Option<RconConfig>,
+}
+
+impl Default for ListenerConfig {
+    fn default() -> Self {
+        Self {
+            address: "localhost:4000".to_owned(),
+            tls: None,
+            rcon: None,
+        }
+    }
+}
+
+impl ListenerConfig {
+    /// Load the listener config, listening locally in plaintext if there's none
+    pub fn load(path: &str) -> Self {
+        match File::open(path) {
+            Ok(file) => serde_json::from_reader(file)
+                .unwrap_or_else(|e| panic!("Unable to read server config at {}: {}", path, e)),
+            Err(_) => {
+                info!("No server config at {}, using the defaults.", path);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// Paths to the PEM encoded certificate chain and private key to terminate TLS with
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsConfig {
+    pub cert: String,
+    pub key: String,
+}
+
+impl TlsConfig {
+    /// Read the certificate chain and key into a rustls config, PKCS#8 or RSA keys alike
+    pub fn rustls_config(&self) -> io::Result<ServerConfig> {
+        let invalid = |what: &str, path: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid TLS {} at {}", what, path),
+            )
+        };
+
+        let cert_chain = certs(&mut BufReader::new(File::open(&self.cert)?))
+            .ok()
+            .filter(|chain| !chain.is_empty())
+            .ok_or_else(|| invalid("certificate", &self.cert))?;
+
+        let mut keys = pkcs8_private_keys(&mut BufReader::new(File::open(&self.key)?))
+            .map_err(|_| invalid("key", &self.key))?;
+
+        if keys.is_empty() {
+            keys = rsa_private_keys(&mut BufReader::new(File::open(&self.key)?))
+                .map_err(|_| invalid("key", &self.key))?;
+        }
+
+        if keys.is_empty() {
+            return Err(invalid("key", &self.key));
+        }
+
+        let mut config = ServerConfig::new(NoClientAuth::new());
+        config
+            .set_single_cert(cert_chain, keys.remove(0))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        Ok(config)
+    }
+}
@@ -1,17 +1,25 @@
 use serde::{Deserialize, Serialize};
 
+use std::sync::Arc;
+
+use server_common::vec::{Vec2, Vec3};
+
 use actix::prelude::*;
 
+use crate::engine::border::WorldBorder;
 use crate::engine::entities::EntityPrototypes;
+use crate::engine::sounds::SoundInfos;
 
 use super::super::engine::registry::{Blocks, Ranges};
 
+use super::auth::{AuthError, Identity};
 use super::models;
+use super::outbox::SharedOutbox;
 
-/// Base actor message to derive from
+/// Wakes a session up to write out its queued messages
 #[derive(Clone, Message)]
 #[rtype(result = "()")]
-pub struct Message(pub models::messages::Message);
+pub struct Flush;
 
 /* -------------------------------------------------------------------------- */
 /*                             Connection Messages                            */
@@ -23,14 +31,27 @@ pub struct JoinResult {
     pub tick_speed: f32,
     pub spawn: [i32; 3],
     pub passables: Vec<u32>,
+    pub game_mode: String,
+}
+
+/// Check the login token a client sent along with its handshake
+#[derive(Clone, Message)]
+#[rtype(result = "Result<Identity, AuthError>")]
+pub struct Authenticate {
+    pub token: Option<String>,
 }
 
 #[derive(Clone, Message)]
 #[rtype(result = "JoinResult")]
 pub struct JoinWorld {
     pub world_name: String,
-    pub player_name: Option<String>,
-    pub player_addr: Recipient<Message>,
+    /// Who the player logged in as, their UUID is used to store their data
+    pub player_identity: Identity,
+    pub player_addr: Recipient<Flush>,
+    pub player_outbox: SharedOutbox,
+    pub session_addr: Recipient<SwitchWorld>,
+    /// Where to spawn the player, defaults to the world spawn
+    pub spawn: Option<Vec3<f32>>,
 }
 
 #[derive(Clone, Message)]
@@ -40,6 +61,14 @@ pub struct LeaveWorld {
     pub player_id: usize,
 }
 
+/// Sent to a session to move its player to another world, such as through a portal
+#[derive(Clone, Message)]
+#[rtype(result = "()")]
+pub struct SwitchWorld {
+    pub world_name: String,
+    pub spawn: Vec3<f32>,
+}
+
 /* -------------------------------------------------------------------------- */
 /*                             Game Play Messages                             */
 /* -------------------------------------------------------------------------- */
@@ -54,6 +83,26 @@ pub struct PlayerMessage {
     pub raw: models::messages::Message,
 }
 
+/// A player's round trip time, measured by their session's keepalive pings
+#[derive(Clone, Message)]
+#[rtype(result = "()")]
+pub struct PlayerLatency {
+    pub world_name: String,
+    pub player_id: usize,
+    /// Milliseconds
+    pub latency: u32,
+}
+
+/// A command from the server console, run as an admin in a world or the first one by name
+#[derive(Clone, Message)]
+#[rtype(result = "()")]
+pub struct ConsoleCommand {
+    pub world_name: Option<String>,
+    pub command: String,
+    /// Where the lines the command replied with go
+    pub reply: crossbeam_channel::Sender<Vec<String>>,
+}
+
 #[derive(Clone, Message)]
 #[rtype(result = "()")]
 pub struct Noop;
@@ -86,10 +135,13 @@ pub struct FullWorldData {
     pub save: bool,
     pub tick_speed: f32,
     pub render_radius: usize,
+    pub max_horizon_radius: usize,
     pub sub_chunks: u32,
     pub blocks: Blocks,
     pub ranges: Ranges,
     pub entities: EntityPrototypes,
+    pub sounds: SoundInfos,
+    pub border: Option<WorldBorder>,
     pub uv_side_count: u32,
     pub uv_texture_size: u32,
     pub packs: Vec<String>,
@@ -102,3 +154,12 @@ pub struct ListWorlds;
 #[derive(Clone, Message)]
 #[rtype(result = "FullWorldData")]
 pub struct GetWorld(pub String);
+
+#[derive(Clone, Message)]
+#[rtype(result = "Option<Arc<Vec<u8>>>")]
+pub struct GetAtlas(pub String);
+
+/// Get the map tile of a world's chunk, None if the chunk was never loaded
+#[derive(Clone, Message)]
+#[rtype(result = "Option<Arc<Vec<u8>>>")]
+pub struct GetMapTile(pub String, pub Vec2<i32>);
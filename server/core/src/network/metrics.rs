@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+/// Counters of everything written out to clients since the server started, across every
+/// connection
+#[derive(Debug, Default)]
+pub struct NetworkMetrics {
+    frames: AtomicU64,
+    messages: AtomicU64,
+    compressed_frames: AtomicU64,
+    bytes: AtomicU64,
+    wire_bytes: AtomicU64,
+}
+
+/// The network counters at one point in time, as served on `/metrics`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkSnapshot {
+    /// Websocket frames written
+    pub frames: u64,
+    /// Messages written, more than frames where they were batched together
+    pub messages: u64,
+    /// Frames that were large enough to be compressed
+    pub compressed_frames: u64,
+    /// Bytes of the encoded frames before compression
+    pub bytes: u64,
+    /// Bytes actually written, after compression
+    pub wire_bytes: u64,
+}
+
+pub static NETWORK_METRICS: NetworkMetrics = NetworkMetrics::new();
+
+impl NetworkMetrics {
+    pub const fn new() -> Self {
+        Self {
+            frames: AtomicU64::new(0),
+            messages: AtomicU64::new(0),
+            compressed_frames: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            wire_bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a frame of `messages` messages, `bytes` long encoded and `wire_bytes` long as written
+    pub fn record(&self, messages: usize, bytes: usize, wire_bytes: usize, compressed: bool) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        self.messages.fetch_add(messages as u64, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.wire_bytes
+            .fetch_add(wire_bytes as u64, Ordering::Relaxed);
+
+        if compressed {
+            self.compressed_frames.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn snapshot(&self) -> NetworkSnapshot {
+        NetworkSnapshot {
+            frames: self.frames.load(Ordering::Relaxed),
+            messages: self.messages.load(Ordering::Relaxed),
+            compressed_frames: self.compressed_frames.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            wire_bytes: self.wire_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_metrics_works() {
+        let metrics = NetworkMetrics::new();
+
+        metrics.record(5, 2048, 512, true);
+        metrics.record(1, 64, 64, false);
+
+        assert_eq!(
+            metrics.snapshot(),
+            NetworkSnapshot {
+                frames: 2,
+                messages: 6,
+                compressed_frames: 1,
+                bytes: 2112,
+                wire_bytes: 576,
+            }
+        );
+    }
+}
@@ -1,5 +1,12 @@
+pub mod auth;
+pub mod handshake;
+pub mod keepalive;
+pub mod listener;
 pub mod message;
+pub mod metrics;
 pub mod models;
+pub mod outbox;
+pub mod rcon;
 pub mod routes;
 pub mod server;
 pub mod session;
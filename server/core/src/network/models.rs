@@ -2,18 +2,30 @@ use prost::Message;
 
 use std::io::Cursor;
 
-use super::super::engine::chunk::Meshes;
+use tracing::info_span;
 
-use server_common::{ndarray::Ndarray, vec::Vec3};
+use specs::Entity;
+
+use super::super::comp::{
+    experience::Experience, health::Health, inventory::Inventory, profile::PlayerProfile,
+};
+use super::super::engine::{chunk::Meshes, waypoints::Waypoint};
+
+use server_common::{
+    array::Array3,
+    vec::{Vec2, Vec3},
+};
 
 /// Protobuf format for chunks
 #[derive(Debug)]
 pub struct ChunkProtocol {
     pub x: i32,
     pub z: i32,
+    /// Nothing but air, sent on its own instead of meshes, voxels and lights
+    pub empty: bool,
     pub meshes: Option<Vec<Meshes>>,
-    pub voxels: Option<Ndarray<u32>>,
-    pub lights: Option<Ndarray<u32>>,
+    pub voxels: Option<Array3<u32>>,
+    pub lights: Option<Array3<u32>>,
 }
 
 /// Protobuf format for peer updates
@@ -28,6 +40,14 @@ pub struct PeerProtocol {
     pub qy: f32,
     pub qz: f32,
     pub qw: f32,
+    pub swim_up: bool,
+    /// Team the peer is on, empty if none
+    pub team: String,
+    pub crouching: bool,
+    /// Id of the entity the peer rides, empty if none
+    pub vehicle: String,
+    /// Where the peer sits relative to the vehicle's position, empty if not riding
+    pub offset: Vec<f32>,
 }
 
 /// Protobuf format for entity updates
@@ -40,6 +60,9 @@ pub struct EntityProtocol {
     pub pz: f32,
     pub heading: Option<Vec3<f32>>,
     pub look_at: Option<Vec3<f32>>,
+    pub velocity: Vec3<f32>,
+    /// The side the entity is resting against on each axis, so clients snap it there
+    pub resting: Vec3<i32>,
 }
 
 /// Protobuf format for voxel updates
@@ -51,6 +74,29 @@ pub struct UpdateProtocol {
     pub r#type: u32,
     pub rotation: u32,
     pub y_rotation: u32,
+    pub stage: u32,
+}
+
+/// Protobuf format for particle effects
+#[derive(Debug, Clone)]
+pub struct ParticleProtocol {
+    pub r#type: String,
+    pub px: f32,
+    pub py: f32,
+    pub pz: f32,
+    pub count: u32,
+    pub spread: f32,
+}
+
+/// Protobuf format for sound effects
+#[derive(Debug, Clone)]
+pub struct SoundProtocol {
+    pub sound: u32,
+    pub px: f32,
+    pub py: f32,
+    pub pz: f32,
+    pub volume: f32,
+    pub pitch: f32,
 }
 
 /// Protobuf format for chat messages
@@ -72,6 +118,10 @@ pub struct MessageComponents {
     pub entities: Option<Vec<EntityProtocol>>,
     pub chunks: Option<Vec<ChunkProtocol>>,
     pub updates: Option<Vec<UpdateProtocol>>,
+    pub particles: Option<Vec<ParticleProtocol>>,
+    pub sounds: Option<Vec<SoundProtocol>>,
+    /// The tick and milliseconds since the epoch the message was sent on, to interpolate by
+    pub tick: Option<(u32, f64)>,
 }
 
 impl MessageComponents {
@@ -85,6 +135,9 @@ impl MessageComponents {
             entities: None,
             chunks: None,
             updates: None,
+            particles: None,
+            sounds: None,
+            tick: None,
         }
     }
 }
@@ -106,6 +159,20 @@ pub fn create_of_type(r#type: messages::message::Type) -> messages::Message {
     create_message(MessageComponents::default_for(r#type))
 }
 
+/// Wrap messages up into one frame, to be unpacked in order by the client
+pub fn create_batch_message(batch: Vec<messages::Message>) -> messages::Message {
+    let mut message = create_of_type(MessageType::Batch);
+    message.batch = batch;
+    message
+}
+
+/// Send the heightfields of far chunks along, to be drawn past the meshes
+pub fn create_horizon_message(horizons: Vec<messages::Horizon>) -> messages::Message {
+    let mut message = create_of_type(MessageType::Horizon);
+    message.horizons = horizons;
+    message
+}
+
 /// Create a message with protobuf components
 pub fn create_message(components: MessageComponents) -> messages::Message {
     let mut message = messages::Message {
@@ -142,6 +209,11 @@ pub fn create_message(components: MessageComponents) -> messages::Message {
                 qy: peer.qy,
                 qz: peer.qz,
                 qw: peer.qw,
+                swim_up: peer.swim_up,
+                team: peer.team,
+                crouching: peer.crouching,
+                vehicle: peer.vehicle,
+                offset: peer.offset,
             })
             .collect()
     }
@@ -165,10 +237,17 @@ pub fn create_message(components: MessageComponents) -> messages::Message {
                 } else {
                     vec![]
                 },
+                velocity: vec![entity.velocity.0, entity.velocity.1, entity.velocity.2],
+                resting: vec![entity.resting.0, entity.resting.1, entity.resting.2],
             })
             .collect()
     }
 
+    if let Some((tick, timestamp)) = components.tick {
+        message.tick = tick;
+        message.timestamp = timestamp;
+    }
+
     if let Some(chunks) = components.chunks {
         message.chunks = chunks
             .into_iter()
@@ -188,6 +267,9 @@ pub fn create_message(components: MessageComponents) -> messages::Message {
                                     positions: opaque.positions.to_owned(),
                                     lights: opaque.lights.to_owned(),
                                     uvs: opaque.uvs.to_owned(),
+                                    normals: opaque.normals.to_owned(),
+                                    block_ids: opaque.block_ids.to_owned(),
+                                    effects: opaque.effects.to_owned(),
                                 }),
                                 transparent: transparent.map(|transparent| messages::Geometry {
                                     aos: transparent.aos.to_owned(),
@@ -195,6 +277,9 @@ pub fn create_message(components: MessageComponents) -> messages::Message {
                                     positions: transparent.positions.to_owned(),
                                     lights: transparent.lights.to_owned(),
                                     uvs: transparent.uvs.to_owned(),
+                                    normals: transparent.normals.to_owned(),
+                                    block_ids: transparent.block_ids.to_owned(),
+                                    effects: transparent.effects.to_owned(),
                                 }),
                             }
                         })
@@ -214,6 +299,7 @@ pub fn create_message(components: MessageComponents) -> messages::Message {
                 },
                 x: chunk.x,
                 z: chunk.z,
+                empty: chunk.empty,
             })
             .collect()
     }
@@ -228,6 +314,35 @@ pub fn create_message(components: MessageComponents) -> messages::Message {
                 vz: update.vz,
                 rotation: update.rotation,
                 y_rotation: update.y_rotation,
+                stage: update.stage,
+            })
+            .collect()
+    }
+
+    if let Some(particles) = components.particles {
+        message.particles = particles
+            .into_iter()
+            .map(|particle| messages::ParticleEvent {
+                r#type: particle.r#type,
+                px: particle.px,
+                py: particle.py,
+                pz: particle.pz,
+                count: particle.count,
+                spread: particle.spread,
+            })
+            .collect()
+    }
+
+    if let Some(sounds) = components.sounds {
+        message.sounds = sounds
+            .into_iter()
+            .map(|sound| messages::SoundEvent {
+                sound: sound.sound,
+                px: sound.px,
+                py: sound.py,
+                pz: sound.pz,
+                volume: sound.volume,
+                pitch: sound.pitch,
             })
             .collect()
     }
@@ -253,8 +368,163 @@ pub fn create_chat_message(
     create_message(components)
 }
 
+/// Answer a chunk request about the chunks not sent along: the ones still being generated or
+/// meshed, and the ones turned away for good
+pub fn create_chunk_reply(pending: &[Vec2<i32>], rejected: &[Vec2<i32>]) -> messages::Message {
+    let to_pairs = |coords: &[Vec2<i32>]| {
+        coords
+            .iter()
+            .map(|Vec2(cx, cz)| [*cx, *cz])
+            .collect::<Vec<_>>()
+    };
+
+    let mut components = MessageComponents::default_for(MessageType::Request);
+    components.json = Some(
+        serde_json::json!({
+            "pending": to_pairs(pending),
+            "rejected": to_pairs(rejected),
+        })
+        .to_string(),
+    );
+
+    create_message(components)
+}
+
+/// Tell a player about the far chunks whose horizons they won't be sent
+pub fn create_horizon_reply(rejected: &[Vec2<i32>]) -> messages::Message {
+    let pairs = rejected
+        .iter()
+        .map(|Vec2(cx, cz)| [*cx, *cz])
+        .collect::<Vec<_>>();
+
+    let mut components = MessageComponents::default_for(MessageType::Request);
+    components.json = Some(serde_json::json!({ "rejectedHorizons": pairs }).to_string());
+
+    create_message(components)
+}
+
+/// Tell players how others appear, by the ids they know those players by
+pub fn create_profiles_message(profiles: &[(usize, &PlayerProfile)]) -> messages::Message {
+    let profiles = profiles
+        .iter()
+        .map(|(id, profile)| {
+            let mut json = serde_json::to_value(profile).unwrap();
+            json["id"] = serde_json::json!(id.to_string());
+            json
+        })
+        .collect::<Vec<_>>();
+
+    let mut components = MessageComponents::default_for(MessageType::Profile);
+    components.json = Some(serde_json::json!({ "profiles": profiles }).to_string());
+
+    create_message(components)
+}
+
+/// Tell a player what's in their inventory and what they're wearing
+pub fn create_inventory_message(inventory: &Inventory) -> messages::Message {
+    let mut components = MessageComponents::default_for(MessageType::Inventory);
+    components.json = Some(
+        serde_json::json!({
+            "slots": inventory.slots,
+            "selected": inventory.selected,
+            "equipment": inventory.equipment,
+        })
+        .to_string(),
+    );
+
+    create_message(components)
+}
+
+/// Tell a player how much experience they've gathered, and the level it takes them to
+pub fn create_experience_message(experience: &Experience) -> messages::Message {
+    let mut components = MessageComponents::default_for(MessageType::Info);
+    components.json = Some(
+        serde_json::json!({
+            "experience": {
+                "points": experience.points,
+                "level": experience.level(),
+                "progress": experience.progress(),
+            },
+        })
+        .to_string(),
+    );
+
+    create_message(components)
+}
+
+/// Send a player their own waypoints along with the markers admins set for everyone
+pub fn create_waypoints_message(waypoints: &[Waypoint], markers: &[Waypoint]) -> messages::Message {
+    let mut components = MessageComponents::default_for(MessageType::Info);
+    components.json = Some(
+        serde_json::json!({
+            "waypoints": {
+                "own": waypoints,
+                "markers": markers,
+            },
+        })
+        .to_string(),
+    );
+
+    create_message(components)
+}
+
+/// Tell a player what they're riding and how high they sit on it in voxels, or that they
+/// got off
+pub fn create_mount_message(mount: Option<(Entity, f32)>) -> messages::Message {
+    let mut components = MessageComponents::default_for(MessageType::Info);
+    components.json = Some(
+        serde_json::json!({
+            "mount": mount.map(|(entity, seat)| serde_json::json!({
+                "entity": entity.id().to_string(),
+                "seat": seat,
+            })),
+        })
+        .to_string(),
+    );
+
+    create_message(components)
+}
+
+/// Tell players how hurt a player or a mob is, players going by their id
+pub fn create_health_message(
+    player: Option<usize>,
+    entity: Entity,
+    health: &Health,
+) -> messages::Message {
+    let mut json = serde_json::json!({
+        "health": health.current,
+        "max": health.max,
+        "dead": health.is_dead(),
+    });
+
+    match player {
+        Some(id) => json["player"] = serde_json::json!(id.to_string()),
+        None => json["entity"] = serde_json::json!(entity.id().to_string()),
+    }
+
+    let mut components = MessageComponents::default_for(MessageType::Health);
+    components.json = Some(json.to_string());
+
+    create_message(components)
+}
+
+/// Tell players how long round trips to others take, in milliseconds by player id
+pub fn create_latency_message(latencies: &[(usize, u32)]) -> messages::Message {
+    let latencies = latencies
+        .iter()
+        .map(|(id, latency)| (id.to_string(), serde_json::json!(latency)))
+        .collect::<serde_json::Map<_, _>>();
+
+    let mut components = MessageComponents::default_for(MessageType::Latency);
+    components.json = Some(serde_json::json!({ "latencies": latencies }).to_string());
+
+    create_message(components)
+}
+
 /// Encode message into protobuf buffer
 pub fn encode_message(message: &messages::Message) -> Vec<u8> {
+    let _span = info_span!("encode_message", r#type = message.r#type).entered();
+
     let mut buf = Vec::new();
     buf.reserve(message.encoded_len());
     message.encode(&mut buf).unwrap();
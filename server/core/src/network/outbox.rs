@@ -0,0 +1,241 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use prost::Message as ProstMessage;
+
+use crate::constants::{OUTBOX_HARD_LIMIT, OUTBOX_SOFT_LIMIT};
+
+use super::models::{messages, MessageType};
+
+/// How urgently a message needs to reach the client, lowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Cosmetic or superseded by the next tick: particles, sounds, peers, entities and latencies
+    Low = 0,
+    /// Chunk data
+    Normal = 1,
+    /// Voxel updates and everything else
+    High = 2,
+}
+
+impl Priority {
+    pub fn of(message: &messages::Message) -> Self {
+        match MessageType::from_i32(message.r#type) {
+            Some(MessageType::Particle)
+            | Some(MessageType::Sound)
+            | Some(MessageType::Peer)
+            | Some(MessageType::Entity)
+            | Some(MessageType::Latency) => Priority::Low,
+            Some(MessageType::Load) => Priority::Normal,
+            _ => Priority::High,
+        }
+    }
+}
+
+/// A bounded queue of messages waiting to be written to one connection.
+///
+/// Past the soft limit, particles and sounds are dropped and peer and entity updates are
+/// coalesced into the ones already queued. Past the hard limit the connection is considered
+/// stuck, and the queue overflows so the player can be disconnected.
+#[derive(Debug, Default)]
+pub struct Outbox {
+    tiers: [VecDeque<(messages::Message, usize)>; 3],
+    bytes: usize,
+    overflowed: bool,
+    awaiting_flush: bool,
+}
+
+pub type SharedOutbox = Arc<Mutex<Outbox>>;
+
+impl Outbox {
+    /// Queue a message, returning false once the outbox has overflowed
+    pub fn push(&mut self, message: messages::Message) -> bool {
+        if self.overflowed {
+            return false;
+        }
+
+        let size = message.encoded_len();
+        let priority = Priority::of(&message);
+
+        if self.bytes + size > OUTBOX_SOFT_LIMIT {
+            if priority == Priority::Low {
+                self.coalesce(message);
+                return true;
+            }
+
+            self.shed();
+        }
+
+        if self.bytes + size > OUTBOX_HARD_LIMIT {
+            self.overflow();
+            return false;
+        }
+
+        self.bytes += size;
+        self.tiers[priority as usize].push_back((message, size));
+
+        true
+    }
+
+    /// Take the most urgent messages, up to roughly `budget` bytes but always at least one
+    pub fn take(&mut self, budget: usize) -> Vec<messages::Message> {
+        let mut taken = vec![];
+        let mut bytes = 0;
+
+        while bytes < budget {
+            let next = self
+                .tiers
+                .iter_mut()
+                .rev()
+                .find_map(|tier| tier.pop_front());
+
+            match next {
+                Some((message, size)) => {
+                    bytes += size;
+                    self.bytes -= size;
+                    taken.push(message);
+                }
+                None => break,
+            }
+        }
+
+        if self.is_empty() {
+            self.awaiting_flush = false;
+        }
+
+        taken
+    }
+
+    /// Mark the outbox as waiting for its connection to flush it, returning whether the
+    /// connection still needs to be woken up
+    pub fn wake(&mut self) -> bool {
+        !std::mem::replace(&mut self.awaiting_flush, true)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiers.iter().all(|tier| tier.is_empty())
+    }
+
+    pub fn is_overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Queued bytes across all tiers
+    pub fn len_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Fold a low priority message into what's already queued, or drop it
+    fn coalesce(&mut self, message: messages::Message) {
+        let r#type = message.r#type;
+        let tier = &mut self.tiers[Priority::Low as usize];
+        let queued = tier.iter_mut().rev().find(|(m, _)| m.r#type == r#type);
+
+        let (queued, size) = match (MessageType::from_i32(r#type), queued) {
+            (Some(MessageType::Entity), Some(queued)) => {
+                // entity updates are full snapshots, the newest one wins
+                queued.0 = message;
+                queued
+            }
+            (Some(MessageType::Peer), Some(queued)) => {
+                message.peers.into_iter().for_each(|peer| {
+                    match queued.0.peers.iter_mut().find(|p| p.id == peer.id) {
+                        Some(existing) => *existing = peer,
+                        None => queued.0.peers.push(peer),
+                    }
+                });
+                queued
+            }
+            _ => return,
+        };
+
+        let new_size = queued.encoded_len();
+        self.bytes = self.bytes - *size + new_size;
+        *size = new_size;
+    }
+
+    /// Drop every queued particle and sound to make room for more important messages
+    fn shed(&mut self) {
+        let mut freed = 0;
+
+        self.tiers[Priority::Low as usize].retain(|(message, size)| {
+            let droppable = matches!(
+                MessageType::from_i32(message.r#type),
+                Some(MessageType::Particle) | Some(MessageType::Sound)
+            );
+
+            if droppable {
+                freed += size;
+            }
+
+            !droppable
+        });
+
+        self.bytes -= freed;
+    }
+
+    fn overflow(&mut self) {
+        self.tiers.iter_mut().for_each(|tier| tier.clear());
+        self.bytes = 0;
+        self.overflowed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::network::models::create_of_type;
+
+    #[test]
+    fn outbox_priority_works() {
+        let mut outbox = Outbox::default();
+
+        outbox.push(create_of_type(MessageType::Particle));
+        outbox.push(create_of_type(MessageType::Load));
+        outbox.push(create_of_type(MessageType::Update));
+
+        let types = outbox
+            .take(usize::MAX)
+            .iter()
+            .map(|m| m.r#type)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            types,
+            vec![
+                MessageType::Update as i32,
+                MessageType::Load as i32,
+                MessageType::Particle as i32
+            ]
+        );
+        assert_eq!(outbox.len_bytes(), 0);
+    }
+
+    #[test]
+    fn outbox_saturation_works() {
+        let mut outbox = Outbox::default();
+
+        let mut filler = create_of_type(MessageType::Load);
+        filler.json = "x".repeat(OUTBOX_SOFT_LIMIT);
+
+        outbox.push(create_of_type(MessageType::Sound));
+        outbox.push(create_of_type(MessageType::Entity));
+        assert!(outbox.push(filler.clone()));
+
+        // saturated: sounds are shed, entity snapshots replace each other
+        assert!(outbox.push(create_of_type(MessageType::Sound)));
+        assert!(outbox.push(create_of_type(MessageType::Entity)));
+        assert_eq!(outbox.tiers[Priority::Low as usize].len(), 1);
+
+        while outbox.len_bytes() + filler.encoded_len() <= OUTBOX_HARD_LIMIT {
+            assert!(outbox.push(filler.clone()));
+        }
+
+        assert!(!outbox.push(filler));
+        assert!(outbox.is_overflowed());
+        assert!(outbox.is_empty());
+    }
+}
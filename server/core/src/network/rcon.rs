@@ -0,0 +1,370 @@
+use std::{
+    io::{self, Read, Write},
+    net::{IpAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use actix::Addr;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use log::{info, warn};
+use serde::Deserialize;
+
+use hashbrown::HashMap;
+
+use crate::constants::{
+    MAX_RCON_BODY_SIZE, MAX_RCON_CONNECTIONS, MAX_RCON_FAILED_LOGINS, RCON_COMMAND_TIMEOUT,
+    RCON_FAILED_LOGIN_DELAY, RCON_IDLE_TIMEOUT, RCON_LOCKOUT,
+};
+
+use super::message::ConsoleCommand;
+use super::server::WsServer;
+
+const SERVERDATA_AUTH: i32 = 3;
+const SERVERDATA_AUTH_RESPONSE: i32 = 2;
+const SERVERDATA_EXECCOMMAND: i32 = 2;
+const SERVERDATA_RESPONSE_VALUE: i32 = 0;
+
+/// Settings of the remote console, set by `rcon` in `server.json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RconConfig {
+    pub address: String,
+    pub password: String,
+    /// World commands run in unless they start with `@<world>`, the first by name if not set
+    #[serde(default)]
+    pub world: Option<String>,
+}
+
+/// A packet of the Source RCON protocol, which server hosting panels speak
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    pub id: i32,
+    pub kind: i32,
+    pub body: String,
+}
+
+impl Packet {
+    pub fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let size = reader.read_i32::<LittleEndian>()?;
+
+        // id, kind and the two terminating nulls around a body that isn't too long
+        if size < 10 || size as usize > MAX_RCON_BODY_SIZE + 10 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid RCON packet size {}", size),
+            ));
+        }
+
+        let id = reader.read_i32::<LittleEndian>()?;
+        let kind = reader.read_i32::<LittleEndian>()?;
+
+        let mut body = vec![0; size as usize - 8];
+        reader.read_exact(&mut body)?;
+
+        let end = body
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(body.len());
+
+        Ok(Self {
+            id,
+            kind,
+            body: String::from_utf8_lossy(&body[..end]).into_owned(),
+        })
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let body = self.body.as_bytes();
+
+        let mut bytes = Vec::with_capacity(body.len() + 14);
+        bytes.write_i32::<LittleEndian>(body.len() as i32 + 10)?;
+        bytes.write_i32::<LittleEndian>(self.id)?;
+        bytes.write_i32::<LittleEndian>(self.kind)?;
+        bytes.extend_from_slice(body);
+        bytes.extend_from_slice(&[0, 0]);
+
+        writer.write_all(&bytes)
+    }
+}
+
+/// Split a response into bodies that fit in a packet each, between characters
+fn split_body(text: &str) -> Vec<&str> {
+    let mut bodies = vec![];
+    let mut rest = text;
+
+    while rest.len() > MAX_RCON_BODY_SIZE {
+        let mut end = MAX_RCON_BODY_SIZE;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let (body, after) = rest.split_at(end);
+        bodies.push(body);
+        rest = after;
+    }
+
+    bodies.push(rest);
+    bodies
+}
+
+/// Compare passwords in time that doesn't depend on where they differ
+fn passwords_match(given: &str, password: &str) -> bool {
+    given.len() == password.len()
+        && given
+            .bytes()
+            .zip(password.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Failed logins by address, locking out addresses guessing at the password
+#[derive(Debug, Default)]
+pub struct Lockouts {
+    failures: HashMap<IpAddr, (u32, Instant)>,
+}
+
+impl Lockouts {
+    /// Whether an address failed to log in too many times lately
+    pub fn is_locked(&mut self, address: &IpAddr, now: Instant) -> bool {
+        let lockout = Duration::from_secs(RCON_LOCKOUT);
+        self.failures
+            .retain(|_, (_, last)| now.duration_since(*last) < lockout);
+
+        self.failures
+            .get(address)
+            .map_or(false, |(count, _)| *count >= MAX_RCON_FAILED_LOGINS)
+    }
+
+    /// Count a failed login, the lockout starting over from the latest one
+    pub fn fail(&mut self, address: IpAddr, now: Instant) {
+        let (count, last) = self.failures.entry(address).or_insert((0, now));
+        *count += 1;
+        *last = now;
+    }
+
+    /// Forget the failures of an address once it logs in
+    pub fn succeed(&mut self, address: &IpAddr) {
+        self.failures.remove(address);
+    }
+}
+
+/// Listen for remote consoles on a thread of their own, serving each connection on its own
+/// thread as well, and run the commands they send on the game server
+///
+/// Only a few consoles are served at once, and addresses failing to log in too many times are
+/// turned away for a while.
+pub fn start(config: RconConfig, server: Addr<WsServer>) -> io::Result<()> {
+    if config.password.is_empty() {
+        warn!("Remote console has no password, not starting it.");
+        return Ok(());
+    }
+
+    let listener = TcpListener::bind(&config.address)?;
+    info!("Remote console listening on {}", config.address);
+
+    let connections = Arc::new(AtomicUsize::new(0));
+    let lockouts = Arc::new(Mutex::new(Lockouts::default()));
+
+    thread::Builder::new()
+        .name("rcon".to_owned())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let peer = match stream.peer_addr() {
+                    Ok(peer) => peer,
+                    Err(_) => continue,
+                };
+
+                if lockouts
+                    .lock()
+                    .unwrap()
+                    .is_locked(&peer.ip(), Instant::now())
+                {
+                    warn!("Remote console {} is locked out, turning it away.", peer);
+                    continue;
+                }
+
+                if connections.fetch_add(1, Ordering::SeqCst) >= MAX_RCON_CONNECTIONS {
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                    warn!("Too many remote consoles, turning {} away.", peer);
+                    continue;
+                }
+
+                let config = config.clone();
+                let server = server.clone();
+                let connections = connections.clone();
+                let lockouts = lockouts.clone();
+
+                thread::spawn(move || {
+                    if let Err(e) = serve(stream, peer.ip(), &config, &server, &lockouts) {
+                        if e.kind() != io::ErrorKind::UnexpectedEof {
+                            warn!("Remote console {} disconnected: {}", peer, e);
+                        }
+                    }
+
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        })?;
+
+    Ok(())
+}
+
+/// Answer a remote console until it disconnects, goes quiet or fails to log in
+fn serve(
+    mut stream: TcpStream,
+    address: IpAddr,
+    config: &RconConfig,
+    server: &Addr<WsServer>,
+    lockouts: &Mutex<Lockouts>,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(RCON_IDLE_TIMEOUT)))?;
+
+    let mut authenticated = false;
+
+    loop {
+        let packet = Packet::read(&mut stream)?;
+
+        match packet.kind {
+            SERVERDATA_AUTH => {
+                authenticated = passwords_match(&packet.body, &config.password);
+
+                if authenticated {
+                    lockouts.lock().unwrap().succeed(&address);
+                } else {
+                    lockouts.lock().unwrap().fail(address, Instant::now());
+                    thread::sleep(Duration::from_secs(RCON_FAILED_LOGIN_DELAY));
+                }
+
+                // like Source servers, an empty response comes before the verdict
+                Packet {
+                    id: packet.id,
+                    kind: SERVERDATA_RESPONSE_VALUE,
+                    body: String::new(),
+                }
+                .write(&mut stream)?;
+
+                Packet {
+                    id: if authenticated { packet.id } else { -1 },
+                    kind: SERVERDATA_AUTH_RESPONSE,
+                    body: String::new(),
+                }
+                .write(&mut stream)?;
+
+                if !authenticated {
+                    warn!("Remote console {} failed to log in.", address);
+                    return Ok(());
+                }
+            }
+            SERVERDATA_EXECCOMMAND if authenticated => {
+                info!("Remote console: {}", packet.body);
+
+                let output = run_command(&packet.body, config, server);
+
+                for body in split_body(&output) {
+                    Packet {
+                        id: packet.id,
+                        kind: SERVERDATA_RESPONSE_VALUE,
+                        body: body.to_owned(),
+                    }
+                    .write(&mut stream)?;
+                }
+            }
+            // clients send these after a command to tell where a split response ends
+            SERVERDATA_RESPONSE_VALUE if authenticated => {
+                Packet {
+                    id: packet.id,
+                    kind: SERVERDATA_RESPONSE_VALUE,
+                    body: String::new(),
+                }
+                .write(&mut stream)?;
+            }
+            _ => return Ok(()),
+        }
+    }
+}
+
+/// Run a command on the game server and wait for what it replied, in a world named by an
+/// `@<world>` prefix or the configured one
+fn run_command(command: &str, config: &RconConfig, server: &Addr<WsServer>) -> String {
+    let (world_name, command) = match command.strip_prefix('@') {
+        Some(rest) => {
+            let (world, command) = rest.split_once(' ').unwrap_or((rest, ""));
+            (Some(world.to_owned()), command)
+        }
+        None => (config.world.to_owned(), command),
+    };
+
+    let (reply, replies) = crossbeam_channel::bounded(1);
+
+    server.do_send(ConsoleCommand {
+        world_name,
+        command: command.to_owned(),
+        reply,
+    });
+
+    match replies.recv_timeout(Duration::from_secs(RCON_COMMAND_TIMEOUT)) {
+        Ok(lines) => lines.join("\n"),
+        Err(_) => "The server didn't answer in time.".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packet_works() {
+        let packet = Packet {
+            id: 7,
+            kind: SERVERDATA_EXECCOMMAND,
+            body: "list".to_owned(),
+        };
+
+        let mut bytes = vec![];
+        packet.write(&mut bytes).unwrap();
+        assert_eq!(&bytes[..4], &14i32.to_le_bytes());
+        assert_eq!(Packet::read(&mut bytes.as_slice()).unwrap(), packet);
+
+        let mut oversized = vec![];
+        oversized.extend_from_slice(&i32::MAX.to_le_bytes());
+        assert!(Packet::read(&mut oversized.as_slice()).is_err());
+
+        let long = "é".repeat(MAX_RCON_BODY_SIZE);
+        let bodies = split_body(&long);
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies.concat(), long);
+
+        assert!(passwords_match("hunter2", "hunter2"));
+        assert!(!passwords_match("hunter3", "hunter2"));
+        assert!(!passwords_match("hunter", "hunter2"));
+    }
+
+    #[test]
+    fn lockouts_work() {
+        let mut lockouts = Lockouts::default();
+        let address = IpAddr::from([127, 0, 0, 1]);
+        let other = IpAddr::from([127, 0, 0, 2]);
+        let now = Instant::now();
+
+        for _ in 0..MAX_RCON_FAILED_LOGINS {
+            assert!(!lockouts.is_locked(&address, now));
+            lockouts.fail(address, now);
+        }
+
+        assert!(lockouts.is_locked(&address, now));
+        assert!(!lockouts.is_locked(&other, now));
+
+        // locked out until a while after the last failure
+        let later = now + Duration::from_secs(RCON_LOCKOUT);
+        assert!(!lockouts.is_locked(&address, later));
+
+        lockouts.fail(other, later);
+        lockouts.succeed(&other);
+        assert!(!lockouts.is_locked(&other, later));
+    }
+}
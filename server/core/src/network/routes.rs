@@ -12,7 +12,9 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use super::{message, server::WsServer, session};
+use server_common::vec::Vec2;
+
+use super::{message, metrics::NETWORK_METRICS, server::WsServer, session};
 
 /// Main websocket route
 pub async fn ws_route(
@@ -68,6 +70,45 @@ pub async fn world(params: Query<HashMap<String, String>>) -> Result<HttpRespons
     Ok(HttpResponse::Ok().json(world_data))
 }
 
+/// Route to get a texture pack's atlas, packed when the server started
+#[get("/atlas/{pack}-atlas.png")]
+pub async fn atlas(pack: web::Path<String>) -> Result<HttpResponse> {
+    let atlas = WsServer::from_registry()
+        .send(message::GetAtlas(pack.into_inner()))
+        .await
+        .unwrap();
+
+    Ok(match atlas {
+        Some(png) => HttpResponse::Ok()
+            .content_type("image/png")
+            .body(png.as_ref().to_owned()),
+        None => HttpResponse::NotFound().finish(),
+    })
+}
+
+/// Route to get the top-down map tile of a world's chunk, for minimaps and web maps
+#[get("/map/{world}/{cx}/{cz}.png")]
+pub async fn map_tile(path: web::Path<(String, i32, i32)>) -> Result<HttpResponse> {
+    let (world_name, cx, cz) = path.into_inner();
+    let tile = WsServer::from_registry()
+        .send(message::GetMapTile(world_name, Vec2(cx, cz)))
+        .await
+        .unwrap();
+
+    Ok(match tile {
+        Some(png) => HttpResponse::Ok()
+            .content_type("image/png")
+            .body(png.as_ref().to_owned()),
+        None => HttpResponse::NotFound().finish(),
+    })
+}
+
+/// Route to get counters of what the server has sent out to clients
+#[get("/metrics")]
+pub async fn metrics() -> Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(NETWORK_METRICS.snapshot()))
+}
+
 /// Route to get time of world
 #[get("/time")]
 pub async fn time(params: Query<HashMap<String, String>>) -> Result<HttpResponse> {
@@ -2,23 +2,30 @@ use actix::prelude::*;
 use actix_broker::BrokerSubscribe;
 
 use hashbrown::HashMap;
+use log::warn;
 use std::time::Duration;
 
 use crate::engine::config::Configs;
 use crate::engine::entities::Entities;
+use crate::engine::portals::{PortalQueue, PortalTransfer, Traveler};
+use crate::engine::profiler::Profiler;
+use crate::engine::sounds::Sounds;
 use crate::engine::world::{WorldConfig, WorldMeta};
 
 use super::super::engine::{chunks::Chunks, clock::Clock, players::Players, world::World};
 
+use super::auth::{Auth, AuthError, Identity};
 use super::message::{
-    FullWorldData, GetWorld, JoinWorld, LeaveWorld, ListWorldNames, ListWorlds, Noop,
-    PlayerMessage, SimpleWorldData,
+    Authenticate, ConsoleCommand, FullWorldData, GetAtlas, GetMapTile, GetWorld, JoinWorld,
+    LeaveWorld, ListWorldNames, ListWorlds, Noop, PlayerLatency, PlayerMessage, SimpleWorldData,
+    SwitchWorld,
 };
-use super::models::{messages, messages::message::Type as MessageType};
 
 #[derive(Default)]
 pub struct WsServer {
     worlds: HashMap<String, World>,
+    sessions: HashMap<usize, Recipient<SwitchWorld>>,
+    auth: Auth,
 }
 
 impl WsServer {
@@ -26,9 +33,11 @@ impl WsServer {
         // Loading worlds from `worlds.json`
         let mut worlds: HashMap<String, World> = HashMap::new();
         let (configs, registry) = Configs::load_worlds("assets/metadata/worlds.json");
+        let profiler = Profiler::install();
 
         configs.into_iter().for_each(|(_, (meta, config))| {
-            let mut new_world = World::new(meta, config, registry.to_owned());
+            let mut new_world = World::new(meta, config, registry.clone());
+            new_world.ecs_mut().insert(profiler.clone());
             new_world.preload();
             worlds.insert(new_world.name.to_owned(), new_world);
         });
@@ -50,12 +59,55 @@ impl WsServer {
             intervals.push(
                 ctx.run_interval(Duration::from_millis(tick_rate), move |act, _ctx| {
                     act.worlds.get_mut(&name).unwrap().tick();
+                    act.transfer_portals(&name);
                 }),
             );
         });
 
         intervals
     }
+
+    /// Move everything that went through a portal in a world over to the linked world
+    fn transfer_portals(&mut self, name: &str) {
+        let transfers = self
+            .worlds
+            .get_mut(name)
+            .unwrap()
+            .write_resource::<PortalQueue>()
+            .drain(..)
+            .collect::<Vec<_>>();
+
+        for PortalTransfer {
+            traveler,
+            world: world_name,
+            position,
+        } in transfers
+        {
+            let world = match self.worlds.get_mut(&world_name) {
+                Some(world) => world,
+                None => {
+                    warn!(
+                        "Portal in \"{}\" leads to unknown world \"{}\".",
+                        name, world_name
+                    );
+                    continue;
+                }
+            };
+
+            let spawn = world.link_portal(&position);
+
+            match traveler {
+                Traveler::Player(id) => {
+                    if let Some(session) = self.sessions.get(&id) {
+                        session.do_send(SwitchWorld { world_name, spawn }).ok();
+                    }
+                }
+                Traveler::Entity(etype) => {
+                    world.spawn_entity(&etype, &spawn);
+                }
+            }
+        }
+    }
 }
 
 impl Actor for WsServer {
@@ -74,17 +126,30 @@ impl Handler<JoinWorld> for WsServer {
     fn handle(&mut self, msg: JoinWorld, _ctx: &mut Self::Context) -> Self::Result {
         let JoinWorld {
             world_name,
-            player_name,
+            player_identity,
             player_addr,
+            player_outbox,
+            session_addr,
+            spawn,
         } = msg;
 
         let world = self.worlds.get_mut(&world_name).expect("World not found!");
-        let result = world.add_player(None, player_name, player_addr);
+        let result = world.add_player(None, player_identity, player_addr, player_outbox, spawn);
+
+        self.sessions.insert(result.id, session_addr);
 
         MessageResult(result)
     }
 }
 
+impl Handler<Authenticate> for WsServer {
+    type Result = Result<Identity, AuthError>;
+
+    fn handle(&mut self, msg: Authenticate, _ctx: &mut Self::Context) -> Self::Result {
+        self.auth.authenticate(msg.token.as_deref())
+    }
+}
+
 impl Handler<LeaveWorld> for WsServer {
     type Result = ();
 
@@ -92,6 +157,8 @@ impl Handler<LeaveWorld> for WsServer {
         if let Some(world) = self.worlds.get_mut(&msg.world_name) {
             world.remove_player(&msg.player_id);
         }
+
+        self.sessions.remove(&msg.player_id);
     }
 }
 
@@ -113,20 +180,40 @@ impl Handler<PlayerMessage> for WsServer {
             raw,
         } = msg;
 
-        let msg_type = messages::Message::r#type(&raw);
-        let world = self.worlds.get_mut(&world_name).unwrap();
+        if let Some(world) = self.worlds.get_mut(&world_name) {
+            world.on_message(player_id, raw);
+        }
+    }
+}
+
+impl Handler<PlayerLatency> for WsServer {
+    type Result = ();
 
-        match msg_type {
-            MessageType::Request => world.on_chunk_request(player_id, raw),
-            MessageType::Config => world.on_config(player_id, raw),
-            MessageType::Update => world.on_update(player_id, raw),
-            MessageType::Peer => world.on_peer(player_id, raw),
-            MessageType::Message => world.on_chat_message(player_id, raw),
-            _ => {}
+    fn handle(&mut self, msg: PlayerLatency, _ctx: &mut Self::Context) {
+        if let Some(world) = self.worlds.get_mut(&msg.world_name) {
+            world.set_latency(msg.player_id, msg.latency);
         }
     }
 }
 
+impl Handler<ConsoleCommand> for WsServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: ConsoleCommand, _ctx: &mut Self::Context) {
+        let world_name = msg
+            .world_name
+            .or_else(|| self.worlds.keys().min().cloned())
+            .unwrap_or_default();
+
+        let lines = match self.worlds.get_mut(&world_name) {
+            Some(world) => world.run_console_command(&msg.command),
+            None => vec![format!("No world named \"{}\".", world_name)],
+        };
+
+        msg.reply.send(lines).ok();
+    }
+}
+
 impl Handler<Noop> for WsServer {
     type Result = ();
 
@@ -168,6 +255,7 @@ impl Handler<GetWorld> for WsServer {
         let clock = world.read_resource::<Clock>();
         let chunks = world.read_resource::<Chunks>();
         let entities = world.read_resource::<Entities>();
+        let sounds = world.read_resource::<Sounds>();
         let meta = world.read_resource::<WorldMeta>();
 
         let config = chunks.config.clone();
@@ -180,6 +268,7 @@ impl Handler<GetWorld> for WsServer {
             max_light_level: config.max_light_level,
             name: world.name.to_owned(),
             render_radius: config.render_radius,
+            max_horizon_radius: config.max_horizon_radius,
             save: config.save,
             sub_chunks: config.sub_chunks,
             tick_speed: clock.tick_speed,
@@ -187,6 +276,8 @@ impl Handler<GetWorld> for WsServer {
             blocks: registry.blocks.to_owned(),
             ranges: registry.ranges.to_owned(),
             entities: entities.get_all(),
+            sounds: sounds.get_all(),
+            border: config.border.to_owned(),
             uv_side_count: registry.uv_side_count,
             uv_texture_size: registry.uv_texture_size,
             packs: meta.packs.to_owned(),
@@ -194,8 +285,36 @@ impl Handler<GetWorld> for WsServer {
     }
 }
 
+impl Handler<GetAtlas> for WsServer {
+    type Result = MessageResult<GetAtlas>;
+
+    fn handle(&mut self, msg: GetAtlas, _ctx: &mut Self::Context) -> Self::Result {
+        // every world shares the same registry
+        let atlas = self.worlds.values().next().and_then(|world| {
+            let chunks = world.read_resource::<Chunks>();
+            chunks.registry.atlases.get(&msg.0).cloned()
+        });
+
+        MessageResult(atlas)
+    }
+}
+
+impl Handler<GetMapTile> for WsServer {
+    type Result = MessageResult<GetMapTile>;
+
+    fn handle(&mut self, msg: GetMapTile, _ctx: &mut Self::Context) -> Self::Result {
+        let tile = self
+            .worlds
+            .get_mut(&msg.0)
+            .and_then(|world| world.map_tile(&msg.1));
+
+        MessageResult(tile)
+    }
+}
+
 impl SystemService for WsServer {
     fn service_started(&mut self, ctx: &mut Context<Self>) {
+        self.auth = Auth::load("assets/metadata/auth.json");
         self.load_worlds();
         self.start_worlds(ctx);
     }
@@ -1,16 +1,30 @@
 use libflate::zlib::Encoder;
 
-use std::io::Write;
+use std::{io::Write, sync::Arc, time::Duration};
 
 use actix::prelude::*;
 use actix_broker::BrokerIssue;
 use actix_web_actors::ws;
+use log::warn;
+use uuid::Uuid;
 
-use super::super::network::models::{create_of_type, encode_message, messages};
+use server_common::vec::Vec3;
 
-use super::message::{self, PlayerMessage};
-use super::message::{JoinWorld, LeaveWorld};
+use super::super::constants::{HANDSHAKE_TIMEOUT, KEEPALIVE_INTERVAL, OUTBOX_FLUSH_BUDGET};
+use super::super::network::models::{
+    create_batch_message, create_of_type, encode_message, messages,
+};
+
+use super::auth::Identity;
+use super::handshake::{
+    compression_threshold, create_handshake_message, negotiate, Capability, HandshakeError,
+};
+use super::keepalive::Keepalive;
+use super::message::{Authenticate, Flush, JoinWorld, LeaveWorld, SwitchWorld};
+use super::message::{PlayerLatency, PlayerMessage};
+use super::metrics::NETWORK_METRICS;
 use super::models;
+use super::outbox::SharedOutbox;
 use super::server::WsServer;
 
 #[derive(Debug, Default)]
@@ -21,10 +35,24 @@ pub struct WsSession {
     pub world_name: String,
     // name in world
     pub name: Option<String>,
+    // messages waiting to be written, filled by the world
+    pub outbox: SharedOutbox,
+    // whether the client's handshake has been accepted
+    pub handshaken: bool,
+    // protocol capabilities agreed on in the handshake
+    pub capabilities: Vec<Capability>,
+    // bytes past which frames get compressed, agreed on in the handshake
+    pub compression_threshold: usize,
+    // persistent identity the player logged in as, None if anonymous
+    pub uuid: Option<Uuid>,
+    // whether the player logged in as an admin
+    pub admin: bool,
+    // when the client was last heard from, and how long a round trip to it takes
+    pub keepalive: Keepalive,
 }
 
 impl WsSession {
-    pub fn join_world(&self, ctx: &mut ws::WebsocketContext<Self>) {
+    pub fn join_world(&self, ctx: &mut ws::WebsocketContext<Self>, spawn: Option<Vec3<f32>>) {
         let world_name = self.world_name.to_owned();
 
         // First send a leave message for the current room
@@ -38,8 +66,15 @@ impl WsSession {
 
         let join_msg = JoinWorld {
             world_name: world_name.to_owned(),
-            player_name: self.name.clone(),
+            player_identity: Identity {
+                uuid: self.uuid,
+                name: self.name.clone(),
+                admin: self.admin,
+            },
             player_addr: ctx.address().recipient(),
+            player_outbox: Arc::clone(&self.outbox),
+            session_addr: ctx.address().recipient(),
+            spawn,
         };
 
         WsServer::from_registry()
@@ -58,26 +93,29 @@ impl WsSession {
                         r#"
                     {{
                         "id": "{}",
+                        "world": "{}",
                         "time": {},
                         "tickSpeed": {},
                         "spawn": [{}, {}, {}],
-                        "passables": {}
+                        "passables": {},
+                        "gameMode": "{}"
                     }}
                     "#,
                         result.id,
+                        act.world_name,
                         result.time,
                         result.tick_speed,
                         result.spawn[0],
                         result.spawn[1],
                         result.spawn[2],
-                        format!("[{}]", passables)
+                        format!("[{}]", passables),
+                        result.game_mode
                     );
 
                     let mut message = create_of_type(messages::message::Type::Init);
                     message.json = data;
-                    let encoded = encode_message(&message);
 
-                    ctx.binary(encoded);
+                    act.write(&message, ctx);
                 }
 
                 fut::ready(())
@@ -85,6 +123,167 @@ impl WsSession {
             .wait(ctx);
     }
 
+    /// Write out the most urgent queued messages, yielding to the socket before writing more.
+    ///
+    /// The session is only polled while the socket keeps up, so a slow client leaves its
+    /// messages in the outbox, where they're prioritized and bounded, instead of buffered here.
+    fn flush(&mut self, ctx: &mut ws::WebsocketContext<Self>) {
+        let (messages, overflowed, remaining) = {
+            let mut outbox = self.outbox.lock().unwrap();
+            let messages = outbox.take(OUTBOX_FLUSH_BUDGET);
+            (messages, outbox.is_overflowed(), !outbox.is_empty())
+        };
+
+        if overflowed {
+            warn!(
+                "Disconnecting player {}, too far behind on messages.",
+                self.id
+            );
+
+            ctx.close(Some(ws::CloseReason {
+                code: ws::CloseCode::Policy,
+                description: Some("Too far behind on messages".to_owned()),
+            }));
+            ctx.stop();
+            return;
+        }
+
+        self.write_all(messages, ctx);
+
+        if remaining {
+            ctx.run_later(Duration::default(), |act, ctx| act.flush(ctx));
+        }
+    }
+
+    /// Write messages out in one frame if the client takes batches, or in a frame each if not
+    fn write_all(&self, messages: Vec<messages::Message>, ctx: &mut ws::WebsocketContext<Self>) {
+        if messages.len() > 1 && self.capabilities.contains(&Capability::Batch) {
+            let count = messages.len();
+            self.send(&create_batch_message(messages), count, ctx);
+        } else {
+            messages.iter().for_each(|message| self.write(message, ctx));
+        }
+    }
+
+    fn write(&self, message: &messages::Message, ctx: &mut ws::WebsocketContext<Self>) {
+        self.send(message, 1, ctx);
+    }
+
+    /// Encode a frame of `count` messages onto the socket, compressing it past the agreed
+    /// threshold if the client can inflate it
+    fn send(
+        &self,
+        message: &messages::Message,
+        count: usize,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        let encoded = encode_message(message);
+        let bytes = encoded.len();
+        let compressed =
+            bytes > self.compression_threshold && self.capabilities.contains(&Capability::Zlib);
+
+        let frame = if compressed {
+            let mut encoder = Encoder::new(Vec::new()).unwrap();
+            encoder.write_all(encoded.as_slice()).unwrap();
+            encoder.finish().into_result().unwrap()
+        } else {
+            encoded
+        };
+
+        NETWORK_METRICS.record(count, bytes, frame.len(), compressed);
+        ctx.binary(frame);
+    }
+
+    /// Accept or reject the client's handshake, joining the world once the client's
+    /// login token checks out
+    fn on_handshake(&mut self, message: messages::Message, ctx: &mut ws::WebsocketContext<Self>) {
+        let capabilities = match negotiate(&message) {
+            Ok(capabilities) => capabilities,
+            Err(err) => return self.reject(err, ctx),
+        };
+        let compression_threshold = compression_threshold(&message);
+
+        let token = message
+            .parse_json()
+            .ok()
+            .and_then(|json| json["token"].as_str().map(|t| t.to_owned()));
+
+        WsServer::from_registry()
+            .send(Authenticate { token })
+            .into_actor(self)
+            .then(move |result, act, ctx| {
+                match result {
+                    Ok(Ok(identity)) => {
+                        act.capabilities = capabilities;
+                        act.compression_threshold = compression_threshold;
+                        act.handshaken = true;
+                        act.uuid = identity.uuid;
+                        act.admin = identity.admin;
+
+                        if identity.name.is_some() {
+                            act.name = identity.name;
+                        }
+
+                        let handshake =
+                            create_handshake_message(&act.capabilities, act.compression_threshold);
+                        act.write(&handshake, ctx);
+                        act.join_world(ctx, None);
+                    }
+                    Ok(Err(err)) => act.reject(HandshakeError::Unauthorized(err), ctx),
+                    Err(_) => ctx.stop(),
+                }
+
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    /// Tell the client why it can't be served, then close the connection
+    fn reject(&self, err: HandshakeError, ctx: &mut ws::WebsocketContext<Self>) {
+        let reason = err.to_string();
+        warn!("Rejecting connection: {}", reason);
+
+        let mut message = create_of_type(messages::message::Type::Error);
+        message.text = reason.to_owned();
+        self.write(&message, ctx);
+
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Protocol,
+            description: Some(reason),
+        }));
+        ctx.stop();
+    }
+
+    /// Ping the client to measure its latency, closing the connection once it's stopped
+    /// answering
+    fn keepalive(&mut self, ctx: &mut ws::WebsocketContext<Self>) {
+        if self.keepalive.is_timed_out() {
+            warn!("Disconnecting player {}, timed out.", self.id);
+
+            ctx.close(Some(ws::CloseReason {
+                code: ws::CloseCode::Away,
+                description: Some("Timed out".to_owned()),
+            }));
+            ctx.stop();
+            return;
+        }
+
+        ctx.ping(&self.keepalive.ping());
+    }
+
+    /// Let the world know how long a round trip to the client takes
+    fn on_pong(&mut self, payload: &[u8]) {
+        if let Some(rtt) = self.keepalive.pong(payload) {
+            if self.handshaken {
+                WsServer::from_registry().do_send(PlayerLatency {
+                    player_id: self.id,
+                    world_name: self.world_name.to_owned(),
+                    latency: rtt.as_millis() as u32,
+                });
+            }
+        }
+    }
+
     fn on_request(&mut self, message: messages::Message) {
         WsServer::from_registry().do_send(PlayerMessage {
             player_id: self.id,
@@ -98,7 +297,16 @@ impl Actor for WsSession {
     type Context = ws::WebsocketContext<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
-        self.join_world(ctx);
+        // the world is only joined once the client's handshake is accepted
+        ctx.run_later(Duration::from_secs(HANDSHAKE_TIMEOUT), |act, ctx| {
+            if !act.handshaken {
+                act.reject(HandshakeError::TimedOut, ctx);
+            }
+        });
+
+        ctx.run_interval(Duration::from_secs(KEEPALIVE_INTERVAL), |act, ctx| {
+            act.keepalive(ctx)
+        });
     }
 
     fn stopped(&mut self, _ctx: &mut Self::Context) {
@@ -109,21 +317,28 @@ impl Actor for WsSession {
     }
 }
 
-impl Handler<message::Message> for WsSession {
+impl Handler<SwitchWorld> for WsSession {
     type Result = ();
 
-    fn handle(&mut self, msg: message::Message, ctx: &mut Self::Context) {
-        let message::Message(msg) = msg;
-        let encoded = encode_message(&msg);
+    fn handle(&mut self, msg: SwitchWorld, ctx: &mut Self::Context) {
+        self.issue_system_sync(
+            LeaveWorld {
+                world_name: self.world_name.clone(),
+                player_id: self.id,
+            },
+            ctx,
+        );
 
-        if encoded.len() > 1024 {
-            let mut encoder = Encoder::new(Vec::new()).unwrap();
-            encoder.write_all(encoded.as_slice()).unwrap();
-            let encoded = encoder.finish().into_result().unwrap();
-            ctx.binary(encoded);
-        } else {
-            ctx.binary(encoded);
-        }
+        self.world_name = msg.world_name;
+        self.join_world(ctx, Some(msg.spawn));
+    }
+}
+
+impl Handler<Flush> for WsSession {
+    type Result = ();
+
+    fn handle(&mut self, _: Flush, ctx: &mut Self::Context) {
+        self.flush(ctx);
     }
 }
 
@@ -137,11 +352,20 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
             Ok(msg) => msg,
         };
 
+        self.keepalive.heard();
+
         match msg {
             ws::Message::Binary(bytes) => {
                 let message = models::decode_message(&bytes.to_vec()).unwrap();
-                self.on_request(message);
+
+                if self.handshaken {
+                    self.on_request(message);
+                } else {
+                    self.on_handshake(message, ctx);
+                }
             }
+            ws::Message::Ping(bytes) => ctx.pong(&bytes),
+            ws::Message::Pong(bytes) => self.on_pong(&bytes),
             ws::Message::Close(reason) => {
                 ctx.close(reason);
                 ctx.stop();
@@ -0,0 +1,283 @@
+use hashbrown::HashMap;
+use rand::Rng;
+
+use specs::{
+    Builder, Entities, Entity, LazyUpdate, Read, ReadExpect, ReadStorage, System, WriteExpect,
+    WriteStorage,
+};
+
+use server_common::{aabb::Aabb, quaternion::Quaternion, vec::Vec3};
+use server_utils::convert::{map_voxel_to_chunk, map_world_to_voxel};
+
+use crate::{
+    comp::{
+        behavior::{Behavior, BehaviorState},
+        etype::EType,
+        id::Id,
+        inventory::Inventory,
+        rigidbody::RigidBody,
+    },
+    constants::{BREED_DISTANCE, FOLLOW_STOP_DISTANCE, MAX_ENTITIES_PER_CHUNK, WANDER_TIMEOUT},
+    engine::{
+        chunks::Chunks,
+        clock::Clock,
+        entities::Entities as Prototypes,
+        residency::ChunkResidency,
+        rng::WorldRng,
+        sounds::{SoundQueue, Sounds},
+    },
+};
+
+/// A snapshot of a mob taken before any behavior changes this tick
+struct MobSnapshot {
+    entity: Entity,
+    etype: String,
+    position: Vec3<f32>,
+    can_breed: bool,
+    breeding_with: Option<Entity>,
+}
+
+pub struct BehaviorSystem;
+
+impl<'a> System<'a> for BehaviorSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, LazyUpdate>,
+        ReadExpect<'a, Clock>,
+        ReadExpect<'a, Chunks>,
+        ReadExpect<'a, Prototypes>,
+        ReadExpect<'a, ChunkResidency>,
+        ReadExpect<'a, Sounds>,
+        ReadStorage<'a, Id>,
+        ReadStorage<'a, EType>,
+        ReadStorage<'a, Inventory>,
+        WriteStorage<'a, RigidBody>,
+        WriteStorage<'a, Behavior>,
+        WriteExpect<'a, SoundQueue>,
+        WriteExpect<'a, WorldRng>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (
+            entities,
+            lazy,
+            clock,
+            chunks,
+            prototypes,
+            residency,
+            sounds,
+            ids,
+            types,
+            inventories,
+            mut bodies,
+            mut behaviors,
+            mut sound_queue,
+            mut rng,
+        ) = data;
+
+        let delta = clock.delta;
+        let dimension = chunks.config.dimension as f32;
+
+        // players along with the name of the block they're holding
+        let holders = (&*entities, &ids, &bodies, &inventories)
+            .join()
+            .filter_map(|(ent, _, body, inventory)| {
+                let held = inventory.held()?;
+                let name = chunks.registry.get_block_by_id(held.id).name.to_owned();
+                Some((ent, body.get_position(), name))
+            })
+            .collect::<Vec<_>>();
+
+        let mobs = (&*entities, &types, &bodies, &behaviors)
+            .join()
+            .map(|(ent, etype, body, behavior)| MobSnapshot {
+                entity: ent,
+                etype: etype.0.to_owned(),
+                position: body.get_position(),
+                can_breed: behavior.can_breed(),
+                breeding_with: match behavior.state {
+                    BehaviorState::Breed(partner) => Some(partner),
+                    _ => None,
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let mut births = vec![];
+
+        for (ent, etype, body, behavior) in (&*entities, &types, &mut bodies, &mut behaviors).join()
+        {
+            let position = body.get_position();
+
+            behavior.timer -= delta;
+            behavior.love = (behavior.love - delta).max(0.0);
+            behavior.cooldown = (behavior.cooldown - delta).max(0.0);
+
+            if behavior.is_child() {
+                behavior.growth -= delta;
+
+                if !behavior.is_child() {
+                    grow_up(body, behavior.options.child_scale);
+                }
+            }
+
+            match behavior.state.clone() {
+                BehaviorState::Flee => {
+                    if behavior.timer > 0.0 {
+                        continue;
+                    }
+
+                    behavior.set_state(BehaviorState::Idle, 0.0);
+                }
+                BehaviorState::Breed(partner) => {
+                    let partner = mobs
+                        .iter()
+                        .find(|m| m.entity == partner && m.can_breed && behavior.can_breed());
+
+                    if let Some(partner) = partner {
+                        let mutual = partner.breeding_with == Some(ent);
+
+                        if mutual
+                            && distance(&position, &partner.position) <= BREED_DISTANCE * dimension
+                        {
+                            // only one of the two parents gives birth
+                            if ent.id() < partner.entity.id() {
+                                let middle = position.add(&partner.position).scale(0.5);
+                                sound_queue.extend(sounds.event("mob.breed", middle.clone()));
+                                births.push((etype.0.to_owned(), middle));
+                            }
+
+                            behavior.bred();
+                        } else {
+                            behavior.goal = Some(partner.position.clone());
+                        }
+
+                        continue;
+                    }
+
+                    behavior.set_state(BehaviorState::Idle, 0.0);
+                }
+                _ => {}
+            }
+
+            let follow_distance = behavior.options.follow_distance * dimension;
+
+            if behavior.can_breed() {
+                let partner = mobs
+                    .iter()
+                    .filter(|m| m.entity != ent && m.can_breed && m.etype == etype.0)
+                    .map(|m| (m.entity, distance(&position, &m.position)))
+                    .filter(|&(_, d)| d <= follow_distance)
+                    .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+                if let Some((partner, _)) = partner {
+                    behavior.set_state(BehaviorState::Breed(partner), 0.0);
+                    continue;
+                }
+            }
+
+            let holder = holders
+                .iter()
+                .filter(|(_, _, name)| behavior.is_food(name))
+                .map(|(player, pos, _)| (*player, pos, distance(&position, pos)))
+                .filter(|&(_, _, d)| d <= follow_distance)
+                .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+
+            if let Some((player, pos, d)) = holder {
+                if behavior.state != BehaviorState::Follow(player) {
+                    behavior.set_state(BehaviorState::Follow(player), 0.0);
+                }
+
+                behavior.goal = if d > FOLLOW_STOP_DISTANCE * dimension {
+                    Some(pos.clone())
+                } else {
+                    None
+                };
+
+                continue;
+            }
+
+            match behavior.state {
+                BehaviorState::Wander => {
+                    let arrived = behavior
+                        .goal
+                        .as_ref()
+                        .map(|goal| {
+                            let mut diff = goal.sub(&position);
+                            diff.1 = 0.0;
+                            diff.len() < dimension
+                        })
+                        .unwrap_or(true);
+
+                    if arrived || behavior.timer <= 0.0 {
+                        let idle = rng.gen::<f32>() * behavior.options.idle_time;
+                        behavior.set_state(BehaviorState::Idle, idle);
+                    }
+                }
+                BehaviorState::Idle => {
+                    if behavior.timer <= 0.0 {
+                        let r = behavior.options.wander_radius;
+                        let offset = Vec3(
+                            rng.gen_range(-r..=r) as f32,
+                            0.0,
+                            rng.gen_range(-r..=r) as f32,
+                        );
+
+                        behavior.set_state(BehaviorState::Wander, WANDER_TIMEOUT);
+                        behavior.goal = Some(position.add(&offset.scale(dimension)));
+                    }
+                }
+                // stopped following, or partner lost
+                _ => behavior.set_state(BehaviorState::Idle, 0.0),
+            }
+        }
+
+        // children born this tick, by the chunk they're born in
+        let mut born = HashMap::new();
+
+        births.into_iter().for_each(|(etype, position)| {
+            let Vec3(vx, vy, vz) =
+                map_world_to_voxel(position.0, position.1, position.2, chunks.config.dimension);
+            let coords = map_voxel_to_chunk(vx, vy, vz, chunks.config.chunk_size);
+            let count = born.entry(coords.clone()).or_insert(0);
+
+            if residency.count(&coords) + *count >= MAX_ENTITIES_PER_CHUNK {
+                return;
+            }
+
+            *count += 1;
+
+            if let Some(prototype) = prototypes.get_prototype(&etype) {
+                Prototypes::build_entity(
+                    lazy.create_entity(&entities),
+                    prototype,
+                    &etype,
+                    &position,
+                    &Quaternion(0.0, 0.0, 0.0, 0.0),
+                    true,
+                )
+                .build();
+            }
+        });
+    }
+}
+
+fn distance(a: &Vec3<f32>, b: &Vec3<f32>) -> f32 {
+    a.sub(b).len()
+}
+
+/// Scale a child's body back up to its full size, keeping its feet in place
+fn grow_up(body: &mut RigidBody, child_scale: f32) {
+    if child_scale <= 0.0 {
+        return;
+    }
+
+    let scale = 1.0 / child_scale;
+    let position = body.get_position();
+
+    body.aabb = Aabb::new(&body.aabb.base, &body.aabb.vec.scale(scale));
+    body.head *= scale;
+    body.set_position(&position);
+}
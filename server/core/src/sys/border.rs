@@ -0,0 +1,72 @@
+use specs::{Entities, ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
+
+use server_common::vec::Vec3;
+
+use crate::{
+    comp::{id::Id, rigidbody::RigidBody},
+    engine::{
+        damage::{DamageEvent, DamageQueue},
+        teleport::Teleport,
+        world::{MessagesQueue, WorldConfig},
+    },
+};
+
+/// Pushes players and entities that went past the world border back in, damaging the
+/// ones that went too far.
+pub struct BorderSystem;
+
+impl<'a> System<'a> for BorderSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, WorldConfig>,
+        ReadStorage<'a, Id>,
+        WriteStorage<'a, RigidBody>,
+        WriteExpect<'a, DamageQueue>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (entities, config, ids, mut bodies, mut damages, mut messages) = data;
+
+        let border = match &config.border {
+            Some(border) => border,
+            None => return,
+        };
+
+        let dimension = config.dimension as f32;
+
+        for (ent, body) in (&*entities, &mut bodies).join() {
+            let Vec3(px, py, pz) = body.get_position();
+            let (x, z) = (px / dimension, pz / dimension);
+
+            let outside = border.distance_outside(x, z);
+
+            if outside <= 0.0 {
+                continue;
+            }
+
+            let (cx, cz) = border.clamp(x, z);
+            let pushed = Vec3(cx * dimension, py, cz * dimension);
+
+            if outside > border.buffer && border.damage > 0.0 {
+                damages.push(DamageEvent {
+                    target: ent,
+                    amount: border.damage,
+                    source: None,
+                    from: Vec3(px, py, pz),
+                    knockback: 0.0,
+                });
+            }
+
+            body.set_position(&pushed);
+
+            // players move themselves, so they have to be told to go back
+            let player = ids.get(ent).map(|id| id.0);
+            let message = Teleport::create_message(player, ent, &pushed, config.dimension);
+            messages.push((message, None, None, None));
+        }
+    }
+}
@@ -1,19 +1,39 @@
-use specs::{ReadExpect, ReadStorage, System, WriteStorage};
+use specs::{
+    Builder, Entities, LazyUpdate, Read, ReadExpect, ReadStorage, System, WriteExpect, WriteStorage,
+};
 
 use server_utils::convert::{map_voxel_to_chunk, map_world_to_voxel};
 
-use server_common::vec::Vec3;
+use server_common::{quaternion::Quaternion, vec::Vec3};
 
 use crate::{
-    comp::{curr_chunk::CurrChunk, rigidbody::RigidBody},
-    engine::world::WorldConfig,
+    comp::{
+        behavior::Behavior, curr_chunk::CurrChunk, etype::EType, health::Health, id::Id,
+        rigidbody::RigidBody,
+    },
+    engine::{
+        chunks::Chunks,
+        entities::{Entities as Prototypes, SuspendedEntity},
+        residency::ChunkResidency,
+        world::WorldConfig,
+    },
 };
 
 pub struct ChunkingSystem;
 
 impl<'a> System<'a> for ChunkingSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (
+        Entities<'a>,
+        Read<'a, LazyUpdate>,
         ReadExpect<'a, WorldConfig>,
+        ReadExpect<'a, Prototypes>,
+        WriteExpect<'a, Chunks>,
+        WriteExpect<'a, ChunkResidency>,
+        ReadStorage<'a, Id>,
+        ReadStorage<'a, EType>,
+        ReadStorage<'a, Behavior>,
+        ReadStorage<'a, Health>,
         ReadStorage<'a, RigidBody>,
         WriteStorage<'a, CurrChunk>,
     );
@@ -21,18 +41,104 @@ impl<'a> System<'a> for ChunkingSystem {
     fn run(&mut self, data: Self::SystemData) {
         use specs::Join;
 
-        let (configs, bodies, mut curr_chunks) = data;
+        let (
+            entities,
+            lazy,
+            configs,
+            prototypes,
+            mut chunks,
+            mut residency,
+            ids,
+            types,
+            behaviors,
+            healths,
+            bodies,
+            mut curr_chunks,
+        ) = data;
         let chunk_size = configs.chunk_size;
         let dimension = configs.dimension;
 
-        for (body, curr_chunk) in (&bodies, &mut curr_chunks).join() {
+        residency.retain(|ent| entities.is_alive(ent));
+
+        // items, orbs and falling blocks too, as they're kept still where chunks are missing
+        for (ent, body, curr_chunk) in (&*entities, &bodies, (&mut curr_chunks).maybe()).join() {
             let Vec3(px, py, pz) = body.get_position();
             let Vec3(vx, vy, vz) = map_world_to_voxel(px, py, pz, dimension);
             let new_chunk = map_voxel_to_chunk(vx, vy, vz, chunk_size);
 
-            if curr_chunk.diff(&new_chunk) {
-                curr_chunk.val = Some(new_chunk.clone());
-                curr_chunk.changed = true;
+            // always, as teleports move the current chunk along without the index
+            residency.insert(ent, &new_chunk);
+
+            if let Some(curr_chunk) = curr_chunk {
+                if curr_chunk.diff(&new_chunk) {
+                    curr_chunk.val = Some(new_chunk.clone());
+                    curr_chunk.changed = true;
+                }
+            }
+        }
+
+        // mobs are saved away along with the chunk they're in instead of walking on nothing,
+        // while the rest stays still until the chunk is back
+        let suspended = chunks
+            .evicted_coords()
+            .into_iter()
+            .map(|coords| {
+                let mobs = residency
+                    .in_chunk(&coords)
+                    .filter(|&&ent| ids.get(ent).is_none())
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                let suspended = mobs
+                    .into_iter()
+                    .filter_map(|ent| {
+                        let suspended = SuspendedEntity {
+                            etype: types.get(ent)?.0.to_owned(),
+                            position: bodies.get(ent)?.get_position(),
+                            child: behaviors.get(ent).map_or(false, |b| b.is_child()),
+                            health: healths.get(ent).map(|health| health.current),
+                        };
+
+                        residency.remove(ent);
+                        entities.delete(ent).ok();
+
+                        Some(suspended)
+                    })
+                    .collect::<Vec<_>>();
+
+                (coords, suspended)
+            })
+            .collect::<Vec<_>>();
+
+        chunks.save_evicted(suspended);
+
+        for suspended in chunks.take_restored() {
+            let SuspendedEntity {
+                etype,
+                position,
+                child,
+                health,
+            } = suspended;
+
+            if let Some(prototype) = prototypes.get_prototype(&etype) {
+                let builder = Prototypes::build_entity(
+                    lazy.create_entity(&entities),
+                    prototype,
+                    &etype,
+                    &position,
+                    &Quaternion(0.0, 0.0, 0.0, 0.0),
+                    child,
+                );
+
+                match (health, prototype.health) {
+                    (Some(current), Some(max)) => builder
+                        .with(Health {
+                            current,
+                            ..Health::new(max)
+                        })
+                        .build(),
+                    _ => builder.build(),
+                };
             }
         }
     }
@@ -0,0 +1,69 @@
+use specs::{ReadExpect, System, WriteExpect};
+
+use server_common::vec::Vec3;
+
+use crate::{
+    engine::{
+        chunks::Chunks,
+        circuits::{CircuitSchedule, Circuits},
+        clock::Clock,
+        world::MessagesQueue,
+    },
+    network::models::{create_message, MessageComponents, MessageType},
+};
+
+pub struct CircuitsSystem;
+
+impl<'a> System<'a> for CircuitsSystem {
+    type SystemData = (
+        ReadExpect<'a, Clock>,
+        WriteExpect<'a, Chunks>,
+        WriteExpect<'a, CircuitSchedule>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (clock, mut chunks, mut schedule, mut messages) = data;
+
+        if schedule.is_empty() {
+            return;
+        }
+
+        let mut updates = vec![];
+
+        for Vec3(vx, vy, vz) in schedule.due(clock.tick) {
+            let stage = match chunks.get_chunk_by_voxel(vx, vy, vz) {
+                Some(chunk) => chunk.get_voxel_stage(vx, vy, vz),
+                None => continue,
+            };
+
+            if !chunks.get_block_by_voxel(vx, vy, vz).consumes_power {
+                continue;
+            }
+
+            let powered = if Circuits::is_powered(&chunks, vx, vy, vz) {
+                1
+            } else {
+                0
+            };
+
+            if powered == stage {
+                continue;
+            }
+
+            chunks.set_voxel_stage_by_voxel(vx, vy, vz, powered);
+            chunks.mark_saving_from_voxel(vx, vy, vz);
+
+            updates.extend(chunks.get_update_protocol(vx, vy, vz));
+        }
+
+        if updates.is_empty() {
+            return;
+        }
+
+        let mut components = MessageComponents::default_for(MessageType::Update);
+        components.updates = Some(updates);
+
+        messages.push((create_message(components), None, None, None));
+    }
+}
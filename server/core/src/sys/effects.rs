@@ -0,0 +1,45 @@
+use specs::{ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
+
+use crate::{
+    comp::{effects::Effects, id::Id},
+    engine::{clock::Clock, world::MessagesQueue},
+    network::models::{create_of_type, MessageType},
+};
+
+/// Wears status effects off, letting players know of the effects they're under as they change
+pub struct EffectsSystem;
+
+impl<'a> System<'a> for EffectsSystem {
+    type SystemData = (
+        ReadExpect<'a, Clock>,
+        ReadStorage<'a, Id>,
+        WriteStorage<'a, Effects>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (clock, ids, mut effects, mut messages) = data;
+
+        for (effects, id) in (&mut effects, ids.maybe()).join() {
+            effects.tick(clock.delta);
+
+            if !effects.changed {
+                continue;
+            }
+
+            effects.changed = false;
+
+            if let Some(id) = id {
+                let mut message = create_of_type(MessageType::Info);
+                message.json = serde_json::json!({
+                    "effects": effects.list,
+                    "effectSpeed": effects.speed_multiplier(),
+                })
+                .to_string();
+                messages.push((message, Some(vec![id.0]), None, None));
+            }
+        }
+    }
+}
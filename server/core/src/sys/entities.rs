@@ -1,3 +1,5 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use server_utils::convert::map_world_to_voxel;
 use specs::{Entities, ReadExpect, ReadStorage, System, WriteExpect};
 
@@ -8,7 +10,11 @@ use crate::{
         curr_chunk::CurrChunk, etype::EType, rigidbody::RigidBody, target::Target,
         walk_towards::WalkTowards,
     },
-    engine::world::{MessagesQueue, WorldConfig},
+    engine::{
+        physics::PhysicsStep,
+        ticker::Ticker,
+        world::{MessagesQueue, WorldConfig},
+    },
     network::models::{create_message, EntityProtocol, MessageComponents, MessageType},
 };
 
@@ -19,6 +25,8 @@ impl<'a> System<'a> for EntitiesSystem {
     type SystemData = (
         Entities<'a>,
         ReadExpect<'a, WorldConfig>,
+        ReadExpect<'a, Ticker>,
+        ReadExpect<'a, PhysicsStep>,
         WriteExpect<'a, MessagesQueue>,
         ReadStorage<'a, EType>,
         ReadStorage<'a, RigidBody>,
@@ -30,11 +38,22 @@ impl<'a> System<'a> for EntitiesSystem {
     fn run(&mut self, data: Self::SystemData) {
         use specs::Join;
 
-        let (entities, configs, mut messages, types, bodies, curr_chunks, targets, walk_towards) =
-            data;
+        let (
+            entities,
+            configs,
+            ticker,
+            physics_step,
+            mut messages,
+            types,
+            bodies,
+            curr_chunks,
+            targets,
+            walk_towards,
+        ) = data;
 
         let dimension = configs.dimension;
 
+        let alpha = physics_step.alpha();
         let mut entity_updates = vec![];
 
         for (ent, etype, body, _curr_chunk, target, walk_toward) in (
@@ -47,7 +66,7 @@ impl<'a> System<'a> for EntitiesSystem {
         )
             .join()
         {
-            let Vec3(px, py, pz) = body.get_position();
+            let Vec3(px, py, pz) = body.get_interpolated_position(alpha);
 
             let look_target = target.get_position();
             let mut heading: Option<Vec3<f32>> = walk_toward.0.as_ref().map(|nodes| {
@@ -70,6 +89,10 @@ impl<'a> System<'a> for EntitiesSystem {
                 }
             }
 
+            // f32::signum would count not resting at all as resting on the positive side
+            let side = |r: f32| (r > 0.0) as i32 - (r < 0.0) as i32;
+            let &Vec3(rx, ry, rz) = &body.resting;
+
             entity_updates.push(EntityProtocol {
                 id: ent.id().to_string(),
                 r#type: etype.0.to_owned(),
@@ -78,6 +101,8 @@ impl<'a> System<'a> for EntitiesSystem {
                 px,
                 py,
                 pz,
+                velocity: body.velocity.clone(),
+                resting: Vec3(side(rx), side(ry), side(rz)),
             })
         }
 
@@ -85,6 +110,13 @@ impl<'a> System<'a> for EntitiesSystem {
             let mut components = MessageComponents::default_for(MessageType::Entity);
             components.entities = Some(entity_updates);
 
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Time went backwards?")
+                .as_secs_f64()
+                * 1000.0;
+            components.tick = Some((ticker.tick, timestamp));
+
             let msg = create_message(components);
             messages.push((msg, None, None, None));
         }
@@ -0,0 +1,88 @@
+use hashbrown::HashMap;
+
+use specs::{ReadExpect, ReadStorage, System, WriteExpect};
+
+use crate::{
+    comp::{
+        inventory::{Inventory, ItemStack},
+        rigidbody::RigidBody,
+    },
+    constants::EQUIPMENT_VIEW_DISTANCE,
+    engine::{
+        players::Players,
+        world::{MessagesQueue, WorldConfig},
+    },
+    network::models::{create_of_type, MessageType},
+};
+
+/// Lets players know what the players around them are wearing, as it changes or as they come
+/// close enough to see
+#[derive(Default)]
+pub struct EquipmentSystem {
+    /// What each player was last told a player nearby was wearing, keyed by (viewer, wearer)
+    sent: HashMap<(usize, usize), [Option<ItemStack>; 4]>,
+}
+
+impl<'a> System<'a> for EquipmentSystem {
+    type SystemData = (
+        ReadExpect<'a, WorldConfig>,
+        ReadExpect<'a, Players>,
+        ReadStorage<'a, RigidBody>,
+        ReadStorage<'a, Inventory>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (config, players, bodies, inventories, mut messages) = data;
+
+        let view_distance = EQUIPMENT_VIEW_DISTANCE * config.dimension as f32;
+
+        let wearers = players
+            .iter()
+            .filter_map(|(id, player)| {
+                match (bodies.get(player.entity), inventories.get(player.entity)) {
+                    (Some(body), Some(inventory)) => {
+                        Some((*id, body.get_position(), &inventory.equipment))
+                    }
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // forget what was sent to or about players out of sight, so it's sent again once back
+        self.sent.retain(|(viewer, wearer), _| {
+            match (
+                wearers.iter().find(|(id, ..)| id == viewer),
+                wearers.iter().find(|(id, ..)| id == wearer),
+            ) {
+                (Some((_, a, _)), Some((_, b, _))) => a.sub(b).len() <= view_distance,
+                _ => false,
+            }
+        });
+
+        for (viewer, position, _) in wearers.iter() {
+            for (wearer, other, equipment) in wearers.iter() {
+                if viewer == wearer || position.sub(other).len() > view_distance {
+                    continue;
+                }
+
+                if self.sent.get(&(*viewer, *wearer)) == Some(*equipment) {
+                    continue;
+                }
+
+                self.sent
+                    .insert((*viewer, *wearer), (*equipment).to_owned());
+
+                let mut message = create_of_type(MessageType::Info);
+                message.json = serde_json::json!({
+                    "equipment": {
+                        "player": wearer.to_string(),
+                        "items": equipment,
+                    },
+                })
+                .to_string();
+                messages.push((message, Some(vec![*viewer]), None, None));
+            }
+        }
+    }
+}
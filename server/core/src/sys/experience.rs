@@ -0,0 +1,127 @@
+use hashbrown::HashSet;
+
+use specs::{Entities, ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
+
+use crate::{
+    comp::{
+        experience::{Experience, ExperienceOrb},
+        health::Health,
+        rigidbody::RigidBody,
+    },
+    constants::{
+        EXPERIENCE_ORB_ATTRACT_DISTANCE, EXPERIENCE_ORB_DESPAWN_TIME,
+        EXPERIENCE_ORB_PICKUP_DISTANCE, EXPERIENCE_ORB_SPEED,
+    },
+    engine::{
+        clock::Clock,
+        gamemode::{GameMode, GameModes},
+        players::Players,
+        world::{MessagesQueue, WorldConfig},
+    },
+    network::models::create_experience_message,
+};
+
+/// Draws experience orbs toward the closest player around, who takes them in on reaching them
+pub struct ExperienceSystem;
+
+impl<'a> System<'a> for ExperienceSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Clock>,
+        ReadExpect<'a, WorldConfig>,
+        ReadExpect<'a, Players>,
+        ReadExpect<'a, GameModes>,
+        ReadStorage<'a, Health>,
+        WriteStorage<'a, RigidBody>,
+        WriteStorage<'a, ExperienceOrb>,
+        WriteStorage<'a, Experience>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (
+            entities,
+            clock,
+            config,
+            players,
+            game_modes,
+            healths,
+            mut bodies,
+            mut orbs,
+            mut experiences,
+            mut messages,
+        ) = data;
+
+        let dimension = config.dimension as f32;
+        let attract_distance = EXPERIENCE_ORB_ATTRACT_DISTANCE * dimension;
+        let pickup_distance = EXPERIENCE_ORB_PICKUP_DISTANCE * dimension;
+
+        // the dead and spectators don't take in experience
+        let collectors = players
+            .iter()
+            .filter(|(id, player)| {
+                game_modes.get(id).copied().unwrap_or_default() != GameMode::Spectator
+                    && !healths.get(player.entity).map_or(false, |h| h.is_dead())
+            })
+            .filter_map(|(id, player)| {
+                bodies
+                    .get(player.entity)
+                    .map(|body| (*id, player.entity, body.get_position()))
+            })
+            .collect::<Vec<_>>();
+
+        let mut changed = HashSet::new();
+
+        for (entity, orb, body) in (&entities, &mut orbs, &mut bodies).join() {
+            orb.age += clock.delta;
+
+            let position = body.get_position();
+
+            // fell out of the world, or lay around for too long
+            if position.1 < 0.0 || orb.age >= EXPERIENCE_ORB_DESPAWN_TIME {
+                entities.delete(entity).ok();
+                continue;
+            }
+
+            let closest = collectors
+                .iter()
+                .map(|(id, player, at)| (id, player, at.sub(&position)))
+                .filter(|(.., offset)| offset.len() <= attract_distance)
+                .min_by(|(.., a), (.., b)| a.len().partial_cmp(&b.len()).unwrap());
+
+            let (id, player, offset) = match closest {
+                Some(closest) => closest,
+                None => continue,
+            };
+
+            if offset.len() <= pickup_distance {
+                if let Some(experience) = experiences.get_mut(*player) {
+                    experience.add(orb.points);
+                    changed.insert(*id);
+                }
+
+                entities.delete(entity).ok();
+                continue;
+            }
+
+            body.velocity = offset.normalize().scale(EXPERIENCE_ORB_SPEED * dimension);
+        }
+
+        for id in changed {
+            if let Some(experience) = players
+                .get(&id)
+                .and_then(|player| experiences.get(player.entity))
+            {
+                messages.push((
+                    create_experience_message(experience),
+                    Some(vec![id]),
+                    None,
+                    None,
+                ));
+            }
+        }
+    }
+}
@@ -22,11 +22,11 @@ impl<'a> System<'a> for GenerationSystem {
         let (mut chunks, ids, radiuses, mut curr_chunks) = data;
         let chunk_size = chunks.config.chunk_size;
 
-        for (radius, curr_chunk, _) in (&radiuses, &mut curr_chunks, &ids).join() {
+        for (radius, curr_chunk, id) in (&radiuses, &mut curr_chunks, &ids).join() {
             if let Some(coords) = &curr_chunk.val {
                 if curr_chunk.changed {
                     let r = (radius.0 as f32 / chunk_size as f32).ceil() as i16;
-                    chunks.generate(coords, r, false);
+                    chunks.watch(id.0, coords, r);
                     curr_chunk.changed = false;
                 }
             }
@@ -0,0 +1,185 @@
+use specs::{Builder, Entities, LazyUpdate, Read, ReadExpect, ReadStorage, System, WriteExpect};
+
+use server_common::{aabb::Aabb, vec::Vec3};
+use server_utils::convert::map_world_to_voxel;
+
+use crate::{
+    comp::{dropped_item::DroppedItem, falling_block::FallingBlock, rigidbody::RigidBody},
+    constants::FALLING_BLOCK_SIZE,
+    engine::{
+        chunks::Chunks,
+        gravity::{Gravity, GravityQueue},
+        loot::LootTables,
+        remesh::RemeshQueue,
+        rng::WorldRng,
+        support::Supports,
+        world::MessagesQueue,
+    },
+    gen::blocks::BlockRotation,
+    network::models::{create_message, MessageComponents, MessageType},
+};
+
+/// Turns unsupported blocks with gravity into falling blocks, and places them back into the
+/// world once they land. Blocks that lost what they need next to them pop off as items.
+pub struct GravitySystem;
+
+impl<'a> System<'a> for GravitySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, LazyUpdate>,
+        WriteExpect<'a, Chunks>,
+        WriteExpect<'a, GravityQueue>,
+        WriteExpect<'a, RemeshQueue>,
+        WriteExpect<'a, MessagesQueue>,
+        WriteExpect<'a, WorldRng>,
+        ReadExpect<'a, LootTables>,
+        ReadStorage<'a, FallingBlock>,
+        ReadStorage<'a, RigidBody>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (
+            entities,
+            lazy,
+            mut chunks,
+            mut queue,
+            mut remeshes,
+            mut messages,
+            mut rng,
+            loot,
+            falling_blocks,
+            bodies,
+        ) = data;
+
+        let dimension = chunks.config.dimension as f32;
+        let &air = chunks.registry.get_id_by_name("Air");
+
+        let mut changed = vec![];
+
+        chunks.start_caching();
+
+        for (entity, block, body) in (&entities, &falling_blocks, &bodies).join() {
+            let Vec3(px, py, pz) = body.get_position();
+
+            // fell out of the world
+            if py < 0.0 {
+                entities.delete(entity).ok();
+                continue;
+            }
+
+            if body.resting.1 >= 0.0 {
+                continue;
+            }
+
+            let Vec3(vx, vy, vz) =
+                map_world_to_voxel(px, py + dimension * 0.5, pz, chunks.config.dimension);
+
+            // wait for the chunk it landed in to be there again
+            if chunks.get_chunk_by_voxel(vx, vy, vz).is_none() {
+                continue;
+            }
+
+            entities.delete(entity).ok();
+
+            if let Some(voxel) = Gravity::settle(&chunks, vx, vy, vz) {
+                let Vec3(vx, vy, vz) = voxel;
+                chunks.update(vx, vy, vz, block.id, block.rotation, block.y_rotation);
+
+                queue.push(voxel.clone());
+                changed.push(voxel);
+            }
+        }
+
+        for voxel in queue.take() {
+            let Vec3(vx, vy, vz) = voxel;
+
+            if Supports::is_unsupported(&chunks, vx, vy, vz) {
+                let id = chunks.get_voxel_by_voxel(vx, vy, vz);
+                let stage = chunks.get_voxel_stage_by_voxel(vx, vy, vz);
+
+                chunks.update(vx, vy, vz, air, 0, 0);
+
+                // what rested on it or hung off of it goes next, on the next tick
+                queue.push(Vec3(vx, vy + 1, vz));
+                for &(ox, oz) in [(1, 0), (-1, 0), (0, 1), (0, -1)].iter() {
+                    queue.push(Vec3(vx + ox, vy, vz + oz));
+                }
+                changed.push(voxel);
+
+                let position = Vec3(
+                    (vx as f32 + 0.5) * dimension,
+                    vy as f32 * dimension,
+                    (vz as f32 + 0.5) * dimension,
+                );
+
+                for stack in Supports::drops(&chunks, &loot, id, stage, &mut *rng) {
+                    DroppedItem::build_entity(
+                        lazy.create_entity(&entities),
+                        stack,
+                        &position,
+                        dimension,
+                    )
+                    .build();
+                }
+
+                continue;
+            }
+
+            if !Gravity::is_unsupported(&chunks, vx, vy, vz) {
+                continue;
+            }
+
+            let id = chunks.get_voxel_by_voxel(vx, vy, vz);
+            let (rotation, y_rotation) =
+                BlockRotation::decode(&chunks.get_voxel_rotation_by_voxel(vx, vy, vz));
+
+            chunks.update(vx, vy, vz, air, 0, 0);
+
+            // the block above loses its support next, on the next tick
+            queue.push(Vec3(vx, vy + 1, vz));
+            changed.push(voxel);
+
+            let size = FALLING_BLOCK_SIZE * dimension;
+            let inset = (dimension - size) / 2.0;
+            let base = Vec3(
+                vx as f32 * dimension + inset,
+                vy as f32 * dimension,
+                vz as f32 * dimension + inset,
+            );
+
+            lazy.create_entity(&entities)
+                .with(RigidBody::new(
+                    Aabb::new(&base, &Vec3(size, size, size)),
+                    0.0,
+                    1.0,
+                    1.0,
+                    0.0,
+                    1.0,
+                    false,
+                ))
+                .with(FallingBlock::new(id, rotation, y_rotation))
+                .build();
+        }
+
+        chunks.stop_caching();
+
+        if changed.is_empty() {
+            chunks.clear_cache();
+            return;
+        }
+
+        let updates = changed
+            .into_iter()
+            .filter_map(|Vec3(vx, vy, vz)| chunks.get_update_protocol(vx, vy, vz))
+            .collect();
+
+        let mut components = MessageComponents::default_for(MessageType::Update);
+        components.updates = Some(updates);
+        components.chunks = Some(chunks.take_cache_protocols(&mut remeshes));
+
+        messages.push((create_message(components), None, None, None));
+    }
+}
@@ -0,0 +1,263 @@
+use specs::{
+    Builder, Entities, LazyUpdate, Read, ReadExpect, ReadStorage, System, WriteExpect, WriteStorage,
+};
+
+use server_common::types::EquipmentSlot;
+
+use crate::{
+    comp::{
+        attack_cooldown::AttackCooldown, behavior::Behavior, dropped_item::DroppedItem,
+        effects::Effects, etype::EType, experience::ExperienceOrb, health::Health, id::Id,
+        inventory::Inventory, rigidbody::RigidBody,
+    },
+    constants::{ARMOR_REDUCTION_PER_POINT, MAX_ARMOR_REDUCTION},
+    engine::{
+        chunks::Chunks,
+        clock::Clock,
+        damage::DamageQueue,
+        entities::Entities as Prototypes,
+        knockback::{KnockbackEvent, KnockbackQueue},
+        loot::{LootContext, LootTables},
+        registry::Registry,
+        rng::WorldRng,
+        sounds::{SoundQueue, Sounds},
+        teams::Teams,
+        world::{MessagesQueue, WorldConfig},
+    },
+    network::models::{create_health_message, create_inventory_message},
+};
+
+/// Dispatches the damage queued up this tick to the entities' health
+pub struct HealthSystem;
+
+impl<'a> System<'a> for HealthSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, LazyUpdate>,
+        ReadExpect<'a, Clock>,
+        ReadExpect<'a, WorldConfig>,
+        ReadExpect<'a, Sounds>,
+        ReadExpect<'a, Chunks>,
+        ReadExpect<'a, LootTables>,
+        ReadExpect<'a, Prototypes>,
+        ReadExpect<'a, Teams>,
+        ReadStorage<'a, Id>,
+        ReadStorage<'a, EType>,
+        WriteStorage<'a, Inventory>,
+        ReadStorage<'a, RigidBody>,
+        ReadStorage<'a, Effects>,
+        WriteStorage<'a, Health>,
+        WriteStorage<'a, AttackCooldown>,
+        WriteStorage<'a, Behavior>,
+        WriteExpect<'a, DamageQueue>,
+        WriteExpect<'a, KnockbackQueue>,
+        WriteExpect<'a, MessagesQueue>,
+        WriteExpect<'a, SoundQueue>,
+        WriteExpect<'a, WorldRng>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (
+            entities,
+            lazy,
+            clock,
+            config,
+            sounds,
+            chunks,
+            loot,
+            prototypes,
+            teams,
+            ids,
+            etypes,
+            mut inventories,
+            bodies,
+            effects,
+            mut healths,
+            mut cooldowns,
+            mut behaviors,
+            mut damages,
+            mut knockbacks,
+            mut messages,
+            mut sound_queue,
+            mut rng,
+        ) = data;
+
+        for health in (&mut healths).join() {
+            health.tick(clock.delta);
+        }
+
+        for cooldown in (&mut cooldowns).join() {
+            cooldown.tick(clock.delta);
+        }
+
+        for event in damages.drain(..) {
+            if !entities.is_alive(event.target) {
+                continue;
+            }
+
+            // teammates can't hurt each other unless friendly fire is on
+            if !config.friendly_fire {
+                let source = event.source.and_then(|source| ids.get(source));
+
+                if let (Some(source), Some(target)) = (source, ids.get(event.target)) {
+                    if teams.same_team(source.0, target.0) {
+                        continue;
+                    }
+                }
+            }
+
+            // mobs run away when hit, even if they can't be hurt
+            if let (Some(behavior), Some(body)) =
+                (behaviors.get_mut(event.target), bodies.get(event.target))
+            {
+                behavior.hurt(&body.get_position(), &event.from);
+            }
+
+            let health = match healths.get_mut(event.target) {
+                Some(health) => health,
+                None => continue,
+            };
+
+            let amount = event.amount
+                * event
+                    .source
+                    .and_then(|source| effects.get(source))
+                    .map_or(1.0, |effects| effects.damage_dealt_multiplier())
+                * effects
+                    .get(event.target)
+                    .map_or(1.0, |effects| effects.damage_taken_multiplier())
+                * inventories.get(event.target).map_or(1.0, |inventory| {
+                    armor_multiplier(inventory, &chunks.registry)
+                });
+
+            if !health.damage(amount) {
+                continue;
+            }
+
+            if let Some(body) = bodies.get(event.target) {
+                sound_queue.extend(sounds.event("entity.hurt", body.get_head_position()));
+
+                if event.knockback > 0.0 {
+                    knockbacks.push(KnockbackEvent::away_from(
+                        event.target,
+                        &body.get_position(),
+                        &event.from,
+                        event.knockback,
+                    ));
+                }
+            }
+
+            let player = ids.get(event.target).map(|id| id.0);
+
+            messages.push((
+                create_health_message(player, event.target, health),
+                None,
+                None,
+                None,
+            ));
+
+            // dead players stay around, ignored, until they ask to respawn
+            if let (true, Some(player)) = (health.is_dead(), player) {
+                if config.keep_inventory {
+                    continue;
+                }
+
+                if let (Some(inventory), Some(body)) =
+                    (inventories.get_mut(event.target), bodies.get(event.target))
+                {
+                    let position = body.get_position();
+
+                    let dropped = inventory
+                        .slots
+                        .iter_mut()
+                        .chain(inventory.equipment.iter_mut())
+                        .filter_map(Option::take)
+                        .collect::<Vec<_>>();
+
+                    for stack in dropped {
+                        DroppedItem::build_entity(
+                            lazy.create_entity(&entities),
+                            stack,
+                            &position,
+                            config.dimension as f32,
+                        )
+                        .build();
+                    }
+
+                    messages.push((
+                        create_inventory_message(inventory),
+                        Some(vec![player]),
+                        None,
+                        None,
+                    ));
+                }
+
+                continue;
+            }
+
+            if health.is_dead() {
+                if let (Some(etype), Some(body)) =
+                    (etypes.get(event.target), bodies.get(event.target))
+                {
+                    let position = body.get_position();
+
+                    let context = LootContext {
+                        tool: event
+                            .source
+                            .and_then(|source| inventories.get(source))
+                            .and_then(|inventory| inventory.held())
+                            .map(|held| chunks.registry.get_block_by_id(held.id).name.to_owned()),
+                        ..Default::default()
+                    };
+
+                    for stack in loot.roll_entity(&chunks.registry, &etype.0, &context, &mut *rng) {
+                        DroppedItem::build_entity(
+                            lazy.create_entity(&entities),
+                            stack,
+                            &position,
+                            config.dimension as f32,
+                        )
+                        .build();
+                    }
+
+                    // experience is only for players, mobs dying some other way just drop their loot
+                    let killed_by_player =
+                        event.source.and_then(|source| ids.get(source)).is_some();
+
+                    let points = prototypes
+                        .get_prototype(&etype.0)
+                        .map_or(0, |prototype| prototype.experience);
+
+                    if killed_by_player && points > 0 {
+                        ExperienceOrb::build_entity(
+                            lazy.create_entity(&entities),
+                            points,
+                            &position,
+                            config.dimension as f32,
+                        )
+                        .build();
+                    }
+                }
+
+                entities
+                    .delete(event.target)
+                    .expect("Error removing dead entity...");
+            }
+        }
+    }
+}
+
+/// Multiplier on the damage taken by whoever wears the armor in an inventory
+fn armor_multiplier(inventory: &Inventory, registry: &Registry) -> f32 {
+    let points = EquipmentSlot::ALL
+        .iter()
+        .filter_map(|&slot| inventory.equipped(slot))
+        .filter_map(|worn| registry.get_block_by_id(worn.id).armor.as_ref())
+        .map(|armor| armor.points)
+        .sum::<f32>();
+
+    1.0 - (points * ARMOR_REDUCTION_PER_POINT).clamp(0.0, MAX_ARMOR_REDUCTION)
+}
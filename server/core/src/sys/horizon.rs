@@ -0,0 +1,54 @@
+use specs::{System, WriteExpect};
+
+use crate::{
+    constants::HORIZON_REPLIES_PER_TICK,
+    engine::{chunks::Chunks, players::Players, world::MessagesQueue},
+    network::models::create_horizon_message,
+};
+
+/// Sends players the horizons of the far chunks they requested, to draw past their meshes, a
+/// bunch per tick
+pub struct HorizonSystem;
+
+impl<'a> System<'a> for HorizonSystem {
+    type SystemData = (
+        WriteExpect<'a, Players>,
+        WriteExpect<'a, MessagesQueue>,
+        WriteExpect<'a, Chunks>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut players, mut messages, mut chunks) = data;
+
+        players.iter_mut().for_each(|(&player_id, player)| {
+            if player.name.is_none() {
+                return;
+            }
+
+            let mut horizons = vec![];
+
+            // the ones still being generated are looked at again next tick
+            for _ in 0..player.requested_horizons.len() {
+                if horizons.len() >= HORIZON_REPLIES_PER_TICK {
+                    break;
+                }
+
+                let coords = player.requested_horizons.pop_front().unwrap();
+
+                match chunks.horizon(&coords) {
+                    Some(horizon) => horizons.push(horizon),
+                    None => player.requested_horizons.push_back(coords),
+                }
+            }
+
+            if !horizons.is_empty() {
+                messages.push((
+                    create_horizon_message(horizons),
+                    Some(vec![player_id]),
+                    None,
+                    None,
+                ));
+            }
+        });
+    }
+}
@@ -0,0 +1,112 @@
+use specs::{Entities, ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
+
+use crate::{
+    comp::{health::Health, hostile::Hostile, rigidbody::RigidBody, target::Target},
+    engine::{
+        clock::Clock,
+        damage::{DamageEvent, DamageQueue},
+        sounds::{SoundQueue, Sounds},
+        world::WorldConfig,
+    },
+};
+
+pub struct HostileSystem;
+
+impl<'a> System<'a> for HostileSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Clock>,
+        ReadExpect<'a, WorldConfig>,
+        ReadExpect<'a, Sounds>,
+        ReadStorage<'a, Target>,
+        ReadStorage<'a, RigidBody>,
+        ReadStorage<'a, Health>,
+        WriteStorage<'a, Hostile>,
+        WriteExpect<'a, DamageQueue>,
+        WriteExpect<'a, SoundQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (
+            entities,
+            clock,
+            config,
+            sounds,
+            targets,
+            bodies,
+            healths,
+            mut hostiles,
+            mut damages,
+            mut sound_queue,
+        ) = data;
+
+        let delta = clock.delta;
+        let dimension = config.dimension as f32;
+        let difficulty = config.difficulty;
+
+        // no hostile mobs on peaceful, even the ones from before or spawned in since
+        if difficulty.is_peaceful() {
+            for (ent, _) in (&*entities, &hostiles).join() {
+                entities.delete(ent).ok();
+            }
+
+            return;
+        }
+
+        for (ent, target, body, hostile) in (&*entities, &targets, &bodies, &mut hostiles).join() {
+            hostile.cooldown = (hostile.cooldown - delta).max(0.0);
+
+            let head = body.get_head_position();
+            let chase_distance = hostile.options.chase_distance * dimension;
+
+            // the observed target only counts if there's a clear line of sight to it
+            let seen = match (target.get_entity(), target.get_position()) {
+                (Some(other), Some(position))
+                    if !target.is_obstructed()
+                        && head.sub(&position).len() <= chase_distance
+                        && !healths.get(other).map(|h| h.is_dead()).unwrap_or(false) =>
+                {
+                    Some((other, position))
+                }
+                _ => None,
+            };
+
+            let (other, position) = match seen {
+                Some(seen) => seen,
+                None => {
+                    // keep chasing the last seen position for a while
+                    hostile.memory -= delta;
+
+                    if hostile.memory <= 0.0 {
+                        hostile.forget();
+                    }
+
+                    continue;
+                }
+            };
+
+            hostile.spot(other, &position);
+
+            if hostile.cooldown > 0.0
+                || head.sub(&position).len() > hostile.options.attack_range * dimension
+            {
+                continue;
+            }
+
+            damages.push(DamageEvent {
+                target: other,
+                amount: hostile.options.attack_damage * difficulty.damage_multiplier(),
+                source: Some(ent),
+                from: head,
+                knockback: hostile.options.attack_knockback,
+            });
+
+            sound_queue.extend(sounds.event("mob.attack", body.get_position()));
+
+            hostile.cooldown = hostile.options.attack_cooldown;
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use hashbrown::HashSet;
+
+use specs::{Entities, ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
+
+use crate::{
+    comp::{dropped_item::DroppedItem, health::Health, inventory::Inventory, rigidbody::RigidBody},
+    constants::{ITEM_DESPAWN_TIME, ITEM_PICKUP_DISTANCE},
+    engine::{
+        clock::Clock,
+        gamemode::{GameMode, GameModes},
+        players::Players,
+        world::{MessagesQueue, WorldConfig},
+    },
+    network::models::create_inventory_message,
+};
+
+/// Lets players pick up the items lying around them, and clears away the ones left too long
+pub struct ItemsSystem;
+
+impl<'a> System<'a> for ItemsSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Clock>,
+        ReadExpect<'a, WorldConfig>,
+        ReadExpect<'a, Players>,
+        ReadExpect<'a, GameModes>,
+        ReadStorage<'a, RigidBody>,
+        ReadStorage<'a, Health>,
+        WriteStorage<'a, DroppedItem>,
+        WriteStorage<'a, Inventory>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (
+            entities,
+            clock,
+            config,
+            players,
+            game_modes,
+            bodies,
+            healths,
+            mut items,
+            mut inventories,
+            mut messages,
+        ) = data;
+
+        let pickup_distance = ITEM_PICKUP_DISTANCE * config.dimension as f32;
+
+        // the dead and spectators don't pick anything up
+        let collectors = players
+            .iter()
+            .filter(|(id, player)| {
+                game_modes.get(id).copied().unwrap_or_default() != GameMode::Spectator
+                    && !healths.get(player.entity).map_or(false, |h| h.is_dead())
+            })
+            .filter_map(|(id, player)| {
+                bodies
+                    .get(player.entity)
+                    .map(|body| (*id, player.entity, body.get_position()))
+            })
+            .collect::<Vec<_>>();
+
+        let mut changed = HashSet::new();
+
+        for (entity, item, body) in (&entities, &mut items, &bodies).join() {
+            item.age += clock.delta;
+            item.pickup_delay = (item.pickup_delay - clock.delta).max(0.0);
+
+            let position = body.get_position();
+
+            // fell out of the world, or lay around for too long
+            if position.1 < 0.0 || item.age >= ITEM_DESPAWN_TIME {
+                entities.delete(entity).ok();
+                continue;
+            }
+
+            if item.pickup_delay > 0.0 {
+                continue;
+            }
+
+            for (id, player, at) in collectors.iter() {
+                if at.sub(&position).len() > pickup_distance {
+                    continue;
+                }
+
+                let inventory = match inventories.get_mut(*player) {
+                    Some(inventory) => inventory,
+                    None => continue,
+                };
+
+                let count = item.stack.count;
+
+                match inventory.add(item.stack.to_owned()) {
+                    None => {
+                        entities.delete(entity).ok();
+                        changed.insert(*id);
+                        break;
+                    }
+                    Some(rest) => {
+                        if rest.count < count {
+                            changed.insert(*id);
+                        }
+
+                        item.stack = rest;
+                    }
+                }
+            }
+        }
+
+        for id in changed {
+            if let Some(inventory) = players
+                .get(&id)
+                .and_then(|player| inventories.get(player.entity))
+            {
+                messages.push((
+                    create_inventory_message(inventory),
+                    Some(vec![id]),
+                    None,
+                    None,
+                ));
+            }
+        }
+    }
+}
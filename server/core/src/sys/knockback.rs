@@ -0,0 +1,49 @@
+use specs::{ReadStorage, System, WriteExpect, WriteStorage};
+
+use crate::{
+    comp::{id::Id, rigidbody::RigidBody},
+    engine::{knockback::KnockbackQueue, world::MessagesQueue},
+    network::models::{create_of_type, MessageType},
+};
+
+/// Pushes entities around with the knockback queued up this tick
+///
+/// Players move themselves, so they are also told to apply the same push locally, along with
+/// where the server has them to snap back to if they drifted too far off.
+pub struct KnockbackSystem;
+
+impl<'a> System<'a> for KnockbackSystem {
+    type SystemData = (
+        ReadStorage<'a, Id>,
+        WriteStorage<'a, RigidBody>,
+        WriteExpect<'a, KnockbackQueue>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (ids, mut bodies, mut knockbacks, mut messages) = data;
+
+        for event in knockbacks.drain(..) {
+            let body = match bodies.get_mut(event.target) {
+                Some(body) => body,
+                None => continue,
+            };
+
+            let impulse = event.velocity.scale(body.mass);
+            body.apply_impulse(&impulse);
+
+            if let Some(id) = ids.get(event.target) {
+                let position = body.get_head_position();
+
+                let mut message = create_of_type(MessageType::Knockback);
+                message.json = serde_json::json!({
+                    "impulse": [impulse.0, impulse.1, impulse.2],
+                    "position": [position.0, position.1, position.2],
+                })
+                .to_string();
+
+                messages.push((message, Some(vec![id.0]), None, None));
+            }
+        }
+    }
+}
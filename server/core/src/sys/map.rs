@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use specs::{ReadExpect, System, WriteExpect};
+
+use crate::{
+    constants::MAP_TILES_PER_TICK,
+    engine::{
+        chunks::Chunks,
+        map::{render_tile, MapTiles},
+        remesh::RemeshQueue,
+    },
+};
+
+/// Keeps the cached map tiles up to date, rendering the tiles of chunks that changed again a
+/// few per tick
+pub struct MapSystem;
+
+impl<'a> System<'a> for MapSystem {
+    type SystemData = (
+        ReadExpect<'a, Chunks>,
+        ReadExpect<'a, RemeshQueue>,
+        WriteExpect<'a, MapTiles>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (chunks, remeshes, mut tiles) = data;
+
+        // chunks waiting to be remeshed are the ones whose voxels changed
+        remeshes
+            .coords()
+            .for_each(|coords| tiles.invalidate(coords));
+
+        let stale = tiles
+            .stale()
+            .take(MAP_TILES_PER_TICK)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for coords in stale {
+            match chunks.get_chunk(&coords) {
+                Some(chunk) if !chunk.needs_terrain && !chunk.needs_decoration => {
+                    let tile = render_tile(chunk, &chunks.registry);
+                    tiles.insert(&coords, Arc::new(tile));
+                }
+                // whatever was last seen of unloaded chunks stays on the map
+                _ => tiles.unmark_stale(&coords),
+            }
+        }
+    }
+}
@@ -1,14 +1,17 @@
 use specs::{System, WriteExpect};
 
 use crate::{
+    constants::CHUNK_REPLIES_PER_TICK,
     engine::{
         chunks::{Chunks, MeshLevel},
         players::Players,
         world::MessagesQueue,
     },
-    network::models::{create_message, MessageComponents, MessageType},
+    network::models::{create_chunk_reply, create_message, MessageComponents, MessageType},
 };
 
+/// Sends players the chunks they requested once they're generated and meshed, a few per tick,
+/// telling them once about the ones that aren't ready yet.
 pub struct MeshingSystem;
 
 impl<'a> System<'a> for MeshingSystem {
@@ -21,44 +24,81 @@ impl<'a> System<'a> for MeshingSystem {
     fn run(&mut self, data: Self::SystemData) {
         let (mut players, mut messages, mut chunks) = data;
 
-        let mut request_queue = vec![];
-
-        players.iter_mut().for_each(|(id, player)| {
+        players.iter_mut().for_each(|(&player_id, player)| {
             if player.name.is_none() {
                 return;
             }
 
-            let requested_chunk = player.requested_chunks.pop_front();
-            request_queue.push((requested_chunk, id.to_owned()));
-        });
+            let mut empty = vec![];
+            let mut loaded = vec![];
+            let mut pending = vec![];
+
+            // every requested chunk is looked at, so the ones not ready are meshed together
+            for _ in 0..player.requested_chunks.len() {
+                if empty.len() + loaded.len() >= CHUNK_REPLIES_PER_TICK {
+                    break;
+                }
+
+                let coords = player.requested_chunks.pop_front().unwrap();
 
-        request_queue.into_iter().for_each(|(coords, player_id)| {
-            if let Some(coords) = coords {
-                if let Some(chunk) = chunks.get(&coords, &MeshLevel::All, false) {
-                    // SEND CHUNK BACK TO PLAYER
-                    // SEND THEM IN SEPARATE MESSAGES TO LOWER NETWORK LAG
-
-                    for i in 0..3 {
-                        let mut component = MessageComponents::default_for(MessageType::Load);
-                        component.chunks = Some(vec![if i == 0 {
-                            chunk.get_protocol(true, false, false, MeshLevel::All)
-                        } else if i == 1 {
-                            chunk.get_protocol(false, true, false, MeshLevel::All)
+                match chunks.get(&coords, &MeshLevel::All, false) {
+                    Some(chunk) => {
+                        player.pending_chunks.remove(&coords);
+
+                        // nothing but air, no need to send the data along
+                        if chunk.is_empty {
+                            empty.push(chunk.get_protocol(false, false, false, MeshLevel::All));
                         } else {
-                            chunk.get_protocol(false, false, true, MeshLevel::All)
-                        }]);
+                            loaded.push(coords);
+                        }
+                    }
+                    None => {
+                        if player.pending_chunks.insert(coords.clone()) {
+                            pending.push(coords.clone());
+                        }
 
-                        let new_message = create_message(component);
-                        messages.push((new_message, Some(vec![player_id]), None, None));
+                        player.requested_chunks.push_back(coords);
                     }
-                } else {
-                    players
-                        .get_mut(&player_id)
-                        .unwrap()
-                        .requested_chunks
-                        .push_back(coords);
                 }
             }
+
+            if !pending.is_empty() {
+                messages.push((
+                    create_chunk_reply(&pending, &[]),
+                    Some(vec![player_id]),
+                    None,
+                    None,
+                ));
+            }
+
+            if !empty.is_empty() {
+                let mut component = MessageComponents::default_for(MessageType::Load);
+                component.chunks = Some(empty);
+
+                messages.push((create_message(component), Some(vec![player_id]), None, None));
+            }
+
+            if loaded.is_empty() {
+                return;
+            }
+
+            // SEND MESHES, VOXELS AND LIGHTS IN SEPARATE MESSAGES TO LOWER NETWORK LAG
+            for i in 0..3 {
+                let protocols = loaded
+                    .iter()
+                    .filter_map(|coords| chunks.raw(coords))
+                    .map(|chunk| match i {
+                        0 => chunk.get_protocol(true, false, false, MeshLevel::All),
+                        1 => chunk.get_protocol(false, true, false, MeshLevel::All),
+                        _ => chunk.get_protocol(false, false, true, MeshLevel::All),
+                    })
+                    .collect();
+
+                let mut component = MessageComponents::default_for(MessageType::Load);
+                component.chunks = Some(protocols);
+
+                messages.push((create_message(component), Some(vec![player_id]), None, None));
+            }
         });
     }
 }
@@ -1,23 +1,65 @@
+mod behavior;
+mod border;
 mod broadcast;
 mod chunking;
+mod circuits;
+mod effects;
 mod entities;
+mod equipment;
+mod experience;
 mod generation;
+mod gravity;
+mod health;
+mod horizon;
+mod hostile;
+mod items;
+mod knockback;
+mod map;
 mod meshing;
+mod mounts;
 mod observe;
+mod particles;
 mod pathfind;
 mod peers;
 mod physics;
+mod portals;
+mod profiles;
+mod random_tick;
+mod remesh;
 mod search;
+mod sounds;
+mod timed;
 mod walk_towards;
 
+pub use behavior::BehaviorSystem;
+pub use border::BorderSystem;
 pub use broadcast::BroadcastSystem;
 pub use chunking::ChunkingSystem;
+pub use circuits::CircuitsSystem;
+pub use effects::EffectsSystem;
 pub use entities::EntitiesSystem;
+pub use equipment::EquipmentSystem;
+pub use experience::ExperienceSystem;
 pub use generation::GenerationSystem;
+pub use gravity::GravitySystem;
+pub use health::HealthSystem;
+pub use horizon::HorizonSystem;
+pub use hostile::HostileSystem;
+pub use items::ItemsSystem;
+pub use knockback::KnockbackSystem;
+pub use map::MapSystem;
 pub use meshing::MeshingSystem;
+pub use mounts::MountsSystem;
 pub use observe::ObserveSystem;
+pub use particles::ParticlesSystem;
 pub use pathfind::PathFindSystem;
 pub use peers::PeersSystem;
 pub use physics::PhysicsSystem;
+pub use portals::PortalsSystem;
+pub use profiles::ProfilesSystem;
+pub use random_tick::RandomTickSystem;
+pub use remesh::RemeshSystem;
 pub use search::SearchSystem;
+pub use sounds::SoundsSystem;
+pub use timed::Timed;
 pub use walk_towards::WalkTowardsSystem;
@@ -0,0 +1,111 @@
+use specs::{Entities, ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
+
+use server_common::vec::Vec3;
+
+use crate::{
+    comp::{
+        brain::Brain,
+        id::Id,
+        mount::{Mountable, Mounted},
+        rigidbody::RigidBody,
+        rotation::Rotation,
+    },
+    engine::{
+        clock::Clock,
+        world::{MessagesQueue, WorldConfig},
+    },
+    network::models::create_mount_message,
+};
+
+/// Steers mounts by their riders' inputs, then sits the riders back on them
+pub struct MountsSystem;
+
+impl<'a> System<'a> for MountsSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Clock>,
+        ReadExpect<'a, WorldConfig>,
+        ReadStorage<'a, Id>,
+        ReadStorage<'a, Rotation>,
+        WriteStorage<'a, Mounted>,
+        WriteStorage<'a, Mountable>,
+        WriteStorage<'a, RigidBody>,
+        WriteStorage<'a, Brain>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (
+            entities,
+            clock,
+            config,
+            ids,
+            rotations,
+            mut mounted,
+            mountables,
+            mut bodies,
+            mut brains,
+            mut messages,
+        ) = data;
+
+        let dimension = config.dimension as f32;
+        let mut thrown = vec![];
+
+        for (entity, id, rotation, rider) in (&entities, &ids, &rotations, &mounted).join() {
+            let mount = rider.mount;
+
+            let seat = match (
+                bodies.get_mut(mount),
+                brains.get_mut(mount),
+                mountables.get(mount),
+            ) {
+                (Some(body), Some(brain), Some(mountable)) => {
+                    // boats and such crawl along out of the water
+                    brain.options.max_speed = if body.in_fluid {
+                        mountable.max_speed
+                    } else {
+                        mountable.max_speed * mountable.options.land_speed
+                    };
+
+                    let position = body.get_position();
+
+                    match rider.steer_target(&rotation.0, &position) {
+                        Some(target) => {
+                            brain.walk();
+                            brain.operate(&target, body, clock.delta);
+                        }
+                        None => {
+                            brain.stop();
+                            brain.operate(&position, body, clock.delta);
+                        }
+                    }
+
+                    position.add(&Vec3(0.0, mountable.options.seat * dimension, 0.0))
+                }
+                // the mount is gone, killed or unloaded
+                _ => {
+                    thrown.push((entity, id.0));
+                    continue;
+                }
+            };
+
+            if let Some(body) = bodies.get_mut(entity) {
+                body.set_position(&seat);
+                body.velocity = Vec3::default();
+            }
+        }
+
+        for (entity, player_id) in thrown {
+            mounted.remove(entity);
+            messages.push((
+                create_mount_message(None),
+                Some(vec![player_id]),
+                None,
+                None,
+            ));
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use specs::{ReadExpect, ReadStorage, System, WriteExpect};
+
+use crate::{
+    comp::rigidbody::RigidBody,
+    constants::PARTICLE_VIEW_DISTANCE,
+    engine::{
+        particles::ParticleQueue,
+        players::Players,
+        world::{MessagesQueue, WorldConfig},
+    },
+    network::models::{create_message, MessageComponents, MessageType},
+};
+
+/// Sends the particle effects of this tick to the players close enough to see them
+pub struct ParticlesSystem;
+
+impl<'a> System<'a> for ParticlesSystem {
+    type SystemData = (
+        ReadExpect<'a, WorldConfig>,
+        ReadExpect<'a, Players>,
+        ReadStorage<'a, RigidBody>,
+        WriteExpect<'a, ParticleQueue>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (config, players, bodies, mut particles, mut messages) = data;
+
+        if particles.is_empty() {
+            return;
+        }
+
+        let view_distance = PARTICLE_VIEW_DISTANCE * config.dimension as f32;
+
+        for (id, player) in players.iter() {
+            let position = match bodies.get(player.entity) {
+                Some(body) => body.get_position(),
+                None => continue,
+            };
+
+            let nearby = particles
+                .iter()
+                .filter(|particle| particle.position.sub(&position).len() <= view_distance)
+                .map(|particle| particle.to_protocol())
+                .collect::<Vec<_>>();
+
+            if nearby.is_empty() {
+                continue;
+            }
+
+            let mut components = MessageComponents::default_for(MessageType::Particle);
+            components.particles = Some(nearby);
+
+            messages.push((create_message(components), Some(vec![*id]), None, None));
+        }
+
+        particles.clear();
+    }
+}
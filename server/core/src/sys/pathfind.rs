@@ -4,10 +4,13 @@ use std::sync::{Arc, Mutex};
 
 use server_common::vec::Vec3;
 use server_utils::convert::map_world_to_voxel;
-use specs::{ReadExpect, ReadStorage, System, WriteStorage};
+use specs::{Join, ReadExpect, ReadStorage, System, WriteStorage};
 
 use crate::{
-    comp::{rigidbody::RigidBody, target::Target, walk_towards::WalkTowards},
+    comp::{
+        behavior::Behavior, hostile::Hostile, rigidbody::RigidBody, target::Target,
+        walk_towards::WalkTowards,
+    },
     engine::{
         astar::{AStar, PathNode},
         chunks::Chunks,
@@ -24,6 +27,8 @@ impl<'a> System<'a> for PathFindSystem {
         ReadExpect<'a, Chunks>,
         ReadStorage<'a, RigidBody>,
         ReadStorage<'a, Target>,
+        ReadStorage<'a, Behavior>,
+        ReadStorage<'a, Hostile>,
         WriteStorage<'a, WalkTowards>,
     );
 
@@ -31,7 +36,7 @@ impl<'a> System<'a> for PathFindSystem {
         use rayon::prelude::*;
         use specs::ParJoin;
 
-        let (chunks, bodies, targets, mut walk_towards) = data;
+        let (chunks, bodies, targets, behaviors, hostiles, mut walk_towards) = data;
 
         let dimension = chunks.config.dimension;
 
@@ -50,9 +55,25 @@ impl<'a> System<'a> for PathFindSystem {
             true
         };
 
-        (&bodies, &targets, &mut walk_towards).par_join().for_each(
-            |(body, target, walk_toward)| {
-                if let Some(position) = target.get_position() {
+        (
+            &bodies,
+            &targets,
+            behaviors.maybe(),
+            hostiles.maybe(),
+            &mut walk_towards,
+        )
+            .par_join()
+            .for_each(|(body, target, behavior, hostile, walk_toward)| {
+                // mobs with behaviors walk towards their own goals instead of what they observe,
+                // hostile mobs only chase targets they've actually seen
+                let goal = match (hostile, behavior) {
+                    (Some(hostile), _) if hostile.target.is_some() => hostile.goal.clone(),
+                    (_, Some(behavior)) => behavior.goal.clone(),
+                    (Some(_), None) => None,
+                    (None, None) => target.get_position(),
+                };
+
+                if let Some(position) = goal {
                     let body_pos = body.get_position();
                     let body_dim = body.get_dimension();
 
@@ -185,7 +206,6 @@ impl<'a> System<'a> for PathFindSystem {
                         walk_toward.0 = None;
                     }
                 }
-            },
-        );
+            });
     }
 }
@@ -10,10 +10,20 @@ use specs::{ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
 use server_common::{quaternion::Quaternion, vec::Vec3};
 
 use crate::{
-    comp::{id::Id, name::Name, rigidbody::RigidBody, rotation::Rotation},
+    comp::{
+        health::Health,
+        id::Id,
+        mount::Mounted,
+        name::Name,
+        profile::{clean_name, unique_name, PlayerProfile},
+        rigidbody::RigidBody,
+        rotation::Rotation,
+    },
+    constants::DEFAULT_PLAYER_NAME,
     engine::{
         chunks::Chunks,
-        players::{PlayerUpdates, Players},
+        players::{is_name_taken, PlayerUpdates, Players},
+        teams::Teams,
         world::MessagesQueue,
     },
     network::models::{
@@ -29,11 +39,15 @@ impl<'a> System<'a> for PeersSystem {
     type SystemData = (
         ReadExpect<'a, String>,
         ReadExpect<'a, Chunks>,
+        ReadExpect<'a, Teams>,
         WriteExpect<'a, PlayerUpdates>,
         WriteExpect<'a, MessagesQueue>,
         WriteExpect<'a, Players>,
         ReadStorage<'a, Id>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Mounted>,
         WriteStorage<'a, Name>,
+        WriteStorage<'a, PlayerProfile>,
         WriteStorage<'a, RigidBody>,
         WriteStorage<'a, Rotation>,
     );
@@ -44,22 +58,49 @@ impl<'a> System<'a> for PeersSystem {
         let (
             world_name,
             chunks,
+            teams,
             mut updates,
             mut messages,
             mut players,
             ids,
+            healths,
+            mounted,
             mut names,
+            mut profiles,
             mut bodies,
             mut rotations,
         ) = data;
 
         let mut peers_update = HashMap::new();
 
-        for (id, name, body, rotation) in (&ids, &mut names, &mut bodies, &mut rotations).join() {
+        let vehicles = mounted
+            .join()
+            .filter_map(|rider| {
+                bodies
+                    .get(rider.mount)
+                    .map(|body| (rider.mount, body.get_position()))
+            })
+            .collect::<HashMap<_, _>>();
+
+        for (id, name, profile, body, rotation, health, rider) in (
+            &ids,
+            &mut names,
+            &mut profiles,
+            &mut bodies,
+            &mut rotations,
+            healths.maybe(),
+            mounted.maybe(),
+        )
+            .join()
+        {
             if let Some(update) = updates.remove(&id.0) {
+                // the dead stay where they fell until they respawn
+                if health.map_or(false, |health| health.is_dead()) {
+                    continue;
+                }
+
                 let messages::Peer {
-                    id: peer_id,
-                    name: new_name,
+                    name: requested_name,
                     px,
                     py,
                     pz,
@@ -67,23 +108,71 @@ impl<'a> System<'a> for PeersSystem {
                     qy,
                     qz,
                     qw,
+                    swim_up,
+                    crouching,
                     ..
                 } = update;
 
-                peers_update.insert(
-                    id.0,
-                    PeerProtocol {
-                        id: peer_id,
-                        name: new_name.clone(),
-                        px,
-                        py,
-                        pz,
-                        qx,
-                        qy,
-                        qz,
-                        qw,
-                    },
-                );
+                // riders sit wherever their mounts carry them, whatever their clients say
+                let (px, py, pz, crouching) = match rider {
+                    Some(_) => {
+                        let Vec3(px, py, pz) = body.get_head_position();
+                        (px, py, pz, false)
+                    }
+                    None => (px, py, pz, crouching),
+                };
+
+                // clients only get to name anonymous players, and only once, so that nobody
+                // can take on the name of somebody else
+                if profile.display_name.is_none() {
+                    let requested = clean_name(&requested_name)
+                        .unwrap_or_else(|| DEFAULT_PLAYER_NAME.to_owned());
+                    let claimed =
+                        unique_name(&requested, |name| is_name_taken(&players, name, id.0));
+
+                    if let Some(player) = players.get_mut(&id.0) {
+                        player.name = Some(claimed.to_owned());
+                    }
+
+                    profile.set_display_name(claimed);
+                }
+
+                let new_name = profile.display_name.clone().unwrap_or_default();
+
+                // clients carry riders along with what they ride, so that the two don't drift apart
+                let vehicle = rider.and_then(|rider| {
+                    vehicles
+                        .get(&rider.mount)
+                        .map(|position| (rider.mount, position))
+                });
+                let (vehicle, offset) = match vehicle {
+                    Some((mount, Vec3(vx, vy, vz))) => {
+                        (mount.id().to_string(), vec![px - vx, py - vy, pz - vz])
+                    }
+                    None => (String::new(), vec![]),
+                };
+
+                if profile.visible {
+                    peers_update.insert(
+                        id.0,
+                        PeerProtocol {
+                            id: id.0.to_string(),
+                            name: new_name.clone(),
+                            px,
+                            py,
+                            pz,
+                            qx,
+                            qy,
+                            qz,
+                            qw,
+                            swim_up,
+                            team: teams.team(id.0).unwrap_or_default().to_owned(),
+                            crouching,
+                            vehicle,
+                            offset,
+                        },
+                    );
+                }
 
                 if name.0.is_none() {
                     let message =
@@ -101,19 +190,20 @@ impl<'a> System<'a> for PeersSystem {
                     messages.push((new_message, None, None, Some(id.0.to_owned())));
                 }
 
-                name.0 = Some(new_name.clone());
-                body.set_head_position(&Vec3(px, py, pz));
+                name.0 = Some(new_name);
+                if rider.is_none() {
+                    // clients lower their camera as they crouch, so the head is shrunk first
+                    body.set_crouching(crouching);
+                    body.set_head_position(&Vec3(px, py, pz));
+                }
                 rotation.0 = Quaternion(qx, qy, qz, qw);
+                body.swim_up = swim_up;
 
                 let voxel = map_world_to_voxel(px, py, pz, chunks.config.dimension);
                 let biome = chunks.biomes.get_biome(voxel.0, voxel.2);
                 let mut new_message = create_of_type(MessageType::Info);
                 new_message.json = format!("{{\"biome\": \"{}\"}}", biome.name);
                 messages.push((new_message, Some(vec![id.0]), None, None));
-
-                if let Some(player) = players.get_mut(&id.0) {
-                    player.name = Some(new_name);
-                }
             }
         }
 
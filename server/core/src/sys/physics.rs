@@ -1,30 +1,112 @@
-use specs::{ReadExpect, System, WriteStorage};
+use specs::{ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
 
-use crate::{comp::rigidbody::RigidBody, engine::physics::Physics};
+use server_common::{aabb::Aabb, vec::Vec3};
+use server_utils::convert::map_world_to_voxel;
+
+use crate::{
+    comp::{effects::Effects, footsteps::Footsteps, id::Id, mount::Mounted, rigidbody::RigidBody},
+    constants::FOOTSTEP_STRIDE,
+    engine::{
+        gamemode::{GameModes, PlayerAbilities},
+        physics::{Physics, PhysicsStep},
+        sounds::{SoundQueue, Sounds},
+    },
+};
 
 use super::super::engine::{chunks::Chunks, clock::Clock};
 
 pub struct PhysicsSystem;
 
 impl<'a> System<'a> for PhysicsSystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (
         ReadExpect<'a, Physics>,
         ReadExpect<'a, Clock>,
         ReadExpect<'a, Chunks>,
+        ReadExpect<'a, Sounds>,
+        ReadExpect<'a, GameModes>,
+        ReadExpect<'a, PlayerAbilities>,
+        ReadStorage<'a, Id>,
+        ReadStorage<'a, Effects>,
+        ReadStorage<'a, Mounted>,
         WriteStorage<'a, RigidBody>,
+        WriteStorage<'a, Footsteps>,
+        WriteExpect<'a, SoundQueue>,
+        WriteExpect<'a, PhysicsStep>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         use specs::Join;
 
-        let (core, clock, chunks, mut bodies) = data;
+        let (
+            core,
+            clock,
+            chunks,
+            sounds,
+            game_modes,
+            abilities,
+            ids,
+            effects,
+            mounted,
+            mut bodies,
+            mut footsteps,
+            mut sound_queue,
+            mut physics_step,
+        ) = data;
+
+        let (steps, step_secs) = physics_step.advance(clock.delta_secs());
+
+        let get_boxes = |region: &Aabb| chunks.get_collision_boxes(region);
+        let test_fluid = |x: i32, y: i32, z: i32| -> bool { chunks.get_fluidity_by_voxel(x, y, z) };
+        let get_surface = |aabb: &Aabb| chunks.get_surface(aabb);
+
+        // riders are carried along by their mounts instead
+        for (body, id, effects, _) in (&mut bodies, ids.maybe(), effects.maybe(), !&mounted).join()
+        {
+            // what's left where chunks are missing waits for them instead of falling through
+            let Vec3(px, py, pz) = body.get_position();
+            let Vec3(vx, vy, vz) = map_world_to_voxel(px, py, pz, chunks.config.dimension);
+            if id.is_none() && chunks.get_chunk_by_voxel(vx, vy, vz).is_none() {
+                continue;
+            }
+
+            body.no_clip = id.map_or(false, |id| {
+                game_modes
+                    .get(&id.0)
+                    .map_or(false, |mode| mode.is_no_clip())
+                    || abilities
+                        .get(&id.0)
+                        .map_or(false, |abilities| abilities.fly)
+            });
+            body.modifiers = chunks.get_movement_modifiers(&body.aabb);
+
+            if let Some(effects) = effects {
+                body.modifiers.speed_multiplier *= effects.speed_multiplier();
+            }
+
+            for _ in 0..steps {
+                body.last_position = Some(body.get_position());
+                core.iterate_body(body, step_secs, &get_boxes, &test_fluid, &get_surface);
+            }
+        }
+
+        let dimension = chunks.config.dimension as f32;
+        let stride = FOOTSTEP_STRIDE * dimension;
+
+        for (body, footsteps) in (&bodies, &mut footsteps).join() {
+            if body.no_clip {
+                continue;
+            }
+
+            let position = body.get_position();
+            let Vec3(px, py, pz) = position;
 
-        let test_solid =
-            |x: i32, y: i32, z: i32| -> bool { !chunks.get_walkable_by_voxel(x, y, z) };
-        let test_fluid = |_, _, _| false;
+            let below = chunks.get_voxel_by_world(px, py - dimension * 0.1, pz);
+            let on_ground = chunks.registry.get_block_by_id(below).is_solid;
 
-        for body in (&mut bodies).join() {
-            core.iterate_body(body, clock.delta_secs(), &test_solid, &test_fluid);
+            if footsteps.step(&position, on_ground, stride) {
+                sound_queue.extend(sounds.event("footstep", position));
+            }
         }
     }
 }
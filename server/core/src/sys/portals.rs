@@ -0,0 +1,78 @@
+use specs::{Entities, ReadExpect, ReadStorage, System, WriteExpect, WriteStorage};
+
+use server_common::vec::Vec3;
+
+use crate::{
+    comp::{etype::EType, id::Id, portal_cooldown::PortalCooldown, rigidbody::RigidBody},
+    constants::PORTAL_COOLDOWN,
+    engine::{
+        chunks::Chunks,
+        clock::Clock,
+        portals::{PortalQueue, PortalTransfer, Portals, Traveler},
+        world::WorldConfig,
+    },
+};
+
+/// Finds the players and entities standing in a portal, queueing them up to travel to the
+/// linked world. The actual transfer is done by the server, which owns every world.
+pub struct PortalsSystem;
+
+impl<'a> System<'a> for PortalsSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Clock>,
+        ReadExpect<'a, WorldConfig>,
+        ReadExpect<'a, Chunks>,
+        ReadStorage<'a, Id>,
+        ReadStorage<'a, EType>,
+        ReadStorage<'a, RigidBody>,
+        WriteStorage<'a, PortalCooldown>,
+        WriteExpect<'a, PortalQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (entities, clock, config, chunks, ids, types, bodies, mut cooldowns, mut transfers) =
+            data;
+
+        let world = match &config.portal_to {
+            Some(world) => world,
+            None => return,
+        };
+
+        for (ent, body, cooldown) in (&*entities, &bodies, &mut cooldowns).join() {
+            let Vec3(px, py, pz) = body.get_position();
+            let id = chunks.get_voxel_by_world(px, py + config.dimension as f32 * 0.1, pz);
+
+            if !chunks.registry.is_portal(id) {
+                cooldown.0 = (cooldown.0 - clock.delta).max(0.0);
+                continue;
+            }
+
+            if !cooldown.is_ready() {
+                continue;
+            }
+
+            cooldown.0 = PORTAL_COOLDOWN;
+
+            let traveler = if let Some(id) = ids.get(ent) {
+                Traveler::Player(id.0)
+            } else if let Some(etype) = types.get(ent) {
+                entities
+                    .delete(ent)
+                    .expect("Error removing traveling entity...");
+                Traveler::Entity(etype.0.to_owned())
+            } else {
+                continue;
+            };
+
+            transfers.push(PortalTransfer {
+                traveler,
+                world: world.to_owned(),
+                position: Portals::scale(&body.get_position(), config.portal_scale),
+            });
+        }
+    }
+}
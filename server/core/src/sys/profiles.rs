@@ -0,0 +1,45 @@
+use specs::{ReadStorage, System, WriteExpect, WriteStorage};
+
+use crate::{
+    comp::{id::Id, profile::PlayerProfile},
+    engine::world::MessagesQueue,
+    network::models::create_profiles_message,
+};
+
+/// Tells every player about the profiles that changed this tick
+///
+/// Players who joined get everyone else's profiles when they spawn, so only changes are sent
+/// here. Anonymous players are left out until their client has named them.
+pub struct ProfilesSystem;
+
+impl<'a> System<'a> for ProfilesSystem {
+    type SystemData = (
+        ReadStorage<'a, Id>,
+        WriteStorage<'a, PlayerProfile>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (ids, mut profiles, mut messages) = data;
+
+        let mut changed = vec![];
+
+        for (id, profile) in (&ids, &mut profiles).join() {
+            if profile.changed && profile.display_name.is_some() {
+                profile.changed = false;
+                changed.push((id.0, profile.clone()));
+            }
+        }
+
+        if !changed.is_empty() {
+            let changed = changed
+                .iter()
+                .map(|(id, profile)| (*id, profile))
+                .collect::<Vec<_>>();
+
+            messages.push((create_profiles_message(&changed), None, None, None));
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use rand::Rng;
+
+use specs::{Builder, Entities, LazyUpdate, Read, ReadExpect, ReadStorage, System, WriteExpect};
+
+use hashbrown::HashSet;
+
+use server_common::vec::{Vec2, Vec3};
+
+use crate::{
+    comp::{curr_chunk::CurrChunk, dropped_item::DroppedItem},
+    constants::RANDOM_TICK_CHUNK_RADIUS,
+    engine::{
+        chunks::Chunks,
+        farming::Farming,
+        leaves::Leaves,
+        loot::LootTables,
+        players::Players,
+        remesh::RemeshQueue,
+        rng::WorldRng,
+        world::{MessagesQueue, WorldConfig},
+    },
+    network::models::{create_message, MessageComponents, MessageType},
+};
+
+/// Picks random voxels in the chunks around players every tick, letting blocks such as
+/// farmland, crops and leaves change over time. Decaying leaves drop their loot as items.
+pub struct RandomTickSystem;
+
+impl<'a> System<'a> for RandomTickSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, LazyUpdate>,
+        ReadExpect<'a, WorldConfig>,
+        ReadExpect<'a, Players>,
+        ReadExpect<'a, LootTables>,
+        ReadStorage<'a, CurrChunk>,
+        WriteExpect<'a, Chunks>,
+        WriteExpect<'a, RemeshQueue>,
+        WriteExpect<'a, MessagesQueue>,
+        WriteExpect<'a, WorldRng>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            lazy,
+            config,
+            players,
+            loot,
+            curr_chunks,
+            mut chunks,
+            mut remeshes,
+            mut messages,
+            mut rng,
+        ) = data;
+
+        if config.random_tick_speed == 0 {
+            return;
+        }
+
+        let mut to_tick = HashSet::new();
+
+        players.values().for_each(|player| {
+            if let Some(Vec2(cx, cz)) = curr_chunks.get(player.entity).and_then(|c| c.val.clone()) {
+                for x in -RANDOM_TICK_CHUNK_RADIUS..=RANDOM_TICK_CHUNK_RADIUS {
+                    for z in -RANDOM_TICK_CHUNK_RADIUS..=RANDOM_TICK_CHUNK_RADIUS {
+                        to_tick.insert(Vec2(cx + x, cz + z));
+                    }
+                }
+            }
+        });
+
+        let chunk_size = config.chunk_size as i32;
+        let max_height = config.max_height as i32;
+        let picks = config.random_tick_speed * config.sub_chunks;
+
+        let mut changed = vec![];
+        let mut dropped = vec![];
+
+        // picked in the same order every time, for seeded worlds to play out the same
+        let mut to_tick = to_tick.into_iter().collect::<Vec<_>>();
+        to_tick.sort_by_key(|coords| (coords.0, coords.1));
+
+        chunks.start_caching();
+
+        for coords in to_tick.iter() {
+            let Vec3(min_x, _, min_z) = match chunks.raw(coords) {
+                Some(chunk)
+                    if !chunk.needs_terrain
+                        && !chunk.needs_decoration
+                        && !chunk.needs_propagation =>
+                {
+                    chunk.min.clone()
+                }
+                _ => continue,
+            };
+
+            for _ in 0..picks {
+                let vx = min_x + rng.gen_range(0..chunk_size);
+                let vy = rng.gen_range(0..max_height);
+                let vz = min_z + rng.gen_range(0..chunk_size);
+
+                if Farming::random_tick(&mut chunks, vx, vy, vz, &mut *rng) {
+                    changed.push(Vec3(vx, vy, vz));
+                } else if let Some(drops) =
+                    Leaves::random_tick(&mut chunks, &loot, vx, vy, vz, &mut *rng)
+                {
+                    changed.push(Vec3(vx, vy, vz));
+                    dropped.extend(drops.into_iter().map(|stack| (Vec3(vx, vy, vz), stack)));
+                }
+            }
+        }
+
+        chunks.stop_caching();
+
+        let dimension = config.dimension as f32;
+
+        for (Vec3(vx, vy, vz), stack) in dropped {
+            let position = Vec3(
+                (vx as f32 + 0.5) * dimension,
+                (vy as f32 + 0.5) * dimension,
+                (vz as f32 + 0.5) * dimension,
+            );
+
+            DroppedItem::build_entity(lazy.create_entity(&entities), stack, &position, dimension)
+                .build();
+        }
+
+        if changed.is_empty() {
+            chunks.clear_cache();
+            return;
+        }
+
+        let updates = changed
+            .into_iter()
+            .filter_map(|Vec3(vx, vy, vz)| chunks.get_update_protocol(vx, vy, vz))
+            .collect();
+
+        let mut components = MessageComponents::default_for(MessageType::Update);
+        components.updates = Some(updates);
+        components.chunks = Some(chunks.take_cache_protocols(&mut remeshes));
+
+        messages.push((create_message(components), None, None, None));
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+
+use specs::{ReadExpect, ReadStorage, System, WriteExpect};
+
+use crate::{
+    comp::curr_chunk::CurrChunk,
+    constants::REMESH_BUDGET_MS,
+    engine::{
+        chunks::{Chunks, MeshLevel},
+        players::Players,
+        remesh::RemeshQueue,
+        world::MessagesQueue,
+    },
+    network::models::{create_message, MessageComponents, MessageType},
+};
+
+/// Remeshes the chunks changed since last tick, nearest to players first, until the tick's
+/// remeshing budget runs out. The rest wait for the next tick.
+pub struct RemeshSystem;
+
+impl<'a> System<'a> for RemeshSystem {
+    type SystemData = (
+        ReadExpect<'a, Players>,
+        ReadStorage<'a, CurrChunk>,
+        WriteExpect<'a, Chunks>,
+        WriteExpect<'a, RemeshQueue>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (players, curr_chunks, mut chunks, mut remeshes, mut messages) = data;
+
+        if remeshes.is_empty() {
+            return;
+        }
+
+        let centers = players
+            .values()
+            .filter_map(|player| curr_chunks.get(player.entity).and_then(|c| c.val.clone()))
+            .collect::<Vec<_>>();
+
+        let budget = Duration::from_millis(REMESH_BUDGET_MS);
+        let start = Instant::now();
+
+        let mut protocols = vec![];
+
+        for coords in remeshes.by_distance(&centers) {
+            // at least one chunk a tick, however long it takes
+            if !protocols.is_empty() && start.elapsed() >= budget {
+                break;
+            }
+
+            let levels = remeshes.take(&coords).unwrap();
+
+            if levels.is_empty() {
+                continue;
+            }
+
+            let level = MeshLevel::Levels(levels);
+
+            if let Some(chunk) = chunks.get(&coords, &level, true) {
+                protocols.push(chunk.get_protocol(true, false, false, level));
+            }
+        }
+
+        if protocols.is_empty() {
+            return;
+        }
+
+        let mut components = MessageComponents::default_for(MessageType::Update);
+        components.chunks = Some(protocols);
+
+        messages.push((create_message(components), None, None, None));
+    }
+}
@@ -0,0 +1,58 @@
+use specs::{ReadExpect, ReadStorage, System, WriteExpect};
+
+use crate::{
+    comp::rigidbody::RigidBody,
+    engine::{
+        players::Players,
+        sounds::SoundQueue,
+        world::{MessagesQueue, WorldConfig},
+    },
+    network::models::{create_message, MessageComponents, MessageType},
+};
+
+/// Sends the sounds played this tick to the players close enough to hear them
+pub struct SoundsSystem;
+
+impl<'a> System<'a> for SoundsSystem {
+    type SystemData = (
+        ReadExpect<'a, WorldConfig>,
+        ReadExpect<'a, Players>,
+        ReadStorage<'a, RigidBody>,
+        WriteExpect<'a, SoundQueue>,
+        WriteExpect<'a, MessagesQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (config, players, bodies, mut sounds, mut messages) = data;
+
+        if sounds.is_empty() {
+            return;
+        }
+
+        let dimension = config.dimension as f32;
+
+        for (id, player) in players.iter() {
+            let position = match bodies.get(player.entity) {
+                Some(body) => body.get_head_position(),
+                None => continue,
+            };
+
+            let audible = sounds
+                .iter()
+                .filter(|sound| sound.position.sub(&position).len() <= sound.distance * dimension)
+                .map(|sound| sound.to_protocol())
+                .collect::<Vec<_>>();
+
+            if audible.is_empty() {
+                continue;
+            }
+
+            let mut components = MessageComponents::default_for(MessageType::Sound);
+            components.sounds = Some(audible);
+
+            messages.push((create_message(components), Some(vec![*id]), None, None));
+        }
+
+        sounds.clear();
+    }
+}
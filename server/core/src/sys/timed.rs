@@ -0,0 +1,51 @@
+use std::{
+    any::type_name,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use specs::{RunningTime, System, World};
+use tracing::info_span;
+
+use crate::engine::ticker::Timings;
+
+/// Wraps a system to record how long it runs under a tick stage, and trace it as a span
+pub struct Timed<S> {
+    system: S,
+    stage: &'static str,
+    timings: Arc<Mutex<Timings>>,
+}
+
+impl<S> Timed<S> {
+    pub fn new(system: S, stage: &'static str, timings: &Arc<Mutex<Timings>>) -> Self {
+        Self {
+            system,
+            stage,
+            timings: Arc::clone(timings),
+        }
+    }
+}
+
+impl<'a, S: System<'a>> System<'a> for Timed<S> {
+    type SystemData = S::SystemData;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let _span = info_span!("system", name = type_name::<S>(), stage = self.stage).entered();
+        let start = Instant::now();
+
+        self.system.run(data);
+
+        self.timings
+            .lock()
+            .unwrap()
+            .record(self.stage, start.elapsed().as_secs_f32());
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        self.system.setup(world);
+    }
+}
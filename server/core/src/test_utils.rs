@@ -0,0 +1,336 @@
+//! Chunks, registries and configs for tests and benchmarks, built in memory instead of read
+//! from the assets.
+//!
+//! Worlds are kept small so that tests stay fast and easy to reason about: chunks of 8 by 32
+//! voxels in 2 sub-chunks, with light levels up to 15. Tests of other sizes make their own with
+//! `sized_config`.
+
+use std::{collections::HashMap, sync::Arc};
+
+use serde_json::{json, Value};
+
+use server_common::{types::Block, vec::Vec2};
+use server_utils::json::merge;
+
+use crate::{
+    constants::LEVEL_SEED,
+    engine::{
+        chunk::Chunk, chunks::Chunks, metadata::WorldMetrics, registry::Registry,
+        world::WorldConfig,
+    },
+    gen::{
+        biomes::{BiomeConfigs, Biomes},
+        structures::Structures,
+    },
+};
+
+pub const AIR: u32 = 0;
+pub const STONE: u32 = 1;
+pub const GLASS: u32 = 2;
+pub const TORCH: u32 = 3;
+pub const BED: u32 = 4;
+pub const BED_FOOT: u32 = 5;
+pub const POWER_SOURCE: u32 = 6;
+pub const POWER_WIRE: u32 = 7;
+pub const POWER_LAMP: u32 = 8;
+pub const WATER: u32 = 9;
+pub const LEAVES: u32 = 10;
+pub const SAND: u32 = 11;
+pub const LOG: u32 = 12;
+pub const DIRT: u32 = 13;
+pub const FARMLAND: u32 = 14;
+pub const WHEAT: u32 = 15;
+pub const LAVA: u32 = 16;
+
+/// A block like the ones of `base-block.json`, with some fields changed
+pub fn block(name: &str, overrides: Value) -> Block {
+    let mut block = json!({
+        "name": name,
+        "rotatable": false,
+        "yRotatable": false,
+        "isEmpty": false,
+        "isSolid": true,
+        "isFluid": false,
+        "isTransparent": false,
+        "isLight": false,
+        "isBlock": true,
+        "isPlant": false,
+        "isPlantable": false,
+        "redLightLevel": 0,
+        "greenLightLevel": 0,
+        "blueLightLevel": 0,
+        "transparentStandalone": false,
+        "textures": { "all": format!("{}.png", name.to_lowercase()) }
+    });
+
+    merge(&mut block, &overrides, true);
+
+    serde_json::from_value(block).unwrap()
+}
+
+/// A registry of air, stone, glass, a red torch, a bed two voxels long, the blocks of a
+/// circuit, water and leaves that dim the light going through them, sand that falls, a
+/// log holding leaves up, dirt to till into farmland and grow wheat on, and lava
+pub fn registry() -> Registry {
+    let mut blocks = HashMap::new();
+
+    blocks.insert(
+        AIR,
+        block(
+            "Air",
+            json!({
+                "isEmpty": true,
+                "isSolid": false,
+                "isTransparent": true,
+                "isBlock": false
+            }),
+        ),
+    );
+    blocks.insert(STONE, block("Stone", json!({})));
+    blocks.insert(
+        GLASS,
+        block(
+            "Glass",
+            json!({ "isTransparent": true, "transparentStandalone": true }),
+        ),
+    );
+    blocks.insert(
+        TORCH,
+        block(
+            "Torch",
+            json!({
+                "isSolid": false,
+                "isTransparent": true,
+                "isLight": true,
+                "redLightLevel": 15,
+                "support": "attached"
+            }),
+        ),
+    );
+
+    blocks.insert(
+        BED,
+        block(
+            "Bed",
+            json!({
+                "yRotatable": true,
+                "parts": [{ "block": "Bed Foot", "offset": [0, 0, 1] }]
+            }),
+        ),
+    );
+    blocks.insert(BED_FOOT, block("Bed Foot", json!({ "partOf": "Bed" })));
+
+    blocks.insert(
+        POWER_SOURCE,
+        block("Power Source", json!({ "powerLevel": 15 })),
+    );
+    blocks.insert(
+        POWER_WIRE,
+        block("Power Wire", json!({ "conductsPower": true })),
+    );
+    blocks.insert(
+        POWER_LAMP,
+        block("Power Lamp", json!({ "consumesPower": true })),
+    );
+
+    blocks.insert(
+        WATER,
+        block(
+            "Water",
+            json!({ "isFluid": true, "isTransparent": true, "lightReduce": 2 }),
+        ),
+    );
+    blocks.insert(
+        LEAVES,
+        block(
+            "Oak Leaves",
+            json!({
+                "isLeaves": true,
+                "isTransparent": true,
+                "lightReduce": 2,
+                "transparentStandalone": true
+            }),
+        ),
+    );
+
+    blocks.insert(SAND, block("Sand", json!({ "hasGravity": true })));
+    blocks.insert(LOG, block("Oak Log", json!({ "isLog": true })));
+
+    blocks.insert(DIRT, block("Dirt", json!({ "isTillable": true })));
+    blocks.insert(FARMLAND, block("Farmland", json!({ "isFarmland": true })));
+    blocks.insert(
+        WHEAT,
+        block(
+            "Wheat",
+            json!({
+                "isSolid": false,
+                "isTransparent": true,
+                "isPlant": true,
+                "isBlock": false,
+                "growthStages": 8
+            }),
+        ),
+    );
+
+    blocks.insert(LAVA, block("Lava", json!({ "isFluid": true })));
+
+    Registry::from_blocks(blocks)
+}
+
+/// A small world that isn't saved
+pub fn config() -> WorldConfig {
+    sized_config(8, 32, 2)
+}
+
+/// A world that isn't saved, with chunks `chunk_size` wide and `max_height` tall
+pub fn sized_config(chunk_size: usize, max_height: u32, sub_chunks: u32) -> WorldConfig {
+    serde_json::from_value(json!({
+        "chunkSize": chunk_size,
+        "dimension": 1,
+        "maxHeight": max_height,
+        "maxLightLevel": 15,
+        "save": false,
+        "chunkRoot": "data",
+        "renderRadius": 16,
+        "maxRenderRadius": 32,
+        "maxLoadedChunks": 1000,
+        "subChunks": sub_chunks,
+        "generation": "flat",
+        "playerDimensions": [0.6, 1.8, 0.6],
+        "playerHead": 1.6,
+        "maxPerThread": 10,
+        "serverTickRate": 2,
+        "tps": 50.0,
+        "randomTickSpeed": 0,
+        "skyLight": true,
+        "portalScale": 1.0
+    }))
+    .unwrap()
+}
+
+pub fn metrics() -> WorldMetrics {
+    WorldMetrics::from_config(&config())
+}
+
+fn biomes() -> Biomes {
+    let biome = json!({
+        "name": "Plains",
+        "presets": [[0.5, 0.5]],
+        "blocks": { "cover": "Stone" },
+        "config": {
+            "scale": 0.004,
+            "octaves": 3,
+            "persistence": 0.8,
+            "lacunarity": 0.4,
+            "heightOffset": 8,
+            "heightScale": 0.01,
+            "treeScale": 0.1,
+            "plantScale": 0.2,
+            "amplifier": 0.8
+        }
+    });
+
+    let configs: BiomeConfigs = serde_json::from_value(json!({
+        "temperatureScale": 0.0003,
+        "temperatureSeed": 1,
+        "humidityScale": 0.003,
+        "humiditySeed": 2,
+        "riverScale": 0.008,
+        "riverSeed": 3,
+        "waterHeight": 4,
+        "solidThreshold": -0.6,
+        "riverThreshold": 0.02,
+        "radiusScale": 1.3,
+        "radiusMinimum": 0.1,
+        "river": biome,
+        "biomes": [biome]
+    }))
+    .unwrap();
+
+    Biomes::from_configs(configs)
+}
+
+/// Chunks within `radius` chunks of the origin, filled with stone below `floor` and lit.
+///
+/// Nothing is generated: every chunk is there from the start, like a world that's been
+/// played in, so updates light and remesh them right away.
+pub fn chunks(radius: i32, floor: i32) -> Chunks {
+    chunks_with(radius, |_, vy, _| vy < floor)
+}
+
+/// Chunks of rolling hills, for meshes with many more faces than flat ground
+pub fn hilly_chunks(radius: i32) -> Chunks {
+    chunks_with(radius, |vx, vy, vz| {
+        let height = 12.0 + (vx as f32 * 0.4).sin() * 4.0 + (vz as f32 * 0.3).cos() * 4.0;
+        (vy as f32) < height
+    })
+}
+
+/// Chunks of stone riddled with tunnels, for light that has to find its way underground
+pub fn cave_chunks(radius: i32) -> Chunks {
+    chunks_with(radius, |vx, vy, vz| {
+        let (x, y, z) = (vx as f32 * 0.5, vy as f32 * 0.5, vz as f32 * 0.5);
+        let tunnel = x.sin() * y.cos() + y.sin() * z.cos() + z.sin() * x.cos();
+        vy < 24 && (vy == 0 || tunnel < 0.6)
+    })
+}
+
+/// Chunks within `radius` chunks of the origin, with stone wherever `is_solid` says and lit,
+/// like the ones of `chunks`
+pub fn chunks_with(radius: i32, is_solid: impl Fn(i32, i32, i32) -> bool) -> Chunks {
+    sized_chunks_with(config(), radius, is_solid)
+}
+
+/// Chunks like the ones of `chunks_with`, in a world of another size
+pub fn sized_chunks_with(
+    config: WorldConfig,
+    radius: i32,
+    is_solid: impl Fn(i32, i32, i32) -> bool,
+) -> Chunks {
+    let size = config.chunk_size as i32;
+    let max_height = config.max_height as i32;
+
+    let mut chunks = Chunks::from_parts(
+        "test",
+        config.clone(),
+        Arc::new(registry()),
+        biomes(),
+        Structures::from_templates(HashMap::new(), &config.generation, LEVEL_SEED),
+    );
+
+    let mut coords = vec![];
+
+    for cx in -radius..=radius {
+        for cz in -radius..=radius {
+            let mut chunk = Chunk::new(Vec2(cx, cz), &config, None);
+            chunk.needs_terrain = false;
+            chunk.needs_decoration = false;
+
+            chunks.add_chunk(chunk);
+            coords.push(Vec2(cx, cz));
+        }
+    }
+
+    for vx in -radius * size..(radius + 1) * size {
+        for vz in -radius * size..(radius + 1) * size {
+            let mut height = 0;
+
+            for vy in 0..max_height {
+                if is_solid(vx, vy, vz) {
+                    chunks.set_voxel_by_voxel(vx, vy, vz, STONE);
+                    height = vy;
+                }
+            }
+
+            chunks.set_max_height(vx, vz, height as u32);
+        }
+    }
+
+    coords.iter().for_each(|coords| {
+        chunks.get_chunk_mut(coords).unwrap().calc_is_empty();
+    });
+
+    chunks.propagate_chunks(&coords);
+
+    chunks
+}
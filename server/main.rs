@@ -1,36 +1,126 @@
-use log::info;
+use std::{env, process, sync::Arc};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{error, info};
 
 use actix::SystemService;
 use actix_cors::Cors;
 use actix_files as fs;
 use actix_web::{web, App, HttpServer};
 
-use server_core::network::{message, routes, server::WsServer};
-
-fn setup_logger() -> Result<(), fern::InitError> {
-    fern::Dispatch::new()
-        .format(|out, message, record| {
-            out.finish(format_args!(
-                "{} [{}] [{}]: {}",
-                chrono::Local::now().format("[%H:%M:%S]"),
-                record.level(),
-                record.target(),
-                message
-            ))
-        })
-        .level(log::LevelFilter::Debug)
-        .chain(std::io::stdout())
-        // .chain(fern::log_file("output.log")?)
-        .apply()?;
-
-    Ok(())
+use server_core::{
+    engine::{
+        chunks::Chunks, config::Configs, prune::prune_chunks, registry::Registry,
+        world::WorldConfig,
+    },
+    logger::LogConfig,
+    network::{listener::ListenerConfig, message, rcon, routes, server::WsServer},
+};
+
+/// Config of a world that's saved to disk, exiting if there's no such world
+fn load_saved_world(world: &str) -> (WorldConfig, Arc<Registry>) {
+    let (mut configs, registry) = Configs::load_worlds("assets/metadata/worlds.json");
+
+    let config = match configs.remove(world) {
+        Some((_, config)) => config,
+        None => {
+            error!("No world named \"{}\" in worlds.json.", world);
+            process::exit(1);
+        }
+    };
+
+    if !config.save {
+        error!("World \"{}\" isn't saved to disk.", world);
+        process::exit(1);
+    }
+
+    (config, registry)
 }
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    setup_logger().expect("Something went wrong with fern...");
+/// Generate, light and save a world's chunks ahead of time, resuming any earlier run
+fn pregen(world: &str, radius: i16) {
+    let (config, registry) = load_saved_world(world);
+
+    let mut chunks = Chunks::new(world, config, registry);
+
+    let bar = ProgressBar::new(0);
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template("{elapsed_precise} [{bar:40}] {pos}/{len} chunks, {eta} left")
+            .progress_chars("=> "),
+    );
+
+    chunks.pregenerate(radius, |done, total| {
+        bar.set_length(total as u64);
+        bar.set_position(done as u64);
+    });
+
+    bar.finish();
+    info!(
+        "Pregenerated world \"{}\" within {} chunks of the origin.",
+        world, radius
+    );
+}
+
+/// Delete a world's saved chunks that are outside of its border or never changed
+fn prune(world: &str, dry_run: bool) {
+    let (config, registry) = load_saved_world(world);
+
+    let report = prune_chunks(world, &config, &registry, dry_run);
 
-    let addr = "localhost:4000";
+    info!(
+        "{} {} of {} saved chunks in world \"{}\" ({} outside the border, {} unmodified), reclaiming {:.1} MiB.",
+        if dry_run { "Would prune" } else { "Pruned" },
+        report.outside_border + report.unmodified,
+        report.scanned,
+        world,
+        report.outside_border,
+        report.unmodified,
+        report.bytes as f64 / (1024.0 * 1024.0)
+    );
+}
+
+fn main() -> std::io::Result<()> {
+    LogConfig::load("assets/metadata/server.json")
+        .apply()
+        .expect("Something went wrong with fern...");
+
+    let args = env::args().collect::<Vec<_>>();
+
+    match args.get(1).map(String::as_str) {
+        Some("pregen") => {
+            let radius = args.get(3).and_then(|radius| radius.parse::<i16>().ok());
+
+            match (args.get(2), radius) {
+                (Some(world), Some(radius)) if radius >= 0 => pregen(world, radius),
+                _ => {
+                    error!("Usage: {} pregen <world> <radius in chunks>", args[0]);
+                    process::exit(1);
+                }
+            }
+
+            Ok(())
+        }
+        Some("prune") => {
+            let dry_run = args.get(3).map(String::as_str) == Some("--dry-run");
+
+            match args.get(2) {
+                Some(world) if args.len() == 3 || dry_run => prune(world, dry_run),
+                _ => {
+                    error!("Usage: {} prune <world> [--dry-run]", args[0]);
+                    process::exit(1);
+                }
+            }
+
+            Ok(())
+        }
+        _ => serve(),
+    }
+}
+
+#[actix_web::main]
+async fn serve() -> std::io::Result<()> {
+    let listener = ListenerConfig::load("assets/metadata/server.json");
 
     let srv = HttpServer::new(move || {
         let cors = Cors::permissive();
@@ -42,21 +132,36 @@ async fn main() -> std::io::Result<()> {
             .service(routes::worlds)
             .service(routes::world)
             .service(routes::time)
+            .service(routes::atlas)
+            .service(routes::map_tile)
+            .service(routes::metrics)
             .service(web::resource("/ws/").to(routes::ws_route))
-            .service(fs::Files::new("/atlas/", "assets/textures/generated/").show_files_listing())
             .service(
                 fs::Files::new("/procedural/", "assets/textures/procedural").show_files_listing(),
             )
             .service(fs::Files::new("/packs/", "assets/textures/packs/").show_files_listing())
             .service(fs::Files::new("/models/", "assets/models/objects/").show_files_listing())
             .service(fs::Files::new("/", "public/").show_files_listing())
-    })
-    .bind(&addr)?;
+    });
+
+    let srv = match &listener.tls {
+        Some(tls) => srv.bind_rustls(&listener.address, tls.rustls_config()?)?,
+        None => srv.bind(&listener.address)?,
+    };
 
-    info!("🚀  MineJS running on http://{}", &addr);
+    let scheme = if listener.tls.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    info!("🚀  MineJS running on {}://{}", scheme, &listener.address);
 
     // Wake up the sever
     WsServer::from_registry().do_send(message::Noop);
 
+    if let Some(rcon) = listener.rcon.clone() {
+        rcon::start(rcon, WsServer::from_registry())?;
+    }
+
     srv.run().await
 }
@@ -0,0 +1,317 @@
+use std::{cell::RefCell, collections::HashMap, f32::consts::PI, io::Read, rc::Rc, time::Duration};
+
+use actix_rt::time::interval;
+use awc::ws::{Frame, Message as WsMessage};
+use futures::{channel::mpsc, FutureExt, StreamExt};
+use libflate::zlib::Decoder;
+use rand::Rng;
+
+use server_core::{
+    constants::PROTOCOL_VERSION,
+    network::{
+        message::FullWorldData,
+        models::{create_of_type, decode_message, encode_message, messages, MessageType},
+    },
+};
+
+/// Largest frame a bot takes from the server, chunk loads included
+const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+/// Milliseconds between the position updates a bot sends, as often as the web client does
+const PEER_INTERVAL: u64 = 16;
+
+/// Chunks around themselves bots ask the server for, kept under its limit per request
+const RENDER_RADIUS: i32 = 2;
+
+/// Voxels a second bots walk at
+const WALK_SPEED: f32 = 4.0;
+
+/// Chance a bot turns somewhere else each update
+const TURN_CHANCE: f64 = 0.01;
+
+/// Updates between a bot placing a block and breaking it again
+const BUILD_INTERVAL: u32 = 120;
+
+/// Block bots build with
+const BUILD_BLOCK: &str = "Stone";
+
+/// How all of the bots are doing, shared between them and the reports
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Bots that made it into the world
+    pub connected: usize,
+    /// Latest round trip time the server measured for each bot, in milliseconds
+    pub latencies: HashMap<usize, u32>,
+    /// Messages received since the last report
+    pub messages: u64,
+    /// Bytes received since the last report, as written by the server
+    pub bytes: u64,
+    /// Block updates sent since the last report
+    pub updates: u64,
+    /// Why bots got disconnected, if they were
+    pub errors: Vec<String>,
+}
+
+/// Where a bot is and what it's up to
+#[derive(Debug, Default)]
+struct Walker {
+    id: String,
+    position: [f32; 3],
+    heading: f32,
+    chunk: Option<(i32, i32)>,
+    updates: u32,
+    placed: Option<[i32; 3]>,
+}
+
+/// A simulated player that walks around randomly, placing and breaking blocks as it goes
+pub struct Bot {
+    index: usize,
+    name: String,
+    world: Rc<FullWorldData>,
+    report: Rc<RefCell<Report>>,
+}
+
+impl Bot {
+    pub fn new(index: usize, world: Rc<FullWorldData>, report: Rc<RefCell<Report>>) -> Self {
+        Self {
+            index,
+            name: format!("bot{}", index),
+            world,
+            report,
+        }
+    }
+
+    /// Connect to the server and play until disconnected, noting down why in the report
+    pub async fn run(self, url: String) {
+        let walker = Rc::new(RefCell::new(Walker::default()));
+        let result = self.play(url, &walker).await;
+
+        let mut report = self.report.borrow_mut();
+        report.latencies.remove(&self.index);
+
+        if !walker.borrow().id.is_empty() {
+            report.connected -= 1;
+        }
+
+        if let Err(err) = result {
+            report
+                .errors
+                .push(format!("{} disconnected: {}", self.name, err));
+        }
+    }
+
+    async fn play(&self, url: String, walker: &Rc<RefCell<Walker>>) -> Result<(), String> {
+        let (_, connection) = awc::Client::new()
+            .ws(url)
+            .max_frame_size(MAX_FRAME_SIZE)
+            .connect()
+            .await
+            .map_err(|err| err.to_string())?;
+
+        let (sink, mut stream) = connection.split();
+        let (sender, receiver) = mpsc::unbounded();
+        actix_rt::spawn(receiver.map(Ok).forward(sink).map(|_| ()));
+
+        let mut handshake = create_of_type(MessageType::Handshake);
+        handshake.json = serde_json::json!({
+            "version": PROTOCOL_VERSION,
+            "capabilities": ["zlib", "rgbLight", "batch"],
+        })
+        .to_string();
+        send(&sender, &handshake);
+
+        while let Some(frame) = stream.next().await {
+            match frame.map_err(|err| err.to_string())? {
+                Frame::Binary(bytes) => {
+                    self.report.borrow_mut().bytes += bytes.len() as u64;
+
+                    for message in unpack(&bytes)? {
+                        self.on_message(message, walker, &sender)?;
+                    }
+                }
+                Frame::Ping(payload) => {
+                    let _ = sender.unbounded_send(WsMessage::Pong(payload));
+                }
+                Frame::Close(reason) => return Err(format!("Closed: {:?}", reason)),
+                _ => {}
+            }
+        }
+
+        Err("Connection lost".to_owned())
+    }
+
+    fn on_message(
+        &self,
+        message: messages::Message,
+        walker: &Rc<RefCell<Walker>>,
+        sender: &mpsc::UnboundedSender<WsMessage>,
+    ) -> Result<(), String> {
+        self.report.borrow_mut().messages += 1;
+
+        match MessageType::from_i32(message.r#type) {
+            Some(MessageType::Error) => return Err(message.text),
+            Some(MessageType::Init) => {
+                let json = message.parse_json().map_err(|err| err.to_string())?;
+                let spawn = |i: usize| json["spawn"][i].as_f64().unwrap_or_default() as f32;
+                let dimension = self.world.dimension as f32;
+
+                {
+                    let mut state = walker.borrow_mut();
+                    state.id = json["id"].as_str().unwrap_or_default().to_owned();
+                    state.position = [
+                        (spawn(0) + 0.5) * dimension,
+                        (spawn(1) + 1.0) * dimension,
+                        (spawn(2) + 0.5) * dimension,
+                    ];
+                    state.heading = rand::thread_rng().gen_range(0.0..PI * 2.0);
+                }
+
+                self.report.borrow_mut().connected += 1;
+                actix_rt::spawn(self.walk(Rc::clone(walker), sender.clone()));
+            }
+            Some(MessageType::Latency) => {
+                let json = message.parse_json().map_err(|err| err.to_string())?;
+                let id = walker.borrow().id.to_owned();
+
+                if let Some(latency) = json["latencies"][id].as_u64() {
+                    self.report
+                        .borrow_mut()
+                        .latencies
+                        .insert(self.index, latency as u32);
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Walk around until the connection closes, telling the server where the bot is every update
+    fn walk(
+        &self,
+        walker: Rc<RefCell<Walker>>,
+        sender: mpsc::UnboundedSender<WsMessage>,
+    ) -> impl std::future::Future<Output = ()> {
+        let name = self.name.to_owned();
+        let world = Rc::clone(&self.world);
+        let report = Rc::clone(&self.report);
+
+        async move {
+            let mut ticks = interval(Duration::from_millis(PEER_INTERVAL));
+            let step = WALK_SPEED * world.dimension as f32 * PEER_INTERVAL as f32 / 1000.0;
+            let chunk_width = (world.chunk_size * world.dimension) as f32;
+            let block = world
+                .blocks
+                .iter()
+                .find(|(_, block)| block.name == BUILD_BLOCK)
+                .map_or(1, |(&id, _)| id);
+
+            while !sender.is_closed() {
+                ticks.tick().await;
+
+                let mut rng = rand::thread_rng();
+                let mut walker = walker.borrow_mut();
+
+                if rng.gen_bool(TURN_CHANCE) {
+                    walker.heading = rng.gen_range(0.0..PI * 2.0);
+                }
+
+                walker.position[0] += walker.heading.cos() * step;
+                walker.position[2] += walker.heading.sin() * step;
+
+                let [px, py, pz] = walker.position;
+                let mut peer = create_of_type(MessageType::Peer);
+                peer.peers = vec![messages::Peer {
+                    id: walker.id.to_owned(),
+                    name: name.to_owned(),
+                    px,
+                    py,
+                    pz,
+                    qw: 1.0,
+                    ..Default::default()
+                }];
+                send(&sender, &peer);
+
+                // ask for the chunks around the bot whenever it walks into another one
+                let chunk = (
+                    (px / chunk_width).floor() as i32,
+                    (pz / chunk_width).floor() as i32,
+                );
+
+                if walker.chunk != Some(chunk) {
+                    walker.chunk = Some(chunk);
+
+                    let chunks = (-RENDER_RADIUS..=RENDER_RADIUS)
+                        .flat_map(|x| {
+                            (-RENDER_RADIUS..=RENDER_RADIUS)
+                                .map(move |z| [chunk.0 + x, chunk.1 + z])
+                        })
+                        .collect::<Vec<_>>();
+
+                    let mut request = create_of_type(MessageType::Request);
+                    request.json = serde_json::json!({ "chunks": chunks }).to_string();
+                    send(&sender, &request);
+                }
+
+                // place a block beside the bot, then break it the next time around
+                walker.updates += 1;
+
+                if walker.updates % BUILD_INTERVAL == 0 {
+                    let dimension = world.dimension as f32;
+                    let voxel = match walker.placed.take() {
+                        Some(voxel) => (voxel, 0),
+                        None => {
+                            let voxel = [
+                                (px / dimension).floor() as i32 + rng.gen_range(-2..=2),
+                                (py / dimension).floor() as i32,
+                                (pz / dimension).floor() as i32 + rng.gen_range(-2..=2),
+                            ];
+                            walker.placed = Some(voxel);
+                            (voxel, block)
+                        }
+                    };
+
+                    let ([vx, vy, vz], r#type) = voxel;
+                    let mut update = create_of_type(MessageType::Update);
+                    update.updates = vec![messages::Update {
+                        vx,
+                        vy,
+                        vz,
+                        r#type,
+                        ..Default::default()
+                    }];
+                    send(&sender, &update);
+
+                    report.borrow_mut().updates += 1;
+                }
+            }
+        }
+    }
+}
+
+fn send(sender: &mpsc::UnboundedSender<WsMessage>, message: &messages::Message) {
+    let _ = sender.unbounded_send(WsMessage::Binary(encode_message(message).into()));
+}
+
+/// Inflate a frame if the server compressed it, then take out the messages batched into it
+fn unpack(bytes: &[u8]) -> Result<Vec<messages::Message>, String> {
+    let inflated;
+    let mut bytes = bytes;
+
+    if bytes.starts_with(&[0x78, 0x9c]) {
+        let mut buffer = vec![];
+        Decoder::new(bytes)
+            .and_then(|mut decoder| decoder.read_to_end(&mut buffer))
+            .map_err(|err| err.to_string())?;
+        inflated = buffer;
+        bytes = &inflated;
+    }
+
+    let message = decode_message(bytes).map_err(|err| err.to_string())?;
+
+    Ok(if message.r#type == MessageType::Batch as i32 {
+        message.batch
+    } else {
+        vec![message]
+    })
+}
@@ -0,0 +1,131 @@
+//! Connects simulated players to a running server to load test it, reporting how long the
+//! server takes to hear back from them
+//!
+//! Usage: `bot <count> [world]`, connecting to the address in `server.json`.
+
+mod bot;
+
+use std::{cell::RefCell, env, process, rc::Rc, time::Duration};
+
+use actix_rt::time::{delay_for, interval};
+
+use server_core::network::{
+    listener::ListenerConfig,
+    message::{FullWorldData, SimpleWorldData},
+};
+
+use bot::{Bot, Report};
+
+/// Seconds between reports
+const REPORT_INTERVAL: u64 = 5;
+
+/// Milliseconds between bots connecting, so that they don't all join on the same tick
+const CONNECT_INTERVAL: u64 = 50;
+
+/// Largest world description taken from the server
+const MAX_WORLD_SIZE: usize = 16 * 1024 * 1024;
+
+/// Fetch the description of a world, the server's first one if not named
+async fn fetch_world(address: &str, world: Option<&String>) -> Result<FullWorldData, String> {
+    let client = awc::Client::new();
+
+    let name = match world {
+        Some(name) => name.to_owned(),
+        None => client
+            .get(format!("http://{}/worlds", address))
+            .send()
+            .await
+            .map_err(|err| err.to_string())?
+            .json::<Vec<SimpleWorldData>>()
+            .limit(MAX_WORLD_SIZE)
+            .await
+            .map_err(|err| err.to_string())?
+            .into_iter()
+            .next()
+            .map(|world| world.name)
+            .ok_or("The server has no worlds.")?,
+    };
+
+    client
+        .get(format!("http://{}/world?world={}", address, name))
+        .send()
+        .await
+        .map_err(|err| err.to_string())?
+        .json::<FullWorldData>()
+        .limit(MAX_WORLD_SIZE)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// Print how the bots have been doing since the last report, then start counting anew
+fn print_report(report: &mut Report, count: usize) {
+    let latencies = report.latencies.values().copied().collect::<Vec<_>>();
+    let average = latencies.iter().sum::<u32>() as f32 / latencies.len().max(1) as f32;
+    let max = latencies.iter().max().copied().unwrap_or_default();
+    let seconds = REPORT_INTERVAL as f32;
+
+    println!(
+        "{}/{} bots in, latency {:.1}ms average, {}ms max, receiving {:.0} messages ({:.1} KiB) and sending {:.1} block updates a second",
+        report.connected,
+        count,
+        average,
+        max,
+        report.messages as f32 / seconds,
+        report.bytes as f32 / 1024.0 / seconds,
+        report.updates as f32 / seconds,
+    );
+
+    report
+        .errors
+        .drain(..)
+        .for_each(|err| println!("  {}", err));
+    report.messages = 0;
+    report.bytes = 0;
+    report.updates = 0;
+}
+
+#[actix_rt::main]
+async fn main() {
+    let args = env::args().collect::<Vec<_>>();
+
+    let count = match args.get(1).and_then(|count| count.parse::<usize>().ok()) {
+        Some(count) if count > 0 => count,
+        _ => {
+            eprintln!("Usage: {} <count> [world]", args[0]);
+            process::exit(1);
+        }
+    };
+
+    let address = ListenerConfig::load("assets/metadata/server.json").address;
+
+    let world = match fetch_world(&address, args.get(2)).await {
+        Ok(world) => Rc::new(world),
+        Err(err) => {
+            eprintln!("Could not get the world from {}: {}", address, err);
+            process::exit(1);
+        }
+    };
+
+    println!(
+        "Connecting {} bots to world \"{}\" on {}",
+        count, world.name, address
+    );
+
+    let url = format!("ws://{}/ws/?world={}", address, world.name);
+    let report = Rc::new(RefCell::new(Report::default()));
+
+    for index in 0..count {
+        let bot = Bot::new(index, Rc::clone(&world), Rc::clone(&report));
+        actix_rt::spawn(bot.run(url.to_owned()));
+
+        delay_for(Duration::from_millis(CONNECT_INTERVAL)).await;
+    }
+
+    let mut reports = interval(Duration::from_secs(REPORT_INTERVAL));
+    reports.tick().await;
+
+    loop {
+        reports.tick().await;
+        print_report(&mut report.borrow_mut(), count);
+    }
+}
@@ -1,53 +1,44 @@
-use std::{
-    fs::{self, File},
-    path::PathBuf,
-    str::FromStr,
-};
+use std::path::PathBuf;
 
-use server_core::engine::{chunk::Chunk, config::Configs, registry::Registry};
-use server_utils::convert::parse_chunk_name;
+use server_core::engine::{
+    chunk::Chunk,
+    config::Configs,
+    registry::Registry,
+    storage::{self, Storage},
+};
 
 use indicatif::{ProgressBar, ProgressStyle};
 
-pub fn loop_through_chunks(func: &dyn Fn(&mut Chunk, &Registry)) {
+pub fn loop_through_chunks(func: &dyn Fn(&mut Chunk, &Registry, &dyn Storage)) {
     let (configs, registry) = Configs::load_worlds("assets/metadata/worlds.json");
 
     configs.into_iter().for_each(|(name, (_, config))| {
         if config.save {
-            let path = PathBuf::from_str(format!("./data/{}/chunks", name).as_ref()).unwrap();
+            let root = PathBuf::from(&config.chunk_root).join(&name);
+            let storage = storage::open(config.storage, &root);
 
             println!("Processing world: {}", name);
 
-            let save_dir = fs::read_dir(&path).unwrap();
-            let pb = ProgressBar::new(save_dir.count() as u64);
+            let saved = storage.list_chunks();
+            let pb = ProgressBar::new(saved.len() as u64);
 
             pb.set_style(ProgressStyle::default_bar()
                 .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes}")
                 .progress_chars("#>-"));
 
-            let save_dir = fs::read_dir(&path).unwrap();
+            for (coords, _) in saved {
+                let data = storage.load_chunk(&coords);
 
-            for chunk_file in save_dir.flatten() {
-                let chunk_path = chunk_file.path();
-                if let Ok(chunk_data) = File::open(&chunk_path) {
-                    let results: Result<serde_json::Value, serde_json::Error> =
-                        serde_json::from_reader(chunk_data);
-                    if results.is_err() {
-                        // remove chunk file
-                        fs::remove_file(&chunk_path).unwrap();
+                if data.is_none() {
+                    // remove corrupted chunk
+                    storage.remove_chunks(&[coords]);
 
-                        continue;
-                    }
+                    continue;
                 }
 
-                let chunk_file = chunk_file.file_name().into_string().unwrap();
-                let chunk_file = &chunk_file[0..chunk_file.len() - 5];
-
-                let coords = parse_chunk_name(chunk_file);
-
-                let mut chunk = Chunk::new(coords.to_owned(), &config, &path);
+                let mut chunk = Chunk::new(coords, &config, data);
 
-                func(&mut chunk, &registry);
+                func(&mut chunk, &registry, storage.as_ref());
 
                 pb.inc(1);
             }
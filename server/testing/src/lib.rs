@@ -0,0 +1,244 @@
+//! Runs a world in-process for integration tests, with clients that read what the world
+//! sends them straight out of their outboxes instead of over a socket.
+//!
+//! Worlds are saved to memory and run in lockstep, ticking only when told to and playing out
+//! the same way every run, so that tests can follow joins, block edits and chunk loads one
+//! tick at a time:
+//!
+//! ```no_run
+//! use server_testing::TestServer;
+//!
+//! let mut server = TestServer::new();
+//! let client = server.join();
+//!
+//! server.tick();
+//! assert!(!client.receive().is_empty());
+//! ```
+
+use std::{cell::RefCell, collections::VecDeque, env, sync::Arc};
+
+use actix::{dev::channel, prelude::*};
+
+use server_common::vec::Vec3;
+use server_core::{
+    engine::{
+        config::Configs,
+        storage::StorageKind,
+        world::{World, WorldConfig, WorldMeta},
+    },
+    network::{
+        auth::Identity,
+        message::Flush,
+        models::{create_chat_message, create_of_type, messages, ChatType, MessageType},
+        outbox::SharedOutbox,
+    },
+};
+
+/// World the tests play in by default, flat and small
+const DEFAULT_WORLD: &str = "testbed";
+
+/// Seed test worlds run in lockstep with, unless configured otherwise
+pub const TEST_SEED: u64 = 1234;
+
+/// Radius in chunks loaded around 0,0 on boot, for the chunks there and their neighbors to be
+/// decorated before anyone joins, like on a server
+const PRELOAD_RADIUS: i16 = 3;
+
+/// Ticks to wait for a message before giving up on it
+const MAX_TICKS: usize = 500;
+
+/// Stands in for a session, taking the world's wake-ups without writing anything out
+struct Loopback;
+
+impl Actor for Loopback {
+    type Context = Context<Self>;
+}
+
+impl Handler<Flush> for Loopback {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Flush, _ctx: &mut Self::Context) {}
+}
+
+/// A fake player in a test world
+pub struct TestClient {
+    pub id: usize,
+    /// Voxel the player spawned at
+    pub spawn: Vec3<i32>,
+    outbox: SharedOutbox,
+    /// Taken out of the outbox but not looked at yet
+    inbox: RefCell<VecDeque<messages::Message>>,
+    /// Kept around for the world to have somewhere to send wake-ups to
+    _mailbox: Context<Loopback>,
+}
+
+impl TestClient {
+    /// Take everything the world sent the player since last called, most urgent first
+    pub fn receive(&self) -> Vec<messages::Message> {
+        let mut inbox = self.inbox.borrow_mut();
+        inbox.extend(self.outbox.lock().unwrap().take(usize::MAX));
+        inbox.drain(..).collect()
+    }
+
+    /// Take the next message the world sent the player, if there's any
+    pub fn next_message(&self) -> Option<messages::Message> {
+        let mut inbox = self.inbox.borrow_mut();
+
+        if inbox.is_empty() {
+            inbox.extend(self.outbox.lock().unwrap().take(usize::MAX));
+        }
+
+        inbox.pop_front()
+    }
+}
+
+/// A world running in-process, saved to memory and ticked by hand in lockstep
+pub struct TestServer {
+    pub world: World,
+}
+
+impl Default for TestServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestServer {
+    /// Boot the testbed world
+    pub fn new() -> Self {
+        Self::with_world(DEFAULT_WORLD, |_, _| {})
+    }
+
+    /// Boot a world of `worlds.json`, changing its configs before it starts
+    pub fn with_world(
+        name: &str,
+        configure: impl FnOnce(&mut WorldMeta, &mut WorldConfig),
+    ) -> Self {
+        // the assets are read relative to the root of the repository
+        env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../..")).unwrap();
+
+        let (mut configs, registry) = Configs::load_worlds("assets/metadata/worlds.json");
+        let (mut meta, mut config) = configs
+            .remove(name)
+            .unwrap_or_else(|| panic!("No world named \"{}\".", name));
+
+        meta.preload = PRELOAD_RADIUS;
+        config.save = true;
+        config.storage = StorageKind::Memory;
+        config.lockstep = Some(TEST_SEED);
+
+        configure(&mut meta, &mut config);
+
+        let mut world = World::new(meta, config, registry);
+        world.preload();
+
+        Self { world }
+    }
+
+    /// Join a new player into the world, logged in as nobody in particular
+    pub fn join(&mut self) -> TestClient {
+        let (_, receiver) = channel::channel(16);
+        let mailbox = Context::<Loopback>::with_receiver(receiver);
+        let outbox = SharedOutbox::default();
+
+        let result = self.world.add_player(
+            None,
+            // named like a client would be by its handshake, nameless players get no chunks
+            Identity {
+                name: Some("Tester".to_owned()),
+                ..Identity::anonymous()
+            },
+            mailbox.address().recipient(),
+            Arc::clone(&outbox),
+            None,
+        );
+        let [vx, vy, vz] = result.spawn;
+
+        TestClient {
+            id: result.id,
+            spawn: Vec3(vx, vy, vz),
+            outbox,
+            inbox: RefCell::default(),
+            _mailbox: mailbox,
+        }
+    }
+
+    /// Take the player out of the world, like when their connection closes
+    pub fn leave(&mut self, client: TestClient) {
+        self.world.remove_player(&client.id);
+    }
+
+    /// Handle a message from a player as if it came over their connection
+    pub fn send(&mut self, client: &TestClient, message: messages::Message) {
+        self.world.on_message(client.id, message);
+    }
+
+    /// Say something in chat as the player, or run a command when it starts with `/`
+    pub fn chat(&mut self, client: &TestClient, body: &str) {
+        let message = create_chat_message(MessageType::Message, ChatType::Info, "", body);
+        self.send(client, message);
+    }
+
+    /// Ask for the chunks at some `[x, z]` coordinates, like a client does when it moves
+    pub fn request_chunks(&mut self, client: &TestClient, chunks: &[[i32; 2]]) {
+        let mut request = create_of_type(MessageType::Request);
+        request.json = serde_json::json!({ "chunks": chunks }).to_string();
+
+        self.send(client, request);
+    }
+
+    /// Ask for the horizons of far chunks at some `[x, z]` coordinates, to draw as heightfields
+    pub fn request_horizons(&mut self, client: &TestClient, chunks: &[[i32; 2]]) {
+        let mut request = create_of_type(MessageType::Request);
+        request.json = serde_json::json!({ "horizons": chunks }).to_string();
+
+        self.send(client, request);
+    }
+
+    /// Place a block at a voxel as the player, or break it with the block id 0
+    pub fn update(&mut self, client: &TestClient, voxel: &Vec3<i32>, id: u32) {
+        let &Vec3(vx, vy, vz) = voxel;
+        let mut update = create_of_type(MessageType::Update);
+        update.updates = vec![messages::Update {
+            vx,
+            vy,
+            vz,
+            r#type: id,
+            ..Default::default()
+        }];
+
+        self.send(client, update);
+    }
+
+    /// Run a single tick of the world
+    pub fn tick(&mut self) {
+        self.world.step();
+    }
+
+    /// Tick until the player receives a message that passes the check, throwing away the
+    /// ones that came before it
+    ///
+    /// Panics if nothing does within a few hundred ticks.
+    pub fn wait_for(
+        &mut self,
+        client: &TestClient,
+        mut check: impl FnMut(&messages::Message) -> bool,
+    ) -> messages::Message {
+        for _ in 0..MAX_TICKS {
+            while let Some(message) = client.next_message() {
+                if check(&message) {
+                    return message;
+                }
+            }
+
+            self.tick();
+        }
+
+        panic!("Player {} never received the message.", client.id);
+    }
+
+    /// Tick until the player receives a message of some type
+    pub fn wait_for_type(&mut self, client: &TestClient, r#type: MessageType) -> messages::Message {
+        self.wait_for(client, |message| message.r#type == r#type as i32)
+    }
+}
@@ -0,0 +1,42 @@
+use specs::WorldExt;
+
+use server_common::vec::Vec3;
+use server_core::{
+    comp::rigidbody::RigidBody,
+    network::models::{messages, MessageType},
+};
+use server_testing::TestServer;
+
+/// Where some pigs wander off to in a while, in a world run in lockstep with a seed
+fn wander(seed: u64) -> Vec<Vec3<f32>> {
+    let mut server = TestServer::with_world("testbed", |_, config| config.lockstep = Some(seed));
+    let client = server.join();
+
+    server.request_chunks(&client, &[[-1, -1], [-1, 0], [0, -1], [0, 0]]);
+    server.wait_for(&client, |message: &messages::Message| {
+        message.r#type == MessageType::Load as i32
+            && message.chunks.iter().any(|chunk| !chunk.voxels.is_empty())
+    });
+
+    let Vec3(_, sy, _) = client.spawn;
+    let pigs = (0..4)
+        .map(|i| {
+            let position = Vec3(i as f32 * 2.0 - 4.0, sy as f32 + 1.0, -2.0);
+            server.world.spawn_entity("Pig", &position).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    for _ in 0..300 {
+        server.tick();
+    }
+
+    let bodies = server.world.ecs().read_storage::<RigidBody>();
+    pigs.iter()
+        .map(|&pig| bodies.get(pig).unwrap().get_position())
+        .collect()
+}
+
+#[test]
+fn lockstep_works() {
+    assert_eq!(wander(7), wander(7));
+}
@@ -0,0 +1,457 @@
+use specs::{Entity, Join, WorldExt};
+
+use server_common::vec::Vec3;
+use server_core::{
+    comp::{
+        attack_cooldown::AttackCooldown,
+        dropped_item::DroppedItem,
+        health::Health,
+        id::Id,
+        inventory::{Inventory, ItemStack},
+        rigidbody::RigidBody,
+    },
+    engine::{
+        chunks::Chunks,
+        damage::{DamageEvent, DamageQueue},
+        difficulty::Difficulty,
+        gravity::GravityQueue,
+        world::WorldConfig,
+    },
+    gen::lights::LightColor,
+    network::models::{create_of_type, messages, MessageType},
+};
+use server_testing::{TestClient, TestServer};
+
+fn has_voxels(message: &messages::Message) -> bool {
+    message.r#type == MessageType::Load as i32
+        && message.chunks.iter().any(|chunk| !chunk.voxels.is_empty())
+}
+
+/// Whether a message is a chat message saying something
+fn says(message: &messages::Message, text: &str) -> bool {
+    message
+        .message
+        .as_ref()
+        .map_or(false, |chat| chat.body.contains(text))
+}
+
+/// The entity a player's body, health and inventory are on
+fn player_entity(server: &TestServer, client: &TestClient) -> Entity {
+    let ecs = server.world.ecs();
+    let ids = ecs.read_storage::<Id>();
+
+    (&ecs.entities(), &ids)
+        .join()
+        .find(|(_, id)| id.0 == client.id)
+        .map(|(entity, _)| entity)
+        .unwrap()
+}
+
+/// Kill a player outright, wherever they stand
+fn kill(server: &mut TestServer, client: &TestClient) {
+    let target = player_entity(server, client);
+
+    server
+        .world
+        .write_resource::<DamageQueue>()
+        .push(DamageEvent {
+            target,
+            amount: f32::MAX,
+            source: None,
+            from: Vec3::default(),
+            knockback: 0.0,
+        });
+    server.tick();
+
+    assert!(server.world.is_dead(client.id));
+}
+
+fn player_position(server: &TestServer, client: &TestClient) -> Vec3<f32> {
+    server
+        .world
+        .ecs()
+        .read_storage::<RigidBody>()
+        .get(player_entity(server, client))
+        .unwrap()
+        .get_position()
+}
+
+/// The voxel a player's feet are in
+fn player_voxel(server: &TestServer, client: &TestClient) -> Vec3<i32> {
+    let Vec3(px, py, pz) = player_position(server, client);
+    Vec3(px.floor() as i32, py.floor() as i32, pz.floor() as i32)
+}
+
+/// Move a player's feet to the middle of a voxel
+fn move_player(server: &mut TestServer, client: &TestClient, voxel: &Vec3<i32>) {
+    let entity = player_entity(server, client);
+    let &Vec3(vx, vy, vz) = voxel;
+
+    server
+        .world
+        .ecs()
+        .write_storage::<RigidBody>()
+        .get_mut(entity)
+        .unwrap()
+        .set_position(&Vec3(vx as f32 + 0.5, vy as f32, vz as f32 + 0.5));
+}
+
+#[test]
+fn join_and_load_chunks_works() {
+    let mut server = TestServer::new();
+    let client = server.join();
+
+    server.request_chunks(&client, &[[0, 0]]);
+
+    let load = server.wait_for(&client, has_voxels);
+    let chunk = &load.chunks[0];
+    assert_eq!((chunk.x, chunk.z), (0, 0));
+
+    let lights = server.wait_for(&client, |message| {
+        message.r#type == MessageType::Load as i32
+            && message.chunks.iter().any(|chunk| !chunk.lights.is_empty())
+    });
+    assert_eq!(lights.chunks[0].lights.len(), chunk.voxels.len());
+}
+
+#[test]
+fn horizons_work() {
+    let mut server = TestServer::new();
+    let client = server.join();
+
+    // far off chunks that were never loaded, generated just for their horizons
+    server.request_horizons(&client, &[[20, 0], [0, -20]]);
+
+    let message = server.wait_for_type(&client, MessageType::Horizon);
+    assert_eq!(message.horizons.len(), 2);
+
+    let horizon = &message.horizons[0];
+    assert_eq!((horizon.x, horizon.z), (20, 0));
+    assert_eq!(horizon.heights.len(), 12 * 12);
+
+    // the flat ground's top is the same all over
+    assert!(horizon.heights.iter().all(|&height| height == 29));
+    assert!(horizon.colors.iter().all(|&color| color != 0));
+}
+
+#[test]
+fn place_light_works() {
+    let mut server = TestServer::new();
+    let client = server.join();
+
+    server.request_chunks(&client, &[[0, 0]]);
+    server.wait_for(&client, has_voxels);
+
+    let Vec3(sx, sy, sz) = client.spawn;
+    let voxel = Vec3(sx + 1, sy + 1, sz + 1);
+    let &red = server
+        .world
+        .read_resource::<Chunks>()
+        .registry
+        .get_id_by_name("Color Red");
+
+    server.update(&client, &voxel, red);
+
+    let update = server.wait_for_type(&client, MessageType::Update);
+    assert!(update
+        .updates
+        .iter()
+        .any(|update| (update.vx, update.vy, update.vz, update.r#type)
+            == (voxel.0, voxel.1, voxel.2, red)));
+
+    let chunks = server.world.read_resource::<Chunks>();
+    let Vec3(vx, vy, vz) = voxel;
+    assert_eq!(chunks.get_voxel_by_voxel(vx, vy, vz), red);
+    assert_eq!(chunks.get_torch_light(vx, vy, vz, &LightColor::Red), 8);
+    assert_eq!(chunks.get_torch_light(vx, vy + 1, vz, &LightColor::Red), 7);
+}
+
+#[test]
+fn leave_works() {
+    let mut server = TestServer::new();
+    let first = server.join();
+    let second = server.join();
+    let id = second.id;
+
+    server.leave(second);
+
+    let leave = server.wait_for_type(&first, MessageType::Leave);
+    assert_eq!(leave.text, id.to_string());
+}
+
+#[test]
+fn peaceful_despawns_hostiles_works() {
+    let mut server = TestServer::new();
+    let client = server.join();
+
+    server.request_chunks(&client, &[[0, 0]]);
+    server.wait_for(&client, has_voxels);
+
+    let Vec3(sx, sy, sz) = client.spawn;
+    let position = Vec3(sx as f32, sy as f32 + 1.0, sz as f32 + 2.0);
+    let zombie = server.world.spawn_entity("Zombie", &position).unwrap();
+    let pig = server.world.spawn_entity("Pig", &position).unwrap();
+
+    server.world.write_resource::<WorldConfig>().difficulty = Difficulty::Peaceful;
+    server.tick();
+
+    assert!(!server.world.ecs().is_alive(zombie));
+    assert!(server.world.ecs().is_alive(pig));
+}
+
+#[test]
+fn team_spawn_needs_owner_works() {
+    let mut server = TestServer::new();
+    let owner = server.join();
+    let member = server.join();
+
+    server.chat(&owner, "/team join red");
+    server.wait_for(&owner, |message| says(message, "joined team red"));
+
+    // started teams take an invite from their owner to join
+    server.chat(&member, "/team join red");
+    server.wait_for(&member, |message| says(message, "needs an invite"));
+
+    server.chat(&member, "/nick member");
+    server.wait_for(&member, |message| says(message, "Renamed to member"));
+    server.chat(&owner, "/team invite member");
+    server.wait_for(&owner, |message| {
+        says(message, "member can now join team red")
+    });
+    server.chat(&member, "/team join red");
+    server.wait_for(&member, |message| says(message, "member joined team red"));
+
+    // members other than the owner can't move the team's spawn
+    server.chat(&member, "/team spawn");
+    server.wait_for(&member, |message| says(message, "Only the team's owner"));
+    server.chat(&member, "/team spawn clear");
+    server.wait_for(&member, |message| says(message, "Only the team's owner"));
+
+    server.chat(&owner, "/team spawn");
+    server.wait_for(&owner, |message| says(message, "Team red respawns at"));
+}
+
+#[test]
+fn sand_stack_falls_works() {
+    let mut server = TestServer::new();
+    let client = server.join();
+
+    server.request_chunks(&client, &[[0, 0]]);
+    server.wait_for(&client, has_voxels);
+
+    let Vec3(sx, _, sz) = client.spawn;
+    let (vx, vz) = (sx + 2, sz + 2);
+
+    // three blocks of sand with three blocks of air under them
+    let (sand, ground) = {
+        let mut chunks = server.world.write_resource::<Chunks>();
+        let sand = *chunks.registry.get_id_by_name("Sand");
+        let ground = chunks.get_max_height(vx, vz) as i32;
+
+        for vy in ground + 4..ground + 7 {
+            chunks.update(vx, vy, vz, sand, 0, 0);
+        }
+
+        (sand, ground)
+    };
+    server
+        .world
+        .write_resource::<GravityQueue>()
+        .push(Vec3(vx, ground + 4, vz));
+
+    let is_sand = |server: &TestServer, vy: i32| {
+        server
+            .world
+            .read_resource::<Chunks>()
+            .get_voxel_by_voxel(vx, vy, vz)
+            == sand
+    };
+
+    // the bottom block falls first, and the ones above it on the ticks after
+    server.tick();
+    assert!(!is_sand(&server, ground + 4) && is_sand(&server, ground + 5));
+
+    server.tick();
+    assert!(!is_sand(&server, ground + 5) && is_sand(&server, ground + 6));
+
+    server.tick();
+    assert!(!is_sand(&server, ground + 6));
+
+    // and they land on top of each other
+    for _ in 0..200 {
+        if (1..=3).all(|dy| is_sand(&server, ground + dy)) {
+            return;
+        }
+
+        server.tick();
+    }
+
+    panic!("The sand never landed.");
+}
+
+#[test]
+fn hits_need_cooldown_and_clear_line_works() {
+    let mut server = TestServer::new();
+    let client = server.join();
+
+    server.request_chunks(&client, &[[0, 0]]);
+    server.wait_for(&client, has_voxels);
+
+    let Vec3(sx, _, sz) = client.spawn;
+    let player = player_entity(&server, &client);
+
+    // the player stands on the ground, with a pig a few blocks off and a step in front of its feet
+    let vy = server
+        .world
+        .read_resource::<Chunks>()
+        .get_max_height(sx, sz) as i32
+        + 1;
+    server
+        .world
+        .ecs()
+        .write_storage::<RigidBody>()
+        .get_mut(player)
+        .unwrap()
+        .set_position(&Vec3(sx as f32 + 0.3, vy as f32, sz as f32 + 0.3));
+
+    let position = Vec3(sx as f32 + 3.5, vy as f32, sz as f32 - 0.1);
+    let pig = server.world.spawn_entity("Pig", &position).unwrap();
+
+    let stone = *server
+        .world
+        .read_resource::<Chunks>()
+        .registry
+        .get_id_by_name("Stone");
+    server
+        .world
+        .write_resource::<Chunks>()
+        .update(sx + 1, vy, sz, stone, 0, 0);
+
+    let mut hit = create_of_type(MessageType::Interact);
+    hit.json = serde_json::json!({ "action": "hit", "entity": pig.id().to_string() }).to_string();
+    let hits = |server: &TestServer| server.world.read_resource::<DamageQueue>().len();
+
+    // the step hides the pig's feet but not its head
+    server.send(&client, hit.clone());
+    assert_eq!(hits(&server), 1);
+
+    // hitting again before the cooldown is up does nothing
+    server.send(&client, hit.clone());
+    assert_eq!(hits(&server), 1);
+
+    // with the cooldown over, a wall in between still blocks the hit
+    {
+        let mut chunks = server.world.write_resource::<Chunks>();
+        chunks.update(sx + 1, vy + 1, sz, stone, 0, 0);
+        chunks.update(sx + 1, vy + 2, sz, stone, 0, 0);
+    }
+    server
+        .world
+        .ecs()
+        .write_storage::<AttackCooldown>()
+        .get_mut(player)
+        .unwrap()
+        .0 = 0.0;
+
+    server.send(&client, hit);
+    assert_eq!(hits(&server), 1);
+}
+
+#[test]
+fn death_drops_inventory_and_respawns_works() {
+    let mut server = TestServer::new();
+    let client = server.join();
+
+    server.request_chunks(&client, &[[0, 0]]);
+    server.wait_for(&client, has_voxels);
+
+    let player = player_entity(&server, &client);
+
+    let (stone, sand) = {
+        let chunks = server.world.read_resource::<Chunks>();
+        (
+            *chunks.registry.get_id_by_name("Stone"),
+            *chunks.registry.get_id_by_name("Sand"),
+        )
+    };
+    {
+        let mut inventories = server.world.ecs().write_storage::<Inventory>();
+        let inventory = inventories.get_mut(player).unwrap();
+        inventory.add(ItemStack::new(stone, 5));
+        inventory.add(ItemStack::new(sand, 3));
+    }
+
+    let Vec3(sx, sy, sz) = client.spawn;
+    move_player(&mut server, &client, &Vec3(sx + 4, sy + 1, sz + 4));
+    let death = player_position(&server, &client);
+
+    kill(&mut server, &client);
+
+    // everything that was carried lies where the player died
+    assert!(server
+        .world
+        .ecs()
+        .read_storage::<Inventory>()
+        .get(player)
+        .unwrap()
+        .is_empty());
+
+    let mut dropped = {
+        let ecs = server.world.ecs();
+        let (items, bodies) = (
+            ecs.read_storage::<DroppedItem>(),
+            ecs.read_storage::<RigidBody>(),
+        );
+
+        (&items, &bodies)
+            .join()
+            .map(|(item, body)| {
+                let Vec3(px, _, pz) = body.get_position();
+                assert!((px - death.0).abs() < 0.5 && (pz - death.2).abs() < 0.5);
+                (item.stack.id, item.stack.count)
+            })
+            .collect::<Vec<_>>()
+    };
+    dropped.sort_unstable();
+
+    let mut expected = vec![(stone, 5), (sand, 3)];
+    expected.sort_unstable();
+    assert_eq!(dropped, expected);
+
+    // and they come back to life standing on the world's spawn
+    server.send(&client, create_of_type(MessageType::Respawn));
+
+    assert!(!server.world.is_dead(client.id));
+    {
+        let healths = server.world.ecs().read_storage::<Health>();
+        let health = healths.get(player).unwrap();
+        assert_eq!(health.current, health.max);
+    }
+    assert_eq!(player_voxel(&server, &client), Vec3(sx, sy + 1, sz));
+}
+
+#[test]
+fn respawn_at_team_spawn_works() {
+    let mut server = TestServer::new();
+    let client = server.join();
+
+    server.request_chunks(&client, &[[0, 0]]);
+    server.wait_for(&client, has_voxels);
+
+    let Vec3(sx, sy, sz) = client.spawn;
+    let team_spawn = Vec3(sx + 3, sy + 1, sz - 2);
+
+    server.chat(&client, "/team join red");
+    server.wait_for(&client, |message| says(message, "joined team red"));
+
+    move_player(&mut server, &client, &team_spawn);
+    server.chat(&client, "/team spawn");
+    server.wait_for(&client, |message| says(message, "Team red respawns at"));
+
+    move_player(&mut server, &client, &Vec3(sx - 3, sy + 1, sz + 2));
+    kill(&mut server, &client);
+    server.send(&client, create_of_type(MessageType::Respawn));
+
+    assert!(!server.world.is_dead(client.id));
+    assert_eq!(player_voxel(&server, &client), team_spawn);
+}
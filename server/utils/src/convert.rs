@@ -8,28 +8,11 @@ pub fn get_chunk_name(x: i32, z: i32) -> String {
     format!("{}{}{}", x, CONCAT, z)
 }
 
-pub fn get_voxel_name(x: i32, y: i32, z: i32) -> String {
-    format!("{}{}{}{}{}", x, CONCAT, y, CONCAT, z)
-}
-
-pub fn get_position_name(x: f32, y: f32, z: f32) -> String {
-    format!("{}{}{}{}{}", x, CONCAT, y, CONCAT, z)
-}
-
 pub fn parse_chunk_name(name: &str) -> Vec2<i32> {
     let vec = name.split(CONCAT).collect::<Vec<&str>>();
     Vec2(vec[0].parse().unwrap(), vec[1].parse().unwrap())
 }
 
-pub fn parse_voxel_name(name: &str) -> Vec3<i32> {
-    let vec = name.split(CONCAT).collect::<Vec<&str>>();
-    Vec3(
-        vec[0].parse().unwrap(),
-        vec[1].parse().unwrap(),
-        vec[2].parse().unwrap(),
-    )
-}
-
 fn floor_scale_coords(x: f32, y: f32, z: f32, factor: f32) -> Vec3<f32> {
     Vec3(
         (x * factor).floor(),
@@ -68,11 +51,6 @@ mod tests {
         assert_eq!(get_chunk_name(1, 2), "1_2");
     }
 
-    #[test]
-    fn voxel_name_works() {
-        assert_eq!(get_voxel_name(1, 2, 3), "1_2_3");
-    }
-
     #[test]
     fn parse_chunk_name_works() {
         let name = String::from("-1_2");
@@ -80,13 +58,6 @@ mod tests {
         assert_eq!(coords, Vec2(-1, 2));
     }
 
-    #[test]
-    fn parse_voxel_name_works() {
-        let name = String::from("-2_-4_-5");
-        let coords = parse_voxel_name(&name);
-        assert_eq!(coords, Vec3(-2, -4, -5));
-    }
-
     #[test]
     fn mapping() {
         const CHUNK_SIZE: usize = 16;